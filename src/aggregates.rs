@@ -0,0 +1,279 @@
+use crate::analysis::{TimeRange, group_label};
+use crate::categories::{Categories, UNCATEGORIZED, category_label};
+use crate::models::Event;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+/// Time per app and category of one bucket on one day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DayTotals {
+    /// Seconds per app
+    pub apps: BTreeMap<String, f64>,
+
+    /// Seconds per full category path ("Uncategorized" for unmatched time)
+    pub categories: BTreeMap<String, f64>,
+}
+
+impl DayTotals {
+    /// Total the events' time within `range`; categories are only totalled
+    /// when rules are given
+    pub fn from_events(
+        events: &[Event],
+        categories: Option<&Categories>,
+        range: &TimeRange,
+    ) -> Self {
+        let mut totals = Self::default();
+        for event in events {
+            let Some((start, end)) = range.clip((event.timestamp, event.end())) else {
+                continue;
+            };
+            let secs = (end - start).num_milliseconds() as f64 / 1000.0;
+            *totals.apps.entry(group_label(event, "app")).or_default() += secs;
+            if let Some(categories) = categories {
+                let label = categories
+                    .classify(event)
+                    .map_or(UNCATEGORIZED.to_string(), |path| category_label(path, None));
+                *totals.categories.entry(label).or_default() += secs;
+            }
+        }
+        totals
+    }
+
+    /// Add another day's totals
+    pub fn add(&mut self, other: &DayTotals) {
+        for (app, secs) in &other.apps {
+            *self.apps.entry(app.clone()).or_default() += secs;
+        }
+        for (category, secs) in &other.categories {
+            *self.categories.entry(category.clone()).or_default() += secs;
+        }
+    }
+
+    /// Total seconds
+    pub fn total_secs(&self) -> f64 {
+        self.apps.values().fold(0.0, |total, secs| total + secs)
+    }
+}
+
+/// Stored daily totals of one bucket.
+///
+/// Only completed days are stored. To notice backfilled, imported or
+/// deleted events, the store remembers how many events the bucket had before
+/// `checked_until` and how many each completed month had; when the first
+/// count changes, the days of the months whose count changed are dropped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketAggregates {
+    /// Bucket ID
+    pub bucket_id: String,
+
+    /// Timezone the days are taken in
+    pub timezone: String,
+
+    /// The category rules (as categories.toml) the totals were computed with
+    pub rules: String,
+
+    /// Totals of completed days
+    pub days: BTreeMap<NaiveDate, DayTotals>,
+
+    /// End of the latest stored day
+    pub checked_until: Option<DateTime<Utc>>,
+
+    /// Events before `checked_until` when last checked
+    pub history_count: i64,
+
+    /// Events per completed month (`YYYY-MM`) with stored days
+    pub month_counts: BTreeMap<String, i64>,
+}
+
+impl BucketAggregates {
+    /// An empty store for a bucket
+    pub fn new(bucket_id: &str, timezone: &str, rules: &str) -> Self {
+        Self {
+            bucket_id: bucket_id.to_string(),
+            timezone: timezone.to_string(),
+            rules: rules.to_string(),
+            days: BTreeMap::new(),
+            checked_until: None,
+            history_count: 0,
+            month_counts: BTreeMap::new(),
+        }
+    }
+
+    /// Whether the totals were computed for this timezone and these rules
+    pub fn matches(&self, timezone: &str, rules: &str) -> bool {
+        self.timezone == timezone && self.rules == rules
+    }
+
+    /// Months (`YYYY-MM`) that have stored days
+    pub fn months(&self) -> Vec<String> {
+        let mut months: Vec<String> = self.days.keys().map(month_key).collect();
+        months.dedup();
+        months
+    }
+
+    /// Drop the stored days `first..=last` and the event counts of their
+    /// months, returning how many days were dropped
+    pub fn drop_days(&mut self, first: NaiveDate, last: NaiveDate) -> usize {
+        let before = self.days.len();
+        let months: Vec<String> = self
+            .days
+            .range(first..=last)
+            .map(|(date, _)| month_key(date))
+            .collect();
+        self.days.retain(|date, _| *date < first || *date > last);
+        months.iter().for_each(|month| {
+            self.month_counts.remove(month);
+        });
+        before - self.days.len()
+    }
+
+    /// Drop the stored days of a month, returning how many were dropped
+    pub fn drop_month(&mut self, month: &str) -> usize {
+        let before = self.days.len();
+        self.days.retain(|date, _| month_key(date) != month);
+        self.month_counts.remove(month);
+        before - self.days.len()
+    }
+}
+
+/// The `YYYY-MM` key of a date's month
+pub fn month_key(date: &NaiveDate) -> String {
+    format!("{:04}-{:02}", date.year(), date.month())
+}
+
+/// Daily totals per bucket, kept in memory and, when a directory is
+/// configured, on disk so they survive restarts
+#[derive(Debug, Default)]
+pub struct AggregateStore {
+    dir: Option<PathBuf>,
+    buckets: Mutex<HashMap<String, BucketAggregates>>,
+}
+
+impl AggregateStore {
+    /// A store persisted in `dir`, or kept in memory only
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Exclusive access to the stored buckets; held while a bucket is being
+    /// brought up to date so concurrent reports do not compute twice
+    pub async fn lock(&self) -> tokio::sync::MutexGuard<'_, HashMap<String, BucketAggregates>> {
+        self.buckets.lock().await
+    }
+
+    /// Load a bucket's aggregates from disk, if stored
+    pub async fn load(&self, bucket_id: &str) -> Option<BucketAggregates> {
+        let path = self.path_for(bucket_id)?;
+        tokio::fs::read(&path)
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<BucketAggregates>(&bytes).ok())
+            .filter(|aggregates| aggregates.bucket_id == bucket_id)
+    }
+
+    /// Persist a bucket's aggregates; failures are logged and otherwise
+    /// ignored
+    pub async fn save(&self, aggregates: &BucketAggregates) {
+        let Some(path) = self.path_for(&aggregates.bucket_id) else {
+            return;
+        };
+        let result = async {
+            if let Some(dir) = &self.dir {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            let bytes = serde_json::to_vec(aggregates)?;
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, bytes).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to persist aggregates of {}: {}",
+                aggregates.bucket_id,
+                e
+            );
+        }
+    }
+
+    /// File path for a bucket, when persisted
+    fn path_for(&self, bucket_id: &str) -> Option<PathBuf> {
+        let name: String = bucket_id
+            .chars()
+            .map(
+                |c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    true => c,
+                    false => '_',
+                },
+            )
+            .collect();
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn event(hour: u32, minutes: f64, app: &str, title: &str) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 3, 15, hour, 0, 0).unwrap(),
+            minutes * 60.0,
+            json!({"app": app, "title": title}),
+        )
+    }
+
+    #[test]
+    fn totals_days_and_drops_changed_months() {
+        let categories = Categories::parse_toml(r#""Work > Programming" = "Code""#).unwrap();
+        let day = TimeRange::new(
+            Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 16, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let events = [
+            event(9, 30.0, "Code", "main.rs"),
+            event(23, 90.0, "Firefox", "Docs"),
+        ];
+        let totals = DayTotals::from_events(&events, Some(&categories), &day);
+        assert_eq!(totals.apps["Code"], 1800.0);
+        assert_eq!(totals.apps["Firefox"], 3600.0);
+        assert_eq!(totals.categories["Work > Programming"], 1800.0);
+        assert_eq!(totals.categories[UNCATEGORIZED], 3600.0);
+
+        let mut sum = totals.clone();
+        sum.add(&totals);
+        assert_eq!(sum.total_secs(), 10800.0);
+
+        let mut aggregates = BucketAggregates::new("window", "UTC", "");
+        for date in ["2024-02-29", "2024-03-01", "2024-03-15"] {
+            aggregates
+                .days
+                .insert(date.parse().unwrap(), totals.clone());
+        }
+        aggregates.month_counts.insert("2024-02".to_string(), 10);
+        assert_eq!(aggregates.months(), ["2024-02", "2024-03"]);
+        let mut rebuilt = aggregates.clone();
+        let (first, last) = ("2024-02-29".parse().unwrap(), "2024-03-01".parse().unwrap());
+        assert_eq!(rebuilt.drop_days(first, last), 2);
+        assert!(rebuilt.month_counts.is_empty());
+        assert_eq!(rebuilt.months(), ["2024-03"]);
+        assert_eq!(aggregates.drop_month("2024-03"), 2);
+        assert_eq!(aggregates.drop_month("2024-02"), 1);
+        assert!(aggregates.days.is_empty() && aggregates.month_counts.is_empty());
+        assert!(aggregates.matches("UTC", "") && !aggregates.matches("Europe/Berlin", ""));
+    }
+}
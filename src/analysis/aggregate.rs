@@ -0,0 +1,353 @@
+use super::intervals::{Interval, active_intervals};
+use super::pack::clip_to_active;
+use super::range::TimeRange;
+use crate::models::Event;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Label used when an event lacks the grouping key
+pub const UNKNOWN_GROUP: &str = "(unknown)";
+
+/// Sum event durations per value of a data key, clipped to the range.
+///
+/// Results are sorted by duration, longest first.
+pub fn totals_by_key(events: &[Event], key: &str, range: Option<&TimeRange>) -> Vec<(String, f64)> {
+    let mut totals: HashMap<String, f64> = HashMap::new();
+
+    for event in events {
+        let secs = match range {
+            Some(range) => match range.clip((event.timestamp, event.end())) {
+                Some((start, end)) => (end - start).num_milliseconds() as f64 / 1000.0,
+                None => continue,
+            },
+            None => event.duration.as_secs_f64(),
+        };
+        *totals.entry(group_label(event, key)).or_default() += secs;
+    }
+
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+/// An event shown as evidence for a group's total
+#[derive(Debug, Clone, Serialize)]
+pub struct Example {
+    /// When the event started
+    pub timestamp: DateTime<Utc>,
+
+    /// The event's title (its URL or app without one)
+    pub title: String,
+
+    /// Seconds of the event within the range
+    pub secs: f64,
+}
+
+/// Up to `limit` example events per value of a data key: the longest ones
+/// within the range, longest first
+pub fn examples_by_key(
+    events: &[Event],
+    key: &str,
+    range: Option<&TimeRange>,
+    limit: usize,
+) -> HashMap<String, Vec<Example>> {
+    let mut examples: HashMap<String, Vec<Example>> = HashMap::new();
+    if limit == 0 {
+        return examples;
+    }
+    for event in events {
+        let (timestamp, secs) = match range {
+            Some(range) => match range.clip((event.timestamp, event.end())) {
+                Some((start, end)) => (start, (end - start).num_milliseconds() as f64 / 1000.0),
+                None => continue,
+            },
+            None => (event.timestamp, event.duration.as_secs_f64()),
+        };
+        let title = ["title", "url", "app"]
+            .iter()
+            .filter_map(|key| event.data.get(*key).and_then(|v| v.as_str()))
+            .find(|value| !value.is_empty())
+            .unwrap_or(UNKNOWN_GROUP);
+        examples
+            .entry(group_label(event, key))
+            .or_default()
+            .push(Example {
+                timestamp,
+                title: title.to_string(),
+                secs,
+            });
+    }
+    for group in examples.values_mut() {
+        group.sort_by(|a, b| {
+            b.secs
+                .total_cmp(&a.secs)
+                .then(a.timestamp.cmp(&b.timestamp))
+        });
+        group.truncate(limit);
+    }
+    examples
+}
+
+/// Sum event durations per value of `sub_key` among the events whose `key`
+/// label is `group` (e.g., titles of one app), longest first
+pub fn totals_within(
+    events: &[Event],
+    key: &str,
+    group: &str,
+    sub_key: &str,
+    range: Option<&TimeRange>,
+) -> Vec<(String, f64)> {
+    let events: Vec<Event> = events
+        .iter()
+        .filter(|event| group_label(event, key) == group)
+        .cloned()
+        .collect();
+    totals_by_key(&events, sub_key, range)
+}
+
+/// The grouping label of an event for a data key
+pub fn group_label(event: &Event, key: &str) -> String {
+    match event.data.get(key) {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Null) | None => UNKNOWN_GROUP.to_string(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// A group whose duration changed between two ranges
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupChange {
+    /// Group label
+    pub group: String,
+
+    /// Seconds in the first range
+    pub before_secs: f64,
+
+    /// Seconds in the second range
+    pub after_secs: f64,
+}
+
+impl GroupChange {
+    /// Signed change in seconds
+    pub fn delta_secs(&self) -> f64 {
+        self.after_secs - self.before_secs
+    }
+}
+
+/// Differences between group totals of two ranges
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActivityDiff {
+    /// Groups only present in the second range
+    pub appeared: Vec<GroupChange>,
+
+    /// Groups only present in the first range
+    pub disappeared: Vec<GroupChange>,
+
+    /// Groups present in both ranges, largest absolute change first
+    pub changed: Vec<GroupChange>,
+
+    /// Total seconds in the first range
+    pub before_total_secs: f64,
+
+    /// Total seconds in the second range
+    pub after_total_secs: f64,
+}
+
+/// Compare group totals of two ranges
+pub fn diff_totals(before: &[(String, f64)], after: &[(String, f64)]) -> ActivityDiff {
+    let before_map: HashMap<&str, f64> = before.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    let after_map: HashMap<&str, f64> = after.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    let mut diff = ActivityDiff {
+        before_total_secs: before.iter().map(|(_, v)| v).sum(),
+        after_total_secs: after.iter().map(|(_, v)| v).sum(),
+        ..Default::default()
+    };
+
+    for (group, &after_secs) in &after_map {
+        let change = GroupChange {
+            group: group.to_string(),
+            before_secs: before_map.get(group).copied().unwrap_or(0.0),
+            after_secs,
+        };
+        if before_map.contains_key(group) {
+            diff.changed.push(change);
+        } else {
+            diff.appeared.push(change);
+        }
+    }
+    for (group, &before_secs) in &before_map {
+        if !after_map.contains_key(group) {
+            diff.disappeared.push(GroupChange {
+                group: group.to_string(),
+                before_secs,
+                after_secs: 0.0,
+            });
+        }
+    }
+
+    let by_magnitude = |a: &GroupChange, b: &GroupChange| {
+        b.delta_secs()
+            .abs()
+            .total_cmp(&a.delta_secs().abs())
+            .then_with(|| a.group.cmp(&b.group))
+    };
+    diff.appeared.sort_by(by_magnitude);
+    diff.disappeared.sort_by(by_magnitude);
+    diff.changed.sort_by(by_magnitude);
+    diff
+}
+
+/// Window activity intersected with the AFK watcher's presence
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActiveTime {
+    /// Time the AFK watcher saw the user present
+    pub present_secs: f64,
+
+    /// Time of all window events
+    pub window_secs: f64,
+
+    /// Time of window events while the user was present
+    pub active_secs: f64,
+
+    /// Active time per value of the grouping key, longest first
+    pub groups: Vec<(String, f64)>,
+
+    /// Example events of each group while present
+    pub examples: HashMap<String, Vec<Example>>,
+}
+
+/// Total window time while the user was present, per value of `key`,
+/// within the range, with up to `examples` example events per group
+pub fn active_time(
+    window_events: &[Event],
+    afk_events: &[Event],
+    key: &str,
+    range: &TimeRange,
+    examples: usize,
+) -> ActiveTime {
+    let present: Vec<Interval> = active_intervals(afk_events)
+        .into_iter()
+        .filter_map(|i| range.clip(i))
+        .collect();
+    let active_events = clip_to_active(window_events, &present);
+    let groups = totals_by_key(&active_events, key, Some(range));
+    let secs = |(start, end): Interval| (end - start).num_milliseconds() as f64 / 1000.0;
+    ActiveTime {
+        present_secs: present.iter().copied().map(secs).sum(),
+        window_secs: totals_by_key(window_events, key, Some(range))
+            .iter()
+            .map(|g| g.1)
+            .sum(),
+        active_secs: groups.iter().map(|(_, secs)| secs).sum(),
+        groups,
+        examples: examples_by_key(&active_events, key, Some(range), examples),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn event(secs: i64, duration: f64, app: &str) -> Event {
+        test_event(
+            Utc.timestamp_opt(secs, 0).unwrap(),
+            duration,
+            serde_json::json!({"app": app}),
+        )
+    }
+
+    #[test]
+    fn totals_are_clipped_and_sorted() {
+        let range = TimeRange::new(
+            Utc.timestamp_opt(0, 0).unwrap(),
+            Utc.timestamp_opt(100, 0).unwrap(),
+        )
+        .unwrap();
+        let events = vec![
+            event(-20, 30.0, "a"),
+            event(10, 50.0, "b"),
+            event(90, 30.0, "a"),
+            event(200, 10.0, "c"),
+        ];
+
+        let totals = totals_by_key(&events, "app", Some(&range));
+        assert_eq!(
+            totals,
+            vec![("b".to_string(), 50.0), ("a".to_string(), 20.0)]
+        );
+    }
+
+    #[test]
+    fn totals_within_one_group() {
+        let mut events = vec![
+            event(0, 10.0, "a"),
+            event(10, 30.0, "a"),
+            event(40, 5.0, "b"),
+        ];
+        events[1]
+            .data
+            .insert("title".to_string(), serde_json::json!("x"));
+
+        let titles = totals_within(&events, "app", "a", "title", None);
+        assert_eq!(
+            titles,
+            vec![("x".to_string(), 30.0), (UNKNOWN_GROUP.to_string(), 10.0)]
+        );
+    }
+
+    #[test]
+    fn diff_classifies_groups() {
+        let before = vec![("a".to_string(), 100.0), ("b".to_string(), 50.0)];
+        let after = vec![("a".to_string(), 40.0), ("c".to_string(), 30.0)];
+
+        let diff = diff_totals(&before, &after);
+        assert_eq!(diff.appeared[0].group, "c");
+        assert_eq!(diff.disappeared[0].group, "b");
+        assert_eq!(diff.changed[0].delta_secs(), -60.0);
+        assert_eq!(diff.before_total_secs, 150.0);
+        assert_eq!(diff.after_total_secs, 70.0);
+    }
+
+    #[test]
+    fn active_time_leaves_out_afk_time() {
+        let range = TimeRange::new(
+            Utc.timestamp_opt(0, 0).unwrap(),
+            Utc.timestamp_opt(1000, 0).unwrap(),
+        )
+        .unwrap();
+        let window = vec![
+            event(0, 400.0, "a"),
+            event(400, 300.0, "b"),
+            event(900, 200.0, "a"),
+        ];
+        let mut afk = vec![
+            event(-100, 300.0, "x"),
+            event(200, 300.0, "x"),
+            event(500, 600.0, "x"),
+        ];
+        for (event, status) in afk.iter_mut().zip(["not-afk", "afk", "not-afk"]) {
+            event
+                .data
+                .insert("status".to_string(), serde_json::json!(status));
+        }
+
+        let active = active_time(&window, &afk, "app", &range, 1);
+        assert_eq!(active.present_secs, 700.0);
+        assert_eq!(active.window_secs, 800.0);
+        assert_eq!(active.active_secs, 500.0);
+        assert_eq!(
+            active.groups,
+            vec![("a".to_string(), 300.0), ("b".to_string(), 200.0)]
+        );
+        // Longest piece while present, titled by the app without a title
+        let example = &active.examples["a"];
+        assert_eq!((example.len(), example[0].secs), (1, 200.0));
+        assert_eq!(
+            (example[0].timestamp.timestamp(), example[0].title.as_str()),
+            (0, "a")
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use super::intervals::Interval;
+use super::pack::{format_hours_minutes, parse_hours_minutes};
+use crate::format::format_duration;
+use crate::models::Event;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Subject of the rule watching for missing window events
+const NO_WINDOW_SUBJECT: &str = "no-window";
+
+/// A condition the background alert job watches for, e.g. `no-window>=30m`
+/// (no window events for 30 minutes while the user is not AFK) or
+/// `Media>=2h before 12:00` (2 hours of Media and its subcategories today
+/// before noon)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertRule {
+    /// The window watcher recorded nothing while the AFK watcher saw the user
+    NoWindowEvents {
+        /// How long the gap must be
+        secs: f64,
+    },
+    /// Time in a category today reached a limit
+    CategoryOver {
+        /// Category path, including its subcategories
+        category: String,
+
+        /// The limit
+        secs: f64,
+
+        /// Only time before this local time of day counts
+        before: Option<NaiveTime>,
+    },
+}
+
+impl FromStr for AlertRule {
+    type Err = String;
+
+    /// Parse `no-window>=time` or `category>=time`, optionally followed by
+    /// `before HH:MM`, with times like `30m`, `2h` or `1h30m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rule, before) = match s.split_once(" before ") {
+            Some((rule, time)) => {
+                let time = NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| {
+                    format!(
+                        "Invalid time of day '{}' in alert, expected e.g. 12:00",
+                        time.trim()
+                    )
+                })?;
+                (rule, Some(time))
+            }
+            None => (s, None),
+        };
+        let (subject, limit) = rule.split_once(">=").ok_or_else(|| {
+            format!(
+                "Expected category>=time or no-window>=time, got '{}'",
+                s.trim()
+            )
+        })?;
+        let secs = parse_hours_minutes(limit).ok_or_else(|| {
+            format!(
+                "Invalid time '{}' in alert, expected e.g. 30m or 2h",
+                limit.trim()
+            )
+        })?;
+        match subject.trim() {
+            "" => Err(format!("Alert '{}' has no category", s.trim())),
+            NO_WINDOW_SUBJECT if before.is_some() => {
+                Err(format!("Alert '{}' cannot have a time of day", s.trim()))
+            }
+            NO_WINDOW_SUBJECT => Ok(Self::NoWindowEvents { secs }),
+            category => Ok(Self::CategoryOver {
+                category: category.to_string(),
+                secs,
+                before,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoWindowEvents { secs } => {
+                write!(f, "{}>={}", NO_WINDOW_SUBJECT, format_hours_minutes(*secs))
+            }
+            Self::CategoryOver {
+                category,
+                secs,
+                before,
+            } => {
+                write!(f, "{}>={}", category, format_hours_minutes(*secs))?;
+                match before {
+                    Some(before) => write!(f, " before {}", before.format("%H:%M")),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl AlertRule {
+    /// The alert when `observed_secs` (the window event gap, or the time in
+    /// the category) reaches the rule's limit
+    pub fn check(&self, observed_secs: f64, now: DateTime<Utc>) -> Option<Alert> {
+        let message = match self {
+            Self::NoWindowEvents { secs } if observed_secs >= *secs => format!(
+                "No window events for {} while not AFK",
+                format_duration(observed_secs)
+            ),
+            Self::CategoryOver {
+                category,
+                secs,
+                before,
+            } if observed_secs >= *secs => format!(
+                "{} in {} today{} (limit {})",
+                format_duration(observed_secs),
+                category,
+                before
+                    .map(|t| format!(" before {}", t.format("%H:%M")))
+                    .unwrap_or_default(),
+                format_duration(*secs)
+            ),
+            _ => return None,
+        };
+        Some(Alert {
+            rule: self.to_string(),
+            message,
+            triggered_at: now,
+        })
+    }
+}
+
+/// A rule that fired
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// The rule, as configured
+    pub rule: String,
+
+    /// What was observed
+    pub message: String,
+
+    /// When the check found the rule firing
+    pub triggered_at: DateTime<Utc>,
+}
+
+/// Seconds the window watcher has recorded nothing at `now`, counted from
+/// the end of its last event (or from `since` without any), while the user
+/// is present: the last interval the AFK watchers saw them in ended at most
+/// `slack` before `now`. `None` while the user is away.
+pub fn window_gap(
+    window_events: &[Event],
+    active: &[Interval],
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+    slack: Duration,
+) -> Option<f64> {
+    let present = active.last().is_some_and(|(_, end)| *end >= now - slack);
+    if !present {
+        return None;
+    }
+    let last_end = window_events
+        .iter()
+        .map(Event::end)
+        .max()
+        .unwrap_or(since)
+        .min(now);
+    Some((now - last_end).num_milliseconds() as f64 / 1000.0)
+}
+
+/// Which rules are firing, so each alert is delivered once when its rule
+/// starts firing instead of at every check
+#[derive(Debug, Default)]
+pub struct AlertMonitor {
+    firing: BTreeSet<String>,
+}
+
+impl AlertMonitor {
+    /// Take the alerts of a check and return those whose rule did not fire
+    /// at the previous one
+    pub fn update(&mut self, alerts: Vec<Alert>) -> Vec<Alert> {
+        let firing = alerts.iter().map(|alert| alert.rule.clone()).collect();
+        let started = alerts
+            .into_iter()
+            .filter(|alert| !self.firing.contains(&alert.rule))
+            .collect();
+        self.firing = firing;
+        started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 15, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_alert_rules() {
+        assert_eq!(
+            "no-window>=30m".parse::<AlertRule>().unwrap(),
+            AlertRule::NoWindowEvents { secs: 1800.0 }
+        );
+        let rule: AlertRule = "Media > Video >= 2h before 12:00".parse().unwrap();
+        assert_eq!(
+            rule,
+            AlertRule::CategoryOver {
+                category: "Media > Video".to_string(),
+                secs: 7200.0,
+                before: NaiveTime::from_hms_opt(12, 0, 0),
+            }
+        );
+        assert_eq!(rule.to_string(), "Media > Video>=2h before 12:00");
+        assert_eq!(rule.to_string().parse::<AlertRule>().unwrap(), rule);
+        assert!("Media<=2h".parse::<AlertRule>().is_err());
+        assert!(">=2h".parse::<AlertRule>().is_err());
+        assert!("Media>=2h before noon".parse::<AlertRule>().is_err());
+        assert!("no-window>=30m before 12:00".parse::<AlertRule>().is_err());
+    }
+
+    #[test]
+    fn fires_rules_once_per_episode() {
+        let rule: AlertRule = "no-window>=30m".parse().unwrap();
+        let windows = vec![test_event(at(9, 0), 600.0, json!({"app": "Code"}))];
+        let active = [(at(9, 0), at(9, 44))];
+        let slack = Duration::minutes(2);
+
+        let gap = window_gap(&windows, &active, at(0, 0), at(9, 45), slack).unwrap();
+        assert_eq!(gap, 35.0 * 60.0);
+        assert!(window_gap(&windows, &active, at(0, 0), at(10, 0), slack).is_none());
+        assert!(rule.check(gap, at(9, 45)).is_some());
+        assert!(rule.check(20.0 * 60.0, at(9, 30)).is_none());
+
+        let mut monitor = AlertMonitor::default();
+        let alert = rule.check(gap, at(9, 45)).unwrap();
+        assert_eq!(monitor.update(vec![alert.clone()]).len(), 1);
+        assert!(monitor.update(vec![alert.clone()]).is_empty());
+        assert!(monitor.update(Vec::new()).is_empty());
+        assert_eq!(monitor.update(vec![alert.clone()]), [alert]);
+    }
+}
@@ -0,0 +1,354 @@
+use super::devices::{BROWSER_APPS, is_browser_window};
+use super::intervals::{Interval, merge_intervals};
+use super::range::TimeRange;
+use super::reviews::pull_request_of;
+use crate::categories::url_domain;
+use crate::models::Event;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Window watcher bucket type
+pub const WINDOW_BUCKET_TYPE: &str = "currentwindow";
+
+/// Browser tab watcher (aw-watcher-web) bucket type
+pub const WEB_BUCKET_TYPE: &str = "web.tab.current";
+
+/// How much browser tab time counts, by whether the browser had focus.
+///
+/// aw-watcher-web reports the active tab of the browser even while another
+/// app is in front, so raw tab events overstate browsing time. Tab time is
+/// focused while a window watcher shows a browser window in front; other
+/// tab time is background time, weighted separately when the tab plays
+/// audio (`audible`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TabWeights {
+    /// Weight of time while the browser window is in front
+    pub focused: f64,
+
+    /// Weight of background time of tabs playing audio
+    pub audible: f64,
+
+    /// Weight of other background time
+    pub background: f64,
+}
+
+impl TabWeights {
+    /// Count every tab event in full, as aw-watcher-web records it
+    pub const RAW: Self = Self {
+        focused: 1.0,
+        audible: 1.0,
+        background: 1.0,
+    };
+}
+
+impl Default for TabWeights {
+    /// Focused time in full, audible background tabs at half weight, other
+    /// background time not at all
+    fn default() -> Self {
+        Self {
+            focused: 1.0,
+            audible: 0.5,
+            background: 0.0,
+        }
+    }
+}
+
+impl FromStr for TabWeights {
+    type Err = String;
+
+    /// Parse `raw`, `focused` (focused time only) or `key=weight` pairs such
+    /// as `audible=0.5,background=0`; keys left out keep their default
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "raw" => return Ok(Self::RAW),
+            "focused" => {
+                return Ok(Self {
+                    audible: 0.0,
+                    ..Self::default()
+                });
+            }
+            _ => {}
+        }
+        let mut weights = Self::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=weight, got '{}'", pair))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .ok()
+                .filter(|w| (0.0..=1.0).contains(w))
+                .ok_or_else(|| format!("Weight of '{}' must be between 0 and 1", key.trim()))?;
+            match key.trim() {
+                "focused" => weights.focused = weight,
+                "audible" => weights.audible = weight,
+                "background" => weights.background = weight,
+                other => {
+                    return Err(format!(
+                        "Unknown tab weight '{}'. Expected focused, audible or background",
+                        other
+                    ));
+                }
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for TabWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "focused={},audible={},background={}",
+            self.focused, self.audible, self.background
+        )
+    }
+}
+
+/// Weighted browsing time of a range
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BrowserTotals {
+    /// Tab event time as recorded
+    pub raw_secs: f64,
+
+    /// Tab time after weighting
+    pub weighted_secs: f64,
+
+    /// Tab time while the browser was in front
+    pub focused_secs: f64,
+
+    /// Background time of tabs playing audio
+    pub audible_secs: f64,
+
+    /// Other background time
+    pub background_secs: f64,
+
+    /// Weighted time per group (domain, URL or title), longest first
+    pub groups: Vec<(String, f64)>,
+}
+
+impl BrowserTotals {
+    /// Add another bucket's totals
+    pub fn add(&mut self, other: &BrowserTotals) {
+        self.raw_secs += other.raw_secs;
+        self.weighted_secs += other.weighted_secs;
+        self.focused_secs += other.focused_secs;
+        self.audible_secs += other.audible_secs;
+        self.background_secs += other.background_secs;
+        let mut groups: HashMap<String, f64> = self.groups.drain(..).collect();
+        for (label, secs) in &other.groups {
+            *groups.entry(label.clone()).or_default() += secs;
+        }
+        self.groups = sorted_groups(groups);
+    }
+}
+
+/// The browser a tab bucket belongs to, from a browser name in its ID
+/// (e.g., "firefox" for `aw-watcher-web-firefox`)
+pub fn browser_of(bucket_id: &str) -> Option<&'static str> {
+    let id = bucket_id.to_lowercase();
+    let browser = id
+        .split(|c: char| !c.is_alphanumeric())
+        .find_map(|word| BROWSER_APPS.iter().find(|app| **app == word))?;
+    Some(match *browser {
+        "msedge" => "edge",
+        browser => browser,
+    })
+}
+
+/// Merged intervals during which a browser window was in front, from
+/// window watcher events; with `browser`, only windows of that browser
+pub fn browser_focus(window_events: &[Event], browser: Option<&str>) -> Vec<Interval> {
+    let of_browser = |event: &Event| match browser {
+        Some(browser) => event
+            .data
+            .get("app")
+            .and_then(|v| v.as_str())
+            .is_some_and(|app| app.to_lowercase().contains(browser)),
+        None => true,
+    };
+    merge_intervals(
+        window_events
+            .iter()
+            .filter(|event| is_browser_window(event) && of_browser(event))
+            .map(|event| (event.timestamp, event.end()))
+            .collect(),
+    )
+}
+
+/// The group of a tab event: its domain, pull request, URL or title
+pub fn tab_group(event: &Event, group_by: &str) -> String {
+    let field = |key: &str| event.data.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    let pull_request;
+    let label = match group_by {
+        "domain" => url_domain(field("url")).unwrap_or(""),
+        "pull_request" => {
+            pull_request = pull_request_of(field("url")).map(|pr| pr.to_string());
+            pull_request.as_deref().unwrap_or("")
+        }
+        key => field(key),
+    };
+    match label.is_empty() {
+        true => "(unknown)".to_string(),
+        false => label.to_string(),
+    }
+}
+
+/// Total tab events within `range`, weighted by browser focus.
+///
+/// Without `focus` (no window watcher on the tab's host) all tab time is
+/// taken as focused.
+pub fn browser_totals(
+    tab_events: &[Event],
+    focus: Option<&[Interval]>,
+    weights: &TabWeights,
+    group_by: &str,
+    range: &TimeRange,
+) -> BrowserTotals {
+    let mut totals = BrowserTotals::default();
+    let mut groups: HashMap<String, f64> = HashMap::new();
+    for event in tab_events {
+        let Some((start, end)) = range.clip((event.timestamp, event.end())) else {
+            continue;
+        };
+        let secs = (end - start).num_milliseconds() as f64 / 1000.0;
+        let focused = match focus {
+            Some(focus) => focus
+                .iter()
+                .filter_map(|&(f_start, f_end)| {
+                    let (s, e) = (f_start.max(start), f_end.min(end));
+                    (e > s).then(|| (e - s).num_milliseconds() as f64 / 1000.0)
+                })
+                .sum(),
+            None => secs,
+        };
+        let background = secs - focused;
+        let audible = event.data.get("audible").and_then(|v| v.as_bool()) == Some(true);
+
+        let mut weighted = focused * weights.focused;
+        totals.focused_secs += focused;
+        if audible {
+            weighted += background * weights.audible;
+            totals.audible_secs += background;
+        } else {
+            weighted += background * weights.background;
+            totals.background_secs += background;
+        }
+        totals.raw_secs += secs;
+        totals.weighted_secs += weighted;
+        if weighted > 0.0 {
+            *groups.entry(tab_group(event, group_by)).or_default() += weighted;
+        }
+    }
+
+    totals.groups = sorted_groups(groups);
+    totals
+}
+
+/// Groups longest first, ties by name
+fn sorted_groups(groups: HashMap<String, f64>) -> Vec<(String, f64)> {
+    let mut groups: Vec<(String, f64)> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn event(minute: u32, minutes: f64, data: serde_json::Value) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, minute, 0).unwrap(),
+            minutes * 60.0,
+            data,
+        )
+    }
+
+    #[test]
+    fn weighs_tab_time_by_browser_focus() {
+        let range = TimeRange::new(
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 15, 10, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let windows = [
+            event(0, 10.0, json!({"app": "Firefox", "title": "Docs"})),
+            event(10, 20.0, json!({"app": "Code", "title": "main.rs"})),
+        ];
+        let tabs = [
+            event(
+                0,
+                20.0,
+                json!({"url": "https://www.docs.rs/chrono", "audible": false}),
+            ),
+            event(
+                20,
+                20.0,
+                json!({"url": "https://youtube.com/watch", "audible": true}),
+            ),
+        ];
+        let focus = browser_focus(&windows, None);
+
+        let totals = browser_totals(
+            &tabs,
+            Some(&focus),
+            &TabWeights::default(),
+            "domain",
+            &range,
+        );
+        assert_eq!(totals.raw_secs, 2400.0);
+        assert_eq!(totals.focused_secs, 600.0);
+        assert_eq!(totals.audible_secs, 1200.0);
+        assert_eq!(totals.background_secs, 600.0);
+        assert_eq!(totals.weighted_secs, 1200.0);
+        assert_eq!(
+            totals.groups,
+            [
+                ("docs.rs".to_string(), 600.0),
+                ("youtube.com".to_string(), 600.0)
+            ]
+        );
+
+        let raw = browser_totals(&tabs, None, &"focused".parse().unwrap(), "domain", &range);
+        assert_eq!(raw.weighted_secs, 2400.0);
+
+        let mut merged = totals.clone();
+        merged.add(&raw);
+        assert_eq!(merged.weighted_secs, 3600.0);
+        assert_eq!(merged.groups[0], ("docs.rs".to_string(), 1800.0));
+    }
+
+    #[test]
+    fn detects_browsers_of_buckets_and_windows() {
+        assert_eq!(browser_of("aw-watcher-web-firefox"), Some("firefox"));
+        assert_eq!(browser_of("aw-watcher-web-msedge_laptop"), Some("edge"));
+        assert_eq!(browser_of("aw-watcher-web"), None);
+
+        let windows = [
+            event(0, 10.0, json!({"app": "Google-chrome", "title": "Docs"})),
+            event(10, 10.0, json!({"app": "Firefox", "title": "Docs"})),
+        ];
+        let focus = browser_focus(&windows, Some("firefox"));
+        assert_eq!(focus, [(windows[1].timestamp, windows[1].end())]);
+        assert_eq!(browser_focus(&windows, None).len(), 1);
+    }
+
+    #[test]
+    fn parses_tab_weights() {
+        assert_eq!("raw".parse::<TabWeights>().unwrap(), TabWeights::RAW);
+        let weights: TabWeights = "audible=0.25, background=0.1".parse().unwrap();
+        assert_eq!(
+            (weights.focused, weights.audible, weights.background),
+            (1.0, 0.25, 0.1)
+        );
+        assert_eq!(weights.to_string().parse::<TabWeights>().unwrap(), weights);
+        assert!("audible=2".parse::<TabWeights>().is_err());
+        assert!("muted=0".parse::<TabWeights>().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::format::format_duration;
+
+/// Seconds totals may be off by before a check fails, for float rounding
+const TOLERANCE_SECS: f64 = 1.0;
+
+/// Totals a report presents together, which must be consistent with each
+/// other: all in seconds, with the ones a report lacks left out
+#[derive(Debug, Clone, Default)]
+pub struct TotalsCheck {
+    /// Length of the report's range
+    pub range_secs: f64,
+
+    /// The active (or total tracked) time
+    pub active_secs: f64,
+
+    /// Time the user was away
+    pub afk_secs: Option<f64>,
+
+    /// Time the AFK watcher saw the user present
+    pub present_secs: Option<f64>,
+
+    /// Sum of all groups (apps, categories, ...) the active time is split into
+    pub group_secs: Option<f64>,
+}
+
+impl TotalsCheck {
+    /// A check of a total against its range
+    pub fn new(range_secs: f64, active_secs: f64) -> Self {
+        Self {
+            range_secs,
+            active_secs,
+            ..Self::default()
+        }
+    }
+
+    /// Descriptions of the impossible combinations of totals; empty when
+    /// they add up
+    pub fn discrepancies(&self) -> Vec<String> {
+        let mut found = Vec::new();
+        let exceeds = |a: f64, b: f64| a > b + TOLERANCE_SECS;
+        if exceeds(self.active_secs, self.range_secs) {
+            found.push(format!(
+                "The total of {} is longer than the {} range; events probably overlap",
+                format_duration(self.active_secs),
+                format_duration(self.range_secs)
+            ));
+        }
+        if let Some(afk_secs) = self.afk_secs
+            && exceeds(self.active_secs + afk_secs, self.range_secs)
+        {
+            found.push(format!(
+                "Active and AFK time add up to {}, more than the {} range",
+                format_duration(self.active_secs + afk_secs),
+                format_duration(self.range_secs)
+            ));
+        }
+        if let Some(present_secs) = self.present_secs
+            && exceeds(self.active_secs, present_secs)
+        {
+            found.push(format!(
+                "Active time of {} is more than the {} the user was present",
+                format_duration(self.active_secs),
+                format_duration(present_secs)
+            ));
+        }
+        if let Some(group_secs) = self.group_secs
+            && (group_secs - self.active_secs).abs() > TOLERANCE_SECS
+        {
+            found.push(format!(
+                "The groups add up to {}, not the {} total",
+                format_duration(group_secs),
+                format_duration(self.active_secs)
+            ));
+        }
+        found
+    }
+}
+
+/// Markdown warning listing discrepancies, if there are any
+pub fn discrepancy_markdown(discrepancies: &[String]) -> Option<String> {
+    if discrepancies.is_empty() {
+        return None;
+    }
+    let mut lines =
+        vec!["> **Warning**: These totals do not add up, so some of them are wrong:".to_string()];
+    lines.extend(discrepancies.iter().map(|d| format!("> - {}", d)));
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_impossible_totals() {
+        let consistent = TotalsCheck {
+            afk_secs: Some(3600.0),
+            present_secs: Some(7200.0),
+            group_secs: Some(7200.0),
+            ..TotalsCheck::new(86400.0, 7200.0)
+        };
+        assert!(consistent.discrepancies().is_empty());
+        assert!(discrepancy_markdown(&[]).is_none());
+
+        let overlapping = TotalsCheck {
+            afk_secs: Some(3600.0),
+            present_secs: Some(80000.0),
+            group_secs: Some(90000.0),
+            ..TotalsCheck::new(86400.0, 86400.5)
+        };
+        let found = overlapping.discrepancies();
+        assert_eq!(found.len(), 3);
+        assert!(found[0].starts_with("Active and AFK time add up to"));
+        assert!(
+            discrepancy_markdown(&found)
+                .unwrap()
+                .starts_with("> **Warning**")
+        );
+    }
+}
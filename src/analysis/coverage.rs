@@ -0,0 +1,106 @@
+use super::intervals::merge_intervals;
+use super::range::TimeRange;
+use crate::format::format_percent;
+use crate::models::Event;
+use serde::Serialize;
+
+/// How much of a time range is covered by AFK watcher data
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Coverage {
+    /// Seconds of the range covered by AFK events (AFK or not)
+    pub covered_secs: f64,
+
+    /// Length of the range in seconds
+    pub range_secs: f64,
+
+    /// Covered fraction of the range (0.0 - 1.0)
+    pub fraction: f64,
+}
+
+impl Coverage {
+    /// Compute coverage of a range from AFK bucket events
+    pub fn from_afk_events(afk_events: &[Event], range: &TimeRange) -> Self {
+        let covered_secs: f64 = merge_intervals(
+            afk_events
+                .iter()
+                .filter_map(|e| range.clip((e.timestamp, e.end())))
+                .collect(),
+        )
+        .iter()
+        .map(|(start, end)| (*end - *start).num_milliseconds() as f64 / 1000.0)
+        .sum();
+        let range_secs = range.duration_secs();
+
+        Self {
+            covered_secs,
+            range_secs,
+            fraction: if range_secs > 0.0 {
+                (covered_secs / range_secs).min(1.0)
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Whether coverage is below the given minimum fraction
+    pub fn is_low(&self, min_fraction: f64) -> bool {
+        self.fraction < min_fraction
+    }
+
+    /// Markdown line describing coverage, with a warning when it is low
+    pub fn to_markdown(&self, min_fraction: f64) -> String {
+        let line = format!(
+            "**Data Coverage**: {} of the requested range has AFK watcher data",
+            format_percent(self.fraction * 100.0)
+        );
+        if self.is_low(min_fraction) {
+            format!(
+                "{}\n\n> **Warning**: Low coverage. The watcher was likely off or the machine asleep for much of this range, so totals are probably understated.",
+                line
+            )
+        } else {
+            line
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn afk_event(secs: i64, duration: f64, status: &str) -> Event {
+        test_event(
+            Utc.timestamp_opt(secs, 0).unwrap(),
+            duration,
+            serde_json::json!({"status": status}),
+        )
+    }
+
+    #[test]
+    fn coverage_counts_afk_and_not_afk_within_range() {
+        let range = TimeRange::new(
+            Utc.timestamp_opt(0, 0).unwrap(),
+            Utc.timestamp_opt(100, 0).unwrap(),
+        )
+        .unwrap();
+        let events = vec![
+            afk_event(-10, 30.0, "not-afk"),
+            afk_event(20, 20.0, "afk"),
+            afk_event(30, 5.0, "not-afk"),
+        ];
+
+        let coverage = Coverage::from_afk_events(&events, &range);
+        assert_eq!(coverage.covered_secs, 40.0);
+        assert_eq!(coverage.fraction, 0.4);
+        assert!(coverage.is_low(0.5));
+        assert!(!coverage.is_low(0.4));
+    }
+
+    #[test]
+    fn range_rejects_inverted_bounds() {
+        assert!(TimeRange::parse("2024-01-02T00:00:00Z", "2024-01-01T00:00:00Z").is_err());
+        assert!(TimeRange::parse("2024-01-01T00:00:00Z", "2024-01-02T00:00:00+02:00").is_ok());
+    }
+}
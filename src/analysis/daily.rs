@@ -0,0 +1,142 @@
+use super::aggregate::totals_by_key;
+use super::intervals::{Interval, active_intervals, merge_intervals};
+use super::pack::clip_to_active;
+use super::range::TimeRange;
+use crate::models::Event;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// AFK status value marking the user as away
+const AFK_STATUS: &str = "afk";
+
+/// One day of activity, consolidated from the window and AFK watchers
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DailySummary {
+    /// Window time while the AFK watcher saw the user present (all window
+    /// time without AFK data)
+    pub active_secs: f64,
+
+    /// Time the AFK watcher saw the user away
+    pub afk_secs: f64,
+
+    /// Time the AFK watcher saw the user present; none without AFK data
+    pub present_secs: Option<f64>,
+
+    /// Active time per app, longest first
+    pub top_apps: Vec<(String, f64)>,
+
+    /// Active time per window title, longest first
+    pub top_titles: Vec<(String, f64)>,
+
+    /// Start of the first activity: the user present, or a window event
+    /// without AFK data
+    pub first_activity: Option<DateTime<Utc>>,
+
+    /// End of the last activity
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+/// Summarize a day (or any range) of window and AFK events, keeping the
+/// `top` apps and titles
+pub fn daily_summary(
+    window_events: &[Event],
+    afk_events: &[Event],
+    range: &TimeRange,
+    top: usize,
+) -> DailySummary {
+    let clipped = |intervals: Vec<Interval>| -> Vec<Interval> {
+        intervals
+            .into_iter()
+            .filter_map(|i| range.clip(i))
+            .collect()
+    };
+    let present = clipped(active_intervals(afk_events));
+    let away = clipped(merge_intervals(
+        afk_events
+            .iter()
+            .filter(|e| e.data.get("status").and_then(|v| v.as_str()) == Some(AFK_STATUS))
+            .map(|e| (e.timestamp, e.end()))
+            .collect(),
+    ));
+    // Without AFK data, window events are the only sign of activity
+    let (active_events, activity) = match afk_events.is_empty() {
+        true => (
+            window_events.to_vec(),
+            clipped(
+                window_events
+                    .iter()
+                    .map(|e| (e.timestamp, e.end()))
+                    .collect(),
+            ),
+        ),
+        false => (clip_to_active(window_events, &present), present.clone()),
+    };
+    let mut top_apps = totals_by_key(&active_events, "app", Some(range));
+    let mut top_titles = totals_by_key(&active_events, "title", Some(range));
+    let active_secs = top_apps.iter().map(|(_, secs)| secs).sum();
+    top_apps.truncate(top);
+    top_titles.truncate(top);
+    let secs = |(start, end): &Interval| (*end - *start).num_milliseconds() as f64 / 1000.0;
+
+    DailySummary {
+        active_secs,
+        afk_secs: away.iter().map(secs).sum(),
+        present_secs: (!afk_events.is_empty()).then(|| present.iter().map(secs).sum()),
+        top_apps,
+        top_titles,
+        first_activity: activity.iter().map(|(start, _)| *start).min(),
+        last_activity: activity.iter().map(|(_, end)| *end).max(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn event(hour: u32, minutes: f64, data: serde_json::Value) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 3, 15, hour, 0, 0).unwrap(),
+            minutes * 60.0,
+            data,
+        )
+    }
+
+    fn at(hour: u32, minute: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(2024, 3, 15, hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn summarizes_a_day() {
+        let day = TimeRange::new(
+            Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 16, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let windows = [
+            event(9, 60.0, json!({"app": "Code", "title": "main.rs"})),
+            event(10, 30.0, json!({"app": "Firefox", "title": "Docs"})),
+            event(12, 60.0, json!({"app": "Code", "title": "lib.rs"})),
+        ];
+        let afk = [
+            event(9, 90.0, json!({"status": "not-afk"})),
+            event(11, 120.0, json!({"status": "afk"})),
+            event(13, 30.0, json!({"status": "not-afk"})),
+        ];
+
+        let summary = daily_summary(&windows, &afk, &day, 1);
+        assert_eq!(summary.active_secs, 5400.0);
+        assert_eq!(summary.afk_secs, 7200.0);
+        assert_eq!(summary.present_secs, Some(7200.0));
+        assert_eq!(summary.top_apps, [("Code".to_string(), 3600.0)]);
+        assert_eq!(summary.top_titles, [("main.rs".to_string(), 3600.0)]);
+        assert_eq!(summary.first_activity, at(9, 0));
+        assert_eq!(summary.last_activity, at(13, 30));
+
+        let without_afk = daily_summary(&windows, &[], &day, 5);
+        assert_eq!(without_afk.active_secs, 9000.0);
+        assert_eq!(without_afk.last_activity, at(13, 0));
+    }
+}
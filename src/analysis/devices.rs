@@ -0,0 +1,332 @@
+use super::intervals::{Interval, contains_instant};
+use crate::models::{Event, EventDuration, MergeStrategy};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Lowercase words of browser app names, whose window events overlap
+/// browser tab events
+pub(super) const BROWSER_APPS: &[&str] = &[
+    "chrome", "chromium", "firefox", "safari", "edge", "msedge", "brave", "opera", "vivaldi", "arc",
+];
+
+/// Events from a single device, ready to be merged with other devices
+#[derive(Debug, Clone)]
+pub struct DeviceEvents {
+    /// Hostname of the device
+    pub hostname: String,
+
+    /// Events recorded on the device
+    pub events: Vec<Event>,
+
+    /// Merged intervals with input activity, if the device has an AFK bucket
+    pub active: Option<Vec<Interval>>,
+}
+
+/// An event in a merged multi-device timeline
+#[derive(Debug, Clone, Serialize)]
+pub struct MergedEvent {
+    /// Hostname of the device the event came from
+    pub hostname: String,
+
+    /// The (possibly trimmed) event
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Result of merging several devices into one timeline
+#[derive(Debug, Clone)]
+pub struct DeviceMerge {
+    /// Merged events, oldest first
+    pub events: Vec<MergedEvent>,
+
+    /// Seconds where events from more than one device overlapped
+    pub overlap_secs: f64,
+}
+
+/// Shift event timestamps by a clock offset in seconds
+pub fn apply_clock_offset(events: &mut [Event], offset_secs: f64) {
+    if offset_secs == 0.0 {
+        return;
+    }
+    let offset = Duration::milliseconds((offset_secs * 1000.0) as i64);
+    for event in events {
+        event.timestamp += offset;
+    }
+}
+
+/// Whether an event is a window event of a web browser
+pub(super) fn is_browser_window(event: &Event) -> bool {
+    !event.data.contains_key("url")
+        && event
+            .data
+            .get("app")
+            .and_then(|v| v.as_str())
+            .is_some_and(|app| {
+                app.to_lowercase()
+                    .split(|c: char| !c.is_alphanumeric())
+                    .any(|word| BROWSER_APPS.contains(&word))
+            })
+}
+
+/// Merge events from several devices into a single non-overlapping timeline.
+///
+/// With [`MergeStrategy::Priority`], where events overlap, the device with
+/// input activity at that moment wins; devices without AFK data rank between
+/// active and AFK devices. Remaining ties go to the device listed first. The
+/// other strategies decide by event kind or length first, or split the time.
+pub fn merge_devices(devices: &[DeviceEvents], strategy: MergeStrategy) -> DeviceMerge {
+    // (device index, event index, start, end)
+    let mut items: Vec<(usize, usize, DateTime<Utc>, DateTime<Utc>)> = devices
+        .iter()
+        .enumerate()
+        .flat_map(|(d, device)| {
+            device
+                .events
+                .iter()
+                .enumerate()
+                .map(move |(e, event)| (d, e, event.timestamp, event.end()))
+        })
+        .filter(|(_, _, start, end)| end > start)
+        .collect();
+    items.sort_by_key(|(_, _, start, _)| *start);
+
+    // Activity changes split segments too, so the winner can change mid-event
+    let mut boundaries: Vec<DateTime<Utc>> = items
+        .iter()
+        .flat_map(|(_, _, start, end)| [*start, *end])
+        .chain(
+            devices
+                .iter()
+                .filter_map(|d| d.active.as_ref())
+                .flatten()
+                .flat_map(|(start, end)| [*start, *end]),
+        )
+        .collect();
+    boundaries.sort();
+    boundaries.dedup();
+
+    let rank = |device: &DeviceEvents, at: DateTime<Utc>| match &device.active {
+        Some(active) if contains_instant(active, at) => 2,
+        Some(_) => 0,
+        None => 1,
+    };
+
+    let mut merged: Vec<(usize, usize, DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    let mut next = 0;
+    let mut overlap_ms = 0i64;
+
+    for window in boundaries.windows(2) {
+        let (seg_start, seg_end) = (window[0], window[1]);
+        while next < items.len() && items[next].2 <= seg_start {
+            open.push(next);
+            next += 1;
+        }
+        open.retain(|&i| items[i].3 > seg_start);
+        if open.is_empty() {
+            continue;
+        }
+
+        let first_device = items[open[0]].0;
+        if open.iter().any(|&i| items[i].0 != first_device) {
+            overlap_ms += (seg_end - seg_start).num_milliseconds();
+        }
+
+        let midpoint = seg_start + (seg_end - seg_start) / 2;
+        let event = |i: usize| &devices[items[i].0].events[items[i].1];
+        let browser_window_open = open.iter().any(|&i| is_browser_window(event(i)));
+        let by_priority = |a: usize, b: usize| {
+            let (da, db) = (items[a].0, items[b].0);
+            rank(&devices[da], midpoint)
+                .cmp(&rank(&devices[db], midpoint))
+                .then(db.cmp(&da))
+                .then(items[a].2.cmp(&items[b].2))
+        };
+        let winners: Vec<usize> = match strategy {
+            MergeStrategy::SplitProportional => {
+                let top = open
+                    .iter()
+                    .map(|&i| rank(&devices[items[i].0], midpoint))
+                    .max();
+                let mut winners: Vec<usize> = open
+                    .iter()
+                    .copied()
+                    .filter(|&i| Some(rank(&devices[items[i].0], midpoint)) == top)
+                    .collect();
+                winners.sort_by_key(|&i| items[i].0);
+                winners
+            }
+            _ => open
+                .iter()
+                .copied()
+                .max_by(|&a, &b| match strategy {
+                    MergeStrategy::BrowserOverWindow if browser_window_open => {
+                        let tab = |i: usize| event(i).data.contains_key("url");
+                        tab(a).cmp(&tab(b)).then(by_priority(a, b))
+                    }
+                    MergeStrategy::LongestWins => {
+                        let length = |i: usize| items[i].3 - items[i].2;
+                        length(a).cmp(&length(b)).then(by_priority(a, b))
+                    }
+                    _ => by_priority(a, b),
+                })
+                .into_iter()
+                .collect(),
+        };
+
+        // Equal consecutive slices of the segment, one per winner
+        let slice = (seg_end - seg_start) / winners.len() as i32;
+        for (n, &winner) in winners.iter().enumerate() {
+            let (device, event, _, _) = items[winner];
+            let start = seg_start + slice * n as i32;
+            let end = if n + 1 == winners.len() {
+                seg_end
+            } else {
+                start + slice
+            };
+            match merged.last_mut() {
+                Some(last) if last.0 == device && last.1 == event && last.3 == start => {
+                    last.3 = end;
+                }
+                _ => merged.push((device, event, start, end)),
+            }
+        }
+    }
+
+    let events = merged
+        .into_iter()
+        .map(|(d, e, start, end)| {
+            let mut event = devices[d].events[e].clone();
+            event.timestamp = start;
+            event.duration = EventDuration::between(start, end);
+            MergedEvent {
+                hostname: devices[d].hostname.clone(),
+                event,
+            }
+        })
+        .collect();
+
+    DeviceMerge {
+        events,
+        overlap_secs: overlap_ms as f64 / 1000.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn event(secs: i64, duration: f64, app: &str) -> Event {
+        test_event(at(secs), duration, serde_json::json!({"app": app}))
+    }
+
+    #[test]
+    fn clock_offset_shifts_timestamps() {
+        let mut events = vec![event(0, 10.0, "a")];
+        apply_clock_offset(&mut events, -2.5);
+        assert_eq!(events[0].timestamp, at(0) - Duration::milliseconds(2500));
+    }
+
+    #[test]
+    fn active_device_wins_overlap() {
+        let desktop = DeviceEvents {
+            hostname: "desktop".into(),
+            events: vec![event(0, 100.0, "editor")],
+            active: Some(vec![(at(0), at(50))]),
+        };
+        let phone = DeviceEvents {
+            hostname: "phone".into(),
+            events: vec![event(40, 20.0, "chat")],
+            active: None,
+        };
+
+        let merge = merge_devices(&[desktop, phone], MergeStrategy::Priority);
+        let timeline: Vec<_> = merge
+            .events
+            .iter()
+            .map(|m| (m.hostname.as_str(), m.event.duration.as_secs_f64()))
+            .collect();
+
+        // Desktop is active until 50s, then AFK; the phone (unknown) wins 50-60s
+        assert_eq!(
+            timeline,
+            vec![("desktop", 50.0), ("phone", 10.0), ("desktop", 40.0)]
+        );
+        assert_eq!(merge.overlap_secs, 20.0);
+    }
+
+    #[test]
+    fn strategies_resolve_window_and_browser_overlaps() {
+        let tab = |secs, duration| {
+            let mut event = event(secs, duration, "");
+            event.data = HashMap::from([("url".to_string(), serde_json::json!("https://a"))]);
+            event
+        };
+        let window = DeviceEvents {
+            hostname: "desk".into(),
+            events: vec![event(0, 60.0, "Firefox"), event(60, 60.0, "Terminal")],
+            active: None,
+        };
+        let web = DeviceEvents {
+            hostname: "desk".into(),
+            events: vec![tab(0, 120.0)],
+            active: None,
+        };
+        let timeline = |strategy| {
+            merge_devices(&[window.clone(), web.clone()], strategy)
+                .events
+                .iter()
+                .map(|m| {
+                    let label = m.event.data.get("app").or(m.event.data.get("url"));
+                    let label = label.and_then(|v| v.as_str()).unwrap_or_default();
+                    (label.to_string(), m.event.duration.as_secs_f64())
+                })
+                .collect::<Vec<_>>()
+        };
+        let entry = |label: &str, secs: f64| (label.to_string(), secs);
+
+        // The tab wins over the browser window, but not over the terminal
+        assert_eq!(
+            timeline(MergeStrategy::BrowserOverWindow),
+            vec![entry("https://a", 60.0), entry("Terminal", 60.0)]
+        );
+        assert_eq!(
+            timeline(MergeStrategy::LongestWins),
+            vec![entry("https://a", 120.0)]
+        );
+        assert_eq!(
+            timeline(MergeStrategy::SplitProportional),
+            vec![
+                entry("Firefox", 30.0),
+                entry("https://a", 30.0),
+                entry("Terminal", 30.0),
+                entry("https://a", 30.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn first_device_wins_ties() {
+        let a = DeviceEvents {
+            hostname: "a".into(),
+            events: vec![event(0, 10.0, "x")],
+            active: None,
+        };
+        let b = DeviceEvents {
+            hostname: "b".into(),
+            events: vec![event(0, 10.0, "y")],
+            active: None,
+        };
+
+        let merge = merge_devices(&[a, b], MergeStrategy::Priority);
+        assert_eq!(merge.events.len(), 1);
+        assert_eq!(merge.events[0].hostname, "a");
+    }
+}
@@ -0,0 +1,178 @@
+use super::aggregate::group_label;
+use super::intervals::active_intervals;
+use super::pack::clip_to_active;
+use super::range::{TimeRange, format_timestamp, parse_timestamp};
+use crate::models::Event;
+use std::fmt;
+use std::str::FromStr;
+
+/// First field of a group handle, naming its layout
+const HANDLE_VERSION: &str = "grp1";
+
+/// Separator of the fields of a group handle, safe in markdown tables
+const HANDLE_SEPARATOR: char = '~';
+
+/// Characters percent-escaped in the fields of a group handle: the
+/// separator, and those that would break a handle in a markdown table cell
+const HANDLE_ESCAPES: [(char, &str); 4] = [
+    (HANDLE_SEPARATOR, "%7E"),
+    ('|', "%7C"),
+    ('\n', "%0A"),
+    ('\r', "%0D"),
+];
+
+/// One group of a summary, such as an app's active time on a day, as a
+/// handle summaries emit (`group_id`) and `aw_drilldown` takes back to list
+/// the events behind the group. Handles are self-contained, so they stay
+/// valid across server restarts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupHandle {
+    /// Bucket of the group's events
+    pub bucket_id: String,
+
+    /// Event data key the summary grouped by (e.g., "app")
+    pub key: String,
+
+    /// The group's value of the key
+    pub value: String,
+
+    /// Range of the summary
+    pub range: TimeRange,
+
+    /// AFK watcher bucket, when the summary counted only the time the user
+    /// was present
+    pub afk_bucket_id: Option<String>,
+}
+
+impl GroupHandle {
+    /// The parts of a bucket's events that make up the group: those with
+    /// the group's value, clipped to the range and, with AFK events, to the
+    /// time the user was present
+    pub fn events(&self, events: &[Event], afk_events: Option<&[Event]>) -> Vec<Event> {
+        let within = match afk_events {
+            Some(afk_events) => active_intervals(afk_events)
+                .into_iter()
+                .filter_map(|interval| self.range.clip(interval))
+                .collect(),
+            None => vec![(self.range.start, self.range.end)],
+        };
+        let mut events: Vec<Event> = events
+            .iter()
+            .filter(|event| group_label(event, &self.key) == self.value)
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| event.timestamp);
+        clip_to_active(&events, &within)
+    }
+}
+
+impl fmt::Display for GroupHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            HANDLE_VERSION.to_string(),
+            escape_field(&self.bucket_id),
+            escape_field(&self.key),
+            escape_field(&self.value),
+            format_timestamp(&self.range.start),
+            format_timestamp(&self.range.end),
+            self.afk_bucket_id
+                .as_deref()
+                .map(escape_field)
+                .unwrap_or_default(),
+        ];
+        f.write_str(&fields.join(&HANDLE_SEPARATOR.to_string()))
+    }
+}
+
+impl FromStr for GroupHandle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid group ID '{}'. Pass a group_id from a summary such as aw_top_apps unchanged",
+                s
+            )
+        };
+        let fields: Vec<&str> = s.trim().split(HANDLE_SEPARATOR).collect();
+        let [version, bucket_id, key, value, start, end, afk_bucket_id] = fields[..] else {
+            return Err(invalid());
+        };
+        if version != HANDLE_VERSION || bucket_id.is_empty() || key.is_empty() {
+            return Err(invalid());
+        }
+        let start = parse_timestamp(start).map_err(|_| invalid())?;
+        let end = parse_timestamp(end).map_err(|_| invalid())?;
+        Ok(Self {
+            bucket_id: unescape_field(bucket_id),
+            key: unescape_field(key),
+            value: unescape_field(value),
+            range: TimeRange::new(start, end).map_err(|_| invalid())?,
+            afk_bucket_id: Some(afk_bucket_id)
+                .filter(|id| !id.is_empty())
+                .map(unescape_field),
+        })
+    }
+}
+
+/// Percent-escape a field of a group handle
+fn escape_field(field: &str) -> String {
+    HANDLE_ESCAPES
+        .iter()
+        .fold(field.replace('%', "%25"), |field, (c, escaped)| {
+            field.replace(*c, escaped)
+        })
+}
+
+/// Undo [`escape_field`]
+fn unescape_field(field: &str) -> String {
+    HANDLE_ESCAPES
+        .iter()
+        .fold(field.to_string(), |field, (c, escaped)| {
+            field.replace(escaped, &c.to_string())
+        })
+        .replace("%25", "%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn event(hour: u32, minutes: f64, data: serde_json::Value) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 3, 15, hour, 0, 0).unwrap(),
+            minutes * 60.0,
+            data,
+        )
+    }
+
+    #[test]
+    fn handles_round_trip_and_select_the_group() {
+        let handle = GroupHandle {
+            bucket_id: "aw-watcher-window_host".to_string(),
+            key: "title".to_string(),
+            value: "a | b ~ 100%".to_string(),
+            range: TimeRange::parse("2024-03-15T00:00:00Z", "2024-03-16T00:00:00Z").unwrap(),
+            afk_bucket_id: Some("aw-watcher-afk_host".to_string()),
+        };
+        let text = handle.to_string();
+        assert!(text.starts_with("grp1~aw-watcher-window_host~title~a %7C b %7E 100%25~"));
+        assert_eq!(text.parse::<GroupHandle>().unwrap(), handle);
+        assert!("grp1~bucket~app".parse::<GroupHandle>().is_err());
+
+        let windows = [
+            event(9, 60.0, serde_json::json!({"title": "a | b ~ 100%"})),
+            event(11, 60.0, serde_json::json!({"title": "other"})),
+        ];
+        let afk = [event(9, 30.0, serde_json::json!({"status": "not-afk"}))];
+        let events = handle.events(&windows, Some(&afk));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].duration.as_secs_f64(), 1800.0);
+        assert_eq!(
+            handle.events(&windows, None)[0].duration.as_secs_f64(),
+            3600.0
+        );
+    }
+}
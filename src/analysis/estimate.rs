@@ -0,0 +1,199 @@
+use crate::constants::CHARACTER_LIMIT;
+use crate::models::{DetailLevel, Event};
+use serde::Serialize;
+
+/// Rough number of characters per LLM token
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimated size of an event listing in one output format
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FormatEstimate {
+    /// Estimated response size in characters
+    pub chars: usize,
+
+    /// Estimated response size in tokens (about 4 characters per token)
+    pub tokens: usize,
+
+    /// Whether the response would be truncated at the character limit
+    pub truncated: bool,
+}
+
+impl FormatEstimate {
+    fn new(chars: usize, truncated: bool) -> Self {
+        Self {
+            chars,
+            tokens: chars.div_ceil(CHARS_PER_TOKEN),
+            truncated,
+        }
+    }
+}
+
+/// Estimated size of an `aw_get_events` response
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SizeEstimate {
+    /// Events in the requested range
+    pub total_events: i64,
+
+    /// Events the call would return with the given limit
+    pub returned_events: usize,
+
+    /// Events the per-event size was measured on
+    pub sampled_events: usize,
+
+    /// Markdown output
+    pub markdown: FormatEstimate,
+
+    /// JSON output
+    pub json: FormatEstimate,
+}
+
+impl SizeEstimate {
+    /// Extrapolate response sizes from a sample of the events.
+    ///
+    /// Markdown output is capped at the character limit, as the tool
+    /// truncates it; JSON output is not truncated.
+    pub fn from_sample(sample: &[Event], total_events: i64, limit: usize, escape: bool) -> Self {
+        let returned_events = (total_events.max(0) as usize).min(limit);
+        let (markdown_per_event, json_per_event) = if sample.is_empty() {
+            (0.0, 0.0)
+        } else {
+            let markdown: usize = sample
+                .iter()
+                .map(|event| event.to_markdown(escape).len() + 2)
+                .sum();
+            let json = serde_json::to_string_pretty(sample)
+                .map(|json| json.len())
+                .unwrap_or_default();
+            (
+                markdown as f64 / sample.len() as f64,
+                json as f64 / sample.len() as f64,
+            )
+        };
+
+        // Heading and count line of the markdown listing
+        let markdown_chars = 80 + (markdown_per_event * returned_events as f64) as usize;
+        let json_chars = 2 + (json_per_event * returned_events as f64) as usize;
+        Self {
+            total_events,
+            returned_events,
+            sampled_events: sample.len(),
+            markdown: FormatEstimate::new(
+                markdown_chars.min(CHARACTER_LIMIT),
+                markdown_chars > CHARACTER_LIMIT,
+            ),
+            json: FormatEstimate::new(json_chars, false),
+        }
+    }
+}
+
+/// Smallest accepted `max_tokens_hint`
+pub const MIN_TOKENS_HINT: usize = 100;
+
+/// A response size budget from a caller's `max_tokens_hint`, which tools
+/// use to pick the detail level, the number of rows and the output shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBudget {
+    /// The budget in characters (about 4 per token), at most the character
+    /// limit
+    pub chars: usize,
+}
+
+impl TokenBudget {
+    /// The budget for a number of tokens
+    pub fn from_tokens(tokens: usize) -> Result<Self, String> {
+        if tokens < MIN_TOKENS_HINT {
+            return Err(format!(
+                "max_tokens_hint must be at least {}",
+                MIN_TOKENS_HINT
+            ));
+        }
+        Ok(Self {
+            chars: tokens.saturating_mul(CHARS_PER_TOKEN).min(CHARACTER_LIMIT),
+        })
+    }
+
+    /// The most detail that fits: brief below 1500 tokens, full from 6000
+    pub fn detail(self) -> DetailLevel {
+        match self.chars / CHARS_PER_TOKEN {
+            ..1500 => DetailLevel::Brief,
+            1500..6000 => DetailLevel::Normal,
+            _ => DetailLevel::Full,
+        }
+    }
+
+    /// How many rows of about `row_chars` fit beside `overhead_chars` of
+    /// headings and totals (at least one)
+    pub fn rows(self, row_chars: usize, overhead_chars: usize) -> usize {
+        (self.chars.saturating_sub(overhead_chars) / row_chars.max(1)).max(1)
+    }
+
+    /// How many leading items fit beside `overhead_chars`, measuring each
+    /// with `chars`
+    pub fn fitting<T>(
+        self,
+        items: &[T],
+        overhead_chars: usize,
+        chars: impl Fn(&T) -> usize,
+    ) -> usize {
+        let mut used = overhead_chars;
+        items
+            .iter()
+            .take_while(|item| {
+                used += chars(item);
+                used <= self.chars
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn event(title: &str) -> Event {
+        Event::builder()
+            .timestamp(Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap())
+            .duration_secs(10.0)
+            .data("title", title)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn extrapolates_from_sample_and_caps_markdown() {
+        let sample = vec![event("a"), event("b")];
+        let small = SizeEstimate::from_sample(&sample, 10, 100, true);
+        assert_eq!(small.returned_events, 10);
+        assert!(!small.markdown.truncated);
+        assert_eq!(small.json.tokens, small.json.chars.div_ceil(4));
+
+        let large = SizeEstimate::from_sample(&sample, 100_000, 10_000, true);
+        assert_eq!(large.returned_events, 10_000);
+        assert!(large.markdown.truncated);
+        assert_eq!(large.markdown.chars, CHARACTER_LIMIT);
+        assert!(large.json.chars > small.json.chars * 900);
+    }
+
+    #[test]
+    fn shapes_responses_to_token_budgets() {
+        assert!(TokenBudget::from_tokens(50).is_err());
+        let small = TokenBudget::from_tokens(500).unwrap();
+        assert_eq!(small.chars, 2000);
+        assert_eq!(small.detail(), DetailLevel::Brief);
+        assert_eq!(small.rows(100, 500), 15);
+        assert_eq!(small.rows(100, 5000), 1);
+        assert_eq!(small.fitting(&[900, 900, 900], 100, |chars| *chars), 2);
+
+        let large = TokenBudget::from_tokens(1_000_000).unwrap();
+        assert_eq!(large.chars, CHARACTER_LIMIT);
+        assert_eq!(large.detail(), DetailLevel::Full);
+    }
+
+    #[test]
+    fn handles_empty_ranges() {
+        let estimate = SizeEstimate::from_sample(&[], 0, 100, true);
+        assert_eq!(estimate.returned_events, 0);
+        assert_eq!(estimate.json.chars, 2);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::models::{Event, EventDuration, ZeroDurationPolicy};
+use std::collections::HashSet;
+
+/// Problems fixed in a list of events received from aw-server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventFixes {
+    /// Neighbouring events that were not newest-first
+    pub out_of_order: usize,
+
+    /// Events dropped as duplicates (same ID, or identical without an ID)
+    pub duplicates: usize,
+}
+
+impl EventFixes {
+    /// Number of problems fixed
+    pub fn total(&self) -> usize {
+        self.out_of_order + self.duplicates
+    }
+}
+
+/// Put events into the newest-first order aw-server promises and drop
+/// duplicates, which imports can leave behind.
+///
+/// Transforms such as merging and sampling assume ordered, distinct events;
+/// this restores that. Events that were already in order are left as they
+/// are.
+pub fn sanitize_events(events: &mut Vec<Event>) -> EventFixes {
+    let out_of_order = events
+        .windows(2)
+        .filter(|w| w[0].timestamp < w[1].timestamp)
+        .count();
+    if out_of_order > 0 {
+        events.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    }
+
+    let before = events.len();
+    let mut ids = HashSet::new();
+    let mut kept: Vec<Event> = Vec::with_capacity(before);
+    for event in events.drain(..) {
+        let duplicate = match event.id {
+            Some(id) => !ids.insert(id),
+            None => kept.last().is_some_and(|last| {
+                last.id.is_none()
+                    && last.timestamp == event.timestamp
+                    && last.duration == event.duration
+                    && last.data == event.data
+            }),
+        };
+        if !duplicate {
+            kept.push(event);
+        }
+    }
+    *events = kept;
+
+    EventFixes {
+        out_of_order,
+        duplicates: before - events.len(),
+    }
+}
+
+/// Apply a zero-duration policy to a list of events.
+///
+/// The relative order of the input (newest-first as returned by aw-server,
+/// or oldest-first) is preserved.
+pub fn apply_zero_duration_policy(events: Vec<Event>, policy: ZeroDurationPolicy) -> Vec<Event> {
+    match policy {
+        ZeroDurationPolicy::Keep => events,
+        ZeroDurationPolicy::Drop => events
+            .into_iter()
+            .filter(|e| !e.duration.is_zero())
+            .collect(),
+        ZeroDurationPolicy::MergeIntoPrevious => merge_zero_duration(events),
+    }
+}
+
+/// Fold each zero-duration event into the chronologically preceding event.
+///
+/// When the preceding event carries the same data (a heartbeat tail), it is
+/// extended to end at the zero-duration event's timestamp. Otherwise the
+/// zero-duration event is simply absorbed.
+fn merge_zero_duration(mut events: Vec<Event>) -> Vec<Event> {
+    let newest_first = match (events.first(), events.last()) {
+        (Some(first), Some(last)) => first.timestamp > last.timestamp,
+        _ => false,
+    };
+    events.sort_by_key(|e| e.timestamp);
+
+    let mut merged: Vec<Event> = Vec::with_capacity(events.len());
+    for event in events {
+        if !event.duration.is_zero() {
+            merged.push(event);
+            continue;
+        }
+        if let Some(previous) = merged.last_mut()
+            && previous.data == event.data
+        {
+            let gap = EventDuration::between(previous.timestamp, event.timestamp);
+            previous.duration = previous.duration.max(gap);
+        }
+    }
+
+    if newest_first {
+        merged.reverse();
+    }
+    merged
+}
+
+/// Pick `n` events spread evenly over time: the first and last event plus
+/// the events nearest to evenly spaced points in between.
+///
+/// The result is oldest-first. When there are at most `n` events, all are
+/// returned.
+pub fn sample_events(mut events: Vec<Event>, n: usize) -> Vec<Event> {
+    events.sort_by_key(|e| e.timestamp);
+    if events.len() <= n {
+        return events;
+    }
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let first = events[0].timestamp;
+    let span = (events[events.len() - 1].timestamp - first).num_milliseconds() as f64;
+    let mut picked = vec![false; events.len()];
+    for i in 0..n {
+        let fraction = if n == 1 {
+            0.0
+        } else {
+            i as f64 / (n - 1) as f64
+        };
+        let target = first + chrono::Duration::milliseconds((span * fraction) as i64);
+        let nearest = events.partition_point(|e| e.timestamp < target);
+        // Closest unpicked event on either side of the target
+        let before = (0..nearest.min(events.len())).rev().find(|&j| !picked[j]);
+        let after = (nearest..events.len()).find(|&j| !picked[j]);
+        let distance = |j: usize| (events[j].timestamp - target).num_milliseconds().abs();
+        let choice = match (before, after) {
+            (Some(b), Some(a)) => {
+                if distance(a) < distance(b) {
+                    a
+                } else {
+                    b
+                }
+            }
+            (Some(j), None) | (None, Some(j)) => j,
+            (None, None) => break,
+        };
+        picked[choice] = true;
+    }
+
+    events
+        .into_iter()
+        .zip(picked)
+        .filter_map(|(event, picked)| picked.then_some(event))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn event(secs: i64, duration: f64, app: &str) -> Event {
+        test_event(
+            Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            duration,
+            serde_json::json!({"app": app}),
+        )
+    }
+
+    #[test]
+    fn keep_returns_events_unchanged() {
+        let events = vec![event(0, 10.0, "a"), event(10, 0.0, "a")];
+        let result = apply_zero_duration_policy(events, ZeroDurationPolicy::Keep);
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn drop_removes_zero_duration_events() {
+        let events = vec![
+            event(0, 10.0, "a"),
+            event(10, 0.0, "a"),
+            event(20, 5.0, "b"),
+        ];
+        let result = apply_zero_duration_policy(events, ZeroDurationPolicy::Drop);
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|e| !e.duration.is_zero()));
+    }
+
+    #[test]
+    fn merge_extends_previous_event_with_same_data() {
+        // Newest first, as returned by aw-server
+        let events = vec![event(15, 0.0, "a"), event(0, 10.0, "a")];
+        let result = apply_zero_duration_policy(events, ZeroDurationPolicy::MergeIntoPrevious);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].duration.as_secs_f64(), 15.0);
+    }
+
+    #[test]
+    fn merge_absorbs_event_with_different_data() {
+        let events = vec![event(5, 0.0, "b"), event(0, 10.0, "a")];
+        let result = apply_zero_duration_policy(events, ZeroDurationPolicy::MergeIntoPrevious);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].duration.as_secs_f64(), 10.0);
+    }
+
+    #[test]
+    fn merge_preserves_newest_first_order() {
+        let events = vec![event(20, 5.0, "b"), event(10, 0.0, "a"), event(0, 5.0, "a")];
+        let result = apply_zero_duration_policy(events, ZeroDurationPolicy::MergeIntoPrevious);
+        assert_eq!(result.len(), 2);
+        assert!(result[0].timestamp > result[1].timestamp);
+        assert_eq!(result[1].duration.as_secs_f64(), 10.0);
+    }
+
+    #[test]
+    fn sanitize_sorts_and_drops_duplicates() {
+        let mut imported = event(5, 1.0, "b");
+        imported.id = Some(7);
+        let mut events = vec![
+            event(10, 1.0, "a"),
+            imported.clone(),
+            event(20, 1.0, "c"),
+            imported,
+            event(10, 1.0, "a"),
+        ];
+        let fixes = sanitize_events(&mut events);
+        assert_eq!(
+            fixes,
+            EventFixes {
+                out_of_order: 2,
+                duplicates: 2
+            }
+        );
+        let apps: Vec<&str> = events
+            .iter()
+            .map(|e| e.data["app"].as_str().unwrap())
+            .collect();
+        assert_eq!(apps, ["c", "a", "b"]);
+
+        assert_eq!(sanitize_events(&mut events).total(), 0);
+    }
+
+    #[test]
+    fn sample_spreads_over_time() {
+        // A dense burst at the start and sparse events later
+        let mut events: Vec<Event> = (0..50).map(|i| event(i, 1.0, "burst")).collect();
+        events.extend([event(1000, 1.0, "mid"), event(2000, 1.0, "end")]);
+        events.reverse();
+
+        let sample = sample_events(events, 3);
+        let apps: Vec<&str> = sample
+            .iter()
+            .map(|e| e.data["app"].as_str().unwrap())
+            .collect();
+        assert_eq!(apps, vec!["burst", "mid", "end"]);
+        assert_eq!(sample[0].timestamp, event(0, 1.0, "").timestamp);
+    }
+
+    #[test]
+    fn sample_returns_distinct_events() {
+        let events: Vec<Event> = (0..10)
+            .map(|i| event(i, 1.0, "a"))
+            .chain([event(1000, 1.0, "b")])
+            .collect();
+        let sample = sample_events(events, 5);
+        assert_eq!(sample.len(), 5);
+        assert!(sample.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+        assert_eq!(sample_events(vec![event(0, 1.0, "a")], 5).len(), 1);
+    }
+}
@@ -0,0 +1,170 @@
+use super::intervals::{AFK_BUCKET_TYPE, NOT_AFK_STATUS};
+use crate::models::{Bucket, Event};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::fmt;
+
+/// Whether a watcher is still reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherState {
+    /// Reported within the threshold
+    Alive,
+    /// Silent although the user is active on its host (or it is the AFK
+    /// watcher itself, which reports even while the user is away)
+    Dead,
+    /// Silent while the user is away or their status is unknown
+    Quiet,
+    /// The bucket has no events
+    Empty,
+}
+
+impl fmt::Display for WatcherState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Alive => "alive",
+            Self::Dead => "dead",
+            Self::Quiet => "quiet",
+            Self::Empty => "empty",
+        })
+    }
+}
+
+/// Health of one watcher bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherStatus {
+    /// The bucket ID
+    pub bucket_id: String,
+
+    /// Hostname of the watcher
+    pub hostname: Option<String>,
+
+    /// When the latest event ended
+    pub last_seen: Option<DateTime<Utc>>,
+
+    /// Seconds since the latest event ended
+    pub age_secs: Option<f64>,
+
+    /// Assessed state
+    pub state: WatcherState,
+}
+
+/// Assess watchers from the latest event of each bucket.
+///
+/// A watcher is alive when its latest event ended less than `stale` ago.
+/// A silent watcher is dead when the AFK watcher of its host is alive and
+/// reports the user as present; an AFK watcher is dead whenever it is
+/// silent. Other silent watchers are quiet.
+pub fn assess_watchers(
+    latest: &[(&Bucket, Option<Event>)],
+    now: DateTime<Utc>,
+    stale: Duration,
+) -> Vec<WatcherStatus> {
+    let is_afk = |bucket: &Bucket| bucket.bucket_type.as_deref() == Some(AFK_BUCKET_TYPE);
+    let fresh = |event: &Event| now - event.end() < stale;
+    let user_present = |hostname: &Option<String>| {
+        latest.iter().any(|(bucket, event)| {
+            is_afk(bucket)
+                && bucket.hostname == *hostname
+                && event.as_ref().is_some_and(|e| {
+                    fresh(e)
+                        && e.data.get("status").and_then(|v| v.as_str()) == Some(NOT_AFK_STATUS)
+                })
+        })
+    };
+
+    latest
+        .iter()
+        .map(|(bucket, event)| {
+            let state = match event {
+                None => WatcherState::Empty,
+                Some(e) if fresh(e) => WatcherState::Alive,
+                Some(_) if is_afk(bucket) || user_present(&bucket.hostname) => WatcherState::Dead,
+                Some(_) => WatcherState::Quiet,
+            };
+            let last_seen = event.as_ref().map(Event::end);
+            WatcherStatus {
+                bucket_id: bucket.id.clone(),
+                hostname: bucket.hostname.clone(),
+                last_seen,
+                age_secs: last_seen.map(|t| (now - t).num_milliseconds() as f64 / 1000.0),
+                state,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn bucket(id: &str, bucket_type: &str, hostname: &str) -> Bucket {
+        Bucket {
+            id: id.to_string(),
+            client: None,
+            bucket_type: Some(bucket_type.to_string()),
+            hostname: Some(hostname.to_string()),
+            created: None,
+            data: None,
+            last_updated: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn event(minutes_ago: i64, status: Option<&str>) -> Option<Event> {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let data = match status {
+            Some(status) => serde_json::json!({"status": status}),
+            None => serde_json::json!({}),
+        };
+        Some(test_event(now - Duration::minutes(minutes_ago), 0.0, data))
+    }
+
+    #[test]
+    fn flags_silent_watchers_while_the_user_is_present() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let desk_afk = bucket("afk_desk", "afkstatus", "desk");
+        let desk_window = bucket("window_desk", "currentwindow", "desk");
+        let (laptop_afk, laptop_window, laptop_web) = (
+            bucket("afk_laptop", "afkstatus", "laptop"),
+            bucket("window_laptop", "currentwindow", "laptop"),
+            bucket("web_laptop", "web.tab.current", "laptop"),
+        );
+        let latest = vec![
+            (&desk_afk, event(1, Some("not-afk"))),
+            (&desk_window, event(30, None)),
+            (&laptop_afk, event(1, Some("afk"))),
+            (&laptop_window, event(30, None)),
+            (&laptop_web, None),
+        ];
+
+        let states: Vec<WatcherState> = assess_watchers(&latest, now, Duration::minutes(10))
+            .iter()
+            .map(|s| s.state)
+            .collect();
+        assert_eq!(
+            states,
+            [
+                WatcherState::Alive,
+                WatcherState::Dead,
+                WatcherState::Alive,
+                WatcherState::Quiet,
+                WatcherState::Empty
+            ]
+        );
+
+        // A silent AFK watcher is dead, and its host's status is unknown
+        let latest = vec![
+            (&desk_afk, event(30, Some("not-afk"))),
+            (&desk_window, event(30, None)),
+        ];
+        let states: Vec<WatcherState> = assess_watchers(&latest, now, Duration::minutes(10))
+            .iter()
+            .map(|s| s.state)
+            .collect();
+        assert_eq!(states, [WatcherState::Dead, WatcherState::Quiet]);
+    }
+}
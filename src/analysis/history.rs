@@ -0,0 +1,296 @@
+use crate::models::Bucket;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The metadata of a bucket kept in its history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketRecord {
+    /// Bucket ID
+    pub id: String,
+
+    /// Watcher that created the bucket
+    pub client: Option<String>,
+
+    /// Type of events stored
+    pub bucket_type: Option<String>,
+
+    /// Host that created the bucket
+    pub hostname: Option<String>,
+
+    /// When the bucket was created
+    pub created: Option<DateTime<Utc>>,
+
+    /// When the bucket last received events
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+impl BucketRecord {
+    /// The record of a bucket's current metadata
+    pub fn new(bucket: &Bucket) -> Self {
+        Self {
+            id: bucket.id.clone(),
+            client: bucket.client.clone(),
+            bucket_type: bucket.bucket_type.clone(),
+            hostname: bucket.hostname.clone(),
+            created: bucket.created,
+            last_updated: bucket.last_activity(),
+        }
+    }
+}
+
+/// The buckets aw-server had over a stretch of time, kept in the local
+/// store to tell how they changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BucketSnapshot {
+    /// When these buckets were first seen
+    pub taken_at: DateTime<Utc>,
+
+    /// When they were last seen unchanged (`last_updated` aside)
+    pub checked_at: DateTime<Utc>,
+
+    /// The buckets, by ID
+    pub buckets: Vec<BucketRecord>,
+}
+
+impl BucketSnapshot {
+    /// A snapshot of the current buckets
+    pub fn new<'a>(buckets: impl IntoIterator<Item = &'a Bucket>, now: DateTime<Utc>) -> Self {
+        let mut buckets: Vec<BucketRecord> = buckets.into_iter().map(BucketRecord::new).collect();
+        buckets.sort_by(|a, b| a.id.cmp(&b.id));
+        Self {
+            taken_at: now,
+            checked_at: now,
+            buckets,
+        }
+    }
+
+    /// Whether both snapshots have the same buckets with the same metadata,
+    /// apart from when they last received events
+    pub fn same_buckets(&self, other: &Self) -> bool {
+        let identity = |r: &BucketRecord| {
+            (
+                r.id.clone(),
+                r.client.clone(),
+                r.bucket_type.clone(),
+                r.hostname.clone(),
+            )
+        };
+        self.buckets.len() == other.buckets.len()
+            && self
+                .buckets
+                .iter()
+                .map(identity)
+                .eq(other.buckets.iter().map(identity))
+    }
+
+    /// The record of a bucket
+    fn get(&self, id: &str) -> Option<&BucketRecord> {
+        self.buckets.iter().find(|record| record.id == id)
+    }
+}
+
+/// How a bucket changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum BucketChangeKind {
+    /// The bucket was created or first seen
+    Appeared,
+    /// The bucket was deleted
+    Disappeared,
+    /// The bucket's host changed: a bucket of the same watcher appeared on
+    /// a new hostname after the old one's went quiet (e.g., after an OS
+    /// reinstall or renaming the machine), or the bucket's own hostname
+    /// changed
+    HostChanged {
+        /// The previous hostname
+        from: Option<String>,
+        /// The new hostname
+        to: Option<String>,
+        /// The bucket of the previous host, when it is a different one
+        previous_bucket: Option<String>,
+    },
+    /// The bucket has received no events for longer than the threshold
+    Stale,
+}
+
+/// A change of one bucket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BucketChange {
+    /// When it happened (for stale buckets: their last events)
+    pub at: DateTime<Utc>,
+
+    /// The bucket
+    pub bucket_id: String,
+
+    /// What changed
+    #[serde(flatten)]
+    pub kind: BucketChangeKind,
+}
+
+/// The changes between consecutive snapshots of the buckets, oldest first,
+/// and the buckets that are stale in the latest one: without events for
+/// longer than `stale_after` before it was last checked
+pub fn bucket_history(snapshots: &[BucketSnapshot], stale_after: Duration) -> Vec<BucketChange> {
+    let mut changes = Vec::new();
+    let Some(first) = snapshots.first() else {
+        return changes;
+    };
+    changes.extend(first.buckets.iter().map(|record| BucketChange {
+        at: record.created.unwrap_or(first.taken_at).min(first.taken_at),
+        bucket_id: record.id.clone(),
+        kind: BucketChangeKind::Appeared,
+    }));
+
+    for pair in snapshots.windows(2) {
+        let (previous, current) = (&pair[0], &pair[1]);
+        for record in &current.buckets {
+            let change = |kind| BucketChange {
+                at: current.taken_at,
+                bucket_id: record.id.clone(),
+                kind,
+            };
+            match previous.get(&record.id) {
+                Some(before) if before.hostname != record.hostname => {
+                    changes.push(change(BucketChangeKind::HostChanged {
+                        from: before.hostname.clone(),
+                        to: record.hostname.clone(),
+                        previous_bucket: None,
+                    }))
+                }
+                Some(_) => {}
+                None => {
+                    // A new host's bucket takes over from one of the same
+                    // watcher that received nothing since it was created
+                    let appeared = record.created.unwrap_or(current.taken_at);
+                    let predecessor = previous.buckets.iter().find(|before| {
+                        before.client == record.client
+                            && before.bucket_type == record.bucket_type
+                            && before.hostname != record.hostname
+                            && current
+                                .get(&before.id)
+                                .unwrap_or(before)
+                                .last_updated
+                                .is_none_or(|last| last <= appeared)
+                    });
+                    changes.push(change(match predecessor {
+                        Some(before) => BucketChangeKind::HostChanged {
+                            from: before.hostname.clone(),
+                            to: record.hostname.clone(),
+                            previous_bucket: Some(before.id.clone()),
+                        },
+                        None => BucketChangeKind::Appeared,
+                    }));
+                }
+            }
+        }
+        changes.extend(
+            previous
+                .buckets
+                .iter()
+                .filter(|before| current.get(&before.id).is_none())
+                .map(|before| BucketChange {
+                    at: current.taken_at,
+                    bucket_id: before.id.clone(),
+                    kind: BucketChangeKind::Disappeared,
+                }),
+        );
+    }
+
+    if let Some(latest) = snapshots.last() {
+        changes.extend(latest.buckets.iter().filter_map(|record| {
+            let last = record.last_updated?;
+            (latest.checked_at - last > stale_after).then(|| BucketChange {
+                at: last,
+                bucket_id: record.id.clone(),
+                kind: BucketChangeKind::Stale,
+            })
+        }));
+    }
+    changes.sort_by(|a, b| a.at.cmp(&b.at).then_with(|| a.bucket_id.cmp(&b.bucket_id)));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap()
+    }
+
+    fn record(id: &str, hostname: &str, created: u32, last_updated: u32) -> BucketRecord {
+        BucketRecord {
+            id: id.to_string(),
+            client: Some("aw-watcher-window".to_string()),
+            bucket_type: Some("currentwindow".to_string()),
+            hostname: Some(hostname.to_string()),
+            created: Some(day(created)),
+            last_updated: Some(day(last_updated)),
+        }
+    }
+
+    fn snapshot(at: u32, buckets: Vec<BucketRecord>) -> BucketSnapshot {
+        BucketSnapshot {
+            taken_at: day(at),
+            checked_at: day(at),
+            buckets,
+        }
+    }
+
+    #[test]
+    fn reports_new_hosts_and_stale_buckets() {
+        let snapshots = [
+            snapshot(1, vec![record("window_old", "old", 1, 1)]),
+            // Reinstalled on the 5th under a new hostname
+            snapshot(
+                5,
+                vec![
+                    record("window_new", "new", 5, 5),
+                    record("window_old", "old", 1, 4),
+                ],
+            ),
+            snapshot(20, vec![record("window_new", "new", 5, 20)]),
+        ];
+        assert!(!snapshots[0].same_buckets(&snapshots[1]));
+
+        let history = bucket_history(&snapshots, Duration::days(7));
+        let kinds: Vec<(&str, &BucketChangeKind)> = history
+            .iter()
+            .map(|c| (c.bucket_id.as_str(), &c.kind))
+            .collect();
+        assert_eq!(kinds[0], ("window_old", &BucketChangeKind::Appeared));
+        assert_eq!(
+            kinds[1],
+            (
+                "window_new",
+                &BucketChangeKind::HostChanged {
+                    from: Some("old".to_string()),
+                    to: Some("new".to_string()),
+                    previous_bucket: Some("window_old".to_string()),
+                }
+            )
+        );
+        assert_eq!(kinds[2], ("window_old", &BucketChangeKind::Disappeared));
+        assert_eq!(history.len(), 3);
+
+        // A bucket of a second machine running at the same time is new
+        let concurrent = [
+            snapshot(1, vec![record("window_a", "a", 1, 1)]),
+            snapshot(
+                5,
+                vec![record("window_a", "a", 1, 5), record("window_b", "b", 3, 5)],
+            ),
+        ];
+        let history = bucket_history(&concurrent, Duration::days(7));
+        assert_eq!(history.last().unwrap().kind, BucketChangeKind::Appeared);
+
+        // Without events for more than the threshold
+        let quiet = [snapshot(20, vec![record("window_a", "a", 1, 10)])];
+        let history = bucket_history(&quiet, Duration::days(7));
+        assert_eq!(
+            (history[1].at, &history[1].kind),
+            (day(10), &BucketChangeKind::Stale)
+        );
+    }
+}
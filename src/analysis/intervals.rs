@@ -0,0 +1,45 @@
+use crate::models::Event;
+use chrono::{DateTime, Utc};
+
+/// A half-open time interval `[start, end)`
+pub type Interval = (DateTime<Utc>, DateTime<Utc>);
+
+/// AFK watcher bucket type
+pub const AFK_BUCKET_TYPE: &str = "afkstatus";
+
+/// AFK status value marking the user as present
+pub const NOT_AFK_STATUS: &str = "not-afk";
+
+/// Sort and merge overlapping or touching intervals
+pub fn merge_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.retain(|(start, end)| end > start);
+    intervals.sort_by_key(|(start, _)| *start);
+
+    let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => {
+                *last_end = (*last_end).max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Merged intervals during which an AFK bucket reports the user as present
+pub fn active_intervals(afk_events: &[Event]) -> Vec<Interval> {
+    merge_intervals(
+        afk_events
+            .iter()
+            .filter(|e| e.data.get("status").and_then(|v| v.as_str()) == Some(NOT_AFK_STATUS))
+            .map(|e| (e.timestamp, e.end()))
+            .collect(),
+    )
+}
+
+/// Whether a point in time falls inside any of the given merged intervals
+pub fn contains_instant(intervals: &[Interval], instant: DateTime<Utc>) -> bool {
+    let idx = intervals.partition_point(|(start, _)| *start <= instant);
+    idx > 0 && intervals[idx - 1].1 > instant
+}
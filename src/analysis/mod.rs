@@ -0,0 +1,43 @@
+mod aggregate;
+mod alerts;
+mod browser;
+mod checks;
+mod coverage;
+mod daily;
+mod devices;
+mod drilldown;
+mod estimate;
+mod events;
+mod health;
+mod history;
+mod intervals;
+mod pack;
+mod period;
+mod preset;
+mod quality;
+mod range;
+mod reviews;
+mod series;
+mod tickets;
+
+pub use aggregate::*;
+pub use alerts::*;
+pub use browser::*;
+pub use checks::*;
+pub use coverage::*;
+pub use daily::*;
+pub use devices::*;
+pub use drilldown::*;
+pub use estimate::*;
+pub use events::*;
+pub use health::*;
+pub use history::*;
+pub use intervals::*;
+pub use pack::*;
+pub use period::*;
+pub use preset::*;
+pub use quality::*;
+pub use range::*;
+pub use reviews::*;
+pub use series::*;
+pub use tickets::*;
@@ -0,0 +1,395 @@
+use super::intervals::{Interval, merge_intervals};
+use super::range::TimeRange;
+use crate::categories::CATEGORY_SEPARATOR;
+use crate::models::{Event, EventDuration};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A weekly target for the time in a category, e.g. `Work>=20h` (at least
+/// 20 hours of Work and its subcategories) or `Media<=5h` (at most 5 hours)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Goal {
+    /// Category path the goal is about (e.g., "Work > Programming")
+    pub category: String,
+
+    /// Whether the time should reach the target (`>=`) or stay below it
+    /// (`<=`)
+    pub at_least: bool,
+
+    /// Target seconds per week
+    pub weekly_secs: f64,
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    /// Parse `category>=time` or `category<=time`, with times like `20h`,
+    /// `90m` or `7h30m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (category, at_least, target) = if let Some((category, target)) = s.split_once(">=") {
+            (category, true, target)
+        } else if let Some((category, target)) = s.split_once("<=") {
+            (category, false, target)
+        } else {
+            return Err(format!(
+                "Expected category>=time or category<=time, got '{}'",
+                s
+            ));
+        };
+        let category = category.trim();
+        if category.is_empty() {
+            return Err(format!("Goal '{}' has no category", s.trim()));
+        }
+        let weekly_secs = parse_hours_minutes(target).ok_or_else(|| {
+            format!(
+                "Invalid time '{}' in goal, expected e.g. 20h or 7h30m",
+                target.trim()
+            )
+        })?;
+        Ok(Self {
+            category: category.to_string(),
+            at_least,
+            weekly_secs,
+        })
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = if self.at_least { ">=" } else { "<=" };
+        write!(
+            f,
+            "{}{}{}",
+            self.category,
+            op,
+            format_hours_minutes(self.weekly_secs)
+        )
+    }
+}
+
+/// A time like `20h`, `90m` or `7h30m`, rounded to the minute
+pub(super) fn format_hours_minutes(secs: f64) -> String {
+    let minutes = (secs / 60.0).round() as u64;
+    match (minutes / 60, minutes % 60) {
+        (hours, 0) => format!("{}h", hours),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h{}m", hours, minutes),
+    }
+}
+
+/// Seconds of a time like `20h`, `90m` or `7h30m`
+pub(super) fn parse_hours_minutes(text: &str) -> Option<f64> {
+    let text = text.trim().to_ascii_lowercase();
+    let (hours, rest) = match text.split_once('h') {
+        Some((hours, rest)) => (hours.trim().parse::<f64>().ok()?, rest),
+        None => (0.0, text.as_str()),
+    };
+    let minutes = match rest.trim() {
+        "" => 0.0,
+        rest => rest.strip_suffix('m')?.trim().parse::<f64>().ok()?,
+    };
+    let secs = (hours * 60.0 + minutes) * 60.0;
+    (secs.is_finite() && secs > 0.0).then_some(secs)
+}
+
+/// Whether a category path is the given category or one of its
+/// subcategories
+pub fn in_category(path: &str, category: &str) -> bool {
+    path == category
+        || path
+            .strip_prefix(category)
+            .is_some_and(|rest| rest.starts_with(CATEGORY_SEPARATOR))
+}
+
+/// How a range measured up to a goal
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalStatus {
+    /// The goal
+    pub goal: Goal,
+
+    /// The weekly target scaled to the range's length
+    pub target_secs: f64,
+
+    /// Time in the category and its subcategories
+    pub actual_secs: f64,
+
+    /// Whether the target was reached (or not exceeded)
+    pub met: bool,
+}
+
+/// Check goals against the time per full category path; weekly targets are
+/// scaled to `days`
+pub fn goal_status(goals: &[Goal], leaves: &[(String, f64)], days: f64) -> Vec<GoalStatus> {
+    goals
+        .iter()
+        .map(|goal| {
+            let actual_secs = leaves
+                .iter()
+                .filter(|(path, _)| in_category(path, &goal.category))
+                .map(|(_, secs)| secs)
+                .sum();
+            let target_secs = goal.weekly_secs * days / 7.0;
+            GoalStatus {
+                goal: goal.clone(),
+                target_secs,
+                actual_secs,
+                met: match goal.at_least {
+                    true => actual_secs >= target_secs,
+                    false => actual_secs <= target_secs,
+                },
+            }
+        })
+        .collect()
+}
+
+/// A stretch of uninterrupted, undistracted activity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FocusSession {
+    /// Start of the session
+    pub start: DateTime<Utc>,
+
+    /// End of the session
+    pub end: DateTime<Utc>,
+
+    /// Seconds of activity within the session
+    pub secs: f64,
+
+    /// The app the session spent most time in
+    pub app: String,
+}
+
+/// The parts of events that fall within `active` intervals (e.g., when
+/// the AFK watcher saw the user), as events of their own
+pub fn clip_to_active(events: &[Event], active: &[Interval]) -> Vec<Event> {
+    let mut clipped = Vec::new();
+    for event in events {
+        let (start, end) = (event.timestamp, event.end());
+        for &(a_start, a_end) in active {
+            let (from, to) = (a_start.max(start), a_end.min(end));
+            if to > from {
+                let mut part = event.clone();
+                part.timestamp = from;
+                part.duration = EventDuration::between(from, to);
+                clipped.push(part);
+            }
+        }
+    }
+    clipped
+}
+
+/// Focus sessions: undistracted activity lasting at least `min_secs`, where
+/// breaks of up to `max_break_secs` (a quick look elsewhere, a short AFK)
+/// do not end a session
+pub fn focus_sessions(
+    events: &[Event],
+    is_distraction: impl Fn(&Event) -> bool,
+    range: &TimeRange,
+    min_secs: f64,
+    max_break_secs: f64,
+) -> Vec<FocusSession> {
+    let mut pieces: Vec<(Interval, &str)> = events
+        .iter()
+        .filter(|event| !is_distraction(event))
+        .filter_map(|event| {
+            let interval = range.clip((event.timestamp, event.end()))?;
+            let app = event
+                .data
+                .get("app")
+                .and_then(|v| v.as_str())
+                .unwrap_or("(unknown)");
+            Some((interval, app))
+        })
+        .collect();
+    pieces.sort_by_key(|((start, _), _)| *start);
+
+    let max_break = Duration::milliseconds((max_break_secs * 1000.0) as i64);
+    let mut sessions: Vec<Vec<(Interval, &str)>> = Vec::new();
+    let mut session_end: Option<DateTime<Utc>> = None;
+    for piece in pieces {
+        match (sessions.last_mut(), session_end) {
+            (Some(session), Some(end)) if piece.0.0 <= end + max_break => {
+                session.push(piece);
+                session_end = Some(end.max(piece.0.1));
+            }
+            _ => {
+                sessions.push(vec![piece]);
+                session_end = Some(piece.0.1);
+            }
+        }
+    }
+
+    sessions
+        .into_iter()
+        .filter_map(|pieces| {
+            let start = pieces.first()?.0.0;
+            let end = pieces.iter().map(|((_, end), _)| *end).max()?;
+            let intervals = pieces.iter().map(|(interval, _)| *interval).collect();
+            let secs: f64 = merge_intervals(intervals)
+                .iter()
+                .map(|(start, end)| (*end - *start).num_milliseconds() as f64 / 1000.0)
+                .sum();
+            if secs < min_secs {
+                return None;
+            }
+            let mut apps: HashMap<&str, f64> = HashMap::new();
+            for ((start, end), app) in &pieces {
+                *apps.entry(app).or_default() += (*end - *start).num_milliseconds() as f64;
+            }
+            let app = apps
+                .into_iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(app, _)| app.to_string())?;
+            Some(FocusSession {
+                start,
+                end,
+                secs,
+                app,
+            })
+        })
+        .collect()
+}
+
+/// A day whose total stands out from the other days of the range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayAnomaly {
+    /// The day's label
+    pub day: String,
+
+    /// The day's total in seconds
+    pub secs: f64,
+
+    /// The median of the active days' totals
+    pub median_secs: f64,
+
+    /// What stands out, e.g. "unusually long"
+    pub note: &'static str,
+}
+
+/// Days with at least 1.5 times, or at most half, the median total of the
+/// days with any activity (given at least three such days)
+pub fn day_anomalies(days: &[(String, f64)]) -> Vec<DayAnomaly> {
+    let mut active: Vec<f64> = days
+        .iter()
+        .map(|(_, secs)| *secs)
+        .filter(|s| *s > 0.0)
+        .collect();
+    if active.len() < 3 {
+        return Vec::new();
+    }
+    active.sort_by(f64::total_cmp);
+    let mid = active.len() / 2;
+    let median_secs = match active.len() % 2 {
+        0 => (active[mid - 1] + active[mid]) / 2.0,
+        _ => active[mid],
+    };
+    days.iter()
+        .filter(|(_, secs)| *secs > 0.0)
+        .filter_map(|(day, secs)| {
+            let note = if *secs >= median_secs * 1.5 {
+                "unusually long"
+            } else if *secs <= median_secs * 0.5 {
+                "unusually short"
+            } else {
+                return None;
+            };
+            Some(DayAnomaly {
+                day: day.clone(),
+                secs: *secs,
+                median_secs,
+                note,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn parses_and_checks_goals() {
+        let goal: Goal = "Work >= 7h30m".parse().unwrap();
+        assert_eq!(
+            (goal.category.as_str(), goal.at_least, goal.weekly_secs),
+            ("Work", true, 27000.0)
+        );
+        assert_eq!(goal.to_string(), "Work>=7h30m");
+        assert_eq!("Media<=90m".parse::<Goal>().unwrap().weekly_secs, 5400.0);
+        assert!("Work=20h".parse::<Goal>().is_err());
+        assert!("Work>=lots".parse::<Goal>().is_err());
+        assert!(">=2h".parse::<Goal>().is_err());
+
+        let goals = ["Work>=14h".parse().unwrap(), "Media<=1h".parse().unwrap()];
+        let leaves = [
+            ("Work > Programming".to_string(), 3.0 * 3600.0),
+            ("Workshop".to_string(), 3600.0),
+            ("Media > Video".to_string(), 3600.0),
+        ];
+        // Half a week: 7h of Work and 30m of Media are expected
+        let status = goal_status(&goals, &leaves, 3.5);
+        assert_eq!(
+            (status[0].target_secs, status[0].actual_secs),
+            (25200.0, 10800.0)
+        );
+        assert!(!status[0].met);
+        assert_eq!(status[1].target_secs, 1800.0);
+        assert!(!status[1].met);
+    }
+
+    #[test]
+    fn finds_focus_sessions() {
+        let at = |minute: i64| {
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap() + Duration::minutes(minute)
+        };
+        let event = |minute: i64, minutes: f64, app: &str| -> Event {
+            test_event(at(minute), minutes * 60.0, json!({"app": app}))
+        };
+        let events = [
+            event(0, 20.0, "Code"),
+            event(21, 15.0, "Terminal"),
+            event(36, 10.0, "YouTube"),
+            event(46, 20.0, "Code"),
+        ];
+        let range = TimeRange::new(at(0), at(120)).unwrap();
+        let distraction = |e: &Event| e.data["app"] == "YouTube";
+
+        let sessions = focus_sessions(&events, distraction, &range, 1500.0, 120.0);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!((sessions[0].start, sessions[0].end), (at(0), at(36)));
+        assert_eq!(
+            (sessions[0].secs, sessions[0].app.as_str()),
+            (2100.0, "Code")
+        );
+
+        // Away for 10 minutes early on: what is left is too short
+        let active = [(at(0), at(5)), (at(15), at(120))];
+        let present = clip_to_active(&events, &active);
+        assert_eq!(present.len(), 5);
+        assert_eq!((present[1].timestamp, present[1].end()), (at(15), at(20)));
+        assert!(focus_sessions(&present, distraction, &range, 1500.0, 120.0).is_empty());
+    }
+
+    #[test]
+    fn flags_unusual_days() {
+        let days: Vec<(String, f64)> = [8.0, 7.0, 0.0, 12.0, 3.0, 7.5]
+            .iter()
+            .enumerate()
+            .map(|(i, hours)| (format!("day {}", i), hours * 3600.0))
+            .collect();
+        let anomalies = day_anomalies(&days);
+        let flagged: Vec<(&str, &str)> =
+            anomalies.iter().map(|a| (a.day.as_str(), a.note)).collect();
+        assert_eq!(
+            flagged,
+            [("day 3", "unusually long"), ("day 4", "unusually short")]
+        );
+        assert_eq!(anomalies[0].median_secs, 7.5 * 3600.0);
+        assert!(day_anomalies(&days[..2]).is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use super::range::TimeRange;
+use crate::timezone::Timezone;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+
+/// Resolve a named period to a range starting and ending at local midnight
+/// in `tz`.
+///
+/// Supported specifiers (case-insensitive):
+/// - `2024-W07`: ISO week (Monday to Monday)
+/// - `2024-03`, `2024-Q1`, `2024`: calendar month, quarter and year
+/// - `FY2024`, `FY2024-Q1`, `FY2024-M03`: fiscal year, quarter and month,
+///   where fiscal years start in `fiscal_year_start` (1-12) and are named
+///   after the calendar year they start in
+pub fn parse_period(
+    spec: &str,
+    fiscal_year_start: u32,
+    tz: &Timezone,
+) -> Result<TimeRange, String> {
+    let spec = spec.trim().to_ascii_uppercase();
+    let invalid = || {
+        format!(
+            "Invalid period '{}'. Expected e.g. 2024-W07, 2024-03, 2024-Q1, 2024, FY2024, FY2024-Q1 or FY2024-M03",
+            spec
+        )
+    };
+
+    let (fiscal, rest) = match spec.strip_prefix("FY") {
+        Some(rest) => (true, rest),
+        None => (false, spec.as_str()),
+    };
+    let (year, part) = match rest.split_once('-') {
+        Some((year, part)) => (year, Some(part)),
+        None => (rest, None),
+    };
+    if year.len() != 4 {
+        return Err(invalid());
+    }
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let number = |digits: &str, max: u32| {
+        digits
+            .parse::<u32>()
+            .ok()
+            .filter(|n| (1..=max).contains(n))
+            .ok_or_else(invalid)
+    };
+
+    // Months counted from the start of the (fiscal) year
+    let first_month = if fiscal { fiscal_year_start } else { 1 };
+    let (from, months) = match part {
+        None => (0, 12),
+        Some(part) if !fiscal && part.starts_with('W') => {
+            let week = number(&part[1..], 53)?;
+            let monday =
+                NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).ok_or_else(invalid)?;
+            let next = monday + chrono::Duration::weeks(1);
+            return TimeRange::new(tz.start_of_day(monday), tz.start_of_day(next));
+        }
+        Some(part) if part.starts_with('Q') => ((number(&part[1..], 4)? - 1) * 3, 3),
+        Some(part) if fiscal && part.starts_with('M') => (number(&part[1..], 12)? - 1, 1),
+        Some(part) if !fiscal && part.len() == 2 => (number(part, 12)? - 1, 1),
+        Some(_) => return Err(invalid()),
+    };
+    let start = month_start(year, first_month + from, tz).ok_or_else(invalid)?;
+    let end = month_start(year, first_month + from + months, tz).ok_or_else(invalid)?;
+    TimeRange::new(start, end)
+}
+
+/// Resolve a week to its Monday: `this week`, `last week`, an ISO week
+/// (`2024-W07`) or any date within it (`2024-03-15`), relative to `now` in
+/// `tz`
+pub fn parse_week(spec: &str, tz: &Timezone, now: DateTime<Utc>) -> Result<NaiveDate, String> {
+    let spec = spec.trim().to_ascii_lowercase().replace('_', " ");
+    let monday =
+        |date: NaiveDate| date - Duration::days(date.weekday().num_days_from_monday() as i64);
+    let today = tz.date_of(now);
+    match spec.as_str() {
+        "this week" => return Ok(monday(today)),
+        "last week" => return Ok(monday(today) - Duration::weeks(1)),
+        _ => {}
+    }
+    if let Ok(date) = spec.parse::<NaiveDate>() {
+        return Ok(monday(date));
+    }
+    let (year, week) = spec.split_once("-w").ok_or_else(|| {
+        format!(
+            "Invalid week '{}'. Expected this week, last week, e.g. 2024-W07 or a date (YYYY-MM-DD)",
+            spec
+        )
+    })?;
+    year.parse()
+        .ok()
+        .zip(week.parse().ok())
+        .and_then(|(year, week)| NaiveDate::from_isoywd_opt(year, week, Weekday::Mon))
+        .ok_or_else(|| format!("Invalid week '{}'", spec))
+}
+
+/// Local midnight on the first of a month, where months past 12 roll over
+/// into the following years
+fn month_start(year: i32, month: u32, tz: &Timezone) -> Option<DateTime<Utc>> {
+    let year = year + ((month - 1) / 12) as i32;
+    let month = (month - 1) % 12 + 1;
+    NaiveDate::from_ymd_opt(year, month, 1).map(|date| tz.start_of_day(date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn bounds(spec: &str, fiscal_year_start: u32) -> (String, String) {
+        let range = parse_period(spec, fiscal_year_start, &Timezone::Utc).unwrap();
+        (
+            range.start.format("%Y-%m-%d").to_string(),
+            range.end.format("%Y-%m-%d").to_string(),
+        )
+    }
+
+    #[test]
+    fn parses_calendar_periods() {
+        assert_eq!(
+            bounds("2024-W07", 1),
+            ("2024-02-12".into(), "2024-02-19".into())
+        );
+        // ISO week 1 of 2021 starts in January, week 53 of 2020 ends there
+        assert_eq!(
+            bounds("2020-w53", 1),
+            ("2020-12-28".into(), "2021-01-04".into())
+        );
+        assert_eq!(
+            bounds("2024-Q4", 1),
+            ("2024-10-01".into(), "2025-01-01".into())
+        );
+        assert_eq!(
+            bounds("2024-02", 1),
+            ("2024-02-01".into(), "2024-03-01".into())
+        );
+        assert_eq!(
+            bounds("2024", 1),
+            ("2024-01-01".into(), "2025-01-01".into())
+        );
+    }
+
+    #[test]
+    fn parses_fiscal_periods() {
+        // Fiscal year starting in April, named after its starting year
+        assert_eq!(
+            bounds("FY2024", 4),
+            ("2024-04-01".into(), "2025-04-01".into())
+        );
+        assert_eq!(
+            bounds("fy2024-q4", 4),
+            ("2025-01-01".into(), "2025-04-01".into())
+        );
+        assert_eq!(
+            bounds("FY2024-M10", 4),
+            ("2025-01-01".into(), "2025-02-01".into())
+        );
+        assert_eq!(bounds("FY2024-Q1", 1), bounds("2024-Q1", 1));
+    }
+
+    #[test]
+    fn rejects_invalid_periods() {
+        for spec in [
+            "2024-W54",
+            "2024-Q5",
+            "2024-13",
+            "FY2024-W01",
+            "24-Q1",
+            "2024-Q1x",
+        ] {
+            assert!(parse_period(spec, 1, &Timezone::Utc).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_weeks() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        let week = |spec: &str| parse_week(spec, &Timezone::Utc, now).map(|d| d.to_string());
+        assert_eq!(week("this week").unwrap(), "2024-03-11");
+        assert_eq!(week("last_week").unwrap(), "2024-03-04");
+        assert_eq!(week("2024-W07").unwrap(), "2024-02-12");
+        assert_eq!(week("2024-03-17").unwrap(), "2024-03-11");
+        assert!(week("next week").is_err());
+        assert!(week("2024-W54").is_err());
+    }
+
+    #[test]
+    fn periods_start_at_local_midnight() {
+        let tz: Timezone = "+09:00".parse().unwrap();
+        let range = parse_period("2024-Q1", 1, &tz).unwrap();
+        assert_eq!(range.start.to_rfc3339(), "2023-12-31T15:00:00+00:00");
+        assert_eq!(range.end.to_rfc3339(), "2024-03-31T15:00:00+00:00");
+    }
+}
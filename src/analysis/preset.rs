@@ -0,0 +1,166 @@
+use super::range::TimeRange;
+use crate::timezone::Timezone;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// Daily working hours in local time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkHours {
+    /// When work starts
+    pub start: NaiveTime,
+
+    /// When work ends (after `start`)
+    pub end: NaiveTime,
+}
+
+impl Default for WorkHours {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::from_hms_opt(9, 0, 0).expect("valid time"),
+            end: NaiveTime::from_hms_opt(17, 0, 0).expect("valid time"),
+        }
+    }
+}
+
+impl FromStr for WorkHours {
+    type Err = String;
+
+    /// Parse `09:00-17:30`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid working hours '{}', expected e.g. 09:00-17:00", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        let hours = Self {
+            start: time(start)?,
+            end: time(end)?,
+        };
+        if hours.end <= hours.start {
+            return Err(invalid());
+        }
+        Ok(hours)
+    }
+}
+
+impl fmt::Display for WorkHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Resolve a sub-day preset such as `this morning`, `work hours yesterday`
+/// or `evening 2024-03-01`, relative to the working hours:
+///
+/// - `morning`: work start to noon
+/// - `afternoon`: noon to work end
+/// - `work hours`: work start to work end
+/// - `evening`: work end to midnight
+/// - `today`, `yesterday`, a date: the whole day
+///
+/// The day is `today` (or `this`) unless `yesterday` or a `YYYY-MM-DD` date
+/// is given. Ranges reaching into the future end now. Returns `None` when
+/// the text is not a preset.
+pub fn parse_preset(
+    spec: &str,
+    work_hours: WorkHours,
+    tz: &Timezone,
+    now: DateTime<Utc>,
+) -> Option<Result<TimeRange, String>> {
+    let spec = spec.trim().to_ascii_lowercase().replace('_', " ");
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).expect("valid time");
+
+    let mut part = None;
+    let mut day = None;
+    let mut rest = words.as_slice();
+    while let Some((word, tail)) = rest.split_first() {
+        match (*word, tail.first()) {
+            ("work", Some(&"hours")) => {
+                part = Some((work_hours.start, Some(work_hours.end)));
+                rest = &tail[1..];
+                continue;
+            }
+            ("morning", _) => part = Some((work_hours.start, Some(noon))),
+            ("afternoon", _) => part = Some((noon, Some(work_hours.end))),
+            ("evening", _) => part = Some((work_hours.end, None)),
+            ("this" | "today", _) => day = Some(0),
+            ("yesterday", _) => day = Some(1),
+            (word, _) => match word.parse::<NaiveDate>() {
+                Ok(date) => day = Some((tz.date_of(now) - date).num_days()),
+                // Also covers period specifiers like "2024-03"
+                Err(_) => return None,
+            },
+        }
+        rest = tail;
+    }
+    if part.is_none() && day.is_none() {
+        return None;
+    }
+
+    let date = tz.date_of(now) - Duration::days(day.unwrap_or(0));
+    let (from, to) = part.unwrap_or((NaiveTime::MIN, None));
+    let start = tz.from_local(date.and_time(from));
+    let end = match to {
+        Some(to) => tz.from_local(date.and_time(to)),
+        None => tz.start_of_day(date + Duration::days(1)),
+    };
+    if start >= now {
+        return Some(Err(format!("'{}' has not started yet", spec)));
+    }
+    Some(TimeRange::new(start, end.min(now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn resolve(spec: &str) -> Option<Result<(String, String), String>> {
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 14, 30, 0).unwrap();
+        parse_preset(spec, "08:30-17:00".parse().unwrap(), &Timezone::Utc, now).map(|r| {
+            r.map(|range| {
+                (
+                    range.start.format("%m-%d %H:%M").to_string(),
+                    range.end.format("%m-%d %H:%M").to_string(),
+                )
+            })
+        })
+    }
+
+    fn range(start: &str, end: &str) -> Option<Result<(String, String), String>> {
+        Some(Ok((start.to_string(), end.to_string())))
+    }
+
+    #[test]
+    fn resolves_presets_relative_to_work_hours() {
+        assert_eq!(resolve("this morning"), range("03-15 08:30", "03-15 12:00"));
+        assert_eq!(
+            resolve("work hours yesterday"),
+            range("03-14 08:30", "03-14 17:00")
+        );
+        assert_eq!(
+            resolve("yesterday evening"),
+            range("03-14 17:00", "03-15 00:00")
+        );
+        assert_eq!(
+            resolve("evening 2024-03-01"),
+            range("03-01 17:00", "03-02 00:00")
+        );
+        assert_eq!(resolve("today"), range("03-15 00:00", "03-15 14:30"));
+        // Clipped to now
+        assert_eq!(resolve("afternoon"), range("03-15 12:00", "03-15 14:30"));
+        assert!(resolve("this evening").unwrap().is_err());
+    }
+
+    #[test]
+    fn ignores_other_specifiers() {
+        assert_eq!(resolve("2024-Q1"), None);
+        assert_eq!(resolve("2024-03"), None);
+        assert!("17:00-09:00".parse::<WorkHours>().is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use super::intervals::{Interval, merge_intervals};
+use super::range::TimeRange;
+use super::series::intervals;
+use crate::models::{Event, Resolution};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Shortest break in AFK data counted as a gap, in seconds
+pub const MIN_GAP_SECS: f64 = 300.0;
+
+/// Score from which a day's data counts as trustworthy
+pub const TRUSTWORTHY_SCORE: u32 = 90;
+
+/// Data quality of one local day
+#[derive(Debug, Clone, Serialize)]
+pub struct DayQuality {
+    /// Start of the day (clipped to the range)
+    pub start: DateTime<Utc>,
+
+    /// End of the day (clipped to the range)
+    pub end: DateTime<Utc>,
+
+    /// Fraction of the day covered by AFK watcher data (0.0 - 1.0)
+    pub coverage: f64,
+
+    /// Breaks in AFK data of at least [`MIN_GAP_SECS`] between the day's
+    /// first and last data
+    pub gaps: usize,
+
+    /// Longest of those breaks in seconds
+    pub longest_gap_secs: f64,
+
+    /// Seconds in which events of the checked bucket overlap each other
+    pub overlap_secs: f64,
+
+    /// 0-100: the share of the tracked span without gaps, reduced by the
+    /// share of overlapping event time; 0 without AFK data
+    pub score: u32,
+}
+
+impl DayQuality {
+    /// Whether the day is good enough to base reports on
+    pub fn is_trustworthy(&self, min_coverage: f64) -> bool {
+        self.score >= TRUSTWORTHY_SCORE && self.coverage >= min_coverage
+    }
+}
+
+/// Score each local day of a range by AFK data coverage, gaps in the AFK
+/// data, and overlaps between the checked bucket's events
+pub fn data_quality(
+    afk_events: &[Event],
+    events: &[Event],
+    range: &TimeRange,
+    tz: &Timezone,
+) -> Vec<DayQuality> {
+    intervals(range, Resolution::Day, tz, usize::MAX)
+        .into_iter()
+        .filter_map(|(start, end)| TimeRange::new(start.max(range.start), end.min(range.end)).ok())
+        .map(|day| day_quality(afk_events, events, &day))
+        .collect()
+}
+
+fn day_quality(afk_events: &[Event], events: &[Event], day: &TimeRange) -> DayQuality {
+    let clipped = |events: &[Event]| -> Vec<Interval> {
+        events
+            .iter()
+            .filter_map(|e| day.clip((e.timestamp, e.end())))
+            .collect()
+    };
+    let secs = |intervals: &[Interval]| -> f64 {
+        intervals
+            .iter()
+            .map(|(start, end)| (*end - *start).num_milliseconds() as f64 / 1000.0)
+            .fold(0.0, |total, secs| total + secs)
+    };
+
+    let covered = merge_intervals(clipped(afk_events));
+    let covered_secs = secs(&covered);
+    let gaps: Vec<f64> = covered
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].1).num_milliseconds() as f64 / 1000.0)
+        .filter(|gap| *gap >= MIN_GAP_SECS)
+        .collect();
+
+    let event_intervals = clipped(events);
+    let event_secs = secs(&event_intervals);
+    let overlap_secs = event_secs - secs(&merge_intervals(event_intervals));
+
+    let score = match (covered.first(), covered.last()) {
+        (Some(first), Some(last)) => {
+            let span_secs = (last.1 - first.0).num_milliseconds() as f64 / 1000.0;
+            let continuity = covered_secs / span_secs;
+            let overlap = if event_secs > 0.0 {
+                overlap_secs / event_secs
+            } else {
+                0.0
+            };
+            (100.0 * continuity * (1.0 - overlap)).round() as u32
+        }
+        _ => 0,
+    };
+
+    DayQuality {
+        start: day.start,
+        end: day.end,
+        coverage: covered_secs / day.duration_secs(),
+        gaps: gaps.len(),
+        longest_gap_secs: gaps.iter().copied().fold(0.0, f64::max),
+        overlap_secs,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+
+    fn event(day: u32, hour: u32, minutes: f64) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap(),
+            minutes * 60.0,
+            serde_json::json!({}),
+        )
+    }
+
+    #[test]
+    fn scores_days_by_gaps_and_overlaps() {
+        let range = TimeRange::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 4, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let afk = [
+            // Day 1: 8 hours without gaps
+            event(1, 9, 480.0),
+            // Day 2: 3 hours with a one-hour gap
+            event(2, 9, 60.0),
+            event(2, 11, 120.0),
+        ];
+        let window = [event(1, 9, 60.0), event(1, 9, 60.0), event(2, 9, 60.0)];
+
+        let days = data_quality(&afk, &window, &range, &Timezone::Utc);
+        assert_eq!(days.len(), 3);
+
+        assert_eq!((days[0].gaps, days[0].overlap_secs), (0, 3600.0));
+        assert!((days[0].coverage - 1.0 / 3.0).abs() < 1e-9);
+        // Half of the window time overlaps
+        assert_eq!(days[0].score, 50);
+
+        assert_eq!((days[1].gaps, days[1].longest_gap_secs), (1, 3600.0));
+        assert_eq!(days[1].score, 75);
+        assert!(!days[1].is_trustworthy(0.0));
+
+        assert_eq!((days[2].coverage, days[2].score), (0.0, 0));
+    }
+}
@@ -0,0 +1,111 @@
+use super::intervals::Interval;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::Serialize;
+
+/// A resolved time range with inclusive start and exclusive end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TimeRange {
+    /// Start of the range
+    pub start: DateTime<Utc>,
+
+    /// End of the range
+    pub end: DateTime<Utc>,
+}
+
+impl TimeRange {
+    /// Create a range, rejecting empty or inverted ranges
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Self, String> {
+        if end <= start {
+            return Err(format!(
+                "End time {} must be after start time {}",
+                end.to_rfc3339(),
+                start.to_rfc3339()
+            ));
+        }
+        Ok(Self { start, end })
+    }
+
+    /// Parse a range from ISO 8601 start and end strings
+    pub fn parse(start: &str, end: &str) -> Result<Self, String> {
+        Self::new(parse_timestamp(start)?, parse_timestamp(end)?)
+    }
+
+    /// Length of the range
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    /// Length of the range in seconds
+    pub fn duration_secs(&self) -> f64 {
+        self.duration().num_milliseconds() as f64 / 1000.0
+    }
+
+    /// Clip an interval to this range, returning `None` if they don't overlap
+    pub fn clip(&self, (start, end): Interval) -> Option<Interval> {
+        let start = start.max(self.start);
+        let end = end.min(self.end);
+        (end > start).then_some((start, end))
+    }
+}
+
+/// Resolve optional range bounds.
+///
+/// Only `start` means "from start until `now`"; only `end` means "from the
+/// beginning of the data (the Unix epoch) until end". Without either bound
+/// there is no range.
+pub fn resolve_open_range(
+    start: Option<&str>,
+    end: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Option<TimeRange>, String> {
+    let range = match (start, end) {
+        (None, None) => return Ok(None),
+        (Some(start), Some(end)) => TimeRange::parse(start, end)?,
+        (Some(start), None) => {
+            let start = parse_timestamp(start)?;
+            if start >= now {
+                return Err(format!(
+                    "Start time {} is in the future; give an end time as well",
+                    start.to_rfc3339()
+                ));
+            }
+            TimeRange::new(start, now)?
+        }
+        (None, Some(end)) => TimeRange::new(DateTime::UNIX_EPOCH, parse_timestamp(end)?)?,
+    };
+    Ok(Some(range))
+}
+
+/// Parse an ISO 8601 / RFC 3339 timestamp
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid timestamp '{}': {}", value, e))
+}
+
+/// Format a timestamp for ActivityWatch API query parameters
+pub fn format_timestamp(value: &DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn resolves_open_ranges() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let start = "2024-01-15T00:00:00Z";
+
+        let range = resolve_open_range(Some(start), None, now).unwrap().unwrap();
+        assert_eq!(range.end, now);
+
+        let range = resolve_open_range(None, Some(start), now).unwrap().unwrap();
+        assert_eq!(range.start, DateTime::UNIX_EPOCH);
+        assert_eq!(range.end.to_rfc3339(), "2024-01-15T00:00:00+00:00");
+
+        assert!(resolve_open_range(None, None, now).unwrap().is_none());
+        assert!(resolve_open_range(Some("2024-02-01T00:00:00Z"), None, now).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use super::browser::{BrowserTotals, TabWeights, browser_totals};
+use super::intervals::Interval;
+use super::range::TimeRange;
+use super::series::{interval_end, interval_label, interval_start};
+use crate::models::{Event, Resolution};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+
+/// A pull request (GitHub) or merge request (GitLab) a URL points at
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct PullRequest {
+    /// Host of the forge (e.g., "github.com" or a self-hosted GitLab)
+    pub host: String,
+
+    /// Repository path (e.g., "owner/repo" or "group/subgroup/project")
+    pub repo: String,
+
+    /// Pull or merge request number
+    pub number: u64,
+
+    /// Whether this is a GitLab merge request rather than a pull request
+    pub merge_request: bool,
+}
+
+impl fmt::Display for PullRequest {
+    /// `owner/repo#12` for pull requests and `group/project!12` for merge
+    /// requests, as the forges write them
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.merge_request { '!' } else { '#' };
+        write!(f, "{}/{}{}{}", self.host, self.repo, sign, self.number)
+    }
+}
+
+/// The pull request a URL belongs to, from the forges' URL layouts:
+/// `/owner/repo/pull/12[/files]` (GitHub, including GitHub Enterprise hosts)
+/// and `/group/project/-/merge_requests/12[/diffs]` (GitLab, any host)
+pub fn pull_request_of(url: &str) -> Option<PullRequest> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split(['?', '#']).next().unwrap_or("");
+    let mut parts = rest.split('/').filter(|part| !part.is_empty());
+    let host = parts.next()?.to_lowercase();
+    let segments: Vec<&str> = parts.collect();
+
+    if let Some(i) = segments.iter().position(|s| *s == "merge_requests")
+        && i >= 3
+        && segments[i - 1] == "-"
+    {
+        let number = segments.get(i + 1)?.parse().ok()?;
+        return Some(PullRequest {
+            host,
+            repo: segments[..i - 1].join("/"),
+            number,
+            merge_request: true,
+        });
+    }
+    match segments.as_slice() {
+        [owner, repo, "pull", number, ..] => Some(PullRequest {
+            host,
+            repo: format!("{}/{}", owner, repo),
+            number: number.parse().ok()?,
+            merge_request: false,
+        }),
+        _ => None,
+    }
+}
+
+/// Review time of one week
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewWeek {
+    /// Start of the week (clipped to the range)
+    pub start: DateTime<Utc>,
+
+    /// End of the week (clipped to the range)
+    pub end: DateTime<Utc>,
+
+    /// Human-readable label (e.g., "Week of 2024-03-11")
+    pub label: String,
+
+    /// Weighted review time and time per pull request
+    #[serde(flatten)]
+    pub totals: BrowserTotals,
+}
+
+/// Weighted browser time on pull requests per week (starting Monday in the
+/// timezone), from tab events and the focus intervals of their browser.
+///
+/// Tab events not on a pull request are ignored; the groups of each week
+/// are pull requests as [`PullRequest`] displays them, longest first.
+pub fn review_weeks(
+    sources: &[(&[Event], Option<&[Interval]>)],
+    weights: &TabWeights,
+    range: &TimeRange,
+    tz: &Timezone,
+) -> Vec<ReviewWeek> {
+    let reviews: Vec<(Vec<Event>, Option<&[Interval]>)> = sources
+        .iter()
+        .map(|(events, focus)| {
+            let events = events
+                .iter()
+                .filter(|event| {
+                    event
+                        .data
+                        .get("url")
+                        .and_then(|v| v.as_str())
+                        .is_some_and(|url| pull_request_of(url).is_some())
+                })
+                .cloned()
+                .collect();
+            (events, *focus)
+        })
+        .collect();
+
+    let mut weeks = Vec::new();
+    let mut start = interval_start(Resolution::Week, range.start, tz);
+    while start < range.end {
+        let end = interval_end(Resolution::Week, start, tz);
+        let Ok(week) = TimeRange::new(start.max(range.start), end.min(range.end)) else {
+            break;
+        };
+        let mut totals = BrowserTotals::default();
+        for (events, focus) in &reviews {
+            totals.add(&browser_totals(
+                events,
+                *focus,
+                weights,
+                "pull_request",
+                &week,
+            ));
+        }
+        weeks.push(ReviewWeek {
+            start: week.start,
+            end: week.end,
+            label: interval_label(Resolution::Week, start, tz),
+            totals,
+        });
+        start = end;
+    }
+    weeks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn recognizes_pull_request_urls() {
+        let pr = pull_request_of("https://github.com/owner/repo/pull/12/files?w=1").unwrap();
+        assert_eq!(pr.to_string(), "github.com/owner/repo#12");
+        let url = "https://gitlab.example.com/group/sub/project/-/merge_requests/7/diffs";
+        let mr = pull_request_of(url).unwrap();
+        assert_eq!(mr.to_string(), "gitlab.example.com/group/sub/project!7");
+        assert!(mr.merge_request);
+        assert_eq!(pull_request_of("https://github.com/owner/repo/pulls"), None);
+        assert_eq!(
+            pull_request_of("https://github.com/owner/repo/issues/12"),
+            None
+        );
+        assert_eq!(
+            pull_request_of("https://github.com/owner/repo/pull/new"),
+            None
+        );
+    }
+
+    #[test]
+    fn totals_review_time_per_week() {
+        let at = |day: u32, hour: u32| Utc.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap();
+        let tab = |day: u32, url: &str| -> Event {
+            test_event(
+                at(day, 9),
+                3600.0,
+                json!({"url": url, "title": "PR", "audible": false}),
+            )
+        };
+        let events = [
+            tab(15, "https://github.com/a/b/pull/1"),
+            tab(16, "https://docs.rs/chrono"),
+            tab(18, "https://github.com/a/b/pull/1#discussion"),
+            tab(19, "https://gitlab.com/g/p/-/merge_requests/2"),
+        ];
+        // The browser was in front for half of Tuesday's review
+        let focus = [(at(19, 9), at(19, 9) + chrono::Duration::minutes(30))];
+        let range = TimeRange::new(at(14, 0), at(21, 0)).unwrap();
+
+        let sources: [(&[Event], Option<&[Interval]>); 2] =
+            [(&events[..3], None), (&events[3..], Some(&focus))];
+        let weeks = review_weeks(&sources, &TabWeights::default(), &range, &Timezone::Utc);
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[0].label, "Week of 2024-03-11");
+        assert_eq!(weeks[0].start, at(14, 0));
+        assert_eq!(weeks[0].totals.weighted_secs, 3600.0);
+        assert_eq!(weeks[1].totals.raw_secs, 7200.0);
+        assert_eq!(weeks[1].totals.weighted_secs, 5400.0);
+        assert_eq!(
+            weeks[1].totals.groups,
+            [
+                ("github.com/a/b#1".to_string(), 3600.0),
+                ("gitlab.com/g/p!2".to_string(), 1800.0)
+            ]
+        );
+    }
+}
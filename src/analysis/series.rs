@@ -0,0 +1,323 @@
+use super::range::TimeRange;
+use crate::constants::{MAX_DAILY_RANGE_DAYS, MAX_HOURLY_RANGE_DAYS, MAX_TIME_SERIES_POINTS};
+use crate::models::{Event, Resolution};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use serde::Serialize;
+
+/// Total event time within one interval
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeriesPoint {
+    /// Start of the interval (clipped to the range)
+    pub start: DateTime<Utc>,
+
+    /// End of the interval (clipped to the range)
+    pub end: DateTime<Utc>,
+
+    /// Seconds of events within the interval
+    pub secs: f64,
+}
+
+/// Why a time series has its resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionChoice {
+    /// The requested resolution was used
+    Requested,
+    /// No resolution was requested; chosen from the range length
+    Automatic,
+    /// The requested resolution would have produced too many points
+    Downsampled,
+}
+
+/// Choose the resolution for a range: the requested one, coarsened while it
+/// would produce more than the maximum number of points, or hourly up to
+/// two weeks, daily up to half a year and weekly beyond.
+pub fn choose_resolution(
+    range: &TimeRange,
+    requested: Option<Resolution>,
+    tz: &Timezone,
+) -> (Resolution, ResolutionChoice) {
+    let Some(mut resolution) = requested else {
+        let duration = range.duration();
+        let resolution = if duration <= Duration::days(MAX_HOURLY_RANGE_DAYS) {
+            Resolution::Hour
+        } else if duration <= Duration::days(MAX_DAILY_RANGE_DAYS) {
+            Resolution::Day
+        } else {
+            Resolution::Week
+        };
+        return (resolution, ResolutionChoice::Automatic);
+    };
+
+    let mut choice = ResolutionChoice::Requested;
+    while intervals(range, resolution, tz, MAX_TIME_SERIES_POINTS + 1).len()
+        > MAX_TIME_SERIES_POINTS
+        && let Some(coarser) = resolution.coarser()
+    {
+        resolution = coarser;
+        choice = ResolutionChoice::Downsampled;
+    }
+    (resolution, choice)
+}
+
+/// Start of the interval containing `time`: the whole hour, or local
+/// midnight of the day or of the week's Monday
+pub fn interval_start(resolution: Resolution, time: DateTime<Utc>, tz: &Timezone) -> DateTime<Utc> {
+    let local = tz.to_local(time);
+    match resolution {
+        // Offsets are whole hours in practice, so local hours align with UTC hours
+        Resolution::Hour => {
+            time - Duration::seconds((local.minute() * 60 + local.second()) as i64)
+                - Duration::nanoseconds(local.nanosecond() as i64)
+        }
+        Resolution::Day => tz.start_of_day(local.date()),
+        Resolution::Week => tz.start_of_day(
+            local.date() - Duration::days(local.weekday().num_days_from_monday() as i64),
+        ),
+    }
+}
+
+/// End of the interval starting at `start`; local days are 23 or 25 hours
+/// long when daylight-saving time begins or ends
+pub fn interval_end(resolution: Resolution, start: DateTime<Utc>, tz: &Timezone) -> DateTime<Utc> {
+    let date = tz.date_of(start);
+    match resolution {
+        Resolution::Hour => start + Duration::hours(1),
+        Resolution::Day => tz.start_of_day(date + Duration::days(1)),
+        Resolution::Week => tz.start_of_day(date + Duration::weeks(1)),
+    }
+}
+
+/// Human-readable label of the interval starting at `start`
+pub fn interval_label(resolution: Resolution, start: DateTime<Utc>, tz: &Timezone) -> String {
+    let local = tz.to_local(start);
+    match resolution {
+        Resolution::Hour => local.format("%Y-%m-%d %H:00").to_string(),
+        Resolution::Day => local.format("%Y-%m-%d (%a)").to_string(),
+        Resolution::Week => local.format("Week of %Y-%m-%d").to_string(),
+    }
+}
+
+/// Interval boundaries covering a range, stopping after `max` intervals
+pub(super) fn intervals(
+    range: &TimeRange,
+    resolution: Resolution,
+    tz: &Timezone,
+    max: usize,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut intervals = Vec::new();
+    let mut start = interval_start(resolution, range.start, tz);
+    while start < range.end && intervals.len() < max {
+        let end = interval_end(resolution, start, tz);
+        intervals.push((start, end));
+        start = end;
+    }
+    intervals
+}
+
+/// Sum event durations per interval, splitting events across interval
+/// boundaries. Intervals are aligned to the resolution (whole hours, local
+/// days, weeks starting Monday) in the timezone and clipped to the range.
+pub fn time_series(
+    events: &[Event],
+    range: &TimeRange,
+    resolution: Resolution,
+    tz: &Timezone,
+) -> Vec<SeriesPoint> {
+    let mut points: Vec<SeriesPoint> = intervals(range, resolution, tz, usize::MAX)
+        .into_iter()
+        .map(|(start, end)| SeriesPoint {
+            start: start.max(range.start),
+            end: end.min(range.end),
+            secs: 0.0,
+        })
+        .collect();
+
+    for event in events {
+        let Some((mut from, to)) = range.clip((event.timestamp, event.end())) else {
+            continue;
+        };
+        let mut index = points.partition_point(|point| point.end <= from);
+        while from < to
+            && let Some(point) = points.get_mut(index)
+        {
+            let until = to.min(point.end);
+            point.secs += (until - from).num_milliseconds() as f64 / 1000.0;
+            from = until;
+            index += 1;
+        }
+    }
+    points
+}
+
+/// Event time in one hour of the day, over all days of a range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourBin {
+    /// Local hour of the day (0-23)
+    pub hour: u32,
+
+    /// Seconds of events within the hour
+    pub secs: f64,
+
+    /// Events starting within the hour
+    pub events: usize,
+}
+
+/// Total event time and count per local hour of the day (24 bins, from
+/// midnight), so that e.g. 9:00-10:00 on every day of the range adds up
+pub fn hourly_histogram(events: &[Event], range: &TimeRange, tz: &Timezone) -> Vec<HourBin> {
+    let mut bins: Vec<HourBin> = (0..24)
+        .map(|hour| HourBin {
+            hour,
+            secs: 0.0,
+            events: 0,
+        })
+        .collect();
+    for point in time_series(events, range, Resolution::Hour, tz) {
+        bins[tz.to_local(point.start).hour() as usize].secs += point.secs;
+    }
+    for event in events {
+        if let Some((start, _)) = range.clip((event.timestamp, event.end())) {
+            bins[tz.to_local(start).hour() as usize].events += 1;
+        }
+    }
+    bins
+}
+
+/// Event time per local weekday and hour of the day over a range: 7 rows
+/// from Monday, of 24 hours from midnight
+pub fn activity_heatmap(events: &[Event], range: &TimeRange, tz: &Timezone) -> Vec<[f64; 24]> {
+    let mut matrix = vec![[0.0; 24]; 7];
+    for point in time_series(events, range, Resolution::Hour, tz) {
+        let local = tz.to_local(point.start);
+        matrix[local.weekday().num_days_from_monday() as usize][local.hour() as usize] +=
+            point.secs;
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, min, 0).unwrap()
+    }
+
+    fn event(start: DateTime<Utc>, secs: f64) -> Event {
+        Event::builder()
+            .timestamp(start)
+            .duration_secs(secs)
+            .data("app", "a")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn splits_events_across_intervals() {
+        let range = TimeRange::new(at(1, 9, 30), at(1, 12, 0)).unwrap();
+        let events = vec![event(at(1, 9, 0), 3600.0), event(at(1, 10, 45), 1800.0)];
+
+        let series = time_series(&events, &range, Resolution::Hour, &Timezone::Utc);
+        assert_eq!(series.len(), 3);
+        assert_eq!(series[0].start, at(1, 9, 30));
+        assert_eq!(series[0].secs, 1800.0);
+        assert_eq!(series[1].secs, 900.0);
+        assert_eq!(series[2].secs, 900.0);
+    }
+
+    #[test]
+    fn bins_time_by_hour_of_day() {
+        let range = TimeRange::new(at(1, 0, 0), at(3, 0, 0)).unwrap();
+        let events = vec![
+            event(at(1, 9, 30), 3600.0),
+            event(at(2, 9, 0), 1800.0),
+            event(at(2, 23, 30), 3600.0),
+        ];
+
+        let bins = hourly_histogram(&events, &range, &Timezone::Utc);
+        assert_eq!(bins.len(), 24);
+        assert_eq!((bins[9].secs, bins[9].events), (3600.0, 2));
+        assert_eq!((bins[10].secs, bins[10].events), (1800.0, 0));
+        // Clipped at the end of the range
+        assert_eq!((bins[23].secs, bins[0].secs), (1800.0, 0.0));
+    }
+
+    #[test]
+    fn bins_time_by_weekday_and_hour() {
+        // 2024-01-01 is a Monday
+        let range = TimeRange::new(at(1, 0, 0), at(15, 0, 0)).unwrap();
+        let events = vec![
+            event(at(1, 9, 30), 3600.0),
+            event(at(8, 9, 0), 1800.0),
+            event(at(7, 23, 30), 3600.0),
+        ];
+
+        let matrix = activity_heatmap(&events, &range, &Timezone::Utc);
+        assert_eq!(matrix.len(), 7);
+        assert_eq!((matrix[0][9], matrix[0][10]), (3600.0, 1800.0));
+        // Sunday night runs into Monday
+        assert_eq!((matrix[6][23], matrix[0][0]), (1800.0, 1800.0));
+        assert_eq!(matrix[1].iter().sum::<f64>(), 0.0);
+    }
+
+    #[test]
+    fn downsamples_long_ranges() {
+        let week = TimeRange::new(at(1, 0, 0), at(8, 0, 0)).unwrap();
+        assert_eq!(
+            choose_resolution(&week, None, &Timezone::Utc),
+            (Resolution::Hour, ResolutionChoice::Automatic)
+        );
+
+        let month = TimeRange::new(at(1, 0, 0), at(31, 0, 0)).unwrap();
+        assert_eq!(
+            choose_resolution(&month, None, &Timezone::Utc).0,
+            Resolution::Day
+        );
+        assert_eq!(
+            choose_resolution(&month, Some(Resolution::Hour), &Timezone::Utc),
+            (Resolution::Day, ResolutionChoice::Downsampled)
+        );
+        assert_eq!(
+            choose_resolution(&week, Some(Resolution::Hour), &Timezone::Utc).1,
+            ResolutionChoice::Requested
+        );
+    }
+
+    #[test]
+    fn aligns_weeks_to_monday() {
+        // 2024-01-03 is a Wednesday
+        assert_eq!(
+            interval_start(Resolution::Week, at(3, 15, 0), &Timezone::Utc),
+            at(1, 0, 0)
+        );
+    }
+
+    #[test]
+    fn splits_days_at_local_midnight_across_dst() {
+        let tz: Timezone = "EST5EDT,M3.2.0,M11.1.0".parse().unwrap();
+        let utc = |d, h| Utc.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap();
+        // Local 2024-03-09 00:00 EST to 2024-03-12 00:00 EDT
+        let range = TimeRange::new(utc(9, 5), utc(12, 4)).unwrap();
+        // One hour of activity just before and after local midnight each day
+        let events: Vec<Event> = [utc(10, 4), utc(11, 3)]
+            .into_iter()
+            .map(|start| event(start, 7200.0))
+            .collect();
+
+        let series = time_series(&events, &range, Resolution::Day, &tz);
+        let hours: Vec<f64> = series
+            .iter()
+            .map(|p| (p.end - p.start).num_hours() as f64)
+            .collect();
+        assert_eq!(hours, vec![24.0, 23.0, 24.0]);
+        let secs: Vec<f64> = series.iter().map(|p| p.secs).collect();
+        assert_eq!(secs, vec![3600.0, 7200.0, 3600.0]);
+        assert_eq!(
+            interval_label(Resolution::Day, series[1].start, &tz),
+            "2024-03-10 (Sun)"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use super::intervals::{Interval, merge_intervals};
+use super::range::TimeRange;
+use crate::categories::Regex;
+use crate::models::Event;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Editor watcher (e.g., aw-watcher-vscode) bucket type
+pub const EDITOR_BUCKET_TYPE: &str = "app.editor.activity";
+
+/// Event data keys ticket IDs are extracted from: window and tab titles,
+/// URLs, and the file, project and branch of editor watchers
+pub const TICKET_KEYS: &[&str] = &["title", "url", "file", "project", "branch"];
+
+/// Time spent on one ticket
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TicketTotal {
+    /// The extracted ticket ID (e.g., "PROJ-123")
+    pub ticket: String,
+
+    /// Seconds during which any bucket showed the ticket, counted once
+    pub secs: f64,
+
+    /// Buckets the ticket was seen in
+    pub buckets: Vec<String>,
+}
+
+/// The ticket IDs the patterns extract from an event's data
+pub fn extract_tickets(event: &Event, patterns: &[Regex]) -> BTreeSet<String> {
+    TICKET_KEYS
+        .iter()
+        .filter_map(|key| event.data.get(*key).and_then(|v| v.as_str()))
+        .flat_map(|text| {
+            patterns
+                .iter()
+                .flat_map(move |pattern| pattern.find_all(text))
+        })
+        .collect()
+}
+
+/// Time per ticket across buckets (bucket ID and events), longest first.
+///
+/// Time where several buckets show the same ticket (e.g., the editor window
+/// and its editor watcher) is counted once per ticket; an event naming two
+/// tickets counts for both.
+pub fn ticket_totals(
+    sources: &[(String, Vec<Event>)],
+    patterns: &[Regex],
+    range: &TimeRange,
+) -> Vec<TicketTotal> {
+    let mut tickets: BTreeMap<String, (Vec<Interval>, BTreeSet<String>)> = BTreeMap::new();
+    for (bucket_id, events) in sources {
+        for event in events {
+            let Some(interval) = range.clip((event.timestamp, event.end())) else {
+                continue;
+            };
+            for ticket in extract_tickets(event, patterns) {
+                let entry = tickets.entry(ticket).or_default();
+                entry.0.push(interval);
+                entry.1.insert(bucket_id.clone());
+            }
+        }
+    }
+
+    let mut totals: Vec<TicketTotal> = tickets
+        .into_iter()
+        .map(|(ticket, (intervals, buckets))| TicketTotal {
+            ticket,
+            secs: merge_intervals(intervals)
+                .iter()
+                .map(|(start, end)| (*end - *start).num_milliseconds() as f64 / 1000.0)
+                .sum(),
+            buckets: buckets.into_iter().collect(),
+        })
+        .collect();
+    totals.sort_by(|a, b| {
+        b.secs
+            .total_cmp(&a.secs)
+            .then_with(|| a.ticket.cmp(&b.ticket))
+    });
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn event(minute: u32, minutes: f64, data: serde_json::Value) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, minute, 0).unwrap(),
+            minutes * 60.0,
+            data,
+        )
+    }
+
+    #[test]
+    fn rolls_up_time_per_ticket_across_buckets() {
+        let patterns = [Regex::new(r"\b[A-Z][A-Z0-9]+-\d+\b", false).unwrap()];
+        let range = TimeRange::new(
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 15, 10, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let sources = [
+            (
+                "window".to_string(),
+                vec![
+                    event(0, 20.0, json!({"app": "Code", "title": "PROJ-1 parser.rs"})),
+                    event(
+                        20,
+                        10.0,
+                        json!({"app": "Slack", "title": "PROJ-1 and OPS-2"}),
+                    ),
+                ],
+            ),
+            (
+                "web".to_string(),
+                vec![event(
+                    10,
+                    30.0,
+                    json!({"url": "https://jira/browse/PROJ-1", "title": "x"}),
+                )],
+            ),
+        ];
+
+        let totals = ticket_totals(&sources, &patterns, &range);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].ticket, "PROJ-1");
+        assert_eq!(totals[0].secs, 2400.0);
+        assert_eq!(totals[0].buckets, ["web", "window"]);
+        assert_eq!(
+            (totals[1].ticket.as_str(), totals[1].secs),
+            ("OPS-2", 600.0)
+        );
+    }
+}
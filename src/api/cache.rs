@@ -0,0 +1,335 @@
+use crate::constants::QUERY_CACHE_MAX_ENTRIES;
+use crate::models::{Bucket, QueryValue};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// Shared bucket map
+pub type BucketMap = Arc<HashMap<String, Bucket>>;
+
+/// In-memory cache of the bucket map with a time-to-live
+#[derive(Debug, Default)]
+pub(crate) struct BucketCache {
+    entry: RwLock<Option<CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    /// When the buckets were fetched, for the time-to-live
+    fetched_at: Instant,
+    /// When the buckets were fetched, for reporting
+    fetched_time: DateTime<Utc>,
+    buckets: BucketMap,
+}
+
+/// Statistics of the in-memory bucket cache
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketCacheStats {
+    /// Number of cached buckets (0 when nothing is cached)
+    pub entries: usize,
+
+    /// Seconds since the buckets were fetched
+    pub age_secs: Option<f64>,
+
+    /// Approximate memory used by the cached buckets, in bytes
+    pub approx_bytes: usize,
+
+    /// Lookups answered from the cache
+    pub hits: u64,
+
+    /// Lookups that had to fetch from aw-server
+    pub misses: u64,
+}
+
+impl BucketCache {
+    /// The cached buckets and their fetch time, if present and younger
+    /// than `ttl`
+    pub async fn get(&self, ttl: Duration) -> Option<(DateTime<Utc>, BucketMap)> {
+        match &*self.entry.read().await {
+            Some(entry) if entry.fetched_at.elapsed() < ttl => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some((entry.fetched_time, entry.buckets.clone()))
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Replace the cached buckets fetched at `now`, returning the previous
+    /// map
+    pub async fn store(
+        &self,
+        buckets: HashMap<String, Bucket>,
+        now: DateTime<Utc>,
+    ) -> (BucketMap, Option<BucketMap>) {
+        let buckets = Arc::new(buckets);
+        let previous = self
+            .entry
+            .write()
+            .await
+            .replace(CacheEntry {
+                fetched_at: Instant::now(),
+                fetched_time: now,
+                buckets: buckets.clone(),
+            })
+            .map(|previous| previous.buckets);
+        (buckets, previous)
+    }
+
+    /// Drop the cached buckets
+    pub async fn invalidate(&self) {
+        self.entry.write().await.take();
+    }
+
+    /// Current statistics
+    pub async fn stats(&self) -> BucketCacheStats {
+        let entry = self.entry.read().await;
+        let (entries, age_secs, approx_bytes) = match &*entry {
+            Some(entry) => (
+                entry.buckets.len(),
+                Some(entry.fetched_at.elapsed().as_secs_f64()),
+                // The serialized size is a reasonable proxy for the heap size
+                serde_json::to_vec(&*entry.buckets)
+                    .map(|b| b.len())
+                    .unwrap_or(0),
+            ),
+            None => (0, None, 0),
+        };
+        BucketCacheStats {
+            entries,
+            age_secs,
+            approx_bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// In-memory cache of query2 results per time period.
+///
+/// Entries are keyed by a hash of the normalized script and the time period.
+/// Only periods that have ended are stored; they are kept until cleared or
+/// evicted (oldest first) once the cache is full.
+#[derive(Debug, Default)]
+pub(crate) struct QueryCache {
+    entries: Mutex<HashMap<(u64, String), QueryCacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug)]
+struct QueryCacheEntry {
+    /// The normalized script, to clear entries by bucket
+    script: Arc<str>,
+    fetched_at: DateTime<Utc>,
+    value: QueryValue,
+}
+
+/// Statistics of the in-memory query cache
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryCacheStats {
+    /// Number of cached period results
+    pub entries: usize,
+
+    /// Distinct scripts with cached results
+    pub scripts: usize,
+
+    /// Periods answered from the cache
+    pub hits: u64,
+
+    /// Periods that had to be computed by aw-server
+    pub misses: u64,
+}
+
+impl QueryCache {
+    /// The cached result of a script for a time period, with its fetch time
+    pub async fn get(&self, script: &str, timeperiod: &str) -> Option<(DateTime<Utc>, QueryValue)> {
+        let entries = self.entries.lock().await;
+        match entries.get(&(script_hash(script), timeperiod.to_string())) {
+            Some(entry) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some((entry.fetched_at, entry.value.clone()))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Cache a script's result for a time period, if the period has ended
+    /// by `now`
+    pub async fn store(
+        &self,
+        script: &str,
+        timeperiod: &str,
+        value: &QueryValue,
+        now: DateTime<Utc>,
+    ) {
+        if !period_ended(timeperiod, now) {
+            return;
+        }
+        let mut entries = self.entries.lock().await;
+        if entries.len() >= QUERY_CACHE_MAX_ENTRIES
+            && let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.fetched_at)
+                .map(|(key, _)| key.clone())
+        {
+            entries.remove(&oldest);
+        }
+        // Periods of the same script share one copy of it
+        let shared = entries
+            .values()
+            .find(|entry| &*entry.script == script)
+            .map_or_else(|| Arc::from(script), |entry| entry.script.clone());
+        entries.insert(
+            (script_hash(script), timeperiod.to_string()),
+            QueryCacheEntry {
+                script: shared,
+                fetched_at: now,
+                value: value.clone(),
+            },
+        );
+    }
+
+    /// Drop the entries matching a predicate on the script and time period,
+    /// returning how many were dropped
+    pub async fn clear(&self, matches: impl Fn(&str, &str) -> bool) -> usize {
+        let mut entries = self.entries.lock().await;
+        let before = entries.len();
+        entries.retain(|(_, timeperiod), entry| !matches(&entry.script, timeperiod));
+        before - entries.len()
+    }
+
+    /// Current statistics
+    pub async fn stats(&self) -> QueryCacheStats {
+        let entries = self.entries.lock().await;
+        let mut scripts: Vec<u64> = entries.keys().map(|(hash, _)| *hash).collect();
+        scripts.sort_unstable();
+        scripts.dedup();
+        QueryCacheStats {
+            entries: entries.len(),
+            scripts: scripts.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Normalize a query2 script into its statements, so scripts that differ
+/// only in layout share cache entries.
+///
+/// Whitespace outside string literals is dropped except between two words,
+/// and empty statements are removed.
+pub(crate) fn normalize_script(script: &str) -> Vec<String> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut space = false;
+    for c in script.chars() {
+        if let Some(q) = quote {
+            current.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            ';' => {
+                if !current.is_empty() {
+                    statements.push(std::mem::take(&mut current));
+                }
+                space = false;
+            }
+            _ if c.is_whitespace() => space = true,
+            _ => {
+                if space && current.ends_with(is_word) && is_word(c) {
+                    current.push(' ');
+                }
+                space = false;
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+                current.push(c);
+            }
+        }
+    }
+    if !current.is_empty() {
+        statements.push(current);
+    }
+    statements
+}
+
+/// Stable key of a normalized script
+fn script_hash(script: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    script.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether a `start/end` time period ended before `now`
+fn period_ended(timeperiod: &str, now: DateTime<Utc>) -> bool {
+    timeperiod
+        .split_once('/')
+        .and_then(|(_, end)| DateTime::parse_from_rfc3339(end).ok())
+        .is_some_and(|end| end <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_script_layout() {
+        let script = r#"
+            events = query_bucket( "aw-watcher-window_host" );
+            events = merge_events_by_keys(events, ["app"]);;
+            RETURN = sort_by_duration(events);
+        "#;
+        let compact = concat!(
+            r#"events=query_bucket("aw-watcher-window_host");"#,
+            r#"events = merge_events_by_keys(events,["app"]); RETURN=sort_by_duration(events)"#
+        );
+        assert_eq!(normalize_script(script), normalize_script(compact));
+        assert_eq!(
+            normalize_script(script)[2],
+            "RETURN=sort_by_duration(events)"
+        );
+        assert_eq!(normalize_script(r#"a = "x ; y";"#), [r#"a="x ; y""#]);
+        assert_eq!(normalize_script("RETURN = not  x"), ["RETURN=not x"]);
+    }
+
+    #[tokio::test]
+    async fn caches_only_ended_periods() {
+        let cache = QueryCache::default();
+        let past = "2024-01-01T00:00:00+00:00/2024-01-02T00:00:00+00:00";
+        // Ended in real time, but not yet at the clock's "now"
+        let open = "2024-01-01T00:00:00+00:00/2024-01-04T00:00:00+00:00";
+        let now = "2024-01-03T00:00:00Z".parse().unwrap();
+        let one = QueryValue::Number(1.0);
+        cache.store("RETURN=1", past, &one, now).await;
+        cache.store("RETURN=1", open, &one, now).await;
+
+        assert!(cache.get("RETURN=1", past).await.is_some());
+        assert!(cache.get("RETURN=1", open).await.is_none());
+        assert!(cache.get("RETURN=2", past).await.is_none());
+        let stats = cache.stats().await;
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 2));
+        assert_eq!(cache.clear(|script, _| script.contains("RETURN")).await, 1);
+    }
+}
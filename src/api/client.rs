@@ -1,55 +1,691 @@
-use crate::models::{Bucket, Event};
-use reqwest::Client;
+use super::cache::{
+    BucketCache, BucketCacheStats, BucketMap, QueryCache, QueryCacheStats, normalize_script,
+};
+use super::error::AwServerError;
+use super::recording::Recording;
+use super::store::{ResponseStore, StoreStats, StoredResponse, Tombstone};
+use super::sync::{
+    SYNC_FILE_KEY, SYNC_MERGE_KEY, SyncDir, SyncMerge, SyncedBucket, filter_events, merge_bucket,
+    union_events,
+};
+use super::version::{ApiVersion, Endpoint, split_api_url};
+use crate::analysis::{BucketSnapshot, TimeRange, parse_timestamp, sanitize_events};
+use crate::clock::{Clock, FixedClock, SystemClock};
+use crate::config::{Config, HttpSettings};
+use crate::constants::{DEFAULT_BUCKET_CACHE_TTL_SECS, QUERY_PARALLELISM};
+use crate::meta::{
+    correlation_id, record_cached, record_event_fixes, record_private_excluded,
+    record_query_fallback, record_stale, record_sync_merge, untracked,
+};
+use crate::models::{
+    AwServerInfo, Bucket, Event, QueryResult, QueryValue, ServerFlavor, parse_server_timestamp,
+};
+use crate::privacy::{DoNotTrack, private_included};
+use crate::query2::{PYTHON_ONLY_FUNCTIONS, QueryData, QueryScript};
+use chrono::{DateTime, Utc};
+use percent_encoding::percent_decode_str;
+use reqwest::{Client, Method, RequestBuilder, Url};
 use rmcp::ErrorData as McpError;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, Semaphore, SemaphorePermit};
 
 /// ActivityWatch API client
 #[derive(Clone)]
 pub struct ActivityWatchClient {
     client: Client,
-    base_url: String,
+    server_url: String,
+    api_version: ApiVersion,
+    flavor: Arc<OnceCell<ServerFlavor>>,
+    bucket_cache: Arc<BucketCache>,
+    bucket_cache_ttl: Duration,
+    query_cache: Arc<QueryCache>,
+    store: Option<ResponseStore>,
+    sync: Option<Arc<SyncDir>>,
+    sync_merge: SyncMerge,
+    do_not_track: DoNotTrack,
+    limiter: Option<Arc<Semaphore>>,
+    recording: Option<Recording>,
+    clock: Arc<dyn Clock>,
+}
+
+/// Where a bucket's data is served from
+enum Source {
+    /// The live server
+    Live,
+    /// The sync directory
+    Sync(SyncedBucket),
+    /// Both, merged
+    Union(SyncedBucket),
+}
+
+/// Outcome of forcing a bucket re-discovery
+#[derive(Debug, Clone)]
+pub struct BucketRefresh {
+    /// The freshly fetched buckets
+    pub buckets: BucketMap,
+
+    /// IDs of buckets that were not in the previous cache
+    pub added: Vec<String>,
+
+    /// IDs of cached buckets that no longer exist
+    pub removed: Vec<String>,
+}
+
+/// Statistics of the client's caches
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    /// The in-memory bucket cache
+    pub buckets: BucketCacheStats,
+
+    /// The in-memory query2 result cache
+    pub queries: QueryCacheStats,
+
+    /// The offline response cache, if enabled
+    pub offline: Option<StoreStats>,
+}
+
+/// Outcome of clearing cached data
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheClear {
+    /// Whether the cached bucket list was dropped
+    pub bucket_cache_cleared: bool,
+
+    /// Number of cached query2 period results removed
+    pub queries_removed: usize,
+
+    /// Number of stored offline responses removed
+    pub offline_removed: usize,
 }
 
 impl ActivityWatchClient {
-    /// Create a new ActivityWatch API client
+    /// Create a new ActivityWatch API client.
+    ///
+    /// `base_url` may be the server root (`http://localhost:5600`) or include
+    /// an API prefix (`http://localhost:5600/api/0`). Without a prefix, API v0
+    /// is used until `detect_api_version` is called.
     pub fn new(base_url: &str) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to build HTTP client");
+        let (server_url, api_version) = split_api_url(base_url);
 
         Self {
-            client,
-            base_url: base_url.trim_end_matches('/').to_string(),
+            client: build_http_client(&HttpSettings::default()),
+            server_url,
+            api_version: api_version.unwrap_or(ApiVersion::V0),
+            flavor: Arc::new(OnceCell::new()),
+            bucket_cache: Arc::new(BucketCache::default()),
+            bucket_cache_ttl: Duration::from_secs(DEFAULT_BUCKET_CACHE_TTL_SECS),
+            query_cache: Arc::new(QueryCache::default()),
+            store: None,
+            sync: None,
+            sync_merge: SyncMerge::default(),
+            do_not_track: DoNotTrack::default(),
+            limiter: None,
+            recording: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
-    /// Get all buckets
-    pub async fn get_buckets(&self) -> Result<HashMap<String, Bucket>, McpError> {
-        let response = self
-            .client
-            .get(format!("{}/buckets/", self.base_url))
-            .send()
+    /// Create a client from configuration, detecting the API version when it
+    /// is neither configured nor part of the URL
+    pub async fn from_config(config: &Config) -> Self {
+        let mut client = Self::new(&config.base_url)
+            .with_http_settings(&config.http)
+            .with_bucket_cache_ttl(config.bucket_cache_ttl);
+        if config.max_concurrent_requests > 0 {
+            client = client.with_concurrency_limit(config.max_concurrent_requests);
+        }
+        if let Some(ref dir) = config.cache_dir {
+            client = client.with_offline_cache(dir);
+        }
+        if let Some(ref dir) = config.sync_dir {
+            client = client.with_sync_dir(dir).with_sync_merge(config.sync_merge);
+        }
+        if let Some(ref recording) = config.recording {
+            client = client.with_recording(recording.clone());
+        }
+        if let Some(now) = config.now {
+            client = client.with_clock(FixedClock(now));
+        }
+        if !config.do_not_track.is_empty() {
+            client = client.with_do_not_track(DoNotTrack::new(
+                config.do_not_track.clone(),
+                config.timezone.clone(),
+            ));
+        }
+
+        if let Some(version) = config.api_version {
+            client.api_version = version;
+        } else if split_api_url(&config.base_url).1.is_none() {
+            match client.detect_api_version().await {
+                Some(version) => client.api_version = version,
+                None => tracing::warn!(
+                    "Could not detect ActivityWatch API version, assuming v{}",
+                    client.api_version
+                ),
+            }
+        }
+
+        match client.flavor().await {
+            ServerFlavor::Unknown => {}
+            flavor => tracing::info!("Connected to {}", flavor),
+        }
+
+        // Warm the bucket cache so the first bucket resolution is free
+        if let Err(e) = client.buckets().await {
+            tracing::warn!("Could not load buckets at startup: {:?}", e);
+        }
+
+        client
+    }
+
+    /// Set how long the cached bucket map stays fresh
+    pub fn with_bucket_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.bucket_cache_ttl = ttl;
+        self
+    }
+
+    /// Persist responses in a directory and serve them while aw-server is
+    /// unreachable
+    pub fn with_offline_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.store = Some(ResponseStore::new(dir));
+        self
+    }
+
+    /// Read bucket exports synced from other devices from a directory, and
+    /// serve its buckets next to those of the live server (which wins when
+    /// both have a bucket)
+    pub fn with_sync_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.sync = Some(Arc::new(SyncDir::new(dir)));
+        self
+    }
+
+    /// Set how buckets both the live server and the sync directory have are
+    /// served
+    pub fn with_sync_merge(mut self, policy: SyncMerge) -> Self {
+        self.sync_merge = policy;
+        self
+    }
+
+    /// Leave do-not-track time out of everything returned, unless the
+    /// current tool call includes it (see [`crate::privacy::including_private`])
+    pub fn with_do_not_track(mut self, do_not_track: DoNotTrack) -> Self {
+        self.do_not_track = do_not_track;
+        self
+    }
+
+    /// Whether do-not-track time is left out of the current call's data
+    fn excludes_private(&self) -> bool {
+        !self.do_not_track.is_empty() && !private_included()
+    }
+
+    /// Cut do-not-track time out of events, recording how many changed
+    fn without_private(&self, events: Vec<Event>) -> Vec<Event> {
+        if !self.excludes_private() {
+            return events;
+        }
+        let (events, changed) = self.do_not_track.exclude(events);
+        if changed > 0 {
+            record_private_excluded(changed);
+        }
+        events
+    }
+
+    /// Refuse a request whose results cannot be filtered when it may cover
+    /// do-not-track time
+    fn check_private(&self, what: &str, overlaps: bool) -> Result<(), McpError> {
+        match self.excludes_private() && overlaps {
+            true => Err(McpError::invalid_params(
+                format!(
+                    "{} may cover do-not-track time (AW_MCP_DO_NOT_TRACK); narrow it, or pass \
+                     include_private: true to include that time",
+                    what
+                ),
+                None,
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// Rebuild the HTTP client with the given pool and protocol settings
+    pub fn with_http_settings(mut self, settings: &HttpSettings) -> Self {
+        self.client = build_http_client(settings);
+        self
+    }
+
+    /// Limit the number of simultaneous upstream requests. Waiting requests
+    /// are served in FIFO order.
+    pub fn with_concurrency_limit(mut self, max_requests: usize) -> Self {
+        self.limiter = Some(Arc::new(Semaphore::new(max_requests)));
+        self
+    }
+
+    /// Record upstream exchanges to, or replay them from, a recording
+    pub fn with_recording(mut self, recording: Recording) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+
+    /// Take the current time from the given clock instead of the system's,
+    /// for timestamps of cached data and which query periods have ended
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        self.with_shared_clock(Arc::new(clock))
+    }
+
+    pub(crate) fn with_shared_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Wait for an upstream request slot, if a concurrency limit is set
+    async fn acquire_slot(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.limiter {
+            // The semaphore is never closed, so acquiring cannot fail
+            Some(limiter) => limiter.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Use a specific API version
+    pub fn with_api_version(mut self, version: ApiVersion) -> Self {
+        self.api_version = version;
+        self
+    }
+
+    /// The API version in use
+    pub fn api_version(&self) -> ApiVersion {
+        self.api_version
+    }
+
+    /// Base URL of the versioned API (e.g., `http://localhost:5600/api/0`)
+    pub fn api_url(&self) -> String {
+        format!("{}/{}", self.server_url, self.api_version.prefix())
+    }
+
+    /// Probe the server for the newest supported API version
+    pub async fn detect_api_version(&self) -> Option<ApiVersion> {
+        for version in ApiVersion::PROBE_ORDER {
+            let probe = self.clone().with_api_version(version);
+            let Ok(url) = probe.url(Endpoint::Info) else {
+                return None;
+            };
+            match self.send(Method::GET, url, None).await {
+                Ok((status, _)) if (200..300).contains(&status) => return Some(version),
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::debug!("API version probe for v{} failed: {}", version, e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// Detect which aw-server implementation is running.
+    ///
+    /// The result is cached after the first successful `/info` request;
+    /// failures return `ServerFlavor::Unknown` and are retried next time.
+    pub async fn flavor(&self) -> ServerFlavor {
+        if let Some(flavor) = self.flavor.get() {
+            return *flavor;
+        }
+        match self.get_info().await {
+            Ok(info) => *self.flavor.get_or_init(|| async { info.flavor() }).await,
+            Err(_) => ServerFlavor::Unknown,
+        }
+    }
+
+    /// The detected server flavor, without triggering detection
+    pub fn cached_flavor(&self) -> ServerFlavor {
+        self.flavor.get().copied().unwrap_or(ServerFlavor::Unknown)
+    }
+
+    /// Get server information
+    pub async fn get_info(&self) -> Result<AwServerInfo, McpError> {
+        self.get_json(self.url(Endpoint::Info)?).await
+    }
+
+    /// GET a URL and parse the JSON response.
+    ///
+    /// With a response store configured, successful responses are persisted
+    /// and served back (marked as stale) while aw-server is unreachable.
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: Url) -> Result<T, McpError> {
+        self.fetch_json(url)
             .await
-            .map_err(handle_api_error)?;
+            .and_then(|(body, _)| parse_value(body))
+    }
+
+    /// GET a URL as for [`Self::get_json`], returning the JSON body and
+    /// whether it came from aw-server rather than the response store
+    async fn fetch_json(&self, url: Url) -> Result<(serde_json::Value, bool), McpError> {
+        let error = match self.send(Method::GET, url.clone(), None).await {
+            Ok((status, text)) => {
+                let body = parse_body(status, &text)?;
+                if let Some(store) = &self.store {
+                    store.save(url.as_str(), &body, self.clock.now()).await;
+                }
+                return Ok((body, true));
+            }
+            Err(e) => e,
+        };
+
+        if (error.is_connect() || error.is_timeout())
+            && let Some(store) = &self.store
+        {
+            let tombstones = store.tombstones().await;
+            let accept = |stored: &StoredResponse| !self.deleted_upstream(stored, &tombstones);
+            if let Some(stored) = store.load(url.as_str(), accept).await {
+                tracing::warn!("ActivityWatch unreachable, serving {} from cache", url);
+                record_stale(stored.fetched_at);
+                return Ok((stored.body, false));
+            }
+        }
+
+        Err(handle_api_error(error))
+    }
+
+    /// Whether a stored response may hold data that a tombstone records as
+    /// deleted upstream after the response was fetched
+    fn deleted_upstream(&self, stored: &StoredResponse, tombstones: &[Tombstone]) -> bool {
+        tombstones.iter().any(|tombstone| {
+            let bucket_url = match &tombstone.bucket_id {
+                Some(id) => match self.url(Endpoint::Bucket(id)) {
+                    Ok(url) => Some(url.to_string()),
+                    Err(_) => return false,
+                },
+                None => None,
+            };
+            stored.fetched_at < tombstone.recorded_at
+                && stored_covers(
+                    stored,
+                    bucket_url.as_deref(),
+                    Some((DateTime::<Utc>::MIN_UTC, tombstone.before)),
+                )
+        })
+    }
+
+    /// Send a request upstream and return the status and body text.
+    ///
+    /// Every upstream request goes through here: it waits for a concurrency
+    /// slot, is tagged with the correlation ID, and is recorded or answered
+    /// from a replay when a recording is configured.
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&serde_json::Value>,
+    ) -> Result<(u16, String), reqwest::Error> {
+        if let Some(response) = self
+            .recording
+            .as_ref()
+            .and_then(|r| r.respond(method.as_str(), &url))
+        {
+            return Ok(response);
+        }
+
+        let _slot = self.acquire_slot().await;
+        let started = Instant::now();
+        let mut request = self.client.request(method.clone(), url.clone());
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let result = async {
+            let response = with_request_id(request).send().await?;
+            let status = response.status().as_u16();
+            Ok((status, response.text().await?))
+        }
+        .await;
+
+        match &result {
+            Ok((status, text)) => {
+                tracing::debug!(
+                    method = method.as_str(),
+                    %url,
+                    status,
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "Upstream request"
+                );
+                if let Some(recording) = &self.recording {
+                    recording.capture(method.as_str(), &url, *status, text);
+                }
+            }
+            Err(e) => {
+                tracing::debug!(method = method.as_str(), %url, error = %e, "Upstream request failed")
+            }
+        }
+        result
+    }
+
+    /// Build the URL of an endpoint for the current API version
+    fn url(&self, endpoint: Endpoint<'_>) -> Result<Url, McpError> {
+        let mut url = Url::parse(&self.api_url()).map_err(|e| {
+            McpError::internal_error(format!("Invalid ActivityWatch URL: {}", e), None)
+        })?;
+        url.path_segments_mut()
+            .map_err(|_| McpError::internal_error("Invalid ActivityWatch URL".to_string(), None))?
+            .pop_if_empty()
+            .extend(self.api_version.segments(&endpoint));
+        Ok(url)
+    }
+
+    /// Get all buckets from the cache, fetching them when the cache is stale
+    pub async fn buckets(&self) -> Result<BucketMap, McpError> {
+        if let Some((fetched_at, buckets)) = self.bucket_cache.get(self.bucket_cache_ttl).await {
+            record_cached(fetched_at);
+            return Ok(buckets);
+        }
+        let buckets = self.get_buckets().await?;
+        Ok(self.bucket_cache.store(buckets, self.clock.now()).await.0)
+    }
+
+    /// Re-fetch all buckets and replace the cache
+    pub async fn refresh_buckets(&self) -> Result<BucketRefresh, McpError> {
+        let fetched = match self.get_buckets().await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                self.bucket_cache.invalidate().await;
+                return Err(e);
+            }
+        };
+        let (buckets, previous) = self.bucket_cache.store(fetched, self.clock.now()).await;
+
+        let (mut added, mut removed) = match previous {
+            Some(previous) => (
+                buckets
+                    .keys()
+                    .filter(|id| !previous.contains_key(*id))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                previous
+                    .keys()
+                    .filter(|id| !buckets.contains_key(*id))
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        added.sort();
+        removed.sort();
+
+        Ok(BucketRefresh {
+            buckets,
+            added,
+            removed,
+        })
+    }
+
+    /// Statistics of the bucket cache and the offline response cache
+    pub async fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            buckets: self.bucket_cache.stats().await,
+            queries: self.query_cache.stats().await,
+            offline: match &self.store {
+                Some(store) => Some(store.stats().await),
+                None => None,
+            },
+        }
+    }
+
+    /// The snapshots of the bucket list kept in the response store, oldest
+    /// first; `None` without a store
+    pub async fn bucket_history(&self) -> Option<Vec<BucketSnapshot>> {
+        match &self.store {
+            Some(store) => Some(store.bucket_history().await),
+            None => None,
+        }
+    }
+
+    /// Clear cached data.
+    ///
+    /// Without arguments everything is cleared. With a bucket, only that
+    /// bucket's stored responses (and the bucket list) are cleared; with a
+    /// range, only stored event responses overlapping it. Cached query
+    /// results are cleared for every bucket, since a script can read buckets
+    /// it does not name (e.g. through `find_bucket`).
+    pub async fn clear_cache(
+        &self,
+        bucket_id: Option<&str>,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<CacheClear, McpError> {
+        let bucket_url = match bucket_id {
+            Some(id) => Some(self.url(Endpoint::Bucket(id))?.to_string()),
+            None => None,
+        };
+        let matches = |stored: &StoredResponse| stored_covers(stored, bucket_url.as_deref(), range);
+
+        let bucket_cache_cleared = range.is_none();
+        if bucket_cache_cleared {
+            self.bucket_cache.invalidate().await;
+        }
+        let queries_removed = self
+            .query_cache
+            .clear(|_, timeperiod| {
+                range.is_none_or(|(start, end)| {
+                    let bound = |t: &str| {
+                        DateTime::parse_from_rfc3339(t)
+                            .ok()
+                            .map(|t| t.with_timezone(&Utc))
+                    };
+                    match timeperiod.split_once('/') {
+                        Some((s, e)) => {
+                            bound(s).is_none_or(|s| s < end) && bound(e).is_none_or(|e| e > start)
+                        }
+                        None => true,
+                    }
+                })
+            })
+            .await;
+        let offline_removed = match &self.store {
+            Some(store) => store.clear(matches).await,
+            None => 0,
+        };
+        Ok(CacheClear {
+            bucket_cache_cleared,
+            queries_removed,
+            offline_removed,
+        })
+    }
+
+    /// Drop cached data before `before` that aw-server no longer has (e.g.,
+    /// after the user pruned old data) and, with an offline cache, record a
+    /// tombstone so responses stored earlier are not served for it while
+    /// aw-server is unreachable
+    pub async fn purge_cache_before(
+        &self,
+        bucket_id: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<CacheClear, McpError> {
+        let cleared = self
+            .clear_cache(bucket_id, Some((DateTime::<Utc>::MIN_UTC, before)))
+            .await?;
+        if let Some(store) = &self.store {
+            store
+                .add_tombstone(Tombstone {
+                    bucket_id: bucket_id.map(str::to_string),
+                    before,
+                    recorded_at: self.clock.now(),
+                })
+                .await;
+        }
+        Ok(cleared)
+    }
 
-        handle_response(response).await
+    /// Get all buckets (uncached), with those of the sync directory; buckets
+    /// both have are merged by the sync merge policy
+    pub async fn get_buckets(&self) -> Result<HashMap<String, Bucket>, McpError> {
+        let (body, live) = self.fetch_json(self.url(Endpoint::Buckets)?).await?;
+        let mut buckets: HashMap<String, Bucket> = parse_value(body)?;
+        // Only what aw-server reports now goes into the history
+        if live && let Some(store) = &self.store {
+            store
+                .record_buckets(buckets.values(), self.clock.now())
+                .await;
+        }
+        if let Some(sync) = &self.sync {
+            for (id, synced) in sync.buckets().await.iter() {
+                let bucket = match buckets.remove(id) {
+                    Some(live) => merge_bucket(live, &synced.bucket, self.sync_merge),
+                    None => synced.bucket.clone(),
+                };
+                buckets.insert(id.clone(), bucket);
+            }
+        }
+        Ok(buckets)
     }
 
     /// Get a specific bucket by ID
     pub async fn get_bucket(&self, bucket_id: &str) -> Result<Bucket, McpError> {
-        let response = self
-            .client
-            .get(format!("{}/buckets/{}", self.base_url, bucket_id))
-            .send()
-            .await
-            .map_err(handle_api_error)?;
+        let source = self.source(bucket_id).await;
+        if let Source::Sync(synced) = source {
+            return Ok(synced.bucket);
+        }
+        let live = self
+            .get_json(self.url(Endpoint::Bucket(bucket_id))?)
+            .await?;
+        Ok(match source {
+            Source::Union(synced) => merge_bucket(live, &synced.bucket, SyncMerge::Union),
+            _ => live,
+        })
+    }
 
-        handle_response(response).await
+    /// Where a bucket is served from: the sync directory when only it has
+    /// the bucket (or the live server cannot be asked), otherwise by the
+    /// sync merge policy
+    async fn source(&self, bucket_id: &str) -> Source {
+        let Some(sync) = &self.sync else {
+            return Source::Live;
+        };
+        let Some(synced) = sync.bucket(bucket_id).await else {
+            return Source::Live;
+        };
+        let Ok(buckets) = untracked(self.buckets()).await else {
+            return Source::Sync(synced);
+        };
+        match buckets.get(bucket_id) {
+            Some(bucket) if bucket.extra.contains_key(SYNC_MERGE_KEY) => {
+                record_sync_merge(bucket_id, self.sync_merge);
+                match self.sync_merge {
+                    SyncMerge::PreferLive => Source::Live,
+                    SyncMerge::PreferSync => Source::Sync(synced),
+                    SyncMerge::Union => Source::Union(synced),
+                }
+            }
+            Some(bucket) if !bucket.extra.contains_key(SYNC_FILE_KEY) => Source::Live,
+            _ => Source::Sync(synced),
+        }
     }
 
-    /// Get events from a bucket
+    /// Get events from a bucket, newest first.
+    ///
+    /// Out-of-order and duplicate events are fixed before they are returned
+    /// (see [`sanitize_events`]) and counted in the response metadata. Buckets
+    /// of the sync directory are served from it, by the sync merge policy
+    /// when the live server has them too.
     pub async fn get_events(
         &self,
         bucket_id: &str,
@@ -57,31 +693,101 @@ impl ActivityWatchClient {
         start: Option<&str>,
         end: Option<&str>,
     ) -> Result<Vec<Event>, McpError> {
-        let mut url = format!("{}/buckets/{}/events", self.base_url, bucket_id);
+        let bound = |time: Option<&str>| time.and_then(parse_server_timestamp);
+        let events = match self.source(bucket_id).await {
+            Source::Live => self.fetch_events(bucket_id, limit, start, end).await?,
+            Source::Sync(synced) => filter_events(&synced.events, limit, bound(start), bound(end)),
+            Source::Union(synced) => {
+                let live = self.fetch_events(bucket_id, None, start, end).await?;
+                let synced = filter_events(&synced.events, None, bound(start), bound(end));
+                union_events(live, synced, limit)
+            }
+        };
+        Ok(self.without_private(events))
+    }
+
+    /// Get events of a bucket from the live server
+    async fn fetch_events(
+        &self,
+        bucket_id: &str,
+        limit: Option<i32>,
+        start: Option<&str>,
+        end: Option<&str>,
+    ) -> Result<Vec<Event>, McpError> {
         let mut params = Vec::new();
 
         if let Some(l) = limit {
-            params.push(format!("limit={}", l));
+            params.push(("limit", l.to_string()));
         }
         if let Some(s) = start {
-            params.push(format!("start={}", s));
+            params.push(("start", s.to_string()));
         }
         if let Some(e) = end {
-            params.push(format!("end={}", e));
+            params.push(("end", e.to_string()));
         }
 
-        if !params.is_empty() {
-            url = format!("{}?{}", url, params.join("&"));
+        let mut events: Vec<Event> = self
+            .get_json(with_query(self.url(Endpoint::Events(bucket_id))?, &params))
+            .await?;
+        let fixes = sanitize_events(&mut events);
+        if fixes.total() > 0 {
+            tracing::warn!(
+                bucket_id,
+                out_of_order = fixes.out_of_order,
+                duplicates = fixes.duplicates,
+                "Fixed events returned by aw-server"
+            );
+            record_event_fixes(fixes.total());
         }
+        Ok(events)
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(handle_api_error)?;
+    /// Get one event of a bucket by its ID.
+    ///
+    /// The Python aw-server answers `null` for an unknown event, which is
+    /// reported as not found, like aw-server-rust's 404. Events reaching
+    /// into do-not-track time are refused.
+    pub async fn get_event(&self, bucket_id: &str, event_id: i64) -> Result<Event, McpError> {
+        let event = self.find_event(bucket_id, event_id).await?;
+        let end = event
+            .end()
+            .max(event.timestamp + chrono::Duration::milliseconds(1));
+        let overlaps = self.do_not_track.overlaps(event.timestamp, end);
+        self.check_private(&format!("Event {}", event_id), overlaps)?;
+        Ok(event)
+    }
 
-        handle_response(response).await
+    /// Get one event of a bucket by its ID, from wherever the bucket is
+    /// served
+    async fn find_event(&self, bucket_id: &str, event_id: i64) -> Result<Event, McpError> {
+        let find = |synced: &SyncedBucket| {
+            synced
+                .events
+                .iter()
+                .find(|event| event.id == Some(event_id))
+                .cloned()
+        };
+        let synced = match self.source(bucket_id).await {
+            Source::Live => None,
+            Source::Sync(synced) => {
+                return find(&synced).ok_or_else(|| no_such_event(bucket_id, event_id));
+            }
+            Source::Union(synced) => find(&synced),
+        };
+        let event_id_text = event_id.to_string();
+        let url = self.url(Endpoint::Event(bucket_id, &event_id_text))?;
+        let live = self.get_json::<Option<Event>>(url).await;
+        if let Ok(Some(event)) = live {
+            return Ok(event);
+        }
+        // Under the union policy, the event may be one only the sync directory has
+        if let Some(event) = synced {
+            return Ok(event);
+        }
+        match live {
+            Err(e) => Err(e),
+            Ok(_) => Err(no_such_event(bucket_id, event_id)),
+        }
     }
 
     /// Get event count for a bucket
@@ -91,65 +797,649 @@ impl ActivityWatchClient {
         start: Option<&str>,
         end: Option<&str>,
     ) -> Result<i64, McpError> {
-        let mut url = format!("{}/buckets/{}/events/count", self.base_url, bucket_id);
+        let bound = |time: Option<&str>| time.and_then(parse_server_timestamp);
+        if self.excludes_private() {
+            let overlaps = match (bound(start), bound(end)) {
+                (Some(start), Some(end)) => self.do_not_track.overlaps(start, end),
+                _ => true,
+            };
+            // Count what is left after do-not-track time is cut out
+            if overlaps {
+                return Ok(self.get_events(bucket_id, None, start, end).await?.len() as i64);
+            }
+        }
+        match self.source(bucket_id).await {
+            Source::Live => {}
+            Source::Sync(synced) => {
+                let events = filter_events(&synced.events, None, bound(start), bound(end));
+                return Ok(events.len() as i64);
+            }
+            Source::Union(synced) => {
+                let live = self.fetch_events(bucket_id, None, start, end).await?;
+                let synced = filter_events(&synced.events, None, bound(start), bound(end));
+                return Ok(union_events(live, synced, None).len() as i64);
+            }
+        }
         let mut params = Vec::new();
 
         if let Some(s) = start {
-            params.push(format!("start={}", s));
+            params.push(("start", s.to_string()));
         }
         if let Some(e) = end {
-            params.push(format!("end={}", e));
+            params.push(("end", e.to_string()));
         }
 
-        if !params.is_empty() {
-            url = format!("{}?{}", url, params.join("&"));
+        self.get_json(with_query(
+            self.url(Endpoint::EventCount(bucket_id))?,
+            &params,
+        ))
+        .await
+    }
+
+    /// Get a server setting; `null` when it is not set
+    pub async fn get_setting(&self, key: &str) -> Result<serde_json::Value, McpError> {
+        match self.get_json(self.url(Endpoint::Setting(key))?).await {
+            // Unset keys are a 404 on aw-server-rust and `null` on aw-server
+            Err(e) if e.data.as_ref().and_then(|d| d.get("status")) == Some(&404.into()) => {
+                Ok(serde_json::Value::Null)
+            }
+            Ok(value) if self.flavor().await == ServerFlavor::Rust => Ok(rust_setting(value)),
+            result => result,
         }
+    }
+
+    /// Get all server settings (aw-webui's categories, theme and others) as
+    /// an object keyed by setting
+    pub async fn get_settings(&self) -> Result<serde_json::Value, McpError> {
+        let settings = self.get_json(self.url(Endpoint::Settings)?).await?;
+        Ok(match self.flavor().await {
+            ServerFlavor::Rust => rust_settings(settings),
+            _ => settings,
+        })
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
+    /// Store a server setting, replacing its value
+    pub async fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), McpError> {
+        let (status, text) = self
+            .send(Method::POST, self.url(Endpoint::Setting(key))?, Some(value))
             .await
             .map_err(handle_api_error)?;
+        if !(200..300).contains(&status) {
+            return Err(status_error(status, &text));
+        }
+        Ok(())
+    }
 
-        handle_response(response).await
+    /// Create a bucket; aw-server keeps an existing bucket of that ID as it
+    /// is
+    pub async fn create_bucket(
+        &self,
+        bucket_id: &str,
+        bucket_type: &str,
+        client: &str,
+        hostname: &str,
+    ) -> Result<(), McpError> {
+        let body = serde_json::json!({
+            "client": client,
+            "type": bucket_type,
+            "hostname": hostname,
+        });
+        let (status, text) = self
+            .send(
+                Method::POST,
+                self.url(Endpoint::Bucket(bucket_id))?,
+                Some(&body),
+            )
+            .await
+            .map_err(handle_api_error)?;
+        // aw-server answers 304 Not Modified when the bucket already exists
+        if !(200..300).contains(&status) && status != 304 {
+            return Err(status_error(status, &text));
+        }
+        self.bucket_cache.invalidate().await;
+        Ok(())
+    }
+
+    /// Delete a bucket with all its events, and everything cached about it.
+    ///
+    /// aw-server only deletes buckets when forced (`force=1`) outside its
+    /// testing mode, so the request is always forced.
+    pub async fn delete_bucket(&self, bucket_id: &str) -> Result<(), McpError> {
+        let mut url = self.url(Endpoint::Bucket(bucket_id))?;
+        url.query_pairs_mut().append_pair("force", "1");
+        let (status, text) = self
+            .send(Method::DELETE, url, None)
+            .await
+            .map_err(handle_api_error)?;
+        if !(200..300).contains(&status) {
+            return Err(status_error(status, &text));
+        }
+        self.clear_cache(Some(bucket_id), None).await?;
+        Ok(())
+    }
+
+    /// Insert events into a bucket as they are (no merging), and return
+    /// those the server echoes back.
+    ///
+    /// aw-server-rust answers with the inserted events, the Python aw-server
+    /// with the single event inserted or `null`, so the result may be empty
+    /// even when every event was stored.
+    pub async fn insert_events(
+        &self,
+        bucket_id: &str,
+        events: &[Event],
+    ) -> Result<Vec<Event>, McpError> {
+        let body = serde_json::to_value(events).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize events: {}", e), None)
+        })?;
+        let (status, text) = self
+            .send(
+                Method::POST,
+                self.url(Endpoint::Events(bucket_id))?,
+                Some(&body),
+            )
+            .await
+            .map_err(handle_api_error)?;
+        let value = match text.trim().is_empty() && (200..300).contains(&status) {
+            true => serde_json::Value::Null,
+            false => parse_body(status, &text)?,
+        };
+        let stored = match value {
+            serde_json::Value::Null => Vec::new(),
+            serde_json::Value::Array(_) => parse_value(value)?,
+            value => vec![parse_value(value)?],
+        };
+        let range = events
+            .iter()
+            .map(|event| (event.timestamp, event.end()))
+            .reduce(|(start, end), (s, e)| (start.min(s), end.max(e)));
+        if let Some(range) = range {
+            self.clear_cache(Some(bucket_id), Some(range)).await?;
+        }
+        Ok(stored)
+    }
+
+    /// Delete one event of a bucket by its ID, and drop what is cached
+    /// about the bucket.
+    ///
+    /// The Python aw-server answers `{"success": false}` rather than 404 for
+    /// an unknown event, which is reported as not found as well.
+    pub async fn delete_event(&self, bucket_id: &str, event_id: i64) -> Result<(), McpError> {
+        let event_id_text = event_id.to_string();
+        let url = self.url(Endpoint::Event(bucket_id, &event_id_text))?;
+        let (status, text) = self
+            .send(Method::DELETE, url, None)
+            .await
+            .map_err(handle_api_error)?;
+        if !(200..300).contains(&status) {
+            return Err(status_error(status, &text));
+        }
+        let body: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+        if body.get("success").and_then(|v| v.as_bool()) == Some(false) {
+            return Err(no_such_event(bucket_id, event_id));
+        }
+        self.clear_cache(Some(bucket_id), None).await?;
+        Ok(())
+    }
+
+    /// Export buckets with all their events in aw-server's export format
+    /// (`{"buckets": {id: {...bucket, "events": [...]}}}`): one bucket, or
+    /// all of them without `bucket_id`.
+    ///
+    /// Exports bypass the offline cache, which would otherwise keep a copy
+    /// of every event. Do-not-track time is cut out of the exported events.
+    pub async fn export(&self, bucket_id: Option<&str>) -> Result<serde_json::Value, McpError> {
+        let url = match bucket_id {
+            Some(id) => self.url(Endpoint::BucketExport(id))?,
+            None => self.url(Endpoint::Export)?,
+        };
+        let (status, text) = self
+            .send(Method::GET, url, None)
+            .await
+            .map_err(handle_api_error)?;
+        let mut export = parse_body(status, &text)?;
+        if self.excludes_private() {
+            let buckets = export.get_mut("buckets").and_then(|b| b.as_object_mut());
+            for bucket in buckets.into_iter().flat_map(|buckets| buckets.values_mut()) {
+                let Some(events) = bucket.get_mut("events") else {
+                    continue;
+                };
+                let parsed: Vec<Event> = parse_value(events.take())?;
+                *events = serde_json::to_value(self.without_private(parsed)).map_err(|e| {
+                    McpError::internal_error(format!("Failed to serialize events: {}", e), None)
+                })?;
+            }
+        }
+        Ok(export)
+    }
+
+    /// Import buckets in aw-server's export format, and drop all cached
+    /// data. aw-server refuses buckets that already exist.
+    pub async fn import(&self, export: &serde_json::Value) -> Result<(), McpError> {
+        let (status, text) = self
+            .send(Method::POST, self.url(Endpoint::Import)?, Some(export))
+            .await
+            .map_err(handle_api_error)?;
+        if !(200..300).contains(&status) {
+            return Err(status_error(status, &text));
+        }
+        self.clear_cache(None, None).await?;
+        Ok(())
+    }
+
+    /// Send a heartbeat to a bucket and return the event it produced.
+    ///
+    /// aw-server merges the heartbeat into the bucket's last event when
+    /// their data is equal and the heartbeat starts within `pulsetime`
+    /// seconds of that event's end; otherwise it becomes a new event.
+    pub async fn heartbeat(
+        &self,
+        bucket_id: &str,
+        event: &Event,
+        pulsetime: f64,
+    ) -> Result<Event, McpError> {
+        let mut url = self.url(Endpoint::Heartbeat(bucket_id))?;
+        url.query_pairs_mut()
+            .append_pair("pulsetime", &pulsetime.to_string());
+        let body = serde_json::to_value(event).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize event: {}", e), None)
+        })?;
+        let (status, text) = self
+            .send(Method::POST, url, Some(&body))
+            .await
+            .map_err(handle_api_error)?;
+        let event = parse_value(parse_body(status, &text)?)?;
+        self.forget_queries().await;
+        Ok(event)
     }
-}
 
-/// Handle API response and convert to result
-async fn handle_response<T: serde::de::DeserializeOwned>(
-    response: reqwest::Response,
-) -> Result<T, McpError> {
-    let status = response.status();
+    /// Drop all cached query results after a write, since any script may
+    /// read the written bucket
+    async fn forget_queries(&self) {
+        self.query_cache.clear(|_, _| true).await;
+    }
+
+    /// Run a query2 script over time periods (`start/end`, RFC 3339).
+    ///
+    /// Results of periods that have ended are cached per normalized script,
+    /// so repeating a query (or re-running it with more periods) only sends
+    /// the periods not computed before.
+    ///
+    /// When aw-server cannot run the script (the query fails, or it is
+    /// aw-server-rust and the script uses a function only the Python server
+    /// has), the script is run locally on raw events if the local
+    /// interpreter supports it; the result and the response metadata say so.
+    ///
+    /// Query results cannot have do-not-track time cut out, so periods
+    /// overlapping it are refused.
+    pub async fn query(
+        &self,
+        timeperiods: &[String],
+        script: &str,
+    ) -> Result<QueryResult, McpError> {
+        for timeperiod in timeperiods {
+            let overlaps = timeperiod
+                .split_once('/')
+                .and_then(|(start, end)| {
+                    Some((parse_server_timestamp(start)?, parse_server_timestamp(end)?))
+                })
+                .is_none_or(|(start, end)| self.do_not_track.overlaps(start, end));
+            self.check_private(&format!("Time period '{}'", timeperiod), overlaps)?;
+        }
+        let statements = normalize_script(script);
+        let key = statements.join(";");
+
+        let mut values: Vec<Option<QueryValue>> = Vec::with_capacity(timeperiods.len());
+        for timeperiod in timeperiods {
+            let cached = self.query_cache.get(&key, timeperiod).await;
+            if let Some((fetched_at, _)) = &cached {
+                record_cached(*fetched_at);
+            }
+            values.push(cached.map(|(_, value)| value));
+        }
+
+        let missing: Vec<String> = timeperiods
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(timeperiod, _)| timeperiod.clone())
+            .collect();
+        let mut computed_locally = false;
+        if !missing.is_empty() {
+            let local = QueryScript::parse(script)
+                .ok()
+                .filter(|local| local.unsupported_functions().is_empty());
+            let python_only = match (&local, self.cached_flavor()) {
+                (Some(local), ServerFlavor::Rust) => PYTHON_ONLY_FUNCTIONS
+                    .iter()
+                    .find(|function| local.calls_function(function)),
+                _ => None,
+            };
+
+            let computed = match (&local, python_only) {
+                (Some(local), Some(function)) => {
+                    let reason = format!("aw-server-rust does not implement {}", function);
+                    computed_locally = true;
+                    self.query_locally(local, &missing, &reason).await?
+                }
+                _ => {
+                    let lines: Vec<String> = statements.iter().map(|s| format!("{};", s)).collect();
+                    match (self.query_remote(&missing, &lines).await, &local) {
+                        (Ok(computed), _) => computed,
+                        (Err(e), Some(local)) => {
+                            let reason = format!("aw-server could not run it ({})", e.message);
+                            computed_locally = true;
+                            self.query_locally(local, &missing, &reason)
+                                .await
+                                .map_err(|_| e)?
+                        }
+                        (Err(e), None) => return Err(e),
+                    }
+                }
+            };
+
+            let mut computed = missing.iter().zip(computed);
+            for value in values.iter_mut().filter(|value| value.is_none()) {
+                if let Some((timeperiod, result)) = computed.next() {
+                    self.query_cache
+                        .store(&key, timeperiod, &result, self.clock.now())
+                        .await;
+                    *value = Some(result);
+                }
+            }
+        }
+
+        let mut result =
+            QueryResult::from_response(timeperiods, values.into_iter().flatten().collect())
+                .map_err(|e| McpError::internal_error(e, None))?;
+        result.computed_locally = computed_locally;
+        Ok(result)
+    }
+
+    /// Have aw-server run query lines over time periods.
+    ///
+    /// aw-server computes the periods of one request one after another, so
+    /// they are split into batches that run concurrently.
+    async fn query_remote(
+        &self,
+        timeperiods: &[String],
+        lines: &[String],
+    ) -> Result<Vec<QueryValue>, McpError> {
+        let batch_size = timeperiods.len().div_ceil(QUERY_PARALLELISM);
+        let batches: Vec<&[String]> = timeperiods.chunks(batch_size).collect();
+        let results = join_all(batches.iter().map(|batch| self.query_batch(batch, lines))).await;
+
+        let mut values = Vec::with_capacity(timeperiods.len());
+        for result in results {
+            values.extend(result?);
+        }
+        Ok(values)
+    }
+
+    /// Run a script locally over time periods, on the raw events of the
+    /// buckets it reads
+    async fn query_locally(
+        &self,
+        script: &QueryScript,
+        timeperiods: &[String],
+        reason: &str,
+    ) -> Result<Vec<QueryValue>, McpError> {
+        tracing::warn!("Running query locally: {}", reason);
+        let buckets = untracked(self.buckets()).await?;
+        let ids = script.referenced_buckets(&buckets);
+
+        let mut values = Vec::with_capacity(timeperiods.len());
+        for timeperiod in timeperiods {
+            let invalid =
+                || McpError::invalid_params(format!("Invalid time period '{}'", timeperiod), None);
+            let (start, end) = timeperiod.split_once('/').ok_or_else(invalid)?;
+            let range = TimeRange::new(
+                parse_timestamp(start).map_err(|_| invalid())?,
+                parse_timestamp(end).map_err(|_| invalid())?,
+            )
+            .map_err(|_| invalid())?;
 
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return Err(match status.as_u16() {
-            404 => McpError::invalid_params(
+            let mut events = HashMap::new();
+            for id in &ids {
+                events.insert(
+                    id.clone(),
+                    self.get_events(id, None, Some(start), Some(end)).await?,
+                );
+            }
+            let data = QueryData {
+                range,
+                buckets: &buckets,
+                events: &events,
+            };
+            let value = script
+                .run(&data)
+                .map_err(|e| McpError::invalid_params(format!("Query failed: {}", e), None))?;
+            values.push(parse_value(value)?);
+        }
+        record_query_fallback(reason);
+        Ok(values)
+    }
+
+    /// Run query lines over one batch of time periods
+    async fn query_batch(
+        &self,
+        timeperiods: &[String],
+        lines: &[String],
+    ) -> Result<Vec<QueryValue>, McpError> {
+        let body = serde_json::json!({ "timeperiods": timeperiods, "query": lines });
+        let (status, text) = self
+            .send(Method::POST, self.url(Endpoint::Query)?, Some(&body))
+            .await
+            .map_err(handle_api_error)?;
+        let values: Vec<QueryValue> = parse_value(parse_body(status, &text)?)?;
+        if values.len() != timeperiods.len() {
+            return Err(McpError::internal_error(
                 format!(
-                    "Resource not found. Please check the bucket ID. Details: {}",
-                    body
+                    "Query returned {} results for {} time periods",
+                    values.len(),
+                    timeperiods.len()
                 ),
                 None,
-            ),
-            400 => McpError::invalid_params(
-                format!("Bad request. Please check your parameters. Details: {}", body),
-                None,
-            ),
-            500 => McpError::internal_error(
-                format!("ActivityWatch server error: {}", body),
-                None,
-            ),
-            _ => McpError::internal_error(
-                format!("API request failed with status {}: {}", status, body),
-                None,
-            ),
+            ));
+        }
+        Ok(values)
+    }
+
+    /// Send an arbitrary request to an API path and return the JSON response.
+    ///
+    /// Empty response bodies become `null`; non-JSON bodies are returned as a
+    /// JSON string. Paths returning events are refused while do-not-track
+    /// time is left out, as their responses cannot be filtered.
+    pub async fn raw_request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, McpError> {
+        // aw-server decodes the path before routing, so classify it decoded
+        let decoded = percent_decode_str(path).decode_utf8_lossy();
+        let returns_events = decoded.split(['/', '\\']).any(|segment| {
+            matches!(
+                segment.to_ascii_lowercase().as_str(),
+                "events" | "export" | "query"
+            )
         });
+        self.check_private(&format!("API path '{}'", path), returns_events)?;
+        let mut url = Url::parse(&format!(
+            "{}/{}",
+            self.api_url(),
+            path.trim_start_matches('/')
+        ))
+        .map_err(|e| McpError::invalid_params(format!("Invalid API path: {}", e), None))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        let method_writes = !matches!(method, Method::GET | Method::HEAD | Method::OPTIONS);
+        let (status, text) = self
+            .send(method, url, body)
+            .await
+            .map_err(handle_api_error)?;
+        if !(200..300).contains(&status) {
+            return Err(status_error(status, &text));
+        }
+        if method_writes {
+            self.forget_queries().await;
+        }
+
+        if text.trim().is_empty() {
+            Ok(serde_json::Value::Null)
+        } else {
+            Ok(serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text)))
+        }
     }
+}
+
+/// Whether a stored response falls under a bucket URL prefix (any bucket
+/// when `None`) and, with a range, is an event or count response overlapping
+/// it. Unparseable entries always match.
+fn stored_covers(
+    stored: &StoredResponse,
+    bucket_url: Option<&str>,
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+) -> bool {
+    let Ok(url) = Url::parse(&stored.url) else {
+        return true;
+    };
+    let in_bucket = bucket_url.is_none_or(|prefix| {
+        stored
+            .url
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || rest.starts_with('?'))
+    });
+    let in_range = range.is_none_or(|(start, end)| {
+        let bound = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, v)| DateTime::parse_from_rfc3339(&v).ok())
+                .map(|t| t.with_timezone(&Utc))
+        };
+        (url.path().ends_with("/events") || url.path().ends_with("/events/count"))
+            && bound("start").is_none_or(|s| s < end)
+            && bound("end").is_none_or(|e| e > start)
+    });
+    in_bucket && in_range
+}
 
-    response.json().await.map_err(|e| {
-        McpError::internal_error(format!("Failed to parse API response: {}", e), None)
+/// Run futures concurrently on the current task and collect their outputs
+/// in order.
+///
+/// Polling them in place (rather than spawning tasks) keeps the tool call's
+/// task-local metadata, such as the correlation ID, visible to each request.
+async fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    let mut pending: Vec<Option<Pin<Box<F>>>> = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect();
+    let mut outputs: Vec<Option<F::Output>> = pending.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut done = true;
+        for (slot, output) in pending.iter_mut().zip(outputs.iter_mut()) {
+            if let Some(future) = slot {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => {
+                        *output = Some(value);
+                        *slot = None;
+                    }
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        if done { Poll::Ready(()) } else { Poll::Pending }
     })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+/// Build the underlying HTTP client
+fn build_http_client(settings: &HttpSettings) -> Client {
+    let mut builder = Client::builder()
+        .timeout(settings.timeout)
+        .pool_idle_timeout(settings.pool_idle_timeout)
+        .pool_max_idle_per_host(settings.pool_max_idle_per_host);
+    // Sends Accept-Encoding and decompresses response bodies as they stream in
+    #[cfg(feature = "compression")]
+    {
+        builder = builder
+            .gzip(settings.compression)
+            .deflate(settings.compression);
+    }
+    if !settings.http2 {
+        builder = builder.http1_only();
+    }
+    builder.build().expect("Failed to build HTTP client")
+}
+
+/// Tag a request with the current tool call's correlation ID
+fn with_request_id(request: RequestBuilder) -> RequestBuilder {
+    match correlation_id() {
+        Some(id) => request.header("X-Request-ID", id),
+        None => request,
+    }
+}
+
+/// Append query parameters to a URL
+fn with_query(mut url: Url, params: &[(&str, String)]) -> Url {
+    if !params.is_empty() {
+        url.query_pairs_mut().extend_pairs(params);
+    }
+    url
+}
+
+/// Deserialize a JSON value into a response type
+fn parse_value<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<T, McpError> {
+    serde_json::from_value(value)
+        .map_err(|e| McpError::internal_error(format!("Failed to parse API response: {}", e), None))
+}
+
+/// Check the status of a response and parse its JSON body
+fn parse_body(status: u16, text: &str) -> Result<serde_json::Value, McpError> {
+    if !(200..300).contains(&status) {
+        return Err(status_error(status, text));
+    }
+    serde_json::from_str(text)
+        .map_err(|e| McpError::internal_error(format!("Failed to parse API response: {}", e), None))
+}
+
+/// The error for an event ID a bucket does not have
+fn no_such_event(bucket_id: &str, event_id: i64) -> McpError {
+    let message = format!("There's no event {} in bucket {}", event_id, bucket_id);
+    let body = serde_json::json!({ "type": "NoSuchEvent", "message": message });
+    status_error(404, &body.to_string())
+}
+
+/// Convert a non-success HTTP status to an MCP error
+fn status_error(status: u16, body: &str) -> McpError {
+    if let Some(error) = AwServerError::parse(status, body) {
+        return error.into_mcp_error();
+    }
+    match status {
+        404 => McpError::invalid_params(
+            format!(
+                "Resource not found. Please check the bucket ID. Details: {}",
+                body
+            ),
+            None,
+        ),
+        400 => McpError::invalid_params(
+            format!(
+                "Bad request. Please check your parameters. Details: {}",
+                body
+            ),
+            None,
+        ),
+        500 => McpError::internal_error(format!("ActivityWatch server error: {}", body), None),
+        _ => McpError::internal_error(
+            format!("API request failed with status {}: {}", status, body),
+            None,
+        ),
+    }
 }
 
 /// Convert reqwest errors to MCP errors with clear messages
@@ -165,3 +1455,291 @@ pub fn handle_api_error(error: reqwest::Error) -> McpError {
         McpError::internal_error(format!("Network error: {}", error), None)
     }
 }
+
+/// A setting value as aw-server returns it. aw-server-rust keeps settings
+/// in its key-value store, and some versions return the stored record
+/// (`{"key", "value", "timestamp"}`) or the value still JSON-encoded as a
+/// string.
+fn rust_setting(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        // Records hold the value as stored: always JSON-encoded
+        serde_json::Value::Object(mut record)
+            if record.contains_key("key") && record.contains_key("value") =>
+        {
+            match record.remove("value").unwrap_or_default() {
+                serde_json::Value::String(text) => {
+                    serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text))
+                }
+                value => value,
+            }
+        }
+        // A string holding a JSON object or list is an encoded value; other
+        // strings may be meant as they are
+        serde_json::Value::String(text) => match serde_json::from_str(&text) {
+            Ok(decoded @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => decoded,
+            _ => serde_json::Value::String(text),
+        },
+        value => value,
+    }
+}
+
+/// All settings as an object keyed by setting, from aw-server-rust's
+/// object of values or list of key-value records (keys prefixed with
+/// `settings.`)
+fn rust_settings(settings: serde_json::Value) -> serde_json::Value {
+    match settings {
+        serde_json::Value::Array(records) => records
+            .into_iter()
+            .filter_map(|record| {
+                let key = record.get("key")?.as_str()?;
+                let key = key.strip_prefix("settings.").unwrap_or(key).to_string();
+                Some((key, rust_setting(record)))
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        serde_json::Value::Object(settings) => settings
+            .into_iter()
+            .map(|(key, value)| (key, rust_setting(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_encoded_event_paths_during_do_not_track() {
+        let rules = vec!["00:00-23:59".parse().unwrap()];
+        let client = ActivityWatchClient::new("http://127.0.0.1:9")
+            .with_do_not_track(DoNotTrack::new(rules, crate::timezone::Timezone::Utc));
+        for path in [
+            "/buckets/b/events",
+            "/buckets/b/%65vents",
+            "/buckets/b/%45VENTS",
+            "/buckets/b%2fevents",
+            "/%71uery/",
+        ] {
+            let error = client
+                .raw_request(Method::GET, path, &[], None)
+                .await
+                .unwrap_err();
+            assert!(error.message.contains("AW_MCP_DO_NOT_TRACK"), "{}", path);
+        }
+        let error = client
+            .raw_request(Method::GET, "/buckets/", &[], None)
+            .await
+            .unwrap_err();
+        assert!(!error.message.contains("AW_MCP_DO_NOT_TRACK"));
+    }
+
+    #[test]
+    fn builds_versioned_endpoint_urls() {
+        let client = ActivityWatchClient::new("http://localhost:5600/");
+        assert_eq!(
+            client.url(Endpoint::Buckets).unwrap().as_str(),
+            "http://localhost:5600/api/0/buckets/"
+        );
+
+        let client = client.with_api_version(ApiVersion(1));
+        assert_eq!(
+            client
+                .url(Endpoint::EventCount("my bucket"))
+                .unwrap()
+                .as_str(),
+            "http://localhost:5600/api/1/buckets/my%20bucket/events/count"
+        );
+    }
+
+    #[test]
+    fn unwraps_rust_server_settings() {
+        let classes = serde_json::json!([{"name": ["Work"], "rule": {"type": "none"}}]);
+        assert_eq!(rust_setting(classes.to_string().into()), classes);
+        assert_eq!(
+            rust_setting(serde_json::json!({"key": "settings.theme", "value": "\"dark\""})),
+            serde_json::json!("dark")
+        );
+        assert_eq!(rust_setting("dark".into()), serde_json::json!("dark"));
+
+        let records = serde_json::json!([
+            {"key": "settings.classes", "value": classes.to_string(), "timestamp": null},
+            {"key": "settings.theme", "value": "dark", "timestamp": null},
+        ]);
+        assert_eq!(
+            rust_settings(records),
+            serde_json::json!({"classes": classes, "theme": "dark"})
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_stale_responses_while_unreachable() {
+        let dir = std::env::temp_dir().join(format!("aw-mcp-store-test-{}", std::process::id()));
+        // Nothing listens on port 9, so the connection is refused
+        let client = ActivityWatchClient::new("http://127.0.0.1:9").with_offline_cache(&dir);
+        let store = ResponseStore::new(&dir);
+        let url = client.url(Endpoint::Buckets).unwrap();
+        store
+            .save(
+                url.as_str(),
+                &serde_json::json!({"b": {"id": "b"}}),
+                Utc::now(),
+            )
+            .await;
+
+        let (buckets, meta) =
+            crate::meta::ResponseMeta::collect("test".to_string(), client.get_buckets()).await;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(buckets.unwrap().contains_key("b"));
+        assert!(meta.stale_since.is_some());
+    }
+
+    #[tokio::test]
+    async fn records_concurrent_bucket_snapshots() {
+        let dir = std::env::temp_dir().join(format!("aw-mcp-history-test-{}", std::process::id()));
+        let store = ResponseStore::new(&dir);
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let bucket: Bucket =
+                        serde_json::from_value(serde_json::json!({"id": format!("b{}", i)}))
+                            .unwrap();
+                    store.record_buckets([&bucket], Utc::now()).await;
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let history = store.bucket_history().await;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(history.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn clears_cached_responses_by_bucket_and_range() {
+        let dir = std::env::temp_dir().join(format!("aw-mcp-clear-test-{}", std::process::id()));
+        let client = ActivityWatchClient::new("http://127.0.0.1:9").with_offline_cache(&dir);
+        let store = ResponseStore::new(&dir);
+        let events = |bucket: &str, start: &str, end: &str| {
+            let mut url = client.url(Endpoint::Events(bucket)).unwrap();
+            url.query_pairs_mut()
+                .append_pair("start", start)
+                .append_pair("end", end);
+            url.to_string()
+        };
+        for url in [
+            events("a", "2024-01-15T00:00:00Z", "2024-01-16T00:00:00Z"),
+            events("a", "2024-02-15T00:00:00Z", "2024-02-16T00:00:00Z"),
+            events("ab", "2024-01-15T00:00:00Z", "2024-01-16T00:00:00Z"),
+            client.url(Endpoint::Buckets).unwrap().to_string(),
+        ] {
+            store.save(&url, &serde_json::json!([]), Utc::now()).await;
+        }
+
+        let january = (
+            "2024-01-01T00:00:00Z".parse().unwrap(),
+            "2024-02-01T00:00:00Z".parse().unwrap(),
+        );
+        let in_range = client.clear_cache(Some("a"), Some(january)).await.unwrap();
+        let in_bucket = client.clear_cache(Some("a"), None).await.unwrap();
+        let rest = client.clear_cache(None, None).await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(!in_range.bucket_cache_cleared);
+        assert_eq!(in_range.offline_removed, 1);
+        assert_eq!(in_bucket.offline_removed, 1);
+        assert_eq!(rest.offline_removed, 2);
+    }
+
+    #[tokio::test]
+    async fn skips_stored_responses_of_deleted_history() {
+        let dir = std::env::temp_dir().join(format!("aw-mcp-purge-test-{}", std::process::id()));
+        let client = ActivityWatchClient::new("http://127.0.0.1:9").with_offline_cache(&dir);
+        let store = ResponseStore::new(&dir);
+        let events = |start: &str, end: &str| {
+            let mut url = client.url(Endpoint::Events("a")).unwrap();
+            url.query_pairs_mut()
+                .append_pair("start", start)
+                .append_pair("end", end);
+            url.to_string()
+        };
+        let january = ("2024-01-15T00:00:00Z", "2024-01-16T00:00:00Z");
+        let march = ("2024-03-15T00:00:00Z", "2024-03-16T00:00:00Z");
+        for (start, end) in [january, march] {
+            store
+                .save(&events(start, end), &serde_json::json!([]), Utc::now())
+                .await;
+        }
+        let before = "2024-02-01T00:00:00Z".parse().unwrap();
+        let purged = client.purge_cache_before(Some("a"), before).await.unwrap();
+
+        // A response stored before the deletion was recorded is not served
+        store
+            .save(
+                &events(january.0, january.1),
+                &serde_json::json!([]),
+                Utc::now(),
+            )
+            .await;
+        store
+            .add_tombstone(Tombstone {
+                bucket_id: Some("a".to_string()),
+                before,
+                recorded_at: Utc::now() + chrono::Duration::minutes(1),
+            })
+            .await;
+        let deleted = client
+            .get_events("a", None, Some(january.0), Some(january.1))
+            .await;
+        let kept = client
+            .get_events("a", None, Some(march.0), Some(march.1))
+            .await;
+        let tombstones = store.tombstones().await;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(purged.offline_removed, 1);
+        assert!(deleted.is_err());
+        assert!(kept.is_ok());
+        assert_eq!(tombstones.len(), 1);
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn negotiates_and_decodes_gzip_responses() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let n = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..n]).to_lowercase();
+
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(br#"{"b": {"id": "b"}}"#).unwrap();
+            let body = encoder.finish().unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(&body).await.unwrap();
+            request
+        });
+
+        let client = ActivityWatchClient::new(&format!("http://{}/api/0", addr));
+        let buckets = client.get_buckets().await.unwrap();
+        let request = server.await.unwrap();
+
+        assert!(request.contains("accept-encoding: gzip"));
+        assert!(buckets.contains_key("b"));
+    }
+}
@@ -0,0 +1,69 @@
+use rmcp::ErrorData as McpError;
+use serde::{Deserialize, Serialize};
+
+/// A structured error returned by aw-server.
+///
+/// Both server implementations answer failed requests with a JSON body such
+/// as `{"type": "NoSuchBucket", "message": "There's no bucket named x"}`
+/// (aw-server-rust omits `type`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AwServerError {
+    /// HTTP status code
+    #[serde(default)]
+    pub status: u16,
+
+    /// Error type reported by the server (e.g., "NoSuchBucket")
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+
+    /// The server's own error message
+    pub message: String,
+}
+
+impl AwServerError {
+    /// Parse an error response body; `None` if it carries no message
+    pub fn parse(status: u16, body: &str) -> Option<Self> {
+        let error: AwServerError = serde_json::from_str(body).ok()?;
+        let message = error.message.trim();
+        if message.is_empty() {
+            return None;
+        }
+        Some(Self {
+            status,
+            kind: error.kind.filter(|k| !k.trim().is_empty()),
+            message: message.to_string(),
+        })
+    }
+
+    /// Convert to an MCP error carrying the server message verbatim, with
+    /// the status and type as error data
+    pub fn into_mcp_error(self) -> McpError {
+        let data = serde_json::json!({ "status": self.status, "type": self.kind });
+        match self.status {
+            400 | 404 => McpError::invalid_params(self.message, Some(data)),
+            _ => McpError::internal_error(self.message, Some(data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_structured_error_bodies() {
+        let error = AwServerError::parse(
+            404,
+            r#"{"type": "NoSuchBucket", "message": "There's no bucket named x"}"#,
+        )
+        .unwrap();
+        assert_eq!(error.kind.as_deref(), Some("NoSuchBucket"));
+        assert_eq!(error.into_mcp_error().message, "There's no bucket named x");
+
+        let error = AwServerError::parse(500, r#"{"message": "db locked"}"#).unwrap();
+        assert_eq!(error.kind, None);
+
+        assert!(AwServerError::parse(500, "<html>Internal Server Error</html>").is_none());
+        assert!(AwServerError::parse(400, r#"{"message": ""}"#).is_none());
+    }
+}
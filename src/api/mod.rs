@@ -1,3 +1,15 @@
+mod cache;
 mod client;
+mod error;
+mod recording;
+mod store;
+mod sync;
+mod version;
 
-pub use client::ActivityWatchClient;
+pub use cache::{BucketCacheStats, BucketMap, QueryCacheStats};
+pub use client::{ActivityWatchClient, BucketRefresh, CacheClear, CacheStats};
+pub use error::AwServerError;
+pub use recording::Recording;
+pub use store::{StoreStats, Tombstone};
+pub use sync::SyncMerge;
+pub use version::ApiVersion;
@@ -0,0 +1,402 @@
+use crate::analysis::BucketSnapshot;
+use crate::models::Bucket;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Mutex;
+
+/// A response body persisted to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StoredResponse {
+    /// Request URL the response belongs to
+    pub url: String,
+
+    /// When the response was fetched from aw-server
+    pub fetched_at: DateTime<Utc>,
+
+    /// The JSON response body
+    pub body: serde_json::Value,
+}
+
+/// File in the store directory holding the tombstones
+const TOMBSTONE_FILE: &str = "tombstones.json";
+
+/// File in the store directory holding the snapshots of the bucket list
+const BUCKET_HISTORY_FILE: &str = "bucket_history.json";
+
+/// Most bucket snapshots kept; older ones are dropped
+const MAX_BUCKET_SNAPSHOTS: usize = 500;
+
+/// A record that aw-server's data before a time was deleted (e.g., pruned by
+/// the user), so responses stored before the deletion are not served for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tombstone {
+    /// The bucket whose data was deleted; all buckets when `None`
+    pub bucket_id: Option<String>,
+
+    /// Data before this time was deleted
+    pub before: DateTime<Utc>,
+
+    /// When the deletion was recorded
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Persistent on-disk store of the last successful response per URL, used to
+/// answer queries while aw-server is unreachable
+#[derive(Debug, Clone)]
+pub(crate) struct ResponseStore {
+    dir: PathBuf,
+    /// Fallback lookups that found a stored response
+    hits: Arc<AtomicU64>,
+    /// Fallback lookups that found nothing
+    misses: Arc<AtomicU64>,
+    /// Serializes read-modify-write updates of the tombstone and bucket
+    /// history files, so concurrent updates are not lost
+    updates: Arc<Mutex<()>>,
+}
+
+/// Statistics of the offline response cache
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreStats {
+    /// Cache directory
+    pub dir: PathBuf,
+
+    /// Number of stored responses
+    pub entries: usize,
+
+    /// Total size of the stored responses on disk, in bytes
+    pub disk_bytes: u64,
+
+    /// Fetch time of the oldest stored response
+    pub oldest: Option<DateTime<Utc>>,
+
+    /// Responses served from the cache while aw-server was unreachable
+    pub hits: u64,
+
+    /// Requests that failed because aw-server was unreachable and nothing
+    /// was stored for them
+    pub misses: u64,
+
+    /// Recorded upstream deletions
+    pub tombstones: Vec<Tombstone>,
+}
+
+impl ResponseStore {
+    /// Create a store in the given directory (created on first write)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            hits: Arc::default(),
+            misses: Arc::default(),
+            updates: Arc::default(),
+        }
+    }
+
+    /// Persist a response body; failures are logged and otherwise ignored
+    pub async fn save(&self, url: &str, body: &serde_json::Value, fetched_at: DateTime<Utc>) {
+        let stored = StoredResponse {
+            url: url.to_string(),
+            fetched_at,
+            body: body.clone(),
+        };
+        let result = async {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let bytes = serde_json::to_vec(&stored)?;
+            // Write to a temporary file first so readers never see partial data
+            let path = self.path_for(url);
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, bytes).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to persist response for {}: {}", url, e);
+        }
+    }
+
+    /// Load the last stored response for a URL, if `accept` takes it
+    pub async fn load(
+        &self,
+        url: &str,
+        accept: impl Fn(&StoredResponse) -> bool,
+    ) -> Option<StoredResponse> {
+        let stored = tokio::fs::read(self.path_for(url))
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<StoredResponse>(&bytes).ok())
+            .filter(|stored| stored.url == url && accept(stored));
+        let counter = if stored.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        stored
+    }
+
+    /// Delete the stored responses accepted by `filter`, returning how many
+    /// were removed; unreadable entries are removed too
+    pub async fn clear(&self, filter: impl Fn(&StoredResponse) -> bool) -> usize {
+        let mut removed = 0;
+        for (path, _, stored) in self.entries().await {
+            if stored.as_ref().is_none_or(&filter) && tokio::fs::remove_file(&path).await.is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Current statistics
+    pub async fn stats(&self) -> StoreStats {
+        let entries = self.entries().await;
+        StoreStats {
+            dir: self.dir.clone(),
+            entries: entries.len(),
+            disk_bytes: entries.iter().map(|(_, size, _)| size).sum(),
+            oldest: entries
+                .iter()
+                .filter_map(|(_, _, stored)| stored.as_ref().map(|s| s.fetched_at))
+                .min(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            tombstones: self.tombstones().await,
+        }
+    }
+
+    /// The recorded upstream deletions, oldest first
+    pub async fn tombstones(&self) -> Vec<Tombstone> {
+        tokio::fs::read(self.dir.join(TOMBSTONE_FILE))
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record an upstream deletion; a tombstone it supersedes (same bucket,
+    /// earlier or equal cutoff) is replaced. Failures are logged and
+    /// otherwise ignored.
+    pub async fn add_tombstone(&self, tombstone: Tombstone) {
+        let _update = self.updates.lock().await;
+        let mut tombstones = self.tombstones().await;
+        tombstones.retain(|t| t.bucket_id != tombstone.bucket_id || t.before > tombstone.before);
+        tombstones.push(tombstone);
+        if let Err(e) = self.write_file(TOMBSTONE_FILE, &tombstones).await {
+            tracing::warn!("Failed to persist cache tombstone: {}", e);
+        }
+    }
+
+    /// The recorded snapshots of the bucket list, oldest first
+    pub async fn bucket_history(&self) -> Vec<BucketSnapshot> {
+        tokio::fs::read(self.dir.join(BUCKET_HISTORY_FILE))
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record the buckets aw-server has now: a new snapshot when they differ
+    /// from the latest one, otherwise the latest is brought up to date.
+    /// Failures are logged and otherwise ignored.
+    pub async fn record_buckets<'a>(
+        &self,
+        buckets: impl IntoIterator<Item = &'a Bucket>,
+        now: DateTime<Utc>,
+    ) {
+        let snapshot = BucketSnapshot::new(buckets, now);
+        let _update = self.updates.lock().await;
+        let mut history = self.bucket_history().await;
+        match history.last_mut() {
+            Some(latest) if latest.same_buckets(&snapshot) => {
+                latest.checked_at = snapshot.checked_at;
+                latest.buckets = snapshot.buckets;
+            }
+            _ => history.push(snapshot),
+        }
+        let excess = history.len().saturating_sub(MAX_BUCKET_SNAPSHOTS);
+        history.drain(..excess);
+        if let Err(e) = self.write_file(BUCKET_HISTORY_FILE, &history).await {
+            tracing::warn!("Failed to persist bucket history: {}", e);
+        }
+    }
+
+    /// Write a JSON file to the store directory, through a temporary file so
+    /// readers never see partial data
+    async fn write_file(
+        &self,
+        name: &str,
+        value: &impl Serialize,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let bytes = serde_json::to_vec(value)?;
+        let path = self.dir.join(name);
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    /// All stored files with their size and parsed contents
+    async fn entries(&self) -> Vec<(PathBuf, u64, Option<StoredResponse>)> {
+        let mut result = Vec::new();
+        let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+            return result;
+        };
+        while let Ok(Some(entry)) = dir.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json")
+                || path
+                    .file_name()
+                    .is_some_and(|name| name == TOMBSTONE_FILE || name == BUCKET_HISTORY_FILE)
+            {
+                continue;
+            }
+            let size = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+            let stored = tokio::fs::read(&path)
+                .await
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+            result.push((path, size, stored));
+        }
+        result
+    }
+
+    /// File path for a URL
+    fn path_for(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.json", fnv1a(url.as_bytes())))
+    }
+}
+
+/// FNV-1a hash, stable across Rust releases (unlike `DefaultHasher`)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> ResponseStore {
+        ResponseStore::new(std::env::temp_dir().join(format!(
+            "aw-mcp-{}-{}",
+            name,
+            std::process::id()
+        )))
+    }
+
+    #[tokio::test]
+    async fn loads_saved_responses_and_counts_lookups() {
+        let store = temp_store("store-unit");
+        let fetched_at: DateTime<Utc> = "2024-01-15T10:00:00Z".parse().unwrap();
+        store
+            .save("http://aw/a", &serde_json::json!({"a": 1}), fetched_at)
+            .await;
+        store
+            .save("http://aw/b", &serde_json::json!({"b": 2}), Utc::now())
+            .await;
+
+        let loaded = store.load("http://aw/a", |_| true).await;
+        let refused = store.load("http://aw/a", |_| false).await;
+        let missing = store.load("http://aw/c", |_| true).await;
+        let stats = store.stats().await;
+        let removed = store.clear(|stored| stored.url == "http://aw/a").await;
+        let left = store.stats().await.entries;
+        let _ = std::fs::remove_dir_all(&store.dir);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.body, serde_json::json!({"a": 1}));
+        assert_eq!(loaded.fetched_at, fetched_at);
+        assert!(refused.is_none());
+        assert!(missing.is_none());
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.oldest, Some(fetched_at));
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        assert_eq!((removed, left), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn replaces_superseded_tombstones() {
+        let store = temp_store("tombstone-unit");
+        let tombstone = |bucket_id: Option<&str>, before: &str| Tombstone {
+            bucket_id: bucket_id.map(str::to_string),
+            before: before.parse().unwrap(),
+            recorded_at: Utc::now(),
+        };
+        store
+            .add_tombstone(tombstone(Some("a"), "2024-02-01T00:00:00Z"))
+            .await;
+        store
+            .add_tombstone(tombstone(None, "2024-01-01T00:00:00Z"))
+            .await;
+        // A later cutoff of the same bucket replaces the earlier one
+        store
+            .add_tombstone(tombstone(Some("a"), "2024-03-01T00:00:00Z"))
+            .await;
+        // An earlier one is kept next to it
+        store
+            .add_tombstone(tombstone(Some("a"), "2024-01-01T00:00:00Z"))
+            .await;
+        let tombstones = store.tombstones().await;
+        let entries = store.stats().await.entries;
+        let _ = std::fs::remove_dir_all(&store.dir);
+
+        let cutoffs: Vec<_> = tombstones
+            .iter()
+            .map(|t| (t.bucket_id.as_deref(), t.before.to_rfc3339()))
+            .collect();
+        assert_eq!(
+            cutoffs,
+            [
+                (None, "2024-01-01T00:00:00+00:00".to_string()),
+                (Some("a"), "2024-03-01T00:00:00+00:00".to_string()),
+                (Some("a"), "2024-01-01T00:00:00+00:00".to_string()),
+            ]
+        );
+        // The tombstone file is not a stored response
+        assert_eq!(entries, 0);
+    }
+
+    #[tokio::test]
+    async fn snapshots_the_bucket_list_only_when_it_changes() {
+        let store = temp_store("history-unit");
+        let bucket = |id: &str, last_updated: &str| -> Bucket {
+            serde_json::from_value(serde_json::json!({"id": id, "last_updated": last_updated}))
+                .unwrap()
+        };
+        let at = |time: &str| -> DateTime<Utc> { time.parse().unwrap() };
+        store
+            .record_buckets(
+                [&bucket("a", "2024-01-01T00:00:00Z")],
+                at("2024-01-01T00:00:00Z"),
+            )
+            .await;
+        store
+            .record_buckets(
+                [&bucket("a", "2024-01-02T00:00:00Z")],
+                at("2024-01-02T00:00:00Z"),
+            )
+            .await;
+        store
+            .record_buckets(
+                [
+                    &bucket("a", "2024-01-03T00:00:00Z"),
+                    &bucket("b", "2024-01-03T00:00:00Z"),
+                ],
+                at("2024-01-03T00:00:00Z"),
+            )
+            .await;
+        let history = store.bucket_history().await;
+        let _ = std::fs::remove_dir_all(&store.dir);
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].taken_at, at("2024-01-01T00:00:00Z"));
+        assert_eq!(history[0].checked_at, at("2024-01-02T00:00:00Z"));
+        assert_eq!(history[1].buckets.len(), 2);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::models::{Bucket, Event};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+/// How deep below the sync directory files are looked for (aw-sync nests
+/// them as `<hostname>/<device_id>/...`)
+const MAX_SYNC_DEPTH: usize = 4;
+
+/// Field of a bucket read from a sync directory naming the file it came from
+pub(crate) const SYNC_FILE_KEY: &str = "sync_file";
+
+/// Field of a bucket both the live server and the sync directory have,
+/// naming the merge policy applied
+pub(crate) const SYNC_MERGE_KEY: &str = "sync_merge";
+
+/// How a bucket both the live server and the sync directory have is served
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncMerge {
+    /// The live server's bucket and events; the synced copy is ignored
+    #[default]
+    PreferLive,
+    /// The synced bucket and events; the live server's copy is ignored
+    PreferSync,
+    /// The live server's bucket with the events of both, duplicates dropped
+    Union,
+}
+
+impl FromStr for SyncMerge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "prefer-live" | "live" => Ok(Self::PreferLive),
+            "prefer-sync" | "sync" => Ok(Self::PreferSync),
+            "union" => Ok(Self::Union),
+            other => Err(format!(
+                "Unknown sync merge policy '{}'. Expected prefer-live, prefer-sync or union",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SyncMerge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PreferLive => "prefer-live",
+            Self::PreferSync => "prefer-sync",
+            Self::Union => "union",
+        })
+    }
+}
+
+/// A bucket read from a sync directory, with all its events newest first
+#[derive(Debug, Clone)]
+pub(crate) struct SyncedBucket {
+    pub bucket: Bucket,
+    pub events: Vec<Event>,
+}
+
+/// Path, modification time and size of every file read, to notice changes
+type Signature = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+/// The buckets of a sync directory as last read
+#[derive(Debug, Default)]
+struct Loaded {
+    signature: Signature,
+    buckets: Arc<HashMap<String, SyncedBucket>>,
+}
+
+/// A directory of bucket exports synced from other devices (e.g., by aw-sync
+/// or a file sync tool), read as a second source of buckets next to the
+/// live aw-server.
+///
+/// JSON exports in aw-server's export format (`{"buckets": {id: {..., "events":
+/// [...]}}}`) are read; the directory is re-read when its files change.
+/// SQLite datastores are skipped, as no SQLite driver is built in.
+#[derive(Debug)]
+pub(crate) struct SyncDir {
+    dir: PathBuf,
+    loaded: Mutex<Loaded>,
+}
+
+impl SyncDir {
+    /// A sync directory at `dir`; nothing is read until first used
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            loaded: Mutex::default(),
+        }
+    }
+
+    /// All buckets of the directory, re-reading it when files changed.
+    ///
+    /// When the same bucket ID is in several files, the one with the most
+    /// recent event wins.
+    pub async fn buckets(&self) -> Arc<HashMap<String, SyncedBucket>> {
+        let mut files = Vec::new();
+        collect_files(&self.dir, 0, &mut files).await;
+        files.sort();
+        let mut signature = Signature::new();
+        for path in files {
+            let metadata = tokio::fs::metadata(&path).await.ok();
+            let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+            signature.push((path, modified, metadata.map(|m| m.len()).unwrap_or(0)));
+        }
+
+        let mut loaded = self.loaded.lock().await;
+        if loaded.signature != signature {
+            let mut buckets: HashMap<String, SyncedBucket> = HashMap::new();
+            let mut skipped = 0;
+            for (path, _, _) in &signature {
+                if path.extension().is_some_and(|ext| ext != "json") {
+                    skipped += 1;
+                    continue;
+                }
+                let exported = match tokio::fs::read(path).await {
+                    Ok(bytes) => parse_export(&bytes, path),
+                    Err(e) => Err(e.to_string()),
+                };
+                match exported {
+                    Ok(exported) => {
+                        for synced in exported {
+                            let newer = buckets.get(&synced.bucket.id).is_none_or(|current| {
+                                last_event(&synced.events) > last_event(&current.events)
+                            });
+                            if newer {
+                                buckets.insert(synced.bucket.id.clone(), synced);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("Skipping sync file {}: {}", path.display(), e),
+                }
+            }
+            if skipped > 0 {
+                tracing::warn!(
+                    "Skipped {} non-JSON files in {} (only JSON bucket exports are read)",
+                    skipped,
+                    self.dir.display()
+                );
+            }
+            *loaded = Loaded {
+                signature,
+                buckets: Arc::new(buckets),
+            };
+        }
+        loaded.buckets.clone()
+    }
+
+    /// One bucket of the directory
+    pub async fn bucket(&self, bucket_id: &str) -> Option<SyncedBucket> {
+        self.buckets().await.get(bucket_id).cloned()
+    }
+}
+
+/// Start of the most recent event, if any (events are newest first)
+fn last_event(events: &[Event]) -> Option<DateTime<Utc>> {
+    events.first().map(|event| event.timestamp)
+}
+
+/// Export files below `dir`, up to [`MAX_SYNC_DEPTH`] levels deep: JSON
+/// exports and SQLite datastores (`.db`), which are reported as skipped
+async fn collect_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        match entry.file_type().await {
+            Ok(kind) if kind.is_dir() && depth < MAX_SYNC_DEPTH => {
+                Box::pin(collect_files(&path, depth + 1, files)).await;
+            }
+            Ok(kind)
+                if kind.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == "json" || ext == "db") =>
+            {
+                files.push(path);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse a bucket export, sorting each bucket's events newest first and
+/// recording the file a bucket came from in its [`SYNC_FILE_KEY`] field
+pub(crate) fn parse_export(bytes: &[u8], path: &Path) -> Result<Vec<SyncedBucket>, String> {
+    #[derive(Deserialize)]
+    struct Export {
+        buckets: HashMap<String, ExportedBucket>,
+    }
+
+    #[derive(Deserialize)]
+    struct ExportedBucket {
+        #[serde(flatten)]
+        bucket: Bucket,
+        #[serde(default)]
+        events: Vec<Event>,
+    }
+
+    let export: Export = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+    Ok(export
+        .buckets
+        .into_values()
+        .map(|exported| {
+            let mut bucket = exported.bucket;
+            bucket
+                .extra
+                .insert(SYNC_FILE_KEY.to_string(), path.display().to_string().into());
+            let mut events = exported.events;
+            events.sort_by_key(|event| std::cmp::Reverse(event.timestamp));
+            if bucket.last_activity().is_none() {
+                bucket.last_updated = events.iter().map(Event::end).max();
+            }
+            SyncedBucket { bucket, events }
+        })
+        .collect())
+}
+
+/// The bucket to list when both the live server and the sync directory have
+/// it, marked with the policy (and, when synced data is used, the sync file)
+pub(crate) fn merge_bucket(live: Bucket, synced: &Bucket, policy: SyncMerge) -> Bucket {
+    let mut bucket = match policy {
+        SyncMerge::PreferSync => synced.clone(),
+        SyncMerge::PreferLive | SyncMerge::Union => live,
+    };
+    if policy == SyncMerge::Union {
+        bucket.last_updated = bucket.last_activity().max(synced.last_activity());
+        if let Some(file) = synced.extra.get(SYNC_FILE_KEY) {
+            bucket.extra.insert(SYNC_FILE_KEY.to_string(), file.clone());
+        }
+    }
+    bucket
+        .extra
+        .insert(SYNC_MERGE_KEY.to_string(), policy.to_string().into());
+    bucket
+}
+
+/// Events of the live server and the sync directory, newest first, at most
+/// `limit`. An event starting at the same time as one already kept, with the
+/// same ID or the same data, is a duplicate and dropped.
+pub(crate) fn union_events(live: Vec<Event>, synced: Vec<Event>, limit: Option<i32>) -> Vec<Event> {
+    let mut events: Vec<Event> = Vec::with_capacity(live.len() + synced.len());
+    for event in live.into_iter().chain(synced) {
+        let duplicate = events.iter().any(|kept| {
+            kept.timestamp == event.timestamp
+                && ((kept.id.is_some() && kept.id == event.id) || kept.data == event.data)
+        });
+        if !duplicate {
+            events.push(event);
+        }
+    }
+    events.sort_by_key(|event| std::cmp::Reverse(event.timestamp));
+    events.truncate(
+        limit
+            .and_then(|l| usize::try_from(l).ok())
+            .unwrap_or(usize::MAX),
+    );
+    events
+}
+
+/// Events overlapping `start`..`end` (either open), newest first, at most
+/// `limit`, as aw-server filters them
+pub(crate) fn filter_events(
+    events: &[Event],
+    limit: Option<i32>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Vec<Event> {
+    let limit = limit
+        .and_then(|l| usize::try_from(l).ok())
+        .unwrap_or(usize::MAX);
+    events
+        .iter()
+        .filter(|event| start.is_none_or(|start| event.end() > start))
+        .filter(|event| end.is_none_or(|end| event.timestamp < end))
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn reads_bucket_exports() {
+        let export = br#"{"buckets": {"aw-watcher-window_phone": {
+            "id": "aw-watcher-window_phone", "type": "currentwindow", "hostname": "phone",
+            "created": "2024-03-01T08:00:00Z",
+            "events": [
+                {"id": 1, "timestamp": "2024-03-15T09:00:00Z", "duration": 600, "data": {"app": "a"}},
+                {"id": 2, "timestamp": "2024-03-15T10:00:00Z", "duration": 60, "data": {"app": "b"}}
+            ]}}}"#;
+        let buckets = parse_export(export, Path::new("/sync/phone/export.json")).unwrap();
+        let synced = &buckets[0];
+        assert_eq!(synced.bucket.hostname.as_deref(), Some("phone"));
+        assert_eq!(synced.bucket.extra["sync_file"], "/sync/phone/export.json");
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 3, 15, h, m, 0).unwrap();
+        assert_eq!(synced.bucket.last_activity(), Some(at(10, 1)));
+        assert_eq!(synced.events[0].id, Some(2));
+
+        let events = filter_events(&synced.events, None, Some(at(9, 5)), Some(at(9, 30)));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, Some(1));
+        assert_eq!(
+            filter_events(&synced.events, Some(1), None, None)[0].id,
+            Some(2)
+        );
+        assert!(parse_export(b"[]", Path::new("x.json")).is_err());
+    }
+
+    #[test]
+    fn merges_buckets_by_policy() {
+        let export = br#"{"buckets": {"b": {"id": "b", "hostname": "phone", "events": [
+            {"id": 1, "timestamp": "2024-03-15T09:00:00Z", "duration": 60, "data": {"app": "a"}},
+            {"id": 2, "timestamp": "2024-03-15T10:00:00Z", "duration": 60, "data": {"app": "b"}}
+        ]}}}"#;
+        let synced = parse_export(export, Path::new("sync.json"))
+            .unwrap()
+            .remove(0);
+        let live: Bucket = serde_json::from_str(r#"{"id": "b", "hostname": "laptop"}"#).unwrap();
+
+        let merged = merge_bucket(live.clone(), &synced.bucket, SyncMerge::PreferSync);
+        assert_eq!(merged.hostname.as_deref(), Some("phone"));
+        assert_eq!(merged.extra[SYNC_MERGE_KEY], "prefer-sync");
+        let merged = merge_bucket(live.clone(), &synced.bucket, SyncMerge::Union);
+        assert_eq!(merged.hostname.as_deref(), Some("laptop"));
+        assert_eq!(merged.last_updated, synced.bucket.last_updated);
+        assert_eq!(merged.extra[SYNC_FILE_KEY], "sync.json");
+
+        // The same event with another ID, and an event with the same ID
+        let mut live_events = synced.events.clone();
+        live_events[0].id = Some(7);
+        live_events[1].data.insert("title".to_string(), "x".into());
+        let events = union_events(live_events, synced.events.clone(), None);
+        assert_eq!(events.len(), 2);
+        assert_eq!(union_events(Vec::new(), synced.events, Some(1)).len(), 1);
+
+        assert_eq!(
+            "prefer_sync".parse::<SyncMerge>().unwrap(),
+            SyncMerge::PreferSync
+        );
+        assert_eq!(
+            SyncMerge::Union.to_string().parse::<SyncMerge>().unwrap(),
+            SyncMerge::Union
+        );
+        assert!("newest".parse::<SyncMerge>().is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// ActivityWatch REST API version (the `N` in `/api/N`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApiVersion(pub u32);
+
+impl ApiVersion {
+    /// The API version every current aw-server implements
+    pub const V0: ApiVersion = ApiVersion(0);
+
+    /// Versions probed during auto-detection, newest first
+    pub const PROBE_ORDER: [ApiVersion; 2] = [ApiVersion(1), ApiVersion::V0];
+
+    /// URL path prefix for this version (without slashes)
+    pub fn prefix(&self) -> String {
+        format!("api/{}", self.0)
+    }
+
+    /// Path segments of an endpoint below the version prefix.
+    ///
+    /// This is the compatibility shim: when a new API version moves or
+    /// renames an endpoint, map it here so callers keep using `Endpoint`.
+    /// Unknown versions use the v0 layout. aw-server and aw-server-rust serve
+    /// the same paths (aw-webui uses one client for both); where their
+    /// responses differ, the client branches on the server flavor.
+    pub(crate) fn segments<'a>(&self, endpoint: &Endpoint<'a>) -> Vec<&'a str> {
+        match endpoint {
+            Endpoint::Info => vec!["info"],
+            // aw-server expects a trailing slash on the bucket list
+            Endpoint::Buckets => vec!["buckets", ""],
+            Endpoint::Bucket(id) => vec!["buckets", id],
+            Endpoint::Events(id) => vec!["buckets", id, "events"],
+            Endpoint::Event(id, event_id) => vec!["buckets", id, "events", event_id],
+            Endpoint::EventCount(id) => vec!["buckets", id, "events", "count"],
+            Endpoint::Heartbeat(id) => vec!["buckets", id, "heartbeat"],
+            Endpoint::BucketExport(id) => vec!["buckets", id, "export"],
+            Endpoint::Export => vec!["export"],
+            Endpoint::Import => vec!["import"],
+            Endpoint::Settings => vec!["settings"],
+            Endpoint::Setting(key) => vec!["settings", key],
+            Endpoint::Query => vec!["query", ""],
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .trim_start_matches(['v', 'V'])
+            .parse()
+            .map(ApiVersion)
+            .map_err(|_| format!("Invalid API version '{}'", s))
+    }
+}
+
+/// A logical ActivityWatch API endpoint
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Endpoint<'a> {
+    /// Server information
+    Info,
+    /// All buckets
+    Buckets,
+    /// A single bucket
+    Bucket(&'a str),
+    /// Events of a bucket
+    Events(&'a str),
+    /// A single event of a bucket, by bucket ID and event ID
+    Event(&'a str, &'a str),
+    /// Event count of a bucket
+    EventCount(&'a str),
+    /// Heartbeats of a bucket
+    Heartbeat(&'a str),
+    /// Export of a bucket with its events
+    BucketExport(&'a str),
+    /// Export of all buckets with their events
+    Export,
+    /// Import of exported buckets
+    Import,
+    /// All server-side settings
+    Settings,
+    /// A server-side setting, such as aw-webui's category rules
+    Setting(&'a str),
+    /// The query2 endpoint
+    Query,
+}
+
+/// Split an ActivityWatch URL into the server root and an explicit API
+/// version, if the URL ends with `/api/N`
+pub(crate) fn split_api_url(url: &str) -> (String, Option<ApiVersion>) {
+    let url = url.trim().trim_end_matches('/');
+    if let Some((root, version)) = url.rsplit_once("/api/")
+        && let Ok(version) = version.parse::<u32>()
+    {
+        return (root.to_string(), Some(ApiVersion(version)));
+    }
+    (url.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_api_url_detects_version_suffix() {
+        assert_eq!(
+            split_api_url("http://localhost:5600/api/0/"),
+            ("http://localhost:5600".to_string(), Some(ApiVersion::V0))
+        );
+        assert_eq!(
+            split_api_url("http://example.com/aw"),
+            ("http://example.com/aw".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn api_version_parses_with_optional_prefix() {
+        assert_eq!("v1".parse::<ApiVersion>(), Ok(ApiVersion(1)));
+        assert_eq!("0".parse::<ApiVersion>(), Ok(ApiVersion::V0));
+        assert!("latest".parse::<ApiVersion>().is_err());
+    }
+}
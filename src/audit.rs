@@ -0,0 +1,47 @@
+use crate::constants::AUDIT_LOG_CAPACITY;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// One tool call in the audit log (arguments are not recorded)
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// When the call finished
+    pub time: DateTime<Utc>,
+
+    /// Tool name
+    pub tool: String,
+
+    /// Correlation ID of the call
+    pub correlation_id: String,
+
+    /// `ok`, `error` (the tool reported a failure) or `failed` (protocol error)
+    pub outcome: &'static str,
+
+    /// How long the call took
+    pub elapsed_ms: u64,
+}
+
+/// In-memory log of recent tool calls, dropping the oldest when full
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    /// Append an entry
+    pub fn record(&self, entry: AuditEntry) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == AUDIT_LOG_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Up to `limit` most recent entries, newest first
+    pub fn recent(&self, limit: usize) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+}
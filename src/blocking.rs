@@ -0,0 +1,189 @@
+//! Synchronous wrapper around the async `ActivityWatchClient`.
+//!
+//! Each client owns a single-threaded Tokio runtime and blocks on it, so
+//! scripts and CLI tools can use the API layer without an async runtime of
+//! their own. Do not call these methods from within an async context.
+
+use crate::api::{self, ApiVersion, BucketMap, BucketRefresh};
+use crate::config::{Config, HttpSettings};
+use crate::models::{AwServerInfo, Bucket, Event, ServerFlavor};
+use reqwest::Method;
+use rmcp::ErrorData as McpError;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Blocking ActivityWatch API client
+#[derive(Clone)]
+pub struct ActivityWatchClient {
+    inner: api::ActivityWatchClient,
+    runtime: Arc<Runtime>,
+}
+
+impl ActivityWatchClient {
+    /// Create a new blocking client (see `api::ActivityWatchClient::new`)
+    pub fn new(base_url: &str) -> Self {
+        Self::from_async(api::ActivityWatchClient::new(base_url))
+    }
+
+    /// Create a client from configuration, detecting the API version when
+    /// needed
+    pub fn from_config(config: &Config) -> Self {
+        let runtime = Arc::new(build_runtime());
+        let inner = runtime.block_on(api::ActivityWatchClient::from_config(config));
+        Self { inner, runtime }
+    }
+
+    /// Wrap an existing async client
+    pub fn from_async(inner: api::ActivityWatchClient) -> Self {
+        Self {
+            inner,
+            runtime: Arc::new(build_runtime()),
+        }
+    }
+
+    /// The underlying async client
+    pub fn inner(&self) -> &api::ActivityWatchClient {
+        &self.inner
+    }
+
+    /// Set how long the cached bucket map stays fresh
+    pub fn with_bucket_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.inner = self.inner.with_bucket_cache_ttl(ttl);
+        self
+    }
+
+    /// Persist responses in a directory and serve them while aw-server is
+    /// unreachable
+    pub fn with_offline_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.inner = self.inner.with_offline_cache(dir);
+        self
+    }
+
+    /// Rebuild the HTTP client with the given pool and protocol settings
+    pub fn with_http_settings(mut self, settings: &HttpSettings) -> Self {
+        self.inner = self.inner.with_http_settings(settings);
+        self
+    }
+
+    /// Use a specific API version
+    pub fn with_api_version(mut self, version: ApiVersion) -> Self {
+        self.inner = self.inner.with_api_version(version);
+        self
+    }
+
+    /// The API version in use
+    pub fn api_version(&self) -> ApiVersion {
+        self.inner.api_version()
+    }
+
+    /// Base URL of the versioned API (e.g., `http://localhost:5600/api/0`)
+    pub fn api_url(&self) -> String {
+        self.inner.api_url()
+    }
+
+    /// Probe the server for the newest supported API version
+    pub fn detect_api_version(&self) -> Option<ApiVersion> {
+        self.block_on(self.inner.detect_api_version())
+    }
+
+    /// Detect which aw-server implementation is running
+    pub fn flavor(&self) -> ServerFlavor {
+        self.block_on(self.inner.flavor())
+    }
+
+    /// Get server information
+    pub fn get_info(&self) -> Result<AwServerInfo, McpError> {
+        self.block_on(self.inner.get_info())
+    }
+
+    /// Get all buckets from the cache, fetching them when the cache is stale
+    pub fn buckets(&self) -> Result<BucketMap, McpError> {
+        self.block_on(self.inner.buckets())
+    }
+
+    /// Re-fetch all buckets and replace the cache
+    pub fn refresh_buckets(&self) -> Result<BucketRefresh, McpError> {
+        self.block_on(self.inner.refresh_buckets())
+    }
+
+    /// Get all buckets (uncached)
+    pub fn get_buckets(&self) -> Result<HashMap<String, Bucket>, McpError> {
+        self.block_on(self.inner.get_buckets())
+    }
+
+    /// Get a specific bucket by ID
+    pub fn get_bucket(&self, bucket_id: &str) -> Result<Bucket, McpError> {
+        self.block_on(self.inner.get_bucket(bucket_id))
+    }
+
+    /// Get events from a bucket
+    pub fn get_events(
+        &self,
+        bucket_id: &str,
+        limit: Option<i32>,
+        start: Option<&str>,
+        end: Option<&str>,
+    ) -> Result<Vec<Event>, McpError> {
+        self.block_on(self.inner.get_events(bucket_id, limit, start, end))
+    }
+
+    /// Get event count for a bucket
+    pub fn get_event_count(
+        &self,
+        bucket_id: &str,
+        start: Option<&str>,
+        end: Option<&str>,
+    ) -> Result<i64, McpError> {
+        self.block_on(self.inner.get_event_count(bucket_id, start, end))
+    }
+
+    /// Get all server settings
+    pub fn get_settings(&self) -> Result<serde_json::Value, McpError> {
+        self.block_on(self.inner.get_settings())
+    }
+
+    /// Get a server setting; `null` when it is not set
+    pub fn get_setting(&self, key: &str) -> Result<serde_json::Value, McpError> {
+        self.block_on(self.inner.get_setting(key))
+    }
+
+    /// Send an arbitrary request to an API path and return the JSON response
+    pub fn raw_request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, McpError> {
+        self.block_on(self.inner.raw_request(method, path, query, body))
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+/// Build the runtime that drives a blocking client
+fn build_runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build Tokio runtime")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_connection_errors_synchronously() {
+        // Nothing listens on port 9, so the connection is refused
+        let client = ActivityWatchClient::new("http://127.0.0.1:9/api/0");
+        assert_eq!(client.api_url(), "http://127.0.0.1:9/api/0");
+        assert!(client.get_buckets().is_err());
+    }
+}
@@ -0,0 +1,824 @@
+//! Category rules, as defined in aw-webui or a local `categories.toml`.
+//!
+//! Each rule assigns events whose data values (app, title, URL, ...) match a
+//! regex to a category path such as `Work > Programming > Rust`. When several
+//! rules match, the deepest category wins, as in aw-webui.
+
+mod regex;
+
+pub use regex::{Regex, escape};
+
+use crate::analysis::{GroupChange, TimeRange};
+use crate::models::Event;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Category of events no rule matches
+pub const UNCATEGORIZED: &str = "Uncategorized";
+
+/// Separator between the levels of a category path
+pub const CATEGORY_SEPARATOR: &str = " > ";
+
+/// A category and the rule that assigns events to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryRule {
+    /// Category path, e.g. `["Work", "Programming"]`
+    pub name: Vec<String>,
+
+    /// Regex matched against the event's data values; `None` for categories
+    /// that only group subcategories
+    pub regex: Option<Regex>,
+}
+
+/// An ordered set of category rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Categories {
+    /// The rules, in definition order
+    pub rules: Vec<CategoryRule>,
+}
+
+impl Categories {
+    /// Read aw-webui's `classes` setting: a list of
+    /// `{"name": [...], "rule": {"type": "regex", "regex": "...", "ignore_case": true}}`
+    /// (or an aw-webui export, with the list under `categories`)
+    pub fn from_webui(classes: &serde_json::Value) -> Result<Self, String> {
+        let classes = classes
+            .get("categories")
+            .unwrap_or(classes)
+            .as_array()
+            .ok_or("Expected the aw-webui classes setting to be a list")?;
+        let mut rules = Vec::with_capacity(classes.len());
+        for (i, class) in classes.iter().enumerate() {
+            let invalid = |what: &str| format!("Category {}: {}", i + 1, what);
+            let name: Vec<String> = class
+                .get("name")
+                .and_then(|n| n.as_array())
+                .and_then(|n| n.iter().map(|p| p.as_str().map(str::to_string)).collect())
+                .filter(|n: &Vec<String>| !n.is_empty())
+                .ok_or_else(|| invalid("expected \"name\" to be a list of strings"))?;
+            let rule = class.get("rule");
+            let regex = match rule.and_then(|r| r.get("type")).and_then(|t| t.as_str()) {
+                Some("regex") => {
+                    let pattern = rule
+                        .and_then(|r| r.get("regex"))
+                        .and_then(|r| r.as_str())
+                        .ok_or_else(|| invalid("regex rule without \"regex\""))?;
+                    let ignore_case = rule
+                        .and_then(|r| r.get("ignore_case"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    Some(Regex::new(pattern, ignore_case).map_err(|e| invalid(&e))?)
+                }
+                Some("none") | None => None,
+                Some(other) => return Err(invalid(&format!("unsupported rule type '{}'", other))),
+            };
+            rules.push(CategoryRule { name, regex });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Parse a `categories.toml` file: one `"Path > To > Category" = "regex"`
+    /// entry per line, with `(?i)` for case-insensitive rules and an empty
+    /// regex for categories that only group subcategories
+    pub fn parse_toml(text: &str) -> Result<Self, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut rules: Vec<CategoryRule> = Vec::new();
+        for (key, value) in table {
+            let fail = |message: &str| format!("Category '{}': {}", key, message);
+            let toml::Value::String(pattern) = value else {
+                return Err(fail("the regex must be a quoted string"));
+            };
+            add_rule(&mut rules, &key, &pattern).map_err(|e| fail(&e))?;
+        }
+        Ok(Self { rules })
+    }
+
+    /// Rules from `("Path > To > Category", "regex")` pairs, as in
+    /// `categories.toml`
+    pub fn from_rules(pairs: impl IntoIterator<Item = (String, String)>) -> Result<Self, String> {
+        let mut rules: Vec<CategoryRule> = Vec::new();
+        for (key, pattern) in pairs {
+            add_rule(&mut rules, &key, &pattern)
+                .map_err(|e| format!("Category '{}': {}", key, e))?;
+        }
+        Ok(Self { rules })
+    }
+
+    /// Write the rules as `categories.toml` lines, with `(?i)` for
+    /// case-insensitive rules
+    pub fn to_toml(&self) -> String {
+        let mut text = String::new();
+        for rule in &self.rules {
+            let pattern = match &rule.regex {
+                Some(regex) if regex.ignore_case() && !regex.as_str().starts_with("(?i)") => {
+                    format!("(?i){}", regex.as_str())
+                }
+                Some(regex) => regex.as_str().to_string(),
+                None => String::new(),
+            };
+            let key = toml_string(&rule.name.join(CATEGORY_SEPARATOR));
+            // Literal strings keep regex backslashes readable
+            let value = match pattern.contains(['\'', '\n']) {
+                true => toml_string(&pattern),
+                false => format!("'{}'", pattern),
+            };
+            text.push_str(&format!("{} = {}\n", key, value));
+        }
+        text
+    }
+
+    /// The rules as aw-webui's `classes` setting, adding parent categories
+    /// that have no rule of their own
+    pub fn to_webui(&self) -> serde_json::Value {
+        let mut names: Vec<&[String]> = Vec::new();
+        let mut classes = Vec::new();
+        let mut push = |name: &[String], rule: serde_json::Value| {
+            classes.push(serde_json::json!({
+                "id": classes.len() + 1,
+                "name": name,
+                "rule": rule,
+            }));
+        };
+        for rule in &self.rules {
+            for depth in 1..rule.name.len() {
+                let parent = &rule.name[..depth];
+                let defined = self.rules.iter().any(|r| r.name == parent);
+                if !defined && !names.contains(&parent) {
+                    names.push(parent);
+                    push(parent, serde_json::json!({"type": "none"}));
+                }
+            }
+            let value = match &rule.regex {
+                Some(regex) => serde_json::json!({
+                    "type": "regex",
+                    "regex": regex.as_str().strip_prefix("(?i)").unwrap_or(regex.as_str()),
+                    "ignore_case": regex.ignore_case(),
+                }),
+                None => serde_json::json!({"type": "none"}),
+            };
+            push(&rule.name, value);
+        }
+        serde_json::Value::Array(classes)
+    }
+
+    /// The category of an event: the deepest category whose rule matches
+    /// one of its data values (the first such rule on ties)
+    pub fn classify(&self, event: &Event) -> Option<&[String]> {
+        let mut best: Option<&CategoryRule> = None;
+        for rule in &self.rules {
+            let Some(regex) = &rule.regex else {
+                continue;
+            };
+            let matches = event
+                .data
+                .values()
+                .filter_map(|v| v.as_str())
+                .any(|value| regex.is_match(value));
+            if matches && best.is_none_or(|b| rule.name.len() > b.name.len()) {
+                best = Some(rule);
+            }
+        }
+        best.map(|rule| rule.name.as_slice())
+    }
+
+    /// The category label of an event, rolled up to at most `depth` levels
+    /// (all levels when `None`)
+    pub fn label(&self, event: &Event, depth: Option<usize>) -> String {
+        match self.classify(event) {
+            Some(path) => category_label(path, depth),
+            None => UNCATEGORIZED.to_string(),
+        }
+    }
+}
+
+/// Quote a TOML basic string
+fn toml_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+/// Add a rule for a category path and regex (empty for a category that only
+/// groups subcategories)
+fn add_rule(rules: &mut Vec<CategoryRule>, path: &str, pattern: &str) -> Result<(), String> {
+    let name = split_path(path);
+    if name.is_empty() {
+        return Err("empty category name".to_string());
+    }
+    if rules.iter().any(|rule| rule.name == name) {
+        return Err(format!("duplicate category '{}'", path));
+    }
+    let regex = match pattern.is_empty() {
+        true => None,
+        false => Some(Regex::new(pattern, false)?),
+    };
+    rules.push(CategoryRule { name, regex });
+    Ok(())
+}
+
+/// Split `Work > Programming` into its levels
+pub fn split_path(path: &str) -> Vec<String> {
+    path.split('>')
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Join a category path, keeping at most `depth` levels
+pub fn category_label(path: &[String], depth: Option<usize>) -> String {
+    let levels = depth.map_or(path.len(), |depth| depth.clamp(1, path.len().max(1)));
+    path[..levels.min(path.len())].join(CATEGORY_SEPARATOR)
+}
+
+/// Time per category, both rolled up to a depth and per leaf category
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryTotals {
+    /// Seconds per category rolled up to the depth, largest first
+    pub rolled_up: Vec<(String, f64)>,
+
+    /// Seconds per full category path, largest first
+    pub leaves: Vec<(String, f64)>,
+}
+
+/// Seconds of an event within the range, or `None` if it lies outside
+fn clipped_secs(event: &Event, range: Option<&TimeRange>) -> Option<f64> {
+    match range {
+        Some(range) => range
+            .clip((event.timestamp, event.end()))
+            .map(|(start, end)| (end - start).num_milliseconds() as f64 / 1000.0),
+        None => Some(event.duration.as_secs_f64()),
+    }
+}
+
+/// Sum event time per category, clipping events to the range if given
+pub fn category_totals(
+    events: &[Event],
+    categories: &Categories,
+    depth: usize,
+    range: Option<&TimeRange>,
+) -> CategoryTotals {
+    let mut rolled_up: HashMap<String, f64> = HashMap::new();
+    let mut leaves: HashMap<String, f64> = HashMap::new();
+    for event in events {
+        let Some(secs) = clipped_secs(event, range) else {
+            continue;
+        };
+        let path = categories.classify(event);
+        let label = |depth| path.map_or(UNCATEGORIZED.to_string(), |p| category_label(p, depth));
+        *rolled_up.entry(label(Some(depth))).or_default() += secs;
+        *leaves.entry(label(None)).or_default() += secs;
+    }
+
+    CategoryTotals {
+        rolled_up: sorted_totals(rolled_up),
+        leaves: sorted_totals(leaves),
+    }
+}
+
+impl CategoryTotals {
+    /// Roll totals per full category path (e.g., from the aggregation
+    /// store) up to a depth
+    pub fn from_leaves<'a>(
+        leaves: impl IntoIterator<Item = (&'a String, &'a f64)>,
+        depth: usize,
+    ) -> Self {
+        let mut rolled_up: HashMap<String, f64> = HashMap::new();
+        let mut all: HashMap<String, f64> = HashMap::new();
+        for (path, secs) in leaves {
+            let label = category_label(&split_path(path), Some(depth));
+            *rolled_up.entry(label).or_default() += secs;
+            *all.entry(path.clone()).or_default() += secs;
+        }
+        Self {
+            rolled_up: sorted_totals(rolled_up),
+            leaves: sorted_totals(all),
+        }
+    }
+}
+
+/// Totals sorted largest first, then by name
+fn sorted_totals(totals: HashMap<String, f64>) -> Vec<(String, f64)> {
+    let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+/// Separators between the parts of a window title, e.g. `lib.rs - project`
+const TITLE_SEPARATORS: &[&str] = &[" - ", " \u{2014} ", " \u{2013} ", " | ", " \u{b7} "];
+
+/// An app, title or domain that uncategorized time is spent in
+#[derive(Debug, Clone, Serialize)]
+pub struct Contributor {
+    /// The app name, window title or domain
+    pub value: String,
+
+    /// Uncategorized seconds
+    pub duration_secs: f64,
+
+    /// A regex that would match it in a category rule
+    pub suggested_rule: String,
+}
+
+/// Where uncategorized time goes, largest contributors first
+#[derive(Debug, Clone, Serialize)]
+pub struct UncategorizedReport {
+    /// Seconds of all events
+    pub total_secs: f64,
+
+    /// Seconds no rule matched
+    pub uncategorized_secs: f64,
+
+    /// Apps of uncategorized events
+    pub apps: Vec<Contributor>,
+
+    /// Titles of uncategorized events
+    pub titles: Vec<Contributor>,
+
+    /// Domains of uncategorized events' URLs
+    pub domains: Vec<Contributor>,
+}
+
+/// The host part of a URL, without a leading `www.`
+pub fn url_domain(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split(':').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty()).then_some(host)
+}
+
+/// List the top `limit` apps, titles and domains of events no category
+/// rule matches, each with a rule that would match it
+pub fn uncategorized(
+    events: &[Event],
+    categories: &Categories,
+    range: Option<&TimeRange>,
+    limit: usize,
+) -> UncategorizedReport {
+    let mut total_secs = 0.0;
+    let mut uncategorized_secs = 0.0;
+    let mut apps: HashMap<String, f64> = HashMap::new();
+    let mut titles: HashMap<String, f64> = HashMap::new();
+    let mut domains: HashMap<String, f64> = HashMap::new();
+    for event in events {
+        let Some(secs) = clipped_secs(event, range) else {
+            continue;
+        };
+        total_secs += secs;
+        if categories.classify(event).is_some() {
+            continue;
+        }
+        uncategorized_secs += secs;
+
+        let value = |key: &str| event.data.get(key).and_then(|v| v.as_str());
+        if let Some(app) = value("app").filter(|app| !app.is_empty()) {
+            *apps.entry(app.to_string()).or_default() += secs;
+        }
+        if let Some(title) = value("title").filter(|title| !title.is_empty()) {
+            *titles.entry(title.to_string()).or_default() += secs;
+        }
+        if let Some(domain) = value("url").and_then(url_domain) {
+            *domains.entry(domain.to_string()).or_default() += secs;
+        }
+    }
+
+    let top = |totals: HashMap<String, f64>, rule: fn(&str) -> String| {
+        let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals
+            .into_iter()
+            .take(limit)
+            .map(|(value, duration_secs)| Contributor {
+                suggested_rule: rule(&value),
+                value,
+                duration_secs,
+            })
+            .collect()
+    };
+    UncategorizedReport {
+        total_secs,
+        uncategorized_secs,
+        apps: top(apps, |app| format!("^{}$", escape(app))),
+        titles: top(titles, |title| escape(title_subject(title))),
+        domains: top(domains, escape),
+    }
+}
+
+/// How a candidate rule would have matched past events
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTest {
+    /// Seconds of all events
+    pub total_secs: f64,
+
+    /// Seconds of events the regex matches
+    pub matched_secs: f64,
+
+    /// Number of events the regex matches
+    pub matched_events: usize,
+
+    /// Seconds the rule's category would win over the existing rules, if a
+    /// category was given
+    pub assigned_secs: Option<f64>,
+
+    /// Current categories of the matched time, largest first
+    pub current: Vec<(String, f64)>,
+
+    /// Titles (or URLs, or apps) with the most matched time
+    pub examples: Vec<(String, f64)>,
+}
+
+/// Try a candidate rule against events: how much time it matches, which
+/// categories that time is in now, and (for a named category) how much of
+/// it the rule would win when added after the existing rules
+pub fn test_rule(
+    events: &[Event],
+    categories: &Categories,
+    regex: &Regex,
+    category: Option<&[String]>,
+    range: Option<&TimeRange>,
+    limit: usize,
+) -> RuleTest {
+    let with_rule = category.map(|name| {
+        let mut extended = categories.clone();
+        extended.rules.push(CategoryRule {
+            name: name.to_vec(),
+            regex: Some(regex.clone()),
+        });
+        extended
+    });
+
+    let mut total_secs = 0.0;
+    let mut matched_secs = 0.0;
+    let mut matched_events = 0;
+    let mut assigned_secs = 0.0;
+    let mut current: HashMap<String, f64> = HashMap::new();
+    let mut examples: HashMap<String, f64> = HashMap::new();
+    for event in events {
+        let Some(secs) = clipped_secs(event, range) else {
+            continue;
+        };
+        total_secs += secs;
+        let matches = event
+            .data
+            .values()
+            .filter_map(|v| v.as_str())
+            .any(|value| regex.is_match(value));
+        if !matches {
+            continue;
+        }
+        matched_secs += secs;
+        matched_events += 1;
+        *current.entry(categories.label(event, None)).or_default() += secs;
+        if let (Some(extended), Some(name)) = (&with_rule, category)
+            && extended.classify(event) == Some(name)
+        {
+            assigned_secs += secs;
+        }
+        let example = ["title", "url", "app"]
+            .iter()
+            .filter_map(|key| event.data.get(*key).and_then(|v| v.as_str()))
+            .find(|value| !value.is_empty());
+        if let Some(example) = example {
+            *examples.entry(example.to_string()).or_default() += secs;
+        }
+    }
+
+    let sorted = |totals: HashMap<String, f64>, limit: usize| {
+        let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals.truncate(limit);
+        totals
+    };
+    RuleTest {
+        total_secs,
+        matched_secs,
+        matched_events,
+        assigned_secs: category.map(|_| assigned_secs),
+        current: sorted(current, usize::MAX),
+        examples: sorted(examples, limit),
+    }
+}
+
+/// Time that changes category under a proposed rule set
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryMove {
+    /// Category under the current rules
+    pub from: String,
+
+    /// Category under the proposed rules
+    pub to: String,
+
+    /// Seconds that move
+    pub duration_secs: f64,
+}
+
+/// Category totals under the current and a proposed rule set
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleComparison {
+    /// Seconds of all events
+    pub total_secs: f64,
+
+    /// Seconds whose category changes
+    pub moved_secs: f64,
+
+    /// Totals per category under both rule sets (`before_secs` for the
+    /// current rules), largest change first
+    pub categories: Vec<GroupChange>,
+
+    /// Moves between categories, largest first
+    pub moves: Vec<CategoryMove>,
+}
+
+/// Classify events under both rule sets, rolled up to `depth` levels (all
+/// levels when `None`), and report which time moves between categories
+pub fn compare_rules(
+    events: &[Event],
+    current: &Categories,
+    proposed: &Categories,
+    depth: Option<usize>,
+    range: Option<&TimeRange>,
+) -> RuleComparison {
+    let mut total_secs = 0.0;
+    let mut totals: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut moves: HashMap<(String, String), f64> = HashMap::new();
+    for event in events {
+        let Some(secs) = clipped_secs(event, range) else {
+            continue;
+        };
+        total_secs += secs;
+        let from = current.label(event, depth);
+        let to = proposed.label(event, depth);
+        totals.entry(from.clone()).or_default().0 += secs;
+        totals.entry(to.clone()).or_default().1 += secs;
+        if from != to {
+            *moves.entry((from, to)).or_default() += secs;
+        }
+    }
+
+    let mut categories: Vec<GroupChange> = totals
+        .into_iter()
+        .map(|(group, (before_secs, after_secs))| GroupChange {
+            group,
+            before_secs,
+            after_secs,
+        })
+        .collect();
+    categories.sort_by(|a, b| {
+        b.delta_secs()
+            .abs()
+            .total_cmp(&a.delta_secs().abs())
+            .then_with(|| b.after_secs.total_cmp(&a.after_secs))
+            .then_with(|| a.group.cmp(&b.group))
+    });
+    let mut moves: Vec<CategoryMove> = moves
+        .into_iter()
+        .map(|((from, to), duration_secs)| CategoryMove {
+            from,
+            to,
+            duration_secs,
+        })
+        .collect();
+    moves.sort_by(|a, b| {
+        b.duration_secs
+            .total_cmp(&a.duration_secs)
+            .then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to)))
+    });
+
+    RuleComparison {
+        total_secs,
+        moved_secs: moves.iter().fold(0.0, |total, m| total + m.duration_secs),
+        categories,
+        moves,
+    }
+}
+
+/// The first part of a window title, usually the document or page name
+fn title_subject(title: &str) -> &str {
+    TITLE_SEPARATORS
+        .iter()
+        .filter_map(|separator| title.find(separator))
+        .min()
+        .map(|end| title[..end].trim())
+        .filter(|subject| !subject.is_empty())
+        .unwrap_or(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn event(app: &str, title: &str, secs: f64) -> Event {
+        test_event(
+            Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap(),
+            secs,
+            serde_json::json!({"app": app, "title": title}),
+        )
+    }
+
+    fn categories() -> Categories {
+        Categories::parse_toml(
+            r#"
+            # Work
+            "Work" = ""
+            "Work > Programming" = '(?i)code|terminal'
+            "Work > Programming > Rust" = "\\.rs\\b|cargo"
+            Media = "YouTube"   # bare keys work too
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn deepest_matching_category_wins() {
+        let categories = categories();
+        let label = |app, title| categories.label(&event(app, title, 1.0), None);
+        assert_eq!(
+            label("Code", "main.rs - project"),
+            "Work > Programming > Rust"
+        );
+        assert_eq!(label("Terminal", "npm test"), "Work > Programming");
+        assert_eq!(label("Firefox", "YouTube"), "Media");
+        assert_eq!(label("Mail", "Inbox"), UNCATEGORIZED);
+        assert_eq!(
+            categories.label(&event("Code", "lib.rs", 1.0), Some(1)),
+            "Work"
+        );
+    }
+
+    #[test]
+    fn rolls_totals_up_to_a_depth() {
+        let events = [
+            event("Code", "lib.rs", 60.0),
+            event("Terminal", "npm test", 30.0),
+            event("Firefox", "YouTube", 20.0),
+            event("Mail", "Inbox", 10.0),
+        ];
+        let totals = category_totals(&events, &categories(), 1, None);
+        let entry = |label: &str, secs: f64| (label.to_string(), secs);
+        assert_eq!(
+            totals.rolled_up,
+            vec![
+                entry("Work", 90.0),
+                entry("Media", 20.0),
+                entry(UNCATEGORIZED, 10.0)
+            ]
+        );
+        assert_eq!(totals.leaves[0], entry("Work > Programming > Rust", 60.0));
+        assert_eq!(totals.leaves.len(), 4);
+    }
+
+    #[test]
+    fn reads_webui_classes() {
+        let classes = serde_json::json!([
+            {"name": ["Work"], "rule": {"type": "none"}},
+            {
+                "name": ["Work", "Email"],
+                "rule": {"type": "regex", "regex": "mail", "ignore_case": true}
+            }
+        ]);
+        let categories = Categories::from_webui(&classes).unwrap();
+        assert_eq!(
+            categories.label(&event("Mail", "Inbox", 1.0), None),
+            "Work > Email"
+        );
+        assert!(Categories::from_webui(&serde_json::json!([{"name": "Work"}])).is_err());
+        assert!(Categories::parse_toml("\"Work\" = \"(unclosed\"").is_err());
+    }
+
+    #[test]
+    fn tests_candidate_rules_against_events() {
+        let events = [
+            event("Code", "lib.rs", 60.0),
+            event("Code", "README.md", 30.0),
+            event("Mail", "Inbox", 10.0),
+        ];
+        let regex = Regex::new("(?i)code", false).unwrap();
+
+        // Ties in depth go to the existing Rust rule
+        let name = split_path("Work > Programming > Docs");
+        let test = test_rule(&events, &categories(), &regex, Some(&name), None, 10);
+        assert_eq!((test.matched_secs, test.matched_events), (90.0, 2));
+        assert_eq!(test.assigned_secs, Some(30.0));
+        assert_eq!(
+            test.current[0],
+            ("Work > Programming > Rust".to_string(), 60.0)
+        );
+        assert_eq!(test.examples[0], ("lib.rs".to_string(), 60.0));
+
+        let test = test_rule(&events, &categories(), &regex, None, None, 1);
+        assert_eq!((test.assigned_secs, test.examples.len()), (None, 1));
+    }
+
+    #[test]
+    fn compares_totals_under_proposed_rules() {
+        let events = [
+            event("Code", "lib.rs", 60.0),
+            event("Terminal", "npm test", 30.0),
+            event("Mail", "Inbox", 10.0),
+        ];
+        let proposed = Categories::parse_toml(
+            r#"
+            "Work > Programming" = "(?i)code|terminal"
+            "Work > Email" = "Mail"
+            "#,
+        )
+        .unwrap();
+
+        let comparison = compare_rules(&events, &categories(), &proposed, None, None);
+        assert_eq!(comparison.moved_secs, 70.0);
+        let moves: Vec<(&str, &str, f64)> = comparison
+            .moves
+            .iter()
+            .map(|m| (m.from.as_str(), m.to.as_str(), m.duration_secs))
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                ("Work > Programming > Rust", "Work > Programming", 60.0),
+                (UNCATEGORIZED, "Work > Email", 10.0)
+            ]
+        );
+        assert_eq!(comparison.categories[0].group, "Work > Programming");
+        assert_eq!(comparison.categories[0].delta_secs(), 60.0);
+
+        // Rolled up to the top level, only the email time moves
+        let comparison = compare_rules(&events, &categories(), &proposed, Some(1), None);
+        assert_eq!(comparison.moved_secs, 10.0);
+    }
+
+    #[test]
+    fn converts_between_toml_and_webui_formats() {
+        let classes = serde_json::json!([
+            {
+                "id": 1,
+                "name": ["Work", "Programming"],
+                "rule": {"type": "regex", "regex": "code|'vim'", "ignore_case": true}
+            },
+            {
+                "id": 2,
+                "name": ["Work", "Programming", "Rust"],
+                "rule": {"type": "regex", "regex": "\\.rs\\b"}
+            },
+        ]);
+        let categories = Categories::from_webui(&classes).unwrap();
+        let toml = categories.to_toml();
+        assert_eq!(
+            toml,
+            concat!(
+                "\"Work > Programming\" = \"(?i)code|'vim'\"\n",
+                "\"Work > Programming > Rust\" = '\\.rs\\b'\n"
+            )
+        );
+
+        // The missing parent is added; everything else survives the round trip
+        let webui = Categories::parse_toml(&toml).unwrap().to_webui();
+        assert_eq!(webui[0]["name"], serde_json::json!(["Work"]));
+        assert_eq!(webui[0]["rule"]["type"], "none");
+        assert_eq!(
+            webui[1]["rule"],
+            serde_json::json!({"type": "regex", "regex": "code|'vim'", "ignore_case": true})
+        );
+        assert_eq!(webui[2]["rule"]["regex"], classes[1]["rule"]["regex"]);
+        assert_eq!(Categories::from_webui(&webui).unwrap().rules.len(), 3);
+    }
+
+    #[test]
+    fn suggests_rules_for_uncategorized_time() {
+        let mut tab = event("Firefox", "Pricing | Example (beta)", 40.0);
+        tab.data.insert(
+            "url".to_string(),
+            serde_json::json!("https://www.example.com:8080/x"),
+        );
+        let events = [
+            event("Code", "lib.rs", 60.0),
+            event("Slack", "general - Team", 30.0),
+            tab,
+        ];
+        let report = uncategorized(&events, &categories(), None, 10);
+        assert_eq!(
+            (report.total_secs, report.uncategorized_secs),
+            (130.0, 70.0)
+        );
+        assert_eq!(report.apps[0].value, "Firefox");
+        assert_eq!(report.titles[0].suggested_rule, "Pricing");
+        assert_eq!(report.domains[0].suggested_rule, r"example\.com");
+
+        // Every suggestion matches the value it was made for
+        for contributor in report
+            .apps
+            .iter()
+            .chain(&report.titles)
+            .chain(&report.domains)
+        {
+            let regex = Regex::new(&contributor.suggested_rule, false).unwrap();
+            assert!(regex.is_match(&contributor.value), "{:?}", contributor);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Regular expressions for category rules and other user-supplied
+//! patterns.
+//!
+//! A thin wrapper around `regex-automata`'s meta engine, the engine behind
+//! the `regex` crate: matching runs in time linear in the text, whatever the
+//! pattern, so patterns from rules and tool calls cannot stall the runtime.
+//! Look-around and backreferences are not supported. A leading `(?i)` (or
+//! `ignore_case`) makes matching case-insensitive. Matching is unanchored,
+//! like a search.
+
+use regex_automata::meta;
+use regex_automata::util::syntax;
+use std::fmt;
+
+/// A compiled regular expression
+#[derive(Clone)]
+pub struct Regex {
+    pattern: String,
+    compiled: meta::Regex,
+    ignore_case: bool,
+}
+
+impl Regex {
+    /// Compile a pattern; `ignore_case` has the same effect as a leading `(?i)`
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        let compiled = meta::Regex::builder()
+            .syntax(syntax::Config::new().case_insensitive(ignore_case))
+            .build(pattern)
+            .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            compiled,
+            ignore_case: ignore_case || pattern.starts_with("(?i)"),
+        })
+    }
+
+    /// The pattern the regex was compiled from
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether the regex ignores case, by flag or a leading `(?i)`
+    pub fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// Whether the regex matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        self.compiled.is_match(text)
+    }
+
+    /// The non-overlapping matches in `text`, leftmost first; empty
+    /// matches are skipped
+    pub fn find_all(&self, text: &str) -> Vec<String> {
+        self.compiled
+            .find_iter(text)
+            .filter(|found| !found.is_empty())
+            .map(|found| text[found.range()].to_string())
+            .collect()
+    }
+}
+
+/// Escape text so it matches literally in a pattern
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Regex({:?})", self.pattern)
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.ignore_case == other.ignore_case
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern, false).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn matches_common_rule_patterns() {
+        assert!(matches("Rust|cargo", "cargo build"));
+        assert!(matches(
+            "(?i)visual studio code",
+            "main.rs - Visual Studio Code"
+        ));
+        assert!(!matches("Visual Studio Code", "visual studio code"));
+        assert!(matches("^GitHub", "GitHub - Pull requests"));
+        assert!(!matches("^GitHub", "Issues · GitHub"));
+        assert!(matches(r"\.rs\b", "lib.rs - editor"));
+        assert!(!matches(r"\.rs\b", "lib.rsx"));
+        assert!(matches("[Yy]ou[Tt]ube", "youtube.com"));
+        assert!(matches(r"PR #\d{2,4}$", "Review PR #1234"));
+        assert!(!matches(r"PR #\d{2,4}$", "Review PR #1"));
+        assert!(matches("a(?:b|c)+d", "xxabcbd"));
+        assert!(matches("^$", ""));
+        assert!(matches("colou?r", "color"));
+        assert!(matches("[^a-z]", "abc1"));
+        assert!(matches("a.*?b", "a-b-b"));
+        assert!(matches(&escape("C++ (v2.0) [beta]"), "C++ (v2.0) [beta]"));
+    }
+
+    #[test]
+    fn finds_all_matches() {
+        let regex = Regex::new(r"\b[A-Z][A-Z0-9]+-\d+\b", false).unwrap();
+        assert_eq!(
+            regex.find_all("PROJ-12: fix (see OPS-7, x-1)"),
+            ["PROJ-12", "OPS-7"]
+        );
+        assert!(regex.find_all("no tickets").is_empty());
+        assert_eq!(Regex::new("a*", false).unwrap().find_all("baab"), ["aa"]);
+    }
+
+    #[test]
+    fn rejects_invalid_and_unsupported_syntax() {
+        assert!(Regex::new("(unclosed", false).is_err());
+        assert!(Regex::new("closed)", false).is_err());
+        assert!(Regex::new("*a", false).is_err());
+        assert!(Regex::new("[z-a]", false).is_err());
+        assert!(Regex::new("(?=lookahead)", false).is_err());
+        assert!(Regex::new(r"(a)\1", false).is_err());
+    }
+
+    #[test]
+    fn matches_in_linear_time() {
+        // Exponential for a backtracking engine
+        let regex = Regex::new("(a+)+$", false).unwrap();
+        let text = format!("{}!", "a".repeat(10_000));
+        let started = std::time::Instant::now();
+        assert!(!regex.is_match(&text));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// The source of "now" for everything relative to the current time:
+/// presets such as "today" or "this morning", open-ended ranges, the day
+/// snapshot, watcher health, which aggregate days are settled, which query
+/// periods have ended and the age of cached data.
+///
+/// The server uses [`SystemClock`] unless another clock is given with
+/// [`ActivityWatchMcpServer::with_clock`](crate::ActivityWatchMcpServer::with_clock)
+/// or `AW_MCP_NOW` fixes the time, so such features can be tested at a
+/// known instant.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system's wall-clock time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock standing still at one instant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
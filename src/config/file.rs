@@ -0,0 +1,276 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use toml::Value;
+
+/// Settings whose entries are separated by `;` rather than `,`
+const SEMICOLON_LISTS: &[&str] = &[
+    "AW_MCP_ALERTS",
+    "AW_MCP_DO_NOT_TRACK",
+    "AW_MCP_GOALS",
+    "AW_MCP_TICKET_PATTERNS",
+    "AW_MCP_TIMEZONE_OVERRIDES",
+    "AW_MCP_TOKENS",
+];
+
+/// Declare the keys a config file may contain, one per setting, named after
+/// its environment variable in lower case without the `AW_MCP_` prefix
+macro_rules! settings {
+    ($($key:ident),* $(,)?) => {
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Settings {
+            $($key: Option<Value>,)*
+        }
+
+        impl Settings {
+            /// The values that are set, with their environment variable names
+            fn into_values(self) -> Vec<(String, Value)> {
+                let values = [$((stringify!($key), self.$key)),*];
+                values
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((env_name(key), value?)))
+                    .collect()
+            }
+        }
+    };
+}
+
+settings! {
+    activitywatch_url,
+    api_version,
+    zero_duration_policy,
+    clock_offsets,
+    min_coverage,
+    timezone,
+    timezone_overrides,
+    fiscal_year_start,
+    work_hours,
+    tab_weights,
+    ticket_patterns,
+    goals,
+    distraction_categories,
+    categories,
+    templates,
+    api_methods,
+    bucket_cache_ttl,
+    cache_dir,
+    sync_dir,
+    sync_merge,
+    aggregate_dir,
+    max_concurrent_requests,
+    keepalive_interval,
+    snapshot,
+    snapshot_interval,
+    alerts,
+    alert_interval,
+    alert_webhook,
+    escape_markdown,
+    output_style,
+    duration_unit,
+    duration_rounding,
+    percent_decimals,
+    injection_guard,
+    scrub_secrets,
+    scrub_emails,
+    pseudonymize,
+    pseudonym_salt,
+    pseudonym_users,
+    do_not_track,
+    scope,
+    listen,
+    tls_cert,
+    tls_key,
+    tokens,
+    tool_prefix,
+    tool_renames,
+    admin_tools,
+    allow_writes,
+    now,
+    http_timeout,
+    pool_idle_timeout,
+    pool_max_idle_per_host,
+    compression,
+}
+
+/// A parsed config file
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    /// Setting values keyed by environment variable name
+    pub values: HashMap<String, String>,
+
+    /// Category rules (path and regex) of a `[categories]` table, in order
+    pub categories: Option<Vec<(String, String)>>,
+}
+
+/// Parse a TOML config file into values keyed by environment variable name
+/// (`min_coverage` becomes `AW_MCP_MIN_COVERAGE`).
+///
+/// Arrays are joined with the setting's separator. A table such as
+/// `[clock_offsets]` gives a setting made of `key=value` entries; a
+/// `[categories]` table holds `"Path > To > Category" = "regex"` rules.
+/// Unknown keys, nested tables and arrays of tables are rejected.
+pub fn parse(text: &str) -> Result<ConfigFile, String> {
+    let settings: Settings = toml::from_str(text).map_err(|e| e.to_string())?;
+    let mut file = ConfigFile::default();
+    for (name, value) in settings.into_values() {
+        let fail = |message: &str| format!("Setting '{}': {}", name, message);
+        let value = match value {
+            Value::Table(rules) if name == "AW_MCP_CATEGORIES" => {
+                let rules = rules
+                    .into_iter()
+                    .map(|(path, regex)| match regex {
+                        Value::String(regex) => Ok((path, regex)),
+                        _ => Err(fail("category regexes must be strings")),
+                    })
+                    .collect::<Result<_, _>>()?;
+                file.categories = Some(rules);
+                continue;
+            }
+            Value::Table(entries) => entries
+                .into_iter()
+                .map(|(key, value)| {
+                    // An array is one entry's list, such as a token's scope
+                    let value = match value {
+                        Value::Array(items) => join(items, ","),
+                        value => scalar(value),
+                    };
+                    Ok(format!("{}={}", key, value.map_err(|e| fail(&e))?))
+                })
+                .collect::<Result<Vec<_>, String>>()?
+                .join(separator(&name)),
+            Value::Array(items) => join(items, separator(&name)).map_err(|e| fail(&e))?,
+            value => scalar(value).map_err(|e| fail(&e))?,
+        };
+        file.values.insert(name, value);
+    }
+    Ok(file)
+}
+
+/// The environment variable a key sets
+fn env_name(key: &str) -> String {
+    match key {
+        "activitywatch_url" => "ACTIVITYWATCH_URL".to_string(),
+        key => format!("AW_MCP_{}", key.to_ascii_uppercase()),
+    }
+}
+
+/// Separator between the entries of a setting
+fn separator(env_name: &str) -> &'static str {
+    match SEMICOLON_LISTS.contains(&env_name) {
+        true => ";",
+        false => ",",
+    }
+}
+
+/// A string, number, boolean or date as the text of an environment variable
+fn scalar(value: Value) -> Result<String, String> {
+    match value {
+        Value::String(text) => Ok(text),
+        Value::Integer(number) => Ok(number.to_string()),
+        Value::Float(number) => Ok(number.to_string()),
+        Value::Boolean(flag) => Ok(flag.to_string()),
+        Value::Datetime(datetime) => Ok(datetime.to_string()),
+        Value::Array(_) | Value::Table(_) => {
+            Err("nested arrays and tables are not supported".into())
+        }
+    }
+}
+
+fn join(items: Vec<Value>, separator: &str) -> Result<String, String> {
+    let items = items
+        .into_iter()
+        .map(scalar)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items.join(separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flat_toml() {
+        let values = parse(
+            r#"
+            # Remote server
+            activitywatch_url = "http://nas:5600"
+            min_coverage = 0.5   # warn below half
+            escape_markdown = false
+            api_methods = ["GET", 'POST']
+            alerts = ["no-window>=30m", "Media>=2h before 12:00"]
+            scope = "host:laptop,bucket:aw-watcher-web-*"
+            now = 2024-03-01T12:00:00Z
+            "#,
+        )
+        .unwrap()
+        .values;
+
+        assert_eq!(values["ACTIVITYWATCH_URL"], "http://nas:5600");
+        assert_eq!(values["AW_MCP_MIN_COVERAGE"], "0.5");
+        assert_eq!(values["AW_MCP_ESCAPE_MARKDOWN"], "false");
+        assert_eq!(values["AW_MCP_API_METHODS"], "GET,POST");
+        assert_eq!(
+            values["AW_MCP_ALERTS"],
+            "no-window>=30m;Media>=2h before 12:00"
+        );
+        assert_eq!(
+            values["AW_MCP_SCOPE"],
+            "host:laptop,bucket:aw-watcher-web-*"
+        );
+        assert_eq!(values["AW_MCP_NOW"], "2024-03-01T12:00:00Z");
+    }
+
+    #[test]
+    fn parses_tables() {
+        let file = parse(
+            r#"
+            min_coverage = 0.5
+
+            [clock_offsets]
+            phone = -2.5
+            laptop = 1
+
+            [tokens]
+            tok1 = "host:laptop"
+            tok2 = ["host:desktop", "bucket:aw-watcher-web-*"]
+
+            [categories]
+            "Work > Programming" = "(?i)code|terminal"
+            Media = ""
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.values["AW_MCP_MIN_COVERAGE"], "0.5");
+        assert_eq!(file.values["AW_MCP_CLOCK_OFFSETS"], "phone=-2.5,laptop=1");
+        assert_eq!(
+            file.values["AW_MCP_TOKENS"],
+            "tok1=host:laptop;tok2=host:desktop,bucket:aw-watcher-web-*"
+        );
+        assert_eq!(
+            file.categories.unwrap(),
+            [
+                (
+                    "Work > Programming".to_string(),
+                    "(?i)code|terminal".to_string()
+                ),
+                ("Media".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let error = parse("min_coverag = 0.5").unwrap_err();
+        assert!(error.contains("unknown field `min_coverag`"), "{}", error);
+        assert!(parse("[server.http]\nurl = 1").is_err());
+        assert!(parse("[[alerts]]\nrule = 1").is_err());
+        assert!(parse("[clock_offsets.phone]\nx = 1").is_err());
+        assert!(parse("clock_offsets = \"a=1\"\n[clock_offsets]\nb = 2").is_err());
+        assert!(parse("[tokens]\na = 1\na = 2").is_err());
+        assert!(parse("[categories]\nWork = 1").is_err());
+        assert!(parse("min_coverage").is_err());
+        assert!(parse("title = \"unterminated").is_err());
+        assert!(parse("scope = \"a\"\nscope = \"b\"").is_err());
+    }
+}
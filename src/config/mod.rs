@@ -0,0 +1,913 @@
+mod file;
+
+use crate::analysis::{AlertRule, Goal, TabWeights, WorkHours, parse_timestamp};
+use crate::api::{ApiVersion, Recording, SyncMerge};
+use crate::categories::{Categories, Regex};
+use crate::constants::{
+    DEFAULT_ALERT_INTERVAL_MINUTES, DEFAULT_BASE_URL, DEFAULT_BUCKET_CACHE_TTL_SECS,
+    DEFAULT_DISTRACTION_CATEGORIES, DEFAULT_HTTP_TIMEOUT_SECS, DEFAULT_MAX_CONCURRENT_REQUESTS,
+    DEFAULT_MIN_COVERAGE, DEFAULT_POOL_IDLE_TIMEOUT_SECS, DEFAULT_POOL_MAX_IDLE_PER_HOST,
+    DEFAULT_SNAPSHOT_INTERVAL_MINUTES, DEFAULT_TICKET_PATTERN,
+};
+use crate::format::Rounding;
+use crate::models::ZeroDurationPolicy;
+use crate::naming::ToolNames;
+use crate::privacy::DoNotTrackRule;
+use crate::scope::{AccessScope, ClientToken};
+use crate::style::OutputStyle;
+use crate::timezone::Timezone;
+use crate::units::DurationUnit;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Server configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// ActivityWatch server URL, optionally including `/api/N` (`ACTIVITYWATCH_URL`)
+    pub base_url: String,
+
+    /// API version; `None` uses the version in the URL or auto-detects it
+    /// (`AW_MCP_API_VERSION`, a number or `auto`)
+    pub api_version: Option<ApiVersion>,
+
+    /// Default handling of zero-duration events (`AW_MCP_ZERO_DURATION_POLICY`)
+    pub zero_duration_policy: ZeroDurationPolicy,
+
+    /// Per-host clock offsets in seconds added to that host's timestamps when
+    /// merging devices (`AW_MCP_CLOCK_OFFSETS`, e.g. `phone=-2.5,laptop=1`)
+    pub clock_offsets: HashMap<String, f64>,
+
+    /// Minimum fraction of a range covered by AFK data before summaries warn
+    /// about low coverage (`AW_MCP_MIN_COVERAGE`, 0.0 - 1.0)
+    pub min_coverage: f64,
+
+    /// Timezone that days, weeks and periods are split in
+    /// (`AW_MCP_TIMEZONE`: `UTC`, `local`, an offset such as `+09:00` or a
+    /// zone name such as `Europe/Berlin`), with the per-date overrides of
+    /// `AW_MCP_TIMEZONE_OVERRIDES` applied
+    pub timezone: Timezone,
+
+    /// First month (1-12) of the fiscal year used by `FY` period specifiers
+    /// (`AW_MCP_FISCAL_YEAR_START`)
+    pub fiscal_year_start: u32,
+
+    /// Daily working hours that sub-day presets such as `this morning` and
+    /// `work hours yesterday` are relative to (`AW_MCP_WORK_HOURS`, e.g.
+    /// `09:00-17:00`)
+    pub work_hours: WorkHours,
+
+    /// How browser tab time counts in browser summaries, by whether the
+    /// browser was in front (`AW_MCP_TAB_WEIGHTS`, e.g.
+    /// `focused=1,audible=0.5,background=0`, `focused` or `raw`)
+    pub tab_weights: TabWeights,
+
+    /// Patterns extracting ticket IDs from titles and URLs for per-ticket
+    /// rollups (`AW_MCP_TICKET_PATTERNS`, regexes separated by `;`)
+    pub ticket_patterns: Vec<Regex>,
+
+    /// Weekly category targets checked by `aw_review_pack` (`AW_MCP_GOALS`,
+    /// e.g. `Work>=20h;Media<=5h`)
+    pub goals: Vec<Goal>,
+
+    /// Categories counted as distractions, with their subcategories
+    /// (`AW_MCP_DISTRACTION_CATEGORIES`, separated by `,`)
+    pub distraction_categories: Vec<String>,
+
+    /// A `categories.toml` file with category rules (`AW_MCP_CATEGORIES`);
+    /// the aw-webui categories stored in aw-server are used when unset
+    pub categories_file: Option<PathBuf>,
+
+    /// Category rules from a `[categories]` table of the config file, used
+    /// instead of `categories_file` and aw-webui's categories
+    pub category_rules: Option<Categories>,
+
+    /// Directory of report templates for `aw_render_template`
+    /// (`AW_MCP_TEMPLATES`)
+    pub templates_dir: Option<PathBuf>,
+
+    /// HTTP methods the raw API passthrough tool may use (`AW_MCP_API_METHODS`,
+    /// e.g. `GET,POST`)
+    pub api_methods: Vec<Method>,
+
+    /// How long the cached bucket map stays fresh (`AW_MCP_BUCKET_CACHE_TTL`,
+    /// in seconds)
+    pub bucket_cache_ttl: Duration,
+
+    /// Directory for the persistent response cache used while aw-server is
+    /// unreachable (`AW_MCP_CACHE_DIR`); disabled when unset
+    pub cache_dir: Option<PathBuf>,
+
+    /// Directory of bucket exports synced from other devices, served next to
+    /// the live server's buckets (`AW_MCP_SYNC_DIR`); disabled when unset
+    pub sync_dir: Option<PathBuf>,
+
+    /// How buckets both the live server and the sync directory have are
+    /// served (`AW_MCP_SYNC_MERGE`)
+    pub sync_merge: SyncMerge,
+
+    /// Directory the daily totals of the aggregation store are persisted in
+    /// (`AW_MCP_AGGREGATE_DIR`); kept in memory only when unset
+    pub aggregate_dir: Option<PathBuf>,
+
+    /// Maximum simultaneous requests to aw-server; 0 disables the limit
+    /// (`AW_MCP_MAX_CONCURRENT_REQUESTS`)
+    pub max_concurrent_requests: usize,
+
+    /// Interval between keepalive pings to the MCP client; a client that
+    /// stops answering ends the session (`AW_MCP_KEEPALIVE_INTERVAL`, in
+    /// seconds; disabled when unset or `0`)
+    pub keepalive_interval: Option<Duration>,
+
+    /// File the background job keeps a summary of the current day in
+    /// (`AW_MCP_SNAPSHOT`, e.g. `today.json`); no snapshot when unset
+    pub snapshot_file: Option<PathBuf>,
+
+    /// How often the day snapshot is refreshed (`AW_MCP_SNAPSHOT_INTERVAL`,
+    /// in minutes)
+    pub snapshot_interval: Duration,
+
+    /// Rules the background alert job checks, e.g. `no-window>=30m` or
+    /// `Media>=2h before 12:00` (`AW_MCP_ALERTS`, separated by `;`)
+    pub alerts: Vec<AlertRule>,
+
+    /// How often the alert rules are checked (`AW_MCP_ALERT_INTERVAL`, in
+    /// minutes)
+    pub alert_interval: Duration,
+
+    /// URL alerts are POSTed to as JSON (`AW_MCP_ALERT_WEBHOOK`)
+    pub alert_webhook: Option<reqwest::Url>,
+
+    /// Escape event data in markdown output so window titles cannot alter
+    /// the report structure (`AW_MCP_ESCAPE_MARKDOWN`)
+    pub escape_markdown: bool,
+
+    /// Decoration of markdown responses: as written, with emoji, or plain
+    /// text (`AW_MCP_OUTPUT_STYLE`)
+    pub output_style: OutputStyle,
+
+    /// Unit JSON responses additionally give durations in, with humanized
+    /// values; durations are left as seconds only when unset
+    /// (`AW_MCP_DURATION_UNIT`)
+    pub duration_unit: Option<DurationUnit>,
+
+    /// Rounding of durations and percentages in all reports
+    /// (`AW_MCP_DURATION_ROUNDING`, `AW_MCP_PERCENT_DECIMALS`)
+    pub rounding: Rounding,
+
+    /// Strip URL-like and instruction-like text from tool output and wrap it
+    /// in a block marking it as data, not instructions
+    /// (`AW_MCP_INJECTION_GUARD`)
+    pub injection_guard: bool,
+
+    /// Mask JWTs, API keys and secret URL parameters in all tool output
+    /// (`AW_MCP_SCRUB_SECRETS`)
+    pub scrub_secrets: bool,
+
+    /// Also mask email addresses when scrubbing secrets (`AW_MCP_SCRUB_EMAILS`)
+    pub scrub_emails: bool,
+
+    /// Replace hostnames and OS usernames in tool output with stable salted
+    /// hashes (`AW_MCP_PSEUDONYMIZE`)
+    pub pseudonymize: bool,
+
+    /// Salt for pseudonyms (`AW_MCP_PSEUDONYM_SALT`)
+    pub pseudonym_salt: String,
+
+    /// Usernames to pseudonymize wherever they appear, in addition to those
+    /// found in home directory paths (`AW_MCP_PSEUDONYM_USERS`, comma-separated)
+    pub pseudonym_users: Vec<String>,
+
+    /// Personal time left out of all data unless a tool call passes
+    /// `include_private`, e.g. `weekends` or `2024-08-01..2024-08-14`
+    /// (`AW_MCP_DO_NOT_TRACK`, separated by `;`)
+    pub do_not_track: Vec<DoNotTrackRule>,
+
+    /// Restrict tools to the buckets of some hosts or bucket patterns, for
+    /// sharing one instance between users (`AW_MCP_SCOPE`, e.g.
+    /// `host:laptop,bucket:aw-watcher-web-*`); unrestricted when unset
+    pub scope: Option<AccessScope>,
+
+    /// Serve MCP over streamable HTTP to several clients on this address
+    /// instead of stdio (`AW_MCP_LISTEN`, or the `--listen` flag), e.g.
+    /// `0.0.0.0:5700`; needs the `http` feature
+    pub listen: Option<String>,
+
+    /// PEM certificate chain of the `listen` endpoint (`AW_MCP_TLS_CERT`)
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the `listen` endpoint (`AW_MCP_TLS_KEY`)
+    pub tls_key: Option<PathBuf>,
+
+    /// Auth tokens of the `listen` endpoint, each with the buckets its
+    /// client may see, so users sharing one endpoint only see their own
+    /// activity (`AW_MCP_TOKENS`, e.g. `tok1=host:laptop;tok2=host:desktop`)
+    pub client_tokens: Vec<ClientToken>,
+
+    /// Names the tools are offered under: a prefix replacing `aw_`
+    /// (`AW_MCP_TOOL_PREFIX`) and per-tool renames (`AW_MCP_TOOL_RENAMES`,
+    /// e.g. `aw_get_events=events,aw_status=watchers`)
+    pub tool_names: ToolNames,
+
+    /// Offer the admin tools (config reload, cache clear, audit log,
+    /// self-test) (`AW_MCP_ADMIN_TOOLS`, or the `--admin` flag)
+    pub admin_tools: bool,
+
+    /// Offer the tools that write to aw-server, such as heartbeats
+    /// (`AW_MCP_ALLOW_WRITES`)
+    pub allow_writes: bool,
+
+    /// A fixed current time for presets, open ranges and other times
+    /// relative to now, for reproducible tests (`AW_MCP_NOW`, ISO 8601);
+    /// the system clock when unset
+    pub now: Option<DateTime<Utc>>,
+
+    /// HTTP connection settings
+    pub http: HttpSettings,
+
+    /// Record upstream exchanges to, or replay them from, a file (set from
+    /// the `--record` / `--replay` command-line flags)
+    pub recording: Option<Recording>,
+
+    /// The TOML file this configuration was loaded from (`--config` or
+    /// `AW_MCP_CONFIG`), re-read on reload
+    pub config_file: Option<PathBuf>,
+
+    /// Settings given on the command line, applied again on reload
+    pub overrides: Overrides,
+}
+
+/// Settings given on the command line, which take precedence over the
+/// config file and the environment
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    /// Offer the admin tools (`--admin`)
+    pub admin_tools: bool,
+
+    /// Serve several clients over HTTP on this address (`--listen`)
+    pub listen: Option<String>,
+}
+
+/// HTTP connection pool and protocol settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    /// Request timeout (`AW_MCP_HTTP_TIMEOUT`, in seconds)
+    pub timeout: Duration,
+
+    /// How long idle pooled connections are kept (`AW_MCP_POOL_IDLE_TIMEOUT`,
+    /// in seconds; `0` disables the timeout)
+    pub pool_idle_timeout: Option<Duration>,
+
+    /// Maximum idle connections kept per host (`AW_MCP_POOL_MAX_IDLE_PER_HOST`)
+    pub pool_max_idle_per_host: usize,
+
+    /// Allow HTTP/2 (negotiated over TLS); aw-server itself only speaks
+    /// HTTP/1.1 (`AW_MCP_HTTP2`)
+    pub http2: bool,
+
+    /// Request gzip/deflate compressed responses (`AW_MCP_COMPRESSION`);
+    /// ignored when built without the `compression` feature
+    pub compression: bool,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(DEFAULT_HTTP_TIMEOUT_SECS),
+            pool_idle_timeout: Some(Duration::from_secs(DEFAULT_POOL_IDLE_TIMEOUT_SECS)),
+            pool_max_idle_per_host: DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            http2: false,
+            compression: true,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_version: None,
+            zero_duration_policy: ZeroDurationPolicy::default(),
+            clock_offsets: HashMap::new(),
+            min_coverage: DEFAULT_MIN_COVERAGE,
+            timezone: Timezone::Utc,
+            fiscal_year_start: 1,
+            work_hours: WorkHours::default(),
+            tab_weights: TabWeights::default(),
+            ticket_patterns: vec![
+                Regex::new(DEFAULT_TICKET_PATTERN, false).expect("valid ticket pattern"),
+            ],
+            goals: Vec::new(),
+            distraction_categories: DEFAULT_DISTRACTION_CATEGORIES
+                .iter()
+                .map(|category| category.to_string())
+                .collect(),
+            categories_file: None,
+            category_rules: None,
+            templates_dir: None,
+            api_methods: vec![Method::GET],
+            bucket_cache_ttl: Duration::from_secs(DEFAULT_BUCKET_CACHE_TTL_SECS),
+            cache_dir: None,
+            sync_dir: None,
+            sync_merge: SyncMerge::default(),
+            aggregate_dir: None,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            keepalive_interval: None,
+            snapshot_file: None,
+            snapshot_interval: Duration::from_secs(DEFAULT_SNAPSHOT_INTERVAL_MINUTES * 60),
+            alerts: Vec::new(),
+            alert_interval: Duration::from_secs(DEFAULT_ALERT_INTERVAL_MINUTES * 60),
+            alert_webhook: None,
+            escape_markdown: true,
+            output_style: OutputStyle::default(),
+            duration_unit: None,
+            rounding: Rounding::default(),
+            injection_guard: false,
+            scrub_secrets: true,
+            scrub_emails: false,
+            pseudonymize: false,
+            pseudonym_salt: String::new(),
+            pseudonym_users: Vec::new(),
+            do_not_track: Vec::new(),
+            scope: None,
+            listen: None,
+            tls_cert: None,
+            tls_key: None,
+            client_tokens: Vec::new(),
+            tool_names: ToolNames::default(),
+            admin_tools: false,
+            allow_writes: false,
+            now: None,
+            http: HttpSettings::default(),
+            recording: None,
+            config_file: None,
+            overrides: Overrides::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from environment variables, falling back to defaults
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(env_var)
+    }
+
+    /// Load configuration from a TOML file, with environment variables
+    /// taking precedence over it.
+    ///
+    /// File keys are the environment variable names in lower case without
+    /// the `AW_MCP_` prefix (e.g. `min_coverage = 0.5`, `activitywatch_url`);
+    /// unknown keys are rejected. Settings made of `key=value` entries can
+    /// be tables, and category rules can be given in a `[categories]` table.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read config file {}", path.display()))?;
+        let mut config = Self::from_toml(&text, env_var)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        config.config_file = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Load configuration from the text of a TOML file, with the variables
+    /// of `var` taking precedence over it
+    fn from_toml(text: &str, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let file = file::parse(text).map_err(anyhow::Error::msg)?;
+        let mut config =
+            Self::from_lookup(|name| var(name).or_else(|| file.values.get(name).cloned()))?;
+        // A categories file named in the environment replaces the table
+        if var("AW_MCP_CATEGORIES").is_none() {
+            config.category_rules = file
+                .categories
+                .map(Categories::from_rules)
+                .transpose()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid categories")?;
+        }
+        Ok(config)
+    }
+
+    /// Apply settings given on the command line, which take precedence over
+    /// the config file and the environment
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.admin_tools |= overrides.admin_tools;
+        if let Some(listen) = &overrides.listen {
+            self.listen = Some(listen.clone());
+        }
+        self.overrides = overrides;
+        self
+    }
+
+    /// Load the configuration again from where it originally came from,
+    /// keeping settings made on the command line
+    pub fn reload(&self) -> Result<Self> {
+        let mut config = match &self.config_file {
+            Some(path) => Self::from_file(path)?,
+            None => Self::from_env()?,
+        };
+        config.recording = self.recording.clone();
+        Ok(config.with_overrides(self.overrides.clone()))
+    }
+
+    /// Settings that differ from `other` but only take effect after a restart
+    /// (they are baked into the HTTP client and session)
+    pub fn restart_required(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.base_url != other.base_url {
+            changed.push("ACTIVITYWATCH_URL");
+        }
+        if self.api_version != other.api_version {
+            changed.push("AW_MCP_API_VERSION");
+        }
+        if self.bucket_cache_ttl != other.bucket_cache_ttl {
+            changed.push("AW_MCP_BUCKET_CACHE_TTL");
+        }
+        if self.cache_dir != other.cache_dir {
+            changed.push("AW_MCP_CACHE_DIR");
+        }
+        if self.sync_dir != other.sync_dir {
+            changed.push("AW_MCP_SYNC_DIR");
+        }
+        if self.sync_merge != other.sync_merge {
+            changed.push("AW_MCP_SYNC_MERGE");
+        }
+        if self.listen != other.listen {
+            changed.push("AW_MCP_LISTEN");
+        }
+        if self.tls_cert != other.tls_cert || self.tls_key != other.tls_key {
+            changed.push("AW_MCP_TLS_CERT / AW_MCP_TLS_KEY");
+        }
+        if self.client_tokens != other.client_tokens {
+            changed.push("AW_MCP_TOKENS");
+        }
+        if self.aggregate_dir != other.aggregate_dir {
+            changed.push("AW_MCP_AGGREGATE_DIR");
+        }
+        if self.max_concurrent_requests != other.max_concurrent_requests {
+            changed.push("AW_MCP_MAX_CONCURRENT_REQUESTS");
+        }
+        if self.keepalive_interval != other.keepalive_interval {
+            changed.push("AW_MCP_KEEPALIVE_INTERVAL");
+        }
+        if self.snapshot_file != other.snapshot_file
+            || self.snapshot_interval != other.snapshot_interval
+        {
+            changed.push("AW_MCP_SNAPSHOT / AW_MCP_SNAPSHOT_INTERVAL");
+        }
+        // Rules and the webhook are read at every check, but the job only
+        // runs when rules were configured at startup
+        if self.alerts.is_empty() != other.alerts.is_empty()
+            || self.alert_interval != other.alert_interval
+        {
+            changed.push("AW_MCP_ALERTS / AW_MCP_ALERT_INTERVAL");
+        }
+        if self.do_not_track != other.do_not_track {
+            changed.push("AW_MCP_DO_NOT_TRACK");
+        }
+        if self.now != other.now {
+            changed.push("AW_MCP_NOW");
+        }
+        if self.http != other.http {
+            changed.push("HTTP settings");
+        }
+        if self.tool_names != other.tool_names {
+            changed.push("AW_MCP_TOOL_PREFIX / AW_MCP_TOOL_RENAMES");
+        }
+        if self.admin_tools != other.admin_tools {
+            changed.push("AW_MCP_ADMIN_TOOLS");
+        }
+        changed
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut config = Self::default();
+
+        if let Some(url) = var("ACTIVITYWATCH_URL") {
+            config.base_url = url;
+        }
+        if let Some(version) = var("AW_MCP_API_VERSION")
+            && !version.trim().eq_ignore_ascii_case("auto")
+        {
+            config.api_version = Some(
+                version
+                    .parse()
+                    .map_err(anyhow::Error::msg)
+                    .context("Invalid AW_MCP_API_VERSION")?,
+            );
+        }
+        if let Some(policy) = var("AW_MCP_ZERO_DURATION_POLICY") {
+            config.zero_duration_policy = policy
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_ZERO_DURATION_POLICY")?;
+        }
+
+        if let Some(offsets) = var("AW_MCP_CLOCK_OFFSETS") {
+            config.clock_offsets =
+                parse_clock_offsets(&offsets).context("Invalid AW_MCP_CLOCK_OFFSETS")?;
+        }
+
+        if let Some(min_coverage) = var("AW_MCP_MIN_COVERAGE") {
+            config.min_coverage = min_coverage
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| (0.0..=1.0).contains(v))
+                .context("AW_MCP_MIN_COVERAGE must be a number between 0.0 and 1.0")?;
+        }
+
+        if let Some(timezone) = var("AW_MCP_TIMEZONE") {
+            config.timezone = timezone
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TIMEZONE")?;
+        }
+        if let Some(overrides) = var("AW_MCP_TIMEZONE_OVERRIDES") {
+            config.timezone = config
+                .timezone
+                .with_overrides(&overrides)
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TIMEZONE_OVERRIDES")?;
+        }
+        if let Some(month) = var("AW_MCP_FISCAL_YEAR_START") {
+            config.fiscal_year_start = month
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|m| (1..=12).contains(m))
+                .context("AW_MCP_FISCAL_YEAR_START must be a month number between 1 and 12")?;
+        }
+        if let Some(hours) = var("AW_MCP_WORK_HOURS") {
+            config.work_hours = hours
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_WORK_HOURS")?;
+        }
+        if let Some(weights) = var("AW_MCP_TAB_WEIGHTS") {
+            config.tab_weights = weights
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TAB_WEIGHTS")?;
+        }
+        if let Some(patterns) = var("AW_MCP_TICKET_PATTERNS") {
+            config.ticket_patterns = patterns
+                .split(';')
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .map(|pattern| Regex::new(pattern, false))
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TICKET_PATTERNS")?;
+        }
+        if let Some(goals) = var("AW_MCP_GOALS") {
+            config.goals = goals
+                .split(';')
+                .map(str::trim)
+                .filter(|goal| !goal.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_GOALS")?;
+        }
+        if let Some(categories) = var("AW_MCP_DISTRACTION_CATEGORIES") {
+            config.distraction_categories = categories
+                .split(',')
+                .map(str::trim)
+                .filter(|category| !category.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        config.categories_file = var("AW_MCP_CATEGORIES").map(PathBuf::from);
+        config.templates_dir = var("AW_MCP_TEMPLATES").map(PathBuf::from);
+
+        if let Some(methods) = var("AW_MCP_API_METHODS") {
+            config.api_methods = methods
+                .split(',')
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes()))
+                .collect::<Result<_, _>>()
+                .context("Invalid AW_MCP_API_METHODS")?;
+        }
+
+        if let Some(ttl) = var("AW_MCP_BUCKET_CACHE_TTL") {
+            config.bucket_cache_ttl = Duration::from_secs(
+                ttl.trim()
+                    .parse()
+                    .context("AW_MCP_BUCKET_CACHE_TTL must be a number of seconds")?,
+            );
+        }
+
+        config.cache_dir = var("AW_MCP_CACHE_DIR").map(PathBuf::from);
+        config.sync_dir = var("AW_MCP_SYNC_DIR").map(PathBuf::from);
+        if let Some(policy) = var("AW_MCP_SYNC_MERGE") {
+            config.sync_merge = policy
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_SYNC_MERGE")?;
+        }
+        config.aggregate_dir = var("AW_MCP_AGGREGATE_DIR").map(PathBuf::from);
+        if let Some(max) = var("AW_MCP_MAX_CONCURRENT_REQUESTS") {
+            config.max_concurrent_requests = max
+                .trim()
+                .parse()
+                .context("AW_MCP_MAX_CONCURRENT_REQUESTS must be a non-negative integer")?;
+        }
+        if let Some(interval) = var("AW_MCP_KEEPALIVE_INTERVAL") {
+            let secs: u64 = interval
+                .trim()
+                .parse()
+                .context("AW_MCP_KEEPALIVE_INTERVAL must be a number of seconds")?;
+            config.keepalive_interval = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        config.snapshot_file = var("AW_MCP_SNAPSHOT").map(PathBuf::from);
+        if let Some(interval) = var("AW_MCP_SNAPSHOT_INTERVAL") {
+            let minutes: u64 = interval
+                .trim()
+                .parse()
+                .ok()
+                .filter(|minutes| *minutes > 0)
+                .context("AW_MCP_SNAPSHOT_INTERVAL must be a positive number of minutes")?;
+            config.snapshot_interval = Duration::from_secs(minutes * 60);
+        }
+        if let Some(alerts) = var("AW_MCP_ALERTS") {
+            config.alerts = alerts
+                .split(';')
+                .map(str::trim)
+                .filter(|rule| !rule.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_ALERTS")?;
+        }
+        if let Some(interval) = var("AW_MCP_ALERT_INTERVAL") {
+            let minutes: u64 = interval
+                .trim()
+                .parse()
+                .ok()
+                .filter(|minutes| *minutes > 0)
+                .context("AW_MCP_ALERT_INTERVAL must be a positive number of minutes")?;
+            config.alert_interval = Duration::from_secs(minutes * 60);
+        }
+        if let Some(url) = var("AW_MCP_ALERT_WEBHOOK") {
+            config.alert_webhook =
+                Some(url.trim().parse().context("Invalid AW_MCP_ALERT_WEBHOOK")?);
+        }
+
+        if let Some(escape) = var("AW_MCP_ESCAPE_MARKDOWN") {
+            config.escape_markdown =
+                parse_bool(&escape).context("Invalid AW_MCP_ESCAPE_MARKDOWN")?;
+        }
+
+        if let Some(style) = var("AW_MCP_OUTPUT_STYLE") {
+            config.output_style = style
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_OUTPUT_STYLE")?;
+        }
+
+        if let Some(unit) = var("AW_MCP_DURATION_UNIT") {
+            config.duration_unit = Some(
+                unit.parse()
+                    .map_err(anyhow::Error::msg)
+                    .context("Invalid AW_MCP_DURATION_UNIT")?,
+            );
+        }
+
+        if let Some(rounding) = var("AW_MCP_DURATION_ROUNDING") {
+            config.rounding.duration = rounding
+                .parse()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_DURATION_ROUNDING")?;
+        }
+
+        if let Some(decimals) = var("AW_MCP_PERCENT_DECIMALS") {
+            config.rounding.percent_decimals = decimals
+                .trim()
+                .parse()
+                .ok()
+                .filter(|decimals| *decimals <= 3)
+                .context("Invalid AW_MCP_PERCENT_DECIMALS: expected 0 to 3")?;
+        }
+
+        if let Some(guard) = var("AW_MCP_INJECTION_GUARD") {
+            config.injection_guard =
+                parse_bool(&guard).context("Invalid AW_MCP_INJECTION_GUARD")?;
+        }
+
+        if let Some(scrub) = var("AW_MCP_SCRUB_SECRETS") {
+            config.scrub_secrets = parse_bool(&scrub).context("Invalid AW_MCP_SCRUB_SECRETS")?;
+        }
+        if let Some(emails) = var("AW_MCP_SCRUB_EMAILS") {
+            config.scrub_emails = parse_bool(&emails).context("Invalid AW_MCP_SCRUB_EMAILS")?;
+        }
+
+        if let Some(pseudonymize) = var("AW_MCP_PSEUDONYMIZE") {
+            config.pseudonymize =
+                parse_bool(&pseudonymize).context("Invalid AW_MCP_PSEUDONYMIZE")?;
+        }
+        if let Some(salt) = var("AW_MCP_PSEUDONYM_SALT") {
+            config.pseudonym_salt = salt;
+        }
+        if let Some(users) = var("AW_MCP_PSEUDONYM_USERS") {
+            config.pseudonym_users = users
+                .split(',')
+                .map(str::trim)
+                .filter(|u| !u.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(rules) = var("AW_MCP_DO_NOT_TRACK") {
+            config.do_not_track = rules
+                .split(';')
+                .map(str::trim)
+                .filter(|rule| !rule.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_DO_NOT_TRACK")?;
+        }
+
+        if let Some(scope) = var("AW_MCP_SCOPE") {
+            config.scope = Some(
+                scope
+                    .parse()
+                    .map_err(anyhow::Error::msg)
+                    .context("Invalid AW_MCP_SCOPE")?,
+            );
+        }
+
+        if let Some(listen) = var("AW_MCP_LISTEN") {
+            config.listen = Some(listen.trim().to_string()).filter(|listen| !listen.is_empty());
+        }
+        config.tls_cert = var("AW_MCP_TLS_CERT").map(PathBuf::from);
+        config.tls_key = var("AW_MCP_TLS_KEY").map(PathBuf::from);
+        if let Some(tokens) = var("AW_MCP_TOKENS") {
+            config.client_tokens = tokens
+                .split(';')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TOKENS")?;
+        }
+
+        if let Some(prefix) = var("AW_MCP_TOOL_PREFIX") {
+            config.tool_names = config
+                .tool_names
+                .with_prefix(&prefix)
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TOOL_PREFIX")?;
+        }
+        if let Some(renames) = var("AW_MCP_TOOL_RENAMES") {
+            config.tool_names = config
+                .tool_names
+                .with_renames(&renames)
+                .map_err(anyhow::Error::msg)
+                .context("Invalid AW_MCP_TOOL_RENAMES")?;
+        }
+
+        if let Some(admin) = var("AW_MCP_ADMIN_TOOLS") {
+            config.admin_tools = parse_bool(&admin).context("Invalid AW_MCP_ADMIN_TOOLS")?;
+        }
+        if let Some(writes) = var("AW_MCP_ALLOW_WRITES") {
+            config.allow_writes = parse_bool(&writes).context("Invalid AW_MCP_ALLOW_WRITES")?;
+        }
+        if let Some(now) = var("AW_MCP_NOW") {
+            config.now = Some(
+                parse_timestamp(&now)
+                    .map_err(anyhow::Error::msg)
+                    .context("Invalid AW_MCP_NOW")?,
+            );
+        }
+
+        if let Some(timeout) = var("AW_MCP_HTTP_TIMEOUT") {
+            config.http.timeout = Duration::from_secs(
+                timeout
+                    .trim()
+                    .parse()
+                    .context("AW_MCP_HTTP_TIMEOUT must be a number of seconds")?,
+            );
+        }
+        if let Some(idle) = var("AW_MCP_POOL_IDLE_TIMEOUT") {
+            let secs: u64 = idle
+                .trim()
+                .parse()
+                .context("AW_MCP_POOL_IDLE_TIMEOUT must be a number of seconds")?;
+            config.http.pool_idle_timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        if let Some(max_idle) = var("AW_MCP_POOL_MAX_IDLE_PER_HOST") {
+            config.http.pool_max_idle_per_host = max_idle
+                .trim()
+                .parse()
+                .context("AW_MCP_POOL_MAX_IDLE_PER_HOST must be a non-negative integer")?;
+        }
+        if let Some(http2) = var("AW_MCP_HTTP2") {
+            config.http.http2 = parse_bool(&http2).context("Invalid AW_MCP_HTTP2")?;
+        }
+        if let Some(compression) = var("AW_MCP_COMPRESSION") {
+            config.http.compression =
+                parse_bool(&compression).context("Invalid AW_MCP_COMPRESSION")?;
+        }
+
+        Ok(config)
+    }
+
+    /// Clock offset in seconds for a host (zero when not configured)
+    pub fn clock_offset(&self, hostname: &str) -> f64 {
+        self.clock_offsets.get(hostname).copied().unwrap_or(0.0)
+    }
+}
+
+/// Parse a boolean flag such as `true`, `off` or `1`
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        other => anyhow::bail!("Expected true/false, got '{}'", other),
+    }
+}
+
+/// Parse `host=seconds` pairs separated by commas
+fn parse_clock_offsets(value: &str) -> Result<HashMap<String, f64>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (host, secs) = pair
+                .split_once('=')
+                .with_context(|| format!("Expected host=seconds, got '{}'", pair))?;
+            let secs: f64 = secs
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid offset for host '{}'", host.trim()))?;
+            Ok((host.trim().to_string(), secs))
+        })
+        .collect()
+}
+
+/// Read a non-empty environment variable
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn environment_overrides_file_and_command_line_overrides_both() {
+        let file = r#"
+            min_coverage = 0.5
+            output_style = "plain"
+            listen = "127.0.0.1:8000"
+
+            [categories]
+            Work = "Code"
+        "#;
+        let config = Config::from_toml(file, lookup(&[])).unwrap();
+        assert_eq!(config.min_coverage, 0.5);
+        assert_eq!(config.listen.as_deref(), Some("127.0.0.1:8000"));
+        assert!(config.category_rules.is_some());
+
+        let env = lookup(&[
+            ("AW_MCP_MIN_COVERAGE", "0.75"),
+            ("AW_MCP_LISTEN", "127.0.0.1:9000"),
+            ("AW_MCP_CATEGORIES", "/etc/categories.toml"),
+        ]);
+        let config = Config::from_toml(file, env).unwrap();
+        assert_eq!(config.min_coverage, 0.75);
+        assert_eq!(config.output_style, OutputStyle::Plain);
+        assert_eq!(config.listen.as_deref(), Some("127.0.0.1:9000"));
+        assert!(config.category_rules.is_none());
+
+        let config = config.with_overrides(Overrides {
+            admin_tools: true,
+            listen: Some("127.0.0.1:9100".to_string()),
+        });
+        assert!(config.admin_tools);
+        assert_eq!(config.listen.as_deref(), Some("127.0.0.1:9100"));
+    }
+
+    #[test]
+    fn rejects_invalid_keys_and_values() {
+        let error = Config::from_toml("min_coverge = 0.5", lookup(&[])).unwrap_err();
+        assert!(format!("{:#}", error).contains("min_coverge"));
+        assert!(Config::from_toml("[categories]\nWork = \"(\"", lookup(&[])).is_err());
+        assert!(Config::from_toml("min_coverage = \"lots\"", lookup(&[])).is_err());
+        assert!(Config::from_lookup(lookup(&[("AW_MCP_MIN_COVERAGE", "lots")])).is_err());
+    }
+}
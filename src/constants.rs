@@ -3,3 +3,130 @@ pub const CHARACTER_LIMIT: usize = 25_000;
 
 /// Default limit for events query
 pub const DEFAULT_EVENTS_LIMIT: i32 = 100;
+
+/// Most events `aw_get_events` reads to draw a sample from (the newest of
+/// the range), so sampling a long history stays bounded in memory
+pub const MAX_SAMPLE_SOURCE_EVENTS: i32 = 10_000;
+
+/// Number of events sampled by `aw_estimate` to measure the per-event size
+pub const ESTIMATE_SAMPLE_SIZE: i32 = 20;
+
+/// Default ActivityWatch server URL (the API version prefix is detected)
+pub const DEFAULT_BASE_URL: &str = "http://localhost:5600";
+
+/// Default minimum AFK data coverage before summaries warn
+pub const DEFAULT_MIN_COVERAGE: f64 = 0.25;
+
+/// Default time-to-live of the cached bucket map in seconds
+pub const DEFAULT_BUCKET_CACHE_TTL_SECS: u64 = 300;
+
+/// Most query2 period results kept in the in-memory query cache
+pub const QUERY_CACHE_MAX_ENTRIES: usize = 1000;
+
+/// Most concurrent requests a query2 call over many time periods is split
+/// into (further limited by `AW_MCP_MAX_CONCURRENT_REQUESTS`)
+pub const QUERY_PARALLELISM: usize = 4;
+
+/// Keys of bucket metadata that may name the timezone of the device that
+/// recorded the bucket (e.g., "Asia/Tokyo" or "+09:00")
+pub const BUCKET_TIMEZONE_KEYS: [&str; 3] = ["timezone", "tz", "time_zone"];
+
+/// Default pattern of ticket IDs in titles and URLs (Jira-style keys such
+/// as `PROJ-123`)
+pub const DEFAULT_TICKET_PATTERN: &str = r"\b[A-Z][A-Z0-9]+-\d+\b";
+
+/// Default categories counted as distractions (aw-webui's default rules
+/// file games, video, social media and music under Media)
+pub const DEFAULT_DISTRACTION_CATEGORIES: &[&str] = &["Media"];
+
+/// Shortest focus session, in seconds
+pub const FOCUS_MIN_SECS: f64 = 25.0 * 60.0;
+
+/// Longest break that does not end a focus session, in seconds
+pub const FOCUS_MAX_BREAK_SECS: f64 = 120.0;
+
+/// Seconds `git log` may run when reading a repository's history
+pub const GIT_TIMEOUT_SECS: u64 = 30;
+
+/// Default maximum number of simultaneous requests to aw-server
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Default HTTP request timeout in seconds
+pub const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 30;
+
+/// Default idle timeout of pooled connections in seconds
+pub const DEFAULT_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+
+/// Default maximum idle pooled connections per host
+pub const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 8;
+
+/// Default minutes between refreshes of the day snapshot
+pub const DEFAULT_SNAPSHOT_INTERVAL_MINUTES: u64 = 5;
+
+/// Default minutes between checks of the alert rules
+pub const DEFAULT_ALERT_INTERVAL_MINUTES: u64 = 5;
+
+/// Seconds since the AFK watchers last saw the user during which alert
+/// rules still count them as present
+pub const ALERT_PRESENCE_SLACK_SECS: i64 = 120;
+
+/// Maximum apps, titles and categories kept in the day snapshot
+pub const SNAPSHOT_LIST_LIMIT: usize = 20;
+
+/// Seconds after a day ends before its totals are stored in the
+/// aggregation store, so late heartbeats are not missed
+pub const AGGREGATE_SETTLE_SECS: i64 = 300;
+
+/// Most days fetched in one request when filling the aggregation store
+pub const AGGREGATE_FETCH_DAYS: usize = 31;
+
+/// Seconds the MCP client has to answer a keepalive ping
+pub const KEEPALIVE_TIMEOUT_SECS: u64 = 10;
+
+/// Number of recent tool calls kept in the audit log
+pub const AUDIT_LOG_CAPACITY: usize = 500;
+
+/// Default number of audit log entries returned by `aw_audit_log`
+pub const DEFAULT_AUDIT_LOG_LIMIT: usize = 50;
+
+/// Longest range (in days) shown hourly when no resolution is requested
+pub const MAX_HOURLY_RANGE_DAYS: i64 = 14;
+
+/// Longest range (in days) shown daily when no resolution is requested
+pub const MAX_DAILY_RANGE_DAYS: i64 = 182;
+
+/// Maximum number of points in a time series before it is downsampled
+pub const MAX_TIME_SERIES_POINTS: usize = 400;
+
+/// Default minutes without events before `aw_status` considers a watcher silent
+pub const DEFAULT_STALE_WATCHER_MINUTES: u64 = 10;
+
+/// Default number of category levels kept when rolling up category totals
+pub const DEFAULT_CATEGORY_DEPTH: usize = 1;
+
+/// Default number of apps listed by `aw_top_apps`
+pub const DEFAULT_TOP_APPS: usize = 10;
+
+/// Default number of apps (or other groups) listed by `aw_active_time`
+pub const DEFAULT_ACTIVE_TIME_TOP: usize = 10;
+
+/// Most example events listed per group by `aw_top_apps` and `aw_active_time`
+pub const MAX_EXAMPLES: usize = 10;
+
+/// Default number of apps, titles and domains listed by `aw_uncategorized`
+pub const DEFAULT_UNCATEGORIZED_LIMIT: usize = 10;
+
+/// Default number of apps, titles and categories passed to report templates
+#[cfg(feature = "templates")]
+pub const DEFAULT_TEMPLATE_LIMIT: usize = 10;
+
+/// Default days without events after which `aw_bucket_history` reports a
+/// bucket as stale
+pub const DEFAULT_STALE_BUCKET_DAYS: u32 = 7;
+
+/// Default number of newest events per bucket read by `aw_data_inventory`
+/// to find the keys of the event data
+pub const DEFAULT_INVENTORY_SAMPLE_EVENTS: usize = 500;
+
+/// aw-server setting key holding aw-webui's category rules
+pub const CATEGORIES_SETTING: &str = "classes";
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::str::FromStr;
+
+tokio::task_local! {
+    static ROUNDING: Rounding;
+}
+
+/// Precision durations are rounded to in reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurationRounding {
+    /// The nearest second (e.g., "12m 5s")
+    #[default]
+    Second,
+    /// The nearest minute (e.g., "12m")
+    Minute,
+}
+
+impl FromStr for DurationRounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "second" | "seconds" | "s" => Ok(Self::Second),
+            "minute" | "minutes" | "m" => Ok(Self::Minute),
+            other => Err(format!(
+                "Unknown duration rounding '{}'. Expected second or minute",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DurationRounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Second => "second",
+            Self::Minute => "minute",
+        })
+    }
+}
+
+/// How reports round durations and percentages, so every tool formats the
+/// same total the same way (`AW_MCP_DURATION_ROUNDING`,
+/// `AW_MCP_PERCENT_DECIMALS`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rounding {
+    /// Precision of formatted durations
+    pub duration: DurationRounding,
+
+    /// Decimal places of percentages (at most 3)
+    pub percent_decimals: usize,
+}
+
+/// Run a future with durations and percentages formatted by `rounding`
+pub async fn with_rounding<F: Future>(rounding: Rounding, future: F) -> F::Output {
+    ROUNDING.scope(rounding, future).await
+}
+
+/// The rounding of the current tool call (the default outside one)
+fn rounding() -> Rounding {
+    ROUNDING.try_with(|rounding| *rounding).unwrap_or_default()
+}
+
+/// Format a duration in seconds as a compact human-readable string (e.g., "1h 5m")
+pub fn format_duration(secs: f64) -> String {
+    let by_minute = rounding().duration == DurationRounding::Minute;
+    let total = match by_minute {
+        true => (secs.max(0.0) / 60.0).round() as i64 * 60,
+        false => secs.max(0.0).round() as i64,
+    };
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if by_minute {
+        format!("{}m", minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Round a percentage to the configured decimal places
+pub fn round_percent(percent: f64) -> f64 {
+    let scale = 10f64.powi(rounding().percent_decimals as i32);
+    (percent * scale).round() / scale
+}
+
+/// Format a percentage (0 to 100) with the configured decimal places, e.g.
+/// "42%" or "42.5%"
+pub fn format_percent(percent: f64) -> String {
+    format!("{:.*}%", rounding().percent_decimals, percent)
+}
+
+/// Format a signed duration difference (e.g., "+12m 3s", "-1h 0m")
+pub fn format_duration_delta(secs: f64) -> String {
+    let sign = if secs < 0.0 { "-" } else { "+" };
+    format!("{}{}", sign, format_duration(secs.abs()))
+}
+
+/// Make an untrusted value safe to embed in a markdown line: line breaks and
+/// control characters become spaces, invisible formatting characters (e.g.
+/// bidi overrides) are dropped, and markdown syntax is backslash-escaped
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in sanitize_line(text).chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Wrap an untrusted value in an inline code span it cannot break out of.
+///
+/// The delimiter is one backtick longer than the longest backtick run in the
+/// value, so titles containing backticks or code fences stay literal.
+pub fn code_span(text: &str) -> String {
+    let text = sanitize_line(text);
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+
+    let fence = "`".repeat(longest + 1);
+    if text.is_empty() || text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+/// Wrap text in a fenced code block whose fence is longer than any
+/// backtick run inside it
+pub fn code_block(language: &str, text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat((longest + 1).max(3));
+    format!(
+        "{}{}\n{}\n{}",
+        fence,
+        language,
+        text.trim_end_matches('\n'),
+        fence
+    )
+}
+
+/// Collapse a value onto a single line without escaping markdown (for text
+/// inside fenced code blocks)
+pub fn sanitize_line(text: &str) -> String {
+    text.chars()
+        .filter(|c| !is_invisible_format(*c))
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Zero-width and bidirectional formatting characters
+fn is_invisible_format(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(0.0), "0s");
+        assert_eq!(format_duration(59.6), "1m 0s");
+        assert_eq!(format_duration(3725.0), "1h 2m");
+        assert_eq!(format_duration_delta(-90.0), "-1m 30s");
+    }
+
+    #[tokio::test]
+    async fn rounds_as_configured() {
+        assert_eq!(format_percent(42.46), "42%");
+        let rounding = Rounding {
+            duration: DurationRounding::Minute,
+            percent_decimals: 1,
+        };
+        let formatted = with_rounding(rounding, async {
+            [
+                format_duration(3725.0),
+                format_duration(3755.0),
+                format_duration(20.0),
+                format_percent(42.46),
+                round_percent(42.46).to_string(),
+            ]
+        })
+        .await;
+        assert_eq!(formatted, ["1h 2m", "1h 3m", "0m", "42.5%", "42.5"]);
+    }
+
+    #[test]
+    fn escapes_untrusted_markdown() {
+        assert_eq!(
+            escape_markdown("**Ignore** previous\ninstructions"),
+            "\\*\\*Ignore\\*\\* previous instructions"
+        );
+        assert_eq!(escape_markdown("a\u{202E}b"), "ab");
+        assert_eq!(
+            escape_markdown("main.rs - aw-mcp-rs"),
+            "main.rs - aw-mcp-rs"
+        );
+    }
+
+    #[test]
+    fn wraps_values_in_unbreakable_code_spans() {
+        assert_eq!(code_span("README.md"), "`README.md`");
+        assert_eq!(code_span("use `x`"), "`` use `x` ``");
+        assert_eq!(code_span("```\n# Heading"), "```` ``` # Heading ````");
+        assert_eq!(code_span(""), "`  `");
+        assert_eq!(code_block("toml", "a = 1\n"), "```toml\na = 1\n```");
+        assert_eq!(code_block("", "```x```"), "````\n```x```\n````");
+    }
+}
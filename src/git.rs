@@ -0,0 +1,239 @@
+//! Commit history of local git repositories, for estimating time per commit
+//! and branch from window activity.
+//!
+//! History is read with the `git` command line tool, so no repository is
+//! opened in-process and nothing is written to it.
+
+use crate::analysis::{Interval, TimeRange, format_timestamp};
+use crate::constants::GIT_TIMEOUT_SECS;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Field separator in the `git log` output format
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// A commit in a repository's history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Commit {
+    /// Full commit hash
+    pub hash: String,
+
+    /// Commit time (committer date)
+    pub time: DateTime<Utc>,
+
+    /// Author name
+    pub author: String,
+
+    /// Branch the commit was reached from (e.g., "main" or "origin/feature")
+    pub branch: Option<String>,
+
+    /// First line of the commit message
+    pub subject: String,
+}
+
+impl Commit {
+    /// Abbreviated hash
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(8)]
+    }
+}
+
+/// Read the non-merge commits of all branches committed within a range,
+/// oldest first, optionally only those by an author (a `git log --author`
+/// pattern)
+pub async fn read_commits(
+    repo: &Path,
+    range: &TimeRange,
+    author: Option<&str>,
+) -> Result<Vec<Commit>, String> {
+    let mut command = tokio::process::Command::new("git");
+    command
+        .arg("-C")
+        .arg(repo)
+        .args(["log", "--all", "--source", "--no-merges", "--reverse"])
+        .arg(format!(
+            "--format=%H{0}%ct{0}%an{0}%S{0}%s",
+            FIELD_SEPARATOR
+        ))
+        .arg(format!("--since={}", format_timestamp(&range.start)))
+        .arg(format!("--until={}", format_timestamp(&range.end)));
+    if let Some(author) = author {
+        command.arg(format!("--author={}", author));
+    }
+    command.kill_on_drop(true);
+
+    let output = tokio::time::timeout(Duration::from_secs(GIT_TIMEOUT_SECS), command.output())
+        .await
+        .map_err(|_| format!("git log timed out after {}s", GIT_TIMEOUT_SECS))?
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git log failed in {}: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut commits = parse_log(&String::from_utf8_lossy(&output.stdout));
+    // --since/--until are approximate on histories with skewed dates
+    commits.retain(|commit| (range.start..range.end).contains(&commit.time));
+    commits.sort_by_key(|commit| commit.time);
+    Ok(commits)
+}
+
+/// Parse `git log` output in the format used by [`read_commits`], skipping
+/// malformed lines
+pub fn parse_log(output: &str) -> Vec<Commit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, FIELD_SEPARATOR);
+            let hash = fields.next()?.trim();
+            let time = DateTime::from_timestamp(fields.next()?.trim().parse().ok()?, 0)?;
+            let author = fields.next()?;
+            let branch = fields.next()?;
+            let subject = fields.next().unwrap_or("");
+            (!hash.is_empty()).then(|| Commit {
+                hash: hash.to_string(),
+                time,
+                author: author.to_string(),
+                branch: branch_name(branch),
+                subject: subject.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Branch name of a `--source` ref such as `refs/heads/main`
+fn branch_name(source: &str) -> Option<String> {
+    let name = source
+        .strip_prefix("refs/heads/")
+        .or_else(|| source.strip_prefix("refs/remotes/"))
+        .unwrap_or(source);
+    (!name.is_empty() && name != "HEAD").then(|| name.to_string())
+}
+
+/// Estimated time spent on one commit
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitTime {
+    /// The commit
+    #[serde(flatten)]
+    pub commit: Commit,
+
+    /// Seconds of matching activity since the previous commit (or the start
+    /// of the range)
+    pub secs: f64,
+}
+
+/// Time per commit and branch estimated from activity
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CommitCorrelation {
+    /// Commits oldest first, each with the activity leading up to it
+    pub commits: Vec<CommitTime>,
+
+    /// Seconds and commits per branch ("(unknown)" when git could not tell),
+    /// by name
+    pub branches: BTreeMap<String, (f64, usize)>,
+
+    /// Seconds of matching activity after the last commit
+    pub uncommitted_secs: f64,
+
+    /// Seconds of matching activity in the whole range
+    pub total_secs: f64,
+}
+
+/// Attribute activity to commits: each commit gets the activity between the
+/// previous commit (or the start of the range) and its own time.
+///
+/// `activity` are merged intervals of activity on the repository, e.g.
+/// window events whose title names the project.
+pub fn correlate_commits(
+    commits: &[Commit],
+    activity: &[Interval],
+    range: &TimeRange,
+) -> CommitCorrelation {
+    let secs_between = |start: DateTime<Utc>, end: DateTime<Utc>| -> f64 {
+        activity
+            .iter()
+            .filter_map(|&(a_start, a_end)| {
+                let (s, e) = (a_start.max(start), a_end.min(end));
+                (e > s).then(|| (e - s).num_milliseconds() as f64 / 1000.0)
+            })
+            .sum()
+    };
+
+    let mut correlation = CommitCorrelation::default();
+    let mut since = range.start;
+    for commit in commits {
+        let secs = secs_between(since, commit.time);
+        let branch = commit
+            .branch
+            .clone()
+            .unwrap_or_else(|| "(unknown)".to_string());
+        let entry = correlation.branches.entry(branch).or_default();
+        entry.0 += secs;
+        entry.1 += 1;
+        correlation.commits.push(CommitTime {
+            commit: commit.clone(),
+            secs,
+        });
+        since = since.max(commit.time);
+    }
+    correlation.uncommitted_secs = secs_between(since, range.end);
+    correlation.total_secs = secs_between(range.start, range.end);
+    correlation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn parses_git_log_output() {
+        let output = "abcdef1234567890\u{1f}1710493200\u{1f}Ada\u{1f}refs/heads/feature/x\
+                      \u{1f}Add parser\u{1f}with separator\n\
+                      garbage\n\
+                      0123456789abcdef\u{1f}1710496800\u{1f}Bob\u{1f}refs/remotes/origin/main\
+                      \u{1f}Fix\n";
+        let commits = parse_log(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].short_hash(), "abcdef12");
+        assert_eq!(
+            commits[0].time,
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap()
+        );
+        assert_eq!(commits[0].branch.as_deref(), Some("feature/x"));
+        assert_eq!(commits[0].subject, "Add parser\u{1f}with separator");
+        assert_eq!(commits[1].branch.as_deref(), Some("origin/main"));
+    }
+
+    #[test]
+    fn attributes_activity_to_the_next_commit() {
+        let at =
+            |hour: i64| Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap() + Duration::hours(hour);
+        let range = TimeRange::new(at(8), at(18)).unwrap();
+        let commit = |hour: i64, branch: &str| Commit {
+            hash: format!("{:040}", hour),
+            time: at(hour),
+            author: "Ada".to_string(),
+            branch: Some(branch.to_string()),
+            subject: String::new(),
+        };
+        let commits = [
+            commit(10, "main"),
+            commit(12, "feature"),
+            commit(13, "feature"),
+        ];
+        let activity = [(at(9), at(11)), (at(14), at(15))];
+
+        let correlation = correlate_commits(&commits, &activity, &range);
+        let secs: Vec<f64> = correlation.commits.iter().map(|c| c.secs).collect();
+        assert_eq!(secs, [3600.0, 3600.0, 0.0]);
+        assert_eq!(correlation.branches["feature"], (3600.0, 2));
+        assert_eq!(correlation.uncommitted_secs, 3600.0);
+        assert_eq!(correlation.total_secs, 10800.0);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::scope::ClientToken;
+use crate::tools::ActivityWatchMcpServer;
+use anyhow::{Context, bail};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full, combinators::BoxBody};
+use hyper::body::Incoming;
+use hyper::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
+use rmcp::transport::streamable_http_server::{StreamableHttpServerConfig, StreamableHttpService};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::ring;
+
+/// Path of the MCP endpoint
+pub const MCP_PATH: &str = "/mcp";
+
+type Body = BoxBody<Bytes, Infallible>;
+type McpService = StreamableHttpService<ActivityWatchMcpServer, LocalSessionManager>;
+
+/// Serve MCP over streamable HTTP to several clients until the process ends.
+///
+/// Clients send `Authorization: Bearer <token>` with every request to
+/// [`MCP_PATH`]; the token's scope (`Config::client_tokens`) limits what
+/// that client sees, and each token has its own MCP sessions. Unknown tokens
+/// get `401 Unauthorized`. With a certificate and key (`Config::tls_cert`,
+/// `Config::tls_key`) connections use TLS; plain HTTP is only served on
+/// loopback addresses, e.g. behind a TLS-terminating proxy.
+pub async fn serve_http(
+    server: ActivityWatchMcpServer,
+    listener: TcpListener,
+) -> anyhow::Result<()> {
+    let config = server.config();
+    let tls = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => Some(tls_acceptor(cert, key)?),
+        (None, None) => None,
+        _ => bail!("AW_MCP_TLS_CERT and AW_MCP_TLS_KEY must be set together"),
+    };
+    let address = listener.local_addr()?;
+    if tls.is_none() && !address.ip().is_loopback() {
+        bail!(
+            "Refusing to send tokens in cleartext on {}: set AW_MCP_TLS_CERT and AW_MCP_TLS_KEY, \
+             or listen on a loopback address behind a TLS proxy",
+            address
+        );
+    }
+
+    let endpoint = Arc::new(Endpoint::new(&server));
+    loop {
+        let (socket, peer) = listener
+            .accept()
+            .await
+            .context("Failed to accept a connection")?;
+        let endpoint = endpoint.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let result = match tls {
+                Some(tls) => match tls.accept(socket).await {
+                    Ok(stream) => serve_connection(endpoint, stream).await,
+                    Err(e) => Err(e.into()),
+                },
+                None => serve_connection(endpoint, socket).await,
+            };
+            if let Err(e) = result {
+                tracing::debug!(%peer, error = %e, "Connection closed");
+            }
+        });
+    }
+}
+
+/// Serve HTTP requests on one connection
+async fn serve_connection(
+    endpoint: Arc<Endpoint>,
+    stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static,
+) -> anyhow::Result<()> {
+    let service = hyper::service::service_fn(move |request| {
+        let endpoint = endpoint.clone();
+        async move { Ok::<_, Infallible>(endpoint.handle(request).await) }
+    });
+    hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .await?;
+    Ok(())
+}
+
+/// Load a PEM certificate chain and private key for serving TLS
+fn tls_acceptor(cert: &Path, key: &Path) -> anyhow::Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Cannot read TLS certificate {}", cert.display()))?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .with_context(|| format!("Cannot read TLS key {}", key.display()))?;
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("Invalid TLS certificate or key")?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// The MCP services of the known tokens, each serving the token's scope
+struct Endpoint {
+    tokens: Vec<ClientToken>,
+    services: Vec<McpService>,
+}
+
+impl Endpoint {
+    fn new(server: &ActivityWatchMcpServer) -> Self {
+        let tokens = server.config().client_tokens.clone();
+        let services = tokens
+            .iter()
+            .map(|client| {
+                let server = server.clone();
+                let scope = client.scope.clone();
+                StreamableHttpService::new(
+                    move || Ok(server.for_client(scope.clone())),
+                    Default::default(),
+                    StreamableHttpServerConfig::default(),
+                )
+            })
+            .collect();
+        Self { tokens, services }
+    }
+
+    /// Authenticate a request and hand it to its token's MCP service
+    async fn handle(&self, request: Request<Incoming>) -> Response<Body> {
+        if request.uri().path() != MCP_PATH {
+            return text_response(StatusCode::NOT_FOUND, "Not found");
+        }
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        match token_index(&self.tokens, token.trim()) {
+            Some(index) => self.services[index].handle(request).await,
+            None => {
+                tracing::warn!("Refused a request with a missing or unknown token");
+                let mut response = text_response(StatusCode::UNAUTHORIZED, "Unknown token");
+                response
+                    .headers_mut()
+                    .insert(WWW_AUTHENTICATE, "Bearer".parse().expect("valid header"));
+                response
+            }
+        }
+    }
+}
+
+fn text_response(status: StatusCode, text: &'static str) -> Response<Body> {
+    let mut response = Response::new(Full::new(Bytes::from(text)).boxed());
+    *response.status_mut() = status;
+    response
+}
+
+/// The position of the client with the given token, if the token is known.
+/// Tokens are compared in constant time.
+fn token_index(tokens: &[ClientToken], token: &str) -> Option<usize> {
+    let equal = |known: &str| {
+        known.len() == token.len()
+            && known
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    };
+    tokens.iter().position(|client| equal(&client.token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::ActivityWatchClient;
+    use crate::config::Config;
+
+    #[test]
+    fn finds_tokens() {
+        let tokens: Vec<ClientToken> = ["alice=host:alice-laptop", "bob=host:bob-*"]
+            .iter()
+            .map(|entry| entry.parse().unwrap())
+            .collect();
+        assert_eq!(token_index(&tokens, "bob"), Some(1));
+        assert_eq!(token_index(&tokens, "bo"), None);
+        assert_eq!(token_index(&tokens, ""), None);
+    }
+
+    async fn post(address: std::net::SocketAddr, path: &str, token: Option<&str>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26","capabilities":{},"clientInfo":{"name":"test","version":"1"}}}"#;
+        let auth = token.map_or(String::new(), |token| {
+            format!("Authorization: Bearer {}\r\n", token)
+        });
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Type: application/json\r\n\
+             Accept: application/json, text/event-stream\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            path,
+            auth,
+            body.len(),
+            body
+        );
+        let mut socket = tokio::net::TcpStream::connect(address).await.unwrap();
+        socket.write_all(request.as_bytes()).await.unwrap();
+        let mut status = [0; 12];
+        socket.read_exact(&mut status).await.unwrap();
+        String::from_utf8_lossy(&status[9..12]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn accepts_only_known_tokens() {
+        let config = Config {
+            client_tokens: vec!["alice=host:alice-laptop".parse().unwrap()],
+            ..Config::default()
+        };
+        let server = ActivityWatchMcpServer::new(ActivityWatchClient::new("http://127.0.0.1:9"))
+            .with_config(config);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve_http(server, listener));
+
+        assert_eq!(post(address, MCP_PATH, Some("alice")).await, "200");
+        assert_eq!(post(address, MCP_PATH, Some("bob")).await, "401");
+        assert_eq!(post(address, MCP_PATH, None).await, "401");
+        assert_eq!(post(address, "/other", Some("alice")).await, "404");
+    }
+
+    #[tokio::test]
+    async fn refuses_cleartext_on_public_addresses() {
+        let server = ActivityWatchMcpServer::new(ActivityWatchClient::new("http://127.0.0.1:9"));
+        let listener = TcpListener::bind("0.0.0.0:0").await.unwrap();
+        let error = serve_http(server, listener).await.unwrap_err();
+        assert!(error.to_string().contains("cleartext"));
+    }
+}
@@ -1,8 +1,64 @@
+mod aggregates;
+mod analysis;
 mod api;
+mod audit;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod categories;
+mod clock;
+mod config;
 mod constants;
+mod format;
+mod git;
+mod guard;
+#[cfg(feature = "http")]
+mod http;
+mod meta;
 mod models;
+mod naming;
+mod privacy;
+mod pseudonym;
+mod query2;
+mod scope;
+mod scrub;
+mod selftest;
+mod session;
+mod snapshot;
+mod style;
+#[cfg(feature = "templates")]
+mod template;
+#[cfg(feature = "test-support")]
+pub mod testing;
+mod timezone;
 mod tools;
+mod units;
 
-pub use api::ActivityWatchClient;
+pub use aggregates::{AggregateStore, BucketAggregates, DayTotals};
+pub use analysis::*;
+pub use api::{
+    ActivityWatchClient, ApiVersion, AwServerError, BucketCacheStats, BucketMap, BucketRefresh,
+    CacheClear, CacheStats, QueryCacheStats, Recording, StoreStats, SyncMerge, Tombstone,
+};
+pub use audit::{AuditEntry, AuditLog};
+pub use categories::*;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use config::{Config, HttpSettings, Overrides};
+pub use format::{DurationRounding, Rounding};
+pub use git::{Commit, CommitCorrelation, CommitTime, correlate_commits, parse_log};
+#[cfg(feature = "http")]
+pub use http::{MCP_PATH, serve_http};
+pub use meta::{ResponseMeta, new_correlation_id};
 pub use models::*;
+pub use naming::{DEFAULT_TOOL_PREFIX, ToolNames};
+pub use privacy::{DoNotTrack, DoNotTrackRule, including_private};
+pub use query2::{LOCAL_FUNCTIONS, QueryData, QueryScript};
+pub use scope::{AccessScope, ClientToken};
+pub use selftest::{CheckResult, SelfTestReport, run_self_test};
+pub use session::{EXIT_CLIENT_UNRESPONSIVE, EXIT_TRANSPORT_CLOSED, SessionEnd, serve_stdio};
+pub use snapshot::{DaySnapshot, SnapshotBucket, SnapshotEntry};
+pub use style::OutputStyle;
+#[cfg(feature = "templates")]
+pub use template::Template;
+pub use timezone::{DateOverride, PosixRule, Timezone};
 pub use tools::ActivityWatchMcpServer;
+pub use units::DurationUnit;
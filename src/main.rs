@@ -1,11 +1,87 @@
-use aw_mcp_server::{ActivityWatchClient, ActivityWatchMcpServer};
-use rmcp::transport::stdio;
-use rmcp::ServiceExt;
-use std::env;
+use anyhow::Context;
+use aw_mcp_server::{
+    ActivityWatchClient, ActivityWatchMcpServer, AlertMonitor, Config, Overrides, Recording,
+    SessionEnd, run_self_test, serve_stdio,
+};
+use std::path::PathBuf;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Command-line options
+#[derive(Debug, Default)]
+struct Args {
+    /// Run diagnostics against aw-server and exit
+    self_test: bool,
+
+    /// Keep accepting new stdio clients this long after a disconnect
+    linger: Option<Duration>,
+
+    /// Record all upstream requests and responses to this file
+    record: Option<PathBuf>,
+
+    /// Answer upstream requests from this recording instead of aw-server
+    replay: Option<PathBuf>,
+
+    /// TOML configuration file (overrides `AW_MCP_CONFIG`)
+    config: Option<PathBuf>,
+
+    /// Offer the admin tools
+    admin: bool,
+
+    /// Serve several clients over HTTP on this address (overrides `AW_MCP_LISTEN`)
+    listen: Option<String>,
+}
+
+impl Args {
+    fn parse() -> anyhow::Result<Self> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            match flag.as_str() {
+                "--self-test" => args.self_test = true,
+                "--admin" => args.admin = true,
+                "--linger" => {
+                    let secs: u64 = inline
+                        .or_else(|| iter.next())
+                        .context("--linger requires a number of seconds")?
+                        .parse()
+                        .context("--linger must be a number of seconds")?;
+                    args.linger = (secs > 0).then(|| Duration::from_secs(secs));
+                }
+                "--listen" => {
+                    let address = inline
+                        .or_else(|| iter.next())
+                        .context("--listen requires an address")?;
+                    args.listen = Some(address);
+                }
+                "--record" | "--replay" | "--config" => {
+                    let path = inline
+                        .or_else(|| iter.next())
+                        .with_context(|| format!("{} requires a file path", flag))?;
+                    match flag.as_str() {
+                        "--record" => args.record = Some(path.into()),
+                        "--replay" => args.replay = Some(path.into()),
+                        _ => args.config = Some(path.into()),
+                    }
+                }
+                other => anyhow::bail!("Unknown argument '{}'", other),
+            }
+        }
+        if args.record.is_some() && args.replay.is_some() {
+            anyhow::bail!("--record and --replay cannot be combined");
+        }
+        Ok(args)
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args = Args::parse()?;
+
     // Initialize tracing (logs to stderr so it doesn't interfere with stdio transport)
     tracing_subscriber::registry()
         .with(
@@ -15,24 +91,159 @@ async fn main() -> anyhow::Result<()> {
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
-    // Get ActivityWatch server URL from environment (optional)
-    let base_url = env::var("ACTIVITYWATCH_URL")
-        .unwrap_or_else(|_| "http://localhost:5600/api/0".to_string());
+    // Load configuration from the config file, if any, and the environment
+    let config_file = args
+        .config
+        .clone()
+        .or_else(|| std::env::var_os("AW_MCP_CONFIG").map(PathBuf::from));
+    let mut config = match &config_file {
+        Some(path) => Config::from_file(path)?,
+        None => Config::from_env()?,
+    }
+    .with_overrides(Overrides {
+        admin_tools: args.admin,
+        listen: args.listen.clone(),
+    });
+    if config.listen.is_some() && config.client_tokens.is_empty() {
+        anyhow::bail!("Listening for clients requires AW_MCP_TOKENS");
+    }
+    if config.listen.is_some() && !cfg!(feature = "http") {
+        anyhow::bail!("Listening for clients requires a build with the `http` feature");
+    }
+    if let Some(path) = &args.record {
+        config.recording = Some(
+            Recording::record(path, &config)
+                .with_context(|| format!("Cannot create recording {}", path.display()))?,
+        );
+        eprintln!("Recording upstream requests to: {}", path.display());
+    }
+    if let Some(path) = &args.replay {
+        config.recording = Some(
+            Recording::replay(path)
+                .with_context(|| format!("Cannot read recording {}", path.display()))?,
+        );
+        eprintln!("Replaying upstream responses from: {}", path.display());
+    }
+
+    eprintln!("ActivityWatch MCP Server starting...");
 
-    // Create ActivityWatch API client
-    let client = ActivityWatchClient::new(&base_url);
+    // Create ActivityWatch API client (detects the API version if needed)
+    let client = ActivityWatchClient::from_config(&config).await;
 
-    // Create MCP server
-    let server = ActivityWatchMcpServer::new(client);
+    eprintln!("Connecting to ActivityWatch at: {}", client.api_url());
 
-    eprintln!("ActivityWatch MCP Server starting...");
-    eprintln!("Connecting to ActivityWatch at: {}", base_url);
+    // Run diagnostics instead of serving when requested
+    if args.self_test {
+        let report = run_self_test(&client).await;
+        println!("{}", report);
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
 
-    // Run with stdio transport
-    let service = server.serve(stdio()).await?;
+    // Create MCP server
+    let keepalive = config.keepalive_interval;
+    let snapshot = config
+        .snapshot_file
+        .clone()
+        .map(|path| (path, config.snapshot_interval));
+    let alerts = (!config.alerts.is_empty()).then_some(config.alert_interval);
+    let listen = config.listen.clone();
+    let server = ActivityWatchMcpServer::new(client).with_config(config);
+    server.check_tool_names()?;
+    #[cfg(unix)]
+    reload_on_hangup(server.clone());
+    if let Some((path, interval)) = snapshot {
+        snapshot_periodically(server.clone(), path, interval);
+    }
+    if let Some(interval) = alerts {
+        check_alerts_periodically(server.clone(), interval);
+    }
+
+    // Serve every client that authenticates, each with its own scope
+    #[cfg(feature = "http")]
+    if let Some(address) = listen {
+        let listener = tokio::net::TcpListener::bind(&address)
+            .await
+            .with_context(|| format!("Cannot listen on {}", address))?;
+        eprintln!(
+            "Listening for MCP clients on: {}{}",
+            address,
+            aw_mcp_server::MCP_PATH
+        );
+        return aw_mcp_server::serve_http(server, listener).await;
+    }
+    #[cfg(not(feature = "http"))]
+    let _ = listen;
 
-    // Wait for service to complete
-    service.waiting().await?;
+    // Run with stdio transport until the client goes away
+    let end = serve_stdio(server, keepalive, args.linger).await;
+    if end != SessionEnd::Cancelled {
+        eprintln!("ActivityWatch MCP Server exiting: {:?}", end);
+        std::process::exit(end.exit_code());
+    }
 
     Ok(())
 }
+
+/// Rewrite the day snapshot file at the given interval while the server runs
+fn snapshot_periodically(server: ActivityWatchMcpServer, path: PathBuf, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            match server.write_snapshot(&path).await {
+                Ok(()) => tracing::debug!("Snapshot written to {}", path.display()),
+                Err(e) => tracing::warn!("Failed to write snapshot: {:#}", e),
+            }
+        }
+    });
+}
+
+/// Check the alert rules at the given interval while the server runs,
+/// delivering each alert when its rule starts firing
+fn check_alerts_periodically(server: ActivityWatchMcpServer, interval: Duration) {
+    tokio::spawn(async move {
+        let mut monitor = AlertMonitor::default();
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            match server.check_alerts().await {
+                Ok(alerts) => {
+                    for alert in monitor.update(alerts) {
+                        server.deliver_alert(&alert).await;
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to check alerts: {}", e.message),
+            }
+        }
+    });
+}
+
+/// Reload the configuration whenever the process receives SIGHUP
+#[cfg(unix)]
+fn reload_on_hangup(server: ActivityWatchMcpServer) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            tracing::warn!("Cannot listen for SIGHUP, config reload disabled: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match server.reload_config() {
+                Ok(restart_required) if restart_required.is_empty() => {
+                    tracing::info!("Configuration reloaded")
+                }
+                Ok(restart_required) => tracing::warn!(
+                    "Configuration reloaded; restart to apply: {}",
+                    restart_required.join(", ")
+                ),
+                Err(e) => tracing::warn!("Failed to reload configuration: {:#}", e),
+            }
+        }
+    });
+}
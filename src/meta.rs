@@ -0,0 +1,233 @@
+use crate::api::SyncMerge;
+use crate::format::format_duration;
+use chrono::{DateTime, Utc};
+use rmcp::model::{CallToolResult, Content};
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+tokio::task_local! {
+    static RESPONSE_META: RefCell<ResponseMeta>;
+}
+
+/// Facts about how a tool response was produced, collected while the tool
+/// runs and appended to its result
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMeta {
+    /// Correlation ID of the tool call, also sent upstream as `X-Request-ID`
+    pub correlation_id: String,
+
+    /// Fetch time of the oldest cached response used while aw-server was unreachable
+    pub stale_since: Option<DateTime<Utc>>,
+
+    /// Fetch time of the oldest in-memory cache entry used (e.g., the bucket list)
+    pub cached_since: Option<DateTime<Utc>>,
+
+    /// Why a query2 script was run locally on raw events instead of by aw-server
+    pub query_fallback: Option<String>,
+
+    /// Problems fixed in events from aw-server (out-of-order, duplicate or invalid-duration events)
+    pub event_fixes: usize,
+
+    /// The merge policy applied to buckets both aw-server and the sync directory have
+    pub sync_merge: Option<SyncMerge>,
+
+    /// IDs of the buckets the merge policy was applied to
+    pub sync_merged: Vec<String>,
+
+    /// Events dropped or trimmed because they fell into do-not-track time
+    pub private_excluded: usize,
+
+    /// Notes a tool adds about its response, e.g. the range JSON output covers
+    pub notes: Vec<String>,
+}
+
+impl ResponseMeta {
+    /// Run a future while collecting response metadata
+    pub async fn collect<F: Future>(
+        correlation_id: String,
+        future: F,
+    ) -> (F::Output, ResponseMeta) {
+        let meta = ResponseMeta {
+            correlation_id,
+            ..Default::default()
+        };
+        RESPONSE_META
+            .scope(RefCell::new(meta), async {
+                let output = future.await;
+                (output, RESPONSE_META.with(|meta| meta.borrow().clone()))
+            })
+            .await
+    }
+
+    /// Append notes about the metadata to a tool result, with the age of
+    /// cached data as of `now`
+    pub fn annotate(&self, mut result: CallToolResult, now: DateTime<Utc>) -> CallToolResult {
+        let mut notes = self.notes(now);
+        if result.is_error == Some(true) {
+            notes.push(format!("_Correlation ID: `{}`_", self.correlation_id));
+        }
+        if !notes.is_empty() {
+            result
+                .content
+                .get_or_insert_with(Vec::new)
+                .push(Content::text(notes.join("\n")));
+        }
+        result
+    }
+
+    /// Human-readable notes, one per line
+    fn notes(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let Some(stale_since) = self.stale_since {
+            notes.push(format!(
+                "_ActivityWatch is unreachable. This response was served from the offline cache (data as of {} UTC) and may be stale._",
+                stale_since.format("%Y-%m-%d %H:%M:%S")
+            ));
+        } else if let Some(cached_since) = self.cached_since {
+            let age = (now - cached_since).num_milliseconds().max(0) as f64 / 1000.0;
+            notes.push(format!(
+                "_Partly served from cache (data as of {} UTC, {} old)._",
+                cached_since.format("%Y-%m-%d %H:%M:%S"),
+                format_duration(age)
+            ));
+        }
+        if self.event_fixes > 0 {
+            notes.push(format!(
+                "_Warning: fixed {} out-of-order, duplicate or invalid-duration events from aw-server (common after imports) before processing._",
+                self.event_fixes
+            ));
+        }
+        if let Some(policy) = self.sync_merge {
+            notes.push(format!(
+                "_Buckets on both aw-server and in the sync directory ({}) were served by the `{}` merge policy (AW_MCP_SYNC_MERGE)._",
+                self.sync_merged.join(", "),
+                policy
+            ));
+        }
+        if self.private_excluded > 0 {
+            notes.push(format!(
+                "_{} events overlapping do-not-track time (AW_MCP_DO_NOT_TRACK) were left out or trimmed; pass `include_private: true` to include them._",
+                self.private_excluded
+            ));
+        }
+        if let Some(reason) = &self.query_fallback {
+            notes.push(format!(
+                "_The query was computed locally from raw events: {}._",
+                reason
+            ));
+        }
+        notes.extend(self.notes.iter().cloned());
+        notes
+    }
+}
+
+/// Generate a short, process-unique correlation ID
+pub fn new_correlation_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    // Spread the counter so IDs generated within the same tick still differ
+    format!(
+        "{:08x}",
+        (nanos as u32) ^ (count as u32).wrapping_mul(0x9e37_79b9)
+    )
+}
+
+/// Correlation ID of the tool call currently running, if any
+pub(crate) fn correlation_id() -> Option<String> {
+    RESPONSE_META
+        .try_with(|meta| meta.borrow().correlation_id.clone())
+        .ok()
+}
+
+/// Record that a cached response fetched at `fetched_at` was used
+pub(crate) fn record_stale(fetched_at: DateTime<Utc>) {
+    // Outside a collection scope (e.g., library use) there is nothing to record
+    let _ = RESPONSE_META.try_with(|meta| {
+        let mut meta = meta.borrow_mut();
+        meta.stale_since = Some(meta.stale_since.map_or(fetched_at, |s| s.min(fetched_at)));
+    });
+}
+
+/// Record that an in-memory cache entry fetched at `fetched_at` was used
+pub(crate) fn record_cached(fetched_at: DateTime<Utc>) {
+    let _ = RESPONSE_META.try_with(|meta| {
+        let mut meta = meta.borrow_mut();
+        meta.cached_since = Some(meta.cached_since.map_or(fetched_at, |s| s.min(fetched_at)));
+    });
+}
+
+/// Run a future whose use of in-memory caches is not reported, for internal
+/// lookups such as resolving which buckets to read
+pub(crate) async fn untracked<F: Future>(future: F) -> F::Output {
+    let before = RESPONSE_META.try_with(|meta| meta.borrow().cached_since);
+    let output = future.await;
+    if let Ok(before) = before {
+        let _ = RESPONSE_META.try_with(|meta| meta.borrow_mut().cached_since = before);
+    }
+    output
+}
+
+/// Record that a query2 script was run locally, and why
+pub(crate) fn record_query_fallback(reason: &str) {
+    let _ = RESPONSE_META.try_with(|meta| {
+        meta.borrow_mut()
+            .query_fallback
+            .get_or_insert_with(|| reason.to_string());
+    });
+}
+
+/// Record that a bucket both aw-server and the sync directory have was
+/// served by a merge policy
+pub(crate) fn record_sync_merge(bucket_id: &str, policy: SyncMerge) {
+    let _ = RESPONSE_META.try_with(|meta| {
+        let mut meta = meta.borrow_mut();
+        meta.sync_merge = Some(policy);
+        if !meta.sync_merged.iter().any(|id| id == bucket_id) {
+            meta.sync_merged.push(bucket_id.to_string());
+        }
+    });
+}
+
+/// Record a note about the response, shown after it
+pub(crate) fn record_note(note: impl Into<String>) {
+    let _ = RESPONSE_META.try_with(|meta| meta.borrow_mut().notes.push(note.into()));
+}
+
+/// Record events left out or trimmed for do-not-track time
+pub(crate) fn record_private_excluded(count: usize) {
+    let _ = RESPONSE_META.try_with(|meta| meta.borrow_mut().private_excluded += count);
+}
+
+/// Record problems fixed in events received from aw-server
+pub(crate) fn record_event_fixes(count: usize) {
+    let _ = RESPONSE_META.try_with(|meta| meta.borrow_mut().event_fixes += count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn untracked_lookups_leave_cache_age_alone() {
+        let fetched_at = Utc::now();
+        let ((), meta) = ResponseMeta::collect("a".to_string(), async {
+            untracked(async { record_cached(fetched_at) }).await;
+        })
+        .await;
+        assert!(meta.cached_since.is_none());
+
+        let earlier = fetched_at - chrono::Duration::minutes(5);
+        let ((), meta) = ResponseMeta::collect("b".to_string(), async {
+            record_cached(fetched_at);
+            untracked(async { record_cached(earlier) }).await;
+        })
+        .await;
+        assert_eq!(meta.cached_since, Some(fetched_at));
+    }
+}
@@ -0,0 +1,136 @@
+use super::{Event, EventDuration};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Builder for constructing validated events (see `Event::builder`)
+#[derive(Debug, Clone, Default)]
+pub struct EventBuilder {
+    id: Option<i64>,
+    timestamp: Option<DateTime<Utc>>,
+    duration: Option<Result<EventDuration, String>>,
+    data: HashMap<String, serde_json::Value>,
+}
+
+impl Event {
+    /// Start building an event, e.g.
+    /// `Event::builder().timestamp(now).duration_secs(30.0).data("app", "Firefox").build()`
+    pub fn builder() -> EventBuilder {
+        EventBuilder::default()
+    }
+}
+
+impl EventBuilder {
+    /// Event ID (normally assigned by the server)
+    pub fn id(mut self, id: i64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Start time of the event (required)
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Duration of the event (default: zero)
+    pub fn duration(mut self, duration: EventDuration) -> Self {
+        self.duration = Some(Ok(duration));
+        self
+    }
+
+    /// Duration of the event in seconds; negative or non-finite values fail
+    /// at `build`
+    pub fn duration_secs(mut self, secs: f64) -> Self {
+        self.duration = Some(EventDuration::from_secs_f64(secs));
+        self
+    }
+
+    /// Set one data field
+    pub fn data(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.data.insert(key.into(), value.into());
+        self
+    }
+
+    /// Add all fields of a data map
+    pub fn data_map(mut self, data: HashMap<String, serde_json::Value>) -> Self {
+        self.data.extend(data);
+        self
+    }
+
+    /// Validate and build the event
+    pub fn build(self) -> Result<Event, String> {
+        let timestamp = self.timestamp.ok_or("Event timestamp is required")?;
+        let duration = self.duration.unwrap_or(Ok(EventDuration::ZERO))?;
+        if self.data.is_empty() {
+            return Err("Event data cannot be empty".to_string());
+        }
+
+        Ok(Event {
+            id: self.id,
+            timestamp,
+            duration,
+            data: self.data,
+            extra: HashMap::new(),
+        })
+    }
+}
+
+/// An event for tests, with `data` given as a JSON object (which, unlike
+/// with the builder, may be empty)
+#[cfg(test)]
+pub(crate) fn test_event(timestamp: DateTime<Utc>, secs: f64, data: serde_json::Value) -> Event {
+    let serde_json::Value::Object(data) = data else {
+        panic!("event data must be a JSON object");
+    };
+    Event {
+        id: None,
+        timestamp,
+        duration: EventDuration::from_secs_f64(secs).unwrap(),
+        data: data.into_iter().collect(),
+        extra: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn builds_valid_events() {
+        let timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let event = Event::builder()
+            .timestamp(timestamp)
+            .duration_secs(30.0)
+            .data("app", "Firefox")
+            .build()
+            .unwrap();
+
+        assert_eq!(event.end(), timestamp + chrono::Duration::seconds(30));
+        assert_eq!(event.data["app"], "Firefox");
+    }
+
+    #[test]
+    fn rejects_invalid_events() {
+        let timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        assert!(Event::builder().data("app", "x").build().is_err());
+        assert!(Event::builder().timestamp(timestamp).build().is_err());
+        assert!(
+            Event::builder()
+                .timestamp(timestamp)
+                .duration_secs(-1.0)
+                .data("app", "x")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn builds_test_events_with_empty_data() {
+        let timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let event = test_event(timestamp, 30.0, serde_json::json!({}));
+
+        assert_eq!(event.end(), timestamp + chrono::Duration::seconds(30));
+        assert!(event.data.is_empty());
+    }
+}
@@ -0,0 +1,299 @@
+//! Tolerant deserialization of the field differences between aw-server
+//! versions.
+//!
+//! The Python aw-server (0.11 to 0.13) and aw-server-rust return the same
+//! resources in slightly different shapes: timestamps with `+00:00` or `Z`
+//! (and, in data written by some watchers, without any offset), event IDs as
+//! numbers or strings, `testing` as a boolean or a string, `data` left out or
+//! `null`, and the bucket's last activity as `last_updated` or as
+//! `metadata.end`. The helpers here accept all of them so a server upgrade
+//! doesn't break parsing.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// Timestamp formats without an offset, taken as UTC
+const NAIVE_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+/// Parse a server timestamp: RFC 3339 with any offset, or without an offset
+/// (taken as UTC)
+pub fn parse_server_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f%:z") {
+        return Some(time.with_timezone(&Utc));
+    }
+    NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|time| time.and_utc())
+}
+
+/// A required timestamp in any format [`parse_server_timestamp`] accepts
+pub(crate) fn timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<Utc>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_server_timestamp(&text)
+        .ok_or_else(|| serde::de::Error::custom(format!("Invalid timestamp '{}'", text)))
+}
+
+/// An optional timestamp; `null`, non-strings and unparseable values are
+/// `None` rather than an error, since they only annotate the resource
+pub(crate) fn optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value
+        .as_ref()
+        .and_then(|v| v.as_str())
+        .and_then(parse_server_timestamp))
+}
+
+/// An optional ID given as a number or a numeric string
+pub(crate) fn optional_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<i64>, D::Error> {
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::Number(number)) => number
+            .as_i64()
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid ID {}", number))),
+        Some(serde_json::Value::String(text)) if text.trim().is_empty() => Ok(None),
+        Some(serde_json::Value::String(text)) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("Invalid ID '{}'", text))),
+        Some(other) => Err(serde::de::Error::custom(format!("Invalid ID {}", other))),
+    }
+}
+
+/// An optional flag given as a boolean, `0`/`1` or a string such as "true"
+pub(crate) fn optional_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::Bool(flag)) => Some(flag),
+            Some(serde_json::Value::Number(number)) => number.as_i64().map(|n| n != 0),
+            Some(serde_json::Value::String(text)) => {
+                match text.trim().to_ascii_lowercase().as_str() {
+                    "true" | "1" | "yes" => Some(true),
+                    "false" | "0" | "no" => Some(false),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+    )
+}
+
+/// A value whose `null` means the type's default (e.g., an empty map)
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AwServerInfo, Bucket, Event, ServerFlavor};
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Response shapes of `/info`, `/buckets/` and `/buckets/{id}/events`
+    /// as returned by each server version
+    struct Fixture {
+        version: &'static str,
+        info: &'static str,
+        buckets: &'static str,
+        events: &'static str,
+    }
+
+    const FIXTURES: &[Fixture] = &[
+        Fixture {
+            version: "aw-server 0.11",
+            info: r#"{"hostname": "laptop", "version": "v0.11.0", "testing": false}"#,
+            buckets: r#"{"aw-watcher-window_laptop": {
+                "id": "aw-watcher-window_laptop", "created": "2024-03-01T08:00:00.000000",
+                "name": null, "type": "currentwindow", "client": "aw-watcher-window",
+                "hostname": "laptop", "last_updated": "2024-03-15T09:30:00.000000"}}"#,
+            events: r#"[{"id": 12, "timestamp": "2024-03-15T09:00:00.000000",
+                "duration": 1800.0, "data": {"app": "Code", "title": "main.rs"}}]"#,
+        },
+        Fixture {
+            version: "aw-server 0.12",
+            info: r#"{"hostname": "laptop", "version": "v0.12.2", "testing": "false",
+                "device_id": "8d1e"}"#,
+            buckets: r#"{"aw-watcher-window_laptop": {
+                "id": "aw-watcher-window_laptop", "created": "2024-03-01T08:00:00+00:00",
+                "name": null, "type": "currentwindow", "client": "aw-watcher-window",
+                "hostname": "laptop", "data": {},
+                "last_updated": "2024-03-15T09:30:00.000000+00:00"}}"#,
+            events: r#"[{"id": "12", "timestamp": "2024-03-15T09:00:00+00:00",
+                "duration": "1800", "data": {"app": "Code", "title": "main.rs"}}]"#,
+        },
+        Fixture {
+            version: "aw-server 0.13",
+            info: r#"{"hostname": "laptop", "version": "v0.13.1", "testing": false,
+                "device_id": "8d1e"}"#,
+            buckets: r#"{"aw-watcher-window_laptop": {
+                "id": "aw-watcher-window_laptop", "created": "2024-03-01T08:00:00.000+00:00",
+                "name": null, "type": "currentwindow", "client": "aw-watcher-window",
+                "hostname": "laptop", "data": {"tz": "Europe/Berlin"},
+                "last_updated": "2024-03-15 09:30:00.000+00:00"}}"#,
+            events: r#"[{"id": 12, "timestamp": "2024-03-15T10:00:00.000+01:00",
+                "duration": 1800.0, "data": {"app": "Code", "title": "main.rs"}}]"#,
+        },
+        Fixture {
+            version: "aw-server-rust",
+            info: r#"{"hostname": "laptop", "version": "v0.13.1 (rust)", "testing": 0,
+                "device_id": "8d1e"}"#,
+            buckets: r#"{"aw-watcher-window_laptop": {
+                "id": "aw-watcher-window_laptop", "created": "2024-03-01T08:00:00Z",
+                "name": null, "type": "currentwindow", "client": "aw-watcher-window",
+                "hostname": "laptop", "data": {}, "last_updated": null,
+                "metadata": {"start": "2024-03-15T09:00:00Z", "end": "2024-03-15T09:30:00Z"}}}"#,
+            events: r#"[{"id": 12, "timestamp": "2024-03-15T09:00:00Z",
+                "duration": 1800.0, "data": {"app": "Code", "title": "main.rs"}},
+                {"id": null, "timestamp": "2024-03-15T09:30:00Z", "duration": null,
+                "data": null}]"#,
+        },
+    ];
+
+    #[test]
+    fn parses_every_server_version() {
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 3, 15, h, m, 0).unwrap();
+        for fixture in FIXTURES {
+            let info: AwServerInfo = serde_json::from_str(fixture.info)
+                .unwrap_or_else(|e| panic!("{} info: {}", fixture.version, e));
+            assert_eq!(info.testing, Some(false), "{}", fixture.version);
+            let rust = fixture.version.contains("rust");
+            assert_eq!(
+                info.flavor() == ServerFlavor::Rust,
+                rust,
+                "{}",
+                fixture.version
+            );
+
+            let buckets: HashMap<String, Bucket> = serde_json::from_str(fixture.buckets)
+                .unwrap_or_else(|e| panic!("{} buckets: {}", fixture.version, e));
+            let bucket = &buckets["aw-watcher-window_laptop"];
+            assert_eq!(bucket.bucket_type.as_deref(), Some("currentwindow"));
+            assert_eq!(
+                bucket.created,
+                Some(Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap()),
+                "{}",
+                fixture.version
+            );
+            assert_eq!(
+                bucket.last_activity(),
+                Some(at(9, 30)),
+                "{}",
+                fixture.version
+            );
+
+            let events: Vec<Event> = serde_json::from_str(fixture.events)
+                .unwrap_or_else(|e| panic!("{} events: {}", fixture.version, e));
+            assert_eq!(events[0].id, Some(12), "{}", fixture.version);
+            assert_eq!(events[0].timestamp, at(9, 0), "{}", fixture.version);
+            assert_eq!(events[0].end(), at(9, 30), "{}", fixture.version);
+            assert_eq!(events[0].data["app"], "Code");
+        }
+    }
+
+    #[test]
+    fn round_trips_fields_it_does_not_model() {
+        for fixture in FIXTURES {
+            let buckets: HashMap<String, Bucket> = serde_json::from_str(fixture.buckets).unwrap();
+            let reparsed: HashMap<String, Bucket> =
+                serde_json::from_str(&serde_json::to_string(&buckets).unwrap()).unwrap();
+            let (bucket, again) = (
+                &buckets["aw-watcher-window_laptop"],
+                &reparsed["aw-watcher-window_laptop"],
+            );
+            assert_eq!(bucket.extra, again.extra, "{}", fixture.version);
+            assert_eq!(
+                bucket.last_activity(),
+                again.last_activity(),
+                "{}",
+                fixture.version
+            );
+
+            let events: Vec<Event> = serde_json::from_str(fixture.events).unwrap();
+            let reparsed: Vec<Event> =
+                serde_json::from_str(&serde_json::to_string(&events).unwrap()).unwrap();
+            for (event, again) in events.iter().zip(&reparsed) {
+                assert_eq!((event.id, event.timestamp), (again.id, again.timestamp));
+                assert_eq!((event.duration, &event.data), (again.duration, &again.data));
+            }
+        }
+    }
+
+    #[test]
+    fn accepts_every_combination_of_field_shapes() {
+        let timestamps = [
+            "2024-03-15T09:00:00Z",
+            "2024-03-15T09:00:00+00:00",
+            "2024-03-15T18:00:00+09:00",
+            "2024-03-15T09:00:00.000000",
+            "2024-03-15 09:00:00",
+            "2024-03-15 09:00:00.5+00:00",
+        ];
+        let ids = [json!(7), json!("7"), json!(null)];
+        let durations = [json!(60), json!(60.0), json!("60"), json!("PT1M")];
+        let datas = [json!({"app": "Code"}), json!({}), json!(null)];
+
+        let start = Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap();
+        for timestamp in timestamps {
+            for id in &ids {
+                for duration in &durations {
+                    for data in &datas {
+                        let value = json!({
+                            "id": id, "timestamp": timestamp, "duration": duration, "data": data,
+                        });
+                        let event: Event = serde_json::from_value(value.clone())
+                            .unwrap_or_else(|e| panic!("{}: {}", value, e));
+                        let offset = (event.timestamp - start).num_milliseconds();
+                        assert!((0..=500).contains(&offset), "{}", value);
+                        assert_eq!(event.duration.as_secs_f64(), 60.0, "{}", value);
+                        assert_eq!(event.id, id.as_i64().or(id.as_str().map(|_| 7)));
+                    }
+                }
+            }
+        }
+
+        let missing_data: Event =
+            serde_json::from_str(r#"{"timestamp": "2024-03-15T09:00:00Z"}"#).unwrap();
+        assert!(missing_data.data.is_empty());
+        assert!(serde_json::from_str::<Event>(r#"{"timestamp": "yesterday"}"#).is_err());
+        let fractional_id = r#"{"timestamp": "2024-03-15T09:00:00Z", "id": 1.5}"#;
+        assert!(serde_json::from_str::<Event>(fractional_id).is_err());
+    }
+
+    #[test]
+    fn ignores_unparseable_optional_fields() {
+        let bucket: Bucket = serde_json::from_value(json!({
+            "id": "b",
+            "created": "not a date",
+            "last_updated": 1710493200,
+        }))
+        .unwrap();
+        assert_eq!((bucket.created, bucket.last_updated), (None, None));
+
+        let info: AwServerInfo = serde_json::from_value(json!({"testing": "maybe"})).unwrap();
+        assert_eq!((info.testing, info.flavor()), (None, ServerFlavor::Unknown));
+        assert_eq!(
+            parse_server_timestamp(" 2024-03-15T09:00:00+00:00 "),
+            Some(Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap())
+        );
+    }
+}
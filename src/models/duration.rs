@@ -0,0 +1,208 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Non-negative event duration.
+///
+/// Serialized as float seconds, as aw-server expects. Deserializes from float
+/// seconds, numeric strings, ISO 8601 durations (e.g. `"PT1M30.5S"`) and
+/// `null` (zero), since server implementations and watchers differ.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventDuration(Duration);
+
+impl EventDuration {
+    /// A zero duration
+    pub const ZERO: EventDuration = EventDuration(Duration::zero());
+
+    /// Create a duration from seconds, rejecting negative or non-finite values
+    pub fn from_secs_f64(secs: f64) -> Result<Self, String> {
+        let std = std::time::Duration::try_from_secs_f64(secs).map_err(|_| {
+            format!(
+                "Invalid event duration {} (must be non-negative seconds)",
+                secs
+            )
+        })?;
+        Duration::from_std(std)
+            .map(Self)
+            .map_err(|_| format!("Event duration {} is out of range", secs))
+    }
+
+    /// Create a duration from a `chrono::Duration`, rejecting negative values
+    pub fn from_chrono(duration: Duration) -> Result<Self, String> {
+        if duration < Duration::zero() {
+            return Err(format!(
+                "Invalid event duration {}ms (must be non-negative)",
+                duration.num_milliseconds()
+            ));
+        }
+        Ok(Self(duration))
+    }
+
+    /// The span from `start` to `end`, or zero if `end` is before `start`
+    pub fn between(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self((end - start).max(Duration::zero()))
+    }
+
+    /// Duration in (fractional) seconds
+    pub fn as_secs_f64(&self) -> f64 {
+        match self.0.num_microseconds() {
+            Some(micros) => micros as f64 / 1_000_000.0,
+            None => self.0.num_milliseconds() as f64 / 1000.0,
+        }
+    }
+
+    /// The duration as a `chrono::Duration`
+    pub fn as_chrono(&self) -> Duration {
+        self.0
+    }
+
+    /// Whether the duration is zero
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl From<EventDuration> for Duration {
+    fn from(duration: EventDuration) -> Self {
+        duration.0
+    }
+}
+
+impl fmt::Display for EventDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}s", self.as_secs_f64())
+    }
+}
+
+impl Serialize for EventDuration {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_secs_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for EventDuration {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Secs(f64),
+            Text(String),
+        }
+
+        let duration = match Option::<Raw>::deserialize(deserializer)? {
+            None => Ok(Self::ZERO),
+            Some(Raw::Secs(secs)) => Self::from_received_secs(secs),
+            Some(Raw::Text(text)) => match text.trim().parse::<f64>() {
+                Ok(secs) => Self::from_received_secs(secs),
+                Err(_) => parse_iso8601_duration(&text),
+            },
+        };
+        duration.map_err(serde::de::Error::custom)
+    }
+}
+
+impl EventDuration {
+    /// A duration received from aw-server or a watcher. Negative and
+    /// non-finite values, which broken watchers and imports leave behind,
+    /// become zero and are counted in the response metadata instead of
+    /// failing the whole response.
+    fn from_received_secs(secs: f64) -> Result<Self, String> {
+        if secs.is_finite() && secs >= 0.0 {
+            return Self::from_secs_f64(secs);
+        }
+        tracing::warn!(secs, "Clamped an invalid event duration to zero");
+        crate::meta::record_event_fixes(1);
+        Ok(Self::ZERO)
+    }
+}
+
+/// Parse an ISO 8601 duration with week, day and time components (e.g.
+/// `P1DT2H`, `PT90.5S`). Years and months are rejected as ambiguous.
+fn parse_iso8601_duration(text: &str) -> Result<EventDuration, String> {
+    let invalid = || format!("Invalid ISO 8601 duration '{}'", text);
+    let rest = text.trim().strip_prefix(['P', 'p']).ok_or_else(invalid)?;
+
+    let mut secs = 0.0;
+    let mut in_time = false;
+    let mut number = String::new();
+    let mut components = 0;
+    for c in rest.chars() {
+        match c.to_ascii_uppercase() {
+            'T' if !in_time && number.is_empty() => in_time = true,
+            '0'..='9' | '.' | ',' => number.push(if c == ',' { '.' } else { c }),
+            unit => {
+                let value: f64 = number.parse().map_err(|_| invalid())?;
+                let factor = match (in_time, unit) {
+                    (false, 'W') => 7.0 * 86_400.0,
+                    (false, 'D') => 86_400.0,
+                    (true, 'H') => 3600.0,
+                    (true, 'M') => 60.0,
+                    (true, 'S') => 1.0,
+                    _ => return Err(invalid()),
+                };
+                secs += value * factor;
+                number.clear();
+                components += 1;
+            }
+        }
+    }
+    if !number.is_empty() || components == 0 {
+        return Err(invalid());
+    }
+
+    EventDuration::from_secs_f64(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<EventDuration, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn deserializes_seconds_strings_and_null() {
+        assert_eq!(parse("60.5").unwrap().as_secs_f64(), 60.5);
+        assert_eq!(parse(r#""12""#).unwrap().as_secs_f64(), 12.0);
+        assert_eq!(parse("null").unwrap(), EventDuration::ZERO);
+    }
+
+    #[test]
+    fn deserializes_iso8601_durations() {
+        assert_eq!(parse(r#""PT1M30.5S""#).unwrap().as_secs_f64(), 90.5);
+        assert_eq!(parse(r#""P1DT2H""#).unwrap().as_secs_f64(), 93_600.0);
+        assert!(parse(r#""P1M""#).is_err());
+        assert!(parse(r#""PT""#).is_err());
+    }
+
+    #[test]
+    fn rejects_negative_durations() {
+        assert!(EventDuration::from_secs_f64(-1.0).is_err());
+        assert!(EventDuration::from_secs_f64(f64::NAN).is_err());
+        assert!(EventDuration::from_chrono(Duration::seconds(-1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn clamps_invalid_received_durations() {
+        let (parsed, meta) = crate::meta::ResponseMeta::collect(String::new(), async {
+            [
+                parse("-1.0"),
+                parse(r#""-5""#),
+                parse(r#""NaN""#),
+                parse(r#""inf""#),
+            ]
+        })
+        .await;
+        for duration in parsed {
+            assert_eq!(duration.unwrap(), EventDuration::ZERO);
+        }
+        assert_eq!(meta.event_fixes, 4);
+    }
+
+    #[test]
+    fn serializes_as_float_seconds() {
+        let duration = EventDuration::from_secs_f64(1.25).unwrap();
+        assert_eq!(serde_json::to_string(&duration).unwrap(), "1.25");
+    }
+}
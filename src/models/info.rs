@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Response of the ActivityWatch `/info` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwServerInfo {
+    /// Hostname of the machine running aw-server
+    #[serde(default)]
+    pub hostname: Option<String>,
+
+    /// Server version string (e.g., "v0.12.2" or "v0.12.3 (rust)")
+    #[serde(default)]
+    pub version: Option<String>,
+
+    /// Whether the server runs in testing mode (some versions report it as a
+    /// string)
+    #[serde(default, deserialize_with = "super::compat::optional_bool")]
+    pub testing: Option<bool>,
+
+    /// Unique device identifier
+    #[serde(default)]
+    pub device_id: Option<String>,
+
+    /// Any other fields reported by the server
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Which aw-server implementation is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerFlavor {
+    /// The original Python aw-server
+    Python,
+    /// aw-server-rust
+    Rust,
+    /// Not (yet) detected
+    Unknown,
+}
+
+impl AwServerInfo {
+    /// Detect the server implementation from the version string
+    pub fn flavor(&self) -> ServerFlavor {
+        match self.version.as_deref() {
+            Some(version) if version.to_ascii_lowercase().contains("rust") => ServerFlavor::Rust,
+            Some(_) => ServerFlavor::Python,
+            None => ServerFlavor::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ServerFlavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Python => "aw-server (Python)",
+            Self::Rust => "aw-server-rust",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_server_flavor_from_version() {
+        let info: AwServerInfo = serde_json::from_str(
+            r#"{"hostname": "host", "version": "v0.12.3 (rust)", "testing": false}"#,
+        )
+        .unwrap();
+        assert_eq!(info.flavor(), ServerFlavor::Rust);
+
+        let info: AwServerInfo =
+            serde_json::from_str(r#"{"hostname": "host", "version": "v0.12.2"}"#).unwrap();
+        assert_eq!(info.flavor(), ServerFlavor::Python);
+    }
+}
@@ -1,3 +1,15 @@
 mod bucket;
+mod builder;
+mod compat;
+mod duration;
+mod info;
+mod options;
+mod query;
 
 pub use bucket::*;
+pub use builder::*;
+pub use compat::parse_server_timestamp;
+pub use duration::*;
+pub use info::*;
+pub use options::*;
+pub use query::*;
@@ -0,0 +1,187 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How zero-duration events (e.g., heartbeat tails) are handled
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ZeroDurationPolicy {
+    /// Return zero-duration events unchanged
+    #[default]
+    Keep,
+    /// Remove zero-duration events
+    Drop,
+    /// Fold zero-duration events into the chronologically preceding event
+    MergeIntoPrevious,
+}
+
+impl FromStr for ZeroDurationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "keep" => Ok(Self::Keep),
+            "drop" => Ok(Self::Drop),
+            "merge_into_previous" | "merge" => Ok(Self::MergeIntoPrevious),
+            other => Err(format!(
+                "Unknown zero-duration policy '{}'. Expected keep, drop or merge_into_previous",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ZeroDurationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Keep => "keep",
+            Self::Drop => "drop",
+            Self::MergeIntoPrevious => "merge_into_previous",
+        })
+    }
+}
+
+/// How buckets are grouped in listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketGrouping {
+    /// Group by the hostname that created the bucket
+    Hostname,
+    /// Group by bucket type (e.g., "currentwindow")
+    Type,
+}
+
+/// How much detail markdown summaries include
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DetailLevel {
+    /// Top groups only, for quick answers
+    Brief,
+    /// Top groups with their top titles and one line per event
+    #[default]
+    Normal,
+    /// More groups and titles, and every event with all its data
+    Full,
+}
+
+impl DetailLevel {
+    /// Default number of groups per section
+    pub fn max_groups(self) -> usize {
+        match self {
+            Self::Brief => 5,
+            Self::Normal => 10,
+            Self::Full => 25,
+        }
+    }
+
+    /// Number of top titles listed under each app
+    pub fn titles_per_group(self) -> usize {
+        match self {
+            Self::Brief => 0,
+            Self::Normal => 3,
+            Self::Full => 10,
+        }
+    }
+
+    /// Whether individual events are listed at all
+    pub fn lists_events(self) -> bool {
+        self != Self::Brief
+    }
+
+    /// Whether listed events include all their data fields
+    pub fn event_data(self) -> bool {
+        self == Self::Full
+    }
+}
+
+/// Interval length of time series points
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    /// One point per hour
+    Hour,
+    /// One point per day
+    Day,
+    /// One point per week, starting on Monday
+    Week,
+}
+
+impl Resolution {
+    /// The next coarser resolution, if any
+    pub fn coarser(self) -> Option<Self> {
+        match self {
+            Self::Hour => Some(Self::Day),
+            Self::Day => Some(Self::Week),
+            Self::Week => None,
+        }
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+        })
+    }
+}
+
+/// How charts are drawn in markdown responses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartStyle {
+    /// A table with one row per bin
+    #[default]
+    Table,
+    /// Horizontal bars of `#`, scaled to the largest bin
+    Bars,
+}
+
+/// Which event keeps the time where events from several buckets overlap
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// The bucket whose device shows input activity, then the bucket listed first
+    #[default]
+    Priority,
+    /// Browser tab events win over window events of a browser app; otherwise by priority
+    BrowserOverWindow,
+    /// The longest original event wins; ties by priority
+    LongestWins,
+    /// Overlapping time is split evenly between the events of the highest-priority devices
+    SplitProportional,
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Priority => "priority",
+            Self::BrowserOverWindow => "browser_over_window",
+            Self::LongestWins => "longest_wins",
+            Self::SplitProportional => "split_proportional",
+        })
+    }
+}
+
+/// Format of a category rule set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleFormat {
+    /// `"Category > Subcategory" = "regex"` lines, as in `categories.toml`
+    Toml,
+    /// A list of classes, as in aw-webui's `classes` setting
+    Webui,
+}
+
+impl fmt::Display for RuleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Toml => "toml",
+            Self::Webui => "webui",
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use super::Event;
+use serde::{Deserialize, Serialize};
+
+/// The value a query2 program returned for one time period.
+///
+/// aw-server returns whatever the program's `RETURN` evaluates to, so the
+/// variants are tried in order: scalars, a list of events, a list of event
+/// lists, an object (e.g. `{"events": [...], "duration": 12.5}`), and any
+/// other JSON as a fallback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum QueryValue {
+    /// `null`
+    Null,
+    /// A boolean
+    Bool(bool),
+    /// A number (e.g. `sum_durations`)
+    Number(f64),
+    /// A string
+    Text(String),
+    /// A list of events (the common case)
+    Events(Vec<Event>),
+    /// A list of event lists (e.g. several flooded buckets)
+    EventLists(Vec<Vec<Event>>),
+    /// An object of named results
+    Object(serde_json::Map<String, serde_json::Value>),
+    /// Anything else
+    Other(serde_json::Value),
+}
+
+impl QueryValue {
+    /// The numeric value, if this is a number
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Convert into events.
+    ///
+    /// Event lists are flattened, and objects yield their `events` entry.
+    /// `null` becomes an empty list; other values are an error.
+    pub fn into_events(self) -> Result<Vec<Event>, String> {
+        match self {
+            Self::Null => Ok(Vec::new()),
+            Self::Events(events) => Ok(events),
+            Self::EventLists(lists) => Ok(lists.into_iter().flatten().collect()),
+            Self::Object(mut object) => match object.remove("events") {
+                Some(events) => serde_json::from_value(events)
+                    .map_err(|e| format!("Query result 'events' is not a list of events: {}", e)),
+                None => Err("Query result object has no 'events' entry".to_string()),
+            },
+            other => Err(format!(
+                "Query result is not a list of events: {}",
+                serde_json::to_string(&other).unwrap_or_default()
+            )),
+        }
+    }
+}
+
+/// The result of one time period of a query2 request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodResult {
+    /// The time period as sent to aw-server (`start/end`)
+    pub timeperiod: String,
+
+    /// What the query returned for this period
+    pub value: QueryValue,
+}
+
+/// The result of a query2 request, one entry per requested time period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResult {
+    /// Results in the order the time periods were requested
+    pub periods: Vec<PeriodResult>,
+
+    /// Whether some periods were computed locally from raw events because
+    /// aw-server could not run the query
+    #[serde(default)]
+    pub computed_locally: bool,
+}
+
+impl QueryResult {
+    /// Pair the raw per-period values with the requested time periods
+    pub fn from_response(timeperiods: &[String], values: Vec<QueryValue>) -> Result<Self, String> {
+        if timeperiods.len() != values.len() {
+            return Err(format!(
+                "Query returned {} results for {} time periods",
+                values.len(),
+                timeperiods.len()
+            ));
+        }
+
+        Ok(Self {
+            periods: timeperiods
+                .iter()
+                .cloned()
+                .zip(values)
+                .map(|(timeperiod, value)| PeriodResult { timeperiod, value })
+                .collect(),
+            computed_locally: false,
+        })
+    }
+
+    /// All events of all periods, in period order
+    pub fn into_events(self) -> Result<Vec<Event>, String> {
+        let mut events = Vec::new();
+        for period in self.periods {
+            events.extend(period.value.into_events()?);
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_query_results() {
+        let values: Vec<QueryValue> = serde_json::from_str(
+            r#"[
+                [{"timestamp": "2024-01-01T12:00:00Z", "duration": 5.0, "data": {"app": "a"}}],
+                42.5,
+                {"events": [], "duration": 0},
+                null
+            ]"#,
+        )
+        .unwrap();
+
+        assert!(matches!(values[0], QueryValue::Events(ref e) if e.len() == 1));
+        assert_eq!(values[1].as_f64(), Some(42.5));
+        assert!(matches!(values[2], QueryValue::Object(_)));
+        assert!(matches!(values[3], QueryValue::Null));
+    }
+
+    #[test]
+    fn converts_results_into_events() {
+        let periods = vec!["a/b".to_string(), "b/c".to_string()];
+        let values: Vec<QueryValue> = serde_json::from_str(
+            r#"[
+                [[{"timestamp": "2024-01-01T12:00:00Z", "duration": 5.0, "data": {"app": "a"}}]],
+                {"events": [{"timestamp": "2024-01-02T12:00:00Z", "duration": 1.0, "data": {"app": "b"}}]}
+            ]"#,
+        )
+        .unwrap();
+
+        let result = QueryResult::from_response(&periods, values).unwrap();
+        assert_eq!(result.periods[1].timeperiod, "b/c");
+        assert_eq!(result.into_events().unwrap().len(), 2);
+
+        assert!(QueryResult::from_response(&periods, Vec::new()).is_err());
+        assert!(QueryValue::Number(1.0).into_events().is_err());
+    }
+}
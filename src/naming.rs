@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+/// Prefix of the tools' built-in names
+pub const DEFAULT_TOOL_PREFIX: &str = "aw_";
+
+/// Longest tool name MCP clients are required to accept
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// The names tools are offered under.
+///
+/// Tools are defined with [`DEFAULT_TOOL_PREFIX`]; another prefix replaces
+/// it, and per-tool renames (parsed from entries like
+/// `aw_get_events=events`) take precedence over the prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolNames {
+    /// Prefix replacing `aw_`
+    pub prefix: String,
+
+    /// Built-in name to offered name
+    pub renames: HashMap<String, String>,
+}
+
+impl Default for ToolNames {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_TOOL_PREFIX.to_string(),
+            renames: HashMap::new(),
+        }
+    }
+}
+
+impl ToolNames {
+    /// Use another prefix; it may be empty
+    pub fn with_prefix(mut self, prefix: &str) -> Result<Self, String> {
+        let prefix = prefix.trim();
+        if !prefix.chars().all(is_name_char) {
+            return Err(format!(
+                "Invalid tool prefix '{}': use letters, digits, '_' and '-'",
+                prefix
+            ));
+        }
+        self.prefix = prefix.to_string();
+        Ok(self)
+    }
+
+    /// Add renames from comma-separated `built_in=offered` entries
+    pub fn with_renames(mut self, spec: &str) -> Result<Self, String> {
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((from, to)) = entry.split_once('=') else {
+                return Err(format!(
+                    "Invalid rename '{}': expected 'aw_tool=name'",
+                    entry
+                ));
+            };
+            let (from, to) = (from.trim(), to.trim());
+            if !valid_name(to) {
+                return Err(format!(
+                    "Invalid tool name '{}': use 1-{} letters, digits, '_' and '-'",
+                    to, MAX_TOOL_NAME_LEN
+                ));
+            }
+            if self
+                .renames
+                .insert(from.to_string(), to.to_string())
+                .is_some()
+            {
+                return Err(format!("Tool '{}' is renamed twice", from));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Whether every tool keeps its built-in name
+    pub fn is_default(&self) -> bool {
+        self.prefix == DEFAULT_TOOL_PREFIX && self.renames.is_empty()
+    }
+
+    /// The name a built-in tool is offered under
+    pub fn external(&self, name: &str) -> String {
+        if let Some(renamed) = self.renames.get(name) {
+            return renamed.clone();
+        }
+        match name.strip_prefix(DEFAULT_TOOL_PREFIX) {
+            Some(rest) => format!("{}{}", self.prefix, rest),
+            None => name.to_string(),
+        }
+    }
+
+    /// The built-in tool offered as `name`, if any
+    pub fn internal<'a>(&self, name: &str, tools: &[&'a str]) -> Option<&'a str> {
+        tools
+            .iter()
+            .copied()
+            .find(|tool| self.external(tool) == name)
+    }
+
+    /// Check the renames against the built-in tools: every renamed tool
+    /// must exist, and no two tools may end up with the same name
+    pub fn check(&self, tools: &[&str]) -> Result<(), String> {
+        if let Some(unknown) = self
+            .renames
+            .keys()
+            .find(|name| !tools.contains(&name.as_str()))
+        {
+            return Err(format!("Cannot rename unknown tool '{}'", unknown));
+        }
+        let mut offered: HashMap<String, &str> = HashMap::new();
+        for tool in tools {
+            let name = self.external(tool);
+            if name.len() > MAX_TOOL_NAME_LEN || name.is_empty() {
+                return Err(format!("Tool name '{}' is empty or too long", name));
+            }
+            if let Some(other) = offered.insert(name.clone(), tool) {
+                return Err(format!(
+                    "Tools '{}' and '{}' would both be named '{}'",
+                    other, tool, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the built-in names of `tools` where they appear as whole
+    /// words in text, such as tool descriptions that refer to each other
+    pub fn rename_text(&self, text: &str, tools: &[&str]) -> String {
+        if self.is_default() {
+            return text.to_string();
+        }
+        let mut result = String::with_capacity(text.len());
+        let mut word_start = None;
+        for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            match (is_word_char(c), word_start) {
+                (true, None) => word_start = Some(i),
+                (false, Some(start)) => {
+                    let word = &text[start..i];
+                    match tools.contains(&word) {
+                        true => result.push_str(&self.external(word)),
+                        false => result.push_str(word),
+                    }
+                    word_start = None;
+                }
+                _ => {}
+            }
+            if word_start.is_none() && i < text.len() {
+                result.push(c);
+            }
+        }
+        result
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_TOOL_NAME_LEN && name.chars().all(is_name_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOOLS: &[&str] = &["aw_get_events", "aw_list_buckets", "aw_status"];
+
+    #[test]
+    fn renames_tools_and_references_to_them() {
+        let names = ToolNames::default()
+            .with_prefix("activitywatch_")
+            .unwrap()
+            .with_renames("aw_status=watchers")
+            .unwrap();
+        assert_eq!(names.external("aw_get_events"), "activitywatch_get_events");
+        assert_eq!(names.external("aw_status"), "watchers");
+        assert_eq!(names.internal("watchers", TOOLS), Some("aw_status"));
+        assert_eq!(names.internal("aw_status", TOOLS), None);
+        assert_eq!(
+            names.rename_text("Use aw_list_buckets, then aw_get_events_x.", TOOLS),
+            "Use activitywatch_list_buckets, then aw_get_events_x."
+        );
+        assert!(names.check(TOOLS).is_ok());
+    }
+
+    #[test]
+    fn rejects_conflicting_or_unknown_renames() {
+        let names = ToolNames::default()
+            .with_renames("aw_status=aw_get_events")
+            .unwrap();
+        assert!(names.check(TOOLS).is_err());
+        let names = ToolNames::default().with_renames("aw_missing=x").unwrap();
+        assert!(names.check(TOOLS).is_err());
+        assert!(
+            ToolNames::default()
+                .with_renames("aw_status=has space")
+                .is_err()
+        );
+        assert!(ToolNames::default().with_prefix("a.b").is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use crate::analysis::{Interval, contains_instant, merge_intervals};
+use crate::models::{Event, EventDuration};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+
+tokio::task_local! {
+    static INCLUDE_PRIVATE: bool;
+}
+
+/// Run a future with do-not-track time included (or not) in everything the
+/// client returns
+pub async fn including_private<F: Future>(include: bool, future: F) -> F::Output {
+    INCLUDE_PRIVATE.scope(include, future).await
+}
+
+/// Whether the current tool call asked for do-not-track time
+pub fn private_included() -> bool {
+    INCLUDE_PRIVATE
+        .try_with(|include| *include)
+        .unwrap_or(false)
+}
+
+/// Personal time that is left out of all data, e.g. `2024-08-01..2024-08-14`
+/// (a vacation), `weekends`, or `mon-fri 18:00-08:00` (evenings and nights
+/// on weekdays). Times are local to the configured timezone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DoNotTrackRule {
+    /// Whole local dates, inclusive
+    Dates {
+        /// First day
+        first: NaiveDate,
+
+        /// Last day
+        last: NaiveDate,
+    },
+    /// The same hours on some days of the week
+    Weekly {
+        /// Days of the week the hours start on
+        days: Vec<Weekday>,
+
+        /// Start and end of the hours, crossing midnight when the end is not
+        /// after the start; the whole day when unset
+        hours: Option<(NaiveTime, NaiveTime)>,
+    },
+}
+
+/// All days of the week, Monday first
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+impl FromStr for DoNotTrackRule {
+    type Err = String;
+
+    /// Parse a date (`2024-12-24`), an inclusive date range
+    /// (`2024-08-01..2024-08-14`), or days of the week (`weekends`,
+    /// `weekdays`, `daily`, `sat`, `mon-fri`, `mon,wed`) and/or hours
+    /// (`18:00-08:00`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let date = |text: &str| {
+            NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("Invalid date '{}', expected e.g. 2024-08-01", text.trim()))
+        };
+        if s.starts_with(|c: char| c.is_ascii_digit()) && !s.contains(':') {
+            let (first, last) = match s.split_once("..") {
+                Some((first, last)) => (date(first)?, date(last)?),
+                None => (date(s)?, date(s)?),
+            };
+            if last < first {
+                return Err(format!("Date range '{}' ends before it starts", s));
+            }
+            return Ok(Self::Dates { first, last });
+        }
+
+        let (days, hours) = match s.split_once(char::is_whitespace) {
+            Some((days, hours)) => (days, Some(hours.trim())),
+            None if s.contains(':') => ("daily", Some(s)),
+            None => (s, None),
+        };
+        let hours = hours
+            .map(|hours| {
+                let invalid = || format!("Invalid hours '{}', expected e.g. 18:00-08:00", hours);
+                let (start, end) = hours.split_once('-').ok_or_else(invalid)?;
+                let time =
+                    |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+                Ok::<_, String>((time(start)?, time(end)?))
+            })
+            .transpose()?;
+        Ok(Self::Weekly {
+            days: parse_days(days)?,
+            hours,
+        })
+    }
+}
+
+/// Days of the week of a `weekends`, `mon-fri` or `mon,wed` spec
+fn parse_days(spec: &str) -> Result<Vec<Weekday>, String> {
+    let day = |name: &str| {
+        name.trim().parse::<Weekday>().map_err(|_| {
+            format!(
+                "Invalid day '{}', expected e.g. sat or mon-fri",
+                name.trim()
+            )
+        })
+    };
+    let mut days = Vec::new();
+    for part in spec.to_ascii_lowercase().split(',') {
+        match part.trim() {
+            "daily" => days.extend(WEEK),
+            "weekdays" => days.extend(&WEEK[..5]),
+            "weekends" => days.extend(&WEEK[5..]),
+            part => match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (day(first)?, day(last)?);
+                    let mut current = first;
+                    days.push(current);
+                    while current != last {
+                        current = current.succ();
+                        days.push(current);
+                    }
+                }
+                None => days.push(day(part)?),
+            },
+        }
+    }
+    days.sort_by_key(Weekday::num_days_from_monday);
+    days.dedup();
+    Ok(days)
+}
+
+impl fmt::Display for DoNotTrackRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dates { first, last } if first == last => write!(f, "{}", first),
+            Self::Dates { first, last } => write!(f, "{}..{}", first, last),
+            Self::Weekly { days, hours } => {
+                let names: Vec<String> = days
+                    .iter()
+                    .map(|day| day.to_string().to_lowercase())
+                    .collect();
+                write!(f, "{}", names.join(","))?;
+                match hours {
+                    Some((start, end)) => {
+                        write!(f, " {}-{}", start.format("%H:%M"), end.format("%H:%M"))
+                    }
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// The do-not-track rules, resolved in a timezone
+#[derive(Debug, Clone, Default)]
+pub struct DoNotTrack {
+    rules: Vec<DoNotTrackRule>,
+    timezone: Timezone,
+}
+
+impl DoNotTrack {
+    /// Resolve the rules in the given timezone
+    pub fn new(rules: Vec<DoNotTrackRule>, timezone: Timezone) -> Self {
+        Self { rules, timezone }
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Merged do-not-track intervals overlapping `[start, end)`
+    pub fn intervals(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Interval> {
+        if self.rules.is_empty() || end <= start {
+            return Vec::new();
+        }
+        let tz = &self.timezone;
+        let at = |date: NaiveDate, time: NaiveTime| tz.from_local(date.and_time(time));
+        let mut intervals = Vec::new();
+        // Hours starting the day before may reach past midnight
+        let mut date = tz.date_of(start) - Duration::days(1);
+        while date <= tz.date_of(end) {
+            let next = date + Duration::days(1);
+            for rule in &self.rules {
+                match rule {
+                    DoNotTrackRule::Dates { first, last } if (*first..=*last).contains(&date) => {
+                        intervals.push((tz.start_of_day(date), tz.start_of_day(next)));
+                    }
+                    DoNotTrackRule::Weekly { days, hours } if days.contains(&date.weekday()) => {
+                        intervals.push(match hours {
+                            None => (tz.start_of_day(date), tz.start_of_day(next)),
+                            Some((from, to)) if from < to => (at(date, *from), at(date, *to)),
+                            Some((from, to)) => (at(date, *from), at(next, *to)),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            date = next;
+        }
+        merge_intervals(
+            intervals
+                .into_iter()
+                .map(|(s, e)| (s.max(start), e.min(end)))
+                .collect(),
+        )
+    }
+
+    /// Whether any do-not-track time falls within `[start, end)`
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        !self.intervals(start, end).is_empty()
+    }
+
+    /// Cut do-not-track time out of events: events inside it are dropped,
+    /// events reaching into it are trimmed (or split around it, the later
+    /// part first as events are listed newest first). Returns the remaining
+    /// events and how many events were changed.
+    pub fn exclude(&self, events: Vec<Event>) -> (Vec<Event>, usize) {
+        let start = events.iter().map(|event| event.timestamp).min();
+        let end = events.iter().map(Event::end).max();
+        let (Some(start), Some(end)) = (start, end) else {
+            return (events, 0);
+        };
+        // Zero-duration events at the end still need their instant covered
+        let private = self.intervals(start, end + Duration::milliseconds(1));
+        if private.is_empty() {
+            return (events, 0);
+        }
+
+        let mut changed = 0;
+        let mut kept = Vec::with_capacity(events.len());
+        for event in events {
+            let parts = public_parts(&event, &private);
+            if parts.len() == 1 && parts[0] == (event.timestamp, event.end()) {
+                kept.push(event);
+                continue;
+            }
+            changed += 1;
+            for (start, end) in parts.into_iter().rev() {
+                let mut part = event.clone();
+                part.timestamp = start;
+                part.duration = EventDuration::between(start, end);
+                kept.push(part);
+            }
+        }
+        (kept, changed)
+    }
+}
+
+/// The parts of an event outside the merged private intervals
+fn public_parts(event: &Event, private: &[Interval]) -> Vec<Interval> {
+    let (start, end) = (event.timestamp, event.end());
+    if start == end {
+        return match contains_instant(private, start) {
+            true => Vec::new(),
+            false => vec![(start, end)],
+        };
+    }
+    let mut parts = Vec::new();
+    let mut cursor = start;
+    for &(p_start, p_end) in private {
+        if p_end <= cursor || p_start >= end {
+            continue;
+        }
+        if p_start > cursor {
+            parts.push((cursor, p_start));
+        }
+        cursor = cursor.max(p_end);
+    }
+    if cursor < end {
+        parts.push((cursor, end));
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn event(start: DateTime<Utc>, minutes: i64) -> Event {
+        Event::builder()
+            .timestamp(start)
+            .duration_secs(minutes as f64 * 60.0)
+            .data("app", "Code")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_do_not_track_rules() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 8, d).unwrap();
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        assert_eq!(
+            "2024-08-01..2024-08-14".parse::<DoNotTrackRule>().unwrap(),
+            DoNotTrackRule::Dates {
+                first: date(1),
+                last: date(14),
+            }
+        );
+        let rule: DoNotTrackRule = "mon-wed,sat 18:00-08:00".parse().unwrap();
+        assert_eq!(
+            rule,
+            DoNotTrackRule::Weekly {
+                days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Sat],
+                hours: Some((time(18), time(8))),
+            }
+        );
+        assert_eq!(rule.to_string(), "mon,tue,wed,sat 18:00-08:00");
+        assert_eq!(rule.to_string().parse::<DoNotTrackRule>().unwrap(), rule);
+        assert_eq!(
+            "weekends".parse::<DoNotTrackRule>().unwrap().to_string(),
+            "sat,sun"
+        );
+        assert_eq!(
+            "12:00-13:00".parse::<DoNotTrackRule>().unwrap().to_string(),
+            "mon,tue,wed,thu,fri,sat,sun 12:00-13:00"
+        );
+        assert!("2024-08-14..2024-08-01".parse::<DoNotTrackRule>().is_err());
+        assert!("someday".parse::<DoNotTrackRule>().is_err());
+        assert!("sat 18:00".parse::<DoNotTrackRule>().is_err());
+    }
+
+    #[test]
+    fn cuts_do_not_track_time_out_of_events() {
+        // 2024-03-15 is a Friday
+        let rules = vec![
+            "thu,fri 18:00-08:00".parse().unwrap(),
+            "weekends".parse().unwrap(),
+        ];
+        let dnt = DoNotTrack::new(rules, Timezone::Utc);
+        assert_eq!(
+            dnt.intervals(at(15, 0, 0), at(18, 12, 0)),
+            [(at(15, 0, 0), at(15, 8, 0)), (at(15, 18, 0), at(18, 0, 0))]
+        );
+        assert!(!dnt.overlaps(at(15, 9, 0), at(15, 17, 0)));
+
+        let events = vec![
+            event(at(15, 17, 30), 60),
+            event(at(15, 12, 0), 30),
+            event(at(15, 7, 0), 120),
+            event(at(16, 10, 0), 0),
+        ];
+        let (kept, changed) = dnt.exclude(events);
+        assert_eq!(changed, 3);
+        let parts: Vec<_> = kept.iter().map(|e| (e.timestamp, e.end())).collect();
+        assert_eq!(
+            parts,
+            [
+                (at(15, 17, 30), at(15, 18, 0)),
+                (at(15, 12, 0), at(15, 12, 30)),
+                (at(15, 8, 0), at(15, 9, 0)),
+            ]
+        );
+    }
+}
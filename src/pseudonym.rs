@@ -0,0 +1,178 @@
+//! Pseudonymization of hostnames and OS usernames.
+//!
+//! Known names are replaced in tool output by stable salted hashes such as
+//! `host-3fa2c1d0`, and pseudonyms in tool arguments are mapped back, so an
+//! assistant can keep referring to devices without learning their names.
+//! This hides identities from casual view; it is not anonymization against
+//! someone who can guess and hash candidate names with the salt.
+
+/// Home directory prefixes followed by a username
+const HOME_PREFIXES: &[&str] = &["/home/", "/Users/", "\\Users\\", "\\\\Users\\\\"];
+
+/// Maps known hostnames and usernames to pseudonyms and back
+#[derive(Debug, Clone, Default)]
+pub struct Pseudonymizer {
+    salt: String,
+    /// (real name, pseudonym), longest real name first
+    names: Vec<(String, String)>,
+}
+
+impl Pseudonymizer {
+    /// Create a pseudonymizer for the given hostnames and usernames
+    pub fn new<'a>(
+        salt: &str,
+        hostnames: impl IntoIterator<Item = &'a str>,
+        usernames: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let mut pseudonymizer = Self {
+            salt: salt.to_string(),
+            names: Vec::new(),
+        };
+        for hostname in hostnames {
+            pseudonymizer.add("host", hostname);
+        }
+        for username in usernames {
+            pseudonymizer.add("user", username);
+        }
+        pseudonymizer
+            .names
+            .sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        pseudonymizer.names.dedup();
+        pseudonymizer
+    }
+
+    fn add(&mut self, kind: &str, name: &str) {
+        let name = name.trim();
+        if name.is_empty() || name == "unknown" {
+            return;
+        }
+        let pseudonym = format!("{}-{:08x}", kind, self.hash(name) as u32);
+        // Markdown output escapes underscores
+        if name.contains('_') {
+            self.names
+                .push((name.replace('_', "\\_"), pseudonym.clone()));
+        }
+        self.names.push((name.to_string(), pseudonym));
+    }
+
+    /// Stable salted FNV-1a hash of a name
+    fn hash(&self, name: &str) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.salt.bytes().chain([0]).chain(name.bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    /// Replace known names, and usernames in home directory paths, with
+    /// pseudonyms
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (name, pseudonym) in &self.names {
+            text = replace_word(&text, name, pseudonym);
+        }
+        self.hash_home_directories(&text)
+    }
+
+    /// Map pseudonyms in a tool argument back to the real names
+    pub fn reveal(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (name, pseudonym) in self.names.iter().filter(|(name, _)| !name.contains('\\')) {
+            text = replace_word(&text, pseudonym, name);
+        }
+        text
+    }
+
+    /// Map pseudonyms back in every string of a JSON value
+    pub fn reveal_value(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => *s = self.reveal(s),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.reveal_value(v)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|v| self.reveal_value(v)),
+            _ => {}
+        }
+    }
+
+    /// Replace the username segment after `/home/`, `/Users/` and
+    /// `C:\Users\`
+    fn hash_home_directories(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some((pos, prefix)) = HOME_PREFIXES
+            .iter()
+            .filter_map(|prefix| rest.find(prefix).map(|pos| (pos, *prefix)))
+            .min_by_key(|(pos, prefix)| (*pos, std::cmp::Reverse(prefix.len())))
+        {
+            let start = pos + prefix.len();
+            let len = rest[start..]
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '.' | '_' | '-')))
+                .unwrap_or(rest.len() - start);
+            result.push_str(&rest[..start]);
+            let username = &rest[start..start + len];
+            if username.is_empty() || username.starts_with("user-") || username == "Shared" {
+                result.push_str(username);
+            } else {
+                result.push_str(&format!("user-{:08x}", self.hash(username) as u32));
+            }
+            rest = &rest[start + len..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// Replace occurrences of `from` that are not part of a longer alphanumeric
+/// word (so `mac` in `aw-watcher-window_mac` matches, but not in `machine`)
+fn replace_word(text: &str, from: &str, to: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (pos, _) in text.match_indices(from) {
+        if pos < last {
+            continue;
+        }
+        let before = text[..pos].chars().next_back();
+        let after = text[pos + from.len()..].chars().next();
+        if before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+        result.push_str(&text[last..pos]);
+        result.push_str(to);
+        last = pos + from.len();
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pseudonymizes_names_and_reveals_them_again() {
+        let pseudonymizer = Pseudonymizer::new("salt", ["mac"], ["alice"]);
+        let output =
+            pseudonymizer.apply("aw-watcher-window_mac on machine, alice in /home/bob/src");
+
+        assert!(!output.contains("_mac") && output.contains("machine"));
+        assert!(!output.contains("alice") && !output.contains("bob"));
+        assert_eq!(
+            output,
+            pseudonymizer.apply("aw-watcher-window_mac on machine, alice in /home/bob/src")
+        );
+
+        let pseudonym = output
+            .split_whitespace()
+            .next()
+            .unwrap()
+            .trim_start_matches("aw-watcher-window_");
+        assert_eq!(pseudonymizer.reveal(pseudonym), "mac");
+    }
+
+    #[test]
+    fn salt_changes_pseudonyms() {
+        let a = Pseudonymizer::new("a", ["laptop"], []).apply("laptop");
+        let b = Pseudonymizer::new("b", ["laptop"], []).apply("laptop");
+        assert!(a.starts_with("host-") && a != b);
+    }
+}
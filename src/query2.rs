@@ -0,0 +1,749 @@
+use crate::analysis::{Interval, TimeRange, merge_intervals};
+use crate::categories::{Categories, Regex, UNCATEGORIZED};
+use crate::models::{Bucket, Event, EventDuration};
+use reqwest::Url;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+
+/// Functions the local interpreter implements
+pub const LOCAL_FUNCTIONS: &[&str] = &[
+    "query_bucket",
+    "query_bucket_names",
+    "find_bucket",
+    "flood",
+    "merge_events_by_keys",
+    "filter_keyvals",
+    "filter_keyvals_regex",
+    "exclude_keyvals",
+    "filter_period_intersect",
+    "period_union",
+    "limit_events",
+    "sort_by_duration",
+    "sort_by_timestamp",
+    "sum_durations",
+    "concat",
+    "split_url_events",
+    "categorize",
+];
+
+/// Functions of aw-core's query language that aw-server-rust does not
+/// implement
+pub const PYTHON_ONLY_FUNCTIONS: &[&str] = &[
+    "exclude_keyvals",
+    "period_union",
+    "union_no_overlap",
+    "simplify_window_titles",
+];
+
+/// Default pulse time of `flood`, in seconds
+const FLOOD_PULSETIME: f64 = 5.0;
+
+/// A query2 script, parsed for local evaluation.
+///
+/// This covers the part of aw-server's query language that reports use:
+/// assignments of string, number, list and dict literals, variables and
+/// calls of the [`LOCAL_FUNCTIONS`], ending with an assignment to `RETURN`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryScript {
+    statements: Vec<(String, Expr)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    Variable(String),
+    List(Vec<Expr>),
+    Dict(Vec<(String, Expr)>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Text(String),
+    Number(f64),
+    Symbol(char),
+}
+
+/// Buckets and their events of one time period, as a script sees them
+pub struct QueryData<'a> {
+    /// The time period; events are cropped to it
+    pub range: TimeRange,
+
+    /// All buckets, for `find_bucket` and `query_bucket_names`
+    pub buckets: &'a HashMap<String, Bucket>,
+
+    /// Events of the buckets the script reads, in the period
+    pub events: &'a HashMap<String, Vec<Event>>,
+}
+
+impl QueryScript {
+    /// Parse a script
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let mut statements = Vec::new();
+        while parser.peek().is_some() {
+            if parser.eat(';') {
+                continue;
+            }
+            let name = match parser.next() {
+                Some(Token::Name(name)) => name,
+                other => {
+                    return Err(format!(
+                        "Expected an assignment, found {}",
+                        describe(&other)
+                    ));
+                }
+            };
+            if !parser.eat('=') {
+                return Err(format!("Expected '=' after '{}'", name));
+            }
+            statements.push((name, parser.expr()?));
+            if parser.peek().is_some() && !parser.eat(';') {
+                return Err(format!("Expected ';', found {}", describe(&parser.next())));
+            }
+        }
+        if !statements.iter().any(|(name, _)| name == "RETURN") {
+            return Err("The query has no RETURN".to_string());
+        }
+        Ok(Self { statements })
+    }
+
+    /// Names of the functions the script calls that the local interpreter
+    /// does not implement
+    pub fn unsupported_functions(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .calls()
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .filter(|name| !LOCAL_FUNCTIONS.contains(&name.as_str()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Whether the script calls a function
+    pub fn calls_function(&self, name: &str) -> bool {
+        self.calls().iter().any(|(called, _)| *called == name)
+    }
+
+    /// IDs of the buckets whose events the script may read: string literals
+    /// naming a bucket, and the results of `find_bucket` with literal
+    /// arguments
+    pub fn referenced_buckets(&self, buckets: &HashMap<String, Bucket>) -> Vec<String> {
+        let mut ids = Vec::new();
+        for (name, args) in self.calls() {
+            let literals: Vec<&str> = args
+                .iter()
+                .filter_map(|arg| match arg {
+                    Expr::Literal(Value::String(text)) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            match name {
+                "find_bucket" => ids.extend(
+                    literals
+                        .first()
+                        .and_then(|prefix| find_bucket(buckets, prefix, literals.get(1).copied())),
+                ),
+                _ => ids.extend(
+                    literals
+                        .iter()
+                        .filter(|text| buckets.contains_key(**text))
+                        .map(|text| text.to_string()),
+                ),
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Run the script over one time period and return the value of `RETURN`
+    pub fn run(&self, data: &QueryData<'_>) -> Result<Value, String> {
+        let mut variables: HashMap<&str, Value> = HashMap::new();
+        for (name, expr) in &self.statements {
+            let value = eval(expr, &variables, data)?;
+            variables.insert(name, value);
+        }
+        Ok(variables.remove("RETURN").unwrap_or(Value::Null))
+    }
+
+    /// All function calls with their arguments, including nested ones
+    fn calls(&self) -> Vec<(&str, &[Expr])> {
+        fn visit<'a>(expr: &'a Expr, calls: &mut Vec<(&'a str, &'a [Expr])>) {
+            match expr {
+                Expr::Call(name, args) => {
+                    calls.push((name, args));
+                    args.iter().for_each(|arg| visit(arg, calls));
+                }
+                Expr::List(items) => items.iter().for_each(|item| visit(item, calls)),
+                Expr::Dict(entries) => entries.iter().for_each(|(_, value)| visit(value, calls)),
+                Expr::Literal(_) | Expr::Variable(_) => {}
+            }
+        }
+        let mut calls = Vec::new();
+        self.statements
+            .iter()
+            .for_each(|(_, expr)| visit(expr, &mut calls));
+        calls
+    }
+}
+
+/// Describe a token for error messages
+fn describe(token: &Option<Token>) -> String {
+    match token {
+        Some(Token::Name(name)) => format!("'{}'", name),
+        Some(Token::Text(text)) => format!("\"{}\"", text),
+        Some(Token::Number(n)) => n.to_string(),
+        Some(Token::Symbol(c)) => format!("'{}'", c),
+        None => "the end of the query".to_string(),
+    }
+}
+
+/// Split a script into tokens
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(escaped) => value.push(escaped),
+                            None => return Err("Unterminated string".to_string()),
+                        },
+                        Some(end) if end == c => break,
+                        Some(other) => value.push(other),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Text(value));
+            }
+            _ if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                while let Some(&d) = chars.peek()
+                    && (d.is_ascii_digit() || matches!(d, '.' | '-' | 'e' | 'E'))
+                {
+                    number.push(d);
+                    chars.next();
+                }
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("Invalid number '{}'", number))?;
+                tokens.push(Token::Number(value));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&d) = chars.peek()
+                    && (d.is_alphanumeric() || d == '_')
+                {
+                    name.push(d);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name));
+            }
+            '=' | ';' | ',' | ':' | '(' | ')' | '[' | ']' | '{' | '}' => {
+                chars.next();
+                tokens.push(Token::Symbol(c));
+            }
+            _ => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consume a symbol if it comes next
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(Expr::Literal(Value::String(text))),
+            Some(Token::Number(n)) => Ok(Expr::Literal(json!(n))),
+            Some(Token::Name(name)) if self.eat('(') => {
+                Ok(Expr::Call(name, self.items(')', Self::expr)?))
+            }
+            Some(Token::Name(name)) => Ok(match name.as_str() {
+                "true" | "True" => Expr::Literal(Value::Bool(true)),
+                "false" | "False" => Expr::Literal(Value::Bool(false)),
+                "null" | "None" => Expr::Literal(Value::Null),
+                _ => Expr::Variable(name),
+            }),
+            Some(Token::Symbol('[')) => Ok(Expr::List(self.items(']', Self::expr)?)),
+            Some(Token::Symbol('{')) => Ok(Expr::Dict(self.items('}', Self::entry)?)),
+            other => Err(format!("Expected a value, found {}", describe(&other))),
+        }
+    }
+
+    /// A `"key": value` entry of a dict
+    fn entry(&mut self) -> Result<(String, Expr), String> {
+        let key = match self.next() {
+            Some(Token::Text(key)) => key,
+            other => return Err(format!("Expected a dict key, found {}", describe(&other))),
+        };
+        if !self.eat(':') {
+            return Err(format!("Expected ':' after \"{}\"", key));
+        }
+        Ok((key, self.expr()?))
+    }
+
+    /// Comma-separated items up to a closing symbol
+    fn items<T>(
+        &mut self,
+        close: char,
+        item: fn(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            items.push(item(self)?);
+            if !self.eat(',') && self.peek() != Some(&Token::Symbol(close)) {
+                let found = describe(&self.next());
+                return Err(format!("Expected ',' or '{}', found {}", close, found));
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// Evaluate an expression
+fn eval(
+    expr: &Expr,
+    variables: &HashMap<&str, Value>,
+    data: &QueryData<'_>,
+) -> Result<Value, String> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Variable(name) => variables
+            .get(name.as_str())
+            .cloned()
+            .ok_or_else(|| format!("Unknown variable '{}'", name)),
+        Expr::List(items) => items
+            .iter()
+            .map(|item| eval(item, variables, data))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Expr::Dict(entries) => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                map.insert(key.clone(), eval(value, variables, data)?);
+            }
+            Ok(Value::Object(map))
+        }
+        Expr::Call(name, args) => {
+            let args: Vec<Value> = args
+                .iter()
+                .map(|arg| eval(arg, variables, data))
+                .collect::<Result<_, _>>()?;
+            call(name, &args, data).map_err(|e| format!("{}: {}", name, e))
+        }
+    }
+}
+
+/// Call a function
+fn call(name: &str, args: &[Value], data: &QueryData<'_>) -> Result<Value, String> {
+    let arg = |i: usize| {
+        args.get(i)
+            .ok_or_else(|| format!("missing argument {}", i + 1))
+    };
+    let text = |i: usize| {
+        arg(i)?
+            .as_str()
+            .map(str::to_string)
+            .ok_or(format!("argument {} must be a string", i + 1))
+    };
+    let events = |i: usize| {
+        serde_json::from_value::<Vec<Event>>(arg(i)?.clone())
+            .map_err(|_| format!("argument {} must be a list of events", i + 1))
+    };
+    let strings = |i: usize| -> Result<Vec<Value>, String> {
+        arg(i)?
+            .as_array()
+            .cloned()
+            .ok_or(format!("argument {} must be a list", i + 1))
+    };
+    let to_value = |events: Vec<Event>| serde_json::to_value(events).map_err(|e| e.to_string());
+
+    match name {
+        "query_bucket" => {
+            let id = text(0)?;
+            let events = data
+                .events
+                .get(&id)
+                .ok_or_else(|| format!("There's no bucket named {}", id))?;
+            to_value(
+                events
+                    .iter()
+                    .filter_map(|event| {
+                        let (start, end) = data.range.clip((event.timestamp, event.end()))?;
+                        Some(with_interval(event, start, end))
+                    })
+                    .collect(),
+            )
+        }
+        "query_bucket_names" => {
+            let mut ids: Vec<&String> = data.buckets.keys().collect();
+            ids.sort();
+            Ok(json!(ids))
+        }
+        "find_bucket" => {
+            let prefix = text(0)?;
+            let hostname = args.get(1).and_then(|h| h.as_str());
+            find_bucket(data.buckets, &prefix, hostname)
+                .map(Value::String)
+                .ok_or_else(|| format!("Unable to find bucket matching '{}'", prefix))
+        }
+        "flood" => {
+            let pulsetime = args
+                .get(1)
+                .and_then(|p| p.as_f64())
+                .unwrap_or(FLOOD_PULSETIME);
+            to_value(flood(events(0)?, pulsetime))
+        }
+        "merge_events_by_keys" => {
+            let keys: Vec<String> = strings(1)?
+                .iter()
+                .filter_map(|k| k.as_str().map(str::to_string))
+                .collect();
+            to_value(merge_by_keys(events(0)?, &keys))
+        }
+        "filter_keyvals" | "exclude_keyvals" => {
+            let key = text(1)?;
+            let values = strings(2)?;
+            let keep = name == "filter_keyvals";
+            to_value(
+                events(0)?
+                    .into_iter()
+                    .filter(|e| e.data.get(&key).is_some_and(|v| values.contains(v)) == keep)
+                    .collect(),
+            )
+        }
+        "filter_keyvals_regex" => {
+            let key = text(1)?;
+            let regex = Regex::new(&text(2)?, false)?;
+            to_value(
+                events(0)?
+                    .into_iter()
+                    .filter(|e| {
+                        e.data
+                            .get(&key)
+                            .and_then(|v| v.as_str())
+                            .is_some_and(|v| regex.is_match(v))
+                    })
+                    .collect(),
+            )
+        }
+        "filter_period_intersect" => {
+            let periods =
+                merge_intervals(events(1)?.iter().map(|e| (e.timestamp, e.end())).collect());
+            let mut kept = Vec::new();
+            for event in events(0)? {
+                for (start, end) in &periods {
+                    let from = event.timestamp.max(*start);
+                    let to = event.end().min(*end);
+                    if to > from {
+                        kept.push(with_interval(&event, from, to));
+                    }
+                }
+            }
+            to_value(kept)
+        }
+        "period_union" => {
+            let mut intervals: Vec<Interval> =
+                events(0)?.iter().map(|e| (e.timestamp, e.end())).collect();
+            intervals.extend(events(1)?.iter().map(|e| (e.timestamp, e.end())));
+            Ok(Value::Array(
+                merge_intervals(intervals)
+                    .into_iter()
+                    .map(|(start, end)| {
+                        json!({
+                            "timestamp": start,
+                            "duration": EventDuration::between(start, end).as_secs_f64(),
+                            "data": {},
+                        })
+                    })
+                    .collect(),
+            ))
+        }
+        "limit_events" => {
+            let count = arg(1)?.as_f64().ok_or("argument 2 must be a number")?;
+            to_value(
+                events(0)?
+                    .into_iter()
+                    .take(count.max(0.0) as usize)
+                    .collect(),
+            )
+        }
+        "sort_by_duration" => {
+            let mut events = events(0)?;
+            events.sort_by(|a, b| {
+                b.duration
+                    .as_secs_f64()
+                    .total_cmp(&a.duration.as_secs_f64())
+            });
+            to_value(events)
+        }
+        "sort_by_timestamp" => {
+            let mut events = events(0)?;
+            events.sort_by_key(|e| e.timestamp);
+            to_value(events)
+        }
+        "sum_durations" => {
+            let total = events(0)?
+                .iter()
+                .fold(0.0, |t, e| t + e.duration.as_secs_f64());
+            Ok(json!(total))
+        }
+        "concat" => {
+            let mut all = Vec::new();
+            for i in 0..args.len() {
+                all.extend(strings(i)?);
+            }
+            Ok(Value::Array(all))
+        }
+        "split_url_events" => to_value(events(0)?.into_iter().map(split_url).collect()),
+        "categorize" => {
+            // [[["Work", "Programming"], {"type": "regex", "regex": "..."}], ...]
+            let classes: Vec<Value> = strings(1)?
+                .into_iter()
+                .map(|class| json!({ "name": class.get(0), "rule": class.get(1) }))
+                .collect();
+            let categories = Categories::from_webui(&Value::Array(classes))?;
+            to_value(
+                events(0)?
+                    .into_iter()
+                    .map(|mut event| {
+                        let category = match categories.classify(&event) {
+                            Some(path) => json!(path),
+                            None => json!([UNCATEGORIZED]),
+                        };
+                        event.data.insert("$category".to_string(), category);
+                        event
+                    })
+                    .collect(),
+            )
+        }
+        _ => Err("unknown function".to_string()),
+    }
+}
+
+/// The first bucket (by ID) starting with `prefix`, on `hostname` if given
+fn find_bucket(
+    buckets: &HashMap<String, Bucket>,
+    prefix: &str,
+    hostname: Option<&str>,
+) -> Option<String> {
+    let mut ids: Vec<&Bucket> = buckets
+        .values()
+        .filter(|bucket| bucket.id.starts_with(prefix))
+        .filter(|bucket| hostname.is_none_or(|host| bucket.hostname.as_deref() == Some(host)))
+        .collect();
+    ids.sort_by(|a, b| a.id.cmp(&b.id));
+    ids.first().map(|bucket| bucket.id.clone())
+}
+
+/// A copy of an event moved to `[start, end)`
+fn with_interval(
+    event: &Event,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+) -> Event {
+    let mut event = event.clone();
+    event.timestamp = start;
+    event.duration = EventDuration::between(start, end);
+    event
+}
+
+/// Merge consecutive events with equal data and fill gaps of up to
+/// `pulsetime` seconds by extending the earlier event, like aw-core's `flood`
+fn flood(mut events: Vec<Event>, pulsetime: f64) -> Vec<Event> {
+    events.sort_by_key(|e| e.timestamp);
+    let pulse = chrono::Duration::milliseconds((pulsetime * 1000.0) as i64);
+    let mut flooded: Vec<Event> = Vec::with_capacity(events.len());
+    for event in events {
+        if let Some(last) = flooded.last_mut() {
+            let gap = event.timestamp - last.end();
+            if gap <= pulse {
+                if last.data == event.data {
+                    let end = last.end().max(event.end());
+                    last.duration = EventDuration::between(last.timestamp, end);
+                    continue;
+                }
+                if gap > chrono::Duration::zero() {
+                    last.duration = EventDuration::between(last.timestamp, event.timestamp);
+                }
+            }
+        }
+        flooded.push(event);
+    }
+    flooded
+}
+
+/// Sum the durations of events with equal values of `keys`, keeping only
+/// those keys, in order of first occurrence
+fn merge_by_keys(events: Vec<Event>, keys: &[String]) -> Vec<Event> {
+    let mut merged: Vec<Event> = Vec::new();
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    for event in events {
+        let values: Option<Vec<Value>> = keys.iter().map(|k| event.data.get(k).cloned()).collect();
+        let Some(values) = values else {
+            continue;
+        };
+        let key: Vec<String> = values.iter().map(Value::to_string).collect();
+        match index.get(&key) {
+            Some(&i) => {
+                let secs = merged[i].duration.as_secs_f64() + event.duration.as_secs_f64();
+                merged[i].duration = EventDuration::from_secs_f64(secs).unwrap_or_default();
+            }
+            None => {
+                index.insert(key, merged.len());
+                let mut event = event;
+                event.data = keys.iter().cloned().zip(values).collect();
+                merged.push(event);
+            }
+        }
+    }
+    merged
+}
+
+/// Add `$protocol`, `$domain`, `$path` and `$params` from an event's `url`
+fn split_url(mut event: Event) -> Event {
+    let Some(url) = event
+        .data
+        .get("url")
+        .and_then(|u| u.as_str())
+        .and_then(|u| Url::parse(u).ok())
+    else {
+        return event;
+    };
+    let domain = url.host_str().unwrap_or_default();
+    let parts = [
+        ("$protocol", url.scheme()),
+        ("$domain", domain.strip_prefix("www.").unwrap_or(domain)),
+        ("$path", url.path()),
+        ("$params", url.query().unwrap_or_default()),
+    ];
+    for (key, value) in parts {
+        event.data.insert(key.to_string(), json!(value));
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn event(minute: u32, secs: f64, app: &str) -> Event {
+        Event::builder()
+            .timestamp(Utc.with_ymd_and_hms(2024, 3, 15, 9, minute, 0).unwrap())
+            .duration_secs(secs)
+            .data("app", app)
+            .build()
+            .unwrap()
+    }
+
+    fn bucket(id: &str) -> Bucket {
+        serde_json::from_value(json!({"id": id, "hostname": "host"})).unwrap()
+    }
+
+    #[test]
+    fn runs_report_queries_locally() {
+        let script = QueryScript::parse(
+            r#"
+            events = flood(query_bucket(find_bucket("aw-watcher-window_", "host")));
+            events = filter_keyvals(events, "app", ["Code", "Firefox"]);
+            events = merge_events_by_keys(events, ["app"]);
+            events = sort_by_duration(events);
+            RETURN = {"events": limit_events(events, 1), "duration": sum_durations(events)};
+            "#,
+        )
+        .unwrap();
+        let id = "aw-watcher-window_host".to_string();
+        let buckets = HashMap::from([(id.clone(), bucket(&id))]);
+        assert_eq!(
+            script.referenced_buckets(&buckets),
+            ["aw-watcher-window_host"]
+        );
+        assert!(script.unsupported_functions().is_empty());
+
+        let events = HashMap::from([(
+            id,
+            vec![
+                event(0, 58.0, "Code"),
+                event(1, 60.0, "Firefox"),
+                event(2, 600.0, "Code"),
+                event(30, 60.0, "Slack"),
+            ],
+        )]);
+        let range = TimeRange::new(
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 15, 9, 10, 0).unwrap(),
+        )
+        .unwrap();
+        let data = QueryData {
+            range,
+            buckets: &buckets,
+            events: &events,
+        };
+        let result = script.run(&data).unwrap();
+
+        // Code: 60 (flooded) + 480 (cropped to the period); Firefox: 60
+        assert_eq!(result["duration"], 600.0);
+        assert_eq!(result["events"][0]["data"], json!({"app": "Code"}));
+        assert_eq!(result["events"][0]["duration"], 540.0);
+    }
+
+    #[test]
+    fn reports_query_errors() {
+        let error = QueryScript::parse(r#"events = query_bucket("a");"#).unwrap_err();
+        assert!(error.contains("RETURN"));
+        assert!(QueryScript::parse("RETURN = [1, 2").is_err());
+        let script = QueryScript::parse("RETURN = union_no_overlap(a, b)").unwrap();
+        assert_eq!(script.unsupported_functions(), ["union_no_overlap"]);
+
+        let script = QueryScript::parse("RETURN = query_bucket(\"missing\")").unwrap();
+        let range = TimeRange::new(Utc.timestamp_opt(0, 0).unwrap(), Utc::now()).unwrap();
+        let (buckets, events) = (HashMap::new(), HashMap::new());
+        let data = QueryData {
+            range,
+            buckets: &buckets,
+            events: &events,
+        };
+        assert_eq!(
+            script.run(&data).unwrap_err(),
+            "query_bucket: There's no bucket named missing"
+        );
+    }
+}
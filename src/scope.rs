@@ -0,0 +1,168 @@
+use crate::models::Bucket;
+use std::str::FromStr;
+
+/// The buckets a client may see.
+///
+/// A bucket is in scope when its ID matches one of the bucket patterns or
+/// its hostname matches one of the hostname patterns. Patterns may contain
+/// `*` wildcards. Parsed from entries like
+/// `host:laptop,bucket:aw-watcher-web-*`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessScope {
+    /// Bucket ID patterns
+    pub buckets: Vec<String>,
+
+    /// Hostname patterns
+    pub hostnames: Vec<String>,
+}
+
+impl AccessScope {
+    /// Whether the bucket is visible within this scope
+    pub fn allows(&self, bucket: &Bucket) -> bool {
+        self.allows_id(&bucket.id)
+            || bucket
+                .hostname
+                .as_deref()
+                .is_some_and(|host| self.hostnames.iter().any(|p| glob_match(p, host)))
+    }
+
+    /// Whether a bucket ID matches one of the bucket patterns (without
+    /// considering hostnames)
+    pub fn allows_id(&self, bucket_id: &str) -> bool {
+        self.buckets.iter().any(|p| glob_match(p, bucket_id))
+    }
+}
+
+impl FromStr for AccessScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut scope = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once(':') {
+                Some(("host", pattern)) if !pattern.trim().is_empty() => {
+                    scope.hostnames.push(pattern.trim().to_string())
+                }
+                Some(("bucket", pattern)) if !pattern.trim().is_empty() => {
+                    scope.buckets.push(pattern.trim().to_string())
+                }
+                _ => {
+                    return Err(format!(
+                        "Invalid scope entry '{}': expected 'host:<pattern>' or 'bucket:<pattern>'",
+                        entry
+                    ));
+                }
+            }
+        }
+        if scope.buckets.is_empty() && scope.hostnames.is_empty() {
+            return Err("Scope must list at least one host or bucket".to_string());
+        }
+        Ok(scope)
+    }
+}
+
+/// An auth token of the shared daemon endpoint and the buckets its client
+/// may see. Parsed from entries like `s3cret=host:laptop,bucket:aw-*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientToken {
+    /// The token the client authenticates with
+    pub token: String,
+
+    /// The buckets visible to the client
+    pub scope: AccessScope,
+}
+
+impl FromStr for ClientToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, scope) = s
+            .split_once('=')
+            .ok_or_else(|| "Invalid token entry: expected '<token>=<scope>'".to_string())?;
+        let token = token.trim();
+        if token.is_empty() || token.chars().any(char::is_whitespace) {
+            return Err("Tokens must be non-empty and contain no whitespace".to_string());
+        }
+        Ok(Self {
+            token: token.to_string(),
+            scope: scope.parse()?,
+        })
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn bucket(id: &str, hostname: &str) -> Bucket {
+        Bucket {
+            id: id.to_string(),
+            client: None,
+            bucket_type: None,
+            hostname: Some(hostname.to_string()),
+            created: None,
+            data: None,
+            last_updated: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn scopes_by_host_and_bucket_pattern() {
+        let scope: AccessScope = "host:laptop, bucket:aw-watcher-web-*".parse().unwrap();
+        assert!(scope.allows(&bucket("aw-watcher-window_laptop", "laptop")));
+        assert!(scope.allows(&bucket("aw-watcher-web-firefox", "desktop")));
+        assert!(!scope.allows(&bucket("aw-watcher-window_desktop", "desktop")));
+
+        assert!("laptop".parse::<AccessScope>().is_err());
+        assert!("".parse::<AccessScope>().is_err());
+    }
+
+    #[test]
+    fn parses_client_tokens() {
+        let client: ClientToken = "bob=host:bob-*".parse().unwrap();
+        assert_eq!(client.token, "bob");
+        assert!(
+            client
+                .scope
+                .allows(&bucket("aw-watcher-window_bob-pc", "bob-pc"))
+        );
+        assert!(
+            !client
+                .scope
+                .allows(&bucket("aw-watcher-window_alice-laptop", "alice-laptop"))
+        );
+        assert!("alice".parse::<ClientToken>().is_err());
+        assert!("=host:x".parse::<ClientToken>().is_err());
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(glob_match("a*c*e", "abcde"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("ab*b", "ab"));
+        assert!(!glob_match("laptop", "laptop2"));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::api::ActivityWatchClient;
+use crate::privacy::including_private;
+use crate::scope::AccessScope;
+use std::fmt;
+
+/// Maximum number of buckets whose events are sampled during a self-test
+const SELF_TEST_BUCKET_SAMPLE: usize = 10;
+
+/// Number of events fetched per sampled bucket
+const SELF_TEST_EVENT_LIMIT: i32 = 5;
+
+/// Outcome of a single self-test check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// What was checked
+    pub name: String,
+
+    /// Whether the check passed
+    pub passed: bool,
+
+    /// Details (result summary or error)
+    pub detail: String,
+}
+
+/// Diagnostic report produced by `run_self_test`
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    /// Individual check results in execution order
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfTestReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    fn record(&mut self, name: impl Into<String>, result: Result<String, String>) {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        self.checks.push(CheckResult {
+            name: name.into(),
+            passed,
+            detail,
+        });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ActivityWatch MCP Server self-test")?;
+        writeln!(f)?;
+        for check in &self.checks {
+            let status = if check.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+        let failed = self.checks.iter().filter(|c| !c.passed).count();
+        writeln!(f)?;
+        write!(
+            f,
+            "{} checks, {} passed, {} failed",
+            self.checks.len(),
+            self.checks.len() - failed,
+            failed
+        )
+    }
+}
+
+/// Exercise every read endpoint against the live server and validate that
+/// the responses parse into the crate's models
+pub async fn run_self_test(client: &ActivityWatchClient) -> SelfTestReport {
+    run_scoped_self_test(client, None).await
+}
+
+/// [`run_self_test`] limited to the buckets within an access scope
+pub(crate) async fn run_scoped_self_test(
+    client: &ActivityWatchClient,
+    scope: Option<&AccessScope>,
+) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+
+    report.record("API URL", Ok(client.api_url()));
+
+    report.record(
+        "Server info (/info)",
+        client
+            .get_info()
+            .await
+            .map(|info| {
+                format!(
+                    "{} {} on {}",
+                    info.flavor(),
+                    info.version.as_deref().unwrap_or("(unknown version)"),
+                    info.hostname.as_deref().unwrap_or("(unknown host)")
+                )
+            })
+            .map_err(|e| e.message.to_string()),
+    );
+
+    let buckets = match client.get_buckets().await {
+        Ok(mut buckets) => {
+            if let Some(scope) = scope {
+                buckets.retain(|_, bucket| scope.allows(bucket));
+            }
+            report.record(
+                "Bucket list (/buckets/)",
+                Ok(format!("{} buckets", buckets.len())),
+            );
+            buckets
+        }
+        Err(e) => {
+            report.record("Bucket list (/buckets/)", Err(e.message.to_string()));
+            return report;
+        }
+    };
+
+    let mut ids: Vec<&String> = buckets.keys().collect();
+    ids.sort();
+    if ids.len() > SELF_TEST_BUCKET_SAMPLE {
+        report.record(
+            "Bucket sample",
+            Ok(format!(
+                "checking {} of {} buckets",
+                SELF_TEST_BUCKET_SAMPLE,
+                ids.len()
+            )),
+        );
+    }
+
+    for id in ids.into_iter().take(SELF_TEST_BUCKET_SAMPLE) {
+        report.record(
+            format!("Bucket {}", id),
+            client
+                .get_bucket(id)
+                .await
+                .map(|b| b.bucket_type.unwrap_or_else(|| "(no type)".to_string()))
+                .map_err(|e| e.message.to_string()),
+        );
+        report.record(
+            format!("Events {}", id),
+            client
+                .get_events(id, Some(SELF_TEST_EVENT_LIMIT), None, None)
+                .await
+                .map(|events| format!("parsed {} events", events.len()))
+                .map_err(|e| e.message.to_string()),
+        );
+        report.record(
+            format!("Event count {}", id),
+            // Counted on aw-server rather than over every event
+            including_private(true, client.get_event_count(id, None, None))
+                .await
+                .map(|count| format!("{} events", count))
+                .map_err(|e| e.message.to_string()),
+        );
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeAwServer;
+
+    #[tokio::test]
+    async fn checks_only_buckets_in_scope() {
+        let server = FakeAwServer::start().await;
+        server.add_simple_bucket("aw-watcher-window_alice", "currentwindow", "alice");
+        server.add_simple_bucket("aw-watcher-window_bob", "currentwindow", "bob");
+        let client = ActivityWatchClient::new(server.url());
+
+        let scope: AccessScope = "host:alice".parse().unwrap();
+        let report = run_scoped_self_test(&client, Some(&scope)).await;
+        let text = report.to_string();
+
+        assert!(report.passed(), "{}", text);
+        assert!(text.contains("1 buckets"));
+        assert!(text.contains("aw-watcher-window_alice"));
+        assert!(!text.contains("aw-watcher-window_bob"));
+
+        let report = run_self_test(&client).await;
+        assert!(report.to_string().contains("aw-watcher-window_bob"));
+    }
+}
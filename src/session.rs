@@ -0,0 +1,152 @@
+use crate::constants::KEEPALIVE_TIMEOUT_SECS;
+use crate::tools::ActivityWatchMcpServer;
+use rmcp::model::{PingRequest, ServerRequest};
+use rmcp::service::{PeerRequestOptions, QuitReason, RunningService, ServiceError};
+use rmcp::transport::stdio;
+use rmcp::{Peer, RoleServer, ServiceExt};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Process exit code when the client closed or broke the stdio transport
+pub const EXIT_TRANSPORT_CLOSED: i32 = 3;
+
+/// Process exit code when the client stopped answering keepalive pings
+pub const EXIT_CLIENT_UNRESPONSIVE: i32 = 4;
+
+/// Delay between attempts to accept a new client while lingering
+const RECONNECT_POLL: Duration = Duration::from_secs(1);
+
+/// Why a stdio session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// The service was cancelled locally
+    Cancelled,
+    /// stdin reached EOF or the transport failed
+    TransportClosed,
+    /// The client stopped answering keepalive pings
+    ClientUnresponsive,
+}
+
+impl SessionEnd {
+    /// Process exit code for this outcome
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Cancelled => 0,
+            Self::TransportClosed => EXIT_TRANSPORT_CLOSED,
+            Self::ClientUnresponsive => EXIT_CLIENT_UNRESPONSIVE,
+        }
+    }
+}
+
+/// Serve MCP over stdio until the client goes away.
+///
+/// With `keepalive`, the client is pinged at that interval and a client that
+/// does not answer within `KEEPALIVE_TIMEOUT_SECS` ends the session. With
+/// `linger`, the process keeps accepting a new client on stdio (e.g. a
+/// wrapper reopening a named pipe) for that long after a disconnect.
+pub async fn serve_stdio(
+    server: ActivityWatchMcpServer,
+    keepalive: Option<Duration>,
+    linger: Option<Duration>,
+) -> SessionEnd {
+    let mut session = server.clone().serve(stdio()).await;
+    loop {
+        let end = match session {
+            Ok(service) => supervise(service, keepalive).await,
+            Err(e) => {
+                tracing::error!(error = %e, "MCP client disconnected during initialization");
+                SessionEnd::TransportClosed
+            }
+        };
+        let Some(linger) = linger.filter(|_| end != SessionEnd::Cancelled) else {
+            return end;
+        };
+
+        tracing::info!(
+            ?end,
+            linger_secs = linger.as_secs(),
+            "Client disconnected, waiting for a new one"
+        );
+        match reconnect(&server, linger).await {
+            Some(next) => {
+                tracing::info!("Client reconnected");
+                session = Ok(next);
+            }
+            None => return end,
+        }
+    }
+}
+
+/// Wait for a session to end, pinging the client when keepalive is enabled
+async fn supervise(
+    service: RunningService<RoleServer, ActivityWatchMcpServer>,
+    keepalive: Option<Duration>,
+) -> SessionEnd {
+    let peer = service.peer().clone();
+    let token = service.cancellation_token();
+
+    let monitor = async {
+        match keepalive {
+            Some(interval) => loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = ping(&peer).await {
+                    tracing::warn!(error = %e, "Client did not answer keepalive ping");
+                    return;
+                }
+            },
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        reason = service.waiting() => match reason {
+            Ok(QuitReason::Cancelled) => SessionEnd::Cancelled,
+            Ok(QuitReason::Closed) => SessionEnd::TransportClosed,
+            Ok(QuitReason::JoinError(e)) | Err(e) => {
+                tracing::error!(error = %e, "MCP service task failed");
+                SessionEnd::TransportClosed
+            }
+        },
+        () = monitor => {
+            token.cancel();
+            SessionEnd::ClientUnresponsive
+        }
+    }
+}
+
+/// Ping the client; protocol-level errors (e.g. an unsupported method) still
+/// prove it is alive, only transport failures and timeouts count
+async fn ping(peer: &Peer<RoleServer>) -> Result<(), ServiceError> {
+    let options = PeerRequestOptions {
+        timeout: Some(Duration::from_secs(KEEPALIVE_TIMEOUT_SECS)),
+        meta: None,
+    };
+    let result = match peer
+        .send_request_with_option(ServerRequest::PingRequest(PingRequest::default()), options)
+        .await
+    {
+        Ok(handle) => handle.await_response().await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(ServiceError::McpError(_)) | Err(ServiceError::UnexpectedResponse) => Ok(()),
+        other => other,
+    }
+}
+
+/// Accept a new client on stdio until `linger` has elapsed
+async fn reconnect(
+    server: &ActivityWatchMcpServer,
+    linger: Duration,
+) -> Option<RunningService<RoleServer, ActivityWatchMcpServer>> {
+    let deadline = Instant::now() + linger;
+    while Instant::now() < deadline {
+        if let Ok(Ok(service)) =
+            tokio::time::timeout_at(deadline, server.clone().serve(stdio())).await
+        {
+            return Some(service);
+        }
+        tokio::time::sleep(RECONNECT_POLL).await;
+    }
+    None
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Time spent on one app, title or category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// App name, window title or category path
+    pub name: String,
+
+    /// Seconds spent today
+    pub duration_secs: f64,
+}
+
+/// Time recorded by one window bucket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotBucket {
+    /// Bucket ID
+    pub id: String,
+
+    /// Host that created the bucket
+    pub hostname: Option<String>,
+
+    /// Seconds recorded today
+    pub total_secs: f64,
+}
+
+/// The daily summary of the default window and AFK buckets, as served by
+/// `aw_daily_summary` while the snapshot is fresh
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotDaily {
+    /// The window bucket with the most recent activity
+    pub bucket_id: String,
+
+    /// The AFK watcher on the window bucket's host, if any
+    pub afk_bucket_id: Option<String>,
+
+    /// Window time while not AFK
+    pub active_secs: f64,
+
+    /// Time the AFK watcher saw the user away
+    pub afk_secs: f64,
+
+    /// Time the AFK watcher saw the user present; none without AFK data
+    pub present_secs: Option<f64>,
+
+    /// Start of the first activity
+    pub first_activity: Option<DateTime<Utc>>,
+
+    /// End of the last activity
+    pub last_activity: Option<DateTime<Utc>>,
+
+    /// Active time per app, longest first
+    pub apps: Vec<SnapshotEntry>,
+
+    /// Active time per window title, longest first
+    pub titles: Vec<SnapshotEntry>,
+}
+
+/// Aggregated summary of the current day, kept on disk as `today.json` by
+/// the background snapshot job for local automations to read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaySnapshot {
+    /// The day in the configured timezone
+    pub date: NaiveDate,
+
+    /// Timezone the day is taken in
+    pub timezone: String,
+
+    /// When the snapshot was taken
+    pub generated_at: DateTime<Utc>,
+
+    /// Start of the day
+    pub start: DateTime<Utc>,
+
+    /// End of the covered range (the time of the snapshot)
+    pub end: DateTime<Utc>,
+
+    /// Seconds recorded by all window buckets
+    pub total_secs: f64,
+
+    /// Window buckets included, largest first
+    pub buckets: Vec<SnapshotBucket>,
+
+    /// Time per app, longest first
+    pub apps: Vec<SnapshotEntry>,
+
+    /// Time per window title, longest first
+    pub titles: Vec<SnapshotEntry>,
+
+    /// Time per category, longest first; empty without category rules
+    pub categories: Vec<SnapshotEntry>,
+
+    /// Daily summary of the default buckets; none without a window bucket
+    #[serde(default)]
+    pub daily: Option<SnapshotDaily>,
+}
+
+impl DaySnapshot {
+    /// Read a snapshot file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read snapshot {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid snapshot {}", path.display()))
+    }
+
+    /// Write the snapshot, replacing the file atomically so readers never
+    /// see a partial snapshot
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Cannot serialize snapshot")?;
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, json)
+            .with_context(|| format!("Cannot write snapshot {}", temp.display()))?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("Cannot replace snapshot {}", path.display()))
+    }
+
+    /// How old the snapshot is at `now`
+    pub fn age(&self, now: DateTime<Utc>) -> chrono::Duration {
+        now - self.generated_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn saves_and_loads_snapshots() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 3, 15, 14, 30, 0).unwrap();
+        let snapshot = DaySnapshot {
+            date: start.date_naive(),
+            timezone: "UTC".to_string(),
+            generated_at: end,
+            start,
+            end,
+            total_secs: 90.0,
+            buckets: vec![SnapshotBucket {
+                id: "aw-watcher-window_host".to_string(),
+                hostname: Some("host".to_string()),
+                total_secs: 90.0,
+            }],
+            apps: vec![SnapshotEntry {
+                name: "Code".to_string(),
+                duration_secs: 90.0,
+            }],
+            titles: Vec::new(),
+            categories: Vec::new(),
+            daily: None,
+        };
+
+        let path = std::env::temp_dir().join(format!("aw-mcp-today-{}.json", std::process::id()));
+        snapshot.save(&path).unwrap();
+        assert_eq!(DaySnapshot::load(&path).unwrap(), snapshot);
+        assert!(!path.with_extension("json.tmp").exists());
+        assert_eq!(
+            snapshot
+                .age(end + chrono::Duration::minutes(5))
+                .num_minutes(),
+            5
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// How markdown responses are decorated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// Markdown as the tools write it
+    #[default]
+    Markdown,
+    /// Markdown with emoji marking headings, statuses and warnings
+    Emoji,
+    /// Strictly plain text: no markdown syntax, tables as aligned columns
+    Plain,
+}
+
+impl FromStr for OutputStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "markdown" => Ok(Self::Markdown),
+            "emoji" => Ok(Self::Emoji),
+            "plain" | "text" => Ok(Self::Plain),
+            other => Err(format!(
+                "Unknown output style '{}'. Expected markdown, emoji or plain",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Markdown => "markdown",
+            Self::Emoji => "emoji",
+            Self::Plain => "plain",
+        })
+    }
+}
+
+impl OutputStyle {
+    /// Restyle a markdown response; JSON responses are left alone
+    pub fn apply(self, text: &str) -> String {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            return text.to_string();
+        }
+        match self {
+            Self::Markdown => text.to_string(),
+            Self::Emoji => decorate(text),
+            Self::Plain => plain_text(text),
+        }
+    }
+}
+
+/// Emoji for top-level headings, by the first keyword they contain
+const HEADING_EMOJI: &[(&str, &str)] = &[
+    ("Watcher", "🩺"),
+    ("Quality", "🩺"),
+    ("Categor", "🗂️"),
+    ("Rule", "🗂️"),
+    ("Diff", "⚖️"),
+    ("over Time", "📈"),
+    ("Device", "💻"),
+    ("Bucket", "🪣"),
+    ("Event", "📋"),
+    ("Tools", "🧰"),
+    ("Cache", "🗄️"),
+    ("Audit", "📜"),
+    ("Config", "⚙️"),
+];
+
+/// Emoji placed before bold status markers
+const MARKER_EMOJI: &[(&str, &str)] = &[
+    ("**alive**", "🟢"),
+    ("**quiet**", "🟡"),
+    ("**dead**", "🔴"),
+    ("**empty**", "⚪"),
+    ("**Disabled**", "🚫"),
+    ("**Warning**", "⚠️"),
+];
+
+/// Add emoji to headings, status markers and warnings
+pub fn decorate(markdown: &str) -> String {
+    let mut fenced = false;
+    let lines: Vec<String> = markdown
+        .lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                fenced = !fenced;
+            }
+            if fenced {
+                return line.to_string();
+            }
+            if let Some(heading) = line.strip_prefix("# ") {
+                let emoji = HEADING_EMOJI
+                    .iter()
+                    .find(|(keyword, _)| heading.contains(keyword))
+                    .map_or("📊", |(_, emoji)| emoji);
+                return format!("# {} {}", emoji, heading);
+            }
+            let mut line = line.to_string();
+            for (marker, emoji) in MARKER_EMOJI {
+                line = line.replace(marker, &format!("{} {}", emoji, marker));
+            }
+            line
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Convert markdown to plain text: headings, emphasis, code spans and
+/// escapes lose their syntax, fenced blocks keep their content verbatim and
+/// tables become space-aligned columns
+pub fn plain_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut table: Vec<Vec<String>> = Vec::new();
+    let mut fence: Option<usize> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        let backticks = trimmed.chars().take_while(|c| *c == '`').count();
+        if let Some(open) = fence {
+            if backticks >= open && backticks == trimmed.len() {
+                fence = None;
+            } else {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+        if backticks >= 3 {
+            flush_table(&mut table, &mut lines);
+            fence = Some(backticks);
+            continue;
+        }
+        if trimmed.starts_with('|') {
+            let cells = table_cells(trimmed);
+            let separator = cells
+                .iter()
+                .all(|cell| !cell.is_empty() && cell.chars().all(|c| matches!(c, '-' | ':')));
+            if !separator {
+                table.push(cells.iter().map(|cell| inline_text(cell)).collect());
+            }
+            continue;
+        }
+        flush_table(&mut table, &mut lines);
+
+        let line = line.strip_prefix("> ").unwrap_or(line);
+        let heading = line.trim_start_matches('#');
+        match heading.len() < line.len() && heading.starts_with(' ') {
+            true => lines.push(inline_text(heading.trim())),
+            false => lines.push(inline_text(line)),
+        }
+    }
+    flush_table(&mut table, &mut lines);
+    lines.join("\n")
+}
+
+/// Cells of a markdown table row, split on unescaped pipes
+fn table_cells(row: &str) -> Vec<String> {
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = row
+        .strip_suffix('|')
+        .filter(|r| !r.ends_with('\\'))
+        .unwrap_or(row);
+    let mut cells = vec![String::new()];
+    let mut escaped = false;
+    for c in row.chars() {
+        if c == '|' && !escaped {
+            cells.push(String::new());
+        } else if let Some(cell) = cells.last_mut() {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Append the buffered table rows as aligned columns
+fn flush_table(table: &mut Vec<Vec<String>>, lines: &mut Vec<String>) {
+    let columns = table.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            table
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in table.drain(..) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        lines.push(cells.join("  ").trim_end().to_string());
+    }
+}
+
+/// Remove inline markdown: emphasis, strikethrough, code span delimiters
+/// and backslash escapes
+fn inline_text(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut plain = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\\' if next.is_some_and(|n| n.is_ascii_punctuation()) => {
+                plain.extend(next);
+                i += 2;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|c| **c == '`').count();
+                match closing_run(&chars, i + run, run) {
+                    Some(end) => {
+                        let code: String = chars[i + run..end].iter().collect();
+                        let padded = code.len() > 2 && code.starts_with(' ') && code.ends_with(' ');
+                        match padded && !code.trim().is_empty() {
+                            true => plain.push_str(&code[1..code.len() - 1]),
+                            false => plain.push_str(&code),
+                        }
+                        i = end + run;
+                    }
+                    None => {
+                        plain.extend(&chars[i..i + run]);
+                        i += run;
+                    }
+                }
+            }
+            '*' | '~' if next == Some(c) => i += 2,
+            '_' => {
+                let inner_word = i > 0
+                    && chars[i - 1].is_alphanumeric()
+                    && next.is_some_and(char::is_alphanumeric);
+                if inner_word {
+                    plain.push(c);
+                }
+                i += 1;
+            }
+            _ => {
+                plain.push(c);
+                i += 1;
+            }
+        }
+    }
+    plain
+}
+
+/// Position of the next run of exactly `len` backticks from `from`
+fn closing_run(chars: &[char], from: usize, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        let run = chars[i..].iter().take_while(|c| **c == '`').count();
+        if run == len {
+            return Some(i);
+        }
+        i += run.max(1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_markdown_to_plain_text() {
+        let markdown = concat!(
+            "# Watcher Status\n",
+            "\n",
+            "- **dead** `aw-watcher-afk_host`: last event 2h ago\n",
+            "> **Warning**: Low coverage\n",
+            "| App | Time |\n",
+            "|---|---:|\n",
+            "| Code | 1h 5m |\n",
+            "| a\\|b | 5s |\n",
+            "```toml\n",
+            "\"Work\" = '_x_'\n",
+            "```\n",
+            "_No buckets found._",
+        );
+        assert_eq!(
+            plain_text(markdown),
+            concat!(
+                "Watcher Status\n",
+                "\n",
+                "- dead aw-watcher-afk_host: last event 2h ago\n",
+                "Warning: Low coverage\n",
+                "App   Time\n",
+                "Code  1h 5m\n",
+                "a|b   5s\n",
+                "\"Work\" = '_x_'\n",
+                "No buckets found.",
+            )
+        );
+        assert_eq!(plain_text("`` use `x` ``"), "use `x`");
+    }
+
+    #[test]
+    fn decorates_headings_and_markers() {
+        assert_eq!(
+            decorate("# Watcher Status\n- **dead** `x`\n# Categories"),
+            "# 🩺 Watcher Status\n- 🔴 **dead** `x`\n# 🗂️ Categories"
+        );
+        assert_eq!(
+            OutputStyle::Plain.apply("{\"a\": \"**b**\"}"),
+            "{\"a\": \"**b**\"}"
+        );
+        assert_eq!("text".parse(), Ok(OutputStyle::Plain));
+    }
+}
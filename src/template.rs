@@ -0,0 +1,143 @@
+use crate::format::escape_markdown;
+use minijinja::{AutoEscape, Environment, UndefinedBehavior, Value, escape_formatter};
+
+/// Name of the auto-escaping mode that makes interpolated text inert Markdown
+const MARKDOWN_ESCAPE: &str = "markdown";
+
+/// A report template in Jinja syntax (rendered by minijinja):
+///
+/// - `{{ value }}` inserts a value, `{{ value | safe }}` inserts it verbatim
+/// - `{% for app in apps %}...{% else %}...{% endfor %}` repeats for every
+///   item, or renders the `else` part for an empty list (`loop.index`,
+///   `loop.first` and `loop.last` describe the current item)
+/// - `{% if value %}...{% else %}...{% endif %}` tests whether a value is
+///   present and not false, zero or empty
+/// - `{# comment #}` is dropped
+///
+/// Inserted text is Markdown-escaped, so window titles and URLs cannot inject
+/// links, emphasis or line breaks into a report. Inserting an unknown value
+/// is an error. A block tag alone on a line removes the whole line, so
+/// templates can be laid out readably.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    source: String,
+}
+
+impl Template {
+    /// Parse a template
+    pub fn parse(source: &str) -> Result<Self, String> {
+        environment()
+            .template_from_str(source)
+            .map_err(|e| error_message(&e))?;
+        Ok(Self {
+            source: source.to_string(),
+        })
+    }
+
+    /// Render the template with `data` as the top-level value
+    pub fn render(&self, data: &serde_json::Value) -> Result<String, String> {
+        environment()
+            .render_str(&self.source, Value::from_serialize(data))
+            .map_err(|e| error_message(&e))
+    }
+}
+
+/// The environment templates are parsed and rendered in
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.set_undefined_behavior(UndefinedBehavior::SemiStrict);
+    env.set_auto_escape_callback(|_| AutoEscape::Custom(MARKDOWN_ESCAPE));
+    env.set_formatter(|out, state, value| match state.auto_escape() {
+        AutoEscape::Custom(name) if name == MARKDOWN_ESCAPE => {
+            if value.is_safe() {
+                out.write_str(value.as_str().unwrap_or_default())?;
+            } else if let Some(text) = value.as_str() {
+                out.write_str(&escape_markdown(text))?;
+            } else {
+                out.write_str(&escape_markdown(&value.to_string()))?;
+            }
+            Ok(())
+        }
+        _ => escape_formatter(out, state, value),
+    });
+    env
+}
+
+/// A one-line description of a template error, with its line number
+fn error_message(error: &minijinja::Error) -> String {
+    let detail = error.detail().unwrap_or_default();
+    let message = match error.kind() {
+        minijinja::ErrorKind::UndefinedError => "Unknown value".to_string(),
+        kind if detail.is_empty() => kind.to_string(),
+        kind => format!("{}: {}", kind, detail),
+    };
+    match error.line() {
+        Some(line) => format!("{} (line {})", message, line),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_values_loops_and_conditions() {
+        let template = Template::parse(concat!(
+            "# {{ title }} ({{ total }}s)\n",
+            "{# apps, longest first #}\n",
+            "{% for app in apps %}\n",
+            "{{ loop.index }}. {{ app.name }} {{ app.duration }}{% if loop.last %} (last){% endif %} of {{ title }}\n",
+            "{% else %}\n",
+            "No apps\n",
+            "{% endfor %}\n",
+            "{% if not notes %}No notes{% endif %}",
+        ))
+        .unwrap();
+        let data = json!({
+            "title": "Day",
+            "total": 90.0,
+            "apps": [{"name": "Code", "duration": "1m"}, {"name": "Firefox", "duration": "30s"}],
+            "notes": [],
+        });
+        assert_eq!(
+            template.render(&data).unwrap(),
+            "# Day (90.0s)\n1. Code 1m of Day\n2. Firefox 30s (last) of Day\nNo notes"
+        );
+        assert_eq!(
+            template
+                .render(&json!({"title": "x", "total": 0, "apps": [], "notes": [1]}))
+                .unwrap(),
+            "# x (0s)\nNo apps\n"
+        );
+    }
+
+    #[test]
+    fn escapes_inserted_values() {
+        let template = Template::parse("- {{ title }}\n- {{ title | safe }}").unwrap();
+        let data = json!({"title": "[click](http://evil)\n# *pwned*"});
+        assert_eq!(
+            template.render(&data).unwrap(),
+            "- \\[click\\](http://evil) # \\*pwned\\*\n- [click](http://evil)\n# *pwned*"
+        );
+    }
+
+    #[test]
+    fn reports_template_errors() {
+        assert!(Template::parse("{% for app in apps %}x").is_err());
+        assert!(Template::parse("{% for app in apps %}x{% endif %}").is_err());
+        assert!(Template::parse("a {{ b").is_err());
+        assert!(Template::parse("{% endfor %}").is_err());
+        let template = Template::parse("{{ missing }}").unwrap();
+        assert_eq!(
+            template.render(&json!({})),
+            Err("Unknown value (line 1)".to_string())
+        );
+        // Testing an unknown value is fine
+        let template = Template::parse("{% if missing %}x{% endif %}").unwrap();
+        assert_eq!(template.render(&json!({})).unwrap(), "");
+    }
+}
@@ -0,0 +1,512 @@
+//! An in-process fake aw-server for tests.
+//!
+//! `FakeAwServer` serves the ActivityWatch REST API (info, buckets, events,
+//! event counts, query2, bucket creation, event insertion and deletion,
+//! heartbeats, export and import, and settings) from fixtures on a
+//! local port, and can inject faults such as error statuses and slow
+//! responses.
+
+use crate::models::{Bucket, Event, EventDuration};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// A fault injected into the fake server's responses
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// Respond with this HTTP status and an aw-server style error body
+    Status(u16),
+    /// Wait this long before answering normally (to trigger client timeouts)
+    Delay(Duration),
+}
+
+#[derive(Debug, Default)]
+struct State {
+    info: serde_json::Value,
+    buckets: HashMap<String, Bucket>,
+    events: HashMap<String, Vec<Event>>,
+    query_result: Option<serde_json::Value>,
+    settings: serde_json::Map<String, serde_json::Value>,
+    next_faults: VecDeque<Fault>,
+    fault: Option<Fault>,
+    requests: Vec<String>,
+}
+
+/// A local HTTP server mimicking the ActivityWatch API (v0)
+pub struct FakeAwServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+    handle: JoinHandle<()>,
+}
+
+impl FakeAwServer {
+    /// Start a server on a free local port
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind fake aw-server");
+        let url = format!("http://{}", listener.local_addr().expect("bound address"));
+        let state = Arc::new(Mutex::new(State {
+            info: serde_json::json!({
+                "hostname": "fake-host",
+                "version": "v0.13.1",
+                "testing": true,
+                "device_id": "fake-device",
+            }),
+            ..State::default()
+        }));
+
+        let shared = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(socket, shared.clone()));
+            }
+        });
+
+        Self { url, state, handle }
+    }
+
+    /// Server root URL (e.g., `http://127.0.0.1:41234`)
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Replace the `/info` response
+    pub fn set_info(&self, info: serde_json::Value) {
+        self.state().info = info;
+    }
+
+    /// Add or replace a bucket
+    pub fn add_bucket(&self, bucket: Bucket) {
+        self.state().buckets.insert(bucket.id.clone(), bucket);
+    }
+
+    /// Add a bucket with just an ID, type and hostname
+    pub fn add_simple_bucket(&self, id: &str, bucket_type: &str, hostname: &str) {
+        self.add_bucket(Bucket {
+            id: id.to_string(),
+            client: None,
+            bucket_type: Some(bucket_type.to_string()),
+            hostname: Some(hostname.to_string()),
+            created: None,
+            data: None,
+            last_updated: None,
+            extra: HashMap::new(),
+        });
+    }
+
+    /// Append events to a bucket (served newest first)
+    pub fn add_events(&self, bucket_id: &str, events: Vec<Event>) {
+        self.state()
+            .events
+            .entry(bucket_id.to_string())
+            .or_default()
+            .extend(events);
+    }
+
+    /// The value returned for every time period of a query2 request
+    /// (default: an empty event list)
+    pub fn set_query_result(&self, value: serde_json::Value) {
+        self.state().query_result = Some(value);
+    }
+
+    /// Apply a fault to the next request only (faults queue up)
+    pub fn fail_next(&self, fault: Fault) {
+        self.state().next_faults.push_back(fault);
+    }
+
+    /// Apply a fault to every request until cleared with `None`
+    pub fn fail_all(&self, fault: Option<Fault>) {
+        self.state().fault = fault;
+    }
+
+    /// Requests received so far, as `METHOD /path?query`
+    pub fn requests(&self) -> Vec<String> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for FakeAwServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Read one request from a connection and answer it
+async fn serve_connection(mut socket: TcpStream, state: Arc<Mutex<State>>) {
+    let Some((method, target, body)) = read_request(&mut socket).await else {
+        return;
+    };
+
+    let fault = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.requests.push(format!("{} {}", method, target));
+        state
+            .next_faults
+            .pop_front()
+            .or_else(|| state.fault.clone())
+    };
+    let (status, body) = match fault {
+        Some(Fault::Status(status)) => (
+            status,
+            serde_json::json!({
+                "type": "InjectedFault",
+                "message": format!("Injected {} error", status),
+            }),
+        ),
+        Some(Fault::Delay(delay)) => {
+            tokio::time::sleep(delay).await;
+            route(&state, &method, &target, &body)
+        }
+        None => route(&state, &method, &target, &body),
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        if status < 400 { "OK" } else { "Error" },
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
+
+/// Read the request line and body of an HTTP/1.1 request
+async fn read_request(socket: &mut TcpStream) -> Option<(String, String, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = buffer[head_end..].to_vec();
+    while body.len() < content_length {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    Some((method, target, body))
+}
+
+/// Answer a request from the fixtures
+fn route(
+    state: &Mutex<State>,
+    method: &str,
+    target: &str,
+    body: &[u8],
+) -> (u16, serde_json::Value) {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+    let Ok(url) = Url::parse(&format!("http://fake{}", target)) else {
+        return not_found("Invalid request target");
+    };
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let segments: Vec<String> = url
+        .path_segments()
+        .map(|s| s.map(percent_decode).collect())
+        .unwrap_or_default();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match (method, segments.as_slice()) {
+        ("GET", ["api", "0", "info"]) => (200, state.info.clone()),
+        ("GET", ["api", "0", "buckets", ""]) | ("GET", ["api", "0", "buckets"]) => (
+            200,
+            serde_json::to_value(&state.buckets).unwrap_or_default(),
+        ),
+        ("GET", ["api", "0", "buckets", id, rest @ ..]) => {
+            let Some(bucket) = state.buckets.get(*id) else {
+                return (
+                    404,
+                    serde_json::json!({
+                        "type": "NoSuchBucket",
+                        "message": format!("There's no bucket named {}", id),
+                    }),
+                );
+            };
+            let events = || filter_events(state.events.get(*id), &query);
+            match rest {
+                [] => (200, serde_json::to_value(bucket).unwrap_or_default()),
+                ["events"] => {
+                    let mut events = events();
+                    if let Some(limit) = query.get("limit").and_then(|l| l.parse::<i64>().ok())
+                        && limit >= 0
+                    {
+                        events.truncate(limit as usize);
+                    }
+                    (200, serde_json::to_value(events).unwrap_or_default())
+                }
+                ["events", "count"] => (200, serde_json::json!(events().len())),
+                ["export"] => (200, export(&state, &[id])),
+                ["events", event_id] => {
+                    // The Python aw-server answers null for unknown events
+                    let event = state.events.get(*id).into_iter().flatten().find(|event| {
+                        event.id.map(|id| id.to_string()).as_deref() == Some(*event_id)
+                    });
+                    (200, serde_json::to_value(event).unwrap_or_default())
+                }
+                _ => not_found("Unknown bucket endpoint"),
+            }
+        }
+        ("GET", ["api", "0", "settings"]) => (200, state.settings.clone().into()),
+        // Like the Python aw-server, unset settings are null
+        ("GET", ["api", "0", "settings", key]) => {
+            (200, state.settings.get(*key).cloned().unwrap_or_default())
+        }
+        ("POST", ["api", "0", "settings", key]) => match serde_json::from_slice(body) {
+            Ok(value) => {
+                state.settings.insert(key.to_string(), value);
+                (200, serde_json::Value::Null)
+            }
+            Err(_) => (
+                400,
+                serde_json::json!({ "type": "BadRequest", "message": "Invalid JSON" }),
+            ),
+        },
+        ("GET", ["api", "0", "export"]) => {
+            let ids: Vec<&str> = state.buckets.keys().map(String::as_str).collect();
+            (200, export(&state, &ids))
+        }
+        ("POST", ["api", "0", "import"]) => {
+            let request: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+            let Some(buckets) = request["buckets"].as_object() else {
+                return (
+                    400,
+                    serde_json::json!({ "type": "BadRequest", "message": "Invalid export" }),
+                );
+            };
+            for (id, exported) in buckets {
+                // aw-server refuses to import over an existing bucket
+                if state.buckets.contains_key(id) {
+                    return (
+                        500,
+                        serde_json::json!({
+                            "message": format!("Failed to import bucket: {} already exists", id),
+                        }),
+                    );
+                }
+                let mut exported = exported.clone();
+                let events = exported.as_object_mut().and_then(|b| b.remove("events"));
+                let (Ok(bucket), Ok(events)) = (
+                    serde_json::from_value::<Bucket>(exported),
+                    serde_json::from_value::<Vec<Event>>(events.unwrap_or(serde_json::json!([]))),
+                ) else {
+                    return (
+                        400,
+                        serde_json::json!({ "type": "BadRequest", "message": "Invalid bucket" }),
+                    );
+                };
+                state.buckets.insert(id.clone(), bucket);
+                state.events.insert(id.clone(), events);
+            }
+            (200, serde_json::Value::Null)
+        }
+        ("POST", ["api", "0", "buckets", id]) => {
+            if state.buckets.contains_key(*id) {
+                return (304, serde_json::Value::Null);
+            }
+            let request: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+            let field = |key: &str| request[key].as_str().map(str::to_string);
+            state.buckets.insert(
+                id.to_string(),
+                Bucket {
+                    id: id.to_string(),
+                    client: field("client"),
+                    bucket_type: field("type"),
+                    hostname: field("hostname"),
+                    created: Some(Utc::now()),
+                    data: None,
+                    last_updated: None,
+                    extra: HashMap::new(),
+                },
+            );
+            (200, serde_json::Value::Null)
+        }
+        ("DELETE", ["api", "0", "buckets", id]) => {
+            // aw-server only deletes buckets with force=1 outside testing mode
+            if query.get("force").map(String::as_str) != Some("1") {
+                return (
+                    405,
+                    serde_json::json!({
+                        "message": "Deleting buckets is only permitted if aw-server is running \
+                                    in testing mode or if ?force=1",
+                    }),
+                );
+            }
+            if state.buckets.remove(*id).is_none() {
+                return not_found("No such bucket");
+            }
+            state.events.remove(*id);
+            (200, serde_json::Value::Null)
+        }
+        ("POST", ["api", "0", "buckets", id, "events"]) => {
+            if !state.buckets.contains_key(*id) {
+                return not_found("No such bucket");
+            }
+            let Ok(mut inserted) = serde_json::from_slice::<Vec<Event>>(body) else {
+                return (
+                    400,
+                    serde_json::json!({ "type": "BadRequest", "message": "Invalid events" }),
+                );
+            };
+            let events = state.events.entry(id.to_string()).or_default();
+            for event in &mut inserted {
+                event.id = Some(events.len() as i64 + 1);
+                events.push(event.clone());
+            }
+            // Answer as aw-server-rust does, with the inserted events
+            (200, serde_json::to_value(inserted).unwrap_or_default())
+        }
+        ("DELETE", ["api", "0", "buckets", id, "events", event_id]) => {
+            let Some(events) = state.events.get_mut(*id) else {
+                return not_found("No such bucket");
+            };
+            // The Python aw-server reports unknown events as unsuccessful
+            let count = events.len();
+            events.retain(|event| event.id.map(|id| id.to_string()).as_deref() != Some(event_id));
+            (200, serde_json::json!({ "success": events.len() < count }))
+        }
+        ("POST", ["api", "0", "buckets", id, "heartbeat"]) => {
+            if !state.buckets.contains_key(*id) {
+                return not_found("No such bucket");
+            }
+            let Ok(heartbeat) = serde_json::from_slice::<Event>(body) else {
+                return (
+                    400,
+                    serde_json::json!({ "type": "BadRequest", "message": "Invalid event" }),
+                );
+            };
+            let pulsetime: f64 = query
+                .get("pulsetime")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(0.0);
+            let events = state.events.entry(id.to_string()).or_default();
+            let next_id = events.len() as i64 + 1;
+            let last = events.iter_mut().max_by_key(|e| e.timestamp);
+            let event = match last {
+                // Merge as aw-server does: equal data, starting within pulsetime of the end
+                Some(last)
+                    if last.data == heartbeat.data
+                        && heartbeat.timestamp >= last.timestamp
+                        && (heartbeat.timestamp - last.end()).num_milliseconds() as f64
+                            <= pulsetime * 1000.0 =>
+                {
+                    let end = last.end().max(heartbeat.end());
+                    last.duration = EventDuration::between(last.timestamp, end);
+                    last.clone()
+                }
+                _ => {
+                    let mut event = heartbeat;
+                    event.id = Some(next_id);
+                    events.push(event.clone());
+                    event
+                }
+            };
+            (200, serde_json::to_value(event).unwrap_or_default())
+        }
+        ("POST", ["api", "0", "query", ..]) => {
+            let request: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+            let Some(periods) = request["timeperiods"].as_array() else {
+                return (
+                    400,
+                    serde_json::json!({ "type": "BadRequest", "message": "Missing timeperiods" }),
+                );
+            };
+            let value = state
+                .query_result
+                .clone()
+                .unwrap_or_else(|| serde_json::json!([]));
+            (200, serde_json::Value::Array(vec![value; periods.len()]))
+        }
+        _ => not_found("Unknown endpoint"),
+    }
+}
+
+/// Events of a bucket overlapping the `start`/`end` query, newest first
+fn filter_events(events: Option<&Vec<Event>>, query: &HashMap<String, String>) -> Vec<Event> {
+    let bound = |key: &str| {
+        query
+            .get(key)
+            .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+            .map(|t| t.with_timezone(&Utc))
+    };
+    let (start, end) = (bound("start"), bound("end"));
+
+    let mut events: Vec<Event> = events
+        .into_iter()
+        .flatten()
+        .filter(|e| start.is_none_or(|start| e.end() > start))
+        .filter(|e| end.is_none_or(|end| e.timestamp < end))
+        .cloned()
+        .collect();
+    events.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    events
+}
+
+/// The buckets in aw-server's export format, each with its events
+fn export(state: &State, ids: &[&str]) -> serde_json::Value {
+    let buckets: serde_json::Map<String, serde_json::Value> = ids
+        .iter()
+        .filter_map(|id| {
+            let mut bucket = serde_json::to_value(state.buckets.get(*id)?).ok()?;
+            let events = state.events.get(*id).cloned().unwrap_or_default();
+            bucket["events"] = serde_json::to_value(events).ok()?;
+            Some((id.to_string(), bucket))
+        })
+        .collect();
+    serde_json::json!({ "buckets": buckets })
+}
+
+fn not_found(message: &str) -> (u16, serde_json::Value) {
+    (404, serde_json::json!({ "message": message }))
+}
+
+/// Decode `%XX` escapes in a path segment
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = segment
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,736 @@
+//! Timezones for splitting ranges into local days and weeks.
+//!
+//! Named zones are read from the zoneinfo database (`TZDIR`, or the system
+//! one): instants up to a zone's last recorded transition use its transition
+//! table, so history follows the rules in force at the time, and later ones
+//! the POSIX rule at the end of the TZif file.
+//!
+//! Per-date overrides (for trips) switch zones at local midnight, so the
+//! days a trip starts and ends are shorter or longer than 24 hours.
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday,
+};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Directories searched for the zoneinfo database when `TZDIR` is unset or
+/// lacks a zone
+const ZONEINFO_DIRS: &[&str] = &[
+    "/usr/share/zoneinfo",
+    "/usr/lib/zoneinfo",
+    "/usr/share/lib/zoneinfo",
+];
+
+/// Length of a TZif header
+const TZIF_HEADER_LEN: usize = 44;
+
+/// The timezone days and weeks are split in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Timezone {
+    /// Coordinated Universal Time
+    #[default]
+    Utc,
+    /// A fixed offset such as `+09:00`
+    Fixed(FixedOffset),
+    /// A zone with daylight-saving rules, such as `Europe/Berlin`
+    Rule {
+        /// The name the zone was configured with
+        name: String,
+        /// Offsets (seconds east of UTC) taking effect at recorded
+        /// transitions (Unix seconds), oldest first
+        history: Vec<(i64, i32)>,
+        /// The POSIX rule in force after the last transition
+        rule: PosixRule,
+    },
+    /// A zone replaced by others on some dates
+    Overridden {
+        /// The zone outside the overrides
+        default: Box<Timezone>,
+        /// Non-overlapping date ranges with their own zone
+        overrides: Vec<DateOverride>,
+    },
+}
+
+/// A zone used on a range of dates instead of the configured one
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateOverride {
+    /// First local date of the override
+    pub first: NaiveDate,
+    /// Last local date of the override (inclusive)
+    pub last: NaiveDate,
+    /// The zone used on those dates
+    pub zone: Timezone,
+}
+
+impl Timezone {
+    /// Offset from UTC at an instant
+    pub fn offset_at(&self, time: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Self::Utc => FixedOffset::east_opt(0).expect("zero offset"),
+            Self::Fixed(offset) => *offset,
+            Self::Rule { history, rule, .. } => {
+                match history.partition_point(|&(at, _)| at <= time.timestamp()) {
+                    n if n == history.len() => rule.offset_at(time),
+                    n => FixedOffset::east_opt(history[n - 1].1).expect("offset within a day"),
+                }
+            }
+            Self::Overridden { default, overrides } => overrides
+                .iter()
+                .find(|o| (o.first..=o.last).contains(&o.zone.date_of(time)))
+                .map_or(&**default, |o| &o.zone)
+                .offset_at(time),
+        }
+    }
+
+    /// All offsets the zone uses, in seconds east of UTC
+    fn offsets(&self) -> Vec<i32> {
+        match self {
+            Self::Utc => vec![0],
+            Self::Fixed(offset) => vec![offset.local_minus_utc()],
+            Self::Rule { history, rule, .. } => {
+                let mut offsets: Vec<i32> = history
+                    .iter()
+                    .map(|&(_, offset)| offset)
+                    .chain(rule.offsets())
+                    .collect();
+                offsets.sort_unstable();
+                offsets.dedup();
+                offsets
+            }
+            Self::Overridden { default, overrides } => std::iter::once(&**default)
+                .chain(overrides.iter().map(|o| &o.zone))
+                .flat_map(Self::offsets)
+                .collect(),
+        }
+    }
+
+    /// Apply per-date overrides such as
+    /// `2024-03-04..2024-03-08=America/New_York;2024-05-01=+09:00`
+    /// (entries separated by `;`, a single date or an inclusive range)
+    pub fn with_overrides(self, spec: &str) -> Result<Self, String> {
+        let mut overrides: Vec<DateOverride> = Vec::new();
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let invalid = || {
+                format!(
+                    "Invalid timezone override '{}', expected e.g. 2024-03-04..2024-03-08=America/New_York",
+                    entry
+                )
+            };
+            let (dates, zone) = entry.split_once('=').ok_or_else(invalid)?;
+            let (first, last) = dates.split_once("..").unwrap_or((dates, dates));
+            let date = |d: &str| d.trim().parse::<NaiveDate>().map_err(|_| invalid());
+            let (first, last) = (date(first)?, date(last)?);
+            if last < first {
+                return Err(invalid());
+            }
+            if let Some(other) = overrides
+                .iter()
+                .find(|o| o.first <= last && first <= o.last)
+            {
+                return Err(format!(
+                    "Timezone overrides {}..{} and {}..{} overlap",
+                    other.first, other.last, first, last
+                ));
+            }
+            overrides.push(DateOverride {
+                first,
+                last,
+                zone: zone.parse()?,
+            });
+        }
+        if overrides.is_empty() {
+            return Ok(self);
+        }
+        overrides.sort_by_key(|o| o.first);
+        Ok(Self::Overridden {
+            default: Box::new(self),
+            overrides,
+        })
+    }
+
+    /// Local wall-clock time of an instant
+    pub fn to_local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        time.naive_utc() + Duration::seconds(self.offset_at(time).local_minus_utc() as i64)
+    }
+
+    /// The instant of a local wall-clock time. Ambiguous times (when clocks
+    /// go back) resolve to the earlier instant; skipped times (when clocks
+    /// go forward) are shifted forward by the gap.
+    pub fn from_local(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let candidates: Vec<DateTime<Utc>> = self
+            .offsets()
+            .into_iter()
+            .map(|offset| (local - Duration::seconds(offset as i64)).and_utc())
+            .collect();
+        candidates
+            .iter()
+            .filter(|utc| self.to_local(**utc) == local)
+            .min()
+            .or(candidates.iter().max())
+            .copied()
+            .unwrap_or_else(|| local.and_utc())
+    }
+
+    /// Local calendar date of an instant
+    pub fn date_of(&self, time: DateTime<Utc>) -> NaiveDate {
+        self.to_local(time).date()
+    }
+
+    /// The instant a local calendar day starts (23 or 25 hours before the
+    /// next one across daylight-saving changes)
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        self.from_local(date.and_time(NaiveTime::MIN))
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    /// Parse `UTC`, `local` (the `TZ` variable or `/etc/localtime`), an
+    /// offset such as `+09:00`, a zone name such as `Europe/Berlin`, or a
+    /// POSIX rule such as `CET-1CEST,M3.5.0,M10.5.0/3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("utc") || s == "Z" {
+            return Ok(Self::Utc);
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return match std::env::var("TZ") {
+                Ok(tz) if !tz.trim().is_empty() => tz.trim_start_matches(':').parse(),
+                _ => read_zone(Path::new("/etc/localtime")).map(|(history, rule)| Self::Rule {
+                    name: "local".to_string(),
+                    history,
+                    rule,
+                }),
+            };
+        }
+        if s.starts_with(['+', '-']) {
+            return parse_offset(s)
+                .and_then(FixedOffset::east_opt)
+                .map(Self::Fixed)
+                .ok_or_else(|| format!("Invalid UTC offset '{}', expected e.g. +09:00", s));
+        }
+        if s.contains('/') || !s.contains(|c: char| c.is_ascii_digit()) {
+            if s.split('/')
+                .any(|part| part.is_empty() || part == "." || part == "..")
+            {
+                return Err(format!("Invalid timezone name '{}'", s));
+            }
+            let (history, rule) = read_zone(&find_zone(s)?)?;
+            return Ok(Self::Rule {
+                name: s.to_string(),
+                history,
+                rule,
+            });
+        }
+        Ok(Self::Rule {
+            name: s.to_string(),
+            history: Vec::new(),
+            rule: s.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utc => f.write_str("UTC"),
+            Self::Fixed(offset) => write!(f, "UTC{}", offset),
+            Self::Rule { name, .. } => f.write_str(name),
+            Self::Overridden { default, overrides } => {
+                write!(f, "{}", default)?;
+                for o in overrides {
+                    write!(f, "; {} from {} to {}", o.zone, o.first, o.last)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Find a named zone in `TZDIR` or the system zoneinfo directories
+fn find_zone(name: &str) -> Result<PathBuf, String> {
+    std::env::var_os("TZDIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(ZONEINFO_DIRS.iter().map(PathBuf::from))
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            format!(
+                "Unknown timezone '{}' (set TZDIR to a zoneinfo directory where the system has none)",
+                name
+            )
+        })
+}
+
+/// Read the transition table and POSIX rule of a TZif file
+fn read_zone(path: &Path) -> Result<(Vec<(i64, i32)>, PosixRule), String> {
+    let data = std::fs::read(path)
+        .map_err(|e| format!("Cannot read timezone {}: {}", path.display(), e))?;
+    parse_tzif(&data).map_err(|e| format!("Timezone file {}: {}", path.display(), e))
+}
+
+/// Parse TZif data (RFC 8536) into the offsets taking effect at each
+/// transition and the rule for instants after the last one
+fn parse_tzif(data: &[u8]) -> Result<(Vec<(i64, i32)>, PosixRule), String> {
+    let invalid = || "not valid TZif data".to_string();
+    let mut counts = tzif_counts(data, 0).ok_or_else(invalid)?;
+    let mut at = TZIF_HEADER_LEN;
+    let mut time_len = 4;
+    if data[4] >= b'2' {
+        // Version 2+ repeats the data with 64-bit times after the 32-bit block
+        at += tzif_block_len(counts, time_len);
+        counts = tzif_counts(data, at).ok_or_else(invalid)?;
+        at += TZIF_HEADER_LEN;
+        time_len = 8;
+    }
+    let [_, _, _, time_count, type_count, _] = counts;
+    let block = data
+        .get(at..at + tzif_block_len(counts, time_len))
+        .ok_or_else(invalid)?;
+    let (times, rest) = block.split_at(time_count * time_len);
+    let (indices, rest) = rest.split_at(time_count);
+    let types = &rest[..type_count * 6];
+    let offset_of = |index: u8| {
+        types
+            .get(index as usize * 6..index as usize * 6 + 4)
+            .map(|bytes| i32::from_be_bytes(bytes.try_into().expect("four bytes")))
+            .filter(|offset| offset.abs() < 86_400)
+            .ok_or_else(invalid)
+    };
+
+    let mut history = Vec::with_capacity(time_count + 1);
+    if time_count > 0 {
+        // Instants before the first transition use the first type
+        history.push((i64::MIN, offset_of(0)?));
+    }
+    for (time, &index) in times.chunks(time_len).zip(indices) {
+        let time = match time_len {
+            8 => i64::from_be_bytes(time.try_into().expect("eight bytes")),
+            _ => i32::from_be_bytes(time.try_into().expect("four bytes")) as i64,
+        };
+        history.push((time, offset_of(index)?));
+    }
+
+    let footer = String::from_utf8_lossy(&data[at + block.len()..]);
+    let footer = footer.trim_matches('\n');
+    let rule = if footer.is_empty() {
+        // No rule: the last offset stays in force
+        let std_offset = match history.last() {
+            Some(&(_, offset)) => offset,
+            None => offset_of(0)?,
+        };
+        PosixRule {
+            std_offset,
+            dst: None,
+        }
+    } else {
+        footer.parse()?
+    };
+    Ok((history, rule))
+}
+
+/// The six counts of the TZif header at `at`
+fn tzif_counts(data: &[u8], at: usize) -> Option<[usize; 6]> {
+    let header = data.get(at..at + TZIF_HEADER_LEN)?;
+    if !header.starts_with(b"TZif") {
+        return None;
+    }
+    let mut counts = [0; 6];
+    for (count, bytes) in counts.iter_mut().zip(header[20..].chunks(4)) {
+        *count = u32::from_be_bytes(bytes.try_into().ok()?) as usize;
+    }
+    Some(counts)
+}
+
+/// Length of the TZif data block following a header
+fn tzif_block_len(counts: [usize; 6], time_len: usize) -> usize {
+    let [
+        utc_count,
+        std_count,
+        leap_count,
+        time_count,
+        type_count,
+        char_count,
+    ] = counts;
+    time_count * (time_len + 1)
+        + type_count * 6
+        + char_count
+        + leap_count * (time_len + 4)
+        + std_count
+        + utc_count
+}
+
+/// A POSIX `TZ` rule: a standard offset and optional daylight-saving time
+#[derive(Debug, Clone, PartialEq)]
+pub struct PosixRule {
+    /// Standard offset in seconds east of UTC
+    std_offset: i32,
+    /// Daylight-saving time, if observed
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    /// Daylight-saving offset in seconds east of UTC
+    offset: i32,
+    /// Day and local time (in seconds, standard time) daylight-saving starts
+    start: (TransitionDay, i32),
+    /// Day and local time (in seconds, daylight-saving time) it ends
+    end: (TransitionDay, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransitionDay {
+    /// `Mm.w.d`: weekday `d` (0 = Sunday) of week `w` (5 = last) of month `m`
+    MonthWeekDay(u32, u32, u32),
+    /// `Jn`: day 1-365, never counting February 29
+    Julian(u32),
+    /// `n`: zero-based day of the year, counting February 29
+    DayOfYear(u32),
+}
+
+impl TransitionDay {
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        match self {
+            Self::MonthWeekDay(month, week, weekday) => {
+                let weekday = Weekday::try_from(((weekday + 6) % 7) as u8).ok()?;
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let offset = (weekday.num_days_from_monday() + 7
+                    - first.weekday().num_days_from_monday())
+                    % 7;
+                let mut date = first + Duration::days((offset + (week - 1) * 7) as i64);
+                while date.month() != month {
+                    date -= Duration::weeks(1);
+                }
+                Some(date)
+            }
+            Self::Julian(day) => {
+                let date = NaiveDate::from_yo_opt(year, day)?;
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                Some(if leap && day >= 60 {
+                    date + Duration::days(1)
+                } else {
+                    date
+                })
+            }
+            Self::DayOfYear(day) => NaiveDate::from_yo_opt(year, day + 1),
+        }
+    }
+}
+
+impl PosixRule {
+    /// Offset from UTC at an instant
+    fn offset_at(&self, time: DateTime<Utc>) -> FixedOffset {
+        let secs = match &self.dst {
+            Some(dst) if dst.observed_at(time, self.std_offset) => dst.offset,
+            _ => self.std_offset,
+        };
+        FixedOffset::east_opt(secs).expect("offset within a day")
+    }
+
+    /// All offsets the zone uses
+    fn offsets(&self) -> impl Iterator<Item = i32> + '_ {
+        std::iter::once(self.std_offset).chain(self.dst.as_ref().map(|dst| dst.offset))
+    }
+}
+
+impl DstRule {
+    fn observed_at(&self, time: DateTime<Utc>, std_offset: i32) -> bool {
+        let year = (time + Duration::seconds(std_offset as i64)).year();
+        let instant = |(day, secs): (TransitionDay, i32), offset: i32| {
+            day.date(year).map(|date| {
+                date.and_time(NaiveTime::MIN).and_utc() + Duration::seconds((secs - offset) as i64)
+            })
+        };
+        let (Some(start), Some(end)) = (
+            instant(self.start, std_offset),
+            instant(self.end, self.offset),
+        ) else {
+            return false;
+        };
+        if start < end {
+            start <= time && time < end
+        } else {
+            // Southern hemisphere: daylight-saving time spans the new year
+            time < end || time >= start
+        }
+    }
+}
+
+impl FromStr for PosixRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid POSIX timezone rule '{}'", s);
+        let mut rest = s.trim();
+
+        rest = skip_name(rest).ok_or_else(invalid)?;
+        let (std_offset, after) = take_offset(rest).ok_or_else(invalid)?;
+        // POSIX offsets are west of UTC
+        let std_offset = -std_offset;
+        rest = after;
+        if rest.is_empty() {
+            return Ok(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+
+        rest = skip_name(rest).ok_or_else(invalid)?;
+        let offset = match take_offset(rest) {
+            Some((offset, after)) => {
+                rest = after;
+                -offset
+            }
+            None => std_offset + 3600,
+        };
+        let rules = rest.strip_prefix(',').ok_or_else(invalid)?;
+        let (start, end) = rules.split_once(',').ok_or_else(invalid)?;
+        Ok(Self {
+            std_offset,
+            dst: Some(DstRule {
+                offset,
+                start: parse_transition(start).ok_or_else(invalid)?,
+                end: parse_transition(end).ok_or_else(invalid)?,
+            }),
+        })
+    }
+}
+
+/// Skip a zone abbreviation (`CET` or `<+09>`)
+fn skip_name(s: &str) -> Option<&str> {
+    if let Some(quoted) = s.strip_prefix('<') {
+        return quoted.split_once('>').map(|(_, rest)| rest);
+    }
+    let len = s
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    (len >= 3).then(|| &s[len..])
+}
+
+/// Take an offset like `-1`, `5` or `+05:30` from the start of `s`
+fn take_offset(s: &str) -> Option<(i32, &str)> {
+    let len = s
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == ':' || (i == 0 && (c == '+' || c == '-'))))
+        .map_or(s.len(), |(i, _)| i);
+    parse_offset(&s[..len]).map(|secs| (secs, &s[len..]))
+}
+
+/// Parse `[+-]hh[:mm[:ss]]` into seconds
+fn parse_offset(s: &str) -> Option<i32> {
+    let (sign, digits) = match s.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let mut secs = 0;
+    let mut parts = 0;
+    for (part, scale) in digits.split(':').zip([3600, 60, 1]) {
+        if part.is_empty() || part.len() > 3 {
+            return None;
+        }
+        secs += part.parse::<i32>().ok()? * scale;
+        parts += 1;
+    }
+    (parts > 0 && digits.split(':').count() == parts && secs <= 167 * 3600).then_some(sign * secs)
+}
+
+/// Parse a transition such as `M3.5.0/3` (default time 02:00)
+fn parse_transition(s: &str) -> Option<(TransitionDay, i32)> {
+    let (day, time) = match s.split_once('/') {
+        Some((day, time)) => (day, parse_offset(time)?),
+        None => (s, 2 * 3600),
+    };
+    let day = if let Some(mwd) = day.strip_prefix('M') {
+        let mut parts = mwd.split('.').map(|p| p.parse::<u32>().ok());
+        let (month, week, weekday) = (parts.next()??, parts.next()??, parts.next()??);
+        if parts.next().is_some()
+            || !(1..=12).contains(&month)
+            || !(1..=5).contains(&week)
+            || weekday > 6
+        {
+            return None;
+        }
+        TransitionDay::MonthWeekDay(month, week, weekday)
+    } else if let Some(julian) = day.strip_prefix('J') {
+        TransitionDay::Julian(julian.parse().ok().filter(|d| (1..=365).contains(d))?)
+    } else {
+        TransitionDay::DayOfYear(day.parse().ok().filter(|d| *d <= 365)?)
+    };
+    Some((day, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn new_york() -> Timezone {
+        Timezone::Rule {
+            name: "America/New_York".to_string(),
+            history: Vec::new(),
+            rule: "EST5EDT,M3.2.0,M11.1.0".parse().unwrap(),
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn days_around_dst_changes_are_23_and_25_hours() {
+        let tz = new_york();
+        // Clocks go forward on 2024-03-10 and back on 2024-11-03
+        let spring = tz.start_of_day(date(2024, 3, 11)) - tz.start_of_day(date(2024, 3, 10));
+        let autumn = tz.start_of_day(date(2024, 11, 4)) - tz.start_of_day(date(2024, 11, 3));
+        assert_eq!(spring, Duration::hours(23));
+        assert_eq!(autumn, Duration::hours(25));
+        assert_eq!(
+            tz.start_of_day(date(2024, 3, 10)),
+            Utc.with_ymd_and_hms(2024, 3, 10, 5, 0, 0).unwrap()
+        );
+        assert_eq!(
+            tz.start_of_day(date(2024, 7, 1)),
+            Utc.with_ymd_and_hms(2024, 7, 1, 4, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_skipped_and_repeated_local_times() {
+        let tz = new_york();
+        let local = |h, m| date(2024, 3, 10).and_hms_opt(h, m, 0).unwrap();
+        // 02:30 does not exist; it is shifted to 03:30 EDT
+        assert_eq!(
+            tz.from_local(local(2, 30)),
+            Utc.with_ymd_and_hms(2024, 3, 10, 7, 30, 0).unwrap()
+        );
+        // 01:30 on 2024-11-03 happens twice; the first (EDT) one is used
+        let repeated = date(2024, 11, 3).and_hms_opt(1, 30, 0).unwrap();
+        assert_eq!(
+            tz.from_local(repeated),
+            Utc.with_ymd_and_hms(2024, 11, 3, 5, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn handles_southern_hemisphere_and_fixed_zones() {
+        let sydney = Timezone::Rule {
+            name: "Australia/Sydney".to_string(),
+            history: Vec::new(),
+            rule: "AEST-10AEDT,M10.1.0,M4.1.0/3".parse().unwrap(),
+        };
+        let january = Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap();
+        let july = Utc.with_ymd_and_hms(2024, 7, 15, 0, 0, 0).unwrap();
+        assert_eq!(sydney.offset_at(january).local_minus_utc(), 11 * 3600);
+        assert_eq!(sydney.offset_at(july).local_minus_utc(), 10 * 3600);
+
+        let tokyo: Timezone = "+09:00".parse().unwrap();
+        let evening = Utc.with_ymd_and_hms(2024, 1, 1, 15, 0, 0).unwrap();
+        assert_eq!(tokyo.date_of(evening), date(2024, 1, 2));
+        assert_eq!(
+            "<+0530>-5:30".parse::<PosixRule>().unwrap().std_offset,
+            19800
+        );
+        assert!("EST5EDT,M13.1.0,M11.1.0".parse::<PosixRule>().is_err());
+        assert!("../etc/passwd".parse::<Timezone>().is_err());
+    }
+
+    /// Version 2 TZif data with an empty 32-bit block
+    fn tzif(transitions: &[(i64, u8)], offsets: &[i32], footer: &str) -> Vec<u8> {
+        let header = |times: usize, types: usize| {
+            let mut header = b"TZif2".to_vec();
+            header.resize(20, 0);
+            for count in [0, 0, 0, times, types, 0] {
+                header.extend((count as u32).to_be_bytes());
+            }
+            header
+        };
+        let mut data = header(0, 0);
+        data.extend(header(transitions.len(), offsets.len()));
+        data.extend(transitions.iter().flat_map(|(at, _)| at.to_be_bytes()));
+        data.extend(transitions.iter().map(|&(_, index)| index));
+        for offset in offsets {
+            data.extend(offset.to_be_bytes());
+            data.extend([0, 0]);
+        }
+        data.extend(format!("\n{}\n", footer).bytes());
+        data
+    }
+
+    #[test]
+    fn applies_historic_transitions_before_the_rule() {
+        // New York before the 2007 rule change: April to October
+        let at = |y, m, d, h| Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap().timestamp();
+        // The table runs until the rule describes the transitions
+        let data = tzif(
+            &[
+                (at(2006, 4, 2, 7), 1),
+                (at(2006, 10, 29, 6), 0),
+                (at(2007, 3, 11, 7), 1),
+            ],
+            &[-5 * 3600, -4 * 3600],
+            "EST5EDT,M3.2.0,M11.1.0",
+        );
+        let (history, rule) = parse_tzif(&data).unwrap();
+        let tz = Timezone::Rule {
+            name: "America/New_York".to_string(),
+            history,
+            rule,
+        };
+        let day = |d: NaiveDate| tz.start_of_day(d.succ_opt().unwrap()) - tz.start_of_day(d);
+        assert_eq!(day(date(2006, 3, 12)), Duration::hours(24));
+        assert_eq!(day(date(2006, 4, 2)), Duration::hours(23));
+        assert_eq!(day(date(2006, 10, 29)), Duration::hours(25));
+        assert_eq!(day(date(2006, 11, 5)), Duration::hours(24));
+        // The rule takes over after the last transition
+        assert_eq!(day(date(2007, 3, 11)), Duration::hours(23));
+        assert_eq!(day(date(2007, 11, 4)), Duration::hours(25));
+        let before = Utc.with_ymd_and_hms(1990, 7, 1, 0, 0, 0).unwrap();
+        assert_eq!(tz.offset_at(before).local_minus_utc(), -5 * 3600);
+
+        assert!(parse_tzif(b"TZif2").is_err());
+        assert!(parse_tzif(&tzif(&[(0, 3)], &[0], "UTC0")).is_err());
+    }
+
+    #[test]
+    fn switches_zones_on_overridden_dates() {
+        let tz = Timezone::Utc
+            .with_overrides(
+                "2024-03-04..2024-03-05=+09:00; 2024-03-20..2024-03-21=EST5EDT,M3.2.0,M11.1.0",
+            )
+            .unwrap();
+        // In Tokyo on the 4th and 5th: days start at 15:00 UTC
+        assert_eq!(
+            tz.start_of_day(date(2024, 3, 4)),
+            Utc.with_ymd_and_hms(2024, 3, 3, 15, 0, 0).unwrap()
+        );
+        let evening = Utc.with_ymd_and_hms(2024, 3, 4, 16, 0, 0).unwrap();
+        assert_eq!(tz.date_of(evening), date(2024, 3, 5));
+        // Back home: the 5th lasts until midnight UTC
+        let return_day = tz.start_of_day(date(2024, 3, 6)) - tz.start_of_day(date(2024, 3, 5));
+        assert_eq!(return_day, Duration::hours(33));
+        // Flying west: the 20th starts at home and ends in New York
+        let travel_day = tz.start_of_day(date(2024, 3, 21)) - tz.start_of_day(date(2024, 3, 20));
+        assert_eq!(travel_day, Duration::hours(28));
+        assert_eq!(
+            tz.start_of_day(date(2024, 3, 21)),
+            Utc.with_ymd_and_hms(2024, 3, 21, 4, 0, 0).unwrap()
+        );
+
+        assert!(
+            Timezone::Utc
+                .with_overrides("2024-03-01..2024-03-05=UTC;2024-03-05=UTC")
+                .is_err()
+        );
+        assert!(
+            Timezone::Utc
+                .with_overrides("2024-03-05..2024-03-01=UTC")
+                .is_err()
+        );
+        assert_eq!(Timezone::Utc.with_overrides(" ").unwrap(), Timezone::Utc);
+    }
+}
@@ -0,0 +1,1223 @@
+use super::{
+    ActivityWatchMcpServer, fit_response, json_response, json_text, token_budget, truncate_response,
+};
+use crate::analysis::{
+    AFK_BUCKET_TYPE, Example, GroupChange, GroupHandle, MIN_GAP_SECS, ResolutionChoice,
+    TRUSTWORTHY_SCORE, TimeRange, TokenBudget, TotalsCheck, active_time,
+    apply_zero_duration_policy, choose_resolution, data_quality, diff_totals, discrepancy_markdown,
+    examples_by_key, format_timestamp, interval_label, time_series, totals_by_key, totals_within,
+};
+use crate::constants::{
+    DEFAULT_ACTIVE_TIME_TOP, DEFAULT_TOP_APPS, MAX_DAILY_RANGE_DAYS, MAX_EXAMPLES,
+};
+use crate::format::{
+    code_span, format_duration, format_duration_delta, format_percent, round_percent, sanitize_line,
+};
+use crate::models::{Bucket, DetailLevel, Resolution, ResponseFormat, ZeroDurationPolicy};
+use crate::timezone::Timezone;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Default data key used to group events
+const DEFAULT_GROUP_BY: &str = "app";
+
+/// Data key listed under each group at higher detail levels
+const TITLE_KEY: &str = "title";
+
+/// Approximate characters of a diff row, or of a title listed under it
+const DIFF_ROW_CHARS: usize = 60;
+
+/// Approximate characters of the diff's heading and totals
+const DIFF_HEADER_CHARS: usize = 300;
+
+/// Note on where a bucket's timezone came from, when not configured
+fn timezone_source(tz: &Timezone, configured: &Timezone) -> &'static str {
+    match tz == configured {
+        true => "",
+        false => " (from the bucket's metadata)",
+    }
+}
+
+/// Input for diffing activity between two ranges
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DiffActivityParams {
+    /// The bucket ID to analyze (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// Start of the first range (ISO 8601 format)
+    #[serde(default)]
+    pub first_start: Option<String>,
+
+    /// End of the first range (ISO 8601 format)
+    #[serde(default)]
+    pub first_end: Option<String>,
+
+    /// The first range as a period instead of start and end (e.g., "2024-Q1", "2024-W07", "FY2024-Q1", "work hours yesterday")
+    #[serde(default)]
+    pub first_period: Option<String>,
+
+    /// Start of the second range (ISO 8601 format)
+    #[serde(default)]
+    pub second_start: Option<String>,
+
+    /// End of the second range (ISO 8601 format)
+    #[serde(default)]
+    pub second_end: Option<String>,
+
+    /// The second range as a period instead of start and end (e.g., "2024-Q2")
+    #[serde(default)]
+    pub second_period: Option<String>,
+
+    /// Event data key to group by (default: "app")
+    #[serde(default)]
+    pub group_by: Option<String>,
+
+    /// Maximum rows per section (default: 5, 10 or 25 by detail level)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Detail level: "brief", "normal" (default) or "full"; controls the number of rows and of top titles listed per group
+    #[serde(default)]
+    pub detail: Option<DetailLevel>,
+
+    /// Zero-duration event handling: "keep", "drop" or "merge_into_previous" (default: server setting)
+    #[serde(default)]
+    pub zero_duration: Option<ZeroDurationPolicy>,
+
+    /// Approximate size the response should fit in, in tokens (e.g., 1000); picks the detail level and rows unless given
+    #[serde(default)]
+    pub max_tokens_hint: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for a time series of activity
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TimeSeriesParams {
+    /// The bucket ID to analyze (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "2024-Q1", "FY2024", "this morning")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Interval length: "hour", "day" or "week" (default: chosen from the range length)
+    #[serde(default)]
+    pub resolution: Option<Resolution>,
+
+    /// Zero-duration event handling: "keep", "drop" or "merge_into_previous" (default: server setting)
+    #[serde(default)]
+    pub zero_duration: Option<ZeroDurationPolicy>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the top-apps summary
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TopAppsParams {
+    /// The window watcher bucket (default: the window bucket with the most recent activity)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "today", "yesterday", "2024-W07")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Number of apps listed (default: 10)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Example events listed per group, longest first (default: 0, at most 10)
+    #[serde(default)]
+    pub examples: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the active-time summary
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ActiveTimeParams {
+    /// The window watcher bucket (default: the window bucket with the most recent activity)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// The AFK watcher bucket (default: the AFK watcher on the window bucket's host)
+    #[serde(default)]
+    pub afk_bucket_id: Option<String>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "today", "yesterday", "2024-W07")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Event data key to group by (default: "app")
+    #[serde(default)]
+    pub group_by: Option<String>,
+
+    /// Maximum groups listed (default: 10)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Example events listed per group, longest first (default: 0, at most 10)
+    #[serde(default)]
+    pub examples: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the data-quality report
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DataQualityParams {
+    /// The bucket whose events are checked for overlaps (e.g., "aw-watcher-window_hostname"); AFK data comes from the same host
+    pub bucket_id: String,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = activity_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Compare activity between two equal-length time ranges.
+    #[tool(
+        description = r#"Compare activity between two time ranges (e.g., this week vs last week, or Q1 vs Q2) and report which apps appeared, disappeared, or changed most. Much more compact than two full listings.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `first_start` / `first_end`: The earlier (baseline) range in ISO 8601 format
+- `second_start` / `second_end`: The range to compare, same length as the first
+- `first_period` / `second_period`: The ranges as periods instead, e.g. "2024-W06" vs "2024-W07", "2024-Q1" vs "2024-Q2", or fiscal "FY2024-Q1" or presets such as "work hours yesterday" vs "this morning" (months and quarters may differ in length)
+- `group_by`: Event data key to group by (default: "app"; e.g., "title", "url")
+- `top`: Maximum rows per section (default: 5, 10 or 25 by detail level)
+- `detail`: "brief" (top groups only), "normal" (default; top 3 titles per new or changed group) or "full" (top 10 titles)
+- `max_tokens_hint`: Approximate token budget of the response (e.g., 1000). Without `detail` and `top`, they are chosen to fit, and JSON is compact"#
+    )]
+    async fn aw_diff_activity(
+        &self,
+        Parameters(params): Parameters<DiffActivityParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let budget = match token_budget(params.max_tokens_hint) {
+            Ok(budget) => budget,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let periods = params.first_period.is_some() || params.second_period.is_some();
+        let ranges = self
+            .resolve_range(
+                params.first_start.as_deref(),
+                params.first_end.as_deref(),
+                params.first_period.as_deref(),
+            )
+            .and_then(|first| {
+                let second = self.resolve_range(
+                    params.second_start.as_deref(),
+                    params.second_end.as_deref(),
+                    params.second_period.as_deref(),
+                )?;
+                // Calendar periods such as months and quarters differ in length
+                if !periods && first.duration() != second.duration() {
+                    return Err(format!(
+                        "Ranges must have equal length (first: {}, second: {})",
+                        format_duration(first.duration_secs()),
+                        format_duration(second.duration_secs())
+                    ));
+                }
+                Ok((first, second))
+            });
+        let (first, second) = match ranges {
+            Ok(ranges) => ranges,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.diff_activity(&params, &first, &second, budget).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to diff activity: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Total activity per hour, day or week.
+    #[tool(
+        description = r#"Total event time per interval (hour, day or week) over a range, as a compact table. Long ranges are downsampled automatically so the output stays usable, and the resolution used is reported.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period instead, e.g. "2024-W07", "2024-03", "2024-Q1", "2024", fiscal "FY2024-Q1" or a preset such as "work hours yesterday"
+- `resolution`: "hour", "day" or "week". Default: hourly up to 14 days, daily up to 182 days, weekly beyond. A requested resolution that would produce more than 400 points is coarsened"#
+    )]
+    async fn aw_time_series(
+        &self,
+        Parameters(params): Parameters<TimeSeriesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let tz = self.bucket_timezone(&params.bucket_id).await;
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.time_series(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to build time series: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// List the apps with the most window time.
+    #[tool(
+        description = r#"List the top apps by window time in a range, with each app's total and share of all window time; the remaining apps are summed up in one row. Use this for "which apps did I use most" questions instead of fetching raw events. Window time counts while away from the computer too; aw_active_time leaves that out.
+
+## Parameters
+- `bucket_id`: The window watcher bucket (default: the one with the most recent activity)
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "today", "yesterday" or "2024-W07"
+- `top`: Number of apps listed (default: 10)
+- `examples`: Example events (start, duration and title) listed per group as evidence, longest first (default: 0, at most 10)"#
+    )]
+    async fn aw_top_apps(
+        &self,
+        Parameters(params): Parameters<TopAppsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params
+            .bucket_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.top == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "top must be at least 1",
+            )]));
+        }
+        if params
+            .examples
+            .is_some_and(|examples| examples > MAX_EXAMPLES)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "examples must be at most {}",
+                MAX_EXAMPLES
+            ))]));
+        }
+
+        let tz = match &params.bucket_id {
+            Some(bucket_id) => self.bucket_timezone(bucket_id.trim()).await,
+            None => self.config().timezone.clone(),
+        };
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.top_apps(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to list top apps: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Total window activity while the user was not AFK.
+    #[tool(
+        description = r#"Report active time for a range: window activity while the AFK watcher saw the user present, in total and per app. This joins the window and AFK watcher buckets, so time with a window open while away from the computer is left out. Use this for "how long did I work / use X" questions instead of combining raw events.
+
+The response also gives the time the AFK watcher saw the user present and the window time while away, to show how much was left out.
+
+## Parameters
+- `bucket_id`: The window watcher bucket (default: the one with the most recent activity)
+- `afk_bucket_id`: The AFK watcher bucket (default: the AFK watcher on the same host)
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "today", "yesterday" or "2024-W07"
+- `group_by`: Event data key to group by (default: "app"; e.g., "title")
+- `top`: Maximum groups listed (default: 10)
+- `examples`: Example events (start, duration and title) listed per group as evidence, longest first (default: 0, at most 10)"#
+    )]
+    async fn aw_active_time(
+        &self,
+        Parameters(params): Parameters<ActiveTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let empty = |id: &Option<String>| id.as_deref().is_some_and(|id| id.trim().is_empty());
+        if empty(&params.bucket_id) || empty(&params.afk_bucket_id) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.top == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "top must be at least 1",
+            )]));
+        }
+        if params
+            .examples
+            .is_some_and(|examples| examples > MAX_EXAMPLES)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "examples must be at most {}",
+                MAX_EXAMPLES
+            ))]));
+        }
+
+        let tz = match &params.bucket_id {
+            Some(bucket_id) => self.bucket_timezone(bucket_id.trim()).await,
+            None => self.config().timezone.clone(),
+        };
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.active_time(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to compute active time: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Score each day of a range by data quality.
+    #[tool(
+        description = r#"Score each day in a range by data quality so you know which periods are trustworthy enough to base reports on. Check this before summarizing long or old ranges.
+
+Each day gets a score from 0 to 100: the share of its tracked span (first to last AFK data) without gaps in the AFK data, reduced by the share of the bucket's event time where events overlap each other. Days also report AFK data coverage, the number of gaps of 5 minutes or more, and the longest gap. A day is trustworthy with a score of at least 90 and coverage above the server's minimum.
+
+## Parameters
+- `bucket_id`: The bucket checked for overlaps (e.g., "aw-watcher-window_hostname"); AFK data comes from the AFK watcher on the same host
+- `start` / `end`: The range in ISO 8601 format (at most 182 days)
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday""#
+    )]
+    async fn aw_data_quality(
+        &self,
+        Parameters(params): Parameters<DataQualityParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let tz = self.bucket_timezone(&params.bucket_id).await;
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        if range.duration().num_days() > MAX_DAILY_RANGE_DAYS {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "The range is longer than {} days; check it in parts",
+                MAX_DAILY_RANGE_DAYS
+            ))]));
+        }
+
+        match self.data_quality(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to check data quality: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// The bucket asked for, or the window bucket with the most recent
+    /// activity
+    pub(super) async fn window_bucket<'a>(
+        &self,
+        buckets: &'a HashMap<String, Bucket>,
+        bucket_id: Option<&str>,
+    ) -> Result<&'a Bucket, McpError> {
+        match bucket_id.map(str::trim) {
+            Some(bucket_id) => {
+                self.check_scope(bucket_id).await?;
+                buckets.get(bucket_id).ok_or_else(|| {
+                    McpError::invalid_params(format!("Bucket {} not found", bucket_id), None)
+                })
+            }
+            None => self
+                .latest_window_bucket(buckets)
+                .ok_or_else(|| McpError::invalid_params("No window buckets found", None)),
+        }
+    }
+
+    /// Markdown sections with the example events of the listed groups, if
+    /// any were requested
+    fn example_lines<'a>(
+        &self,
+        groups: impl Iterator<Item = &'a (String, f64)>,
+        examples: &HashMap<String, Vec<Example>>,
+        tz: &Timezone,
+    ) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (group, _) in groups {
+            let Some(examples) = examples.get(group) else {
+                continue;
+            };
+            if lines.is_empty() {
+                lines.extend([String::new(), "## Examples".to_string()]);
+            }
+            lines.extend([String::new(), format!("### {}", self.markdown_text(group))]);
+            lines.push(String::new());
+            lines.extend(examples.iter().map(|example| {
+                format!(
+                    "- {} ({}): {}",
+                    tz.to_local(example.timestamp).format("%Y-%m-%d %H:%M"),
+                    format_duration(example.secs),
+                    self.markdown_text(&example.title)
+                )
+            }));
+        }
+        lines
+    }
+
+    /// Total the app time of a window bucket's events in the range
+    async fn top_apps(
+        &self,
+        params: &TopAppsParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let bucket = self
+            .window_bucket(&buckets, params.bucket_id.as_deref())
+            .await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let events = self.range_events(&bucket.id, range).await?;
+        let apps = totals_by_key(&events, DEFAULT_GROUP_BY, Some(range));
+        let total_secs: f64 = apps.iter().map(|(_, secs)| secs).sum();
+        let top = params.top.unwrap_or(DEFAULT_TOP_APPS);
+        let share = |secs: f64| match total_secs > 0.0 {
+            true => secs / total_secs * 100.0,
+            false => 0.0,
+        };
+        let other_secs: f64 = apps.iter().skip(top).map(|(_, secs)| secs).sum();
+        let examples = examples_by_key(
+            &events,
+            DEFAULT_GROUP_BY,
+            Some(range),
+            params.examples.unwrap_or(0),
+        );
+        let group_id = |app: &str| {
+            GroupHandle {
+                bucket_id: bucket.id.clone(),
+                key: DEFAULT_GROUP_BY.to_string(),
+                value: app.to_string(),
+                range: *range,
+                afk_bucket_id: None,
+            }
+            .to_string()
+        };
+        let discrepancies = TotalsCheck::new(range.duration_secs(), total_secs).discrepancies();
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    "# Top Apps".to_string(),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!("- **Bucket**: {}", bucket.id),
+                    format!(
+                        "- **Total**: {} in {} apps",
+                        format_duration(total_secs),
+                        apps.len()
+                    ),
+                    String::new(),
+                ];
+                if apps.is_empty() {
+                    lines.push("_No window activity in this range._".to_string());
+                } else {
+                    lines.push("| # | App | Time | Share | Group ID |".to_string());
+                    lines.push("|---|---|---|---|---|".to_string());
+                    lines.extend(apps.iter().take(top).enumerate().map(|(i, (app, secs))| {
+                        format!(
+                            "| {} | {} | {} | {} | {} |",
+                            i + 1,
+                            self.markdown_text(app),
+                            format_duration(*secs),
+                            format_percent(share(*secs)),
+                            code_span(&group_id(app))
+                        )
+                    }));
+                    if apps.len() > top {
+                        lines.push(format!(
+                            "| | _{} other apps_ | {} | {} | |",
+                            apps.len() - top,
+                            format_duration(other_secs),
+                            format_percent(share(other_secs))
+                        ));
+                    }
+                }
+                lines.extend(self.example_lines(apps.iter().take(top), &examples, tz));
+                if let Some(warning) = discrepancy_markdown(&discrepancies) {
+                    lines.push(String::new());
+                    lines.push(warning);
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "range": range,
+                "bucket_id": bucket.id,
+                "total_secs": total_secs,
+                "app_count": apps.len(),
+                "apps": apps
+                    .iter()
+                    .take(top)
+                    .map(|(app, secs)| {
+                        let mut entry = serde_json::json!({
+                            "app": app,
+                            "secs": secs,
+                            "percent": round_percent(share(*secs)),
+                            "group_id": group_id(app),
+                        });
+                        if let Some(examples) = examples.get(app) {
+                            entry["examples"] = serde_json::json!(examples);
+                        }
+                        entry
+                    })
+                    .collect::<Vec<_>>(),
+                "other_secs": other_secs,
+                "discrepancies": discrepancies,
+            })),
+        })
+    }
+
+    /// Find the window and AFK buckets, fetch their events and total the
+    /// window time while present
+    async fn active_time(
+        &self,
+        params: &ActiveTimeParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let bucket = self
+            .window_bucket(&buckets, params.bucket_id.as_deref())
+            .await?;
+        let afk_bucket = match params.afk_bucket_id.as_deref().map(str::trim) {
+            Some(afk_bucket_id) => {
+                self.check_scope(afk_bucket_id).await?;
+                buckets.get(afk_bucket_id).ok_or_else(|| {
+                    McpError::invalid_params(format!("Bucket {} not found", afk_bucket_id), None)
+                })?
+            }
+            None => self.afk_bucket_of(&buckets, bucket).ok_or_else(|| {
+                McpError::invalid_params(
+                    format!(
+                        "No AFK watcher bucket found for host {}; pass afk_bucket_id",
+                        bucket.hostname.as_deref().unwrap_or("unknown")
+                    ),
+                    None,
+                )
+            })?,
+        };
+
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let window_events = self.range_events(&bucket.id, range).await?;
+        let afk_events = self.range_events(&afk_bucket.id, range).await?;
+        let group_by = params.group_by.as_deref().unwrap_or(DEFAULT_GROUP_BY);
+        let examples = params.examples.unwrap_or(0);
+        let active = active_time(&window_events, &afk_events, group_by, range, examples);
+        let top = params.top.unwrap_or(DEFAULT_ACTIVE_TIME_TOP);
+        let away_secs = (active.window_secs - active.active_secs).max(0.0);
+        let group_id = |group: &str| {
+            GroupHandle {
+                bucket_id: bucket.id.clone(),
+                key: group_by.to_string(),
+                value: group.to_string(),
+                range: *range,
+                afk_bucket_id: Some(afk_bucket.id.clone()),
+            }
+            .to_string()
+        };
+        let mut discrepancies = TotalsCheck {
+            present_secs: Some(active.present_secs),
+            group_secs: Some(active.groups.iter().map(|(_, secs)| secs).sum()),
+            ..TotalsCheck::new(range.duration_secs(), active.active_secs)
+        }
+        .discrepancies();
+        // Checked only when the rest adds up, as an overlong active time
+        // already implies an overlong present time
+        if discrepancies.is_empty() {
+            discrepancies =
+                TotalsCheck::new(range.duration_secs(), active.present_secs).discrepancies();
+        }
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    "# Active Time".to_string(),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!("- **Window bucket**: {}", bucket.id),
+                    format!("- **AFK bucket**: {}", afk_bucket.id),
+                    format!("- **Active time**: {}", format_duration(active.active_secs)),
+                    format!(
+                        "- **Present (AFK watcher)**: {}",
+                        format_duration(active.present_secs)
+                    ),
+                    format!(
+                        "- **Window time while away**: {} (left out)",
+                        format_duration(away_secs)
+                    ),
+                ];
+                if !active.groups.is_empty() {
+                    lines.push(String::new());
+                    lines.push(format!(
+                        "| {} | Time | Share | Group ID |",
+                        sanitize_line(group_by)
+                    ));
+                    lines.push("|---|---|---|---|".to_string());
+                    lines.extend(active.groups.iter().take(top).map(|(group, secs)| {
+                        format!(
+                            "| {} | {} | {} | {} |",
+                            self.markdown_text(group),
+                            format_duration(*secs),
+                            format_percent(secs / active.active_secs * 100.0),
+                            code_span(&group_id(group))
+                        )
+                    }));
+                    if active.groups.len() > top {
+                        lines.push(String::new());
+                        lines.push(format!(
+                            "_{} more not listed; raise `top` to see them._",
+                            active.groups.len() - top
+                        ));
+                    }
+                }
+                let listed = active.groups.iter().take(top);
+                lines.extend(self.example_lines(listed, &active.examples, tz));
+                if let Some(warning) = discrepancy_markdown(&discrepancies) {
+                    lines.push(String::new());
+                    lines.push(warning);
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "range": range,
+                "bucket_id": bucket.id,
+                "afk_bucket_id": afk_bucket.id,
+                "group_by": group_by,
+                "active_secs": active.active_secs,
+                "present_secs": active.present_secs,
+                "window_secs": active.window_secs,
+                "away_secs": away_secs,
+                "groups": active
+                    .groups
+                    .iter()
+                    .take(top)
+                    .map(|(group, secs)| {
+                        let mut entry = serde_json::json!({
+                            "name": group,
+                            "secs": secs,
+                            "group_id": group_id(group),
+                        });
+                        if let Some(examples) = active.examples.get(group) {
+                            entry["examples"] = serde_json::json!(examples);
+                        }
+                        entry
+                    })
+                    .collect::<Vec<_>>(),
+                "discrepancies": discrepancies,
+            })),
+        })
+    }
+
+    /// Fetch the bucket's and its host's AFK events and score each day
+    async fn data_quality(
+        &self,
+        params: &DataQualityParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let buckets = self.client.buckets().await?;
+        let hostname = buckets
+            .get(&params.bucket_id)
+            .and_then(|b| b.hostname.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+        let afk_bucket = buckets
+            .values()
+            .find(|b| {
+                self.in_scope(b)
+                    && b.bucket_type.as_deref() == Some(AFK_BUCKET_TYPE)
+                    && b.hostname.as_deref() == Some(hostname.as_str())
+            })
+            .ok_or_else(|| {
+                McpError::invalid_params(
+                    format!("No AFK watcher bucket found for host {}", hostname),
+                    None,
+                )
+            })?;
+
+        let events = self.range_events(&params.bucket_id, range).await?;
+        let afk_events = self.range_events(&afk_bucket.id, range).await?;
+        let config = self.config();
+        let days = data_quality(&afk_events, &events, range, tz);
+        let trustworthy = days
+            .iter()
+            .filter(|day| day.is_trustworthy(config.min_coverage))
+            .count();
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!("# Data Quality of {}", params.bucket_id),
+                    String::new(),
+                    format!("- **AFK data**: {}", afk_bucket.id),
+                    format!(
+                        "- **Timezone**: {}{}",
+                        tz,
+                        timezone_source(tz, &config.timezone)
+                    ),
+                    format!(
+                        "- **Trustworthy days**: {} of {} (score {}+ and at least {} coverage)",
+                        trustworthy,
+                        days.len(),
+                        TRUSTWORTHY_SCORE,
+                        format_percent(config.min_coverage * 100.0)
+                    ),
+                    String::new(),
+                    format!(
+                        "| Day | Score | Coverage | Gaps ({}m+) | Longest gap | Overlap | Trustworthy |",
+                        MIN_GAP_SECS / 60.0
+                    ),
+                    "|---|---|---|---|---|---|---|".to_string(),
+                ];
+                lines.extend(days.iter().map(|day| {
+                    format!(
+                        "| {} | {} | {} | {} | {} | {} | {} |",
+                        interval_label(Resolution::Day, day.start, tz),
+                        day.score,
+                        format_percent(day.coverage * 100.0),
+                        day.gaps,
+                        format_duration(day.longest_gap_secs),
+                        format_duration(day.overlap_secs),
+                        if day.is_trustworthy(config.min_coverage) {
+                            "yes"
+                        } else {
+                            "no"
+                        }
+                    )
+                }));
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "range": range,
+                "afk_bucket_id": afk_bucket.id,
+                "timezone": tz.to_string(),
+                "min_coverage": config.min_coverage,
+                "trustworthy_days": trustworthy,
+                "days": days,
+            })),
+        })
+    }
+
+    /// Fetch the range and render the time series
+    async fn time_series(
+        &self,
+        params: &TimeSeriesParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        let policy = params
+            .zero_duration
+            .unwrap_or(self.config().zero_duration_policy);
+
+        self.check_scope(&params.bucket_id).await?;
+
+        let events = self
+            .client
+            .get_events(
+                &params.bucket_id,
+                None,
+                Some(&format_timestamp(&range.start)),
+                Some(&format_timestamp(&range.end)),
+            )
+            .await?;
+        let events = apply_zero_duration_policy(events, policy);
+        let config = self.config();
+        let (resolution, choice) = choose_resolution(range, params.resolution, tz);
+        let points = time_series(&events, range, resolution, tz);
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let note = match choice {
+                    ResolutionChoice::Requested => String::new(),
+                    ResolutionChoice::Automatic => " (chosen for the range length)".to_string(),
+                    ResolutionChoice::Downsampled => format!(
+                        " (downsampled from {}: too many points for this range)",
+                        params.resolution.unwrap_or(resolution)
+                    ),
+                };
+                let total: f64 = points.iter().map(|p| p.secs).sum();
+                let mut lines = vec![
+                    format!("# Activity over Time in {}", params.bucket_id),
+                    String::new(),
+                    format!(
+                        "- **Range**: {} to {}",
+                        tz.to_local(range.start).format("%Y-%m-%d %H:%M"),
+                        tz.to_local(range.end).format("%Y-%m-%d %H:%M")
+                    ),
+                    format!("- **Resolution**: {}{}", resolution, note),
+                    format!(
+                        "- **Timezone**: {}{}",
+                        tz,
+                        timezone_source(tz, &config.timezone)
+                    ),
+                    format!("- **Total**: {}", format_duration(total)),
+                    String::new(),
+                    "| Period | Time |".to_string(),
+                    "|---|---|".to_string(),
+                ];
+                lines.extend(points.iter().map(|point| {
+                    format!(
+                        "| {} | {} |",
+                        interval_label(resolution, point.start, tz),
+                        format_duration(point.secs)
+                    )
+                }));
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "range": range,
+                "resolution": resolution,
+                "resolution_choice": choice,
+                "timezone": tz.to_string(),
+                "points": points,
+            })),
+        })
+    }
+
+    /// Fetch both ranges and render the diff
+    async fn diff_activity(
+        &self,
+        params: &DiffActivityParams,
+        first: &TimeRange,
+        second: &TimeRange,
+        budget: Option<TokenBudget>,
+    ) -> Result<String, McpError> {
+        let group_by = params.group_by.as_deref().unwrap_or(DEFAULT_GROUP_BY);
+        let detail = params
+            .detail
+            .unwrap_or_else(|| budget.map_or_else(DetailLevel::default, TokenBudget::detail));
+        // Rows per section that fit, each row with its top titles
+        let fitting_rows = budget.map_or(usize::MAX, |budget| {
+            let row_chars = DIFF_ROW_CHARS * (1 + detail.titles_per_group());
+            budget.rows(row_chars, DIFF_HEADER_CHARS).div_ceil(3)
+        });
+        let top = params.top.unwrap_or(detail.max_groups().min(fitting_rows));
+        let policy = params
+            .zero_duration
+            .unwrap_or(self.config().zero_duration_policy);
+
+        self.check_scope(&params.bucket_id).await?;
+
+        let mut totals = Vec::with_capacity(2);
+        let mut after_events = Vec::new();
+        for range in [first, second] {
+            let events = self
+                .client
+                .get_events(
+                    &params.bucket_id,
+                    None,
+                    Some(&format_timestamp(&range.start)),
+                    Some(&format_timestamp(&range.end)),
+                )
+                .await?;
+            let events = apply_zero_duration_policy(events, policy);
+            totals.push(totals_by_key(&events, group_by, Some(range)));
+            after_events = events;
+        }
+
+        let mut diff = diff_totals(&totals[0], &totals[1]);
+        diff.appeared.truncate(top);
+        diff.disappeared.truncate(top);
+        diff.changed.truncate(top);
+
+        // Top titles of the groups active in the second range
+        let titles_per_group = if group_by == TITLE_KEY {
+            0
+        } else {
+            detail.titles_per_group()
+        };
+        let titles: BTreeMap<&str, Vec<(String, f64)>> = if titles_per_group == 0 {
+            BTreeMap::new()
+        } else {
+            diff.appeared
+                .iter()
+                .chain(&diff.changed)
+                .map(|change| {
+                    let mut titles = totals_within(
+                        &after_events,
+                        group_by,
+                        &change.group,
+                        TITLE_KEY,
+                        Some(second),
+                    );
+                    titles.truncate(titles_per_group);
+                    (change.group.as_str(), titles)
+                })
+                .collect()
+        };
+        let title_lines = |group: &str| {
+            titles
+                .get(group)
+                .into_iter()
+                .flatten()
+                .map(|(title, secs)| {
+                    format!("      {} {}", sanitize_line(title), format_duration(*secs))
+                })
+        };
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!("# Activity Diff by `{}`", group_by),
+                    String::new(),
+                    format!(
+                        "- **Before**: {} to {}",
+                        first.start.format("%Y-%m-%d %H:%M"),
+                        first.end.format("%Y-%m-%d %H:%M")
+                    ),
+                    format!(
+                        "- **After**: {} to {}",
+                        second.start.format("%Y-%m-%d %H:%M"),
+                        second.end.format("%Y-%m-%d %H:%M")
+                    ),
+                    format!(
+                        "- **Total**: {} → {} ({})",
+                        format_duration(diff.before_total_secs),
+                        format_duration(diff.after_total_secs),
+                        format_duration_delta(diff.after_total_secs - diff.before_total_secs)
+                    ),
+                    String::new(),
+                ];
+
+                if diff.appeared.is_empty()
+                    && diff.disappeared.is_empty()
+                    && diff.changed.is_empty()
+                {
+                    lines.push("_No activity in either range._".to_string());
+                } else {
+                    lines.push("```diff".to_string());
+                    for change in &diff.appeared {
+                        lines.push(diff_line('+', change));
+                        lines.extend(title_lines(&change.group));
+                    }
+                    lines.extend(diff.disappeared.iter().map(|c| diff_line('-', c)));
+                    for change in &diff.changed {
+                        lines.push(diff_line('~', change));
+                        lines.extend(title_lines(&change.group));
+                    }
+                    lines.push("```".to_string());
+                }
+
+                fit_response(lines.join("\n"), budget)
+            }
+            ResponseFormat::Json => json_response(
+                &serde_json::json!({
+                    "group_by": group_by,
+                    "first": first,
+                    "second": second,
+                    "diff": diff,
+                    "titles": titles,
+                }),
+                budget,
+            ),
+        })
+    }
+}
+
+/// Render one diff row; the group label is kept on one line so it cannot
+/// close the surrounding code fence
+fn diff_line(marker: char, change: &GroupChange) -> String {
+    let group = sanitize_line(&change.group);
+    match marker {
+        '+' => format!("+ {} {} (new)", group, format_duration(change.after_secs)),
+        '-' => format!("- {} {} (gone)", group, format_duration(change.before_secs)),
+        _ => format!(
+            "~ {} {} → {} ({})",
+            group,
+            format_duration(change.before_secs),
+            format_duration(change.after_secs),
+            format_duration_delta(change.delta_secs())
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn params<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Parameters<T> {
+        Parameters(serde_json::from_value(value).unwrap())
+    }
+
+    #[tokio::test]
+    async fn lists_top_apps_with_their_share() {
+        let at = |minute| Utc.with_ymd_and_hms(2024, 3, 1, 9, minute, 0).unwrap();
+        let (_fake, server) = crate::tools::test_server(vec![
+            test_event(at(0), 90.0, serde_json::json!({"app": "Code"})),
+            test_event(at(2), 30.0, serde_json::json!({"app": "Firefox"})),
+            test_event(at(3), 60.0, serde_json::json!({"app": "Code"})),
+        ])
+        .await;
+
+        let result = server
+            .aw_top_apps(params(serde_json::json!({
+                "start": "2024-03-01T00:00:00Z",
+                "end": "2024-03-02T00:00:00Z",
+                "top": 1,
+                "response_format": "json",
+            })))
+            .await
+            .unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&crate::tools::result_text(&result)).unwrap();
+        assert_eq!(json["bucket_id"], "aw-watcher-window_test");
+        assert_eq!(json["total_secs"], 180.0);
+        assert_eq!(json["app_count"], 2);
+        assert_eq!(json["apps"].as_array().unwrap().len(), 1);
+        assert_eq!(json["apps"][0]["app"], "Code");
+        assert_eq!(json["apps"][0]["secs"], 150.0);
+        assert_eq!(json["apps"][0]["percent"], 83.0);
+
+        let result = server
+            .aw_top_apps(params(serde_json::json!({"top": 0})))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn keeps_to_the_scope() {
+        let at = |minute| Utc.with_ymd_and_hms(2024, 3, 1, 9, minute, 0).unwrap();
+        let (_fake, server) = crate::tools::scoped_test_server(
+            vec![test_event(at(0), 60.0, serde_json::json!({"app": "Code"}))],
+            vec![test_event(at(1), 60.0, serde_json::json!({"app": "Games"}))],
+        )
+        .await;
+        fn range<T: serde::de::DeserializeOwned>(mut value: serde_json::Value) -> Parameters<T> {
+            value["start"] = "2024-03-01T00:00:00Z".into();
+            value["end"] = "2024-03-02T00:00:00Z".into();
+            params(value)
+        }
+
+        // The other host's bucket is newer, but out of scope
+        let result = server
+            .aw_top_apps(range(serde_json::json!({"response_format": "json"})))
+            .await
+            .unwrap();
+        let text = crate::tools::result_text(&result);
+        assert!(text.contains("aw-watcher-window_test"), "{}", text);
+        assert!(!text.contains("Games"), "{}", text);
+
+        let result = server
+            .aw_top_apps(range(
+                serde_json::json!({"bucket_id": "aw-watcher-window_other"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(
+            crate::tools::result_text(&result).contains("There's no bucket named"),
+            "{}",
+            crate::tools::result_text(&result)
+        );
+        let result = server
+            .aw_active_time(range(
+                serde_json::json!({"afk_bucket_id": "aw-watcher-window_other"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+}
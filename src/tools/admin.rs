@@ -0,0 +1,593 @@
+//! Operational tools, only offered when `AW_MCP_ADMIN_TOOLS` (or `--admin`)
+//! is set.
+
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::{TimeRange, format_timestamp, parse_timestamp};
+use crate::constants::{AGGREGATE_FETCH_DAYS, AGGREGATE_SETTLE_SECS, DEFAULT_AUDIT_LOG_LIMIT};
+use crate::format::{format_duration, format_percent};
+use crate::models::ResponseFormat;
+use crate::selftest::run_scoped_self_test;
+use chrono::{Duration, NaiveDate};
+use rmcp::{
+    ErrorData as McpError, Peer, RoleServer, handler::server::tool::Parameters, model::*, tool,
+    tool_router,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Input for fetching the audit log
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AuditLogParams {
+    /// Maximum number of entries to return, newest first (default: 50)
+    pub limit: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the cache statistics
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CacheStatsParams {
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for clearing cached data
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CacheClearParams {
+    /// Only clear cached responses of this bucket
+    pub bucket_id: Option<String>,
+
+    /// Only clear cached events overlapping this range: start time in ISO 8601 format
+    pub start: Option<String>,
+
+    /// Only clear cached events overlapping this range: end time in ISO 8601 format
+    pub end: Option<String>,
+}
+
+/// Input for purging cached data of deleted history
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PurgeCacheBeforeParams {
+    /// Cached data before this is purged: a date (YYYY-MM-DD, midnight in the configured timezone) or an ISO 8601 timestamp
+    pub date: String,
+
+    /// Only purge cached data of this bucket
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+}
+
+/// Input for rebuilding the aggregation store
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RebuildAggregatesParams {
+    /// The bucket ID whose stored daily totals to recompute
+    pub bucket_id: String,
+
+    /// Start time (ISO 8601 format); the whole day it falls on is rebuilt
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format); the whole day it falls on is rebuilt
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-03", "2024")
+    #[serde(default)]
+    pub period: Option<String>,
+}
+
+#[tool_router(router = admin_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Reload the server configuration without restarting.
+    #[tool(
+        description = "Reload the MCP server configuration (the TOML config file if one was given, otherwise the environment) without restarting or dropping the session. Settings that are baked into the HTTP client, such as the server URL, still need a restart; they are listed in the result."
+    )]
+    async fn aw_reload_config(&self) -> Result<CallToolResult, McpError> {
+        match self.reload_config() {
+            Ok(restart_required) => {
+                let source = match &self.config().config_file {
+                    Some(path) => path.display().to_string(),
+                    None => "environment".to_string(),
+                };
+                let mut lines = vec![
+                    "# Configuration Reloaded".to_string(),
+                    String::new(),
+                    format!("**Source**: {}", source),
+                ];
+                if !restart_required.is_empty() {
+                    lines.push(format!(
+                        "**Restart required for**: {}",
+                        restart_required.join(", ")
+                    ));
+                }
+
+                Ok(CallToolResult::success(vec![Content::text(
+                    lines.join("\n"),
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to reload configuration: {:#}",
+                e
+            ))])),
+        }
+    }
+
+    /// Report cache statistics.
+    #[tool(
+        description = "Report statistics of the MCP server's caches: the cached bucket list (entries, age, approximate memory, hit rate), the query2 result cache (entries, hit rate) and the offline response cache (entries, disk size, oldest entry, responses served while aw-server was unreachable). Use this to debug stale or surprising answers."
+    )]
+    async fn aw_cache_stats(
+        &self,
+        Parameters(params): Parameters<CacheStatsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let stats = self.client.cache_stats().await;
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let buckets = &stats.buckets;
+                let lookups = buckets.hits + buckets.misses;
+                let mut lines = vec![
+                    "# Cache Statistics".to_string(),
+                    String::new(),
+                    "## Bucket Cache".to_string(),
+                    format!("- **Entries**: {}", buckets.entries),
+                    format!(
+                        "- **Age**: {}",
+                        buckets
+                            .age_secs
+                            .map_or("not cached".to_string(), format_duration)
+                    ),
+                    format!("- **Memory**: ~{} KiB", buckets.approx_bytes.div_ceil(1024)),
+                    format!(
+                        "- **Hit rate**: {} ({} of {} lookups)",
+                        hit_rate(buckets.hits, lookups),
+                        buckets.hits,
+                        lookups
+                    ),
+                    String::new(),
+                    "## Query Cache".to_string(),
+                    format!(
+                        "- **Entries**: {} period results of {} scripts",
+                        stats.queries.entries, stats.queries.scripts
+                    ),
+                    format!(
+                        "- **Hit rate**: {} ({} of {} periods)",
+                        hit_rate(
+                            stats.queries.hits,
+                            stats.queries.hits + stats.queries.misses
+                        ),
+                        stats.queries.hits,
+                        stats.queries.hits + stats.queries.misses
+                    ),
+                    String::new(),
+                    "## Offline Cache".to_string(),
+                ];
+                match &stats.offline {
+                    Some(offline) => {
+                        let fallbacks = offline.hits + offline.misses;
+                        lines.push(format!("- **Directory**: {}", offline.dir.display()));
+                        lines.push(format!("- **Entries**: {}", offline.entries));
+                        lines.push(format!(
+                            "- **Disk size**: {} KiB",
+                            offline.disk_bytes.div_ceil(1024)
+                        ));
+                        if let Some(oldest) = offline.oldest {
+                            lines.push(format!(
+                                "- **Oldest entry**: {} UTC",
+                                oldest.format("%Y-%m-%d %H:%M:%S")
+                            ));
+                        }
+                        lines.push(format!(
+                            "- **Served while unreachable**: {} of {} requests ({})",
+                            offline.hits,
+                            fallbacks,
+                            hit_rate(offline.hits, fallbacks)
+                        ));
+                        if let Some(latest) = offline.tombstones.last() {
+                            lines.push(format!(
+                                "- **Tombstones**: {} (latest: before {} of {})",
+                                offline.tombstones.len(),
+                                latest.before.format("%Y-%m-%d %H:%M:%S UTC"),
+                                latest.bucket_id.as_deref().unwrap_or("all buckets")
+                            ));
+                        }
+                    }
+                    None => lines.push("_Disabled (set AW_MCP_CACHE_DIR to enable)._".to_string()),
+                }
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&stats),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    /// Clear cached data, optionally only for a bucket or time range.
+    #[tool(
+        description = r#"Clear the MCP server's caches. Use this when answers look stale.
+
+## Parameters
+- `bucket_id`: Only clear cached responses of this bucket (and the bucket list); query results are always cleared
+- `start`, `end`: Only clear cached events and query results overlapping this range (ISO 8601); both are required together
+
+Without parameters, the cached bucket list, all cached query results and the whole offline response cache are cleared."#
+    )]
+    async fn aw_cache_clear(
+        &self,
+        Parameters(params): Parameters<CacheClearParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let range = match (params.start.as_deref(), params.end.as_deref()) {
+            (Some(start), Some(end)) => match TimeRange::parse(start, end) {
+                Ok(range) => Some((range.start, range.end)),
+                Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+            },
+            (None, None) => None,
+            _ => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "Both start and end are required to clear a time range",
+                )]));
+            }
+        };
+
+        if let Some(bucket_id) = &params.bucket_id
+            && let Err(e) = self.check_scope(bucket_id).await
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to clear cache: {}",
+                e.message
+            ))]));
+        }
+
+        match self
+            .client
+            .clear_cache(params.bucket_id.as_deref(), range)
+            .await
+        {
+            Ok(cleared) => {
+                let bucket_cache = if cleared.bucket_cache_cleared {
+                    "cleared"
+                } else {
+                    "kept"
+                };
+                Ok(CallToolResult::success(vec![Content::text(format!(
+                    "# Cache Cleared\n\n**Bucket cache**: {}\n**Query results removed**: {}\n**Offline responses removed**: {}",
+                    bucket_cache, cleared.queries_removed, cleared.offline_removed
+                ))]))
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to clear cache: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Purge cached data before a date after it was deleted upstream.
+    #[tool(
+        description = r#"Purge the MCP server's cached data from before a date, after old data was pruned or deleted on aw-server. Cached query results, offline responses and stored daily totals reaching before the date are dropped, and a tombstone is recorded in the offline cache (AW_MCP_CACHE_DIR) so responses stored earlier are never served for the deleted range while aw-server is unreachable.
+
+## Parameters
+- `date`: Data before this was deleted: a date ("2024-01-01", midnight in the configured timezone or the bucket's own) or an ISO 8601 timestamp
+- `bucket_id`: Only purge this bucket's cached data (default: all buckets; required when access is scoped)"#
+    )]
+    async fn aw_purge_cache_before(
+        &self,
+        Parameters(params): Parameters<PurgeCacheBeforeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let bucket_id = params.bucket_id.as_deref().map(str::trim);
+        if bucket_id.is_some_and(str::is_empty) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        match bucket_id {
+            Some(bucket_id) => {
+                if let Err(e) = self.check_scope(bucket_id).await {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Failed to purge cache: {}",
+                        e.message
+                    ))]));
+                }
+            }
+            None if self.config().scope.is_some() => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "bucket_id is required when access is scoped (AW_MCP_SCOPE)",
+                )]));
+            }
+            None => {}
+        }
+
+        let tz = match bucket_id {
+            Some(bucket_id) => self.bucket_timezone(bucket_id).await,
+            None => self.config().timezone.clone(),
+        };
+        let date = params.date.trim();
+        let before = match (parse_timestamp(date), date.parse::<NaiveDate>()) {
+            (Ok(before), _) => before,
+            (_, Ok(date)) => tz.start_of_day(date),
+            _ => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Invalid date '{}', expected e.g. 2024-01-01 or an ISO 8601 timestamp",
+                    date
+                ))]));
+            }
+        };
+
+        let cleared = match self.client.purge_cache_before(bucket_id, before).await {
+            Ok(cleared) => cleared,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to purge cache: {}",
+                    e.message
+                ))]));
+            }
+        };
+        let bucket_ids = match bucket_id {
+            Some(bucket_id) => vec![bucket_id.to_string()],
+            None => match self.lookup_buckets().await {
+                Ok(buckets) => buckets.keys().cloned().collect(),
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Purged cached responses but failed to list buckets: {}",
+                        e.message
+                    ))]));
+                }
+            },
+        };
+        let last = tz.date_of(before - Duration::nanoseconds(1));
+        let mut days_dropped = 0;
+        for bucket_id in &bucket_ids {
+            days_dropped += self
+                .drop_aggregate_days(bucket_id, NaiveDate::MIN, last)
+                .await;
+        }
+
+        let tombstone = match self.config().cache_dir {
+            Some(_) => "recorded",
+            None => "not recorded (no offline cache)",
+        };
+        let lines = [
+            "# Cache Purged".to_string(),
+            String::new(),
+            format!("**Before**: {}", format_timestamp(&before)),
+            format!("**Bucket**: {}", bucket_id.unwrap_or("all buckets")),
+            format!("**Query results removed**: {}", cleared.queries_removed),
+            format!("**Offline responses removed**: {}", cleared.offline_removed),
+            format!("**Stored daily totals dropped**: {} days", days_dropped),
+            format!("**Tombstone**: {}", tombstone),
+        ];
+        Ok(CallToolResult::success(vec![Content::text(
+            lines.join("\n"),
+        )]))
+    }
+
+    /// Recompute the aggregation store for a range.
+    #[tool(
+        description = r#"Recompute the stored daily totals that month and year reports (such as aw_category_summary) are served from, for the days of a range. Use this after importing or deleting events or changing category rules, when reports still show old totals. Edits are usually noticed automatically; this forces it.
+
+Sends progress notifications while rebuilding when the client asks for them.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `start` / `end`: The range in ISO 8601 format; partial days are rebuilt whole
+- `period`: The range as a period instead, e.g. "2024-03" or "2024""#
+    )]
+    async fn aw_rebuild_aggregates(
+        &self,
+        Parameters(params): Parameters<RebuildAggregatesParams>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        let tz = self.bucket_timezone(&params.bucket_id).await;
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        if let Err(e) = self.check_scope(&params.bucket_id).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to rebuild aggregates: {}",
+                e.message
+            ))]));
+        }
+
+        let first = tz.date_of(range.start);
+        let last = tz.date_of(range.end - Duration::nanoseconds(1));
+        let dates: Vec<_> = first.iter_days().take_while(|date| *date <= last).collect();
+        // Totals without category rules still cover apps
+        let categories = self
+            .categories()
+            .await
+            .ok()
+            .map(|(categories, _)| categories);
+        let dropped = self
+            .drop_aggregate_days(&params.bucket_id, first, last)
+            .await;
+
+        let progress_token = meta.get_progress_token();
+        let mut total_secs = 0.0;
+        let mut done = 0;
+        for chunk in dates.chunks(AGGREGATE_FETCH_DAYS) {
+            let (Some(chunk_first), Some(chunk_last)) = (chunk.first(), chunk.last()) else {
+                continue;
+            };
+            let daily = match self
+                .daily_totals(
+                    &params.bucket_id,
+                    *chunk_first,
+                    *chunk_last,
+                    categories.as_ref(),
+                )
+                .await
+            {
+                Ok(daily) => daily,
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Failed to rebuild aggregates after {} of {} days: {}",
+                        done,
+                        dates.len(),
+                        e.message
+                    ))]));
+                }
+            };
+            total_secs = daily
+                .days
+                .iter()
+                .fold(total_secs, |t, (_, day)| t + day.total_secs());
+            done += chunk.len();
+
+            if let Some(progress_token) = &progress_token {
+                let notification = ProgressNotificationParam {
+                    progress_token: progress_token.clone(),
+                    progress: done as f64,
+                    total: Some(dates.len() as f64),
+                    message: Some(format!("Rebuilt {} to {}", chunk_first, chunk_last)),
+                };
+                if let Err(e) = peer.notify_progress(notification).await {
+                    tracing::debug!("Cannot send progress notification: {}", e);
+                }
+            }
+        }
+
+        let settled = self.now() - Duration::seconds(AGGREGATE_SETTLE_SECS);
+        let stored = dates
+            .iter()
+            .filter(|date| tz.start_of_day(**date + Duration::days(1)) <= settled)
+            .count();
+        let mut lines = vec![
+            "# Aggregates Rebuilt".to_string(),
+            String::new(),
+            format!("**Bucket**: {}", params.bucket_id),
+            format!("**Days**: {} to {} ({} days)", first, last, dates.len()),
+            format!("**Previously stored**: {} days", dropped),
+            format!("**Stored now**: {} days", stored),
+            format!("**Total**: {}", format_duration(total_secs)),
+        ];
+        if stored < dates.len() {
+            lines.push(String::new());
+            lines.push(
+                "_Days that are not over yet are computed per report and not stored._".to_string(),
+            );
+        }
+        Ok(CallToolResult::success(vec![Content::text(
+            lines.join("\n"),
+        )]))
+    }
+
+    /// Fetch the log of recent tool calls.
+    #[tool(
+        description = "Fetch the audit log of recent tool calls (time, tool, outcome, duration and correlation ID; arguments are not recorded), newest first."
+    )]
+    async fn aw_audit_log(
+        &self,
+        Parameters(params): Parameters<AuditLogParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let entries = self
+            .audit
+            .recent(params.limit.unwrap_or(DEFAULT_AUDIT_LOG_LIMIT));
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec!["# Audit Log".to_string(), String::new()];
+                if entries.is_empty() {
+                    lines.push("_No tool calls recorded yet._".to_string());
+                }
+                for entry in &entries {
+                    lines.push(format!(
+                        "- {} `{}` {} in {}ms ({})",
+                        entry.time.format("%Y-%m-%d %H:%M:%S"),
+                        entry.tool,
+                        entry.outcome,
+                        entry.elapsed_ms,
+                        entry.correlation_id
+                    ));
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&entries),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    /// Run the self-test against aw-server.
+    #[tool(
+        description = "Run the server's self-test against aw-server: API reachability, bucket listing, and event reads for a sample of buckets in scope. Reports each check as PASS or FAIL."
+    )]
+    async fn aw_self_test(&self) -> Result<CallToolResult, McpError> {
+        let report = run_scoped_self_test(&self.client, self.config().scope.as_ref()).await;
+        let text = report.to_string();
+        Ok(if report.passed() {
+            CallToolResult::success(vec![Content::text(text)])
+        } else {
+            CallToolResult::error(vec![Content::text(text)])
+        })
+    }
+}
+
+/// Format a hit rate as a percentage ("n/a" without lookups)
+fn hit_rate(hits: u64, total: u64) -> String {
+    if total == 0 {
+        "n/a".to_string()
+    } else {
+        format_percent(hits as f64 * 100.0 / total as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::tools::{result_text, scoped_test_server, test_server};
+
+    #[tokio::test]
+    async fn reloads_the_config_file() {
+        let path = std::env::temp_dir().join(format!("aw-mcp-admin-{}.toml", std::process::id()));
+        std::fs::write(&path, "bucket_cache_ttl = 60\n").unwrap();
+        let (_fake, server) = test_server(Vec::new()).await;
+        let server = server.with_config(Config::from_file(&path).unwrap());
+
+        std::fs::write(&path, "bucket_cache_ttl = 120\npercent_decimals = 2\n").unwrap();
+        let result = server.aw_reload_config().await.unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let text = result_text(&result);
+        assert_ne!(result.is_error, Some(true), "{}", text);
+        assert!(text.contains(&format!("**Source**: {}", path.display())));
+        assert!(text.contains("**Restart required for**: AW_MCP_BUCKET_CACHE_TTL"));
+        assert_eq!(server.config().rounding.percent_decimals, 2);
+    }
+
+    #[tokio::test]
+    async fn clears_only_buckets_in_scope() {
+        let (_fake, server) = scoped_test_server(Vec::new(), Vec::new()).await;
+        let clear = |params: serde_json::Value| {
+            server.aw_cache_clear(Parameters(serde_json::from_value(params).unwrap()))
+        };
+
+        let result = clear(serde_json::json!({"bucket_id": "aw-watcher-window_other"}))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        let result = clear(serde_json::json!({"start": "2025-01-01T00:00:00Z"}))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+
+        let result = clear(serde_json::json!({"bucket_id": "aw-watcher-window_test"}))
+            .await
+            .unwrap();
+        assert_ne!(result.is_error, Some(true), "{}", result_text(&result));
+        assert!(result_text(&result).contains("# Cache Cleared"));
+    }
+}
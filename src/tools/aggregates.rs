@@ -0,0 +1,283 @@
+use super::ActivityWatchMcpServer;
+use crate::aggregates::{BucketAggregates, DayTotals};
+use crate::analysis::{TimeRange, apply_zero_duration_policy, format_timestamp};
+use crate::categories::Categories;
+use crate::constants::{AGGREGATE_FETCH_DAYS, AGGREGATE_SETTLE_SECS};
+use crate::models::ZeroDurationPolicy;
+use crate::privacy::{including_private, private_included};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Duration, Months, NaiveDate, Utc};
+use rmcp::ErrorData as McpError;
+
+/// Daily totals of a range of days
+#[derive(Debug, Clone)]
+pub(super) struct DailyTotals {
+    /// Totals per day, in order
+    pub days: Vec<(NaiveDate, DayTotals)>,
+
+    /// Days that were already in the aggregation store
+    pub stored: usize,
+}
+
+impl ActivityWatchMcpServer {
+    /// The first and last day of a range made of whole days in a timezone
+    pub(super) fn whole_days(range: &TimeRange, tz: &Timezone) -> Option<(NaiveDate, NaiveDate)> {
+        let first = tz.date_of(range.start);
+        let last = tz.date_of(range.end - Duration::nanoseconds(1));
+        let aligned = tz.start_of_day(first) == range.start
+            && tz.start_of_day(last + Duration::days(1)) == range.end;
+        aligned.then_some((first, last))
+    }
+
+    /// Totals per day of a bucket for the days `first..=last` in the
+    /// bucket's timezone (see [`Self::bucket_timezone`]).
+    ///
+    /// Completed days come from the aggregation store when present and are
+    /// added to it otherwise; only missing days are fetched from aw-server.
+    /// Before the store is used, it is checked for edited history (see
+    /// [`BucketAggregates`]). Totals including do-not-track time are
+    /// computed without the store.
+    pub(super) async fn daily_totals(
+        &self,
+        bucket_id: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+        categories: Option<&Categories>,
+    ) -> Result<DailyTotals, McpError> {
+        let tz = self.bucket_timezone(bucket_id).await;
+        let timezone = tz.to_string();
+        let mut rules = categories.map(Categories::to_toml).unwrap_or_default();
+        let do_not_track: Vec<String> = self
+            .config()
+            .do_not_track
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if !do_not_track.is_empty() {
+            rules.push_str(&format!("\n# do not track: {}", do_not_track.join("; ")));
+        }
+        let zero_duration = self.config().zero_duration_policy;
+        if zero_duration != ZeroDurationPolicy::Keep {
+            rules.push_str(&format!("\n# zero duration: {}", zero_duration));
+        }
+        let private = private_included();
+        let now = self.now();
+        let settled = now - Duration::seconds(AGGREGATE_SETTLE_SECS);
+        let day_range = |date: NaiveDate| {
+            let start = tz.start_of_day(date);
+            (start, tz.start_of_day(date + Duration::days(1)))
+        };
+
+        let mut buckets = self.aggregates.lock().await;
+        let stored_aggregates = match private {
+            true => None,
+            false => match buckets.remove(bucket_id) {
+                Some(aggregates) => Some(aggregates),
+                None => self.aggregates.load(bucket_id).await,
+            },
+        };
+        let mut aggregates = stored_aggregates
+            .filter(|aggregates| aggregates.matches(&timezone, &rules))
+            .unwrap_or_else(|| BucketAggregates::new(bucket_id, &timezone, &rules));
+
+        // Event counts only detect edits, so they include do-not-track time
+        let mut changed = including_private(true, self.check_history(&mut aggregates, &tz)).await?;
+        let mut days = Vec::new();
+        let mut stored = 0;
+        let mut missing: Vec<NaiveDate> = Vec::new();
+        let dates: Vec<NaiveDate> = first.iter_days().take_while(|date| *date <= last).collect();
+        for date in dates {
+            if let Some(totals) = aggregates.days.get(&date) {
+                days.push((date, totals.clone()));
+                stored += 1;
+            } else if day_range(date).0 < now {
+                missing.push(date);
+                days.push((date, DayTotals::default()));
+            } else {
+                days.push((date, DayTotals::default()));
+            }
+        }
+
+        // Fetch runs of consecutive missing days together
+        let mut runs: Vec<Vec<NaiveDate>> = Vec::new();
+        for date in missing {
+            match runs.last_mut() {
+                Some(run)
+                    if run.len() < AGGREGATE_FETCH_DAYS
+                        && run
+                            .last()
+                            .is_some_and(|last| *last + Duration::days(1) == date) =>
+                {
+                    run.push(date)
+                }
+                _ => runs.push(vec![date]),
+            }
+        }
+        for run in runs {
+            let (Some(run_first), Some(run_last)) = (run.first(), run.last()) else {
+                continue;
+            };
+            let start = day_range(*run_first).0;
+            let end = day_range(*run_last).1.min(now);
+            let events = self
+                .client
+                .get_events(
+                    bucket_id,
+                    None,
+                    Some(&format_timestamp(&start)),
+                    Some(&format_timestamp(&end)),
+                )
+                .await?;
+            let events = apply_zero_duration_policy(events, zero_duration);
+            for date in run {
+                let (start, end) = day_range(date);
+                let Ok(range) = TimeRange::new(start, end.min(now)) else {
+                    continue;
+                };
+                let totals = DayTotals::from_events(&events, categories, &range);
+                if end <= settled {
+                    aggregates.days.insert(date, totals.clone());
+                    changed = true;
+                }
+                if let Some(day) = days.iter_mut().find(|(day, _)| *day == date) {
+                    day.1 = totals;
+                }
+            }
+        }
+
+        if private {
+            return Ok(DailyTotals { days, stored });
+        }
+        if changed {
+            including_private(true, self.record_history(&mut aggregates, &tz)).await?;
+            self.aggregates.save(&aggregates).await;
+        }
+        buckets.insert(bucket_id.to_string(), aggregates);
+        Ok(DailyTotals { days, stored })
+    }
+
+    /// Drop the stored days `first..=last` of a bucket so the next
+    /// [`Self::daily_totals`] recomputes them, returning how many were stored
+    pub(super) async fn drop_aggregate_days(
+        &self,
+        bucket_id: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> usize {
+        let mut buckets = self.aggregates.lock().await;
+        let aggregates = match buckets.remove(bucket_id) {
+            Some(aggregates) => Some(aggregates),
+            None => self.aggregates.load(bucket_id).await,
+        };
+        let Some(mut aggregates) = aggregates else {
+            return 0;
+        };
+        let dropped = aggregates.drop_days(first, last);
+        if dropped > 0 {
+            self.aggregates.save(&aggregates).await;
+        }
+        buckets.insert(bucket_id.to_string(), aggregates);
+        dropped
+    }
+
+    /// Drop stored days whose history changed since it was last recorded,
+    /// returning whether anything was dropped
+    async fn check_history(
+        &self,
+        aggregates: &mut BucketAggregates,
+        tz: &Timezone,
+    ) -> Result<bool, McpError> {
+        let Some(checked_until) = aggregates.checked_until else {
+            return Ok(false);
+        };
+        let count = self
+            .client
+            .get_event_count(
+                &aggregates.bucket_id,
+                None,
+                Some(&format_timestamp(&checked_until)),
+            )
+            .await?;
+        if count == aggregates.history_count {
+            return Ok(false);
+        }
+
+        for month in aggregates.months() {
+            let unchanged = match aggregates.month_counts.get(&month) {
+                Some(stored) => {
+                    self.month_count(&aggregates.bucket_id, &month, tz).await? == *stored
+                }
+                None => false,
+            };
+            if !unchanged {
+                let dropped = aggregates.drop_month(&month);
+                tracing::info!(
+                    "History of {} changed in {}; recomputing {} days",
+                    aggregates.bucket_id,
+                    month,
+                    dropped
+                );
+            }
+        }
+        Ok(true)
+    }
+
+    /// Remember the event counts the stored days were computed from
+    async fn record_history(
+        &self,
+        aggregates: &mut BucketAggregates,
+        tz: &Timezone,
+    ) -> Result<(), McpError> {
+        let checked_until = aggregates
+            .days
+            .keys()
+            .next_back()
+            .map(|last| tz.start_of_day(*last + Duration::days(1)));
+        aggregates.checked_until = checked_until;
+        aggregates.history_count = match checked_until {
+            Some(until) => {
+                self.client
+                    .get_event_count(&aggregates.bucket_id, None, Some(&format_timestamp(&until)))
+                    .await?
+            }
+            None => 0,
+        };
+
+        for month in aggregates.months() {
+            let complete = month_range(&month, tz)
+                .zip(checked_until)
+                .is_some_and(|((_, end), until)| end <= until);
+            if complete && !aggregates.month_counts.contains_key(&month) {
+                let count = self.month_count(&aggregates.bucket_id, &month, tz).await?;
+                aggregates.month_counts.insert(month, count);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of events of a bucket in a month (`YYYY-MM`) in a timezone
+    async fn month_count(
+        &self,
+        bucket_id: &str,
+        month: &str,
+        tz: &Timezone,
+    ) -> Result<i64, McpError> {
+        let Some((start, end)) = month_range(month, tz) else {
+            return Ok(0);
+        };
+        self.client
+            .get_event_count(
+                bucket_id,
+                Some(&format_timestamp(&start)),
+                Some(&format_timestamp(&end)),
+            )
+            .await
+    }
+}
+
+/// Start and end of a month (`YYYY-MM`) in a timezone
+fn month_range(month: &str, tz: &Timezone) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()?;
+    let next = first.checked_add_months(Months::new(1))?;
+    Some((tz.start_of_day(first), tz.start_of_day(next)))
+}
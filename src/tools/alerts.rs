@@ -0,0 +1,153 @@
+use super::ActivityWatchMcpServer;
+use crate::analysis::{
+    AFK_BUCKET_TYPE, Alert, AlertRule, TimeRange, WINDOW_BUCKET_TYPE, active_intervals,
+    apply_zero_duration_policy, format_timestamp, in_category, window_gap,
+};
+use crate::categories::category_totals;
+use crate::constants::{ALERT_PRESENCE_SLACK_SECS, DEFAULT_CATEGORY_DEPTH};
+use chrono::Duration;
+use rmcp::ErrorData as McpError;
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+
+/// Logger name of alerts sent to the MCP client
+const ALERT_LOGGER: &str = "aw-mcp-alerts";
+
+impl ActivityWatchMcpServer {
+    /// Check the configured alert rules against the current day across all
+    /// window and AFK buckets in scope, returning the rules that fire
+    pub async fn check_alerts(&self) -> Result<Vec<Alert>, McpError> {
+        let config = self.config();
+        if config.alerts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let now = self.now();
+        let date = config.timezone.date_of(now);
+        let day_start = config.timezone.start_of_day(date);
+        let longest_gap = config
+            .alerts
+            .iter()
+            .filter_map(|rule| match rule {
+                AlertRule::NoWindowEvents { secs } => Some(*secs),
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        let since = day_start.min(now - Duration::seconds(longest_gap as i64));
+        let start = format_timestamp(&since);
+        let end = format_timestamp(&now);
+
+        let buckets = self.lookup_buckets().await?;
+        let mut window_events = Vec::new();
+        let mut afk_events = Vec::new();
+        for bucket in buckets.values().filter(|bucket| self.in_scope(bucket)) {
+            let events = match bucket.bucket_type.as_deref() {
+                Some(WINDOW_BUCKET_TYPE) => &mut window_events,
+                Some(AFK_BUCKET_TYPE) => &mut afk_events,
+                _ => continue,
+            };
+            let fetched = self
+                .client
+                .get_events(&bucket.id, None, Some(&start), Some(&end))
+                .await?;
+            events.extend(apply_zero_duration_policy(
+                fetched,
+                config.zero_duration_policy,
+            ));
+        }
+        let active = active_intervals(&afk_events);
+        let by_category = |rule: &AlertRule| matches!(rule, AlertRule::CategoryOver { .. });
+        let categories = match config.alerts.iter().any(by_category) {
+            true => self
+                .categories()
+                .await
+                .ok()
+                .map(|(categories, _)| categories),
+            false => None,
+        };
+
+        let mut alerts = Vec::new();
+        for rule in &config.alerts {
+            let observed_secs = match rule {
+                AlertRule::NoWindowEvents { secs } => {
+                    let since = now - Duration::seconds(*secs as i64);
+                    let slack = Duration::seconds(ALERT_PRESENCE_SLACK_SECS);
+                    match window_gap(&window_events, &active, since, now, slack) {
+                        Some(gap) => gap,
+                        None => continue,
+                    }
+                }
+                AlertRule::CategoryOver {
+                    category, before, ..
+                } => {
+                    let Some(categories) = &categories else {
+                        continue;
+                    };
+                    let end = match before {
+                        Some(before) => config.timezone.from_local(date.and_time(*before)).min(now),
+                        None => now,
+                    };
+                    let Ok(range) = TimeRange::new(day_start, end) else {
+                        continue;
+                    };
+                    let depth = DEFAULT_CATEGORY_DEPTH;
+                    category_totals(&window_events, categories, depth, Some(&range))
+                        .leaves
+                        .iter()
+                        .filter(|(path, _)| in_category(path, category))
+                        .map(|(_, secs)| secs)
+                        .sum()
+                }
+            };
+            alerts.extend(rule.check(observed_secs, now));
+        }
+        Ok(alerts)
+    }
+
+    /// Deliver an alert to the connected MCP client as a log message (unless
+    /// it asked for more severe messages only) and to the configured
+    /// webhook, filtered like tool output
+    pub async fn deliver_alert(&self, alert: &Alert) {
+        tracing::warn!(rule = %alert.rule, "Alert: {}", alert.message);
+        let pseudonymizer = self.pseudonymizer().await;
+        let alert = Alert {
+            rule: self.filter_text(&alert.rule, pseudonymizer.as_ref()),
+            message: self.filter_text(&alert.message, pseudonymizer.as_ref()),
+            ..alert.clone()
+        };
+        let data = serde_json::to_value(&alert).unwrap_or_default();
+
+        let level = LoggingLevel::Warning;
+        let wanted = self
+            .log_level
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none_or(|lowest| level as u8 >= lowest as u8);
+        let peer = self.peer.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(peer) = peer.filter(|_| wanted) {
+            let param = LoggingMessageNotificationParam {
+                level,
+                logger: Some(ALERT_LOGGER.to_string()),
+                data: data.clone(),
+            };
+            if let Err(e) = peer.notify_logging_message(param).await {
+                tracing::debug!(error = %e, "Cannot send alert to the MCP client");
+            }
+        }
+
+        let config = self.config();
+        if let Some(url) = &config.alert_webhook {
+            let result = reqwest::Client::builder()
+                .timeout(config.http.timeout)
+                .build()
+                .map(|client| client.post(url.clone()).json(&data).send());
+            let result = match result {
+                Ok(request) => request
+                    .await
+                    .and_then(|response| response.error_for_status()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::warn!(error = %e, "Failed to deliver alert to the webhook");
+            }
+        }
+    }
+}
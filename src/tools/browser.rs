@@ -0,0 +1,763 @@
+use super::{
+    ActivityWatchMcpServer, fit_response, json_response, json_text, token_budget, truncate_response,
+};
+use crate::analysis::{
+    BrowserTotals, Interval, TabWeights, TimeRange, TokenBudget, WEB_BUCKET_TYPE,
+    WINDOW_BUCKET_TYPE, browser_focus, browser_of, browser_totals, format_timestamp, review_weeks,
+};
+use crate::format::{format_duration, format_percent};
+use crate::models::{Bucket, Event, ResponseFormat};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default grouping of browsing time
+const DEFAULT_BROWSER_GROUP_BY: &str = "domain";
+
+/// Groupings of browsing time
+const BROWSER_GROUPS: [&str; 3] = ["domain", "url", "title"];
+
+/// Default number of groups listed
+const DEFAULT_BROWSER_TOP: usize = 15;
+
+/// Approximate characters of a group row, when fitting rows to a token
+/// budget
+const GROUP_ROW_CHARS: usize = 70;
+
+/// Approximate characters of a summary's heading and totals
+const SUMMARY_HEADER_CHARS: usize = 600;
+
+/// Input for a browsing summary
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BrowserSummaryParams {
+    /// The browser tab bucket to summarize (e.g., "aw-watcher-web-firefox"); all browser tab buckets when omitted
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "yesterday", "2024-W07")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Group time by "domain" (default), "url" or "title"
+    #[serde(default)]
+    pub group_by: Option<String>,
+
+    /// Maximum groups listed (default: 15)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Also list the top groups of each browser separately
+    #[serde(default)]
+    pub by_browser: bool,
+
+    /// Tab weighting instead of the server setting: "raw", "focused" or weights such as "focused=1,audible=0.5,background=0"
+    #[serde(default)]
+    pub tab_weights: Option<String>,
+
+    /// Approximate size the response should fit in, in tokens (e.g., 1000); picks the number of groups unless `top` is given
+    #[serde(default)]
+    pub max_tokens_hint: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for review load per pull request
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReviewLoadParams {
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-03", "2024-W07")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// The browser tab bucket to use (e.g., "aw-watcher-web-firefox"); all browser tab buckets when omitted
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// Maximum pull requests listed (default: 15)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Tab weighting instead of the server setting: "raw", "focused" or weights such as "focused=1,audible=0.5,background=0"
+    #[serde(default)]
+    pub tab_weights: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Weighted browsing time of one browser tab bucket
+#[derive(Debug, Serialize)]
+struct BrowserBucket {
+    bucket_id: String,
+    browser: Option<&'static str>,
+    hostname: Option<String>,
+    /// Window bucket browser focus was taken from
+    focus_bucket_id: Option<String>,
+    #[serde(flatten)]
+    totals: BrowserTotals,
+}
+
+/// Tab events of one browser tab bucket with the focus of its browser
+pub(super) struct TabSource {
+    pub bucket_id: String,
+    pub browser: Option<&'static str>,
+    pub hostname: Option<String>,
+    /// Window bucket browser focus was taken from
+    pub focus_bucket_id: Option<String>,
+    pub events: Vec<Event>,
+    /// Intervals the browser was in front; None without a window watcher
+    pub focus: Option<Vec<Interval>>,
+}
+
+#[tool_router(router = browser_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Summarize browsing time of one or all browser tab buckets.
+    #[tool(
+        description = r#"Summarize time spent in the browser per domain, URL or title from aw-watcher-web buckets, weighted by whether the browser was actually in front. Without `bucket_id`, the tab buckets of all browsers (Firefox, Chrome, Edge, ...) on all hosts are merged into one summary with a line per browser.
+
+aw-watcher-web keeps reporting the active tab while another app is in front, so raw tab events overstate browsing time. Tab time counts as focused while the window watcher on the same host shows that browser's window; the rest is background time, split into audible tabs (playing audio) and others. Each kind is weighted as configured on the server (by default focused 1, audible 0.5, background 0). Without a window watcher on the host, all tab time counts as focused.
+
+## Parameters
+- `bucket_id`: A browser tab bucket (e.g., "aw-watcher-web-firefox"); all browser tab buckets when omitted
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "yesterday" or "2024-W07"
+- `group_by`: "domain" (default), "url" or "title"
+- `top`: Maximum groups listed (default: 15)
+- `by_browser`: Also list the top groups of each browser
+- `tab_weights`: "raw" (every tab event in full), "focused" (focused time only) or weights between 0 and 1 such as "focused=1,audible=0.5,background=0"
+- `max_tokens_hint`: Approximate token budget of the response (e.g., 1000). Without `top`, as many groups are listed as fit, and JSON is compact"#
+    )]
+    async fn aw_browser_summary(
+        &self,
+        Parameters(params): Parameters<BrowserSummaryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params
+            .bucket_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if let Some(group_by) = params.group_by.as_deref()
+            && !BROWSER_GROUPS.contains(&group_by)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Unknown group_by '{}'. Expected domain, url or title",
+                group_by
+            ))]));
+        }
+        let weights = match self.tab_weights(params.tab_weights.as_deref()) {
+            Ok(weights) => weights,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let budget = match token_budget(params.max_tokens_hint) {
+            Ok(budget) => budget,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self
+            .browser_summary(&params, &range, &weights, budget)
+            .await
+        {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to summarize browsing: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Total browser time on pull requests per week.
+    #[tool(
+        description = r#"Quantify code review load: browser time on GitHub pull requests and GitLab merge requests, per pull request and per week (weeks start Monday in the configured timezone), from aw-watcher-web buckets.
+
+Tab URLs are recognized by the forges' layouts, `/owner/repo/pull/12` (GitHub and GitHub Enterprise) and `/group/project/-/merge_requests/12` (GitLab, any host), including their files, commits and diff pages. Tab time is weighted by browser focus as in `aw_browser_summary`.
+
+## Parameters
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-03" or "2024-W07"
+- `bucket_id`: A browser tab bucket; all browser tab buckets when omitted
+- `top`: Maximum pull requests listed (default: 15)
+- `tab_weights`: "raw", "focused" or weights between 0 and 1 such as "focused=1,audible=0.5,background=0""#
+    )]
+    async fn aw_review_load(
+        &self,
+        Parameters(params): Parameters<ReviewLoadParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params
+            .bucket_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        let weights = match self.tab_weights(params.tab_weights.as_deref()) {
+            Ok(weights) => weights,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.review_load(&params, &range, &weights).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to total review time: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Tab weighting given per call, or the server setting
+    fn tab_weights(&self, spec: Option<&str>) -> Result<TabWeights, String> {
+        match spec {
+            Some(spec) => spec
+                .parse()
+                .map_err(|e| format!("Invalid tab_weights: {}", e)),
+            None => Ok(self.config().tab_weights),
+        }
+    }
+
+    /// Fetch the tab events of one browser tab bucket, or of all in scope,
+    /// with the browser focus from the window watcher on each bucket's host
+    pub(super) async fn tab_sources(
+        &self,
+        bucket_id: Option<&str>,
+        range: &TimeRange,
+    ) -> Result<Vec<TabSource>, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let mut tab_buckets: Vec<&Bucket> = match bucket_id {
+            Some(bucket_id) => {
+                self.check_scope(bucket_id).await?;
+                buckets.get(bucket_id).into_iter().collect()
+            }
+            None => buckets
+                .values()
+                .filter(|b| self.in_scope(b) && b.bucket_type.as_deref() == Some(WEB_BUCKET_TYPE))
+                .collect(),
+        };
+        if tab_buckets.is_empty() {
+            return Err(McpError::invalid_params(
+                match bucket_id {
+                    Some(bucket_id) => format!("Bucket {} not found", bucket_id),
+                    None => {
+                        "No browser tab buckets found (is aw-watcher-web installed?)".to_string()
+                    }
+                },
+                None,
+            ));
+        }
+        tab_buckets.sort_by(|a, b| a.id.cmp(&b.id));
+
+        // Window events per window bucket, fetched once for all browsers of its host
+        let mut windows: HashMap<String, Vec<Event>> = HashMap::new();
+        let mut sources = Vec::with_capacity(tab_buckets.len());
+        for bucket in tab_buckets {
+            let hostname = bucket.hostname.clone();
+            let window_bucket = buckets.values().find(|b| {
+                self.in_scope(b)
+                    && b.bucket_type.as_deref() == Some(WINDOW_BUCKET_TYPE)
+                    && hostname.is_some()
+                    && b.hostname == hostname
+            });
+            if let Some(window_bucket) = window_bucket
+                && !windows.contains_key(&window_bucket.id)
+            {
+                let events = self.range_events(&window_bucket.id, range).await?;
+                windows.insert(window_bucket.id.clone(), events);
+            }
+
+            let events = self.range_events(&bucket.id, range).await?;
+            let browser = browser_of(&bucket.id);
+            let focus = window_bucket
+                .and_then(|window_bucket| windows.get(&window_bucket.id))
+                .map(|events| browser_focus(events, browser));
+            sources.push(TabSource {
+                bucket_id: bucket.id.clone(),
+                browser,
+                hostname,
+                focus_bucket_id: window_bucket.map(|window_bucket| window_bucket.id.clone()),
+                events,
+                focus,
+            });
+        }
+        Ok(sources)
+    }
+
+    /// Fetch the tab events of the requested (or all) browser buckets and
+    /// the window events of their hosts, and total the weighted browsing
+    /// time
+    async fn browser_summary(
+        &self,
+        params: &BrowserSummaryParams,
+        range: &TimeRange,
+        weights: &TabWeights,
+        budget: Option<TokenBudget>,
+    ) -> Result<String, McpError> {
+        let group_by = params
+            .group_by
+            .as_deref()
+            .unwrap_or(DEFAULT_BROWSER_GROUP_BY);
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+
+        let browsers: Vec<BrowserBucket> = self
+            .tab_sources(params.bucket_id.as_deref(), range)
+            .await?
+            .into_iter()
+            .map(|source| BrowserBucket {
+                totals: browser_totals(
+                    &source.events,
+                    source.focus.as_deref(),
+                    weights,
+                    group_by,
+                    range,
+                ),
+                bucket_id: source.bucket_id,
+                browser: source.browser,
+                hostname: source.hostname,
+                focus_bucket_id: source.focus_bucket_id,
+            })
+            .collect();
+        let mut totals = BrowserTotals::default();
+        browsers
+            .iter()
+            .for_each(|browser| totals.add(&browser.totals));
+        let top = params.top.unwrap_or(match budget {
+            // The overall table, and one per browser with `by_browser`
+            Some(budget) => {
+                let tables = match params.by_browser && browsers.len() > 1 {
+                    true => 1 + browsers.len(),
+                    false => 1,
+                };
+                let header_chars = SUMMARY_HEADER_CHARS + GROUP_ROW_CHARS * browsers.len();
+                budget.rows(GROUP_ROW_CHARS, header_chars).div_ceil(tables)
+            }
+            None => DEFAULT_BROWSER_TOP,
+        });
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let share = |secs: f64| match totals.weighted_secs > 0.0 {
+                    true => format_percent(secs / totals.weighted_secs * 100.0),
+                    false => "-".to_string(),
+                };
+                let group_table = |groups: &[(String, f64)]| {
+                    let mut lines = vec![
+                        format!("| {} | Time | Share |", group_by),
+                        "|---|---|---|".to_string(),
+                    ];
+                    lines.extend(groups.iter().take(top).map(|(label, secs)| {
+                        format!(
+                            "| {} | {} | {} |",
+                            self.markdown_text(label),
+                            format_duration(*secs),
+                            share(*secs)
+                        )
+                    }));
+                    if groups.len() > top {
+                        lines.push(String::new());
+                        lines.push(format!("_{} more not listed._", groups.len() - top));
+                    }
+                    lines
+                };
+
+                let mut lines = vec![
+                    match &params.bucket_id {
+                        Some(bucket_id) => format!("# Browsing in {}", bucket_id),
+                        None => "# Browsing in All Browsers".to_string(),
+                    },
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!(
+                        "- **Browsing time**: {} (weighted; {} as recorded)",
+                        format_duration(totals.weighted_secs),
+                        format_duration(totals.raw_secs)
+                    ),
+                    format!(
+                        "- **Focused**: {}, **audible background**: {}, **other background**: {}",
+                        format_duration(totals.focused_secs),
+                        format_duration(totals.audible_secs),
+                        format_duration(totals.background_secs)
+                    ),
+                    format!("- **Weights**: {}", weights),
+                ];
+                if browsers
+                    .iter()
+                    .any(|browser| browser.focus_bucket_id.is_none())
+                {
+                    lines.push(
+                        "- **Note**: no window watcher on some hosts; their tab time is \
+                         counted as focused"
+                            .to_string(),
+                    );
+                }
+                lines.extend([
+                    String::new(),
+                    "## Browsers".to_string(),
+                    String::new(),
+                    "| Bucket | Browser | Host | Time | Recorded | Focus from |".to_string(),
+                    "|---|---|---|---|---|---|".to_string(),
+                ]);
+                lines.extend(browsers.iter().map(|browser| {
+                    format!(
+                        "| {} | {} | {} | {} | {} | {} |",
+                        browser.bucket_id,
+                        browser.browser.unwrap_or("-"),
+                        self.markdown_text(browser.hostname.as_deref().unwrap_or("-")),
+                        format_duration(browser.totals.weighted_secs),
+                        format_duration(browser.totals.raw_secs),
+                        browser.focus_bucket_id.as_deref().unwrap_or("-")
+                    )
+                }));
+                lines.extend([String::new(), format!("## Per {}", group_by), String::new()]);
+                lines.extend(group_table(&totals.groups));
+                if params.by_browser && browsers.len() > 1 {
+                    for browser in &browsers {
+                        lines.extend([
+                            String::new(),
+                            format!("## {}", browser.bucket_id),
+                            String::new(),
+                        ]);
+                        lines.extend(group_table(&browser.totals.groups));
+                    }
+                }
+
+                fit_response(lines.join("\n"), budget)
+            }
+            ResponseFormat::Json => {
+                let groups = |groups: &[(String, f64)]| {
+                    groups
+                        .iter()
+                        .take(top)
+                        .map(|(label, secs)| serde_json::json!({"name": label, "secs": secs}))
+                        .collect::<Vec<_>>()
+                };
+                json_response(
+                    &serde_json::json!({
+                        "range": range,
+                        "group_by": group_by,
+                        "weights": weights,
+                        "raw_secs": totals.raw_secs,
+                        "weighted_secs": totals.weighted_secs,
+                        "focused_secs": totals.focused_secs,
+                        "audible_secs": totals.audible_secs,
+                        "background_secs": totals.background_secs,
+                        "groups": groups(&totals.groups),
+                        "browsers": browsers
+                            .iter()
+                            .map(|browser| {
+                                let mut value = serde_json::to_value(browser).unwrap_or_default();
+                                value["groups"] = match params.by_browser {
+                                    true => groups(&browser.totals.groups).into(),
+                                    false => serde_json::Value::Null,
+                                };
+                                value
+                            })
+                            .collect::<Vec<_>>(),
+                    }),
+                    budget,
+                )
+            }
+        })
+    }
+
+    /// Total the weighted tab time on pull requests per week
+    async fn review_load(
+        &self,
+        params: &ReviewLoadParams,
+        range: &TimeRange,
+        weights: &TabWeights,
+    ) -> Result<String, McpError> {
+        let top = params.top.unwrap_or(DEFAULT_BROWSER_TOP);
+        let sources = self.tab_sources(params.bucket_id.as_deref(), range).await?;
+        let tz = &self.config().timezone;
+        let weeks = review_weeks(
+            &sources
+                .iter()
+                .map(|source| (source.events.as_slice(), source.focus.as_deref()))
+                .collect::<Vec<_>>(),
+            weights,
+            range,
+            tz,
+        );
+        let mut totals = BrowserTotals::default();
+        weeks.iter().for_each(|week| totals.add(&week.totals));
+        let bucket_ids: Vec<&str> = sources
+            .iter()
+            .map(|source| source.bucket_id.as_str())
+            .collect();
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    "# Review Load".to_string(),
+                    String::new(),
+                    format!(
+                        "- **Range**: {} to {}",
+                        format_timestamp(&range.start),
+                        format_timestamp(&range.end)
+                    ),
+                    format!("- **Buckets**: {}", bucket_ids.join(", ")),
+                    format!(
+                        "- **Review time**: {} (weighted; {} as recorded)",
+                        format_duration(totals.weighted_secs),
+                        format_duration(totals.raw_secs)
+                    ),
+                    format!("- **Pull requests**: {}", totals.groups.len()),
+                    format!("- **Weights**: {}", weights),
+                ];
+                if sources.iter().any(|source| source.focus.is_none()) {
+                    lines.push(
+                        "- **Note**: no window watcher on some hosts; their tab time is \
+                         counted as focused"
+                            .to_string(),
+                    );
+                }
+                if totals.groups.is_empty() {
+                    lines.push(String::new());
+                    lines.push(
+                        match totals.raw_secs > 0.0 {
+                            true => "_Pull request pages were only open in the background._",
+                            false => "_No pull request pages visited in this range._",
+                        }
+                        .to_string(),
+                    );
+                    return Ok(truncate_response(lines.join("\n")));
+                }
+
+                lines.extend([
+                    String::new(),
+                    "## Per Week".to_string(),
+                    String::new(),
+                    "| Week | Review time | Pull requests | Most time on |".to_string(),
+                    "|---|---|---|---|".to_string(),
+                ]);
+                lines.extend(weeks.iter().map(|week| {
+                    format!(
+                        "| {} | {} | {} | {} |",
+                        week.label,
+                        format_duration(week.totals.weighted_secs),
+                        week.totals.groups.len(),
+                        week.totals
+                            .groups
+                            .first()
+                            .map_or("-".to_string(), |(pr, _)| self.markdown_text(pr))
+                    )
+                }));
+
+                lines.extend([
+                    String::new(),
+                    "## Per Pull Request".to_string(),
+                    String::new(),
+                    "| Pull request | Time | Weeks |".to_string(),
+                    "|---|---|---|".to_string(),
+                ]);
+                lines.extend(totals.groups.iter().take(top).map(|(pr, secs)| {
+                    let active_weeks = weeks
+                        .iter()
+                        .filter(|week| week.totals.groups.iter().any(|(label, _)| label == pr))
+                        .count();
+                    format!(
+                        "| {} | {} | {} |",
+                        self.markdown_text(pr),
+                        format_duration(*secs),
+                        active_weeks
+                    )
+                }));
+                if totals.groups.len() > top {
+                    lines.push(String::new());
+                    lines.push(format!(
+                        "_{} more pull requests not listed._",
+                        totals.groups.len() - top
+                    ));
+                }
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => {
+                let groups = |groups: &[(String, f64)]| {
+                    groups
+                        .iter()
+                        .take(top)
+                        .map(|(pr, secs)| serde_json::json!({"pull_request": pr, "secs": secs}))
+                        .collect::<Vec<_>>()
+                };
+                json_text(&serde_json::json!({
+                    "range": range,
+                    "bucket_ids": bucket_ids,
+                    "weights": weights,
+                    "raw_secs": totals.raw_secs,
+                    "weighted_secs": totals.weighted_secs,
+                    "pull_requests": groups(&totals.groups),
+                    "pull_request_count": totals.groups.len(),
+                    "weeks": weeks
+                        .iter()
+                        .map(|week| {
+                            serde_json::json!({
+                                "start": week.start,
+                                "end": week.end,
+                                "label": week.label,
+                                "weighted_secs": week.totals.weighted_secs,
+                                "raw_secs": week.totals.raw_secs,
+                                "pull_requests": groups(&week.totals.groups),
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                }))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::WEB_BUCKET_TYPE;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    #[tokio::test]
+    async fn weights_tab_time_by_browser_focus() {
+        let at = |minute| Utc.with_ymd_and_hms(2024, 3, 1, 9, minute, 0).unwrap();
+        let (fake, server) = crate::tools::test_server(vec![
+            test_event(
+                at(0),
+                120.0,
+                serde_json::json!({"app": "Firefox", "title": "PR"}),
+            ),
+            test_event(
+                at(2),
+                60.0,
+                serde_json::json!({"app": "Code", "title": "main.rs"}),
+            ),
+        ])
+        .await;
+        fake.add_simple_bucket("aw-watcher-web-firefox_test", WEB_BUCKET_TYPE, "test");
+        fake.add_events(
+            "aw-watcher-web-firefox_test",
+            vec![
+                test_event(
+                    at(0),
+                    120.0,
+                    serde_json::json!({"url": "https://github.com/a/b/pull/1", "title": "PR", "audible": false}),
+                ),
+                test_event(
+                    at(2),
+                    60.0,
+                    serde_json::json!({"url": "https://www.youtube.com/watch?v=x", "title": "Music", "audible": true}),
+                ),
+            ],
+        );
+
+        let result = server
+            .aw_browser_summary(Parameters(
+                serde_json::from_value(serde_json::json!({
+                    "start": "2024-03-01T09:00:00Z",
+                    "end": "2024-03-01T10:00:00Z",
+                    "tab_weights": "focused=1,audible=0.5,background=0",
+                    "response_format": "json",
+                }))
+                .unwrap(),
+            ))
+            .await
+            .unwrap();
+        let text = crate::tools::result_text(&result);
+        let json: serde_json::Value = serde_json::from_str(&text).expect(&text);
+        assert_eq!(json["raw_secs"], 180.0);
+        assert_eq!(json["focused_secs"], 120.0);
+        assert_eq!(json["audible_secs"], 60.0);
+        assert_eq!(json["weighted_secs"], 150.0);
+        assert_eq!(
+            json["groups"][0],
+            serde_json::json!({"name": "github.com", "secs": 120.0})
+        );
+        assert_eq!(
+            json["groups"][1],
+            serde_json::json!({"name": "youtube.com", "secs": 30.0})
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_to_the_scope() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let (fake, server) = crate::tools::scoped_test_server(Vec::new(), Vec::new()).await;
+        for host in ["test", "other"] {
+            let bucket_id = format!("aw-watcher-web-firefox_{}", host);
+            fake.add_simple_bucket(&bucket_id, WEB_BUCKET_TYPE, host);
+            let url = format!("https://{}.example.com/", host);
+            fake.add_events(
+                &bucket_id,
+                vec![test_event(
+                    at,
+                    60.0,
+                    serde_json::json!({"url": url, "title": host}),
+                )],
+            );
+        }
+        let params = |value: serde_json::Value| {
+            let mut value = value;
+            value["start"] = "2024-03-01T09:00:00Z".into();
+            value["end"] = "2024-03-01T10:00:00Z".into();
+            value["tab_weights"] = "raw".into();
+            value["response_format"] = "json".into();
+            Parameters(serde_json::from_value(value).unwrap())
+        };
+
+        let result = server
+            .aw_browser_summary(params(serde_json::json!({})))
+            .await
+            .unwrap();
+        let text = crate::tools::result_text(&result);
+        let json: serde_json::Value = serde_json::from_str(&text).expect(&text);
+        assert_eq!(json["raw_secs"], 60.0);
+        assert_eq!(json["groups"][0]["name"], "test.example.com");
+
+        let result = server
+            .aw_browser_summary(params(serde_json::json!({
+                "bucket_id": "aw-watcher-web-firefox_other",
+            })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(!crate::tools::result_text(&result).contains("other.example.com"));
+    }
+}
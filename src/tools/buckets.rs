@@ -1,27 +1,39 @@
-use crate::api::ActivityWatchClient;
-use crate::constants::{CHARACTER_LIMIT, DEFAULT_EVENTS_LIMIT};
-use crate::models::ResponseFormat;
-use rmcp::{
-    handler::server::router::tool::ToolRouter,
-    handler::server::tool::Parameters,
-    model::*,
-    tool, tool_handler, tool_router,
-    ErrorData as McpError,
+use super::{
+    ActivityWatchMcpServer, fit_response, json_response, json_text, token_budget, truncate_response,
 };
+use crate::analysis::{
+    BucketChange, BucketChangeKind, SizeEstimate, TimeRange, apply_zero_duration_policy,
+    bucket_history, format_timestamp, sample_events,
+};
+use crate::constants::{
+    DEFAULT_EVENTS_LIMIT, DEFAULT_STALE_BUCKET_DAYS, ESTIMATE_SAMPLE_SIZE, MAX_SAMPLE_SOURCE_EVENTS,
+};
+use crate::meta::record_note;
+use crate::models::{Bucket, BucketGrouping, ResponseFormat, ZeroDurationPolicy};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
 use schemars::JsonSchema;
 use serde::Deserialize;
-use std::sync::Arc;
+use std::collections::BTreeMap;
 
-/// ActivityWatch MCP Server
-#[derive(Clone)]
-pub struct ActivityWatchMcpServer {
-    client: Arc<ActivityWatchClient>,
-    tool_router: ToolRouter<Self>,
-}
+/// Room left in an event listing for its heading, range and notes when
+/// fitting events to a token budget
+const EVENTS_HEADER_CHARS: usize = 300;
 
 /// Input for listing all buckets
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListBucketsParams {
+    /// Only include buckets whose ID, type, client or hostname contains this text (case-insensitive)
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// Group buckets by "hostname" or "type"
+    #[serde(default)]
+    pub group_by: Option<BucketGrouping>,
+
+    /// Only return bucket counts per group (grouped by type unless `group_by` is set)
+    #[serde(default)]
+    pub summary_only: bool,
+
     /// Output format: "markdown" (default) or "json"
     #[serde(default)]
     pub response_format: ResponseFormat,
@@ -38,6 +50,20 @@ pub struct GetBucketParams {
     pub response_format: ResponseFormat,
 }
 
+/// Input for getting one event
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetEventParams {
+    /// The bucket ID the event belongs to
+    pub bucket_id: String,
+
+    /// ID of the event (the `id` aw_get_events reports)
+    pub event_id: i64,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
 /// Input for getting events from a bucket
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetEventsParams {
@@ -48,14 +74,30 @@ pub struct GetEventsParams {
     #[serde(default)]
     pub limit: Option<i32>,
 
-    /// Start time (ISO 8601 format, e.g., "2024-01-01T00:00:00Z")
+    /// Start time (ISO 8601 format, e.g., "2024-01-01T00:00:00Z"); alone it means "until now"
     #[serde(default)]
     pub start: Option<String>,
 
-    /// End time (ISO 8601 format, e.g., "2024-01-01T23:59:59Z")
+    /// End time (ISO 8601 format, e.g., "2024-01-01T23:59:59Z"); alone it means "from the beginning of the data"
     #[serde(default)]
     pub end: Option<String>,
 
+    /// The range as a preset such as "this morning" or "work hours yesterday", or a period such as "2024-W07", instead of start and end
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Return this many events spread evenly across the range instead of the newest ones (`limit` is ignored)
+    #[serde(default)]
+    pub sample: Option<usize>,
+
+    /// Zero-duration event handling: "keep", "drop" or "merge_into_previous" (default: server setting)
+    #[serde(default)]
+    pub zero_duration: Option<ZeroDurationPolicy>,
+
+    /// Approximate size the response should fit in, in tokens (e.g., 1000); events that do not fit are left out
+    #[serde(default)]
+    pub max_tokens_hint: Option<usize>,
+
     /// Output format: "markdown" (default) or "json"
     #[serde(default)]
     pub response_format: ResponseFormat,
@@ -67,6 +109,29 @@ pub struct GetEventCountParams {
     /// The bucket ID to count events from
     pub bucket_id: String,
 
+    /// Start time (ISO 8601 format); alone it means "until now"
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format); alone it means "from the beginning of the data"
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a preset such as "this morning" or "work hours yesterday", or a period such as "2024-W07", instead of start and end
+    #[serde(default)]
+    pub period: Option<String>,
+}
+
+/// Input for estimating the size of an event query
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EstimateParams {
+    /// The bucket ID the events would be fetched from
+    pub bucket_id: String,
+
+    /// Intended maximum number of events (default: 100)
+    #[serde(default)]
+    pub limit: Option<i32>,
+
     /// Start time (ISO 8601 format)
     #[serde(default)]
     pub start: Option<String>,
@@ -74,44 +139,140 @@ pub struct GetEventCountParams {
     /// End time (ISO 8601 format)
     #[serde(default)]
     pub end: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
 }
 
-#[tool_router]
-impl ActivityWatchMcpServer {
-    /// Create a new ActivityWatch MCP server
-    pub fn new(client: ActivityWatchClient) -> Self {
-        Self {
-            client: Arc::new(client),
-            tool_router: Self::tool_router(),
-        }
-    }
+/// Input for the history of the bucket list
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BucketHistoryParams {
+    /// Days without events after which a bucket counts as stale (default: 7)
+    #[serde(default)]
+    pub stale_days: Option<u32>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
 
+#[tool_router(router = bucket_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
     /// List all ActivityWatch buckets.
-    #[tool(description = "List all ActivityWatch buckets. Buckets are containers that group events by watcher type and hostname (e.g., aw-watcher-window_hostname for window tracking events).")]
+    #[tool(
+        description = r#"List all ActivityWatch buckets. Buckets are containers that group events by watcher type and hostname (e.g., aw-watcher-window_hostname for window tracking events).
+
+## Parameters
+- `filter`: Only include buckets whose ID, type, client or hostname contains this text
+- `group_by`: Group buckets by "hostname" or "type"
+- `summary_only`: Only list bucket counts per type (or per `group_by`), useful with hundreds of buckets
+
+Bucket metadata is cached; use aw_refresh_buckets to force re-discovery."#
+    )]
     async fn aw_list_buckets(
         &self,
         Parameters(params): Parameters<ListBucketsParams>,
     ) -> Result<CallToolResult, McpError> {
-        match self.client.get_buckets().await {
+        match self.client.buckets().await {
             Ok(buckets) => {
+                let total = buckets.values().filter(|b| self.in_scope(b)).count();
+                let mut buckets: Vec<&Bucket> = buckets
+                    .values()
+                    .filter(|b| self.in_scope(b))
+                    .filter(|b| {
+                        params
+                            .filter
+                            .as_deref()
+                            .is_none_or(|filter| b.matches_filter(filter))
+                    })
+                    .collect();
+                buckets.sort_by(|a, b| a.id.cmp(&b.id));
+
+                let grouping = match (params.group_by, params.summary_only) {
+                    (Some(grouping), _) => Some(grouping),
+                    (None, true) => Some(BucketGrouping::Type),
+                    (None, false) => None,
+                };
+                let mut groups: BTreeMap<String, Vec<&Bucket>> = BTreeMap::new();
+                if let Some(grouping) = grouping {
+                    for &bucket in &buckets {
+                        groups
+                            .entry(bucket.group_label(grouping))
+                            .or_default()
+                            .push(bucket);
+                    }
+                }
+
                 let response = match params.response_format {
                     ResponseFormat::Markdown => {
-                        let mut lines = vec![
-                            "# ActivityWatch Buckets".to_string(),
-                            String::new(),
-                            format!("Found {} buckets:", buckets.len()),
-                            String::new(),
-                        ];
-
-                        for bucket in buckets.values() {
-                            lines.push(bucket.to_markdown());
-                            lines.push(String::new());
+                        let mut lines = vec!["# ActivityWatch Buckets".to_string(), String::new()];
+                        if buckets.len() == total {
+                            lines.push(format!("Found {} buckets:", total));
+                        } else {
+                            lines.push(format!(
+                                "Found {} of {} buckets matching the filter:",
+                                buckets.len(),
+                                total
+                            ));
+                        }
+                        lines.push(String::new());
+
+                        if params.summary_only {
+                            for (label, members) in &groups {
+                                lines.push(format!(
+                                    "- **{}**: {} buckets",
+                                    self.markdown_text(label),
+                                    members.len()
+                                ));
+                            }
+                        } else if let Some(grouping) = grouping {
+                            let kind = match grouping {
+                                BucketGrouping::Hostname => "Hostname",
+                                BucketGrouping::Type => "Type",
+                            };
+                            for (label, members) in &groups {
+                                lines.push(format!(
+                                    "## {}: {} ({} buckets)",
+                                    kind,
+                                    self.markdown_text(label),
+                                    members.len()
+                                ));
+                                lines.push(String::new());
+                                for bucket in members {
+                                    lines.push(bucket.to_markdown(self.config().escape_markdown));
+                                    lines.push(String::new());
+                                }
+                            }
+                        } else {
+                            for bucket in &buckets {
+                                lines.push(bucket.to_markdown(self.config().escape_markdown));
+                                lines.push(String::new());
+                            }
                         }
 
                         truncate_response(lines.join("\n"))
                     }
                     ResponseFormat::Json => {
-                        serde_json::to_string_pretty(&buckets)
+                        let value = if params.summary_only {
+                            serde_json::to_value(
+                                groups
+                                    .iter()
+                                    .map(|(label, members)| (label, members.len()))
+                                    .collect::<BTreeMap<_, _>>(),
+                            )
+                        } else if grouping.is_some() {
+                            serde_json::to_value(&groups)
+                        } else {
+                            serde_json::to_value(
+                                buckets
+                                    .iter()
+                                    .map(|b| (&b.id, *b))
+                                    .collect::<BTreeMap<_, _>>(),
+                            )
+                        };
+                        value
+                            .map(|v| json_text(&v))
                             .unwrap_or_else(|_| "Error formatting JSON".to_string())
                     }
                 };
@@ -119,12 +280,139 @@ impl ActivityWatchMcpServer {
                 Ok(CallToolResult::success(vec![Content::text(response)]))
             }
             Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Failed to list buckets: {:?}",
-                e
+                "Failed to list buckets: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Force re-discovery of ActivityWatch buckets.
+    #[tool(
+        description = "Refresh the cached list of ActivityWatch buckets. Use this after creating or deleting buckets, or when a bucket you expect is missing from aw_list_buckets."
+    )]
+    async fn aw_refresh_buckets(&self) -> Result<CallToolResult, McpError> {
+        match self.client.refresh_buckets().await {
+            Ok(mut refresh) => {
+                let visible = refresh
+                    .buckets
+                    .values()
+                    .filter(|b| self.in_scope(b))
+                    .count();
+                if let Some(scope) = &self.config().scope {
+                    // Removed buckets have no hostname left to check
+                    refresh
+                        .added
+                        .retain(|id| refresh.buckets.get(id).is_some_and(|b| scope.allows(b)));
+                    refresh.removed.retain(|id| scope.allows_id(id));
+                }
+
+                let mut lines = vec![
+                    "# Bucket Cache Refreshed".to_string(),
+                    String::new(),
+                    format!("**Buckets**: {}", visible),
+                ];
+                if !refresh.added.is_empty() {
+                    lines.push(format!("**New**: {}", refresh.added.join(", ")));
+                }
+                if !refresh.removed.is_empty() {
+                    lines.push(format!("**Removed**: {}", refresh.removed.join(", ")));
+                }
+
+                Ok(CallToolResult::success(vec![Content::text(
+                    truncate_response(lines.join("\n")),
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to refresh buckets: {}",
+                e.message
             ))])),
         }
     }
 
+    /// How the bucket list changed over time.
+    #[tool(
+        description = r#"Show how the ActivityWatch buckets changed over time: when each bucket appeared or disappeared, when a watcher moved to a new hostname (e.g., after an OS reinstall or renaming the machine, its events continue in a new bucket), and which buckets have gone stale. Use this when totals look wrong because data is split across old and new buckets. Needs the offline cache (`AW_MCP_CACHE_DIR`), where a snapshot of the bucket metadata is kept each time the bucket list changes; history starts with the first snapshot.
+
+## Parameters
+- `stale_days`: Days without events after which a bucket counts as stale (default: 7)"#
+    )]
+    async fn aw_bucket_history(
+        &self,
+        Parameters(params): Parameters<BucketHistoryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.stale_days == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "stale_days must be at least 1",
+            )]));
+        }
+
+        // Listing the buckets records a snapshot when they changed
+        let buckets = match self.client.buckets().await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to get bucket history: {}",
+                    e.message
+                ))]));
+            }
+        };
+        let Some(snapshots) = self.client.bucket_history().await else {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket history needs the offline cache: set AW_MCP_CACHE_DIR to keep it",
+            )]));
+        };
+
+        let stale_days = params.stale_days.unwrap_or(DEFAULT_STALE_BUCKET_DAYS);
+        let mut changes = bucket_history(&snapshots, chrono::Duration::days(stale_days.into()));
+        if let Some(scope) = &self.config().scope {
+            // Buckets that are gone have no hostname left to check
+            changes.retain(|change| match buckets.get(&change.bucket_id) {
+                Some(bucket) => scope.allows(bucket),
+                None => scope.allows_id(&change.bucket_id),
+            });
+        }
+        let tracked_since = snapshots.first().map(|snapshot| snapshot.taken_at);
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec!["# Bucket History".to_string(), String::new()];
+                match tracked_since {
+                    Some(since) => {
+                        lines.push(format!("**Tracked since**: {}", format_timestamp(&since)))
+                    }
+                    None => lines.push("**Tracked since**: now (no snapshots yet)".to_string()),
+                }
+                lines.push(format!(
+                    "**Stale after**: {} days without events",
+                    stale_days
+                ));
+                lines.push(String::new());
+                if changes.is_empty() {
+                    lines.push("No changes recorded.".to_string());
+                } else {
+                    lines.push("| When | Bucket | Change |".to_string());
+                    lines.push("|---|---|---|".to_string());
+                    lines.extend(changes.iter().map(|change| {
+                        format!(
+                            "| {} | {} | {} |",
+                            format_timestamp(&change.at),
+                            self.markdown_text(&change.bucket_id),
+                            self.markdown_text(&change_text(change))
+                        )
+                    }));
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "tracked_since": tracked_since,
+                "stale_days": stale_days,
+                "changes": changes,
+            })),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
     /// Get a specific ActivityWatch bucket by ID.
     #[tool(description = "Get detailed information about a specific ActivityWatch bucket by its ID. Returns bucket metadata including type, hostname, and creation time.")]
     async fn aw_get_bucket(
@@ -137,23 +425,29 @@ impl ActivityWatchMcpServer {
             )]));
         }
 
+        if let Err(e) = self.check_scope(&params.bucket_id).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to get bucket: {}",
+                e.message
+            ))]));
+        }
+
         match self.client.get_bucket(&params.bucket_id).await {
             Ok(bucket) => {
                 let response = match params.response_format {
                     ResponseFormat::Markdown => {
                         let mut lines = vec!["# Bucket Details".to_string(), String::new()];
-                        lines.push(bucket.to_markdown());
+                        lines.push(bucket.to_markdown(self.config().escape_markdown));
                         lines.join("\n")
                     }
-                    ResponseFormat::Json => serde_json::to_string_pretty(&bucket)
-                        .unwrap_or_else(|_| "Error formatting JSON".to_string()),
+                    ResponseFormat::Json => json_text(&bucket),
                 };
 
                 Ok(CallToolResult::success(vec![Content::text(response)]))
             }
             Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Failed to get bucket: {:?}",
-                e
+                "Failed to get bucket: {}",
+                e.message
             ))])),
         }
     }
@@ -164,8 +458,14 @@ impl ActivityWatchMcpServer {
 ## Parameters
 - `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
 - `limit`: Maximum events to return (default: 100)
-- `start`: Start time in ISO 8601 format (e.g., "2024-01-01T00:00:00Z")
-- `end`: End time in ISO 8601 format (e.g., "2024-01-01T23:59:59Z")
+- `start`: Start time in ISO 8601 format (e.g., "2024-01-01T00:00:00Z"). Without `end`, events until now are returned
+- `end`: End time in ISO 8601 format (e.g., "2024-01-01T23:59:59Z"). Without `start`, events from the beginning of the data are returned
+- `period`: The range as a preset instead: "this morning", "afternoon", "work hours yesterday", "yesterday evening", "today", or with a date ("morning 2024-03-01"); presets follow the configured working hours. Periods such as "2024-W07" work too
+- `sample`: Return this many events spread evenly across the range (first, last and evenly spaced picks in between) instead of the newest ones; gives a representative picture of a long period. Samples are drawn from at most the newest 10000 events of the range
+- `zero_duration`: How to handle zero-duration events: "keep", "drop" or "merge_into_previous"
+- `max_tokens_hint`: Approximate token budget of the response (e.g., 1000). Only the events that fit are returned, and JSON is compact
+
+The resolved range is echoed in the markdown output, and in a note after JSON output.
 
 ## Example
 Get the last 10 window events:
@@ -185,57 +485,208 @@ Get the last 10 window events:
             )]));
         }
 
+        if let Err(e) = self.check_scope(&params.bucket_id).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to get events: {}",
+                e.message
+            ))]));
+        }
+
+        let budget = match token_budget(params.max_tokens_hint) {
+            Ok(budget) => budget,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
         let limit = params.limit.unwrap_or(DEFAULT_EVENTS_LIMIT);
+        let range = match self.resolve_event_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let start = range.map(|r| format_timestamp(&r.start));
+        let end = range.map(|r| format_timestamp(&r.end));
 
         match self
             .client
             .get_events(
                 &params.bucket_id,
-                Some(limit),
-                params.start.as_deref(),
-                params.end.as_deref(),
+                // Sampling picks from the whole range, up to a bound
+                Some(match params.sample {
+                    Some(_) => MAX_SAMPLE_SOURCE_EVENTS,
+                    None => limit,
+                }),
+                start.as_deref(),
+                end.as_deref(),
             )
             .await
         {
             Ok(events) => {
+                let fetched = events.len();
+                let sample_capped =
+                    params.sample.is_some() && fetched as i32 >= MAX_SAMPLE_SOURCE_EVENTS;
+                let capped_note = format!(
+                    "_Sampled from the newest {} events of the range only; narrow the range to sample all of it._",
+                    MAX_SAMPLE_SOURCE_EVENTS
+                );
+                let policy = params
+                    .zero_duration
+                    .unwrap_or(self.config().zero_duration_policy);
+                let events = apply_zero_duration_policy(events, policy);
+                let handled = fetched - events.len();
+                let (events, sampled_from) = match params.sample {
+                    Some(n) => {
+                        let total = events.len();
+                        (sample_events(events, n), Some(total))
+                    }
+                    None => (events, None),
+                };
+                let escape = self.config().escape_markdown;
+                let (events, left_out) = match budget {
+                    Some(budget) => {
+                        let fitting = match params.response_format {
+                            ResponseFormat::Markdown => {
+                                budget.fitting(&events, EVENTS_HEADER_CHARS, |event| {
+                                    event.to_markdown(escape).len() + 2
+                                })
+                            }
+                            ResponseFormat::Json => budget.fitting(&events, 2, |event| {
+                                serde_json::to_string(event).map_or(0, |json| json.len() + 1)
+                            }),
+                        };
+                        let left_out = events.len() - fitting;
+                        (events.into_iter().take(fitting).collect(), left_out)
+                    }
+                    None => (events, 0),
+                };
+
                 let response = match params.response_format {
                     ResponseFormat::Markdown => {
-                        let mut lines = vec![
-                            format!("# Events from {}", params.bucket_id),
-                            String::new(),
-                            format!("Showing {} events:", events.len()),
-                            String::new(),
-                        ];
+                        let mut lines =
+                            vec![format!("# Events from {}", params.bucket_id), String::new()];
+                        if let Some(range) = &range {
+                            lines.push(range_line(
+                                range,
+                                params.start.is_none() && params.period.is_none(),
+                                params.end.is_none() && params.period.is_none(),
+                            ));
+                        }
+                        lines.push(match sampled_from {
+                            Some(total) => format!(
+                                "Showing {} of {} events, sampled evenly across the range (oldest first):",
+                                events.len(),
+                                total
+                            ),
+                            None => format!("Showing {} events:", events.len()),
+                        });
+                        if sample_capped {
+                            lines.push(capped_note);
+                        }
+                        if sampled_from.is_none() && handled > 0 {
+                            lines.push(format!(
+                                "_{} zero-duration events handled with policy `{}`._",
+                                handled, policy
+                            ));
+                        }
+                        lines.push(String::new());
 
                         for event in &events {
-                            lines.push(event.to_markdown());
+                            lines.push(event.to_markdown(escape));
                             lines.push(String::new());
                         }
+                        if left_out > 0 {
+                            lines.push(format!(
+                                "_{} more events left out to fit max_tokens_hint._",
+                                left_out
+                            ));
+                        }
 
-                        if events.len() as i32 >= limit {
+                        if sampled_from.is_none() && fetched as i32 >= limit {
                             lines.push(format!(
                                 "_Limit of {} reached. Use pagination to see more._",
                                 limit
                             ));
                         }
 
-                        truncate_response(lines.join("\n"))
+                        fit_response(lines.join("\n"), budget)
+                    }
+                    ResponseFormat::Json => {
+                        // Keep the array shape; the range goes with the response notes
+                        if let Some(range) = &range {
+                            record_note(range_line(
+                                range,
+                                params.start.is_none() && params.period.is_none(),
+                                params.end.is_none() && params.period.is_none(),
+                            ));
+                        }
+                        if sample_capped {
+                            record_note(capped_note);
+                        }
+                        json_response(&events, budget)
                     }
-                    ResponseFormat::Json => serde_json::to_string_pretty(&events)
-                        .unwrap_or_else(|_| "Error formatting JSON".to_string()),
                 };
 
                 Ok(CallToolResult::success(vec![Content::text(response)]))
             }
             Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Failed to get events: {:?}",
-                e
+                "Failed to get events: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Get one event of an ActivityWatch bucket by ID.
+    #[tool(
+        description = "Get a single event of an ActivityWatch bucket by its ID (the `id` field of aw_get_events and other listings), to inspect one record without fetching a page of events."
+    )]
+    async fn aw_get_event(
+        &self,
+        Parameters(params): Parameters<GetEventParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        if let Err(e) = self.check_scope(&params.bucket_id).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to get event: {}",
+                e.message
+            ))]));
+        }
+
+        match self
+            .client
+            .get_event(&params.bucket_id, params.event_id)
+            .await
+        {
+            Ok(event) => {
+                let response = match params.response_format {
+                    ResponseFormat::Markdown => [
+                        format!("# Event {} from {}", params.event_id, params.bucket_id),
+                        String::new(),
+                        event.to_markdown(self.config().escape_markdown),
+                        format!("- **End**: {}", format_timestamp(&event.end())),
+                    ]
+                    .join("\n"),
+                    ResponseFormat::Json => json_text(&event),
+                };
+
+                Ok(CallToolResult::success(vec![Content::text(response)]))
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to get event: {}",
+                e.message
             ))])),
         }
     }
 
     /// Get the count of events in an ActivityWatch bucket.
-    #[tool(description = "Get the total count of events in an ActivityWatch bucket. Useful for understanding data volume before fetching events. Optionally filter by time range.")]
+    #[tool(
+        description = "Get the total count of events in an ActivityWatch bucket. Useful for understanding data volume before fetching events. Optionally filter by time range: `start` alone counts until now, `end` alone counts from the beginning of the data, and `period` takes a preset such as \"this morning\" or \"work hours yesterday\"."
+    )]
     async fn aw_get_event_count(
         &self,
         Parameters(params): Parameters<GetEventCountParams>,
@@ -246,13 +697,27 @@ Get the last 10 window events:
             )]));
         }
 
+        if let Err(e) = self.check_scope(&params.bucket_id).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to get event count: {}",
+                e.message
+            ))]));
+        }
+
+        let range = match self.resolve_event_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let start = range.map(|r| format_timestamp(&r.start));
+        let end = range.map(|r| format_timestamp(&r.end));
+
         match self
             .client
-            .get_event_count(
-                &params.bucket_id,
-                params.start.as_deref(),
-                params.end.as_deref(),
-            )
+            .get_event_count(&params.bucket_id, start.as_deref(), end.as_deref())
             .await
         {
             Ok(count) => {
@@ -262,45 +727,228 @@ Get the last 10 window events:
                     format!("**Total Events**: {}", count),
                 ];
 
-                if let Some(ref start) = params.start {
-                    lines.push(format!("**From**: {}", start));
-                }
-                if let Some(ref end) = params.end {
-                    lines.push(format!("**To**: {}", end));
+                if let Some(range) = &range {
+                    lines.push(range_line(
+                        range,
+                        params.start.is_none() && params.period.is_none(),
+                        params.end.is_none() && params.period.is_none(),
+                    ));
                 }
 
                 Ok(CallToolResult::success(vec![Content::text(lines.join("\n"))]))
             }
             Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Failed to get event count: {:?}",
-                e
+                "Failed to get event count: {}",
+                e.message
             ))])),
         }
     }
+
+    /// Estimate the size of an event query before running it.
+    #[tool(
+        description = r#"Estimate how large an aw_get_events call would be before making it: the number of events in the range (via the count endpoint) and the approximate response size in characters and tokens for markdown and JSON output, measured on a small sample of events. Use this to choose a limit, range or format before spending a large call.
+
+## Parameters
+- `bucket_id`: The bucket the events would be fetched from
+- `limit`: Intended maximum number of events (default: 100)
+- `start`, `end`: Intended time range (ISO 8601)"#
+    )]
+    async fn aw_estimate(
+        &self,
+        Parameters(params): Parameters<EstimateParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        if let Err(e) = self.check_scope(&params.bucket_id).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to estimate: {}",
+                e.message
+            ))]));
+        }
+
+        let limit = params.limit.unwrap_or(DEFAULT_EVENTS_LIMIT);
+        let start = params.start.as_deref();
+        let end = params.end.as_deref();
+        let (count, sample) = tokio::join!(
+            self.client.get_event_count(&params.bucket_id, start, end),
+            self.client.get_events(
+                &params.bucket_id,
+                Some(ESTIMATE_SAMPLE_SIZE.min(limit)),
+                start,
+                end
+            )
+        );
+        let (count, sample) = match (count, sample) {
+            (Ok(count), Ok(sample)) => (count, sample),
+            (Err(e), _) | (_, Err(e)) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to estimate: {}",
+                    e.message
+                ))]));
+            }
+        };
+        let estimate = SizeEstimate::from_sample(
+            &sample,
+            count,
+            limit.max(0) as usize,
+            self.config().escape_markdown,
+        );
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!("# Estimate for {}", params.bucket_id),
+                    String::new(),
+                    format!("**Events in range**: {}", estimate.total_events),
+                    format!(
+                        "**Events returned** (limit {}): {}",
+                        limit, estimate.returned_events
+                    ),
+                    format!(
+                        "**Markdown**: ~{} characters (~{} tokens){}",
+                        estimate.markdown.chars,
+                        estimate.markdown.tokens,
+                        if estimate.markdown.truncated {
+                            ", truncated at the response limit"
+                        } else {
+                            ""
+                        }
+                    ),
+                    format!(
+                        "**JSON**: ~{} characters (~{} tokens)",
+                        estimate.json.chars, estimate.json.tokens
+                    ),
+                    String::new(),
+                    format!(
+                        "_Sizes extrapolated from {} sampled events._",
+                        estimate.sampled_events
+                    ),
+                ];
+                if estimate.total_events > estimate.returned_events as i64 {
+                    lines.push(format!(
+                        "_{} events fall outside the limit; narrow the range or raise the limit to see them._",
+                        estimate.total_events - estimate.returned_events as i64
+                    ));
+                }
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&estimate),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
 }
 
-#[tool_handler]
-impl rmcp::ServerHandler for ActivityWatchMcpServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            instructions: Some(
-                "ActivityWatch MCP Server - Query your ActivityWatch time tracking data. Use aw_list_buckets to see available data sources, then aw_get_events to retrieve activity logs.".into(),
-            ),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            ..Default::default()
+/// Describe a change of the bucket list
+fn change_text(change: &BucketChange) -> String {
+    let host = |hostname: &Option<String>| hostname.as_deref().unwrap_or("unknown").to_string();
+    match &change.kind {
+        BucketChangeKind::Appeared => "appeared".to_string(),
+        BucketChangeKind::Disappeared => "disappeared".to_string(),
+        BucketChangeKind::HostChanged {
+            from,
+            to,
+            previous_bucket,
+        } => {
+            let mut text = format!("host renamed {} → {}", host(from), host(to));
+            if let Some(previous_bucket) = previous_bucket {
+                text.push_str(&format!(" (was bucket {})", previous_bucket));
+            }
+            text
         }
+        BucketChangeKind::Stale => "stale: no events since".to_string(),
     }
 }
 
-/// Truncate response if it exceeds the character limit
-fn truncate_response(response: String) -> String {
-    if response.len() > CHARACTER_LIMIT {
-        let truncated = &response[..CHARACTER_LIMIT];
-        format!(
-            "{}\n\n_Response truncated at {} characters. Use more specific filters to reduce results._",
-            truncated, CHARACTER_LIMIT
-        )
+/// Echo the resolved range, naming the bounds that were filled in
+fn range_line(range: &TimeRange, open_start: bool, open_end: bool) -> String {
+    let start = if open_start {
+        "beginning of data".to_string()
+    } else {
+        format!("{} UTC", range.start.format("%Y-%m-%d %H:%M:%S"))
+    };
+    let end = if open_end {
+        format!("now ({} UTC)", range.end.format("%Y-%m-%d %H:%M:%S"))
     } else {
-        response
+        format!("{} UTC", range.end.format("%Y-%m-%d %H:%M:%S"))
+    };
+    format!("**Range**: {} to {}", start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{result_text, test_server};
+    use super::*;
+    use crate::meta::ResponseMeta;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn params(value: serde_json::Value) -> Parameters<GetEventsParams> {
+        Parameters(serde_json::from_value(value).unwrap())
+    }
+
+    #[tokio::test]
+    async fn json_events_stay_an_array_with_a_range() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let (_fake, server) = test_server(vec![test_event(
+            start,
+            60.0,
+            serde_json::json!({"app": "a"}),
+        )])
+        .await;
+
+        let (result, meta) = ResponseMeta::collect(
+            "t".to_string(),
+            server.aw_get_events(params(serde_json::json!({
+                "bucket_id": "aw-watcher-window_test",
+                "start": "2024-03-01T00:00:00Z",
+                "end": "2024-03-02T00:00:00Z",
+                "response_format": "json",
+            }))),
+        )
+        .await;
+
+        let events: serde_json::Value =
+            serde_json::from_str(&result_text(&result.unwrap())).unwrap();
+        assert_eq!(events.as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            meta.notes,
+            ["**Range**: 2024-03-01 00:00:00 UTC to 2024-03-02 00:00:00 UTC"]
+        );
+    }
+
+    #[tokio::test]
+    async fn samples_from_a_bounded_number_of_events() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let events = (0..5)
+            .map(|i| {
+                test_event(
+                    start + chrono::Duration::minutes(i),
+                    60.0,
+                    serde_json::json!({"app": "a"}),
+                )
+            })
+            .collect();
+        let (fake, server) = test_server(events).await;
+
+        let result = server
+            .aw_get_events(params(serde_json::json!({
+                "bucket_id": "aw-watcher-window_test",
+                "sample": 3,
+            })))
+            .await
+            .unwrap();
+
+        assert!(result_text(&result).contains("Showing 3 of 5 events"));
+        let request = fake.requests().pop().unwrap();
+        assert!(
+            request.contains(&format!("limit={}", MAX_SAMPLE_SOURCE_EVENTS)),
+            "{}",
+            request
+        );
     }
 }
@@ -0,0 +1,996 @@
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::aggregates::DayTotals;
+use crate::analysis::{TimeRange, TotalsCheck, discrepancy_markdown, format_timestamp};
+use crate::categories::{
+    CATEGORY_SEPARATOR, Categories, CategoryTotals, Contributor, Regex, category_totals,
+    compare_rules, split_path, test_rule, uncategorized,
+};
+use crate::constants::{CATEGORIES_SETTING, DEFAULT_CATEGORY_DEPTH, DEFAULT_UNCATEGORIZED_LIMIT};
+use crate::format::{
+    code_block, code_span, format_duration, format_duration_delta, format_percent,
+};
+use crate::models::{ResponseFormat, RuleFormat};
+use crate::timezone::Timezone;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Input for category totals
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CategorySummaryParams {
+    /// The bucket ID to categorize (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Category levels to roll subcategories up to (default: 1, e.g. "Work > Programming > Rust" counts as "Work")
+    #[serde(default)]
+    pub depth: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the uncategorized time drill-down
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UncategorizedParams {
+    /// The bucket ID to check (e.g., "aw-watcher-window_hostname" or a web watcher bucket for domains)
+    pub bucket_id: String,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Maximum apps, titles and domains to list each (default: 10)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for testing a candidate category rule
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TestRuleParams {
+    /// The bucket ID to test against (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// The candidate regex, matched against app, title, URL and other data values
+    pub regex: String,
+
+    /// Match case-insensitively (default: false; a leading "(?i)" works too)
+    #[serde(default)]
+    pub ignore_case: bool,
+
+    /// The category the rule would assign (e.g., "Work > Programming"), to see how much time it would win over existing rules
+    #[serde(default)]
+    pub category: Option<String>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Maximum example titles to list (default: 10)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for comparing the current category rules with a proposed set
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CompareRulesParams {
+    /// The bucket ID to compare on (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// The proposed rules: categories.toml text, or a list of classes in aw-webui's settings format
+    pub proposed: serde_json::Value,
+
+    /// Category levels to compare at (default: all levels)
+    #[serde(default)]
+    pub depth: Option<usize>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for converting category rules between formats
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ConvertRulesParams {
+    /// Format to convert to: "toml" (categories.toml) or "webui" (aw-webui's classes setting)
+    pub to: RuleFormat,
+
+    /// The rules to convert: categories.toml text, or aw-webui classes (default: the rules in use)
+    #[serde(default)]
+    pub rules: Option<serde_json::Value>,
+}
+
+#[tool_router(router = category_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Total time per category, rolled up to a depth.
+    #[tool(
+        description = r#"Total the time in a bucket per category, using the category rules defined in aw-webui (or the server's categories.toml). Subcategories are rolled up to the requested depth, like the category tree in aw-webui, and the totals per full (leaf) category are listed as well.
+
+An event belongs to the deepest category whose rule matches its app, title or URL; events no rule matches are "Uncategorized".
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday"
+- `depth`: Category levels to keep (default: 1, so "Work > Programming > Rust" counts as "Work")"#
+    )]
+    async fn aw_category_summary(
+        &self,
+        Parameters(params): Parameters<CategorySummaryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.depth == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Depth must be at least 1",
+            )]));
+        }
+
+        // Whole days are taken in the bucket's timezone for the aggregation store
+        let tz = self.bucket_timezone(&params.bucket_id).await;
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.category_summary(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to total categories: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// List what uncategorized time is spent on, with suggested rules.
+    #[tool(
+        description = r#"List the apps, window titles and domains that contribute most to uncategorized time in a range, each with a suggested regex for a category rule. Use it to improve the category rules step by step: add rules for the largest contributors, then check again.
+
+Suggestions are starting points: app rules match the exact app name, title rules the first part of the title (before " - ", " | ", ...), and domain rules the domain anywhere in a URL. Domains are only listed for buckets with URLs, such as web watcher buckets.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday"
+- `limit`: Maximum apps, titles and domains to list each (default: 10)"#
+    )]
+    async fn aw_uncategorized(
+        &self,
+        Parameters(params): Parameters<UncategorizedParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.uncategorized(&params, &range).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to list uncategorized time: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Test a candidate category rule against past events.
+    #[tool(
+        description = r#"Test a candidate category rule before saving it: report how much time in a range its regex matches, which categories that time belongs to now, and the titles it matches most. With a `category`, also report how much of the matched time the rule would actually win (the deepest matching category wins; ties go to existing rules).
+
+The regex syntax is the common subset used by aw-webui rules: literals, `.`, classes such as `[a-z]` and `\d`, groups, `|`, quantifiers, `^`, `$`, `\b` and a leading `(?i)`.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `regex`: The candidate regex, matched against app, title, URL and other data values
+- `ignore_case`: Match case-insensitively (default: false)
+- `category`: The category the rule would assign, e.g. "Work > Programming" (optional)
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday"
+- `limit`: Maximum example titles (default: 10)"#
+    )]
+    async fn aw_test_rule(
+        &self,
+        Parameters(params): Parameters<TestRuleParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.regex.is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Regex cannot be empty",
+            )]));
+        }
+        let regex = match Regex::new(&params.regex, params.ignore_case) {
+            Ok(regex) => regex,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let category = params.category.as_deref().map(split_path);
+        if category.as_ref().is_some_and(|name| name.is_empty()) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Category cannot be empty",
+            )]));
+        }
+
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self
+            .test_rule(&params, &regex, category.as_deref(), &range)
+            .await
+        {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to test rule: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Preview how a proposed rule set would recategorize past time.
+    #[tool(
+        description = r#"Preview a change to the category rules: classify a sample range under both the current rules and a proposed rule set, and show how category totals change and which time moves from one category to another. Use it to check a rule change before saving it in aw-webui or categories.toml.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `proposed`: The complete proposed rule set, either as categories.toml text (`"Work > Programming" = "(?i)code"` lines) or as a list of classes in aw-webui's format (`[{"name": ["Work"], "rule": {"type": "regex", "regex": "..."}}]`)
+- `depth`: Category levels to compare at (default: all levels)
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday""#
+    )]
+    async fn aw_compare_rules(
+        &self,
+        Parameters(params): Parameters<CompareRulesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.depth == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Depth must be at least 1",
+            )]));
+        }
+        let proposed = match parse_rules(&params.proposed) {
+            Ok(proposed) => proposed,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Invalid proposed rules: {}",
+                    e
+                ))]));
+            }
+        };
+
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.compare_rules(&params, &proposed, &range).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to compare rules: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Convert category rules between categories.toml and aw-webui's format.
+    #[tool(
+        description = r#"Convert category rules between the categories.toml format and aw-webui's settings format (the JSON list of classes), to keep rules maintained in one place in sync with the other. Without `rules`, converts the rules currently in use.
+
+Case-insensitive aw-webui rules become `(?i)` regexes in TOML and back. Parent categories without a rule of their own are added when converting to aw-webui. Colors and other display settings from aw-webui are not kept.
+
+## Parameters
+- `to`: "toml" or "webui"
+- `rules`: The rules to convert, as categories.toml text or a list of aw-webui classes (optional)"#
+    )]
+    async fn aw_convert_rules(
+        &self,
+        Parameters(params): Parameters<ConvertRulesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let (categories, source) = match &params.rules {
+            Some(rules) => match parse_rules(rules) {
+                Ok(categories) => (categories, "the given rules".to_string()),
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Invalid rules: {}",
+                        e
+                    ))]));
+                }
+            },
+            None => match self.categories().await {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Failed to load category rules: {}",
+                        e.message
+                    ))]));
+                }
+            },
+        };
+
+        let (title, block, hint) = match params.to {
+            RuleFormat::Toml => (
+                "categories.toml",
+                code_block("toml", &categories.to_toml()),
+                "Save this as the file `AW_MCP_CATEGORIES` points to.",
+            ),
+            RuleFormat::Webui => (
+                "aw-webui Categories",
+                code_block(
+                    "json",
+                    &serde_json::to_string_pretty(&categories.to_webui())
+                        .unwrap_or_else(|_| "Error formatting JSON".to_string()),
+                ),
+                "Store this as the `classes` setting (with `aw_set_setting` when writes are \
+                 enabled); reload aw-webui to see it.",
+            ),
+        };
+        let lines = [
+            format!("# {}", title),
+            String::new(),
+            format!(
+                "- **Source**: {} ({} categories)",
+                source,
+                categories.rules.len()
+            ),
+            String::new(),
+            block,
+            String::new(),
+            hint.to_string(),
+        ];
+        Ok(CallToolResult::success(vec![Content::text(
+            truncate_response(lines.join("\n")),
+        )]))
+    }
+}
+
+/// Read rules given as categories.toml text or as aw-webui classes (a list,
+/// or an export with the list under `categories`)
+fn parse_rules(value: &serde_json::Value) -> Result<Categories, String> {
+    match value {
+        serde_json::Value::String(text) => Categories::parse_toml(text),
+        serde_json::Value::Array(_) => Categories::from_webui(value),
+        serde_json::Value::Object(export) if export.contains_key("categories") => {
+            Categories::from_webui(value)
+        }
+        _ => Err("expected categories.toml text or a list of aw-webui classes".to_string()),
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Load the category rules from `AW_MCP_CATEGORIES` if set, otherwise
+    /// from aw-webui's settings, with a description of where they came from
+    pub(super) async fn categories(&self) -> Result<(Categories, String), McpError> {
+        let config = self.config();
+        if let Some(categories) = &config.category_rules {
+            let source = config
+                .config_file
+                .as_ref()
+                .map_or("config file".to_string(), |path| path.display().to_string());
+            return Ok((categories.clone(), source));
+        }
+        if let Some(path) = &config.categories_file {
+            let categories = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Categories::parse_toml(&text))
+                .map_err(|e| {
+                    McpError::internal_error(
+                        format!("Cannot load categories from {}: {}", path.display(), e),
+                        None,
+                    )
+                })?;
+            return Ok((categories, path.display().to_string()));
+        }
+
+        let classes = self.client.get_setting(CATEGORIES_SETTING).await?;
+        if classes.is_null() {
+            return Err(McpError::invalid_request(
+                "No categories defined: set them up in aw-webui or point AW_MCP_CATEGORIES \
+                 at a categories.toml",
+                None,
+            ));
+        }
+        let categories = Categories::from_webui(&classes).map_err(|e| {
+            McpError::internal_error(format!("Invalid aw-webui categories: {}", e), None)
+        })?;
+        Ok((categories, "aw-webui settings".to_string()))
+    }
+
+    /// Fetch the range and total it per category
+    async fn category_summary(
+        &self,
+        params: &CategorySummaryParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let (categories, source) = self.categories().await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let depth = params.depth.unwrap_or(DEFAULT_CATEGORY_DEPTH);
+
+        // Whole days are served from the aggregation store
+        let (totals, stored_days) = match Self::whole_days(range, tz) {
+            Some((first, last)) => {
+                let daily = self
+                    .daily_totals(&params.bucket_id, first, last, Some(&categories))
+                    .await?;
+                let mut sum = DayTotals::default();
+                daily.days.iter().for_each(|(_, day)| sum.add(day));
+                (
+                    CategoryTotals::from_leaves(&sum.categories, depth),
+                    Some(daily.stored),
+                )
+            }
+            None => {
+                let events = self.range_events(&params.bucket_id, range).await?;
+                (
+                    category_totals(&events, &categories, depth, Some(range)),
+                    None,
+                )
+            }
+        };
+        let total_secs = totals
+            .leaves
+            .iter()
+            .fold(0.0, |total, (_, secs)| total + secs);
+        let discrepancies = TotalsCheck {
+            group_secs: Some(totals.rolled_up.iter().map(|(_, secs)| secs).sum()),
+            ..TotalsCheck::new(range.duration_secs(), total_secs)
+        }
+        .discrepancies();
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let share = |secs: f64| match total_secs > 0.0 {
+                    true => format_percent(secs / total_secs * 100.0),
+                    false => "-".to_string(),
+                };
+                let table = |totals: &[(String, f64)]| {
+                    totals
+                        .iter()
+                        .map(|(label, secs)| {
+                            format!(
+                                "| {} | {} | {} |",
+                                self.markdown_text(label),
+                                format_duration(*secs),
+                                share(*secs)
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                let mut lines = vec![
+                    format!("# Categories of {}", params.bucket_id),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!(
+                        "- **Rules**: {} ({} categories)",
+                        source,
+                        categories.rules.len()
+                    ),
+                    format!("- **Total**: {}", format_duration(total_secs)),
+                    String::new(),
+                    format!("## Rolled up to depth {}", depth),
+                    String::new(),
+                    "| Category | Time | Share |".to_string(),
+                    "|---|---|---|".to_string(),
+                ];
+                lines.extend(table(&totals.rolled_up));
+                lines.extend([
+                    String::new(),
+                    "## Leaf categories".to_string(),
+                    String::new(),
+                    "| Category | Time | Share |".to_string(),
+                    "|---|---|---|".to_string(),
+                ]);
+                lines.extend(table(&totals.leaves));
+                if let Some(stored) = stored_days.filter(|stored| *stored > 0) {
+                    lines.push(String::new());
+                    lines.push(format!(
+                        "_{} days served from the aggregation store._",
+                        stored
+                    ));
+                }
+                if let Some(warning) = discrepancy_markdown(&discrepancies) {
+                    lines.push(String::new());
+                    lines.push(warning);
+                }
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => {
+                let entries = |totals: &[(String, f64)]| {
+                    totals
+                        .iter()
+                        .map(|(category, secs)| {
+                            serde_json::json!({"category": category, "duration_secs": secs})
+                        })
+                        .collect::<Vec<_>>()
+                };
+                json_text(&serde_json::json!({
+                    "bucket_id": params.bucket_id,
+                    "range": range,
+                    "rules_source": source,
+                    "depth": depth,
+                    "total_secs": total_secs,
+                    "rolled_up": entries(&totals.rolled_up),
+                    "leaves": entries(&totals.leaves),
+                    "stored_days": stored_days,
+                    "discrepancies": discrepancies,
+                }))
+            }
+        })
+    }
+
+    /// Fetch the range and list the largest sources of uncategorized time
+    async fn uncategorized(
+        &self,
+        params: &UncategorizedParams,
+        range: &TimeRange,
+    ) -> Result<String, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let (categories, source) = self.categories().await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let events = self.range_events(&params.bucket_id, range).await?;
+
+        let limit = params.limit.unwrap_or(DEFAULT_UNCATEGORIZED_LIMIT);
+        let report = uncategorized(&events, &categories, Some(range), limit);
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let share = |secs: f64, of: f64| match of > 0.0 {
+                    true => format_percent(secs / of * 100.0),
+                    false => "-".to_string(),
+                };
+                let mut lines = vec![
+                    format!("# Uncategorized Time in {}", params.bucket_id),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!(
+                        "- **Rules**: {} ({} categories)",
+                        source,
+                        categories.rules.len()
+                    ),
+                    format!(
+                        "- **Uncategorized**: {} of {} ({})",
+                        format_duration(report.uncategorized_secs),
+                        format_duration(report.total_secs),
+                        share(report.uncategorized_secs, report.total_secs)
+                    ),
+                ];
+                let sections: [(&str, &[Contributor]); 3] = [
+                    ("App", &report.apps),
+                    ("Title", &report.titles),
+                    ("Domain", &report.domains),
+                ];
+                for (name, contributors) in sections {
+                    if contributors.is_empty() {
+                        continue;
+                    }
+                    lines.extend([
+                        String::new(),
+                        format!("## {}s", name),
+                        String::new(),
+                        format!("| {} | Time | Share | Suggested rule |", name),
+                        "|---|---|---|---|".to_string(),
+                    ]);
+                    lines.extend(contributors.iter().map(|c| {
+                        format!(
+                            "| {} | {} | {} | {} |",
+                            code_span(&c.value),
+                            format_duration(c.duration_secs),
+                            share(c.duration_secs, report.uncategorized_secs),
+                            code_span(&c.suggested_rule)
+                        )
+                    }));
+                }
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": params.bucket_id,
+                "range": range,
+                "rules_source": source,
+                "total_secs": report.total_secs,
+                "uncategorized_secs": report.uncategorized_secs,
+                "apps": report.apps,
+                "titles": report.titles,
+                "domains": report.domains,
+            })),
+        })
+    }
+
+    /// Fetch the range and try the candidate rule on it
+    async fn test_rule(
+        &self,
+        params: &TestRuleParams,
+        regex: &Regex,
+        category: Option<&[String]>,
+        range: &TimeRange,
+    ) -> Result<String, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let (categories, source) = self.categories().await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let events = self.range_events(&params.bucket_id, range).await?;
+
+        let limit = params.limit.unwrap_or(DEFAULT_UNCATEGORIZED_LIMIT);
+        let test = test_rule(&events, &categories, regex, category, Some(range), limit);
+        let category = category.map(|name| name.join(CATEGORY_SEPARATOR));
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let share = |secs: f64, of: f64| match of > 0.0 {
+                    true => format_percent(secs / of * 100.0),
+                    false => "-".to_string(),
+                };
+                let mut lines = vec![
+                    format!("# Rule Test on {}", params.bucket_id),
+                    String::new(),
+                    format!("- **Regex**: {}", code_span(regex.as_str())),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!(
+                        "- **Rules**: {} ({} categories)",
+                        source,
+                        categories.rules.len()
+                    ),
+                    format!(
+                        "- **Matched**: {} of {} ({}) in {} events",
+                        format_duration(test.matched_secs),
+                        format_duration(test.total_secs),
+                        share(test.matched_secs, test.total_secs),
+                        test.matched_events
+                    ),
+                ];
+                if let (Some(category), Some(assigned)) = (&category, test.assigned_secs) {
+                    lines.push(format!(
+                        "- **Would be assigned to {}**: {} ({} of matched)",
+                        self.markdown_text(category),
+                        format_duration(assigned),
+                        share(assigned, test.matched_secs)
+                    ));
+                }
+                if !test.current.is_empty() {
+                    lines.extend([
+                        String::new(),
+                        "## Matched time by current category".to_string(),
+                        String::new(),
+                        "| Category | Time | Share |".to_string(),
+                        "|---|---|---|".to_string(),
+                    ]);
+                    lines.extend(test.current.iter().map(|(label, secs)| {
+                        format!(
+                            "| {} | {} | {} |",
+                            self.markdown_text(label),
+                            format_duration(*secs),
+                            share(*secs, test.matched_secs)
+                        )
+                    }));
+                    lines.extend([
+                        String::new(),
+                        "## Examples".to_string(),
+                        String::new(),
+                        "| Title | Time |".to_string(),
+                        "|---|---|".to_string(),
+                    ]);
+                    lines.extend(test.examples.iter().map(|(title, secs)| {
+                        format!("| {} | {} |", code_span(title), format_duration(*secs))
+                    }));
+                }
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": params.bucket_id,
+                "range": range,
+                "regex": regex.as_str(),
+                "category": category,
+                "rules_source": source,
+                "total_secs": test.total_secs,
+                "matched_secs": test.matched_secs,
+                "matched_events": test.matched_events,
+                "assigned_secs": test.assigned_secs,
+                "current_categories": test
+                    .current
+                    .iter()
+                    .map(|(category, secs)| {
+                        serde_json::json!({"category": category, "duration_secs": secs})
+                    })
+                    .collect::<Vec<_>>(),
+                "examples": test
+                    .examples
+                    .iter()
+                    .map(|(title, secs)| serde_json::json!({"title": title, "duration_secs": secs}))
+                    .collect::<Vec<_>>(),
+            })),
+        })
+    }
+
+    /// Fetch the range and classify it under both rule sets
+    async fn compare_rules(
+        &self,
+        params: &CompareRulesParams,
+        proposed: &Categories,
+        range: &TimeRange,
+    ) -> Result<String, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let (current, source) = self.categories().await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let events = self.range_events(&params.bucket_id, range).await?;
+
+        let comparison = compare_rules(&events, &current, proposed, params.depth, Some(range));
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let share = comparison.moved_secs / comparison.total_secs.max(f64::EPSILON);
+                let mut lines = vec![
+                    format!("# Rule Change Preview for {}", params.bucket_id),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!(
+                        "- **Current rules**: {} ({} categories)",
+                        source,
+                        current.rules.len()
+                    ),
+                    format!("- **Proposed rules**: {} categories", proposed.rules.len()),
+                    format!(
+                        "- **Moved**: {} of {} ({})",
+                        format_duration(comparison.moved_secs),
+                        format_duration(comparison.total_secs),
+                        format_percent(share * 100.0)
+                    ),
+                ];
+                if comparison.moves.is_empty() {
+                    lines.push(String::new());
+                    lines.push("_No time changes category._".to_string());
+                    return Ok(truncate_response(lines.join("\n")));
+                }
+
+                lines.extend([
+                    String::new(),
+                    "## Category totals".to_string(),
+                    String::new(),
+                    "| Category | Current | Proposed | Change |".to_string(),
+                    "|---|---|---|---|".to_string(),
+                ]);
+                lines.extend(
+                    comparison
+                        .categories
+                        .iter()
+                        .filter(|c| c.delta_secs() != 0.0)
+                        .map(|c| {
+                            format!(
+                                "| {} | {} | {} | {} |",
+                                self.markdown_text(&c.group),
+                                format_duration(c.before_secs),
+                                format_duration(c.after_secs),
+                                format_duration_delta(c.delta_secs())
+                            )
+                        }),
+                );
+                lines.extend([
+                    String::new(),
+                    "## Moves".to_string(),
+                    String::new(),
+                    "| From | To | Time |".to_string(),
+                    "|---|---|---|".to_string(),
+                ]);
+                lines.extend(comparison.moves.iter().map(|m| {
+                    format!(
+                        "| {} | {} | {} |",
+                        self.markdown_text(&m.from),
+                        self.markdown_text(&m.to),
+                        format_duration(m.duration_secs)
+                    )
+                }));
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": params.bucket_id,
+                "range": range,
+                "rules_source": source,
+                "depth": params.depth,
+                "total_secs": comparison.total_secs,
+                "moved_secs": comparison.moved_secs,
+                "categories": comparison.categories,
+                "moves": comparison.moves,
+            })),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    fn params<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Parameters<T> {
+        Parameters(serde_json::from_value(value).unwrap())
+    }
+
+    #[tokio::test]
+    async fn totals_categories_rolled_up_to_the_depth() {
+        let at = |minute| Utc.with_ymd_and_hms(2024, 3, 1, 9, minute, 0).unwrap();
+        let (_fake, server) = crate::tools::test_server(vec![
+            test_event(
+                at(0),
+                120.0,
+                serde_json::json!({"app": "Code", "title": "main.rs"}),
+            ),
+            test_event(
+                at(2),
+                60.0,
+                serde_json::json!({"app": "Firefox", "title": "YouTube"}),
+            ),
+            test_event(
+                at(3),
+                30.0,
+                serde_json::json!({"app": "Mail", "title": "Inbox"}),
+            ),
+        ])
+        .await;
+        let rules = r#"
+            "Work > Programming" = "Code"
+            Media = "YouTube"
+        "#;
+        let server = server.with_config(crate::config::Config {
+            category_rules: Some(Categories::parse_toml(rules).unwrap()),
+            ..Default::default()
+        });
+
+        let result = server
+            .aw_category_summary(params(serde_json::json!({
+                "bucket_id": "aw-watcher-window_test",
+                "start": "2024-03-01T09:00:00Z",
+                "end": "2024-03-01T12:00:00Z",
+                "response_format": "json",
+            })))
+            .await
+            .unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&crate::tools::result_text(&result)).unwrap();
+        let totals = |key: &str| -> Vec<(String, f64)> {
+            json[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| {
+                    let category = entry["category"].as_str().unwrap().to_string();
+                    (category, entry["duration_secs"].as_f64().unwrap())
+                })
+                .collect()
+        };
+        assert_eq!(json["total_secs"], 210.0);
+        assert_eq!(
+            totals("rolled_up")[..2],
+            [("Work".to_string(), 120.0), ("Media".to_string(), 60.0)]
+        );
+        assert_eq!(
+            totals("leaves")[0],
+            ("Work > Programming".to_string(), 120.0)
+        );
+        assert_eq!(
+            totals("rolled_up")
+                .iter()
+                .map(|(_, secs)| secs)
+                .sum::<f64>(),
+            210.0
+        );
+
+        let result = server
+            .aw_category_summary(params(serde_json::json!({
+                "bucket_id": "aw-watcher-window_test",
+                "depth": 0,
+            })))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[tokio::test]
+    async fn keeps_to_the_scope() {
+        let (_fake, server) = crate::tools::scoped_test_server(Vec::new(), Vec::new()).await;
+        let config = crate::config::Config {
+            category_rules: Some(Categories::parse_toml("Games = \"Games\"").unwrap()),
+            ..(*server.config()).clone()
+        };
+        let server = server.with_config(config);
+        for bucket_id in ["aw-watcher-window_other", "aw-watcher-window_missing"] {
+            let result = server
+                .aw_category_summary(params(serde_json::json!({
+                    "bucket_id": bucket_id,
+                    "start": "2024-03-01T09:00:00Z",
+                    "end": "2024-03-01T12:00:00Z",
+                })))
+                .await
+                .unwrap();
+            let text = crate::tools::result_text(&result);
+            assert_eq!(result.is_error, Some(true), "{}", text);
+            assert!(text.contains("There's no bucket named"), "{}", text);
+        }
+    }
+}
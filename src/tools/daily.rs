@@ -0,0 +1,708 @@
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::{
+    DailySummary, GroupHandle, TimeRange, TotalsCheck, apply_zero_duration_policy, daily_summary,
+    discrepancy_markdown, format_timestamp, parse_week,
+};
+use crate::constants::SNAPSHOT_LIST_LIMIT;
+use crate::format::{code_span, format_duration, format_percent};
+use crate::models::{Bucket, Event, ResponseFormat};
+use crate::snapshot::{DaySnapshot, SnapshotEntry};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Default number of apps and titles in the daily summary
+const DEFAULT_DAILY_TOP: usize = 5;
+
+/// Data key of the app in window events
+const APP_KEY: &str = "app";
+
+/// Data key of the window title in window events
+const TITLE_KEY: &str = "title";
+
+/// Input for the daily summary
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DailySummaryParams {
+    /// The day: "today" (default), "yesterday" or a date (YYYY-MM-DD)
+    #[serde(default)]
+    pub date: Option<String>,
+
+    /// The window watcher bucket (default: the window bucket with the most recent activity)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// The AFK watcher bucket (default: the AFK watcher on the window bucket's host)
+    #[serde(default)]
+    pub afk_bucket_id: Option<String>,
+
+    /// Number of apps and titles listed (default: 5)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the weekly summary
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WeeklySummaryParams {
+    /// The week: "this week" (default), "last week", an ISO week (e.g., "2024-W07") or a date within it (YYYY-MM-DD)
+    #[serde(default)]
+    pub week: Option<String>,
+
+    /// The window watcher bucket (default: the window bucket with the most recent activity)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// The AFK watcher bucket (default: the AFK watcher on the window bucket's host)
+    #[serde(default)]
+    pub afk_bucket_id: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = daily_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Summarize one day of activity.
+    #[tool(
+        description = r#"Summarize one day in a single response: active time (window activity while not AFK), AFK time, first and last activity, and the top apps and window titles by active time. Use this for "what did I do today / on a day" questions instead of combining several tool calls.
+
+Without an AFK watcher on the window bucket's host, all window time counts as active. Today's summary of the default buckets is served from the day snapshot while it is fresh.
+
+## Parameters
+- `date`: "today" (default), "yesterday" or a date such as "2024-03-15", in the bucket's timezone
+- `bucket_id`: The window watcher bucket (default: the one with the most recent activity)
+- `afk_bucket_id`: The AFK watcher bucket (default: the AFK watcher on the same host)
+- `top`: Number of apps and titles listed (default: 5)"#
+    )]
+    async fn aw_daily_summary(
+        &self,
+        Parameters(params): Parameters<DailySummaryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let empty = |id: &Option<String>| id.as_deref().is_some_and(|id| id.trim().is_empty());
+        if empty(&params.bucket_id) || empty(&params.afk_bucket_id) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.top == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "top must be at least 1",
+            )]));
+        }
+        let date = params.date.as_deref().map_or("today", str::trim);
+        if !matches!(date, "today" | "yesterday") && date.parse::<NaiveDate>().is_err() {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid date '{}', expected today, yesterday or YYYY-MM-DD",
+                date
+            ))]));
+        }
+
+        let tz = match &params.bucket_id {
+            Some(bucket_id) => self.bucket_timezone(bucket_id.trim()).await,
+            None => self.config().timezone.clone(),
+        };
+        let range = match self.resolve_range_in(None, None, Some(date), &tz) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        if date == "today"
+            && let Some(response) = self.snapshot_daily_summary(&params).await
+        {
+            return Ok(CallToolResult::success(vec![Content::text(response)]));
+        }
+        match self.daily_summary(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to summarize the day: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Summarize a week of activity day by day.
+    #[tool(
+        description = r#"Summarize a week (Monday to Sunday) day by day in one response: a table of each day's active time (window activity while not AFK) and top app, with the week's totals. Use this for weekly reviews instead of fetching raw events or calling aw_daily_summary seven times.
+
+Without an AFK watcher on the window bucket's host, all window time counts as active. Days after today are left out.
+
+## Parameters
+- `week`: "this week" (default), "last week", an ISO week such as "2024-W07" or a date within the week, in the bucket's timezone
+- `bucket_id`: The window watcher bucket (default: the one with the most recent activity)
+- `afk_bucket_id`: The AFK watcher bucket (default: the AFK watcher on the same host)"#
+    )]
+    async fn aw_weekly_summary(
+        &self,
+        Parameters(params): Parameters<WeeklySummaryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let empty = |id: &Option<String>| id.as_deref().is_some_and(|id| id.trim().is_empty());
+        if empty(&params.bucket_id) || empty(&params.afk_bucket_id) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let tz = match &params.bucket_id {
+            Some(bucket_id) => self.bucket_timezone(bucket_id.trim()).await,
+            None => self.config().timezone.clone(),
+        };
+        let now = self.now();
+        let monday = match parse_week(params.week.as_deref().unwrap_or("this week"), &tz, now) {
+            Ok(monday) => monday,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        // The current week ends now
+        let end = tz.start_of_day(monday + Duration::weeks(1)).min(now);
+        let range = match TimeRange::new(tz.start_of_day(monday), end) {
+            Ok(range) => range,
+            Err(_) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "The week of {} has not started yet",
+                    monday
+                ))]));
+            }
+        };
+
+        match self.weekly_summary(&params, monday, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to summarize the week: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the day's window and AFK events and summarize them
+    async fn daily_summary(
+        &self,
+        params: &DailySummaryParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let (bucket, afk_bucket) = self
+            .summary_buckets(
+                &buckets,
+                params.bucket_id.as_deref(),
+                params.afk_bucket_id.as_deref(),
+            )
+            .await?;
+        let (window_events, afk_events) = self.summary_events(bucket, afk_bucket, range).await?;
+        let top = params.top.unwrap_or(DEFAULT_DAILY_TOP);
+        let summary = daily_summary(&window_events, &afk_events, range, top);
+        Ok(self.daily_response(
+            params,
+            &bucket.id,
+            afk_bucket.map(|bucket| bucket.id.as_str()),
+            &summary,
+            range,
+            tz,
+        ))
+    }
+
+    /// Today's default summary from the day snapshot (`AW_MCP_SNAPSHOT`),
+    /// if it is no older than the snapshot interval and lists enough apps
+    async fn snapshot_daily_summary(&self, params: &DailySummaryParams) -> Option<String> {
+        let config = self.config();
+        let top = params.top.unwrap_or(DEFAULT_DAILY_TOP);
+        if params.bucket_id.is_some() || params.afk_bucket_id.is_some() || top > SNAPSHOT_LIST_LIMIT
+        {
+            return None;
+        }
+        let snapshot = DaySnapshot::load(config.snapshot_file.as_deref()?).ok()?;
+        let now = self.now();
+        let age = snapshot.age(now).to_std().ok()?;
+        if age > config.snapshot_interval || snapshot.date != config.timezone.date_of(now) {
+            return None;
+        }
+        let daily = snapshot.daily?;
+        let range = TimeRange::new(snapshot.start, snapshot.end).ok()?;
+        // The snapshot holds pseudonyms, which group IDs must not
+        let pseudonymizer = self.pseudonymizer().await;
+        let reveal = |id: &str| match &pseudonymizer {
+            Some(pseudonymizer) => pseudonymizer.reveal(id),
+            None => id.to_string(),
+        };
+        let top_entries = |entries: Vec<SnapshotEntry>| {
+            entries
+                .into_iter()
+                .take(top)
+                .map(|entry| (entry.name, entry.duration_secs))
+                .collect()
+        };
+        let summary = DailySummary {
+            active_secs: daily.active_secs,
+            afk_secs: daily.afk_secs,
+            present_secs: daily.present_secs,
+            top_apps: top_entries(daily.apps),
+            top_titles: top_entries(daily.titles),
+            first_activity: daily.first_activity,
+            last_activity: daily.last_activity,
+        };
+        crate::meta::record_cached(snapshot.generated_at);
+        Some(self.daily_response(
+            params,
+            &reveal(&daily.bucket_id),
+            daily.afk_bucket_id.as_deref().map(reveal).as_deref(),
+            &summary,
+            &range,
+            &config.timezone,
+        ))
+    }
+
+    /// Format a daily summary
+    fn daily_response(
+        &self,
+        params: &DailySummaryParams,
+        bucket_id: &str,
+        afk_bucket_id: Option<&str>,
+        summary: &DailySummary,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> String {
+        let date = tz.date_of(range.start);
+        let discrepancies = TotalsCheck {
+            afk_secs: Some(summary.afk_secs),
+            present_secs: summary.present_secs,
+            ..TotalsCheck::new(range.duration_secs(), summary.active_secs)
+        }
+        .discrepancies();
+        let group_id =
+            |key: &str, value: &str| group_handle(bucket_id, afk_bucket_id, key, value, range);
+
+        match params.response_format {
+            ResponseFormat::Markdown => {
+                let afk_bucket =
+                    afk_bucket_id.unwrap_or("none found (all window time counted as active)");
+                let mut lines = vec![
+                    format!("# Daily Summary: {}", date),
+                    String::new(),
+                    format!("- **Window bucket**: {}", bucket_id),
+                    format!("- **AFK bucket**: {}", afk_bucket),
+                    format!(
+                        "- **Active time**: {}",
+                        format_duration(summary.active_secs)
+                    ),
+                    format!("- **AFK time**: {}", format_duration(summary.afk_secs)),
+                ];
+                match (summary.first_activity, summary.last_activity) {
+                    (Some(first), Some(last)) => {
+                        let time = |t: DateTime<Utc>| tz.to_local(t).format("%H:%M").to_string();
+                        lines.push(format!("- **First activity**: {}", time(first)));
+                        lines.push(format!("- **Last activity**: {}", time(last)));
+                    }
+                    _ => lines.push("- **Activity**: none recorded".to_string()),
+                }
+                if let Some(warning) = discrepancy_markdown(&discrepancies) {
+                    lines.push(String::new());
+                    lines.push(warning);
+                }
+                for (heading, column, key, groups) in [
+                    ("Top Apps", "App", APP_KEY, &summary.top_apps),
+                    ("Top Titles", "Title", TITLE_KEY, &summary.top_titles),
+                ] {
+                    if groups.is_empty() {
+                        continue;
+                    }
+                    lines.push(String::new());
+                    lines.push(format!("## {}", heading));
+                    lines.push(String::new());
+                    lines.push(format!("| {} | Time | Share | Group ID |", column));
+                    lines.push("|---|---|---|---|".to_string());
+                    lines.extend(groups.iter().map(|(group, secs)| {
+                        format!(
+                            "| {} | {} | {} | {} |",
+                            self.markdown_text(group),
+                            format_duration(*secs),
+                            format_percent(share(summary, *secs)),
+                            code_span(&group_id(key, group))
+                        )
+                    }));
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "date": date,
+                "range": range,
+                "bucket_id": bucket_id,
+                "afk_bucket_id": afk_bucket_id,
+                "active_secs": summary.active_secs,
+                "afk_secs": summary.afk_secs,
+                "first_activity": summary.first_activity,
+                "last_activity": summary.last_activity,
+                "top_apps": named(&summary.top_apps, |app| group_id(APP_KEY, app)),
+                "top_titles": named(&summary.top_titles, |title| group_id(TITLE_KEY, title)),
+                "discrepancies": discrepancies,
+            })),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the week's window and AFK events and summarize each day
+    async fn weekly_summary(
+        &self,
+        params: &WeeklySummaryParams,
+        monday: NaiveDate,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let (bucket, afk_bucket) = self
+            .summary_buckets(
+                &buckets,
+                params.bucket_id.as_deref(),
+                params.afk_bucket_id.as_deref(),
+            )
+            .await?;
+        let (window_events, afk_events) = self.summary_events(bucket, afk_bucket, range).await?;
+        let day_range = |date: NaiveDate| {
+            TimeRange::new(
+                tz.start_of_day(date),
+                tz.start_of_day(date + Duration::days(1)).min(range.end),
+            )
+        };
+        let days: Vec<(NaiveDate, TimeRange, DailySummary)> = (0..7)
+            .map(|i| monday + Duration::days(i))
+            .filter(|date| tz.start_of_day(*date) < range.end)
+            .filter_map(|date| {
+                let day = day_range(date).ok()?;
+                Some((
+                    date,
+                    day,
+                    daily_summary(&window_events, &afk_events, &day, 1),
+                ))
+            })
+            .collect();
+        let week = daily_summary(&window_events, &afk_events, range, 1);
+        let discrepancies = TotalsCheck {
+            afk_secs: Some(week.afk_secs),
+            present_secs: week.present_secs,
+            group_secs: Some(days.iter().map(|(_, _, day)| day.active_secs).sum()),
+            ..TotalsCheck::new(range.duration_secs(), week.active_secs)
+        }
+        .discrepancies();
+        let top_app_id = |app: &str, range: &TimeRange| {
+            group_handle(
+                &bucket.id,
+                afk_bucket.map(|bucket| bucket.id.as_str()),
+                APP_KEY,
+                app,
+                range,
+            )
+        };
+        let iso_week = monday.iso_week();
+        let label = format!("{}-W{:02}", iso_week.year(), iso_week.week());
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let top_app = |summary: &DailySummary| match summary.top_apps.first() {
+                    Some((app, secs)) => {
+                        format!("{} ({})", self.markdown_text(app), format_duration(*secs))
+                    }
+                    None => "-".to_string(),
+                };
+                let afk_bucket = match afk_bucket {
+                    Some(afk_bucket) => afk_bucket.id.clone(),
+                    None => "none found (all window time counted as active)".to_string(),
+                };
+                let active_days = days
+                    .iter()
+                    .filter(|(_, _, day)| day.active_secs > 0.0)
+                    .count();
+                let mut lines = vec![
+                    format!(
+                        "# Weekly Summary: {} ({} to {})",
+                        label,
+                        monday,
+                        monday + Duration::days(6)
+                    ),
+                    String::new(),
+                    format!("- **Window bucket**: {}", bucket.id),
+                    format!("- **AFK bucket**: {}", afk_bucket),
+                    format!("- **Active time**: {}", format_duration(week.active_secs)),
+                    format!("- **AFK time**: {}", format_duration(week.afk_secs)),
+                    format!(
+                        "- **Average per active day**: {} ({} active days)",
+                        format_duration(week.active_secs / active_days.max(1) as f64),
+                        active_days
+                    ),
+                    String::new(),
+                    "| Day | Active | Top app |".to_string(),
+                    "|---|---|---|".to_string(),
+                ];
+                lines.extend(days.iter().map(|(date, _, day)| {
+                    format!(
+                        "| {} | {} | {} |",
+                        date.format("%a %Y-%m-%d"),
+                        format_duration(day.active_secs),
+                        top_app(day)
+                    )
+                }));
+                lines.push(format!(
+                    "| **Week** | {} | {} |",
+                    format_duration(week.active_secs),
+                    top_app(&week)
+                ));
+                if let Some(warning) = discrepancy_markdown(&discrepancies) {
+                    lines.push(String::new());
+                    lines.push(warning);
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "week": label,
+                "range": range,
+                "bucket_id": bucket.id,
+                "afk_bucket_id": afk_bucket.map(|bucket| &bucket.id),
+                "active_secs": week.active_secs,
+                "afk_secs": week.afk_secs,
+                "top_app": named(&week.top_apps, |app| top_app_id(app, range)).pop(),
+                "days": days
+                    .iter()
+                    .map(|(date, day_range, day)| serde_json::json!({
+                        "date": date,
+                        "weekday": date.format("%A").to_string(),
+                        "active_secs": day.active_secs,
+                        "afk_secs": day.afk_secs,
+                        "top_app": named(&day.top_apps, |app| top_app_id(app, day_range)).pop(),
+                        "first_activity": day.first_activity,
+                        "last_activity": day.last_activity,
+                    }))
+                    .collect::<Vec<_>>(),
+                "discrepancies": discrepancies,
+            })),
+        })
+    }
+
+    /// The window bucket to summarize and its AFK bucket, if any
+    pub(super) async fn summary_buckets<'a>(
+        &self,
+        buckets: &'a HashMap<String, Bucket>,
+        bucket_id: Option<&str>,
+        afk_bucket_id: Option<&str>,
+    ) -> Result<(&'a Bucket, Option<&'a Bucket>), McpError> {
+        let bucket = self.window_bucket(buckets, bucket_id).await?;
+        let afk_bucket = match afk_bucket_id.map(str::trim) {
+            Some(afk_bucket_id) => {
+                self.check_scope(afk_bucket_id).await?;
+                Some(buckets.get(afk_bucket_id).ok_or_else(|| {
+                    McpError::invalid_params(format!("Bucket {} not found", afk_bucket_id), None)
+                })?)
+            }
+            None => self.afk_bucket_of(buckets, bucket),
+        };
+        Ok((bucket, afk_bucket))
+    }
+
+    /// The window and AFK events of a range; no AFK events without an AFK
+    /// bucket
+    pub(super) async fn summary_events(
+        &self,
+        bucket: &Bucket,
+        afk_bucket: Option<&Bucket>,
+        range: &TimeRange,
+    ) -> Result<(Vec<Event>, Vec<Event>), McpError> {
+        let window_events = self.range_events(&bucket.id, range).await?;
+        let afk_events = match afk_bucket {
+            Some(afk_bucket) => self.range_events(&afk_bucket.id, range).await?,
+            None => Vec::new(),
+        };
+        Ok((window_events, afk_events))
+    }
+
+    /// The events of a bucket in a range with the configured zero-duration
+    /// policy applied, so that every aggregation counts them alike
+    pub(super) async fn range_events(
+        &self,
+        bucket_id: &str,
+        range: &TimeRange,
+    ) -> Result<Vec<Event>, McpError> {
+        let events = self
+            .client
+            .get_events(
+                bucket_id,
+                None,
+                Some(&format_timestamp(&range.start)),
+                Some(&format_timestamp(&range.end)),
+            )
+            .await?;
+        Ok(apply_zero_duration_policy(
+            events,
+            self.config().zero_duration_policy,
+        ))
+    }
+}
+
+/// A group's share of the active time, in percent
+fn share(summary: &DailySummary, secs: f64) -> f64 {
+    match summary.active_secs > 0.0 {
+        true => secs / summary.active_secs * 100.0,
+        false => 0.0,
+    }
+}
+
+/// Groups as JSON objects, with their drill-down handles
+fn named(groups: &[(String, f64)], group_id: impl Fn(&str) -> String) -> Vec<serde_json::Value> {
+    groups
+        .iter()
+        .map(|(name, secs)| {
+            serde_json::json!({ "name": name, "secs": secs, "group_id": group_id(name) })
+        })
+        .collect()
+}
+
+/// The drill-down handle of a group of a summary's window events, counted
+/// while the AFK bucket (if any) saw the user
+fn group_handle(
+    bucket_id: &str,
+    afk_bucket_id: Option<&str>,
+    key: &str,
+    value: &str,
+    range: &TimeRange,
+) -> String {
+    GroupHandle {
+        bucket_id: bucket_id.to_string(),
+        key: key.to_string(),
+        value: value.to_string(),
+        range: *range,
+        afk_bucket_id: afk_bucket_id.map(str::to_string),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::AFK_BUCKET_TYPE;
+    use crate::models::test_event;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn summarizes_active_time_while_not_afk() {
+        let at = |minute| Utc.with_ymd_and_hms(2024, 3, 1, 9, minute, 0).unwrap();
+        let (fake, server) = crate::tools::test_server(vec![
+            test_event(
+                at(0),
+                180.0,
+                serde_json::json!({"app": "Code", "title": "main.rs"}),
+            ),
+            test_event(
+                at(3),
+                60.0,
+                serde_json::json!({"app": "Firefox", "title": "Docs"}),
+            ),
+        ])
+        .await;
+        fake.add_simple_bucket("aw-watcher-afk_test", AFK_BUCKET_TYPE, "test");
+        fake.add_events(
+            "aw-watcher-afk_test",
+            vec![
+                test_event(at(0), 120.0, serde_json::json!({"status": "not-afk"})),
+                test_event(at(2), 180.0, serde_json::json!({"status": "afk"})),
+            ],
+        );
+
+        let result = server
+            .aw_daily_summary(Parameters(
+                serde_json::from_value(serde_json::json!({
+                    "date": "2024-03-01",
+                    "top": 1,
+                    "response_format": "json",
+                }))
+                .unwrap(),
+            ))
+            .await
+            .unwrap();
+        let text = crate::tools::result_text(&result);
+        let json: serde_json::Value = serde_json::from_str(&text).expect(&text);
+        assert_eq!(json["afk_bucket_id"], "aw-watcher-afk_test");
+        assert_eq!(json["active_secs"], 120.0);
+        assert_eq!(json["afk_secs"], 180.0);
+        assert_eq!(json["first_activity"], "2024-03-01T09:00:00Z");
+        assert_eq!(json["last_activity"], "2024-03-01T09:02:00Z");
+        assert_eq!(json["top_apps"].as_array().unwrap().len(), 1);
+        assert_eq!(json["top_apps"][0]["name"], "Code");
+        assert_eq!(json["top_apps"][0]["secs"], 120.0);
+    }
+
+    #[tokio::test]
+    async fn serves_today_from_a_fresh_snapshot() {
+        let (fake, server) = crate::tools::test_server(vec![test_event(
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+            60.0,
+            serde_json::json!({"app": "Code", "title": "main.rs"}),
+        )])
+        .await;
+        let path = std::env::temp_dir().join(format!("aw-mcp-daily-{}.json", std::process::id()));
+        let config = |now| crate::config::Config {
+            snapshot_file: Some(path.clone()),
+            now: Some(now),
+            ..Default::default()
+        };
+        let noon = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let server = server.with_config(config(noon));
+        server.write_snapshot(&path).await.unwrap();
+        drop(fake);
+
+        let params = || Parameters(serde_json::from_value(serde_json::json!({})).unwrap());
+        let result = server.aw_daily_summary(params()).await.unwrap();
+        let text = crate::tools::result_text(&result);
+        assert_ne!(result.is_error, Some(true), "{}", text);
+        assert!(text.contains("- **Active time**: 1m"), "{}", text);
+        assert!(text.contains("| Code |"), "{}", text);
+
+        // A stale snapshot is not served
+        let server = server.with_config(config(noon + Duration::minutes(6)));
+        let result = server.aw_daily_summary(params()).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_to_the_scope() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let (_fake, server) = crate::tools::scoped_test_server(
+            vec![test_event(at, 60.0, serde_json::json!({"app": "Code"}))],
+            vec![test_event(at, 60.0, serde_json::json!({"app": "Games"}))],
+        )
+        .await;
+        let summary = |value: serde_json::Value| {
+            let mut value = value;
+            value["date"] = "2024-03-01".into();
+            server.aw_daily_summary(Parameters(serde_json::from_value(value).unwrap()))
+        };
+
+        let result = summary(serde_json::json!({})).await.unwrap();
+        let text = crate::tools::result_text(&result);
+        assert!(text.contains("aw-watcher-window_test"), "{}", text);
+        assert!(!text.contains("Games"), "{}", text);
+        for arguments in [
+            serde_json::json!({"bucket_id": "aw-watcher-window_other"}),
+            serde_json::json!({"afk_bucket_id": "aw-watcher-window_other"}),
+        ] {
+            let result = summary(arguments).await.unwrap();
+            assert_eq!(result.is_error, Some(true));
+        }
+        let result = server
+            .aw_weekly_summary(Parameters(
+                serde_json::from_value(serde_json::json!({
+                    "week": "2024-03-01",
+                    "bucket_id": "aw-watcher-window_other",
+                }))
+                .unwrap(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+}
@@ -0,0 +1,286 @@
+use super::{ActivityWatchMcpServer, fit_response, json_response, token_budget};
+use crate::analysis::{
+    AFK_BUCKET_TYPE, Coverage, DeviceEvents, TimeRange, TokenBudget, active_intervals,
+    apply_clock_offset, apply_zero_duration_policy, merge_devices,
+};
+use crate::constants::DEFAULT_EVENTS_LIMIT;
+use crate::format::{code_span, format_duration};
+use crate::models::{DetailLevel, Event, MergeStrategy, ResponseFormat, ZeroDurationPolicy};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Input for merging events from several devices
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MergeDevicesParams {
+    /// Bucket IDs to merge, in order of preference for ties
+    pub bucket_ids: Vec<String>,
+
+    /// Maximum number of events to fetch per bucket (default: 100)
+    #[serde(default)]
+    pub limit: Option<i32>,
+
+    /// Start time (ISO 8601 format, e.g., "2024-01-01T00:00:00Z")
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format, e.g., "2024-01-01T23:59:59Z")
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// Zero-duration event handling: "keep", "drop" or "merge_into_previous" (default: server setting)
+    #[serde(default)]
+    pub zero_duration: Option<ZeroDurationPolicy>,
+
+    /// How overlapping events are resolved: "priority" (default), "browser_over_window", "longest_wins" or "split_proportional"
+    #[serde(default)]
+    pub strategy: MergeStrategy,
+
+    /// Detail level: "brief" (time per host only), "normal" (default; one line per event) or "full" (all event data)
+    #[serde(default)]
+    pub detail: Option<DetailLevel>,
+
+    /// Approximate size the response should fit in, in tokens (e.g., 1000); picks the detail level unless given
+    #[serde(default)]
+    pub max_tokens_hint: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = device_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Merge events from buckets on several devices into one timeline.
+    #[tool(
+        description = r#"Merge events from buckets on several devices (e.g., desktop window bucket and phone bucket) into one non-overlapping timeline.
+
+Per-host clock offsets from the server configuration are applied first. Overlapping time is counted once, resolved by `strategy`:
+- "priority" (default): the device whose AFK bucket shows input activity wins; otherwise the bucket listed first wins
+- "browser_over_window": browser tab events (e.g., from aw-watcher-web) win over window events of a browser app; other overlaps by priority
+- "longest_wins": the longest original event wins
+- "split_proportional": overlapping time is split evenly between the overlapping events
+
+## Parameters
+- `bucket_ids`: Buckets to merge, in order of preference
+- `limit`: Maximum events fetched per bucket (default: 100)
+- `start` / `end`: Time range in ISO 8601 format
+- `zero_duration`: How to handle zero-duration events: "keep", "drop" or "merge_into_previous"
+- `strategy`: "priority", "browser_over_window", "longest_wins" or "split_proportional"
+- `detail`: "brief" (time per host only), "normal" (default; one line per event) or "full" (every event with all its data)
+- `max_tokens_hint`: Approximate token budget of the response (e.g., 1000). Without `detail`, the detail level is chosen to fit, and JSON is compact"#
+    )]
+    async fn aw_merge_devices(
+        &self,
+        Parameters(params): Parameters<MergeDevicesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_ids.is_empty() || params.bucket_ids.iter().any(|id| id.trim().is_empty()) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "At least one non-empty bucket ID is required",
+            )]));
+        }
+        let budget = match token_budget(params.max_tokens_hint) {
+            Ok(budget) => budget,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.merge_device_events(&params, budget).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to merge devices: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch, align and merge events for the requested buckets
+    async fn merge_device_events(
+        &self,
+        params: &MergeDevicesParams,
+        budget: Option<TokenBudget>,
+    ) -> Result<String, McpError> {
+        let limit = params.limit.unwrap_or(DEFAULT_EVENTS_LIMIT);
+        let detail = params
+            .detail
+            .unwrap_or_else(|| budget.map_or_else(DetailLevel::default, TokenBudget::detail));
+        let policy = params
+            .zero_duration
+            .unwrap_or(self.config().zero_duration_policy);
+        let start = params.start.as_deref();
+        let end = params.end.as_deref();
+        let range = match (start, end) {
+            (Some(s), Some(e)) => {
+                Some(TimeRange::parse(s, e).map_err(|e| McpError::invalid_params(e, None))?)
+            }
+            _ => None,
+        };
+        let buckets = self.client.buckets().await?;
+
+        let mut devices = Vec::with_capacity(params.bucket_ids.len());
+        let mut coverage = Vec::new();
+        for bucket_id in &params.bucket_ids {
+            self.check_scope(bucket_id).await?;
+            let hostname = buckets
+                .get(bucket_id)
+                .and_then(|b| b.hostname.clone())
+                .unwrap_or_else(|| "unknown".to_string());
+            let offset = self.config().clock_offset(&hostname);
+
+            let events = self
+                .client
+                .get_events(bucket_id, Some(limit), start, end)
+                .await?;
+            let mut events = apply_zero_duration_policy(events, policy);
+            apply_clock_offset(&mut events, offset);
+
+            let afk_bucket = buckets.values().find(|b| {
+                self.in_scope(b)
+                    && b.bucket_type.as_deref() == Some(AFK_BUCKET_TYPE)
+                    && b.hostname.as_deref() == Some(hostname.as_str())
+            });
+            let active = match afk_bucket {
+                Some(afk) => {
+                    let afk_events = self.client.get_events(&afk.id, None, start, end).await?;
+                    let mut afk_events = apply_zero_duration_policy(afk_events, policy);
+                    apply_clock_offset(&mut afk_events, offset);
+                    if let Some(ref range) = range {
+                        coverage.push((
+                            hostname.clone(),
+                            Coverage::from_afk_events(&afk_events, range),
+                        ));
+                    }
+                    Some(active_intervals(&afk_events))
+                }
+                None => None,
+            };
+
+            devices.push(DeviceEvents {
+                hostname,
+                events,
+                active,
+            });
+        }
+
+        let merge = merge_devices(&devices, params.strategy);
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    "# Merged Device Timeline".to_string(),
+                    String::new(),
+                    format!(
+                        "Merged {} events from {} buckets ({:.1}s of overlap resolved by {}):",
+                        merge.events.len(),
+                        devices.len(),
+                        merge.overlap_secs,
+                        params.strategy
+                    ),
+                    String::new(),
+                ];
+
+                for device in &devices {
+                    let offset = self.config().clock_offset(&device.hostname);
+                    if offset != 0.0 {
+                        lines.push(format!(
+                            "_Clock offset of {:+.1}s applied to {}._",
+                            offset,
+                            self.markdown_text(&device.hostname)
+                        ));
+                        lines.push(String::new());
+                    }
+                }
+
+                if !coverage.is_empty() {
+                    lines.push("## Coverage".to_string());
+                    lines.push(String::new());
+                    for (hostname, host_coverage) in &coverage {
+                        lines.push(format!("### {}", self.markdown_text(hostname)));
+                        lines.push(host_coverage.to_markdown(self.config().min_coverage));
+                        lines.push(String::new());
+                    }
+                }
+
+                lines.push("## Time per Host".to_string());
+                lines.push(String::new());
+                for device in &devices {
+                    let secs: f64 = merge
+                        .events
+                        .iter()
+                        .filter(|merged| merged.hostname == device.hostname)
+                        .map(|merged| merged.event.duration.as_secs_f64())
+                        .sum();
+                    lines.push(format!(
+                        "- **{}**: {}",
+                        self.markdown_text(&device.hostname),
+                        format_duration(secs)
+                    ));
+                }
+                lines.push(String::new());
+
+                if detail.lists_events() {
+                    lines.push("## Events".to_string());
+                    lines.push(String::new());
+                    for merged in &merge.events {
+                        if detail.event_data() {
+                            lines.push(merged.event.to_markdown(self.config().escape_markdown));
+                            lines.push(format!(
+                                "- **Host**: {}",
+                                self.markdown_text(&merged.hostname)
+                            ));
+                            lines.push(String::new());
+                        } else {
+                            lines.push(format!(
+                                "- {} ({}) on {}: {}",
+                                merged.event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                                format_duration(merged.event.duration.as_secs_f64()),
+                                self.markdown_text(&merged.hostname),
+                                self.event_label(&merged.event)
+                            ));
+                        }
+                    }
+                }
+
+                fit_response(lines.join("\n"), budget)
+            }
+            ResponseFormat::Json => json_response(
+                &serde_json::json!({
+                    "overlap_secs": merge.overlap_secs,
+                    "strategy": params.strategy,
+                    "coverage": coverage
+                        .iter()
+                        .map(|(hostname, c)| {
+                            serde_json::json!({
+                                "hostname": hostname,
+                                "fraction": c.fraction,
+                                "covered_secs": c.covered_secs,
+                                "low": c.is_low(self.config().min_coverage),
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                    "events": merge.events,
+                }),
+                budget,
+            ),
+        })
+    }
+
+    /// One-line label of an event: its app (or URL) and title
+    fn event_label(&self, event: &Event) -> String {
+        let field = |key: &str| event.data.get(key).and_then(|v| v.as_str());
+        let title = field("title").map(|title| {
+            if self.config().escape_markdown {
+                code_span(title)
+            } else {
+                title.to_string()
+            }
+        });
+        match (field("app").or_else(|| field("url")), title) {
+            (Some(app), Some(title)) => format!("{} {}", self.markdown_text(app), title),
+            (Some(app), None) => self.markdown_text(app),
+            (None, Some(title)) => title,
+            (None, None) => "_no app or title_".to_string(),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::{GroupHandle, format_timestamp};
+use crate::constants::DEFAULT_EVENTS_LIMIT;
+use crate::models::ResponseFormat;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Input for the drill-down into a summary group
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DrilldownParams {
+    /// A `group_id` from a summary (e.g., aw_top_apps), passed unchanged
+    pub group_id: String,
+
+    /// Maximum events to return (default: 100)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = drilldown_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// List the events behind one group of a summary.
+    #[tool(
+        description = r#"List the events behind one group of a summary, such as one app's time in aw_top_apps. Summaries (aw_top_apps, aw_active_time, aw_daily_summary) give each group a `group_id`; pass it here unchanged to get exactly the events that make up that group's total, in the same range and, where the summary counted only time while not AFK, clipped to that time. Event durations add up to the group's total.
+
+## Parameters
+- `group_id`: The group's `group_id` from the summary
+- `limit`: Maximum events to return (default: 100), oldest first"#
+    )]
+    async fn aw_drilldown(
+        &self,
+        Parameters(params): Parameters<DrilldownParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let handle: GroupHandle = match params.group_id.parse() {
+            Ok(handle) => handle,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        if params.limit == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "limit must be at least 1",
+            )]));
+        }
+
+        match self.drilldown(&params, &handle).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to drill down: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the group's bucket (and AFK bucket) over its range and keep the
+    /// group's events
+    async fn drilldown(
+        &self,
+        params: &DrilldownParams,
+        handle: &GroupHandle,
+    ) -> Result<String, McpError> {
+        self.check_scope(&handle.bucket_id).await?;
+        let start = format_timestamp(&handle.range.start);
+        let end = format_timestamp(&handle.range.end);
+        let events = self.range_events(&handle.bucket_id, &handle.range).await?;
+        let afk_events = match &handle.afk_bucket_id {
+            Some(afk_bucket_id) => {
+                self.check_scope(afk_bucket_id).await?;
+                Some(self.range_events(afk_bucket_id, &handle.range).await?)
+            }
+            None => None,
+        };
+        let events = handle.events(&events, afk_events.as_deref());
+        let limit = params.limit.unwrap_or(DEFAULT_EVENTS_LIMIT as usize);
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!(
+                        "# Drill-down: {} = {}",
+                        self.markdown_text(&handle.key),
+                        self.markdown_text(&handle.value)
+                    ),
+                    String::new(),
+                    format!("- **Bucket**: {}", handle.bucket_id),
+                    format!("- **Range**: {} to {}", start, end),
+                ];
+                if let Some(afk_bucket_id) = &handle.afk_bucket_id {
+                    lines.push(format!(
+                        "- **Counted**: while {} saw the user",
+                        afk_bucket_id
+                    ));
+                }
+                lines.push(String::new());
+                lines.extend(self.event_lines(&events, limit));
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "group_id": handle.to_string(),
+                "bucket_id": handle.bucket_id,
+                "key": handle.key,
+                "value": handle.value,
+                "range": handle.range,
+                "afk_bucket_id": handle.afk_bucket_id,
+                "total_secs": events.iter().map(|e| e.duration.as_secs_f64()).sum::<f64>(),
+                "event_count": events.len(),
+                "events": events.iter().take(limit).collect::<Vec<_>>(),
+            })),
+        })
+    }
+}
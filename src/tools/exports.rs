@@ -0,0 +1,388 @@
+use super::{ActivityWatchMcpServer, json_text};
+use crate::constants::CHARACTER_LIMIT;
+use crate::format::code_span;
+use crate::models::{Bucket, ResponseFormat};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Input for exporting buckets
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportBucketParams {
+    /// The bucket to export; all buckets when omitted
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// File to write the export to (e.g., "/home/me/aw-backup.json"); without it, the export is returned in the response
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Replace the file at `path` if it exists (default: false)
+    #[serde(default)]
+    pub overwrite: bool,
+
+    /// Output format of the summary when writing to a file: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for importing buckets
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportBucketParams {
+    /// Export file to import (as written by aw_export_bucket or aw-webui)
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// The export itself, {"buckets": {"<id>": {..., "events": [...]}}}, instead of a file
+    #[serde(default)]
+    pub export: Option<serde_json::Value>,
+
+    /// Import only these buckets of the export (default: all)
+    #[serde(default)]
+    pub bucket_ids: Option<Vec<String>>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// One bucket of an export
+#[derive(Debug, Clone, Serialize)]
+struct ExportedBucket {
+    id: String,
+    bucket_type: Option<String>,
+    hostname: Option<String>,
+    events: usize,
+}
+
+#[tool_router(router = export_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Export buckets with all their events.
+    #[tool(
+        description = r#"Export an ActivityWatch bucket with all its events, or all buckets, in aw-server's export format, e.g. to back up data or move it to another machine with aw_import_bucket (or aw-webui's import).
+
+With `path`, the export is written to that file as aw-server returned it and the response summarizes it; an existing file is only replaced with `overwrite: true`. Without `path`, the export is returned in the response, which only works for small buckets and is subject to the server's output filters (e.g. secret scrubbing), so prefer a file for backups.
+
+## Parameters
+- `bucket_id`: The bucket to export (default: all buckets)
+- `path`: File to write the export to
+- `overwrite`: Replace an existing file (default: false)"#
+    )]
+    async fn aw_export_bucket(
+        &self,
+        Parameters(params): Parameters<ExportBucketParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params
+            .bucket_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params
+            .path
+            .as_deref()
+            .is_some_and(|path| path.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "path cannot be empty",
+            )]));
+        }
+        if params.path.is_some()
+            && let Err(e) = self.check_host_path("path")
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+
+        match self.export_bucket(&params).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to export: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Import exported buckets.
+    #[tool(
+        description = r#"Import buckets with their events from an export in aw-server's format (as written by aw_export_bucket or aw-webui), e.g. to restore a backup or merge data from another machine. Buckets that already exist are not touched: the import is refused, so delete or leave out such buckets first (`bucket_ids`). Only available when writes are enabled (AW_MCP_ALLOW_WRITES).
+
+## Parameters
+- `path`: Export file to import
+- `export`: The export itself, instead of `path`
+- `bucket_ids`: Import only these buckets of the export (default: all)"#
+    )]
+    async fn aw_import_bucket(
+        &self,
+        Parameters(params): Parameters<ImportBucketParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = self.check_writes() {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params.path.is_some()
+            && let Err(e) = self.check_host_path("path")
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        let export = match (&params.path, &params.export) {
+            (Some(path), None) => match read_export(Path::new(path.trim())) {
+                Ok(export) => export,
+                Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+            },
+            (None, Some(export)) => export.clone(),
+            _ => {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    "Pass either path or export",
+                )]));
+            }
+        };
+        let export = match select_buckets(export, params.bucket_ids.as_deref()) {
+            Ok(export) => export,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.import_buckets(&params, export).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to import: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the export of a bucket, or of all buckets, leaving out buckets
+    /// outside the access scope
+    pub(super) async fn scoped_export(
+        &self,
+        bucket_id: Option<&str>,
+    ) -> Result<serde_json::Value, McpError> {
+        if let Some(id) = bucket_id {
+            self.check_scope(id).await?;
+        }
+        let mut export = self.client.export(bucket_id).await?;
+        if let Some(buckets) = export.get_mut("buckets").and_then(|b| b.as_object_mut()) {
+            buckets.retain(|_, bucket| exported_bucket(bucket).is_some_and(|b| self.in_scope(&b)));
+        }
+        Ok(export)
+    }
+
+    /// Fetch the export and write or return it
+    async fn export_bucket(&self, params: &ExportBucketParams) -> Result<String, McpError> {
+        let export = self
+            .scoped_export(params.bucket_id.as_deref().map(str::trim))
+            .await?;
+        let buckets = summarize(&export);
+
+        let Some(path) = &params.path else {
+            let text = serde_json::to_string_pretty(&export)
+                .unwrap_or_else(|_| "Error formatting JSON".to_string());
+            if text.len() > CHARACTER_LIMIT {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "The export is {} characters, too large for a response; pass path to \
+                         write it to a file",
+                        text.len()
+                    ),
+                    None,
+                ));
+            }
+            return Ok(text);
+        };
+        let path = PathBuf::from(path.trim());
+        let bytes = write_export(&path, &export, params.overwrite)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![format!(
+                    "# Exported {} Bucket(s) to {}",
+                    buckets.len(),
+                    code_span(&path.display().to_string())
+                )];
+                lines.push(String::new());
+                lines.push(format!("- **Size**: {} bytes", bytes));
+                lines.push(String::new());
+                lines.extend(self.bucket_lines(&buckets));
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "path": path,
+                "bytes": bytes,
+                "buckets": buckets,
+            })),
+        })
+    }
+
+    /// Check the buckets are in scope and new, and import them
+    async fn import_buckets(
+        &self,
+        params: &ImportBucketParams,
+        export: serde_json::Value,
+    ) -> Result<String, McpError> {
+        let existing = self.lookup_buckets().await?;
+        let empty = serde_json::Map::new();
+        let exported = export["buckets"].as_object().unwrap_or(&empty);
+        for (id, bucket) in exported {
+            let Some(bucket) = exported_bucket(bucket) else {
+                return Err(McpError::invalid_params(
+                    format!("Bucket {} of the export is invalid", id),
+                    None,
+                ));
+            };
+            if !self.in_scope(&bucket) {
+                return Err(McpError::invalid_params(
+                    format!("Bucket {} is outside the access scope", id),
+                    None,
+                ));
+            }
+            if existing.contains_key(id) {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "Bucket {} already exists; delete it first or leave it out with \
+                         bucket_ids",
+                        id
+                    ),
+                    None,
+                ));
+            }
+        }
+        let buckets = summarize(&export);
+        self.client.import(&export).await?;
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![format!("# Imported {} Bucket(s)", buckets.len())];
+                lines.push(String::new());
+                lines.extend(self.bucket_lines(&buckets));
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "imported": buckets,
+            })),
+        })
+    }
+
+    /// One markdown line per exported bucket
+    fn bucket_lines(&self, buckets: &[ExportedBucket]) -> Vec<String> {
+        buckets
+            .iter()
+            .map(|bucket| {
+                format!(
+                    "- {}: {} events ({} on {})",
+                    code_span(&bucket.id),
+                    bucket.events,
+                    code_span(bucket.bucket_type.as_deref().unwrap_or("unknown type")),
+                    self.markdown_text(bucket.hostname.as_deref().unwrap_or("unknown host"))
+                )
+            })
+            .collect()
+    }
+}
+
+/// The bucket metadata of an exported bucket, without its events
+fn exported_bucket(bucket: &serde_json::Value) -> Option<Bucket> {
+    let fields: serde_json::Map<String, serde_json::Value> = bucket
+        .as_object()?
+        .iter()
+        .filter(|(key, _)| *key != "events")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    serde_json::from_value(fields.into()).ok()
+}
+
+/// The buckets of an export with their event counts, by ID
+fn summarize(export: &serde_json::Value) -> Vec<ExportedBucket> {
+    let mut buckets: Vec<ExportedBucket> = export["buckets"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(id, bucket)| {
+            let field = |key: &str| bucket[key].as_str().map(str::to_string);
+            ExportedBucket {
+                id: id.clone(),
+                bucket_type: field("type"),
+                hostname: field("hostname"),
+                events: bucket["events"].as_array().map_or(0, Vec::len),
+            }
+        })
+        .collect();
+    buckets.sort_by(|a, b| a.id.cmp(&b.id));
+    buckets
+}
+
+/// Read an export file
+fn read_export(path: &Path) -> Result<serde_json::Value, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Invalid export {}: {}", path.display(), e))
+}
+
+/// Check an export has buckets and keep only the selected ones
+fn select_buckets(
+    mut export: serde_json::Value,
+    bucket_ids: Option<&[String]>,
+) -> Result<serde_json::Value, String> {
+    let Some(buckets) = export.get_mut("buckets").and_then(|b| b.as_object_mut()) else {
+        return Err(
+            "The export has no buckets; expected {\"buckets\": {\"<id>\": {...}}}".to_string(),
+        );
+    };
+    if let Some(ids) = bucket_ids {
+        if let Some(missing) = ids.iter().find(|id| !buckets.contains_key(id.as_str())) {
+            return Err(format!("The export has no bucket {}", missing));
+        }
+        buckets.retain(|id, _| ids.contains(id));
+    }
+    if buckets.is_empty() {
+        return Err("The export has no buckets to import".to_string());
+    }
+    Ok(export)
+}
+
+/// Write an export, replacing the file atomically; returns its size
+pub(super) fn write_export(
+    path: &Path,
+    export: &serde_json::Value,
+    overwrite: bool,
+) -> Result<usize, String> {
+    if !overwrite && path.exists() {
+        return Err(format!(
+            "{} already exists; pass overwrite: true to replace it",
+            path.display()
+        ));
+    }
+    let json = serde_json::to_string(export).map_err(|e| e.to_string())?;
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, &json).map_err(|e| format!("Cannot write {}: {}", temp.display(), e))?;
+    std::fs::rename(&temp, path).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(json.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn selects_buckets_of_exports() {
+        let export = json!({"buckets": {
+            "a": {"id": "a", "type": "currentwindow", "hostname": "h", "events": [{}, {}]},
+            "b": {"id": "b", "type": "afkstatus", "hostname": "h", "events": []},
+        }});
+        let summary = summarize(&export);
+        assert_eq!(summary.len(), 2);
+        assert_eq!((summary[0].id.as_str(), summary[0].events), ("a", 2));
+        assert_eq!(exported_bucket(&export["buckets"]["a"]).unwrap().id, "a");
+
+        let selected = select_buckets(export.clone(), Some(&["b".to_string()])).unwrap();
+        assert_eq!(summarize(&selected)[0].id, "b");
+        assert!(select_buckets(export, Some(&["c".to_string()])).is_err());
+        assert!(select_buckets(json!({"buckets": {}}), None).is_err());
+        assert!(select_buckets(json!([]), None).is_err());
+    }
+}
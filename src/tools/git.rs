@@ -0,0 +1,254 @@
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::{TimeRange, WINDOW_BUCKET_TYPE, format_timestamp, merge_intervals};
+use crate::format::format_duration;
+use crate::git::{correlate_commits, read_commits};
+use crate::models::ResponseFormat;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Input for estimating time per commit
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CommitTimeParams {
+    /// Path of a local git repository (e.g., "/home/me/src/aw-mcp-rs")
+    pub repo_path: String,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Window bucket with the editor activity (default: all window buckets)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// Text window titles must contain to count as work on the repository (case-insensitive; default: the repository's directory name)
+    #[serde(default)]
+    pub match_text: Option<String>,
+
+    /// Only commits by this author (a git author pattern, e.g. a name or email)
+    #[serde(default)]
+    pub author: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = git_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Estimate time per commit and branch of a local git repository.
+    #[tool(
+        description = r#"Estimate how long commits and branches of a local git repository took ("how long did this feature take"), by correlating commit times with window activity.
+
+Window events whose title contains `match_text` (by default the repository's directory name, which editors and terminals usually show) count as work on the repository. Each commit is credited with that activity since the previous commit in the range (or the start of the range); activity after the last commit is reported as uncommitted. Commits of all branches are read with `git log` (merges excluded) and grouped by the branch git reached them from.
+
+## Parameters
+- `repo_path`: Path of a local git repository
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07" or "yesterday"
+- `bucket_id`: Window bucket with the editor activity (default: all window buckets, overlaps counted once)
+- `match_text`: Text window titles must contain (case-insensitive)
+- `author`: Only commits by this author"#
+    )]
+    async fn aw_commit_time(
+        &self,
+        Parameters(params): Parameters<CommitTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.repo_path.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Repository path cannot be empty",
+            )]));
+        }
+        if let Err(e) = self.check_host_path("repo_path") {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params
+            .match_text
+            .as_deref()
+            .is_some_and(|text| text.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "match_text cannot be empty",
+            )]));
+        }
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.commit_time(&params, &range).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to estimate time per commit: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Read the commits, fetch the matching window activity and correlate
+    /// them
+    async fn commit_time(
+        &self,
+        params: &CommitTimeParams,
+        range: &TimeRange,
+    ) -> Result<String, McpError> {
+        let repo = Path::new(params.repo_path.trim());
+        let match_text = match &params.match_text {
+            Some(text) => text.trim().to_string(),
+            None => repo
+                .canonicalize()
+                .ok()
+                .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .ok_or_else(|| {
+                    McpError::invalid_params(
+                        format!("Repository {} not found", repo.display()),
+                        None,
+                    )
+                })?,
+        };
+        let commits = read_commits(repo, range, params.author.as_deref())
+            .await
+            .map_err(|e| McpError::invalid_params(e, None))?;
+
+        let bucket_ids: Vec<String> = match &params.bucket_id {
+            Some(bucket_id) => {
+                self.check_scope(bucket_id).await?;
+                vec![bucket_id.clone()]
+            }
+            None => {
+                let buckets = self.lookup_buckets().await?;
+                let mut ids: Vec<String> = buckets
+                    .values()
+                    .filter(|b| {
+                        self.in_scope(b) && b.bucket_type.as_deref() == Some(WINDOW_BUCKET_TYPE)
+                    })
+                    .map(|b| b.id.clone())
+                    .collect();
+                ids.sort();
+                ids
+            }
+        };
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let needle = match_text.to_lowercase();
+        let mut intervals = Vec::new();
+        for bucket_id in &bucket_ids {
+            let events = self.range_events(bucket_id, range).await?;
+            intervals.extend(
+                events
+                    .iter()
+                    .filter(|event| {
+                        event
+                            .data
+                            .get("title")
+                            .and_then(|v| v.as_str())
+                            .is_some_and(|title| title.to_lowercase().contains(&needle))
+                    })
+                    .map(|event| (event.timestamp, event.end())),
+            );
+        }
+        let activity = merge_intervals(intervals);
+        let correlation = correlate_commits(&commits, &activity, range);
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!(
+                        "# Time per Commit in {}",
+                        self.markdown_text(&repo.display().to_string())
+                    ),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!(
+                        "- **Activity**: window titles containing \"{}\" in {}",
+                        self.markdown_text(&match_text),
+                        match bucket_ids.is_empty() {
+                            true => "no window buckets".to_string(),
+                            false => bucket_ids.join(", "),
+                        }
+                    ),
+                    format!(
+                        "- **Matching activity**: {}",
+                        format_duration(correlation.total_secs)
+                    ),
+                    format!("- **Commits**: {}", correlation.commits.len()),
+                    format!(
+                        "- **After the last commit**: {}",
+                        format_duration(correlation.uncommitted_secs)
+                    ),
+                ];
+                if !correlation.branches.is_empty() {
+                    let mut branches: Vec<_> = correlation.branches.iter().collect();
+                    branches.sort_by(|a, b| b.1.0.total_cmp(&a.1.0));
+                    lines.extend([
+                        String::new(),
+                        "## Branches".to_string(),
+                        String::new(),
+                        "| Branch | Commits | Time |".to_string(),
+                        "|---|---|---|".to_string(),
+                    ]);
+                    lines.extend(branches.iter().map(|(branch, (secs, count))| {
+                        format!(
+                            "| {} | {} | {} |",
+                            self.markdown_text(branch),
+                            count,
+                            format_duration(*secs)
+                        )
+                    }));
+
+                    lines.extend([
+                        String::new(),
+                        "## Commits".to_string(),
+                        String::new(),
+                        "| Committed | Commit | Branch | Subject | Time |".to_string(),
+                        "|---|---|---|---|---|".to_string(),
+                    ]);
+                    let tz = &self.config().timezone;
+                    lines.extend(correlation.commits.iter().map(|c| {
+                        format!(
+                            "| {} | {} | {} | {} | {} |",
+                            tz.to_local(c.commit.time).format("%Y-%m-%d %H:%M"),
+                            c.commit.short_hash(),
+                            self.markdown_text(c.commit.branch.as_deref().unwrap_or("-")),
+                            self.markdown_text(&c.commit.subject),
+                            format_duration(c.secs)
+                        )
+                    }));
+                }
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "range": range,
+                "repo_path": repo.display().to_string(),
+                "match_text": match_text,
+                "bucket_ids": bucket_ids,
+                "total_secs": correlation.total_secs,
+                "uncommitted_secs": correlation.uncommitted_secs,
+                "branches": correlation
+                    .branches
+                    .iter()
+                    .map(|(branch, (secs, commits))| {
+                        serde_json::json!({"branch": branch, "commits": commits, "secs": secs})
+                    })
+                    .collect::<Vec<_>>(),
+                "commits": correlation.commits,
+            })),
+        })
+    }
+}
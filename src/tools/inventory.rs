@@ -0,0 +1,561 @@
+use super::exports::write_export;
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::apply_zero_duration_policy;
+use crate::constants::{CHARACTER_LIMIT, DEFAULT_INVENTORY_SAMPLE_EVENTS};
+use crate::format::code_span;
+use crate::models::{Bucket, Event, ResponseFormat, parse_server_timestamp};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Utc};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// File name of the inventory in an export bundle
+const INVENTORY_FILE: &str = "inventory.json";
+
+/// File name of the bucket export in an export bundle
+const EXPORT_FILE: &str = "export.json";
+
+/// Input for the data inventory
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DataInventoryParams {
+    /// Directory to write an export bundle to (the inventory as inventory.json and all buckets in scope as export.json); created if missing
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Replace bundle files that already exist (default: false)
+    #[serde(default)]
+    pub overwrite: bool,
+
+    /// Newest events per bucket read to find the data keys (default: 500)
+    #[serde(default)]
+    pub sample: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Everything the server can access about the user
+#[derive(Debug, Serialize)]
+struct Inventory {
+    generated_at: DateTime<Utc>,
+    api_url: String,
+    server_hostname: Option<String>,
+    server_version: Option<String>,
+    devices: Vec<DeviceInventory>,
+    buckets: Vec<BucketInventory>,
+    /// Keys of the settings stored on aw-server; `None` when they cannot be
+    /// read
+    settings: Option<Vec<String>>,
+    /// Data the MCP server itself keeps on disk
+    local_storage: Vec<LocalStorage>,
+    do_not_track: Vec<String>,
+}
+
+/// One bucket of the inventory
+#[derive(Debug, Serialize)]
+struct BucketInventory {
+    id: String,
+    bucket_type: Option<String>,
+    client: Option<String>,
+    hostname: Option<String>,
+    first_seen: Option<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+    events: i64,
+    /// Keys of the event data, from the sampled events
+    data_keys: Vec<String>,
+    sampled_events: usize,
+}
+
+/// The buckets of one device
+#[derive(Debug, Serialize)]
+struct DeviceInventory {
+    hostname: String,
+    buckets: Vec<String>,
+    first_seen: Option<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+    events: i64,
+}
+
+/// A file or directory where the MCP server keeps data
+#[derive(Debug, Serialize)]
+struct LocalStorage {
+    kind: &'static str,
+    path: PathBuf,
+}
+
+/// Files of a written export bundle
+#[derive(Debug, Serialize)]
+struct Bundle {
+    inventory: PathBuf,
+    export: PathBuf,
+    export_bytes: usize,
+}
+
+#[tool_router(router = inventory_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Report everything the server can access about the user.
+    #[tool(
+        description = r#"Produce an inventory of everything this MCP server can access about the user: every bucket in scope with its device, type, date range, event count and the keys of its event data, the devices, the settings stored on aw-server, the data this server keeps on disk (caches, aggregates, snapshots) and the configured do-not-track time. Use it for data-portability requests and personal audits.
+
+With `path`, an export bundle is also written to that directory: the inventory as inventory.json and all buckets in scope with their events as export.json (aw-server's export format, importable with aw_import_bucket). Existing files are only replaced with `overwrite: true`.
+
+## Parameters
+- `path`: Directory to write the export bundle to
+- `overwrite`: Replace existing bundle files (default: false)
+- `sample`: Newest events per bucket read to find the data keys (default: 500)"#
+    )]
+    async fn aw_data_inventory(
+        &self,
+        Parameters(params): Parameters<DataInventoryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params
+            .path
+            .as_deref()
+            .is_some_and(|path| path.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "path cannot be empty",
+            )]));
+        }
+        if params.path.is_some()
+            && let Err(e) = self.check_host_path("path")
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params.sample == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "sample must be at least 1",
+            )]));
+        }
+
+        match self.data_inventory(&params).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to build the data inventory: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Build the inventory, write the bundle if asked, and format the result
+    async fn data_inventory(&self, params: &DataInventoryParams) -> Result<String, McpError> {
+        let inventory = self.inventory(params.sample).await?;
+        let bundle = match &params.path {
+            Some(path) => Some(
+                self.write_bundle(Path::new(path.trim()), &inventory, params)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let tz = &self.config().timezone;
+                let mut lines = inventory_markdown(&inventory, tz, |text| self.markdown_text(text));
+                if let Some(bundle) = &bundle {
+                    lines.push(String::new());
+                    lines.push("## Export Bundle".to_string());
+                    lines.push(String::new());
+                    lines.push(format!(
+                        "- **Inventory**: {}",
+                        code_span(&bundle.inventory.display().to_string())
+                    ));
+                    lines.push(format!(
+                        "- **Export**: {} ({} bytes)",
+                        code_span(&bundle.export.display().to_string()),
+                        bundle.export_bytes
+                    ));
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => {
+                let text = json_text(&serde_json::json!({
+                    "inventory": inventory,
+                    "bundle": bundle,
+                }));
+                if text.len() > CHARACTER_LIMIT {
+                    return Err(McpError::invalid_params(
+                        format!(
+                            "The inventory is {} characters, too large for a response; pass \
+                             path to write it to a file",
+                            text.len()
+                        ),
+                        None,
+                    ));
+                }
+                text
+            }
+        })
+    }
+
+    /// Collect the inventory of the buckets in scope
+    async fn inventory(&self, sample: Option<usize>) -> Result<Inventory, McpError> {
+        let config = self.config();
+        let sample = sample.unwrap_or(DEFAULT_INVENTORY_SAMPLE_EVENTS);
+        let info = self.client.get_info().await?;
+        let all = self.client.buckets().await?;
+        let mut in_scope: Vec<&Bucket> = all.values().filter(|b| self.in_scope(b)).collect();
+        in_scope.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut buckets = Vec::with_capacity(in_scope.len());
+        for bucket in in_scope {
+            let events = self.client.get_event_count(&bucket.id, None, None).await?;
+            let limit = Some(sample.min(i32::MAX as usize) as i32);
+            let sampled = self
+                .client
+                .get_events(&bucket.id, limit, None, None)
+                .await?;
+            let sampled = apply_zero_duration_policy(sampled, config.zero_duration_policy);
+            buckets.push(bucket_inventory(bucket, events, &sampled));
+        }
+        let settings = match self.client.get_settings().await {
+            Ok(serde_json::Value::Object(settings)) => Some(settings.keys().cloned().collect()),
+            _ => None,
+        };
+
+        let mut local_storage = Vec::new();
+        let mut stored = |kind, path: &Option<PathBuf>| {
+            if let Some(path) = path {
+                local_storage.push(LocalStorage {
+                    kind,
+                    path: path.clone(),
+                });
+            }
+        };
+        stored("offline cache", &config.cache_dir);
+        stored("synced exports", &config.sync_dir);
+        stored("aggregates", &config.aggregate_dir);
+        stored("day snapshot", &config.snapshot_file);
+        stored("category rules", &config.categories_file);
+
+        Ok(Inventory {
+            generated_at: self.now(),
+            api_url: self.client.api_url(),
+            server_hostname: info.hostname,
+            server_version: info.version,
+            devices: devices(&buckets),
+            buckets,
+            settings,
+            local_storage,
+            do_not_track: config
+                .do_not_track
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        })
+    }
+
+    /// Write the inventory and the export of the buckets in scope to a
+    /// directory
+    async fn write_bundle(
+        &self,
+        dir: &Path,
+        inventory: &Inventory,
+        params: &DataInventoryParams,
+    ) -> Result<Bundle, McpError> {
+        let invalid = |e: String| McpError::invalid_params(e, None);
+        let (inventory_path, export_path) = (dir.join(INVENTORY_FILE), dir.join(EXPORT_FILE));
+        if !params.overwrite
+            && let Some(existing) = [&inventory_path, &export_path]
+                .into_iter()
+                .find(|p| p.exists())
+        {
+            return Err(invalid(format!(
+                "{} already exists; pass overwrite: true to replace it",
+                existing.display()
+            )));
+        }
+        std::fs::create_dir_all(dir)
+            .map_err(|e| invalid(format!("Cannot create {}: {}", dir.display(), e)))?;
+
+        let export = self.scoped_export(None).await?;
+        let export_bytes = write_export(&export_path, &export, true).map_err(invalid)?;
+        let inventory = serde_json::to_value(inventory).map_err(|e| {
+            McpError::internal_error(format!("Failed to serialize the inventory: {}", e), None)
+        })?;
+        write_export(&inventory_path, &inventory, true).map_err(invalid)?;
+        Ok(Bundle {
+            inventory: inventory_path,
+            export: export_path,
+            export_bytes,
+        })
+    }
+}
+
+/// Inventory of a bucket from its metadata, event count and newest events.
+///
+/// The date range comes from the events when all of them were sampled, and
+/// from the bucket metadata otherwise.
+fn bucket_inventory(bucket: &Bucket, events: i64, sampled: &[Event]) -> BucketInventory {
+    let data_keys: BTreeSet<&String> = sampled.iter().flat_map(|event| event.data.keys()).collect();
+    let complete = sampled.len() as i64 >= events;
+    let first_seen = match sampled.iter().map(|event| event.timestamp).min() {
+        Some(first) if complete => Some(first),
+        _ => bucket
+            .extra
+            .get("metadata")
+            .and_then(|metadata| metadata.get("start")?.as_str())
+            .and_then(parse_server_timestamp)
+            .or(bucket.created),
+    };
+    let last_seen = match sampled.iter().map(Event::end).max() {
+        Some(last) => Some(last),
+        None => bucket.last_activity(),
+    };
+    BucketInventory {
+        id: bucket.id.clone(),
+        bucket_type: bucket.bucket_type.clone(),
+        client: bucket.client.clone(),
+        hostname: bucket.hostname.clone(),
+        first_seen,
+        last_seen,
+        events,
+        data_keys: data_keys.into_iter().cloned().collect(),
+        sampled_events: sampled.len(),
+    }
+}
+
+/// The buckets grouped by hostname
+fn devices(buckets: &[BucketInventory]) -> Vec<DeviceInventory> {
+    let mut devices: BTreeMap<String, DeviceInventory> = BTreeMap::new();
+    for bucket in buckets {
+        let hostname = bucket
+            .hostname
+            .clone()
+            .unwrap_or_else(|| "(unknown)".to_string());
+        let device = devices
+            .entry(hostname.clone())
+            .or_insert_with(|| DeviceInventory {
+                hostname,
+                buckets: Vec::new(),
+                first_seen: None,
+                last_seen: None,
+                events: 0,
+            });
+        device.buckets.push(bucket.id.clone());
+        device.events += bucket.events;
+        device.first_seen = match (device.first_seen, bucket.first_seen) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        device.last_seen = device.last_seen.max(bucket.last_seen);
+    }
+    devices.into_values().collect()
+}
+
+/// The inventory as markdown lines, with server-provided text passed
+/// through `text`
+fn inventory_markdown(
+    inventory: &Inventory,
+    tz: &Timezone,
+    text: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let day = |time: Option<DateTime<Utc>>| match time {
+        Some(time) => tz.to_local(time).format("%Y-%m-%d").to_string(),
+        None => "?".to_string(),
+    };
+    let events: i64 = inventory.buckets.iter().map(|bucket| bucket.events).sum();
+    let mut lines = vec!["# Data Inventory".to_string(), String::new()];
+    lines.push(format!(
+        "- **Server**: {} ({} on {})",
+        code_span(&inventory.api_url),
+        text(
+            inventory
+                .server_version
+                .as_deref()
+                .unwrap_or("unknown version")
+        ),
+        text(
+            inventory
+                .server_hostname
+                .as_deref()
+                .unwrap_or("unknown host")
+        )
+    ));
+    lines.push(format!(
+        "- **Generated**: {}",
+        tz.to_local(inventory.generated_at).format("%Y-%m-%d %H:%M")
+    ));
+    lines.push(format!(
+        "- **Data**: {} events in {} bucket(s) on {} device(s)",
+        events,
+        inventory.buckets.len(),
+        inventory.devices.len()
+    ));
+
+    lines.push(String::new());
+    lines.push("## Devices".to_string());
+    lines.push(String::new());
+    for device in &inventory.devices {
+        lines.push(format!(
+            "- **{}**: {} bucket(s), {} events, {} to {}",
+            text(&device.hostname),
+            device.buckets.len(),
+            device.events,
+            day(device.first_seen),
+            day(device.last_seen)
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push("## Buckets".to_string());
+    for bucket in &inventory.buckets {
+        lines.push(String::new());
+        lines.push(format!("### {}", code_span(&bucket.id)));
+        lines.push(String::new());
+        lines.push(format!(
+            "- **Type**: {} (from {} on {})",
+            code_span(bucket.bucket_type.as_deref().unwrap_or("unknown")),
+            text(bucket.client.as_deref().unwrap_or("unknown client")),
+            text(bucket.hostname.as_deref().unwrap_or("unknown host"))
+        ));
+        lines.push(format!(
+            "- **Range**: {} to {}",
+            day(bucket.first_seen),
+            day(bucket.last_seen)
+        ));
+        lines.push(format!("- **Events**: {}", bucket.events));
+        let keys: Vec<String> = bucket.data_keys.iter().map(|key| code_span(key)).collect();
+        lines.push(format!(
+            "- **Data keys**: {} (from the newest {} events)",
+            match keys.is_empty() {
+                true => "none".to_string(),
+                false => keys.join(", "),
+            },
+            bucket.sampled_events
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push("## Settings on aw-server".to_string());
+    lines.push(String::new());
+    lines.push(match &inventory.settings {
+        Some(keys) if keys.is_empty() => "_None stored._".to_string(),
+        Some(keys) => keys
+            .iter()
+            .map(|key| code_span(key))
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => "_Not available from this aw-server._".to_string(),
+    });
+
+    lines.push(String::new());
+    lines.push("## Stored by This Server".to_string());
+    lines.push(String::new());
+    if inventory.local_storage.is_empty() {
+        lines.push("_Nothing; no caches or files are configured._".to_string());
+    }
+    for stored in &inventory.local_storage {
+        lines.push(format!(
+            "- **{}**: {}",
+            stored.kind,
+            code_span(&stored.path.display().to_string())
+        ));
+    }
+
+    if !inventory.do_not_track.is_empty() {
+        lines.push(String::new());
+        lines.push("## Do-Not-Track Time".to_string());
+        lines.push(String::new());
+        lines.push(format!(
+            "Left out of the counts, ranges and export above: {}",
+            inventory
+                .do_not_track
+                .iter()
+                .map(|rule| code_span(rule))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn bucket(id: &str, hostname: &str) -> Bucket {
+        Bucket {
+            id: id.to_string(),
+            client: None,
+            bucket_type: None,
+            hostname: Some(hostname.to_string()),
+            created: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            data: None,
+            last_updated: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn event(day: u32, app: &str, title: Option<&str>) -> Event {
+        let mut builder = Event::builder()
+            .timestamp(Utc.with_ymd_and_hms(2024, 3, day, 9, 0, 0).unwrap())
+            .duration_secs(60.0)
+            .data("app", app);
+        if let Some(title) = title {
+            builder = builder.data("title", title);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn inventories_buckets_by_device() {
+        let events = [event(2, "Code", Some("main.rs")), event(1, "Slack", None)];
+        let complete = bucket_inventory(&bucket("window", "laptop"), 2, &events);
+        assert_eq!(complete.data_keys, ["app", "title"]);
+        assert_eq!(complete.first_seen, Some(events[1].timestamp));
+        assert_eq!(complete.last_seen, Some(events[0].end()));
+
+        // Older events than the sample: the range starts at the bucket's creation
+        let partial = bucket_inventory(&bucket("afk", "laptop"), 10, &events[..1]);
+        assert_eq!(
+            partial.first_seen,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+        );
+        let phone = bucket_inventory(&bucket("phone", "phone"), 0, &[]);
+
+        let devices = devices(&[complete, partial, phone]);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].hostname, "laptop");
+        assert_eq!(devices[0].buckets, ["window", "afk"]);
+        assert_eq!(devices[0].events, 12);
+        assert_eq!(
+            devices[0].first_seen,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn samples_with_the_zero_duration_policy() {
+        use crate::models::{ZeroDurationPolicy, test_event};
+
+        let (_fake, server) = crate::tools::test_server(vec![
+            test_event(
+                Utc.with_ymd_and_hms(2024, 3, 1, 9, 1, 0).unwrap(),
+                0.0,
+                serde_json::json!({"app": "Code", "title": "main.rs"}),
+            ),
+            test_event(
+                Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+                60.0,
+                serde_json::json!({"app": "Code"}),
+            ),
+        ])
+        .await;
+        let server = server.with_config(crate::config::Config {
+            zero_duration_policy: ZeroDurationPolicy::Drop,
+            ..Default::default()
+        });
+        let inventory = server.inventory(None).await.unwrap();
+        assert_eq!(inventory.buckets[0].data_keys, ["app"]);
+        assert_eq!(inventory.buckets[0].sampled_events, 1);
+    }
+}
@@ -1,3 +1,874 @@
+mod activity;
+mod admin;
+mod aggregates;
+mod alerts;
+mod browser;
 mod buckets;
+mod categories;
+mod daily;
+mod devices;
+mod drilldown;
+mod exports;
+mod git;
+mod inventory;
+mod pack;
+mod patterns;
+mod query;
+mod raw;
+mod settings;
+mod snapshot;
+mod status;
+#[cfg(feature = "templates")]
+mod templates;
+mod tickets;
+mod write;
 
-pub use buckets::ActivityWatchMcpServer;
+use crate::aggregates::AggregateStore;
+use crate::analysis::{
+    AFK_BUCKET_TYPE, TimeRange, TokenBudget, WINDOW_BUCKET_TYPE, parse_period, parse_preset,
+    resolve_open_range,
+};
+use crate::api::{ActivityWatchClient, BucketMap};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::clock::{Clock, FixedClock, SystemClock};
+use crate::config::Config;
+use crate::constants::CHARACTER_LIMIT;
+use crate::format::{escape_markdown, with_rounding};
+use crate::guard::guard_output;
+use crate::meta::{ResponseMeta, new_correlation_id, untracked};
+use crate::models::Bucket;
+use crate::privacy::including_private;
+use crate::pseudonym::Pseudonymizer;
+use crate::scope::AccessScope;
+use crate::scrub::scrub_secrets;
+use crate::style::OutputStyle;
+use crate::timezone::Timezone;
+use crate::units::{DurationUnit, to_response_value, with_duration_unit};
+use chrono::{DateTime, Utc};
+use rmcp::{
+    ErrorData as McpError, Peer, RoleServer,
+    handler::server::{router::tool::ToolRouter, tool::ToolCallContext},
+    model::*,
+    service::{NotificationContext, RequestContext},
+};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tracing::Instrument;
+
+/// Argument every tool accepts when do-not-track time is configured, to
+/// include that time
+const INCLUDE_PRIVATE_ARGUMENT: &str = "include_private";
+
+/// Argument every tool with JSON output accepts, to add durations in another
+/// unit and humanized
+const DURATION_UNIT_ARGUMENT: &str = "duration_unit";
+
+/// ActivityWatch MCP Server
+#[derive(Clone)]
+pub struct ActivityWatchMcpServer {
+    client: Arc<ActivityWatchClient>,
+    /// Swapped as a whole when the configuration is reloaded
+    config: Arc<RwLock<Arc<Config>>>,
+    audit: Arc<AuditLog>,
+    aggregates: Arc<AggregateStore>,
+    /// Source of "now" for presets, open ranges and other relative times
+    clock: Arc<dyn Clock>,
+    tool_router: ToolRouter<Self>,
+    /// Operational tools, gated separately by `Config::admin_tools`
+    admin_router: ToolRouter<Self>,
+    /// The connected client, for alerts sent outside of tool calls
+    peer: Arc<RwLock<Option<Peer<RoleServer>>>>,
+    /// Lowest level of log messages the client asked for (all until it
+    /// sets one)
+    log_level: Arc<RwLock<Option<LoggingLevel>>>,
+    /// Whether this serves a client of the shared endpoint rather than the
+    /// local stdio client
+    remote: bool,
+}
+
+impl ActivityWatchMcpServer {
+    /// Create a new ActivityWatch MCP server
+    pub fn new(client: ActivityWatchClient) -> Self {
+        let tool_router = Self::bucket_router()
+            + Self::device_router()
+            + Self::activity_router()
+            + Self::daily_router()
+            + Self::patterns_router()
+            + Self::drilldown_router()
+            + Self::browser_router()
+            + Self::git_router()
+            + Self::ticket_router()
+            + Self::pack_router()
+            + Self::category_router()
+            + Self::raw_router()
+            + Self::query_router()
+            + Self::export_router()
+            + Self::inventory_router()
+            + Self::settings_router()
+            + Self::write_router()
+            + Self::status_router();
+        #[cfg(feature = "templates")]
+        let tool_router = tool_router + Self::template_router();
+
+        Self {
+            client: Arc::new(client),
+            config: Arc::new(RwLock::new(Arc::new(Config::default()))),
+            audit: Arc::new(AuditLog::default()),
+            aggregates: Arc::new(AggregateStore::default()),
+            clock: Arc::new(SystemClock),
+            tool_router,
+            admin_router: Self::admin_router(),
+            peer: Arc::new(RwLock::new(None)),
+            log_level: Arc::new(RwLock::new(None)),
+            remote: false,
+        }
+    }
+
+    /// Use the given configuration instead of the defaults
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.aggregates = Arc::new(AggregateStore::new(config.aggregate_dir.clone()));
+        if let Some(now) = config.now {
+            self.set_clock(Arc::new(FixedClock(now)));
+        }
+        self.config = Arc::new(RwLock::new(Arc::new(config)));
+        self
+    }
+
+    /// Take the current time from the given clock instead of the system's
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.set_clock(Arc::new(clock));
+        self
+    }
+
+    /// Use a clock here and in the client, which shares its caches with the
+    /// client it replaces
+    fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let client = (*self.client).clone().with_shared_clock(clock.clone());
+        self.client = Arc::new(client);
+        self.clock = clock;
+    }
+
+    /// A server for one client of a shared endpoint: the same aw-server
+    /// connection, caches and audit log, with the client's own scope and
+    /// connection. Configuration reloads of this server do not reach it.
+    pub fn for_client(&self, scope: AccessScope) -> Self {
+        let mut config = (*self.config()).clone();
+        config.scope = Some(scope);
+        Self {
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            peer: Arc::new(RwLock::new(None)),
+            log_level: Arc::new(RwLock::new(None)),
+            remote: true,
+            ..self.clone()
+        }
+    }
+
+    /// The current time according to the server's clock
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// The current configuration
+    pub fn config(&self) -> Arc<Config> {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Load the configuration again (from its file, or the environment) and
+    /// use it for subsequent tool calls.
+    ///
+    /// Returns the changed settings that only take effect after a restart.
+    pub fn reload_config(&self) -> anyhow::Result<Vec<&'static str>> {
+        let current = self.config();
+        let mut config = current.reload()?;
+        let mut restart_required = current.restart_required(&config);
+        // Tools must not be able to grant themselves admin access or widen
+        // their scope (a shared endpoint's clients carry their token's scope)
+        config.admin_tools = current.admin_tools;
+        if config.scope != current.scope {
+            restart_required.push("AW_MCP_SCOPE");
+            config.scope = current.scope.clone();
+        }
+        // Clients keep calling tools by the names they were offered
+        config.tool_names = current.tool_names.clone();
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+        Ok(restart_required)
+    }
+
+    /// All tools currently offered (admin tools only when enabled), under
+    /// their configured names
+    fn list_all_tools(&self) -> Vec<Tool> {
+        let config = self.config();
+        let mut tools = self.tool_router.list_all();
+        if config.admin_tools {
+            tools.extend(self.admin_router.list_all());
+        }
+        for tool in &mut tools {
+            let schema = Arc::make_mut(&mut tool.input_schema);
+            let properties = schema.get_mut("properties").and_then(|p| p.as_object_mut());
+            if let Some(properties) = properties
+                && properties.contains_key("response_format")
+            {
+                properties.insert(
+                    DURATION_UNIT_ARGUMENT.to_string(),
+                    serde_json::json!({
+                        "type": "string",
+                        "enum": ["seconds", "minutes", "hours"],
+                        "description": "For JSON output: also give every duration in this unit \
+                                        (rounded to two decimals) and humanized, next to the \
+                                        seconds",
+                    }),
+                );
+            }
+        }
+        if !config.do_not_track.is_empty() {
+            for tool in &mut tools {
+                let schema = Arc::make_mut(&mut tool.input_schema);
+                let properties = schema
+                    .entry("properties")
+                    .or_insert_with(|| serde_json::json!({}));
+                if let Some(properties) = properties.as_object_mut() {
+                    properties.insert(
+                        INCLUDE_PRIVATE_ARGUMENT.to_string(),
+                        serde_json::json!({
+                            "type": "boolean",
+                            "description": "Include do-not-track time (AW_MCP_DO_NOT_TRACK), \
+                                            which is left out by default",
+                        }),
+                    );
+                }
+            }
+        }
+        if config.tool_names.is_default() {
+            return tools;
+        }
+
+        let builtin = self.builtin_tool_names();
+        let builtin: Vec<&str> = builtin.iter().map(String::as_str).collect();
+        for tool in &mut tools {
+            tool.name = config.tool_names.external(&tool.name).into();
+            if let Some(description) = &tool.description {
+                let description = config.tool_names.rename_text(description, &builtin);
+                tool.description = Some(description.into());
+            }
+        }
+        tools
+    }
+
+    /// Built-in names of all tools, including the admin tools
+    fn builtin_tool_names(&self) -> Vec<String> {
+        self.tool_router
+            .list_all()
+            .into_iter()
+            .chain(self.admin_router.list_all())
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    /// Replace built-in tool names in text with the configured ones
+    fn rename_tools_in(&self, text: &str) -> String {
+        let tool_names = &self.config().tool_names;
+        if tool_names.is_default() {
+            return text.to_string();
+        }
+        let builtin = self.builtin_tool_names();
+        let builtin: Vec<&str> = builtin.iter().map(String::as_str).collect();
+        tool_names.rename_text(text, &builtin)
+    }
+
+    /// Check that the configured tool names refer to existing tools and do
+    /// not collide
+    pub fn check_tool_names(&self) -> anyhow::Result<()> {
+        let builtin = self.builtin_tool_names();
+        let builtin: Vec<&str> = builtin.iter().map(String::as_str).collect();
+        self.config()
+            .tool_names
+            .check(&builtin)
+            .map_err(|e| anyhow::anyhow!("Invalid AW_MCP_TOOL_RENAMES: {}", e))
+    }
+
+    /// Whether a bucket is within the configured access scope
+    fn in_scope(&self, bucket: &Bucket) -> bool {
+        self.config()
+            .scope
+            .as_ref()
+            .is_none_or(|scope| scope.allows(bucket))
+    }
+
+    /// The window watcher bucket in scope that most recently received events
+    fn latest_window_bucket<'a>(&self, buckets: &'a HashMap<String, Bucket>) -> Option<&'a Bucket> {
+        buckets
+            .values()
+            .filter(|b| self.in_scope(b) && b.bucket_type.as_deref() == Some(WINDOW_BUCKET_TYPE))
+            .max_by(|a, b| {
+                a.last_activity()
+                    .cmp(&b.last_activity())
+                    .then_with(|| b.id.cmp(&a.id))
+            })
+    }
+
+    /// The AFK watcher bucket in scope recording the same host as `bucket`
+    fn afk_bucket_of<'a>(
+        &self,
+        buckets: &'a HashMap<String, Bucket>,
+        bucket: &Bucket,
+    ) -> Option<&'a Bucket> {
+        buckets.values().find(|b| {
+            self.in_scope(b)
+                && b.bucket_type.as_deref() == Some(AFK_BUCKET_TYPE)
+                && b.hostname.is_some()
+                && b.hostname == bucket.hostname
+        })
+    }
+
+    /// The bucket list for resolving which buckets to read. Its cache age is
+    /// not reported, as the list itself is not part of the response
+    async fn lookup_buckets(&self) -> Result<BucketMap, McpError> {
+        untracked(self.client.buckets()).await
+    }
+
+    /// Refuse parameters naming files or directories on the server host to
+    /// clients of the shared endpoint and scoped clients, which must not
+    /// reach beyond their buckets
+    fn check_host_path(&self, parameter: &str) -> Result<(), String> {
+        if self.remote || self.config().scope.is_some() {
+            return Err(format!(
+                "`{}` names a path on the server host, which remote and scoped clients cannot use",
+                parameter
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail as if the bucket did not exist when it is outside the configured
+    /// access scope, so scoped clients cannot probe for other buckets
+    async fn check_scope(&self, bucket_id: &str) -> Result<(), McpError> {
+        if self.config().scope.is_none() {
+            return Ok(());
+        }
+        let buckets = self.lookup_buckets().await?;
+        match buckets.get(bucket_id) {
+            Some(bucket) if self.in_scope(bucket) => Ok(()),
+            _ => Err(McpError::invalid_params(
+                format!("There's no bucket named {}", bucket_id),
+                None,
+            )),
+        }
+    }
+
+    /// The pseudonymizer for the hostnames of the current buckets, when
+    /// `AW_MCP_PSEUDONYMIZE` is on
+    async fn pseudonymizer(&self) -> Option<Pseudonymizer> {
+        if !self.config().pseudonymize {
+            return None;
+        }
+        let buckets = self.lookup_buckets().await.ok();
+        let hostnames = buckets
+            .iter()
+            .flat_map(|buckets| buckets.values())
+            .filter_map(|bucket| bucket.hostname.as_deref());
+        Some(Pseudonymizer::new(
+            &self.config().pseudonym_salt,
+            hostnames,
+            self.config().pseudonym_users.iter().map(String::as_str),
+        ))
+    }
+
+    /// Use the configured tool names, pseudonymize names, mask secrets and
+    /// apply the prompt-injection guard to a tool result, as configured
+    fn filter_output(
+        &self,
+        mut result: CallToolResult,
+        pseudonymizer: Option<&Pseudonymizer>,
+    ) -> CallToolResult {
+        for content in result.content.iter_mut().flatten() {
+            if let RawContent::Text(text) = &mut content.raw {
+                text.text = self.filter_text(&text.text, pseudonymizer);
+            }
+        }
+        result
+    }
+
+    /// [`Self::filter_output`] for a failed call, whose message can quote
+    /// recorded data (e.g. a window title in aw-server's error), tagged with
+    /// the call's correlation ID
+    fn filter_error(
+        &self,
+        mut error: McpError,
+        pseudonymizer: Option<&Pseudonymizer>,
+        correlation_id: &str,
+    ) -> McpError {
+        error.message = self.filter_text(&error.message, pseudonymizer).into();
+        // Keep the status and type parsed from aw-server's response
+        let mut data = match error.data.take() {
+            Some(serde_json::Value::Object(data)) => data,
+            Some(other) => serde_json::Map::from_iter([("data".to_string(), other)]),
+            None => serde_json::Map::new(),
+        };
+        data.insert("correlation_id".to_string(), correlation_id.into());
+        error.data = Some(serde_json::Value::Object(data));
+        error
+    }
+
+    /// [`Self::filter_output`] for one text
+    fn filter_text(&self, text: &str, pseudonymizer: Option<&Pseudonymizer>) -> String {
+        let config = self.config();
+        let mut text = self.rename_tools_in(text);
+        if let Some(pseudonymizer) = pseudonymizer {
+            text = pseudonymizer.apply(&text);
+        }
+        if config.scrub_secrets {
+            text = scrub_secrets(&text, config.scrub_emails);
+        }
+        if config.injection_guard {
+            text = guard_output(&text);
+        }
+        text
+    }
+
+    /// Restyle the text of a response as configured by `AW_MCP_OUTPUT_STYLE`
+    fn style_output(&self, mut result: CallToolResult) -> CallToolResult {
+        let style = self.config().output_style;
+        if style == OutputStyle::Markdown {
+            return result;
+        }
+        for content in result.content.iter_mut().flatten() {
+            if let RawContent::Text(text) = &mut content.raw {
+                text.text = style.apply(&text.text);
+            }
+        }
+        result
+    }
+
+    /// Resolve a range given either as ISO 8601 start and end or as a
+    /// period: a preset such as `this morning` or a specifier such as
+    /// `2024-Q1`
+    fn resolve_range(
+        &self,
+        start: Option<&str>,
+        end: Option<&str>,
+        period: Option<&str>,
+    ) -> Result<TimeRange, String> {
+        self.resolve_range_in(start, end, period, &self.config().timezone)
+    }
+
+    /// [`Self::resolve_range`] with periods taken in a given timezone
+    fn resolve_range_in(
+        &self,
+        start: Option<&str>,
+        end: Option<&str>,
+        period: Option<&str>,
+        tz: &Timezone,
+    ) -> Result<TimeRange, String> {
+        match (start, end, period) {
+            (None, None, Some(period)) => {
+                let config = self.config();
+                parse_preset(period, config.work_hours, tz, self.now())
+                    .unwrap_or_else(|| parse_period(period, config.fiscal_year_start, tz))
+            }
+            (Some(start), Some(end), None) => TimeRange::parse(start, end),
+            (_, _, Some(_)) => Err("Give either a period or start and end, not both".to_string()),
+            _ => Err("Both start and end are required (or give a period)".to_string()),
+        }
+    }
+
+    /// The timezone a bucket's days are split in: the one named in its
+    /// metadata when the recording device says so (see
+    /// [`Bucket::timezone_hint`]), the configured one otherwise
+    async fn bucket_timezone(&self, bucket_id: &str) -> Timezone {
+        let hint = match self.lookup_buckets().await {
+            Ok(buckets) => buckets
+                .get(bucket_id)
+                .and_then(|bucket| bucket.timezone_hint().map(String::from)),
+            Err(_) => None,
+        };
+        // "local" would mean this server's zone, which is the fallback anyway
+        let Some(hint) = hint.filter(|hint| !hint.eq_ignore_ascii_case("local")) else {
+            return self.config().timezone.clone();
+        };
+        match hint.parse() {
+            Ok(tz) => tz,
+            Err(e) => {
+                tracing::warn!("Ignoring timezone of bucket {}: {}", bucket_id, e);
+                self.config().timezone.clone()
+            }
+        }
+    }
+
+    /// Resolve an optional range given as a period or as start and end,
+    /// either of which may be left open (see [`resolve_open_range`])
+    fn resolve_event_range(
+        &self,
+        start: Option<&str>,
+        end: Option<&str>,
+        period: Option<&str>,
+    ) -> Result<Option<TimeRange>, String> {
+        match period {
+            Some(_) => self.resolve_range(start, end, period).map(Some),
+            None => resolve_open_range(start, end, self.now()),
+        }
+    }
+
+    /// Render an untrusted value (hostname, group label, ...) for markdown
+    /// output, escaped unless `AW_MCP_ESCAPE_MARKDOWN` is off
+    fn markdown_text(&self, value: &str) -> String {
+        if self.config().escape_markdown {
+            escape_markdown(value)
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+impl rmcp::ServerHandler for ActivityWatchMcpServer {
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let correlation_id = new_correlation_id();
+        let tool = request.name.to_string();
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = %request.name,
+            correlation_id = %correlation_id
+        );
+
+        async move {
+            let started = Instant::now();
+            let ((result, pseudonymizer), meta) = ResponseMeta::collect(correlation_id, async {
+                // Tool arguments may refer to devices by their pseudonyms
+                let pseudonymizer = self.pseudonymizer().await;
+                if let Some(pseudonymizer) = &pseudonymizer
+                    && let Some(arguments) = request.arguments.as_mut()
+                {
+                    arguments
+                        .values_mut()
+                        .for_each(|value| pseudonymizer.reveal_value(value));
+                }
+                // Taken by the server for every tool rather than by each tool
+                let include_private = request
+                    .arguments
+                    .as_mut()
+                    .and_then(|arguments| arguments.remove(INCLUDE_PRIVATE_ARGUMENT))
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(false);
+                let config = self.config();
+                let duration_unit = match request
+                    .arguments
+                    .as_mut()
+                    .and_then(|arguments| arguments.remove(DURATION_UNIT_ARGUMENT))
+                {
+                    Some(unit) => match unit.as_str().map(str::parse::<DurationUnit>) {
+                        Some(Ok(unit)) => Some(unit),
+                        _ => {
+                            let error = McpError::invalid_params(
+                                format!(
+                                    "Invalid {}: expected seconds, minutes or hours",
+                                    DURATION_UNIT_ARGUMENT
+                                ),
+                                None,
+                            );
+                            return (Err(error), pseudonymizer);
+                        }
+                    },
+                    None => config.duration_unit,
+                };
+                let called = request.name.clone();
+                if !config.tool_names.is_default() {
+                    let builtin = self.builtin_tool_names();
+                    let builtin: Vec<&str> = builtin.iter().map(String::as_str).collect();
+                    match config.tool_names.internal(&called, &builtin) {
+                        Some(name) => request.name = name.to_string().into(),
+                        None => {
+                            let error = McpError::invalid_params(
+                                format!("Unknown tool '{}'", called),
+                                None,
+                            );
+                            return (Err(error), pseudonymizer);
+                        }
+                    }
+                }
+                let router = if self.admin_router.has_route(&request.name) {
+                    if !config.admin_tools {
+                        let error = McpError::invalid_params(
+                            format!(
+                                "Admin tool '{}' is disabled; enable it with --admin or AW_MCP_ADMIN_TOOLS=true",
+                                called
+                            ),
+                            None,
+                        );
+                        return (Err(error), pseudonymizer);
+                    }
+                    &self.admin_router
+                } else {
+                    &self.tool_router
+                };
+                let tcc = ToolCallContext::new(self, request, context);
+                let call = with_rounding(config.rounding, router.call(tcc));
+                let call = with_duration_unit(duration_unit, call);
+                (including_private(include_private, call).await, pseudonymizer)
+            })
+            .await;
+
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            let outcome = match &result {
+                Ok(result) if result.is_error != Some(true) => {
+                    tracing::info!(elapsed_ms, "Tool call succeeded");
+                    "ok"
+                }
+                Ok(_) => {
+                    tracing::warn!(elapsed_ms, "Tool call returned an error");
+                    "error"
+                }
+                Err(e) => {
+                    tracing::warn!(elapsed_ms, error = %e.message, "Tool call failed");
+                    "failed"
+                }
+            };
+            self.audit.record(AuditEntry {
+                time: self.now(),
+                tool,
+                correlation_id: meta.correlation_id.clone(),
+                outcome,
+                elapsed_ms,
+            });
+
+            match result {
+                Ok(result) => {
+                    let result = meta.annotate(
+                        self.filter_output(result, pseudonymizer.as_ref()),
+                        self.now(),
+                    );
+                    Ok(self.style_output(result))
+                }
+                Err(error) => Err(self.filter_error(
+                    error,
+                    pseudonymizer.as_ref(),
+                    &meta.correlation_id,
+                )),
+            }
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        tracing::info!("client initialized");
+        *self.peer.write().unwrap_or_else(|e| e.into_inner()) = Some(context.peer);
+    }
+
+    /// Alerts, the only messages logged to the client, are sent only at or
+    /// above the requested level
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        *self.log_level.write().unwrap_or_else(|e| e.into_inner()) = Some(request.level);
+        Ok(())
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.list_all_tools()))
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        let instructions = "ActivityWatch MCP Server - Query your ActivityWatch time tracking data. Use aw_help for a catalogue of the available tools by task, aw_list_buckets to see available data sources, then aw_get_events to retrieve activity logs.";
+        ServerInfo {
+            instructions: Some(self.rename_tools_in(instructions)),
+            capabilities: match self.config().alerts.is_empty() {
+                true => ServerCapabilities::builder().enable_tools().build(),
+                false => ServerCapabilities::builder()
+                    .enable_tools()
+                    .enable_logging()
+                    .build(),
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Truncate response if it exceeds the character limit
+fn truncate_response(response: String) -> String {
+    if response.len() > CHARACTER_LIMIT {
+        let truncated = &response[..response.floor_char_boundary(CHARACTER_LIMIT)];
+        format!(
+            "{}\n\n_Response truncated at {} characters. Use more specific filters to reduce results._",
+            truncated, CHARACTER_LIMIT
+        )
+    } else {
+        response
+    }
+}
+
+/// Truncate a markdown response to the caller's token budget, or to the
+/// character limit without one
+fn fit_response(response: String, budget: Option<TokenBudget>) -> String {
+    match budget {
+        Some(budget) if response.len() > budget.chars => format!(
+            "{}\n\n_Response cut to fit max_tokens_hint._",
+            &response[..response.floor_char_boundary(budget.chars)]
+        ),
+        Some(_) => response,
+        None => truncate_response(response),
+    }
+}
+
+/// A JSON response: compact when the caller gave a token budget, pretty
+/// printed otherwise
+fn json_response(value: &impl serde::Serialize, budget: Option<TokenBudget>) -> String {
+    to_response_value(value)
+        .and_then(|value| match budget {
+            Some(_) => serde_json::to_string(&value),
+            None => serde_json::to_string_pretty(&value),
+        })
+        .unwrap_or_else(|_| "Error formatting JSON".to_string())
+}
+
+/// A pretty-printed JSON response, with durations in the unit the call asked
+/// for
+fn json_text(value: &impl serde::Serialize) -> String {
+    json_response(value, None)
+}
+
+/// The token budget of a `max_tokens_hint` parameter
+fn token_budget(max_tokens_hint: Option<usize>) -> Result<Option<TokenBudget>, String> {
+    max_tokens_hint.map(TokenBudget::from_tokens).transpose()
+}
+
+/// A fake aw-server with a window bucket `aw-watcher-window_test` (host
+/// `test`) holding `events`, and a server reading from it
+#[cfg(test)]
+pub(crate) async fn test_server(
+    events: Vec<crate::models::Event>,
+) -> (crate::testing::FakeAwServer, ActivityWatchMcpServer) {
+    let fake = crate::testing::FakeAwServer::start().await;
+    fake.add_simple_bucket("aw-watcher-window_test", WINDOW_BUCKET_TYPE, "test");
+    fake.add_events("aw-watcher-window_test", events);
+    let server = ActivityWatchMcpServer::new(ActivityWatchClient::new(fake.url()));
+    (fake, server)
+}
+
+/// [`test_server`] scoped to host `test`, next to a window bucket
+/// `aw-watcher-window_other` (host `other`) that received events last and
+/// holds `other_events`
+#[cfg(test)]
+pub(crate) async fn scoped_test_server(
+    events: Vec<crate::models::Event>,
+    other_events: Vec<crate::models::Event>,
+) -> (crate::testing::FakeAwServer, ActivityWatchMcpServer) {
+    let (fake, server) = test_server(events).await;
+    fake.add_bucket(
+        serde_json::from_value(serde_json::json!({
+            "id": "aw-watcher-window_other",
+            "type": WINDOW_BUCKET_TYPE,
+            "hostname": "other",
+            "last_updated": "2024-12-31T00:00:00Z",
+        }))
+        .unwrap(),
+    );
+    fake.add_events("aw-watcher-window_other", other_events);
+    let server = server.with_config(Config {
+        scope: Some("host:test".parse().unwrap()),
+        ..Default::default()
+    });
+    (fake, server)
+}
+
+/// The text of a tool result's first content
+#[cfg(test)]
+pub(crate) fn result_text(result: &CallToolResult) -> String {
+    match result
+        .content
+        .iter()
+        .flatten()
+        .next()
+        .map(|content| &content.raw)
+    {
+        Some(RawContent::Text(text)) => text.text.clone(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(config: Config) -> ActivityWatchMcpServer {
+        ActivityWatchMcpServer::new(ActivityWatchClient::new("http://127.0.0.1:9"))
+            .with_config(config)
+    }
+
+    #[test]
+    fn refuses_host_paths_to_remote_and_scoped_clients() {
+        let server = server(Config::default());
+        assert!(server.check_host_path("path").is_ok());
+        let client = server.for_client("host:alice-laptop".parse().unwrap());
+        assert!(client.check_host_path("path").is_err());
+
+        let scoped = ActivityWatchMcpServer::new(ActivityWatchClient::new("http://127.0.0.1:9"))
+            .with_config(Config {
+                scope: Some("host:alice-laptop".parse().unwrap()),
+                ..Config::default()
+            });
+        assert!(scoped.check_host_path("repo_path").is_err());
+    }
+
+    #[test]
+    fn reload_keeps_the_client_scope() {
+        let path = std::env::temp_dir().join(format!("aw-mcp-reload-{}.toml", std::process::id()));
+        std::fs::write(&path, "admin_tools = true\n").unwrap();
+        let config = Config::from_file(&path).unwrap();
+        let client = server(config).for_client("host:alice-laptop".parse().unwrap());
+
+        let restart_required = client.reload_config();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(restart_required.unwrap().contains(&"AW_MCP_SCOPE"));
+        assert_eq!(
+            client.config().scope,
+            Some("host:alice-laptop".parse().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_query_periods_by_the_server_clock() {
+        let (fake, server) = test_server(Vec::new()).await;
+        fake.set_query_result(serde_json::json!(1.0));
+        let server = server.with_config(Config {
+            now: Some("2024-01-03T00:00:00Z".parse().unwrap()),
+            ..Config::default()
+        });
+        // Both periods have ended by the system clock, only one by the server's
+        let periods = [
+            "2024-01-01T00:00:00Z/2024-01-02T00:00:00Z".to_string(),
+            "2024-01-02T00:00:00Z/2024-01-04T00:00:00Z".to_string(),
+        ];
+        server.client.query(&periods, "RETURN = 1;").await.unwrap();
+        assert_eq!(server.client.cache_stats().await.queries.entries, 1);
+    }
+
+    #[test]
+    fn filters_error_messages_like_output() {
+        let server = server(Config {
+            injection_guard: true,
+            ..Config::default()
+        });
+        let error = McpError::invalid_params(
+            "No event titled 'reset?token=abc123' in aw_get_events",
+            None,
+        );
+        let error = server.filter_error(error, None, "c1");
+        assert!(error.message.contains("token=[secret redacted]"));
+        assert!(error.message.starts_with(crate::guard::GUARD_OPEN));
+        assert_eq!(error.data.unwrap()["correlation_id"], "c1");
+    }
+}
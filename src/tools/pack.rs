@@ -0,0 +1,510 @@
+use super::{ActivityWatchMcpServer, fit_response, json_response, token_budget};
+use crate::analysis::{
+    FocusSession, TRUSTWORTHY_SCORE, TimeRange, TokenBudget, active_intervals, clip_to_active,
+    data_quality, day_anomalies, focus_sessions, format_timestamp, goal_status, in_category,
+    interval_label, time_series, totals_by_key,
+};
+use crate::categories::{CATEGORY_SEPARATOR, Categories, category_totals};
+use crate::constants::{FOCUS_MAX_BREAK_SECS, FOCUS_MIN_SECS};
+use crate::format::{format_duration, format_percent};
+use crate::models::{Bucket, Event, Resolution, ResponseFormat};
+use crate::timezone::Timezone;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Default characters per section of the review pack
+const DEFAULT_SECTION_CHARS: usize = 3000;
+
+/// Default number of entries per list in the review pack
+const DEFAULT_PACK_TOP: usize = 5;
+
+/// Sections of the review pack
+const PACK_SECTIONS: usize = 5;
+
+/// Section characters per list entry when a token budget sets the size
+/// (the defaults: 3000 characters, 5 entries)
+const PACK_ENTRY_CHARS: usize = 600;
+
+/// Input for the review pack
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReviewPackParams {
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (default: this week so far; e.g., "2024-W07")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Window bucket to review (default: the most recently updated window bucket)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// Maximum entries per list, e.g. apps or focus sessions (default: 5)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Maximum characters per section in markdown output (default: 3000)
+    #[serde(default)]
+    pub section_chars: Option<usize>,
+
+    /// Approximate size the whole pack should fit in, in tokens (e.g., 2000); sets `section_chars` and `top` unless given
+    #[serde(default)]
+    pub max_tokens_hint: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = pack_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Bundle the material for a weekly review into one response.
+    #[tool(
+        description = r#"Gather the material for an end-of-week review in one call, as input for writing a narrative review: a summary (active time per day, top apps and categories), top distractions, focus sessions, goal status and notable anomalies. Each section is capped to a size budget so the whole pack stays small.
+
+Time counts while the AFK watcher on the bucket's host saw the user (all window time without AFK data). Distractions are the categories configured in AW_MCP_DISTRACTION_CATEGORIES (default: Media) with their subcategories. Focus sessions are at least 25 minutes of undistracted activity, tolerating breaks of up to 2 minutes. Goals are weekly category targets from AW_MCP_GOALS (e.g. "Work>=20h;Media<=5h"), scaled to the range's length. Anomalies are days with unusually much or little activity and days with unreliable data.
+
+## Parameters
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead (default: this week so far)
+- `bucket_id`: Window bucket to review (default: the most recently updated one)
+- `top`: Maximum entries per list (default: 5)
+- `section_chars`: Maximum characters per section (default: 3000)
+- `max_tokens_hint`: Approximate token budget of the whole pack (e.g., 2000). Without `section_chars` and `top`, they are chosen to fit, and JSON is compact"#
+    )]
+    async fn aw_review_pack(
+        &self,
+        Parameters(params): Parameters<ReviewPackParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params
+            .bucket_id
+            .as_deref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        let budget = match token_budget(params.max_tokens_hint) {
+            Ok(budget) => budget,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.review_pack(&params, budget).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to build review pack: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the window and AFK events of the range and assemble the
+    /// sections
+    async fn review_pack(
+        &self,
+        params: &ReviewPackParams,
+        budget: Option<TokenBudget>,
+    ) -> Result<String, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let bucket: &Bucket = match &params.bucket_id {
+            Some(bucket_id) => {
+                self.check_scope(bucket_id).await?;
+                buckets.get(bucket_id).ok_or_else(|| {
+                    McpError::invalid_params(format!("Bucket {} not found", bucket_id), None)
+                })?
+            }
+            None => self
+                .latest_window_bucket(&buckets)
+                .ok_or_else(|| McpError::invalid_params("No window buckets found", None))?,
+        };
+        let tz = self.bucket_timezone(&bucket.id).await;
+        let range = match (&params.start, &params.end, &params.period) {
+            (None, None, None) => this_week(self.now(), &tz),
+            (start, end, period) => {
+                self.resolve_range_in(start.as_deref(), end.as_deref(), period.as_deref(), &tz)
+            }
+        }
+        .map_err(|e| McpError::invalid_params(e, None))?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+
+        let window_events = self.range_events(&bucket.id, &range).await?;
+        let afk_bucket = self.afk_bucket_of(&buckets, bucket);
+        let afk_events = match afk_bucket {
+            Some(afk) => Some(self.range_events(&afk.id, &range).await?),
+            None => None,
+        };
+        let events = match &afk_events {
+            Some(afk_events) => clip_to_active(&window_events, &active_intervals(afk_events)),
+            None => window_events.clone(),
+        };
+        let config = self.config();
+        let categories = self.categories().await.map(|(categories, _)| categories);
+        let pack = Pack::build(
+            &events,
+            &window_events,
+            afk_events.as_deref(),
+            categories.as_ref().ok(),
+            &config.distraction_categories,
+            &range,
+            &tz,
+        );
+
+        let section_chars = params
+            .section_chars
+            .unwrap_or(budget.map_or(DEFAULT_SECTION_CHARS, |budget| budget.chars / PACK_SECTIONS));
+        let top = params.top.unwrap_or(match budget {
+            Some(_) => (section_chars / PACK_ENTRY_CHARS).max(1),
+            None => DEFAULT_PACK_TOP,
+        });
+        let days = range.duration_secs() / 86_400.0;
+        let goals = categories
+            .as_ref()
+            .ok()
+            .map(|_| goal_status(&config.goals, &pack.category_leaves, days));
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let table = |rows: &[(String, f64)]| -> Vec<String> {
+                    let mut lines = vec!["| Name | Time |".to_string(), "|---|---|".to_string()];
+                    lines.extend(rows.iter().take(top).map(|(name, secs)| {
+                        format!(
+                            "| {} | {} |",
+                            self.markdown_text(name),
+                            format_duration(*secs)
+                        )
+                    }));
+                    lines
+                };
+                let local = |time| tz.to_local(time).format("%a %Y-%m-%d %H:%M").to_string();
+
+                let mut summary = vec![
+                    format!("- **Range**: {} to {}", start, end),
+                    format!("- **Window bucket**: {}", bucket.id),
+                    format!(
+                        "- **Active time**: {}{}",
+                        format_duration(pack.total_secs),
+                        match afk_bucket {
+                            Some(afk) => format!(" (while {} saw the user)", afk.id),
+                            None => " (no AFK data; all window time counted)".to_string(),
+                        }
+                    ),
+                    String::new(),
+                    "| Day | Active time |".to_string(),
+                    "|---|---|".to_string(),
+                ];
+                summary.extend(
+                    pack.days
+                        .iter()
+                        .map(|(day, secs)| format!("| {} | {} |", day, format_duration(*secs))),
+                );
+                summary.extend([String::new(), "Top apps:".to_string(), String::new()]);
+                summary.extend(table(&pack.apps));
+                if !pack.categories.is_empty() {
+                    summary.extend([String::new(), "Top categories:".to_string(), String::new()]);
+                    summary.extend(table(&pack.categories));
+                }
+
+                let mut distractions = Vec::new();
+                match &categories {
+                    Ok(_) => {
+                        distractions.push(format!(
+                            "- **Distracted**: {} in {} ({})",
+                            format_duration(pack.distraction_secs),
+                            config.distraction_categories.join(", "),
+                            share(pack.distraction_secs, pack.total_secs)
+                        ));
+                        if pack.distraction_secs > 0.0 {
+                            distractions.push(String::new());
+                            distractions.extend(table(&pack.distraction_categories));
+                            distractions.extend([
+                                String::new(),
+                                "Top distracting titles:".to_string(),
+                                String::new(),
+                            ]);
+                            distractions.extend(table(&pack.distraction_titles));
+                        }
+                    }
+                    Err(e) => distractions.push(format!(
+                        "_Unavailable without category rules: {}_",
+                        e.message
+                    )),
+                }
+
+                let mut focus = vec![format!(
+                    "- **Sessions**: {} totalling {} (at least {} each)",
+                    pack.focus.len(),
+                    format_duration(pack.focus.iter().map(|s| s.secs).sum()),
+                    format_duration(FOCUS_MIN_SECS)
+                )];
+                if !pack.focus.is_empty() {
+                    let mut longest: Vec<_> = pack.focus.iter().collect();
+                    longest.sort_by(|a, b| b.secs.total_cmp(&a.secs));
+                    focus.extend([
+                        String::new(),
+                        "| Started | Length | Mostly in |".to_string(),
+                        "|---|---|---|".to_string(),
+                    ]);
+                    focus.extend(longest.iter().take(top).map(|session| {
+                        format!(
+                            "| {} | {} | {} |",
+                            local(session.start),
+                            format_duration(session.secs),
+                            self.markdown_text(&session.app)
+                        )
+                    }));
+                }
+
+                let goal_lines = match (&goals, &categories) {
+                    (_, Err(e)) => {
+                        vec![format!(
+                            "_Unavailable without category rules: {}_",
+                            e.message
+                        )]
+                    }
+                    (Some(goals), _) if goals.is_empty() => {
+                        vec!["_No goals configured (AW_MCP_GOALS)._".to_string()]
+                    }
+                    (Some(goals), _) => {
+                        let mut lines = vec![
+                            "| Goal | Target | Actual | Status |".to_string(),
+                            "|---|---|---|---|".to_string(),
+                        ];
+                        lines.extend(goals.iter().map(|status| {
+                            format!(
+                                "| {} | {} {} | {} | {} |",
+                                self.markdown_text(&status.goal.category),
+                                if status.goal.at_least {
+                                    "at least"
+                                } else {
+                                    "at most"
+                                },
+                                format_duration(status.target_secs),
+                                format_duration(status.actual_secs),
+                                if status.met { "met" } else { "missed" }
+                            )
+                        }));
+                        lines
+                    }
+                    (None, _) => Vec::new(),
+                };
+
+                let mut anomalies: Vec<String> = pack
+                    .anomalies
+                    .iter()
+                    .map(|(day, note)| format!("- **{}**: {}", day, note))
+                    .collect();
+                if anomalies.is_empty() {
+                    anomalies.push("_Nothing unusual._".to_string());
+                }
+
+                let mut lines = vec!["# Review Pack".to_string()];
+                for (title, section) in [
+                    ("Summary", summary),
+                    ("Distractions", distractions),
+                    ("Focus Sessions", focus),
+                    ("Goals", goal_lines),
+                    ("Anomalies", anomalies),
+                ] {
+                    lines.extend([String::new(), format!("## {}", title), String::new()]);
+                    lines.extend(cap_section(section, section_chars));
+                }
+                fit_response(lines.join("\n"), budget)
+            }
+            ResponseFormat::Json => json_response(
+                &serde_json::json!({
+                    "range": range,
+                    "bucket_id": bucket.id,
+                    "afk_bucket_id": afk_bucket.map(|afk| &afk.id),
+                    "summary": {
+                        "active_secs": pack.total_secs,
+                        "days": pack.days,
+                        "apps": pack.apps.iter().take(top).collect::<Vec<_>>(),
+                        "categories": pack.categories.iter().take(top).collect::<Vec<_>>(),
+                    },
+                    "distractions": categories.as_ref().ok().map(|_| serde_json::json!({
+                        "categories": config.distraction_categories,
+                        "secs": pack.distraction_secs,
+                        "per_category":
+                            pack.distraction_categories.iter().take(top).collect::<Vec<_>>(),
+                        "titles": pack.distraction_titles.iter().take(top).collect::<Vec<_>>(),
+                    })),
+                    "focus_sessions": pack.focus.iter().take(top).collect::<Vec<_>>(),
+                    "focus_session_count": pack.focus.len(),
+                    "goals": goals,
+                    "anomalies": pack
+                        .anomalies
+                        .iter()
+                        .map(|(day, note)| serde_json::json!({"day": day, "note": note}))
+                        .collect::<Vec<_>>(),
+                }),
+                budget,
+            ),
+        })
+    }
+}
+
+/// The computed sections of a review pack
+struct Pack {
+    total_secs: f64,
+    days: Vec<(String, f64)>,
+    apps: Vec<(String, f64)>,
+    categories: Vec<(String, f64)>,
+    category_leaves: Vec<(String, f64)>,
+    distraction_secs: f64,
+    distraction_categories: Vec<(String, f64)>,
+    distraction_titles: Vec<(String, f64)>,
+    focus: Vec<FocusSession>,
+    /// Day label and what stands out about it
+    anomalies: Vec<(String, String)>,
+}
+
+impl Pack {
+    /// Compute the sections from the active window events (`events`), the
+    /// raw window and AFK events (for data quality) and the category rules
+    fn build(
+        events: &[Event],
+        window_events: &[Event],
+        afk_events: Option<&[Event]>,
+        categories: Option<&Categories>,
+        distraction_categories: &[String],
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Self {
+        let days: Vec<(String, f64)> = time_series(events, range, Resolution::Day, tz)
+            .into_iter()
+            .map(|point| (interval_label(Resolution::Day, point.start, tz), point.secs))
+            .collect();
+        let is_distraction = |event: &Event| {
+            categories
+                .and_then(|c| c.classify(event))
+                .is_some_and(|path| {
+                    let path = path.join(CATEGORY_SEPARATOR);
+                    distraction_categories
+                        .iter()
+                        .any(|category| in_category(&path, category))
+                })
+        };
+        let distracting: Vec<Event> = events
+            .iter()
+            .filter(|event| is_distraction(event))
+            .cloned()
+            .collect();
+        let totals = categories.map(|c| category_totals(events, c, 1, Some(range)));
+        let distraction_totals =
+            categories.map(|c| category_totals(&distracting, c, 1, Some(range)));
+
+        let mut anomalies: Vec<(String, String)> = day_anomalies(&days)
+            .into_iter()
+            .map(|anomaly| {
+                let note = format!(
+                    "{} day: {} against a median of {}",
+                    anomaly.note,
+                    format_duration(anomaly.secs),
+                    format_duration(anomaly.median_secs)
+                );
+                (anomaly.day, note)
+            })
+            .collect();
+        if let Some(afk_events) = afk_events {
+            for day in data_quality(afk_events, window_events, range, tz) {
+                if day.score < TRUSTWORTHY_SCORE && day.coverage > 0.0 {
+                    let note = format!(
+                        "unreliable data (quality score {}, {} gaps, longest {})",
+                        day.score,
+                        day.gaps,
+                        format_duration(day.longest_gap_secs)
+                    );
+                    anomalies.push((interval_label(Resolution::Day, day.start, tz), note));
+                }
+            }
+        }
+
+        let distraction_categories = distraction_totals.map(|t| t.leaves).unwrap_or_default();
+        Self {
+            total_secs: days.iter().map(|(_, secs)| secs).sum(),
+            apps: totals_by_key(events, "app", Some(range)),
+            categories: totals
+                .as_ref()
+                .map(|t| t.rolled_up.clone())
+                .unwrap_or_default(),
+            category_leaves: totals.map(|t| t.leaves).unwrap_or_default(),
+            distraction_secs: distraction_categories.iter().map(|(_, secs)| secs).sum(),
+            distraction_categories,
+            distraction_titles: totals_by_key(&distracting, "title", Some(range)),
+            focus: focus_sessions(
+                events,
+                is_distraction,
+                range,
+                FOCUS_MIN_SECS,
+                FOCUS_MAX_BREAK_SECS,
+            ),
+            days,
+            anomalies,
+        }
+    }
+}
+
+/// The current week (from Monday) up to now
+fn this_week(now: DateTime<Utc>, tz: &Timezone) -> Result<TimeRange, String> {
+    let today = tz.date_of(now);
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    TimeRange::new(tz.start_of_day(monday), now)
+}
+
+/// A part of a total as a percentage
+fn share(secs: f64, total: f64) -> String {
+    match total > 0.0 {
+        true => format_percent(secs / total * 100.0),
+        false => "-".to_string(),
+    }
+}
+
+/// Keep whole lines of a section up to `budget` characters, noting how many
+/// were left out
+fn cap_section(lines: Vec<String>, budget: usize) -> Vec<String> {
+    let mut used = 0;
+    let mut kept = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        used += line.chars().count() + 1;
+        if used > budget {
+            kept.push(format!(
+                "_{} more lines left out (section budget)._",
+                lines.len() - i
+            ));
+            return kept;
+        }
+        kept.push(line.clone());
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_sections_at_whole_lines() {
+        let lines: Vec<String> = (0..10).map(|i| format!("line {}", i)).collect();
+        assert_eq!(cap_section(lines.clone(), 1000), lines);
+        let capped = cap_section(lines, 15);
+        assert_eq!(
+            capped,
+            [
+                "line 0",
+                "line 1",
+                "_8 more lines left out (section budget)._"
+            ]
+        );
+    }
+}
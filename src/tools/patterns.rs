@@ -0,0 +1,373 @@
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::{
+    HourBin, TimeRange, active_intervals, activity_heatmap, clip_to_active, format_timestamp,
+    hourly_histogram,
+};
+use crate::format::{code_block, format_duration, format_percent};
+use crate::models::{ChartStyle, ResponseFormat};
+use crate::timezone::Timezone;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Width of the longest bar in bar charts, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Heatmap cells from no activity to the busiest hour, lightest first
+const HEAT_SHADES: [char; 8] = ['.', ':', '-', '=', '+', '*', '#', '@'];
+
+/// Weekday names, from Monday
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Input for the hour-of-day histogram
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HourlyHistogramParams {
+    /// The bucket ID to analyze (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "last week")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Markdown chart: "table" (default) or "bars"
+    #[serde(default)]
+    pub style: ChartStyle,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the weekday by hour heatmap
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ActivityHeatmapParams {
+    /// The window watcher bucket (default: the one with the most recent activity)
+    #[serde(default)]
+    pub bucket_id: Option<String>,
+
+    /// The AFK watcher bucket (default: the AFK watcher on the same host)
+    #[serde(default)]
+    pub afk_bucket_id: Option<String>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "last week")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = patterns_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Total activity per hour of the day.
+    #[tool(
+        description = r#"Total a bucket's event time per hour of the day (24 bins in the bucket's timezone) over a range, summed across all its days, with the number of events starting in each hour. Use this for "when am I most active" questions instead of fetching raw events.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday"
+- `style`: "table" (default) for a table of time, share and events per hour, or "bars" for an ASCII bar chart"#
+    )]
+    async fn aw_hourly_histogram(
+        &self,
+        Parameters(params): Parameters<HourlyHistogramParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let tz = self.bucket_timezone(&params.bucket_id).await;
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.hourly_histogram(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to build hourly histogram: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Active time per weekday and hour of the day.
+    #[tool(
+        description = r#"Total active time (window activity while not AFK) per weekday and hour of the day over a range, as a 7x24 matrix in the bucket's timezone: each cell sums that hour on every such weekday of the range. Markdown gives a compact grid of one character per hour with each weekday's total; JSON gives the matrix in seconds. Use this for "when do I usually work" questions over weeks or months instead of fetching raw events.
+
+Without an AFK watcher on the window bucket's host, all window time counts as active.
+
+## Parameters
+- `bucket_id`: The window watcher bucket (default: the one with the most recent activity)
+- `afk_bucket_id`: The AFK watcher bucket (default: the AFK watcher on the same host)
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "last week""#
+    )]
+    async fn aw_activity_heatmap(
+        &self,
+        Parameters(params): Parameters<ActivityHeatmapParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let empty = |id: &Option<String>| id.as_deref().is_some_and(|id| id.trim().is_empty());
+        if empty(&params.bucket_id) || empty(&params.afk_bucket_id) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        let tz = match &params.bucket_id {
+            Some(bucket_id) => self.bucket_timezone(bucket_id.trim()).await,
+            None => self.config().timezone.clone(),
+        };
+        let range = match self.resolve_range_in(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+            &tz,
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.activity_heatmap(&params, &range, &tz).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to build activity heatmap: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the range, keep the window time while the user was present and
+    /// bin it by weekday and hour
+    async fn activity_heatmap(
+        &self,
+        params: &ActivityHeatmapParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        let (bucket, afk_bucket) = self
+            .summary_buckets(
+                &buckets,
+                params.bucket_id.as_deref(),
+                params.afk_bucket_id.as_deref(),
+            )
+            .await?;
+        let (mut events, afk_events) = self.summary_events(bucket, afk_bucket, range).await?;
+        events.sort_by_key(|event| event.timestamp);
+        let within = match afk_bucket {
+            Some(_) => active_intervals(&afk_events)
+                .into_iter()
+                .filter_map(|interval| range.clip(interval))
+                .collect(),
+            None => vec![(range.start, range.end)],
+        };
+        let matrix = activity_heatmap(&clip_to_active(&events, &within), range, tz);
+        let day_totals: Vec<f64> = matrix.iter().map(|hours| hours.iter().sum()).collect();
+        let total_secs: f64 = day_totals.iter().sum();
+        let peak = (0..7)
+            .flat_map(|day| (0..24).map(move |hour| (day, hour)))
+            .filter(|&(day, hour)| matrix[day][hour] > 0.0)
+            .max_by(|a, b| matrix[a.0][a.1].total_cmp(&matrix[b.0][b.1]).then(b.cmp(a)));
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!("# Activity by Weekday and Hour in {}", bucket.id),
+                    String::new(),
+                    format!(
+                        "- **Range**: {} to {}",
+                        format_timestamp(&range.start),
+                        format_timestamp(&range.end)
+                    ),
+                    format!("- **Timezone**: {}", tz),
+                    format!("- **Active**: {}", format_duration(total_secs)),
+                ];
+                match afk_bucket {
+                    Some(afk_bucket) => lines.push(format!(
+                        "- **Counted**: while {} saw the user",
+                        afk_bucket.id
+                    )),
+                    None => lines.push(
+                        "- **Counted**: all window time (no AFK watcher on this host)".to_string(),
+                    ),
+                }
+                if let Some((day, hour)) = peak {
+                    lines.push(format!(
+                        "- **Busiest hour**: {} {:02}:00-{:02}:00 ({})",
+                        WEEKDAYS[day],
+                        hour,
+                        hour + 1,
+                        format_duration(matrix[day][hour])
+                    ));
+                }
+                lines.push(String::new());
+                lines.push(code_block("", &heatmap_grid(&matrix, &day_totals)));
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": bucket.id,
+                "afk_bucket_id": afk_bucket.map(|b| &b.id),
+                "range": range,
+                "timezone": tz.to_string(),
+                "total_secs": total_secs,
+                "weekdays": WEEKDAYS,
+                "weekday_totals": day_totals,
+                "peak": peak.map(|(day, hour)| serde_json::json!({
+                    "weekday": WEEKDAYS[day],
+                    "hour": hour,
+                    "secs": matrix[day][hour],
+                })),
+                "matrix": matrix,
+            })),
+        })
+    }
+
+    /// Fetch the range and bin its events by hour of the day
+    async fn hourly_histogram(
+        &self,
+        params: &HourlyHistogramParams,
+        range: &TimeRange,
+        tz: &Timezone,
+    ) -> Result<String, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let events = self.range_events(&params.bucket_id, range).await?;
+        let bins = hourly_histogram(&events, range, tz);
+        let total_secs: f64 = bins.iter().map(|bin| bin.secs).sum();
+        let peak = bins
+            .iter()
+            .max_by(|a, b| a.secs.total_cmp(&b.secs).then(b.hour.cmp(&a.hour)));
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    format!("# Activity by Hour of Day in {}", params.bucket_id),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!("- **Timezone**: {}", tz),
+                    format!("- **Total**: {}", format_duration(total_secs)),
+                ];
+                if let Some(peak) = peak.filter(|peak| peak.secs > 0.0) {
+                    lines.push(format!(
+                        "- **Peak hour**: {:02}:00-{:02}:00 ({})",
+                        peak.hour,
+                        peak.hour + 1,
+                        format_duration(peak.secs)
+                    ));
+                }
+                lines.push(String::new());
+                match params.style {
+                    ChartStyle::Table => {
+                        lines.push("| Hour | Time | Share | Events |".to_string());
+                        lines.push("|---|---|---|---|".to_string());
+                        lines.extend(bins.iter().map(|bin| {
+                            let share = match total_secs > 0.0 {
+                                true => format_percent(bin.secs / total_secs * 100.0),
+                                false => "-".to_string(),
+                            };
+                            format!(
+                                "| {:02}:00 | {} | {} | {} |",
+                                bin.hour,
+                                format_duration(bin.secs),
+                                share,
+                                bin.events
+                            )
+                        }));
+                    }
+                    ChartStyle::Bars => lines.push(code_block("", &bar_chart(&bins))),
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": params.bucket_id,
+                "range": range,
+                "timezone": tz.to_string(),
+                "total_secs": total_secs,
+                "peak_hour": peak.filter(|peak| peak.secs > 0.0).map(|peak| peak.hour),
+                "hours": bins,
+            })),
+        })
+    }
+}
+
+/// One row per weekday of one shade per hour, scaled to the busiest hour,
+/// with the weekday's total and a legend
+fn heatmap_grid(matrix: &[[f64; 24]], day_totals: &[f64]) -> String {
+    let max = matrix.iter().flatten().copied().fold(0.0, f64::max);
+    let levels = (HEAT_SHADES.len() - 1) as f64;
+    let mut lines = vec![format!("    {:<6}{:<6}{:<6}{:<6} Total", 0, 6, 12, 18)];
+    lines.extend(
+        matrix
+            .iter()
+            .zip(day_totals)
+            .enumerate()
+            .map(|(day, (hours, total))| {
+                let cells: String = hours
+                    .iter()
+                    .map(|&secs| match max > 0.0 {
+                        true => HEAT_SHADES[(secs / max * levels).ceil() as usize],
+                        false => HEAT_SHADES[0],
+                    })
+                    .collect();
+                format!("{} {} {}", WEEKDAYS[day], cells, format_duration(*total))
+            }),
+    );
+    lines.push(String::new());
+    lines.push(format!(
+        "{} none, {} up to {}",
+        HEAT_SHADES[0],
+        HEAT_SHADES[1..].iter().collect::<String>(),
+        format_duration(max)
+    ));
+    lines.join("\n")
+}
+
+/// One line of `#` per hour, scaled to the busiest hour
+fn bar_chart(bins: &[HourBin]) -> String {
+    let max = bins.iter().map(|bin| bin.secs).fold(0.0, f64::max);
+    bins.iter()
+        .map(|bin| {
+            let length = match max > 0.0 {
+                true => (bin.secs / max * BAR_WIDTH as f64).round() as usize,
+                false => 0,
+            };
+            format!(
+                "{:02} | {:<width$} | {}",
+                bin.hour,
+                "#".repeat(length),
+                format_duration(bin.secs),
+                width = BAR_WIDTH
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,201 @@
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::parse_timestamp;
+use crate::format::{code_block, format_duration};
+use crate::models::{Event, QueryValue, ResponseFormat};
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Default number of events listed per time period
+const DEFAULT_QUERY_EVENT_LIMIT: usize = 50;
+
+/// Input for a query2 request
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct QueryParams {
+    /// The query2 script, statements separated by ";" and ending in a RETURN (e.g., "events = query_bucket(\"aw-watcher-window_myhost\"); RETURN = merge_events_by_keys(events, [\"app\"]);")
+    pub query: String,
+
+    /// Time periods to run the query over, each "start/end" in ISO 8601 format (e.g., "2024-03-15T00:00:00Z/2024-03-16T00:00:00Z")
+    pub timeperiods: Vec<String>,
+
+    /// Maximum events listed per time period in markdown output (default: 50)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = query_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Run an ActivityWatch query2 script over time periods.
+    #[tool(
+        description = r#"Run an ActivityWatch query2 script on the server and return its result for each time period. Use it to filter, merge and total events server-side instead of fetching raw events, e.g. time per app:
+
+events = query_bucket("aw-watcher-window_myhost");
+events = merge_events_by_keys(events, ["app"]);
+RETURN = sort_by_duration(events);
+
+Results of periods that have ended are cached, so refining and re-running a query is cheap. When aw-server cannot run a script, it is run locally on the raw events if the functions it uses are supported; the response says so.
+
+## Parameters
+- `query`: The query2 script; it must assign RETURN
+- `timeperiods`: Time periods as "start/end" in ISO 8601 format
+- `limit`: Maximum events listed per period in markdown output (default: 50)"#
+    )]
+    async fn aw_query(
+        &self,
+        Parameters(params): Parameters<QueryParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if self.config().scope.is_some() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Queries are not available when access is scoped (AW_MCP_SCOPE)",
+            )]));
+        }
+        if params.query.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Query cannot be empty",
+            )]));
+        }
+        if params.timeperiods.is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "At least one time period is required",
+            )]));
+        }
+        for timeperiod in &params.timeperiods {
+            if let Err(e) = validate_timeperiod(timeperiod) {
+                return Ok(CallToolResult::error(vec![Content::text(e)]));
+            }
+        }
+
+        match self.query(&params).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to run query: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Run the query and format the value of each period
+    async fn query(&self, params: &QueryParams) -> Result<String, McpError> {
+        let timeperiods: Vec<String> = params
+            .timeperiods
+            .iter()
+            .map(|t| t.trim().to_string())
+            .collect();
+        let result = self.client.query(&timeperiods, &params.query).await?;
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let limit = params.limit.unwrap_or(DEFAULT_QUERY_EVENT_LIMIT);
+                let mut lines = vec!["# Query Result".to_string()];
+                if result.computed_locally {
+                    lines.push(String::new());
+                    lines.push(
+                        "_aw-server could not run this query; it was computed locally from \
+                         raw events._"
+                            .to_string(),
+                    );
+                }
+                for period in &result.periods {
+                    lines.extend([
+                        String::new(),
+                        format!("## {}", period.timeperiod),
+                        String::new(),
+                    ]);
+                    match &period.value {
+                        QueryValue::Null => lines.push("_null_".to_string()),
+                        QueryValue::Number(n) => {
+                            lines.push(format!("**{}** ({} as seconds)", n, format_duration(*n)))
+                        }
+                        QueryValue::Events(events) => {
+                            lines.extend(self.event_lines(events, limit));
+                        }
+                        QueryValue::EventLists(lists) => {
+                            for (i, events) in lists.iter().enumerate() {
+                                lines.push(format!("### List {}", i + 1));
+                                lines.push(String::new());
+                                lines.extend(self.event_lines(events, limit));
+                            }
+                        }
+                        value => lines.push(code_block(
+                            "json",
+                            &serde_json::to_string_pretty(value).unwrap_or_default(),
+                        )),
+                    }
+                }
+
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&result),
+        })
+    }
+
+    /// Markdown of up to `limit` events with their total duration
+    pub(super) fn event_lines(&self, events: &[Event], limit: usize) -> Vec<String> {
+        let total: f64 = events
+            .iter()
+            .map(|event| event.duration.as_secs_f64())
+            .sum();
+        let mut lines = vec![
+            format!(
+                "{} events, {} in total:",
+                events.len(),
+                format_duration(total)
+            ),
+            String::new(),
+        ];
+        for event in events.iter().take(limit) {
+            lines.push(event.to_markdown(self.config().escape_markdown));
+            lines.push(String::new());
+        }
+        if events.len() > limit {
+            lines.push(format!(
+                "_{} more events not listed._",
+                events.len() - limit
+            ));
+            lines.push(String::new());
+        }
+        lines
+    }
+}
+
+/// Ensure a time period is two ISO 8601 timestamps separated by "/", the
+/// start before the end
+fn validate_timeperiod(timeperiod: &str) -> Result<(), String> {
+    let invalid = || {
+        format!(
+            "Invalid time period '{}'. Expected start/end in ISO 8601 format, e.g. \
+             2024-03-15T00:00:00Z/2024-03-16T00:00:00Z",
+            timeperiod
+        )
+    };
+    let (start, end) = timeperiod.trim().split_once('/').ok_or_else(invalid)?;
+    let start = parse_timestamp(start).map_err(|_| invalid())?;
+    let end = parse_timestamp(end).map_err(|_| invalid())?;
+    if start >= end {
+        return Err(format!(
+            "Time period '{}' must start before it ends",
+            timeperiod
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_timeperiod_requires_ordered_timestamps() {
+        assert!(validate_timeperiod("2024-03-15T00:00:00Z/2024-03-16T00:00:00Z").is_ok());
+        assert!(validate_timeperiod("2024-03-15T00:00:00+09:00/2024-03-15T12:00:00+09:00").is_ok());
+        assert!(validate_timeperiod("2024-03-15T00:00:00Z").is_err());
+        assert!(validate_timeperiod("2024-03-16T00:00:00Z/2024-03-15T00:00:00Z").is_err());
+        assert!(validate_timeperiod("yesterday/today").is_err());
+    }
+}
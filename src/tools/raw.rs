@@ -0,0 +1,130 @@
+use super::{ActivityWatchMcpServer, truncate_response};
+use percent_encoding::percent_decode_str;
+use reqwest::Method;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Input for a raw ActivityWatch API request
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ApiRequestParams {
+    /// API path relative to the API base URL (e.g., "/buckets/" or "/info")
+    pub path: String,
+
+    /// HTTP method (default: "GET"); must be allowed by the server configuration
+    #[serde(default)]
+    pub method: Option<String>,
+
+    /// Query string parameters
+    #[serde(default)]
+    pub query: HashMap<String, String>,
+
+    /// JSON request body
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+}
+
+#[tool_router(router = raw_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Forward a request to an arbitrary ActivityWatch API endpoint.
+    #[tool(
+        description = r#"Send a request to any ActivityWatch REST API endpoint and return the JSON response. Use this only for endpoints without a dedicated tool.
+
+Only GET is allowed unless the server is configured otherwise (AW_MCP_API_METHODS).
+
+## Parameters
+- `path`: API path relative to the API base (e.g., "/info", "/buckets/my-bucket/events")
+- `method`: HTTP method (default: "GET")
+- `query`: Query string parameters as an object (e.g., {"limit": "5"})
+- `body`: JSON request body for methods that accept one"#
+    )]
+    async fn aw_api_request(
+        &self,
+        Parameters(params): Parameters<ApiRequestParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let config = self.config();
+        if config.scope.is_some() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Raw API requests are not available when access is scoped (AW_MCP_SCOPE)",
+            )]));
+        }
+
+        let method = params
+            .method
+            .as_deref()
+            .unwrap_or("GET")
+            .to_ascii_uppercase();
+        let method = match Method::from_bytes(method.as_bytes()) {
+            Ok(method) if config.api_methods.contains(&method) => method,
+            _ => {
+                let allowed: Vec<&str> = config.api_methods.iter().map(Method::as_str).collect();
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Method '{}' is not allowed. Allowed methods: {}",
+                    method,
+                    allowed.join(", ")
+                ))]));
+            }
+        };
+
+        if let Err(e) = validate_path(&params.path) {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+
+        let mut query: Vec<(String, String)> = params.query.into_iter().collect();
+        query.sort();
+
+        match self
+            .client
+            .raw_request(method, &params.path, &query, params.body.as_ref())
+            .await
+        {
+            Ok(value) => {
+                let response = serde_json::to_string_pretty(&value)
+                    .unwrap_or_else(|_| "Error formatting JSON".to_string());
+                Ok(CallToolResult::success(vec![Content::text(
+                    truncate_response(response),
+                )]))
+            }
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "API request failed: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+/// Ensure a path stays within the ActivityWatch API
+fn validate_path(path: &str) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err("Path must start with '/' (e.g., \"/buckets/\")".to_string());
+    }
+    // Servers may decode `%2e` and `%2f`, so check the decoded path too
+    let decoded = percent_decode_str(path).decode_utf8_lossy();
+    let escapes = |path: &str| path.split(['/', '\\']).any(|segment| segment == "..");
+    if path.contains("://") || escapes(path) || escapes(&decoded) {
+        return Err("Path must be relative to the ActivityWatch API".to_string());
+    }
+    if path.contains(['?', '#']) {
+        return Err("Pass query parameters via `query`, not in the path".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_path_rejects_escapes() {
+        assert!(validate_path("/buckets/").is_ok());
+        assert!(validate_path("buckets/").is_err());
+        assert!(validate_path("/../admin").is_err());
+        assert!(validate_path("/http://evil").is_err());
+        assert!(validate_path("/buckets/?limit=1").is_err());
+        assert!(validate_path("/%2e%2e/admin").is_err());
+        assert!(validate_path("/.%2E/admin").is_err());
+        assert!(validate_path("/buckets/..%2fadmin").is_err());
+        assert!(validate_path("/buckets/a%20b/events").is_ok());
+    }
+}
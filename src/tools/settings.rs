@@ -0,0 +1,210 @@
+use super::{ActivityWatchMcpServer, truncate_response};
+use crate::categories::Categories;
+use crate::constants::CATEGORIES_SETTING;
+use crate::format::{code_block, code_span};
+use crate::models::ResponseFormat;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Input for reading server settings
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetSettingsParams {
+    /// The setting to read (e.g., "classes" for aw-webui's categories); all settings when omitted
+    #[serde(default)]
+    pub key: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for storing a server setting
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetSettingParams {
+    /// The setting to store (e.g., "classes")
+    pub key: String,
+
+    /// The new value, any JSON
+    pub value: serde_json::Value,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = settings_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Read aw-server settings.
+    #[tool(
+        description = r#"Read the settings stored on aw-server, such as aw-webui's category rules (`classes`), theme and start page. Use this to see the categories configured in the web UI as they are stored; aw_convert_rules turns them into categories.toml.
+
+## Parameters
+- `key`: The setting to read (default: all settings)"#
+    )]
+    async fn aw_get_settings(
+        &self,
+        Parameters(params): Parameters<GetSettingsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let key = params.key.as_deref().map(str::trim);
+        if let Some(key) = key
+            && let Err(e) = validate_key(key)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+
+        let result = match key {
+            Some(key) => self.client.get_setting(key).await,
+            None => self.client.get_settings().await,
+        };
+        let value = match result {
+            Ok(value) => value,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to read settings: {}",
+                    e.message
+                ))]));
+            }
+        };
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => settings_markdown(key, &value),
+            ResponseFormat::Json => serde_json::to_string_pretty(&value)
+                .unwrap_or_else(|_| "Error formatting JSON".to_string()),
+        };
+        Ok(CallToolResult::success(vec![Content::text(
+            truncate_response(response),
+        )]))
+    }
+
+    /// Store an aw-server setting.
+    #[tool(
+        description = r#"Store a setting on aw-server, replacing its value, e.g. aw-webui's category rules (`classes`, a list of classes as returned by aw_get_settings or written by aw_convert_rules). New `classes` are checked before they are stored. aw-webui picks up changes when reloaded. Only available when writes are enabled (AW_MCP_ALLOW_WRITES) and access is not scoped (AW_MCP_SCOPE), as settings are shared by all devices.
+
+## Parameters
+- `key`: The setting to store
+- `value`: The new value, any JSON"#
+    )]
+    async fn aw_set_setting(
+        &self,
+        Parameters(params): Parameters<SetSettingParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = self.check_writes() {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if self.config().scope.is_some() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Settings cannot be changed when access is scoped (AW_MCP_SCOPE)",
+            )]));
+        }
+        let key = params.key.trim();
+        if let Err(e) = validate_key(key) {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if key == CATEGORIES_SETTING
+            && let Err(e) = Categories::from_webui(&params.value)
+        {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid aw-webui categories: {}",
+                e
+            ))]));
+        }
+
+        if let Err(e) = self.client.set_setting(key, &params.value).await {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to store setting: {}",
+                e.message
+            ))]));
+        }
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let value = serde_json::to_string_pretty(&params.value)
+                    .unwrap_or_else(|_| "Error formatting JSON".to_string());
+                [
+                    format!("# Stored Setting {}", code_span(key)),
+                    code_block("json", &value),
+                ]
+                .join("\n\n")
+            }
+            ResponseFormat::Json => serde_json::to_string_pretty(&serde_json::json!({
+                "key": key,
+                "value": params.value,
+                "stored": true,
+            }))
+            .unwrap_or_else(|_| "Error formatting JSON".to_string()),
+        };
+        Ok(CallToolResult::success(vec![Content::text(
+            truncate_response(response),
+        )]))
+    }
+}
+
+/// Ensure a setting key names a single setting
+fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty() {
+        return Err("Setting key cannot be empty".to_string());
+    }
+    if key.contains(['/', '?', '#']) || key == ".." {
+        return Err(format!("Invalid setting key '{}'", key));
+    }
+    Ok(())
+}
+
+/// Settings as markdown: one section per setting, or just the one asked for
+fn settings_markdown(key: Option<&str>, value: &serde_json::Value) -> String {
+    let json = |value: &serde_json::Value| {
+        code_block(
+            "json",
+            &serde_json::to_string_pretty(value)
+                .unwrap_or_else(|_| "Error formatting JSON".to_string()),
+        )
+    };
+    if let Some(key) = key {
+        let body = match value.is_null() {
+            true => "_Not set._".to_string(),
+            false => json(value),
+        };
+        return [format!("# Setting {}", code_span(key)), body].join("\n\n");
+    }
+
+    let mut lines = vec!["# aw-server Settings".to_string()];
+    match value.as_object() {
+        Some(settings) if !settings.is_empty() => {
+            let mut keys: Vec<&String> = settings.keys().collect();
+            keys.sort();
+            for key in keys {
+                lines.push(String::new());
+                lines.push(format!("## {}", code_span(key)));
+                lines.push(String::new());
+                lines.push(json(&settings[key]));
+            }
+        }
+        Some(_) => {
+            lines.push(String::new());
+            lines.push("_No settings stored._".to_string());
+        }
+        None => {
+            lines.push(String::new());
+            lines.push(json(value));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lists_settings_by_key() {
+        assert!(validate_key("classes").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("../export").is_err());
+
+        let settings = json!({"theme": "dark", "classes": []});
+        let markdown = settings_markdown(None, &settings);
+        assert!(markdown.find("`classes`").unwrap() < markdown.find("`theme`").unwrap());
+        assert!(settings_markdown(Some("startPage"), &json!(null)).ends_with("_Not set._"));
+    }
+}
@@ -0,0 +1,148 @@
+use super::ActivityWatchMcpServer;
+use crate::analysis::{TimeRange, WINDOW_BUCKET_TYPE, daily_summary, totals_by_key};
+use crate::categories::category_totals;
+use crate::constants::{DEFAULT_CATEGORY_DEPTH, SNAPSHOT_LIST_LIMIT};
+use crate::scrub::scrub_secrets;
+use crate::snapshot::{DaySnapshot, SnapshotBucket, SnapshotDaily, SnapshotEntry};
+use rmcp::ErrorData as McpError;
+use std::path::Path;
+
+impl ActivityWatchMcpServer {
+    /// Summarize the current day across all window buckets in scope, with
+    /// names pseudonymized and secrets masked as in tool output
+    pub async fn day_snapshot(&self) -> Result<DaySnapshot, McpError> {
+        let config = self.config();
+        let now = self.now();
+        let date = config.timezone.date_of(now);
+        let range = TimeRange::new(config.timezone.start_of_day(date), now)
+            .map_err(|e| McpError::internal_error(e, None))?;
+
+        let buckets = self.lookup_buckets().await?;
+        // The file is read outside MCP, so it is filtered here
+        let pseudonymizer = self.pseudonymizer().await;
+        let filter = |text: String| {
+            let text = match &pseudonymizer {
+                Some(pseudonymizer) => pseudonymizer.apply(&text),
+                None => text,
+            };
+            match config.scrub_secrets {
+                true => scrub_secrets(&text, config.scrub_emails),
+                false => text,
+            }
+        };
+        let mut windows: Vec<_> = buckets
+            .values()
+            .filter(|bucket| bucket.bucket_type.as_deref() == Some(WINDOW_BUCKET_TYPE))
+            .filter(|bucket| self.in_scope(bucket))
+            .collect();
+        windows.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut events = Vec::new();
+        let mut bucket_totals = Vec::new();
+        for bucket in windows {
+            let bucket_events = self.range_events(&bucket.id, &range).await?;
+            let total_secs = totals_by_key(&bucket_events, "app", Some(&range))
+                .iter()
+                .fold(0.0, |total, (_, secs)| total + secs);
+            bucket_totals.push(SnapshotBucket {
+                id: filter(bucket.id.clone()),
+                hostname: bucket.hostname.clone().map(filter),
+                total_secs,
+            });
+            events.extend(bucket_events);
+        }
+        bucket_totals.sort_by(|a, b| b.total_secs.total_cmp(&a.total_secs));
+
+        let entries = |totals: Vec<(String, f64)>| {
+            totals
+                .into_iter()
+                .take(SNAPSHOT_LIST_LIMIT)
+                .map(|(name, duration_secs)| SnapshotEntry {
+                    name: filter(name),
+                    duration_secs,
+                })
+                .collect::<Vec<_>>()
+        };
+        let categories = match self.categories().await {
+            Ok((categories, _)) => {
+                category_totals(&events, &categories, DEFAULT_CATEGORY_DEPTH, Some(&range)).leaves
+            }
+            Err(_) => Vec::new(),
+        };
+        // The summary aw_daily_summary gives without bucket arguments
+        let daily = match self.summary_buckets(&buckets, None, None).await {
+            Ok((bucket, afk_bucket)) => {
+                let (window_events, afk_events) =
+                    self.summary_events(bucket, afk_bucket, &range).await?;
+                let summary =
+                    daily_summary(&window_events, &afk_events, &range, SNAPSHOT_LIST_LIMIT);
+                Some(SnapshotDaily {
+                    bucket_id: filter(bucket.id.clone()),
+                    afk_bucket_id: afk_bucket.map(|bucket| filter(bucket.id.clone())),
+                    active_secs: summary.active_secs,
+                    afk_secs: summary.afk_secs,
+                    present_secs: summary.present_secs,
+                    first_activity: summary.first_activity,
+                    last_activity: summary.last_activity,
+                    apps: entries(summary.top_apps),
+                    titles: entries(summary.top_titles),
+                })
+            }
+            Err(_) => None,
+        };
+
+        Ok(DaySnapshot {
+            date,
+            timezone: config.timezone.to_string(),
+            generated_at: now,
+            start: range.start,
+            end: range.end,
+            total_secs: bucket_totals
+                .iter()
+                .fold(0.0, |total, b| total + b.total_secs),
+            buckets: bucket_totals,
+            apps: entries(totals_by_key(&events, "app", Some(&range))),
+            titles: entries(totals_by_key(&events, "title", Some(&range))),
+            categories: entries(categories),
+            daily,
+        })
+    }
+
+    /// Take a snapshot of the current day and write it to `path`
+    pub async fn write_snapshot(&self, path: &Path) -> anyhow::Result<()> {
+        let snapshot = self
+            .day_snapshot()
+            .await
+            .map_err(|e| anyhow::anyhow!("Cannot summarize today: {}", e.message))?;
+        snapshot.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::test_event;
+    use chrono::{TimeZone, Utc};
+
+    #[tokio::test]
+    async fn pseudonymizes_the_snapshot() {
+        let (_fake, server) = crate::tools::test_server(vec![test_event(
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+            60.0,
+            serde_json::json!({"app": "Code", "title": "ssh test"}),
+        )])
+        .await;
+        let server = server.with_config(crate::config::Config {
+            pseudonymize: true,
+            now: Some(Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()),
+            ..Default::default()
+        });
+
+        let snapshot = server.day_snapshot().await.unwrap();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!json.contains("ssh test"), "{}", json);
+        assert!(snapshot.titles[0].name.starts_with("ssh host-"));
+        let daily = snapshot.daily.unwrap();
+        assert_eq!(daily.active_secs, 60.0);
+        assert_eq!(daily.titles[0].name, snapshot.titles[0].name);
+    }
+}
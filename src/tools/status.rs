@@ -0,0 +1,670 @@
+use super::write::local_hostname;
+use super::{ActivityWatchMcpServer, json_text, truncate_response};
+use crate::analysis::{WatcherState, WatcherStatus, assess_watchers};
+use crate::config::Config;
+use crate::constants::DEFAULT_STALE_WATCHER_MINUTES;
+use crate::format::{code_span, format_duration};
+use crate::models::{AwServerInfo, ResponseFormat};
+use chrono::Duration;
+use reqwest::Method;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Input for the version and capability report
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct McpVersionParams {
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the aw-server information
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ServerInfoParams {
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the tool catalogue
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HelpParams {
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for the watcher status check
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StatusParams {
+    /// Buckets of the watchers to check (default: all buckets)
+    #[serde(default)]
+    pub bucket_ids: Option<Vec<String>>,
+
+    /// Minutes without events after which a watcher counts as silent (default: 10)
+    #[serde(default)]
+    pub stale_minutes: Option<u64>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Tasks the tool catalogue is grouped by, with the tools for each; tools
+/// not listed here appear under "other"
+const TOOL_GROUPS: &[(&str, &[&str])] = &[
+    (
+        "summaries",
+        &[
+            "aw_daily_summary",
+            "aw_weekly_summary",
+            "aw_top_apps",
+            "aw_active_time",
+            "aw_diff_activity",
+            "aw_time_series",
+            "aw_hourly_histogram",
+            "aw_activity_heatmap",
+            "aw_drilldown",
+            "aw_category_summary",
+            "aw_browser_summary",
+            "aw_review_load",
+            "aw_commit_time",
+            "aw_ticket_time",
+            "aw_review_pack",
+            "aw_merge_devices",
+            "aw_render_template",
+        ],
+    ),
+    (
+        "categories",
+        &[
+            "aw_uncategorized",
+            "aw_test_rule",
+            "aw_compare_rules",
+            "aw_convert_rules",
+        ],
+    ),
+    (
+        "raw data",
+        &[
+            "aw_list_buckets",
+            "aw_get_bucket",
+            "aw_get_events",
+            "aw_get_event",
+            "aw_get_event_count",
+            "aw_estimate",
+            "aw_query",
+            "aw_refresh_buckets",
+            "aw_bucket_history",
+            "aw_api_request",
+        ],
+    ),
+    (
+        "exports",
+        &["aw_export_bucket", "aw_import_bucket", "aw_data_inventory"],
+    ),
+    ("settings", &["aw_get_settings", "aw_set_setting"]),
+    (
+        "writing",
+        &[
+            "aw_heartbeat",
+            "aw_insert_events",
+            "aw_delete_event",
+            "aw_delete_bucket",
+        ],
+    ),
+    (
+        "status",
+        &[
+            "aw_help",
+            "aw_mcp_version",
+            "aw_server_info",
+            "aw_status",
+            "aw_data_quality",
+        ],
+    ),
+];
+
+/// Group of the admin tools in the catalogue
+const ADMIN_GROUP: &str = "admin";
+
+/// A tool in the catalogue
+#[derive(Debug, Serialize)]
+struct ToolEntry {
+    name: String,
+    summary: String,
+    /// Why the tool cannot be used with the current configuration
+    disabled: Option<String>,
+}
+
+/// The MCP server's version, capabilities and connected aw-server
+#[derive(Debug, Serialize)]
+struct VersionReport {
+    version: &'static str,
+    features: Vec<&'static str>,
+    tools: usize,
+    read_only: bool,
+    api_methods: Vec<String>,
+    api_url: String,
+    offline_cache: bool,
+    max_concurrent_requests: usize,
+    aw_server_version: Option<String>,
+    aw_server_flavor: String,
+    aw_server_error: Option<String>,
+}
+
+#[tool_router(router = status_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// List the available tools grouped by task.
+    #[tool(
+        description = r#"List the tools of this server grouped by task (summaries, categories, raw data, exports, status, admin) with a one-line summary each, generated from the running configuration. Tools that cannot be used right now are marked with the reason, e.g. admin tools that are switched off or category tools without category rules. Call this to find the right tool for a task."#
+    )]
+    async fn aw_help(
+        &self,
+        Parameters(params): Parameters<HelpParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let groups = self.tool_catalogue().await;
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let total: usize = groups.iter().map(|(_, tools)| tools.len()).sum();
+                let disabled = groups
+                    .iter()
+                    .flat_map(|(_, tools)| tools)
+                    .filter(|tool| tool.disabled.is_some())
+                    .count();
+                let mut lines = vec![
+                    "# ActivityWatch MCP Tools".to_string(),
+                    String::new(),
+                    format!(
+                        "{} tools, {} usable with the current configuration.",
+                        total,
+                        total - disabled
+                    ),
+                ];
+                for (group, tools) in &groups {
+                    lines.push(String::new());
+                    lines.push(format!("## {}", capitalize(group)));
+                    lines.push(String::new());
+                    lines.extend(tools.iter().map(|tool| match &tool.disabled {
+                        None => format!("- `{}`: {}", tool.name, tool.summary),
+                        Some(reason) => format!(
+                            "- ~~`{}`~~: {} **Disabled**: {}",
+                            tool.name, tool.summary, reason
+                        ),
+                    }));
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => {
+                let groups: serde_json::Map<String, serde_json::Value> = groups
+                    .into_iter()
+                    .map(|(group, tools)| (group.to_string(), serde_json::json!(tools)))
+                    .collect();
+                json_text(&groups)
+            }
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    /// Report the MCP server's version, capabilities and connected aw-server.
+    #[tool(
+        description = r#"Report this MCP server's version, enabled features, configuration summary (read-only or write access, offline cache) and the connected aw-server version. Call this first when troubleshooting or reporting a problem."#
+    )]
+    async fn aw_mcp_version(
+        &self,
+        Parameters(params): Parameters<McpVersionParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let report = self.version_report().await;
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let features = if report.features.is_empty() {
+                    "none".to_string()
+                } else {
+                    report.features.join(", ")
+                };
+                let aw_server = match (&report.aw_server_version, &report.aw_server_error) {
+                    (Some(version), _) => format!("{} ({})", version, report.aw_server_flavor),
+                    (None, Some(e)) => format!("unreachable ({})", e),
+                    (None, None) => "unknown version".to_string(),
+                };
+                let concurrency = match report.max_concurrent_requests {
+                    0 => "unlimited".to_string(),
+                    n => n.to_string(),
+                };
+
+                [
+                    "# ActivityWatch MCP Server".to_string(),
+                    String::new(),
+                    format!("- **Version**: {}", report.version),
+                    format!("- **Features**: {}", features),
+                    format!("- **Tools**: {}", report.tools),
+                    format!(
+                        "- **Access**: {} (API methods: {})",
+                        if report.read_only {
+                            "read-only"
+                        } else {
+                            "read-write"
+                        },
+                        report.api_methods.join(", ")
+                    ),
+                    format!(
+                        "- **Offline cache**: {}",
+                        if report.offline_cache {
+                            "enabled"
+                        } else {
+                            "disabled"
+                        }
+                    ),
+                    format!("- **Max concurrent requests**: {}", concurrency),
+                    format!("- **API URL**: {}", report.api_url),
+                    format!("- **aw-server**: {}", aw_server),
+                ]
+                .join("\n")
+            }
+            ResponseFormat::Json => json_text(&report),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    /// Report the connected aw-server's `/info`.
+    #[tool(
+        description = r#"Report what the connected aw-server says about itself (its /info endpoint): version and implementation, hostname, device ID, testing flag and any build details, with the URL queried. Use this to diagnose a wrong port or instance, e.g. a testing server (port 5666) answering instead of the real one (port 5600), or a server on another machine."#
+    )]
+    async fn aw_server_info(
+        &self,
+        Parameters(params): Parameters<ServerInfoParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let api_url = self.client.api_url();
+        let info = match self.client.get_info().await {
+            Ok(info) => info,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to reach aw-server at {}: {}",
+                    api_url, e.message
+                ))]));
+            }
+        };
+        let notes = server_notes(&info, &local_hostname());
+
+        let response = match params.response_format {
+            ResponseFormat::Markdown => {
+                let text = |value: Option<&str>| self.markdown_text(value.unwrap_or("unknown"));
+                let testing = match info.testing {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "unknown",
+                };
+                let mut lines = vec![
+                    "# aw-server".to_string(),
+                    String::new(),
+                    format!("- **API URL**: {}", api_url),
+                    format!(
+                        "- **Version**: {} ({})",
+                        text(info.version.as_deref()),
+                        info.flavor()
+                    ),
+                    format!("- **Hostname**: {}", text(info.hostname.as_deref())),
+                    format!("- **Device ID**: {}", text(info.device_id.as_deref())),
+                    format!("- **Testing**: {}", testing),
+                ];
+                let mut extra: Vec<_> = info.extra.iter().collect();
+                extra.sort_by(|a, b| a.0.cmp(b.0));
+                for (key, value) in extra {
+                    let value = match value {
+                        serde_json::Value::String(value) => value.clone(),
+                        value => value.to_string(),
+                    };
+                    let key = self.markdown_text(key);
+                    lines.push(format!("- **{}**: {}", key, text(Some(&value))));
+                }
+                if !notes.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(notes.iter().map(|note| format!("> {}", note)));
+                }
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "api_url": api_url,
+                "flavor": info.flavor(),
+                "info": info,
+                "notes": notes,
+            })),
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    /// Check which watchers are alive and reporting.
+    #[tool(
+        description = r#"Check whether each watcher is still reporting by looking at the age of its bucket's latest event. Use this when data looks missing, to notice broken tracking early.
+
+A watcher silent for longer than `stale_minutes` is reported as:
+- **dead** when the AFK watcher of its host shows the user as present (or it is the AFK watcher itself, which reports even while the user is away)
+- **quiet** when the user is away or their status is unknown
+
+## Parameters
+- `bucket_ids`: Buckets to check (default: all)
+- `stale_minutes`: Minutes without events before a watcher counts as silent (default: 10)"#
+    )]
+    async fn aw_status(
+        &self,
+        Parameters(params): Parameters<StatusParams>,
+    ) -> Result<CallToolResult, McpError> {
+        match self.watcher_status(&params).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to check watcher status: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the latest event of each watcher bucket and assess it
+    async fn watcher_status(&self, params: &StatusParams) -> Result<String, McpError> {
+        let stale_minutes = params
+            .stale_minutes
+            .unwrap_or(DEFAULT_STALE_WATCHER_MINUTES);
+        let buckets = self.client.buckets().await?;
+        let mut selected: Vec<_> = match &params.bucket_ids {
+            Some(ids) => {
+                let mut selected = Vec::with_capacity(ids.len());
+                for id in ids {
+                    self.check_scope(id).await?;
+                    let bucket = buckets.get(id).ok_or_else(|| {
+                        McpError::invalid_params(format!("There's no bucket named {}", id), None)
+                    })?;
+                    selected.push(bucket);
+                }
+                selected
+            }
+            None => buckets.values().filter(|b| self.in_scope(b)).collect(),
+        };
+        selected.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut latest = Vec::with_capacity(selected.len());
+        for bucket in selected {
+            let events = self
+                .client
+                .get_events(&bucket.id, Some(1), None, None)
+                .await?;
+            latest.push((bucket, events.into_iter().next()));
+        }
+        let mut statuses =
+            assess_watchers(&latest, self.now(), Duration::minutes(stale_minutes as i64));
+        // Problems first
+        statuses.sort_by_key(|s| match s.state {
+            WatcherState::Dead => 0,
+            WatcherState::Quiet => 1,
+            WatcherState::Empty => 2,
+            WatcherState::Alive => 3,
+        });
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let count = |state| statuses.iter().filter(|s| s.state == state).count();
+                let mut lines = vec![
+                    "# Watcher Status".to_string(),
+                    String::new(),
+                    format!(
+                        "{} alive, {} dead, {} quiet, {} empty (silent means no events for {}m)",
+                        count(WatcherState::Alive),
+                        count(WatcherState::Dead),
+                        count(WatcherState::Quiet),
+                        count(WatcherState::Empty),
+                        stale_minutes
+                    ),
+                    String::new(),
+                ];
+                if statuses.is_empty() {
+                    lines.push("_No buckets found._".to_string());
+                }
+                for status in &statuses {
+                    lines.push(self.status_line(status));
+                }
+                if count(WatcherState::Dead) > 0 {
+                    lines.push(String::new());
+                    lines.push(
+                        "_Dead watchers miss activity while you work; check that they are running._"
+                            .to_string(),
+                    );
+                }
+                truncate_response(lines.join("\n"))
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "stale_minutes": stale_minutes,
+                "watchers": statuses,
+            })),
+        })
+    }
+
+    /// One markdown line describing a watcher's state
+    fn status_line(&self, status: &WatcherStatus) -> String {
+        let host = match &status.hostname {
+            Some(hostname) => format!(" ({})", self.markdown_text(hostname)),
+            None => String::new(),
+        };
+        let seen = match (status.last_seen, status.age_secs) {
+            (Some(last_seen), Some(age)) => format!(
+                "last event {} ago ({} UTC)",
+                format_duration(age),
+                last_seen.format("%Y-%m-%d %H:%M:%S")
+            ),
+            _ => "no events".to_string(),
+        };
+        format!(
+            "- **{}** {}{}: {}",
+            status.state,
+            code_span(&status.bucket_id),
+            host,
+            seen
+        )
+    }
+
+    /// All tools, offered or not, grouped by task and checked against the
+    /// current configuration; empty groups are left out
+    async fn tool_catalogue(&self) -> Vec<(&'static str, Vec<ToolEntry>)> {
+        let config = self.config();
+        let categories_missing = self.categories().await.err().map(|e| e.message.to_string());
+        let disabled_reason = |name: &str| match name {
+            "aw_api_request" | "aw_query" | "aw_set_setting" if config.scope.is_some() => {
+                Some("access is scoped by AW_MCP_SCOPE".to_string())
+            }
+            "aw_heartbeat" | "aw_insert_events" | "aw_delete_event" | "aw_delete_bucket"
+            | "aw_import_bucket" | "aw_set_setting"
+                if !config.allow_writes =>
+            {
+                Some("writes are disabled (AW_MCP_ALLOW_WRITES)".to_string())
+            }
+            "aw_category_summary" | "aw_uncategorized" | "aw_test_rule" | "aw_compare_rules" => {
+                categories_missing.clone()
+            }
+            _ => None,
+        };
+        let builtin = self.builtin_tool_names();
+        let builtin: Vec<&str> = builtin.iter().map(String::as_str).collect();
+        let entry = |tool: &Tool, disabled: Option<String>| ToolEntry {
+            name: config.tool_names.external(&tool.name),
+            summary: tool
+                .description
+                .as_deref()
+                .map(|d| config.tool_names.rename_text(&summary(d), &builtin))
+                .unwrap_or_default(),
+            disabled,
+        };
+
+        let tools = self.tool_router.list_all();
+        let mut groups: Vec<(&'static str, Vec<ToolEntry>)> = TOOL_GROUPS
+            .iter()
+            .map(|(group, names)| {
+                let entries = names
+                    .iter()
+                    .filter_map(|name| tools.iter().find(|tool| tool.name == *name))
+                    .map(|tool| entry(tool, disabled_reason(&tool.name)))
+                    .collect();
+                (*group, entries)
+            })
+            .collect();
+        let mut other: Vec<ToolEntry> = tools
+            .iter()
+            .filter(|tool| {
+                !TOOL_GROUPS
+                    .iter()
+                    .any(|(_, names)| names.contains(&&*tool.name))
+            })
+            .map(|tool| entry(tool, disabled_reason(&tool.name)))
+            .collect();
+        other.sort_by(|a, b| a.name.cmp(&b.name));
+        groups.push(("other", other));
+
+        let admin_disabled = (!config.admin_tools).then(|| {
+            "admin tools are off (set AW_MCP_ADMIN_TOOLS or start with --admin)".to_string()
+        });
+        let mut admin: Vec<ToolEntry> = self
+            .admin_router
+            .list_all()
+            .iter()
+            .map(|tool| entry(tool, admin_disabled.clone()))
+            .collect();
+        admin.sort_by(|a, b| a.name.cmp(&b.name));
+        groups.push((ADMIN_GROUP, admin));
+
+        groups.retain(|(_, tools)| !tools.is_empty());
+        groups
+    }
+
+    /// Collect the version report; an unreachable aw-server is reported, not an error
+    async fn version_report(&self) -> VersionReport {
+        let (aw_server_version, aw_server_flavor, aw_server_error) =
+            match self.client.get_info().await {
+                Ok(info) => (info.version.clone(), info.flavor().to_string(), None),
+                Err(e) => (
+                    None,
+                    self.client.cached_flavor().to_string(),
+                    Some(e.message.to_string()),
+                ),
+            };
+
+        let config = self.config();
+        VersionReport {
+            version: env!("CARGO_PKG_VERSION"),
+            features: enabled_features(),
+            tools: self.list_all_tools().len(),
+            read_only: is_read_only(&config),
+            api_methods: config.api_methods.iter().map(ToString::to_string).collect(),
+            api_url: self.client.api_url(),
+            offline_cache: config.cache_dir.is_some(),
+            max_concurrent_requests: config.max_concurrent_requests,
+            aw_server_version,
+            aw_server_flavor,
+            aw_server_error,
+        }
+    }
+}
+
+/// Hints about an aw-server that may not be the one the user meant
+fn server_notes(info: &AwServerInfo, local_hostname: &str) -> Vec<String> {
+    let mut notes = Vec::new();
+    if info.testing == Some(true) {
+        notes.push(
+            "This is a testing instance (aw-server --testing, port 5666 by default); real \
+             data is usually on port 5600."
+                .to_string(),
+        );
+    }
+    let short = |name: &str| name.split('.').next().unwrap_or_default().to_lowercase();
+    if let Some(hostname) = &info.hostname
+        && local_hostname != "unknown"
+        && short(hostname) != short(local_hostname)
+    {
+        notes.push(format!(
+            "aw-server runs on {}, not on this machine ({}); check ACTIVITYWATCH_URL if a \
+             local server was expected.",
+            hostname, local_hostname
+        ));
+    }
+    notes
+}
+
+/// The first sentence of a tool description
+fn summary(description: &str) -> String {
+    let first_line = description.lines().next().unwrap_or_default();
+    match first_line.find(". ") {
+        Some(end) => first_line[..=end].to_string(),
+        None => first_line.to_string(),
+    }
+}
+
+/// Upper-case the first letter of a group name
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Cargo features this binary was built with
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "compression") {
+        features.push("compression");
+    }
+    if cfg!(feature = "templates") {
+        features.push("templates");
+    }
+    if cfg!(feature = "blocking") {
+        features.push("blocking");
+    }
+    if cfg!(feature = "http") {
+        features.push("http");
+    }
+    features
+}
+
+/// Whether nothing can change aw-server data: the write tools are disabled
+/// and `aw_api_request` only allows reading methods
+fn is_read_only(config: &Config) -> bool {
+    !config.allow_writes
+        && config
+            .api_methods
+            .iter()
+            .all(|m| *m == Method::GET || *m == Method::HEAD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_requires_writes_disabled() {
+        let mut config = Config {
+            api_methods: vec![Method::GET],
+            ..Config::default()
+        };
+        assert!(is_read_only(&config));
+        config.allow_writes = true;
+        assert!(!is_read_only(&config));
+        config.allow_writes = false;
+        config.api_methods.push(Method::POST);
+        assert!(!is_read_only(&config));
+    }
+
+    #[test]
+    fn notes_servers_other_than_the_local_one() {
+        let info = |json: &str| serde_json::from_str::<AwServerInfo>(json).unwrap();
+        let local = info(r#"{"hostname": "Laptop.local", "testing": false}"#);
+        assert!(server_notes(&local, "laptop").is_empty());
+
+        let notes = server_notes(&info(r#"{"hostname": "desk", "testing": true}"#), "laptop");
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].contains("port 5666"));
+        assert!(notes[1].contains("desk"));
+    }
+}
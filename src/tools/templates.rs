@@ -0,0 +1,239 @@
+use super::{ActivityWatchMcpServer, truncate_response};
+use crate::analysis::{TimeRange, format_timestamp, totals_by_key};
+use crate::categories::category_totals;
+use crate::constants::{DEFAULT_CATEGORY_DEPTH, DEFAULT_TEMPLATE_LIMIT};
+use crate::format::{format_duration, round_percent};
+use crate::template::Template;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// Input for rendering a report template
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenderTemplateParams {
+    /// The bucket ID to report on (e.g., "aw-watcher-window_hostname")
+    pub bucket_id: String,
+
+    /// Name of a template file in the AW_MCP_TEMPLATES directory (e.g., "standup" for standup.jinja)
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Template text to render instead of a named template
+    #[serde(default)]
+    pub source: Option<String>,
+
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "2024-03", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Maximum apps, titles and categories passed to the template (default: 10)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Category levels to roll subcategories up to (default: 1)
+    #[serde(default)]
+    pub depth: Option<usize>,
+}
+
+#[tool_router(router = template_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Render a user-provided report template with summary data.
+    #[tool(
+        description = r#"Render a report from a template, for report formats of the user's own design (standup notes, timesheets, journal entries). Templates use Jinja syntax: `{{ total }}`, `{% for app in apps %}{{ app.rank }}. {{ app.name }}: {{ app.duration }}{% endfor %}`, `{% if categories %}...{% else %}...{% endif %}`, `{# comments #}`. Inserted values are Markdown-escaped; `{{ value | safe }}` inserts one verbatim.
+
+The template receives: `bucket_id`, `start`, `end`, `timezone`, `total_secs`, `total`, and the lists `apps`, `titles` and `categories` (longest first; each item has `rank`, `name`, `duration_secs`, `duration` and `percent`). `categories` is empty when no category rules are defined.
+
+## Parameters
+- `bucket_id`: The bucket ID (e.g., "aw-watcher-window_hostname")
+- `template`: Name of a template in the AW_MCP_TEMPLATES directory, with or without its `.jinja` extension
+- `source`: Template text, instead of a named template
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07", "2024-03" or "yesterday"
+- `limit`: Maximum items per list (default: 10)
+- `depth`: Category levels to keep (default: 1)"#
+    )]
+    async fn aw_render_template(
+        &self,
+        Parameters(params): Parameters<RenderTemplateParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.depth == Some(0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Depth must be at least 1",
+            )]));
+        }
+
+        let source = match (&params.template, &params.source) {
+            (Some(name), None) => self.load_template(name),
+            (None, Some(source)) => Ok(source.clone()),
+            (Some(_), Some(_)) => {
+                Err("Give either a template name or its source, not both".to_string())
+            }
+            (None, None) => Err(match self.template_names() {
+                Ok(names) if !names.is_empty() => format!(
+                    "Give a template name or source. Available templates: {}",
+                    names.join(", ")
+                ),
+                _ => "Give a template name or source".to_string(),
+            }),
+        };
+        let template = match source.map(|source| Template::parse(&source)) {
+            Ok(Ok(template)) => template,
+            Ok(Err(e)) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Invalid template: {}",
+                    e
+                ))]));
+            }
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        let data = match self.template_data(&params, &range).await {
+            Ok(data) => data,
+            Err(e) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to collect report data: {}",
+                    e.message
+                ))]));
+            }
+        };
+        match template.render(&data) {
+            Ok(report) => Ok(CallToolResult::success(vec![Content::text(
+                truncate_response(report),
+            )])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to render template: {}",
+                e
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Read a named template from `AW_MCP_TEMPLATES`
+    fn load_template(&self, name: &str) -> Result<String, String> {
+        let Some(dir) = self.config().templates_dir.clone() else {
+            return Err(
+                "No template directory: set AW_MCP_TEMPLATES or pass the template source"
+                    .to_string(),
+            );
+        };
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(format!("Invalid template name '{}'", name));
+        }
+        let path = dir.join(name);
+        let path = if path.is_file() {
+            path
+        } else {
+            dir.join(format!("{}.jinja", name))
+        };
+        std::fs::read_to_string(&path).map_err(|_| {
+            let names = self.template_names().unwrap_or_default();
+            format!(
+                "Template '{}' not found in {}. Available: {}",
+                name,
+                dir.display(),
+                names.join(", ")
+            )
+        })
+    }
+
+    /// Names of the templates in `AW_MCP_TEMPLATES`, sorted
+    fn template_names(&self) -> Result<Vec<String>, String> {
+        let Some(dir) = self.config().templates_dir.clone() else {
+            return Ok(Vec::new());
+        };
+        let entries = std::fs::read_dir(&dir).map_err(|e| e.to_string())?;
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| !name.starts_with('.'))
+            .map(|name| {
+                name.strip_suffix(".jinja")
+                    .map(str::to_string)
+                    .unwrap_or(name)
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Fetch the range and build the data passed to templates
+    async fn template_data(
+        &self,
+        params: &RenderTemplateParams,
+        range: &TimeRange,
+    ) -> Result<Value, McpError> {
+        self.check_scope(&params.bucket_id).await?;
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let events = self.range_events(&params.bucket_id, range).await?;
+
+        let apps = totals_by_key(&events, "app", Some(range));
+        let total_secs = apps.iter().fold(0.0, |total, (_, secs)| total + secs);
+        let limit = params.limit.unwrap_or(DEFAULT_TEMPLATE_LIMIT);
+        let items = |totals: &[(String, f64)]| {
+            totals
+                .iter()
+                .take(limit)
+                .enumerate()
+                .map(|(i, (name, secs))| {
+                    json!({
+                        "rank": i + 1,
+                        "name": name,
+                        "duration_secs": secs,
+                        "duration": format_duration(*secs),
+                        "percent": match total_secs > 0.0 {
+                            true => round_percent(secs / total_secs * 100.0),
+                            false => 0.0,
+                        },
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Reports work without category rules; they just get no categories
+        let categories = match self.categories().await {
+            Ok((categories, _)) => {
+                let depth = params.depth.unwrap_or(DEFAULT_CATEGORY_DEPTH);
+                items(&category_totals(&events, &categories, depth, Some(range)).rolled_up)
+            }
+            Err(_) => Vec::new(),
+        };
+
+        Ok(json!({
+            "bucket_id": params.bucket_id,
+            "start": start,
+            "end": end,
+            "timezone": self.config().timezone.to_string(),
+            "total_secs": total_secs,
+            "total": format_duration(total_secs),
+            "apps": items(&apps),
+            "titles": items(&totals_by_key(&events, "title", Some(range))),
+            "categories": categories,
+        }))
+    }
+}
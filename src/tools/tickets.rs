@@ -0,0 +1,224 @@
+use super::{ActivityWatchMcpServer, fit_response, json_response, token_budget};
+use crate::analysis::{
+    EDITOR_BUCKET_TYPE, TimeRange, TokenBudget, WEB_BUCKET_TYPE, WINDOW_BUCKET_TYPE,
+    format_timestamp, ticket_totals,
+};
+use crate::categories::Regex;
+use crate::format::format_duration;
+use crate::models::ResponseFormat;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Default number of tickets listed
+const DEFAULT_TICKET_TOP: usize = 25;
+
+/// Approximate characters of a ticket row, besides its bucket IDs
+const TICKET_ROW_CHARS: usize = 40;
+
+/// Approximate characters of the heading and totals, besides bucket IDs
+const TICKET_HEADER_CHARS: usize = 300;
+
+/// Input for time per ticket
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TicketTimeParams {
+    /// Start time (ISO 8601 format)
+    #[serde(default)]
+    pub start: Option<String>,
+
+    /// End time (ISO 8601 format)
+    #[serde(default)]
+    pub end: Option<String>,
+
+    /// The range as a period instead of start and end (e.g., "2024-W07", "yesterday")
+    #[serde(default)]
+    pub period: Option<String>,
+
+    /// Buckets to scan (default: all window, browser tab and editor buckets)
+    #[serde(default)]
+    pub bucket_ids: Option<Vec<String>>,
+
+    /// Ticket ID patterns instead of the server setting, as regexes separated by ";" (e.g., "\\bPROJ-\\d+\\b;#\\d{3,}")
+    #[serde(default)]
+    pub patterns: Option<String>,
+
+    /// Maximum tickets listed (default: 25)
+    #[serde(default)]
+    pub top: Option<usize>,
+
+    /// Approximate size the response should fit in, in tokens (e.g., 1000); picks the number of tickets unless `top` is given
+    #[serde(default)]
+    pub max_tokens_hint: Option<usize>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = ticket_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Roll up time per ticket ID found in titles and URLs.
+    #[tool(
+        description = r#"Roll up time per issue-tracker ticket (e.g., "PROJ-123") for time reporting. Ticket IDs are extracted from window and tab titles, URLs, and editor file, project and branch names with the server's patterns (default: Jira-style keys, `\b[A-Z][A-Z0-9]+-\d+\b`).
+
+Time where several buckets show the same ticket at once (an editor window, its editor watcher and a browser tab) is counted once per ticket. An event naming two tickets counts for both.
+
+## Parameters
+- `start` / `end`: The range in ISO 8601 format
+- `period`: The range as a period or preset instead, e.g. "2024-W07" or "yesterday"
+- `bucket_ids`: Buckets to scan (default: all window, browser tab and editor buckets)
+- `patterns`: Regexes separated by ";" used instead of the server's patterns
+- `top`: Maximum tickets listed (default: 25)
+- `max_tokens_hint`: Approximate token budget of the response (e.g., 1000). Without `top`, as many tickets are listed as fit, and JSON is compact"#
+    )]
+    async fn aw_ticket_time(
+        &self,
+        Parameters(params): Parameters<TicketTimeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let patterns = match params.patterns.as_deref() {
+            Some(patterns) => {
+                let parsed: Result<Vec<Regex>, String> = patterns
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(|pattern| Regex::new(pattern, false))
+                    .collect();
+                match parsed {
+                    Ok(parsed) if !parsed.is_empty() => parsed,
+                    Ok(_) => {
+                        return Ok(CallToolResult::error(vec![Content::text(
+                            "At least one ticket pattern is required",
+                        )]));
+                    }
+                    Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+                }
+            }
+            None => self.config().ticket_patterns.clone(),
+        };
+        let budget = match token_budget(params.max_tokens_hint) {
+            Ok(budget) => budget,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+        let range = match self.resolve_range(
+            params.start.as_deref(),
+            params.end.as_deref(),
+            params.period.as_deref(),
+        ) {
+            Ok(range) => range,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.ticket_time(&params, &range, &patterns, budget).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to total time per ticket: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Fetch the buckets' events and total them per ticket
+    async fn ticket_time(
+        &self,
+        params: &TicketTimeParams,
+        range: &TimeRange,
+        patterns: &[Regex],
+        budget: Option<TokenBudget>,
+    ) -> Result<String, McpError> {
+        let bucket_ids: Vec<String> = match &params.bucket_ids {
+            Some(bucket_ids) => {
+                for bucket_id in bucket_ids {
+                    self.check_scope(bucket_id).await?;
+                }
+                bucket_ids.clone()
+            }
+            None => {
+                let buckets = self.lookup_buckets().await?;
+                let mut ids: Vec<String> = buckets
+                    .values()
+                    .filter(|b| {
+                        self.in_scope(b)
+                            && [WINDOW_BUCKET_TYPE, WEB_BUCKET_TYPE, EDITOR_BUCKET_TYPE]
+                                .contains(&b.bucket_type.as_deref().unwrap_or(""))
+                    })
+                    .map(|b| b.id.clone())
+                    .collect();
+                ids.sort();
+                ids
+            }
+        };
+        let start = format_timestamp(&range.start);
+        let end = format_timestamp(&range.end);
+        let mut sources = Vec::with_capacity(bucket_ids.len());
+        for bucket_id in &bucket_ids {
+            let events = self.range_events(bucket_id, range).await?;
+            sources.push((bucket_id.clone(), events));
+        }
+        let totals = ticket_totals(&sources, patterns, range);
+        let top = params.top.unwrap_or(match budget {
+            Some(budget) => {
+                let bucket_chars = bucket_ids.iter().map(|id| id.len() + 2).sum::<usize>();
+                // Rows list the buckets a ticket was seen in, rarely all of them
+                let row_chars = TICKET_ROW_CHARS + bucket_chars / 2;
+                budget.rows(row_chars, TICKET_HEADER_CHARS + bucket_chars)
+            }
+            None => DEFAULT_TICKET_TOP,
+        });
+        let pattern_list = patterns.iter().map(Regex::as_str).collect::<Vec<_>>();
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    "# Time per Ticket".to_string(),
+                    String::new(),
+                    format!("- **Range**: {} to {}", start, end),
+                    format!("- **Patterns**: `{}`", pattern_list.join("`, `")),
+                    format!(
+                        "- **Buckets**: {}",
+                        match bucket_ids.is_empty() {
+                            true => "none found".to_string(),
+                            false => bucket_ids.join(", "),
+                        }
+                    ),
+                    format!("- **Tickets**: {}", totals.len()),
+                ];
+                if totals.is_empty() {
+                    lines.push(String::new());
+                    lines.push("_No ticket IDs found in this range._".to_string());
+                } else {
+                    lines.extend([
+                        String::new(),
+                        "| Ticket | Time | Seen in |".to_string(),
+                        "|---|---|---|".to_string(),
+                    ]);
+                    lines.extend(totals.iter().take(top).map(|total| {
+                        format!(
+                            "| {} | {} | {} |",
+                            self.markdown_text(&total.ticket),
+                            format_duration(total.secs),
+                            total.buckets.join(", ")
+                        )
+                    }));
+                    if totals.len() > top {
+                        lines.push(String::new());
+                        lines.push(format!("_{} more tickets not listed._", totals.len() - top));
+                    }
+                }
+
+                fit_response(lines.join("\n"), budget)
+            }
+            ResponseFormat::Json => json_response(
+                &serde_json::json!({
+                    "range": range,
+                    "patterns": pattern_list,
+                    "bucket_ids": bucket_ids,
+                    "tickets": totals.iter().take(top).collect::<Vec<_>>(),
+                    "ticket_count": totals.len(),
+                }),
+                budget,
+            ),
+        })
+    }
+}
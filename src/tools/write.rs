@@ -0,0 +1,518 @@
+use super::{ActivityWatchMcpServer, json_text};
+use crate::analysis::{format_timestamp, parse_timestamp};
+use crate::format::{code_span, format_duration};
+use crate::models::{Bucket, Event, ResponseFormat};
+use crate::privacy::including_private;
+use rmcp::{ErrorData as McpError, handler::server::tool::Parameters, model::*, tool, tool_router};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Default seconds within which a heartbeat extends the previous event
+const DEFAULT_PULSETIME_SECS: f64 = 60.0;
+
+/// Type of the buckets heartbeats create when the bucket does not exist
+const DEFAULT_HEARTBEAT_BUCKET_TYPE: &str = "app.agent.activity";
+
+/// Client name recorded on the buckets this server creates
+const BUCKET_CLIENT: &str = "aw-mcp-server";
+
+/// Most events one insert may carry
+const MAX_INSERT_EVENTS: usize = 1000;
+
+/// Input for sending a heartbeat
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HeartbeatParams {
+    /// The bucket to record into (e.g., "aw-agent_myhost"); created when it does not exist
+    pub bucket_id: String,
+
+    /// Event data, e.g. {"app": "Claude", "title": "Refactoring the parser"}
+    pub data: HashMap<String, serde_json::Value>,
+
+    /// Time of the heartbeat (ISO 8601 format; default: now)
+    #[serde(default)]
+    pub timestamp: Option<String>,
+
+    /// Duration of the heartbeat in seconds (default: 0)
+    #[serde(default)]
+    pub duration: Option<f64>,
+
+    /// Seconds after the previous event's end within which an identical heartbeat extends it (default: 60)
+    #[serde(default)]
+    pub pulsetime: Option<f64>,
+
+    /// Type of the bucket if it has to be created (default: "app.agent.activity")
+    #[serde(default)]
+    pub bucket_type: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// An event to insert
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NewEvent {
+    /// Start time (ISO 8601 format)
+    pub timestamp: String,
+
+    /// Duration in seconds (default: 0)
+    #[serde(default)]
+    pub duration: Option<f64>,
+
+    /// Event data, e.g. {"app": "Meeting", "title": "Sprint planning"}
+    pub data: HashMap<String, serde_json::Value>,
+}
+
+/// Input for inserting events
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InsertEventsParams {
+    /// The bucket to insert into (e.g., "aw-import_myhost"); created when it does not exist
+    pub bucket_id: String,
+
+    /// The events to insert (at most 1000)
+    pub events: Vec<NewEvent>,
+
+    /// Type of the bucket if it has to be created (default: "app.agent.activity")
+    #[serde(default)]
+    pub bucket_type: Option<String>,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for deleting an event
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeleteEventParams {
+    /// The bucket holding the event
+    pub bucket_id: String,
+
+    /// ID of the event to delete (the `id` aw_get_events reports)
+    pub event_id: i64,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// Input for deleting a bucket
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeleteBucketParams {
+    /// The bucket to delete, with all its events
+    pub bucket_id: String,
+
+    /// Set to true to actually delete; without it, the tool only describes what would be deleted
+    #[serde(default)]
+    pub confirm: bool,
+
+    /// Output format: "markdown" (default) or "json"
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+#[tool_router(router = write_router, vis = "pub(super)")]
+impl ActivityWatchMcpServer {
+    /// Record activity into a bucket with a heartbeat.
+    #[tool(
+        description = r#"Record activity into an ActivityWatch bucket with a heartbeat, as watchers do, e.g. to log an assistant session into a dedicated bucket. Send a heartbeat with the same data regularly while the activity lasts: a heartbeat that starts within `pulsetime` seconds of the end of the bucket's last event, with equal data, extends that event; otherwise it starts a new event.
+
+The bucket is created when it does not exist (on this machine's hostname). Only available when writes are enabled (AW_MCP_ALLOW_WRITES).
+
+## Parameters
+- `bucket_id`: The bucket to record into
+- `data`: Event data, e.g. {"app": "Claude", "title": "Refactoring the parser"}
+- `timestamp`: Time of the heartbeat in ISO 8601 format (default: now)
+- `duration`: Duration in seconds (default: 0)
+- `pulsetime`: Merge window in seconds (default: 60)
+- `bucket_type`: Type of a newly created bucket (default: "app.agent.activity")"#
+    )]
+    async fn aw_heartbeat(
+        &self,
+        Parameters(params): Parameters<HeartbeatParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = self.check_writes() {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.pulsetime.is_some_and(|p| !p.is_finite() || p < 0.0) {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "pulsetime must be a non-negative number of seconds",
+            )]));
+        }
+        let timestamp = match params.timestamp.as_deref().map(parse_timestamp) {
+            Some(Ok(timestamp)) => timestamp,
+            Some(Err(e)) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+            None => self.now(),
+        };
+        let event = match Event::builder()
+            .timestamp(timestamp)
+            .duration_secs(params.duration.unwrap_or(0.0))
+            .data_map(params.data.clone())
+            .build()
+        {
+            Ok(event) => event,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.heartbeat(&params, &event).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to send heartbeat: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Insert events into a bucket as they are.
+    #[tool(
+        description = r#"Insert events into an ActivityWatch bucket as they are, e.g. to import activity from another tool, annotate the timeline (meetings, offline work) or create synthetic events for testing. Unlike heartbeats, inserted events are never merged with existing ones, so inserting the same events twice stores them twice.
+
+The bucket is created when it does not exist (on this machine's hostname). Only available when writes are enabled (AW_MCP_ALLOW_WRITES).
+
+## Parameters
+- `bucket_id`: The bucket to insert into
+- `events`: Up to 1000 events, each with `timestamp` (ISO 8601), `duration` in seconds (default: 0) and non-empty `data`
+- `bucket_type`: Type of a newly created bucket (default: "app.agent.activity")"#
+    )]
+    async fn aw_insert_events(
+        &self,
+        Parameters(params): Parameters<InsertEventsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = self.check_writes() {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+        if params.events.is_empty() || params.events.len() > MAX_INSERT_EVENTS {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "events must hold between 1 and {} events",
+                MAX_INSERT_EVENTS
+            ))]));
+        }
+        let events = match new_events(&params.events) {
+            Ok(events) => events,
+            Err(e) => return Ok(CallToolResult::error(vec![Content::text(e)])),
+        };
+
+        match self.insert_events(&params, &events).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to insert events: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Delete one event of a bucket.
+    #[tool(
+        annotations(destructive_hint = true),
+        description = r#"Delete a single event of an ActivityWatch bucket by its ID, e.g. to correct a wrong or duplicate entry in a manually maintained bucket. Event IDs are the `id` field aw_get_events returns (in JSON format). This cannot be undone, so confirm the event with the user first. Only available when writes are enabled (AW_MCP_ALLOW_WRITES).
+
+## Parameters
+- `bucket_id`: The bucket holding the event
+- `event_id`: ID of the event to delete"#
+    )]
+    async fn aw_delete_event(
+        &self,
+        Parameters(params): Parameters<DeleteEventParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = self.check_writes() {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        match self.delete_event(&params).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to delete event: {}",
+                e.message
+            ))])),
+        }
+    }
+
+    /// Delete a bucket and all its events, after confirmation.
+    #[tool(
+        annotations(destructive_hint = true),
+        description = r#"Delete an ActivityWatch bucket with all its events, e.g. to clean up test buckets or buckets of a retired watcher. This cannot be undone.
+
+Deletion takes two calls. Without `confirm`, nothing is deleted: the response describes the bucket and how many events it holds. Show that to the user and call again with `confirm: true` only once the user has agreed. Only available when writes are enabled (AW_MCP_ALLOW_WRITES).
+
+## Parameters
+- `bucket_id`: The bucket to delete
+- `confirm`: true to delete; false (default) to preview"#
+    )]
+    async fn aw_delete_bucket(
+        &self,
+        Parameters(params): Parameters<DeleteBucketParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = self.check_writes() {
+            return Ok(CallToolResult::error(vec![Content::text(e)]));
+        }
+        if params.bucket_id.trim().is_empty() {
+            return Ok(CallToolResult::error(vec![Content::text(
+                "Bucket ID cannot be empty",
+            )]));
+        }
+
+        match self.delete_bucket(&params).await {
+            Ok(response) => Ok(CallToolResult::success(vec![Content::text(response)])),
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
+                "Failed to delete bucket: {}",
+                e.message
+            ))])),
+        }
+    }
+}
+
+impl ActivityWatchMcpServer {
+    /// Refuse writes unless the configuration allows them
+    pub(super) fn check_writes(&self) -> Result<(), String> {
+        match self.config().allow_writes {
+            true => Ok(()),
+            false => Err(
+                "Writing to ActivityWatch is disabled (set AW_MCP_ALLOW_WRITES=true to \
+                          enable it)"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Check a bucket written to is in scope, creating it (of `bucket_type`,
+    /// by default the heartbeat type) when it does not exist; returns the
+    /// bucket if it was created
+    async fn ensure_bucket(
+        &self,
+        bucket_id: &str,
+        bucket_type: Option<&str>,
+    ) -> Result<Option<Bucket>, McpError> {
+        let buckets = self.lookup_buckets().await?;
+        Ok(match buckets.get(bucket_id) {
+            Some(_) => {
+                self.check_scope(bucket_id).await?;
+                None
+            }
+            None => {
+                let bucket_type = bucket_type.unwrap_or(DEFAULT_HEARTBEAT_BUCKET_TYPE);
+                let hostname = local_hostname();
+                let bucket = Bucket {
+                    id: bucket_id.to_string(),
+                    client: Some(BUCKET_CLIENT.to_string()),
+                    bucket_type: Some(bucket_type.to_string()),
+                    hostname: Some(hostname.clone()),
+                    created: None,
+                    data: None,
+                    last_updated: None,
+                    extra: HashMap::new(),
+                };
+                if !self.in_scope(&bucket) {
+                    return Err(McpError::invalid_params(
+                        format!("Bucket {} is outside the access scope", bucket_id),
+                        None,
+                    ));
+                }
+                self.client
+                    .create_bucket(bucket_id, bucket_type, BUCKET_CLIENT, &hostname)
+                    .await?;
+                Some(bucket)
+            }
+        })
+    }
+
+    /// Create the bucket if needed and send the heartbeat
+    async fn heartbeat(&self, params: &HeartbeatParams, event: &Event) -> Result<String, McpError> {
+        let bucket_id = params.bucket_id.trim();
+        let pulsetime = params.pulsetime.unwrap_or(DEFAULT_PULSETIME_SECS);
+        let created = self
+            .ensure_bucket(bucket_id, params.bucket_type.as_deref())
+            .await?;
+        let stored = self.client.heartbeat(bucket_id, event, pulsetime).await?;
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![format!("# Heartbeat sent to {}", code_span(bucket_id))];
+                lines.push(String::new());
+                if let Some(bucket) = &created {
+                    lines.push(format!(
+                        "- **Created bucket**: type {} on {}",
+                        code_span(bucket.bucket_type.as_deref().unwrap_or("")),
+                        self.markdown_text(bucket.hostname.as_deref().unwrap_or(""))
+                    ));
+                }
+                lines.push(format!(
+                    "- **Event**: {} since {} (pulsetime {}s)",
+                    format_duration(stored.duration.as_secs_f64()),
+                    format_timestamp(&stored.timestamp),
+                    pulsetime
+                ));
+                if let Some(id) = stored.id {
+                    lines.push(format!("- **Event ID**: {}", id));
+                }
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": bucket_id,
+                "created_bucket": created,
+                "pulsetime": pulsetime,
+                "event": stored,
+            })),
+        })
+    }
+
+    /// Create the bucket if needed and insert the events
+    async fn insert_events(
+        &self,
+        params: &InsertEventsParams,
+        events: &[Event],
+    ) -> Result<String, McpError> {
+        let bucket_id = params.bucket_id.trim();
+        let created = self
+            .ensure_bucket(bucket_id, params.bucket_type.as_deref())
+            .await?;
+        self.client.insert_events(bucket_id, events).await?;
+        let start = events.iter().map(|event| event.timestamp).min();
+        let end = events.iter().map(Event::end).max();
+        let total_secs: f64 = events
+            .iter()
+            .map(|event| event.duration.as_secs_f64())
+            .sum();
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![format!("# Events inserted into {}", code_span(bucket_id))];
+                lines.push(String::new());
+                if let Some(bucket) = &created {
+                    lines.push(format!(
+                        "- **Created bucket**: type {} on {}",
+                        code_span(bucket.bucket_type.as_deref().unwrap_or("")),
+                        self.markdown_text(bucket.hostname.as_deref().unwrap_or(""))
+                    ));
+                }
+                lines.push(format!("- **Events**: {}", events.len()));
+                if let (Some(start), Some(end)) = (start, end) {
+                    lines.push(format!(
+                        "- **Span**: {} to {}",
+                        format_timestamp(&start),
+                        format_timestamp(&end)
+                    ));
+                }
+                lines.push(format!(
+                    "- **Total duration**: {}",
+                    format_duration(total_secs)
+                ));
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": bucket_id,
+                "created_bucket": created,
+                "inserted": events.len(),
+                "start": start,
+                "end": end,
+                "total_secs": total_secs,
+            })),
+        })
+    }
+
+    /// Delete the event
+    async fn delete_event(&self, params: &DeleteEventParams) -> Result<String, McpError> {
+        let bucket_id = params.bucket_id.trim();
+        self.check_scope(bucket_id).await?;
+        self.client.delete_event(bucket_id, params.event_id).await?;
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => format!(
+                "# Deleted Event {} from {}\n\nThe event was deleted; cached data of the bucket \
+                 was dropped.",
+                params.event_id,
+                code_span(bucket_id)
+            ),
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket_id": bucket_id,
+                "event_id": params.event_id,
+                "deleted": true,
+            })),
+        })
+    }
+
+    /// Describe the bucket, and delete it when confirmed
+    async fn delete_bucket(&self, params: &DeleteBucketParams) -> Result<String, McpError> {
+        let bucket_id = params.bucket_id.trim();
+        self.check_scope(bucket_id).await?;
+        let bucket = self.client.get_bucket(bucket_id).await?;
+        // Deleting the bucket deletes do-not-track time too
+        let count = self.client.get_event_count(bucket_id, None, None);
+        let events = including_private(true, count).await?;
+        if params.confirm {
+            self.client.delete_bucket(bucket_id).await?;
+        }
+
+        Ok(match params.response_format {
+            ResponseFormat::Markdown => {
+                let mut lines = vec![
+                    match params.confirm {
+                        true => format!("# Deleted Bucket {}", code_span(bucket_id)),
+                        false => format!("# Delete Bucket {}?", code_span(bucket_id)),
+                    },
+                    String::new(),
+                    bucket.to_markdown(self.config().escape_markdown),
+                    format!("- **Events**: {}", events),
+                    String::new(),
+                ];
+                lines.push(match params.confirm {
+                    true => "The bucket and its events were deleted.".to_string(),
+                    false => "_Nothing was deleted. Deleting cannot be undone: ask the user, \
+                              then call again with `confirm: true` to delete the bucket and \
+                              its events._"
+                        .to_string(),
+                });
+                lines.join("\n")
+            }
+            ResponseFormat::Json => json_text(&serde_json::json!({
+                "bucket": bucket,
+                "events": events,
+                "deleted": params.confirm,
+            })),
+        })
+    }
+}
+
+/// Validate events to insert, naming the first invalid one
+fn new_events(events: &[NewEvent]) -> Result<Vec<Event>, String> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| {
+            parse_timestamp(&event.timestamp)
+                .and_then(|timestamp| {
+                    Event::builder()
+                        .timestamp(timestamp)
+                        .duration_secs(event.duration.unwrap_or(0.0))
+                        .data_map(event.data.clone())
+                        .build()
+                })
+                .map_err(|e| format!("Event {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+/// Hostname of this machine, recorded on buckets it creates
+pub(super) fn local_hostname() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
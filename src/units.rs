@@ -0,0 +1,186 @@
+use crate::format::format_duration;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+
+tokio::task_local! {
+    static DURATION_UNIT: DurationUnit;
+}
+
+/// Unit durations in JSON responses are additionally given in, next to the
+/// seconds the tools report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationUnit {
+    /// Seconds, as reported; only the humanized value is added
+    Seconds,
+    /// Decimal minutes
+    Minutes,
+    /// Decimal hours
+    Hours,
+}
+
+impl FromStr for DurationUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "seconds" | "secs" | "s" => Ok(Self::Seconds),
+            "minutes" | "mins" | "m" => Ok(Self::Minutes),
+            "hours" | "h" => Ok(Self::Hours),
+            other => Err(format!(
+                "Unknown duration unit '{}'. Expected seconds, minutes or hours",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DurationUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Seconds => "seconds",
+            Self::Minutes => "minutes",
+            Self::Hours => "hours",
+        })
+    }
+}
+
+impl DurationUnit {
+    /// Seconds in this unit, rounded to two decimals
+    fn in_unit(self, secs: f64) -> f64 {
+        let value = match self {
+            Self::Seconds => secs,
+            Self::Minutes => secs / 60.0,
+            Self::Hours => secs / 3600.0,
+        };
+        (value * 100.0).round() / 100.0
+    }
+
+    /// Add converted and humanized values next to every duration within a
+    /// serialized response: a field `secs` or `<name>_secs` gains `<unit>` or
+    /// `<name>_<unit>` (rounded to two decimals; not for seconds) and `human`
+    /// or `<name>_human` (e.g., "1h 30m"), and an event's `duration` gains
+    /// `duration_<unit>` and `duration_human`. Event data is left alone.
+    pub fn apply(self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                let event = is_event(object);
+                let mut added = Map::new();
+                for (key, value) in object.iter_mut() {
+                    let prefix = match key.as_str() {
+                        "data" if event => continue,
+                        "duration" if event => Some("duration_"),
+                        key => match key.strip_suffix("secs") {
+                            Some("") => Some(""),
+                            Some(prefix) => prefix.strip_suffix('_').map(|_| prefix),
+                            None => None,
+                        },
+                    };
+                    match (prefix, value.as_f64()) {
+                        (Some(prefix), Some(secs)) => {
+                            if self != Self::Seconds {
+                                let converted = self.in_unit(secs);
+                                added.insert(format!("{}{}", prefix, self), converted.into());
+                            }
+                            added.insert(format!("{}human", prefix), format_duration(secs).into());
+                        }
+                        _ => self.apply(value),
+                    }
+                }
+                for (key, value) in added {
+                    object.entry(key).or_insert(value);
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.apply(value)),
+            _ => {}
+        }
+    }
+}
+
+/// Whether an object is a serialized event, whose `data` holds recorded
+/// keys rather than the tool's own fields
+fn is_event(object: &Map<String, Value>) -> bool {
+    object.contains_key("timestamp")
+        && object.get("duration").is_some_and(Value::is_number)
+        && object.get("data").is_some_and(Value::is_object)
+}
+
+/// Run a future whose JSON responses also give durations in `unit`, if any
+pub async fn with_duration_unit<F: Future>(unit: Option<DurationUnit>, future: F) -> F::Output {
+    match unit {
+        Some(unit) => DURATION_UNIT.scope(unit, future).await,
+        None => future.await,
+    }
+}
+
+/// Serialize a JSON response with the durations of the current tool call's
+/// unit added (see [`DurationUnit::apply`])
+pub fn to_response_value(value: &impl Serialize) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(value)?;
+    if let Ok(unit) = DURATION_UNIT.try_with(|unit| *unit) {
+        unit.apply(&mut value);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn adds_durations_in_the_chosen_unit() {
+        let mut value = json!({
+            "total_secs": 5400.0,
+            "groups": [{"name": "Code", "secs": 90.0}],
+            "bucket_id": "window",
+        });
+        DurationUnit::Minutes.apply(&mut value);
+        assert_eq!(value["total_minutes"], 90.0);
+        assert_eq!(value["total_human"], "1h 30m");
+        assert_eq!(value["groups"][0]["minutes"], 1.5);
+        assert_eq!(value["total_secs"], 5400.0);
+
+        let mut value = json!({"total_secs": 5400.0, "groups": [{"secs": 90.0}]});
+        DurationUnit::Seconds.apply(&mut value);
+        assert!(value.get("total_seconds").is_none());
+        assert!(value["groups"][0].get("human").is_some());
+
+        assert_eq!(
+            "mins".parse::<DurationUnit>().unwrap(),
+            DurationUnit::Minutes
+        );
+        assert!("days".parse::<DurationUnit>().is_err());
+    }
+
+    #[test]
+    fn converts_event_durations_but_not_event_data() {
+        let mut value = json!([{
+            "id": 1,
+            "timestamp": "2024-03-01T09:00:00Z",
+            "duration": 5400.0,
+            "data": {"app": "Timer", "elapsed_secs": 30},
+        }]);
+        DurationUnit::Hours.apply(&mut value);
+        assert_eq!(value[0]["duration_hours"], 1.5);
+        assert_eq!(value[0]["duration_human"], "1h 30m");
+        assert_eq!(
+            value[0]["data"],
+            json!({"app": "Timer", "elapsed_secs": 30})
+        );
+    }
+
+    #[tokio::test]
+    async fn applies_the_unit_of_the_current_call() {
+        let response = json!({"total_secs": 120.0});
+        let plain = to_response_value(&response).unwrap();
+        assert_eq!(plain, response);
+        let converted = with_duration_unit(Some(DurationUnit::Minutes), async {
+            to_response_value(&response).unwrap()
+        })
+        .await;
+        assert_eq!(converted["total_minutes"], 2.0);
+    }
+}
@@ -0,0 +1,560 @@
+use aw_mcp_server::testing::{FakeAwServer, Fault};
+use aw_mcp_server::{
+    ActivityWatchClient, ActivityWatchMcpServer, Config, DoNotTrack, Event, FixedClock,
+    HttpSettings, ResponseMeta, SyncMerge, Timezone, including_private,
+};
+use chrono::{TimeZone, Utc};
+use std::time::Duration;
+
+fn event(secs: i64, duration: f64, app: &str) -> Event {
+    Event::builder()
+        .timestamp(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap())
+        .duration_secs(duration)
+        .data("app", app)
+        .build()
+        .unwrap()
+}
+
+async fn server_with_window_bucket() -> FakeAwServer {
+    let server = FakeAwServer::start().await;
+    server.add_simple_bucket("aw-watcher-window_test", "currentwindow", "test");
+    server.add_events(
+        "aw-watcher-window_test",
+        vec![event(0, 60.0, "editor"), event(60, 30.0, "browser")],
+    );
+    server
+}
+
+#[tokio::test]
+async fn reads_buckets_and_events() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+
+    let info = client.get_info().await.unwrap();
+    assert_eq!(info.hostname.as_deref(), Some("fake-host"));
+
+    let buckets = client.get_buckets().await.unwrap();
+    assert!(buckets.contains_key("aw-watcher-window_test"));
+
+    let events = client
+        .get_events("aw-watcher-window_test", Some(1), None, None)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data["app"], "browser");
+
+    let count = client
+        .get_event_count("aw-watcher-window_test", Some("2023-11-14T22:14:30Z"), None)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn surfaces_server_error_messages() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+
+    let error = client.get_bucket("missing").await.unwrap_err();
+    assert_eq!(error.message, "There's no bucket named missing");
+
+    server.fail_next(Fault::Status(500));
+    let error = client.get_buckets().await.unwrap_err();
+    assert_eq!(error.message, "Injected 500 error");
+    assert!(client.get_buckets().await.is_ok());
+}
+
+#[tokio::test]
+async fn times_out_slow_responses() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url()).with_http_settings(&HttpSettings {
+        timeout: Duration::from_millis(100),
+        ..HttpSettings::default()
+    });
+
+    server.fail_all(Some(Fault::Delay(Duration::from_millis(500))));
+    let error = client.get_buckets().await.unwrap_err();
+    assert!(error.message.contains("timed out"));
+    assert_eq!(server.requests(), vec!["GET /api/0/buckets/".to_string()]);
+}
+
+#[tokio::test]
+async fn labels_responses_served_from_cache() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+
+    let (_, fresh) = ResponseMeta::collect("a".to_string(), client.buckets()).await;
+    assert!(fresh.cached_since.is_none());
+
+    let (_, cached) = ResponseMeta::collect("b".to_string(), client.buckets()).await;
+    assert!(cached.cached_since.is_some());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn records_heartbeats_into_created_buckets() {
+    let server = FakeAwServer::start().await;
+    let client = ActivityWatchClient::new(server.url());
+    client
+        .create_bucket("aw-agent_test", "app.agent.activity", "test", "test")
+        .await
+        .unwrap();
+    // Creating it again keeps the bucket
+    client
+        .create_bucket("aw-agent_test", "other", "test", "test")
+        .await
+        .unwrap();
+    let buckets = client.buckets().await.unwrap();
+    assert_eq!(
+        buckets["aw-agent_test"].bucket_type.as_deref(),
+        Some("app.agent.activity")
+    );
+
+    let first = client
+        .heartbeat("aw-agent_test", &event(0, 0.0, "agent"), 60.0)
+        .await
+        .unwrap();
+    assert_eq!(first.id, Some(1));
+    let merged = client
+        .heartbeat("aw-agent_test", &event(45, 0.0, "agent"), 60.0)
+        .await
+        .unwrap();
+    assert_eq!((merged.id, merged.duration.as_secs_f64()), (Some(1), 45.0));
+    let other = client
+        .heartbeat("aw-agent_test", &event(50, 0.0, "other"), 60.0)
+        .await
+        .unwrap();
+    assert_eq!(other.id, Some(2));
+    let heartbeat = "POST /api/0/buckets/aw-agent_test/heartbeat?pulsetime=60".to_string();
+    assert!(server.requests().contains(&heartbeat));
+}
+
+#[tokio::test]
+async fn deletes_buckets_by_force() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+    assert!(
+        client
+            .buckets()
+            .await
+            .unwrap()
+            .contains_key("aw-watcher-window_test")
+    );
+
+    client
+        .delete_bucket("aw-watcher-window_test")
+        .await
+        .unwrap();
+    assert!(
+        server
+            .requests()
+            .contains(&"DELETE /api/0/buckets/aw-watcher-window_test?force=1".to_string())
+    );
+    // The cached bucket list is dropped with it
+    assert!(
+        !client
+            .buckets()
+            .await
+            .unwrap()
+            .contains_key("aw-watcher-window_test")
+    );
+    assert!(
+        client
+            .delete_bucket("aw-watcher-window_test")
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn inserts_events_without_merging() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+    let before = client
+        .get_events("aw-watcher-window_test", None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(before.len(), 2);
+
+    let inserted = [event(90, 30.0, "browser"), event(120, 10.0, "terminal")];
+    let stored = client
+        .insert_events("aw-watcher-window_test", &inserted)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|event| event.id.is_some()));
+    // Cached events of the bucket are dropped, so the new events show up
+    let after = client
+        .get_events("aw-watcher-window_test", None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(after.len(), 4);
+    assert!(client.insert_events("missing", &inserted).await.is_err());
+}
+
+#[tokio::test]
+async fn deletes_single_events() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+    let stored = client
+        .insert_events("aw-watcher-window_test", &[event(90, 30.0, "x")])
+        .await;
+    let event_id = stored.unwrap()[0].id.unwrap();
+
+    client
+        .delete_event("aw-watcher-window_test", event_id)
+        .await
+        .unwrap();
+    assert!(server.requests().contains(&format!(
+        "DELETE /api/0/buckets/aw-watcher-window_test/events/{}",
+        event_id
+    )));
+    let events = client
+        .get_events("aw-watcher-window_test", None, None, None)
+        .await
+        .unwrap();
+    assert!(events.iter().all(|event| event.id != Some(event_id)));
+    // An unknown event is an error, though aw-server answers 200
+    let error = client
+        .delete_event("aw-watcher-window_test", event_id)
+        .await
+        .unwrap_err();
+    assert!(error.message.contains("no event"));
+}
+
+#[tokio::test]
+async fn fetches_single_events() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+    let stored = client
+        .insert_events("aw-watcher-window_test", &[event(90, 30.0, "x")])
+        .await;
+    let event_id = stored.unwrap()[0].id.unwrap();
+
+    let event = client
+        .get_event("aw-watcher-window_test", event_id)
+        .await
+        .unwrap();
+    assert_eq!(event.data["app"], "x");
+    assert!(server.requests().contains(&format!(
+        "GET /api/0/buckets/aw-watcher-window_test/events/{}",
+        event_id
+    )));
+    // aw-server answers null for an unknown event
+    let error = client
+        .get_event("aw-watcher-window_test", 999)
+        .await
+        .unwrap_err();
+    assert!(error.message.contains("no event 999"));
+}
+
+#[tokio::test]
+async fn exports_and_imports_buckets() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+    let export = client.export(Some("aw-watcher-window_test")).await.unwrap();
+    assert_eq!(
+        export["buckets"]["aw-watcher-window_test"]["events"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    let all = client.export(None).await.unwrap();
+    assert_eq!(all["buckets"].as_object().unwrap().len(), 1);
+
+    let target = FakeAwServer::start().await;
+    let restored = ActivityWatchClient::new(target.url());
+    restored.import(&export).await.unwrap();
+    let events = restored
+        .get_events("aw-watcher-window_test", None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].data["app"], "browser");
+    // aw-server refuses to import a bucket twice
+    assert!(restored.import(&export).await.is_err());
+}
+
+#[tokio::test]
+async fn reads_and_stores_settings() {
+    let server = FakeAwServer::start().await;
+    let client = ActivityWatchClient::new(server.url());
+    assert_eq!(
+        client.get_setting("classes").await.unwrap(),
+        serde_json::Value::Null
+    );
+
+    let classes =
+        serde_json::json!([{"name": ["Work"], "rule": {"type": "regex", "regex": "Code"}}]);
+    client.set_setting("classes", &classes).await.unwrap();
+    client.set_setting("theme", &"dark".into()).await.unwrap();
+    assert_eq!(client.get_setting("classes").await.unwrap(), classes);
+    let settings = client.get_settings().await.unwrap();
+    assert_eq!(settings["theme"], "dark");
+    assert_eq!(settings["classes"], classes);
+}
+
+#[tokio::test]
+async fn serves_buckets_of_the_sync_directory() {
+    let server = server_with_window_bucket().await;
+    let dir = std::env::temp_dir().join(format!("aw-mcp-sync-test-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("phone")).unwrap();
+    let export = serde_json::json!({"buckets": {
+        "aw-watcher-window_phone": {
+            "id": "aw-watcher-window_phone", "type": "currentwindow", "hostname": "phone",
+            "events": [event(0, 60.0, "maps"), event(600, 30.0, "mail")],
+        },
+        // The live server's copy wins
+        "aw-watcher-window_test": {"id": "aw-watcher-window_test", "hostname": "stale"},
+    }});
+    std::fs::write(dir.join("phone/export.json"), export.to_string()).unwrap();
+    std::fs::write(dir.join("phone/test.db"), b"SQLite format 3").unwrap();
+    let client = ActivityWatchClient::new(server.url()).with_sync_dir(&dir);
+
+    let buckets = client.buckets().await.unwrap();
+    assert_eq!(
+        buckets["aw-watcher-window_phone"].hostname.as_deref(),
+        Some("phone")
+    );
+    assert_eq!(
+        buckets["aw-watcher-window_test"].hostname.as_deref(),
+        Some("test")
+    );
+    let events = client
+        .get_events("aw-watcher-window_phone", None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].data["app"], "mail");
+    let start = "2023-11-14T22:15:00Z";
+    let count = client
+        .get_event_count("aw-watcher-window_phone", Some(start), None)
+        .await;
+    assert_eq!(count.unwrap(), 1);
+    assert!(
+        !server
+            .requests()
+            .iter()
+            .any(|request| request.contains("_phone"))
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn merges_buckets_on_both_sides_by_policy() {
+    let server = server_with_window_bucket().await;
+    let dir = std::env::temp_dir().join(format!("aw-mcp-merge-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // One event the live server has too, one only synced
+    let export = serde_json::json!({"buckets": {"aw-watcher-window_test": {
+        "id": "aw-watcher-window_test", "hostname": "synced",
+        "events": [event(0, 60.0, "editor"), event(300, 30.0, "mail")],
+    }}});
+    std::fs::write(dir.join("export.json"), export.to_string()).unwrap();
+    let client = |policy: SyncMerge| {
+        ActivityWatchClient::new(server.url())
+            .with_sync_dir(&dir)
+            .with_sync_merge(policy)
+    };
+    let apps = |events: Vec<Event>| -> Vec<String> {
+        events
+            .iter()
+            .map(|e| e.data["app"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let live = client(SyncMerge::PreferLive);
+    let events = live
+        .get_events("aw-watcher-window_test", None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(apps(events), ["browser", "editor"]);
+
+    let synced = client(SyncMerge::PreferSync);
+    let bucket = synced.get_bucket("aw-watcher-window_test").await.unwrap();
+    assert_eq!(bucket.hostname.as_deref(), Some("synced"));
+    let events = synced
+        .get_events("aw-watcher-window_test", None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(apps(events), ["mail", "editor"]);
+
+    let union = client(SyncMerge::Union);
+    let (events, meta) = ResponseMeta::collect(
+        "test".to_string(),
+        union.get_events("aw-watcher-window_test", None, None, None),
+    )
+    .await;
+    assert_eq!(apps(events.unwrap()), ["mail", "browser", "editor"]);
+    assert_eq!(meta.sync_merge, Some(SyncMerge::Union));
+    assert_eq!(meta.sync_merged, ["aw-watcher-window_test"]);
+    let count = union
+        .get_event_count("aw-watcher-window_test", None, None)
+        .await;
+    assert_eq!(count.unwrap(), 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn takes_today_from_the_injected_clock() {
+    let server = server_with_window_bucket().await;
+    let config = Config {
+        timezone: "UTC".parse().unwrap(),
+        ..Config::default()
+    };
+    let now = Utc.timestamp_opt(1_700_000_075, 0).unwrap();
+    let mcp = ActivityWatchMcpServer::new(ActivityWatchClient::new(server.url()))
+        .with_config(config)
+        .with_clock(FixedClock(now));
+
+    assert_eq!(mcp.now(), now);
+    let snapshot = mcp.day_snapshot().await.unwrap();
+    assert_eq!(snapshot.date.to_string(), "2023-11-14");
+    assert_eq!(snapshot.end, now);
+    // The browser event is cut off at the fixed "now"
+    assert_eq!(snapshot.total_secs, 75.0);
+}
+
+#[tokio::test]
+async fn caches_query_results_of_past_periods() {
+    let server = server_with_window_bucket().await;
+    server.set_query_result(serde_json::json!(42.0));
+    let client = ActivityWatchClient::new(server.url());
+    let past = "2024-01-01T00:00:00+00:00/2024-01-02T00:00:00+00:00".to_string();
+    let later = Utc::now() + chrono::Duration::hours(1);
+    let open = format!("2024-01-02T00:00:00+00:00/{}", later.to_rfc3339());
+
+    let first = client
+        .query(std::slice::from_ref(&past), "RETURN = 42;")
+        .await
+        .unwrap();
+    assert_eq!(first.periods[0].value.as_f64(), Some(42.0));
+
+    // Same script in another layout: the past period comes from the cache
+    let (second, meta) =
+        ResponseMeta::collect("q".to_string(), client.query(&[past, open], "RETURN=42")).await;
+    assert_eq!(second.unwrap().periods.len(), 2);
+    assert!(meta.cached_since.is_some());
+    let requests = server.requests();
+    assert_eq!(requests.iter().filter(|r| r.starts_with("POST")).count(), 2);
+    assert_eq!(client.cache_stats().await.queries.entries, 1);
+}
+
+#[tokio::test]
+async fn writes_evict_every_cached_query_result() {
+    let server = server_with_window_bucket().await;
+    server.set_query_result(serde_json::json!(42.0));
+    let client = ActivityWatchClient::new(server.url());
+    let past = vec!["2024-01-01T00:00:00+00:00/2024-01-02T00:00:00+00:00".to_string()];
+    // The script reads the bucket without naming it
+    let script = "RETURN = query_bucket(find_bucket(\"aw-watcher-window_\"));";
+    client.query(&past, script).await.unwrap();
+    assert_eq!(client.cache_stats().await.queries.entries, 1);
+
+    client
+        .heartbeat("aw-watcher-window_test", &event(120, 0.0, "editor"), 60.0)
+        .await
+        .unwrap();
+    assert_eq!(client.cache_stats().await.queries.entries, 0);
+}
+
+#[tokio::test]
+async fn splits_queries_over_many_periods() {
+    let server = server_with_window_bucket().await;
+    server.set_query_result(serde_json::json!(1.0));
+    let client = ActivityWatchClient::new(server.url());
+    let periods: Vec<String> = (1..=30)
+        .map(|day| format!("2024-01-{:02}T00:00:00Z/2024-01-{:02}T23:59:59Z", day, day))
+        .collect();
+
+    let result = client.query(&periods, "RETURN = 1").await.unwrap();
+    assert_eq!(result.periods.len(), 30);
+    assert_eq!(result.periods[29].timeperiod, periods[29]);
+    let requests = server.requests();
+    assert_eq!(requests.iter().filter(|r| r.starts_with("POST")).count(), 4);
+}
+
+#[tokio::test]
+async fn runs_queries_locally_when_aw_server_cannot() {
+    let server = server_with_window_bucket().await;
+    let client = ActivityWatchClient::new(server.url());
+    let period = "2023-11-14T00:00:00Z/2023-11-15T00:00:00Z".to_string();
+    let script = r#"RETURN = sum_durations(query_bucket("aw-watcher-window_test"));"#;
+
+    server.fail_next(Fault::Status(500));
+    let (result, meta) = ResponseMeta::collect(
+        "q".to_string(),
+        client.query(std::slice::from_ref(&period), script),
+    )
+    .await;
+    let result = result.unwrap();
+    assert!(result.computed_locally);
+    assert_eq!(result.periods[0].value.as_f64(), Some(90.0));
+    assert!(meta.query_fallback.unwrap().contains("Injected 500 error"));
+
+    // Scripts the local interpreter cannot run keep the server's error
+    server.fail_next(Fault::Status(500));
+    let error = client.query(&[period], "RETURN = nop()").await.unwrap_err();
+    assert_eq!(error.message, "Injected 500 error");
+}
+
+#[tokio::test]
+async fn counts_fixed_events_in_response_metadata() {
+    let server = server_with_window_bucket().await;
+    // An import that re-added an existing event
+    server.add_events("aw-watcher-window_test", vec![event(60, 30.0, "browser")]);
+    let client = ActivityWatchClient::new(server.url());
+
+    let (events, meta) = ResponseMeta::collect(
+        "e".to_string(),
+        client.get_events("aw-watcher-window_test", None, None, None),
+    )
+    .await;
+    assert_eq!(events.unwrap().len(), 2);
+    assert_eq!(meta.event_fixes, 1);
+}
+
+#[tokio::test]
+async fn leaves_out_do_not_track_time() {
+    let server = server_with_window_bucket().await;
+    // The events start at 22:13:20 UTC
+    let rules = vec!["22:14-23:00".parse().unwrap()];
+    let client = ActivityWatchClient::new(server.url())
+        .with_do_not_track(DoNotTrack::new(rules, Timezone::Utc));
+
+    let (events, meta) = ResponseMeta::collect(
+        "p".to_string(),
+        client.get_events("aw-watcher-window_test", None, None, None),
+    )
+    .await;
+    let events = events.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].duration.as_secs_f64(), 40.0);
+    assert_eq!(meta.private_excluded, 2);
+    let count = client
+        .get_event_count("aw-watcher-window_test", None, None)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    let period = "2023-11-14T22:00:00+00:00/2023-11-14T23:00:00+00:00".to_string();
+    let error = client
+        .query(std::slice::from_ref(&period), "RETURN = 1;")
+        .await
+        .unwrap_err();
+    assert!(error.message.contains("AW_MCP_DO_NOT_TRACK"));
+
+    let events = including_private(
+        true,
+        client.get_events("aw-watcher-window_test", None, None, None),
+    )
+    .await
+    .unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(
+        including_private(true, client.query(&[period], "RETURN = 1;"))
+            .await
+            .is_ok()
+    );
+}